@@ -0,0 +1,74 @@
+//! AttrParser의 memchr 기반 탐색과 한 바이트씩 비교하는 탐색의 처리량 비교
+//! <br>
+//! `cargo bench --bench attr`로 실행함
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use solr_proxy::xml_attr_parser::AttrParser;
+
+/// 값이 긴 속성을 이어붙인 약 10KB의 속성 문자열
+fn attr_str() -> Vec<u8> {
+    let mut attr = String::new();
+    let mut i = 0;
+    while attr.len() < 10 * 1024 {
+        attr.push_str(&format!(
+            r#" name{}  =  "{}" "#,
+            i,
+            "https://cafe.naver.com/moonlightriverside/".repeat(8)
+        ));
+        i += 1;
+    }
+    attr.into_bytes()
+}
+
+/// memchr 적용 전의 한 바이트씩 비교하는 방식
+fn byte_loop_parse(attr_str: &[u8]) -> usize {
+    let find = |cursor: usize, f: &dyn Fn(u8) -> bool| {
+        attr_str
+            .iter()
+            .enumerate()
+            .skip(cursor)
+            .find(|(_, &c)| f(c))
+            .map(|(i, &c)| (i, c))
+    };
+    let is_space = |c: u8| matches!(c, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ');
+
+    let mut cursor = 0;
+    let mut cnt = 0;
+    while let Some((name_start, _)) = find(cursor, &|c| !is_space(c)) {
+        let Some((equal_mark, _)) = find(name_start + 1, &|c| c == b'=') else {
+            break;
+        };
+        let Some((quot_start, quot)) = find(equal_mark + 1, &|c| matches!(c, b'"' | b'\'')) else {
+            break;
+        };
+        let Some((quot_end, _)) = find(quot_start + 1, &|c| c == quot) else {
+            break;
+        };
+        cnt += quot_end - quot_start;
+        cursor = quot_end + 1;
+    }
+    cnt
+}
+
+fn attr_bench(c: &mut Criterion) {
+    let attr = attr_str();
+    let mut group = c.benchmark_group("attr_10kb");
+    group.throughput(Throughput::Bytes(attr.len() as u64));
+
+    group.bench_function("memchr", |b| {
+        b.iter(|| {
+            AttrParser::new(black_box(&attr))
+                .map(|attr| attr.value.len() + 1)
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_function("byte_loop", |b| {
+        b.iter(|| byte_loop_parse(black_box(&attr)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, attr_bench);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+//! read_xml, proc_xml, write_xml 처리량 측정
+//! <br>
+//! `cargo bench`로 실행하며, DB 대신 메모리 저장소를 사용함
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use solr_proxy::media_type::MediaType;
+use solr_proxy::proc_xml::{self, ProcOptions, ProcTiming, ReadLimit, SeedIdField, WriteOk};
+use solr_proxy::seed_store::SeedIdStore;
+use solr_proxy::BoxedError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const SAMPLE_XML: &str = include_str!("../fixtures/sample_update.xml");
+
+/// 테스트용 메모리 저장소
+#[derive(Default)]
+struct MemorySeedIdStore {
+    seed_ids: Mutex<HashMap<String, String>>,
+}
+
+impl SeedIdStore for MemorySeedIdStore {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        Ok(self.seed_ids.lock().unwrap().get(seed_host).cloned())
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        _media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        let mut seed_ids = self.seed_ids.lock().unwrap();
+        let seed_id = format!("seed-{}", seed_ids.len());
+        if seed_ids.contains_key(seed_host) {
+            return Ok(false);
+        }
+        seed_ids.insert(seed_host.to_string(), seed_id);
+        Ok(true)
+    }
+}
+
+/// 예시 요청의 첫번째 doc(seed_id 없음)을 doc_cnt개 복사한 요청
+/// <br>
+/// doc마다 id와 카페 주소를 다르게 하여 seed_host가 겹치지 않도록 함
+fn fixture(doc_cnt: usize) -> Vec<u8> {
+    let docs = proc_xml::read_xml(SAMPLE_XML.as_bytes(), &ReadLimit::default()).unwrap();
+    let template = String::from_utf8(docs[0].ori_str().to_vec()).unwrap();
+
+    let mut xml = String::from("<add>");
+    for i in 0..doc_cnt {
+        xml.push_str(
+            &template
+                .replace("a77b3908fb67bd1b", &format!("bench-{}", i))
+                .replace("moonlightriverside", &format!("bench{}", i)),
+        );
+    }
+    xml.push_str("</add>");
+    xml.into_bytes()
+}
+
+fn xml_bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let limit = ReadLimit::default();
+
+    for doc_cnt in [1, 100, 2_000] {
+        let xml = fixture(doc_cnt);
+        let mut group = c.benchmark_group(format!("{}_docs", doc_cnt));
+        group.throughput(Throughput::Bytes(xml.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("read", doc_cnt), &xml, |b, xml| {
+            b.iter(|| proc_xml::read_xml(xml, &limit).unwrap())
+        });
+
+        // 변경 사항이 없는 경우 원문을 재사용함
+        group.bench_with_input(
+            BenchmarkId::new("read_write_no_change", doc_cnt),
+            &xml,
+            |b, xml| {
+                b.iter(|| {
+                    let docs = proc_xml::read_xml(xml, &limit).unwrap();
+                    let WriteOk::NoChanged(_) =
+                        proc_xml::write_xml(docs, false, SeedIdField::default()).unwrap()
+                    else {
+                        panic!("result is not WriteOk::NoChanged");
+                    };
+                })
+            },
+        );
+
+        // 모든 doc에 seed_id를 추가함
+        let store = MemorySeedIdStore::default();
+        let options = ProcOptions::default();
+        group.bench_with_input(
+            BenchmarkId::new("read_enrich_write", doc_cnt),
+            &xml,
+            |b, xml| {
+                b.iter(|| {
+                    runtime.block_on(async {
+                        let mut docs = proc_xml::read_xml(xml, &limit).unwrap();
+                        let removed = proc_xml::proc_xml_with(
+                            &mut docs,
+                            &store,
+                            &options,
+                            &mut ProcTiming::default(),
+                        )
+                        .await
+                        .unwrap();
+                        let WriteOk::Changed(..) =
+                            proc_xml::write_xml(docs, removed > 0, SeedIdField::default()).unwrap()
+                        else {
+                            panic!("result is not WriteOk::Changed");
+                        };
+                    })
+                })
+            },
+        );
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, xml_bench);
+criterion_main!(benches);
@@ -0,0 +1,121 @@
+use crate::context::RequestContext;
+use hyper::header::CONTENT_LENGTH;
+use hyper::{HeaderMap, Method, StatusCode};
+use log::info;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// access log를 남기는 logger target. setting_log에서 application log와 다른 파일로 보냄
+pub const ACCESS_TARGET: &str = "access";
+
+/// 요청 하나의 access log
+/// <br>
+/// 한 줄에 tab으로 구분하여 아래 순서로 남기며, 값이 없는 경우 "-". 시간은 ms 단위
+/// <br>
+/// timestamp, request_id, remote_ip, method, path, doc_cnt, bytes_in, bytes_out, status, total, solr, db, cache
+/// <br>
+/// timestamp는 log4rs encoder에서 붙이며, cache는 "hit/miss" 형식의 seed_id cache 사용 횟수
+pub struct AccessRecord<'a> {
+    pub ctx: &'a RequestContext,
+    pub method: &'a Method,
+    pub path: &'a str,
+    pub doc_cnt: Option<usize>,
+    pub bytes_in: Option<u64>,
+    pub bytes_out: Option<u64>,
+    pub status: StatusCode,
+    pub total: Duration,
+    pub solr: Duration,
+    pub db: Option<Duration>,
+    pub cache: Option<(u32, u32)>,
+}
+
+impl AccessRecord<'_> {
+    /// timestamp를 제외한 access log 한 줄
+    pub fn line(&self) -> String {
+        [
+            self.ctx.request_id.clone(),
+            self.ctx.remote_ip.to_string(),
+            self.method.to_string(),
+            self.path.to_string(),
+            or_dash(self.doc_cnt),
+            or_dash(self.bytes_in),
+            or_dash(self.bytes_out),
+            self.status.as_u16().to_string(),
+            self.total.as_millis().to_string(),
+            self.solr.as_millis().to_string(),
+            or_dash(self.db.map(|db| db.as_millis())),
+            or_dash(self.cache.map(|(hit, miss)| format!("{}/{}", hit, miss))),
+        ]
+        .join("\t")
+    }
+}
+
+fn or_dash<T: Display>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Content-Length header 값. 없거나 잘못된 값인 경우 None
+pub fn content_length(header_map: &HeaderMap) -> Option<u64> {
+    header_map
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// access log를 남김. access_log 설정이 꺼진 경우 logger에서 버려짐
+pub fn log_access(record: &AccessRecord) {
+    if log::log_enabled!(target: ACCESS_TARGET, log::Level::Info) {
+        info!(target: ACCESS_TARGET, "{}", record.line());
+    }
+}
+
+#[test]
+fn access_record_line_test() {
+    use hyper::header::HeaderValue;
+
+    let mut header_map = HeaderMap::new();
+    header_map.insert("x-request-id", HeaderValue::from_static("req-1"));
+    let ctx = RequestContext::new(&header_map, "10.0.0.1:5000".parse().unwrap());
+
+    let record = AccessRecord {
+        ctx: &ctx,
+        method: &Method::POST,
+        path: "/solr/core/update",
+        doc_cnt: Some(3),
+        bytes_in: Some(1024),
+        bytes_out: Some(120),
+        status: StatusCode::OK,
+        total: Duration::from_millis(45),
+        solr: Duration::from_millis(30),
+        db: Some(Duration::from_millis(7)),
+        cache: Some((2, 1)),
+    };
+    assert_eq!(
+        record.line(),
+        "req-1\t10.0.0.1:5000\tPOST\t/solr/core/update\t3\t1024\t120\t200\t45\t30\t7\t2/1"
+    );
+
+    // select는 doc 수, DB 시간, cache 사용 횟수가 없음
+    let record = AccessRecord {
+        method: &Method::GET,
+        path: "/solr/core/select",
+        doc_cnt: None,
+        bytes_in: None,
+        db: None,
+        cache: None,
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        ..record
+    };
+    assert_eq!(
+        record.line(),
+        "req-1\t10.0.0.1:5000\tGET\t/solr/core/select\t-\t-\t120\t503\t45\t30\t-\t-"
+    );
+
+    header_map.insert(CONTENT_LENGTH, HeaderValue::from_static("512"));
+    assert_eq!(content_length(&header_map), Some(512));
+    header_map.insert(CONTENT_LENGTH, HeaderValue::from_static("abc"));
+    assert_eq!(content_length(&header_map), None);
+}
@@ -0,0 +1,758 @@
+use crate::context::RequestContext;
+use crate::proc_xml;
+use crate::secret;
+use crate::seed_store::{MySqlSeedIdStore, ReadOnlySeedIdStore, SeedRowStore};
+use crate::setting_log;
+use crate::settings::{CollectionSettings, ConfigErrors, Settings};
+use crate::shared_cache::SharedSeedCache;
+use crate::solr::Solr;
+use crate::util::StrError;
+use crate::BoxedError;
+use config::Config;
+use hyper::header::HeaderName;
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode};
+use log::{debug, info, LevelFilter};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 관리용 endpoint에 사용하는 secret header
+pub const X_PROXY_ADMIN_SECRET: HeaderName = HeaderName::from_static("x-proxy-admin-secret");
+
+/// GET은 현재 log level을 조회하고, PUT은 level 파라미터로 log level을 바꿈
+pub const LOG_LEVEL_PATH: &str = "/proxy/loglevel";
+
+/// GET으로 현재 config를 조회함. 비밀번호 등은 가려서 응답함
+pub const CONFIG_PATH: &str = "/proxy/config";
+
+/// POST로 config 파일을 다시 읽고 실행 중 바꿀 수 있는 설정을 적용함
+pub const RELOAD_PATH: &str = "/proxy/reload";
+
+/// DELETE로 seed_host 파라미터의 seed_id cache를 지움. DB를 고친 뒤 이전 값을 다시 읽도록 할 때 사용함
+pub const SEED_CACHE_PATH: &str = "/proxy/seedcache";
+
+/// GET은 update 점검 상태를 조회하고, POST는 update 요청을 받지 않는 점검 상태로 바꿈
+pub const DRAIN_PATH: &str = "/proxy/drain";
+
+/// POST로 점검 상태를 끝내고 update 요청을 다시 받음
+pub const UNDRAIN_PATH: &str = "/proxy/undrain";
+
+/// GET으로 url 파라미터의 seed_host와 cache, DB의 seed_id를 조회함. seed_id를 새로 만들지 않음
+pub const SEED_DEBUG_PATH: &str = "/proxy/debug/seed";
+
+/// 관리용 endpoint 요청인지 확인함
+pub fn is_admin_path(path: &str) -> bool {
+    path == LOG_LEVEL_PATH
+        || path == CONFIG_PATH
+        || path == RELOAD_PATH
+        || path == SEED_CACHE_PATH
+        || path == DRAIN_PATH
+        || path == UNDRAIN_PATH
+        || path == SEED_DEBUG_PATH
+}
+
+/// 관리용 endpoint 요청을 처리함. 솔라로 전달하지 않음
+pub async fn admin_response(
+    req: Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+    admin_secret: &str,
+    config: &Config,
+) -> Result<Response<Body>, BoxedError> {
+    check_secret(admin_secret, req.headers())?;
+
+    if req.uri().path() == CONFIG_PATH {
+        return config_response(&req, config);
+    }
+    if req.uri().path() == RELOAD_PATH {
+        return reload_response(&req, ctx);
+    }
+    if req.uri().path() == SEED_CACHE_PATH {
+        return seed_cache_response(&req, ctx).await;
+    }
+    if req.uri().path() == DRAIN_PATH || req.uri().path() == UNDRAIN_PATH {
+        return drain_response(&req, ctx, solr);
+    }
+    if req.uri().path() == SEED_DEBUG_PATH {
+        return seed_debug_response(&req, ctx).await;
+    }
+
+    match *req.method() {
+        Method::GET => {}
+        Method::PUT => {
+            let value = req
+                .uri()
+                .query()
+                .unwrap_or_default()
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("level="))
+                .unwrap_or_default();
+            let level = parse_level(value)?;
+            let before = setting_log::log_level();
+            setting_log::set_log_level(level)?;
+            info!(
+                "[{}] LOG_LEVEL_CHANGED {} -> {}, from: {}",
+                ctx.request_id, before, level, ctx.remote_ip
+            );
+        }
+        _ => {
+            return Err(Box::new(StrError::with_status(
+                format!("METHOD_NOT_ALLOWED: {} {}", req.method(), LOG_LEVEL_PATH),
+                StatusCode::METHOD_NOT_ALLOWED,
+            )))
+        }
+    }
+
+    let body = serde_json::json!({ "level": setting_log::log_level().to_string() });
+    json_response(body)
+}
+
+fn config_response(req: &Request<Body>, config: &Config) -> Result<Response<Body>, BoxedError> {
+    if req.method() != Method::GET {
+        return Err(Box::new(StrError::with_status(
+            format!("METHOD_NOT_ALLOWED: {} {}", req.method(), CONFIG_PATH),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )));
+    }
+    json_response(secret::redacted_config(config)?)
+}
+
+fn reload_response(
+    req: &Request<Body>,
+    ctx: &RequestContext,
+) -> Result<Response<Body>, BoxedError> {
+    if req.method() != Method::POST {
+        return Err(Box::new(StrError::with_status(
+            format!("METHOD_NOT_ALLOWED: {} {}", req.method(), RELOAD_PATH),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )));
+    }
+
+    info!("[{}] CONFIG_RELOAD from: {}", ctx.request_id, ctx.remote_ip);
+    let report = crate::reload_config().map_err(reload_error)?;
+    json_response(serde_json::json!({ "requires_restart": report.requires_restart }))
+}
+
+/// 잘못된 config로 reload하지 못한 경우의 에러. 서버 에러가 아니므로 확인한 에러를 모두 담아 422로 응답함
+fn reload_error(errors: ConfigErrors) -> BoxedError {
+    Box::new(StrError::with_status(
+        format!(
+            "CONFIG_RELOAD_FAIL: {}",
+            errors.to_string().replace('\n', "; ")
+        ),
+        StatusCode::UNPROCESSABLE_ENTITY,
+    ))
+}
+
+/// query string에서 name 파라미터를 찾아 decode함. 없거나 비어있으면 None
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri()
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        .map(|value| {
+            percent_encoding::percent_decode_str(value)
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .filter(|value| !value.is_empty())
+}
+
+/// collection 파라미터의 컬렉션 설정. 없으면 전역 설정
+fn collection_param<'a>(
+    req: &Request<Body>,
+    settings: &'a Settings,
+) -> Result<&'a Arc<CollectionSettings>, BoxedError> {
+    match query_param(req, "collection") {
+        Some(name) => settings
+            .collections
+            .get(&name.to_ascii_lowercase())
+            .ok_or_else(|| -> BoxedError {
+                Box::new(StrError::with_status(
+                    format!("UNKNOWN_COLLECTION: {}", name),
+                    StatusCode::BAD_REQUEST,
+                ))
+            }),
+        None => Ok(&settings.default_collection),
+    }
+}
+
+async fn seed_cache_response(
+    req: &Request<Body>,
+    ctx: &RequestContext,
+) -> Result<Response<Body>, BoxedError> {
+    if req.method() != Method::DELETE {
+        return Err(Box::new(StrError::with_status(
+            format!("METHOD_NOT_ALLOWED: {} {}", req.method(), SEED_CACHE_PATH),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )));
+    }
+
+    let seed_host = query_param(req, "seed_host").ok_or_else(|| -> BoxedError {
+        Box::new(StrError::with_status(
+            "MISSING_SEED_HOST".to_string(),
+            StatusCode::BAD_REQUEST,
+        ))
+    })?;
+    // collection을 지정한 경우 해당 컬렉션의 cache key를 지움
+    let settings = crate::settings();
+    let collection = collection_param(req, &settings)?;
+    let key = collection.cache_key(&seed_host);
+    let removed = crate::SEED_ID_CACHE.pop(&key).await;
+    // 다른 proxy가 공유 cache의 이전 값을 다시 읽지 않도록 공유 cache에서도 지움
+    if let Some(shared) = crate::SHARED_SEED_CACHE.as_ref() {
+        shared.del(&key).await.map_err(|e| -> BoxedError {
+            Box::new(StrError::with_status(
+                format!("SHARED_CACHE_DELETE_FAIL: {}, {}", seed_host, e),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ))
+        })?;
+    }
+    info!(
+        "[{}] SEED_ID_CACHE_INVALIDATED {} ({}), from: {}",
+        ctx.request_id,
+        key,
+        removed.as_deref().unwrap_or("not cached"),
+        ctx.remote_ip
+    );
+    json_response(serde_json::json!({ "seed_host": seed_host, "removed": removed }))
+}
+
+async fn seed_debug_response(
+    req: &Request<Body>,
+    ctx: &RequestContext,
+) -> Result<Response<Body>, BoxedError> {
+    if req.method() != Method::GET {
+        return Err(Box::new(StrError::with_status(
+            format!("METHOD_NOT_ALLOWED: {} {}", req.method(), SEED_DEBUG_PATH),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )));
+    }
+
+    let url = query_param(req, "url").ok_or_else(|| -> BoxedError {
+        Box::new(StrError::with_status(
+            "MISSING_URL".to_string(),
+            StatusCode::BAD_REQUEST,
+        ))
+    })?;
+    let settings = crate::settings();
+    let collection = collection_param(req, &settings)?;
+    let store = ReadOnlySeedIdStore::new(MySqlSeedIdStore::new(&collection.seed_table));
+    let report =
+        seed_debug_report(&url, collection, &store, crate::SHARED_SEED_CACHE.as_ref()).await?;
+    debug!(
+        "[{}] SEED_DEBUG {} -> {}, from: {}",
+        ctx.request_id, url, report["seed_host"], ctx.remote_ip
+    );
+    json_response(report)
+}
+
+/// enrich와 같은 방식으로 url의 seed_host를 만들고 seed_id cache, 공유 cache, DB에서 조회한 결과
+/// <br>
+/// cache에 있어도 공유 cache와 DB를 함께 조회하며, cache의 LRU 순서를 바꾸거나 조회한 값을 cache에 넣지 않음.
+/// store는 ReadOnlySeedIdStore이므로 DB에 없어도 INSERT하지 않음
+pub async fn seed_debug_report<S: SeedRowStore + Sync, C: SharedSeedCache + Sync>(
+    url: &str,
+    collection: &CollectionSettings,
+    store: &ReadOnlySeedIdStore<S>,
+    shared: Option<&C>,
+) -> Result<serde_json::Value, BoxedError> {
+    let start = Instant::now();
+    let trace = proc_xml::trace_seed_host(url, collection).map_err(|e| -> BoxedError {
+        Box::new(StrError::with_status(
+            e.to_string(),
+            StatusCode::BAD_REQUEST,
+        ))
+    })?;
+    let cache_key = collection.cache_key(&trace.seed_host);
+    let cached = crate::SEED_ID_CACHE.peek(&cache_key).await;
+
+    // 공유 cache는 enrich와 같이 컬렉션의 cache key로 조회함
+    let (shared_seed_id, shared_error) = match shared {
+        Some(shared) => match shared.get(&cache_key).await {
+            Ok(seed_id) => (seed_id, None),
+            Err(e) => (None, Some(e.to_string())),
+        },
+        None => (None, None),
+    };
+
+    let db_start = Instant::now();
+    let (db_row, db_error) = match store.select_row(&trace.seed_host).await {
+        Ok(row) => (row, None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let db_elapsed = db_start.elapsed();
+    let db_seed_id = db_row.as_ref().map(|row| row.seed_id.clone());
+    let db_row = db_row.map(|row| {
+        serde_json::json!({
+            "media_url": trace.seed_host,
+            "seed_id": row.seed_id,
+            "site_name": row.media_type.site_name,
+            "media_type_no": row.media_type.media_type_no,
+        })
+    });
+
+    // enrich는 cache, 공유 cache, DB 순서로 찾은 값을 사용함. 모두 없으면 새로 만듦
+    let seed_id = cached.clone().or(shared_seed_id.clone()).or(db_seed_id);
+    Ok(serde_json::json!({
+        "url": url,
+        "collection": collection.name,
+        "host": trace.host,
+        "seed_host": trace.seed_host,
+        "host_rule": trace.rule.map(|rule| serde_json::json!({
+            "prefix": rule.prefix,
+            "capture_regex": rule.capture_regex.as_str(),
+        })),
+        "cache_key": cache_key,
+        "cache_hit": cached.is_some(),
+        "cache_seed_id": cached,
+        "shared_cache_enabled": shared.is_some(),
+        "shared_cache_hit": shared_seed_id.is_some(),
+        "shared_cache_seed_id": shared_seed_id,
+        "shared_cache_error": shared_error,
+        "seed_table": collection.seed_table,
+        "db_row": db_row,
+        "db_error": db_error,
+        "seed_id": seed_id,
+        "db_elapsed_us": db_elapsed.as_micros() as u64,
+        "elapsed_us": start.elapsed().as_micros() as u64,
+    }))
+}
+
+fn drain_response(
+    req: &Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+) -> Result<Response<Body>, BoxedError> {
+    let path = req.uri().path();
+    match *req.method() {
+        Method::GET if path == DRAIN_PATH => {}
+        Method::POST => {
+            let drain = path == DRAIN_PATH;
+            if solr.set_update_drain(drain) != drain {
+                info!(
+                    "[{}] {}, from: {}",
+                    ctx.request_id,
+                    if drain {
+                        "UPDATE_DRAIN_ON"
+                    } else {
+                        "UPDATE_DRAIN_OFF"
+                    },
+                    ctx.remote_ip
+                );
+            }
+        }
+        _ => {
+            return Err(Box::new(StrError::with_status(
+                format!("METHOD_NOT_ALLOWED: {} {}", req.method(), path),
+                StatusCode::METHOD_NOT_ALLOWED,
+            )))
+        }
+    }
+    json_response(serde_json::json!({ "draining": solr.is_update_draining() }))
+}
+
+fn json_response(body: serde_json::Value) -> Result<Response<Body>, BoxedError> {
+    Ok(Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))?)
+}
+
+/// admin_secret이 설정되지 않은 경우 관리용 endpoint를 사용할 수 없음
+fn check_secret(admin_secret: &str, header_map: &HeaderMap) -> Result<(), BoxedError> {
+    if admin_secret.is_empty() {
+        return Err(Box::new(StrError::with_status(
+            "ADMIN_DISABLED: admin_secret is not configured".to_string(),
+            StatusCode::FORBIDDEN,
+        )));
+    }
+
+    let secret = header_map
+        .get(X_PROXY_ADMIN_SECRET)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    if !constant_time_eq(secret, admin_secret.as_bytes()) {
+        return Err(Box::new(StrError::with_status(
+            "INVALID_ADMIN_SECRET".to_string(),
+            StatusCode::UNAUTHORIZED,
+        )));
+    }
+    Ok(())
+}
+
+/// 비교에 걸리는 시간으로 secret을 추측할 수 없도록 길이가 같으면 끝까지 비교함
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn parse_level(value: &str) -> Result<LevelFilter, BoxedError> {
+    value.parse().map_err(|_| -> BoxedError {
+        Box::new(StrError::with_status(
+            format!("INVALID_LOG_LEVEL: {:?}", value),
+            StatusCode::BAD_REQUEST,
+        ))
+    })
+}
+
+#[tokio::test]
+async fn admin_response_test() {
+    use crate::util::error_status;
+    use hyper::header::HeaderValue;
+
+    let ctx = RequestContext::new(&HeaderMap::new(), "10.0.0.1:5000".parse().unwrap());
+    let solr = Solr::new(
+        "http://127.0.0.1:1".to_string(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let config = Config::builder()
+        .set_override("db_host", "localhost")
+        .unwrap()
+        .set_override("db_pwd", "p@ssw0rd")
+        .unwrap()
+        .build()
+        .unwrap();
+    let request = |method: Method, uri: &str, secret: Option<&'static str>| {
+        let mut req = Request::builder().method(method).uri(uri);
+        if let Some(secret) = secret {
+            req = req.header(X_PROXY_ADMIN_SECRET, HeaderValue::from_static(secret));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+
+    // admin_secret이 없으면 사용할 수 없음
+    let req = request(Method::GET, LOG_LEVEL_PATH, Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::FORBIDDEN));
+
+    for secret in [None, Some("wrong"), Some("secre")] {
+        let req = request(Method::GET, LOG_LEVEL_PATH, secret);
+        let err = admin_response(req, &ctx, &solr, "secret", &config)
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&err), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    let req = request(Method::GET, LOG_LEVEL_PATH, Some("secret"));
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(value["level"], setting_log::log_level().to_string());
+
+    // 잘못된 level은 400
+    let req = request(Method::PUT, "/proxy/loglevel?level=verbose", Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::BAD_REQUEST));
+    assert!(err.to_string().starts_with("INVALID_LOG_LEVEL"));
+
+    let req = request(Method::DELETE, LOG_LEVEL_PATH, Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    // config 조회는 비밀번호를 가림
+    let req = request(Method::GET, CONFIG_PATH, Some("secret"));
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(value["db_host"], "localhost");
+    assert_eq!(value["db_pwd"], "******");
+
+    let req = request(Method::GET, CONFIG_PATH, None);
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::UNAUTHORIZED));
+
+    let req = request(Method::PUT, CONFIG_PATH, Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    // reload는 POST만 사용함
+    let req = request(Method::GET, RELOAD_PATH, Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    // 잘못된 config는 확인한 에러를 모두 담아 422로 응답함
+    let err = reload_error(ConfigErrors(vec![
+        config::ConfigError::Message("INVALID_A".to_string()),
+        config::ConfigError::Message("INVALID_B".to_string()),
+    ]));
+    assert_eq!(error_status(&err), Some(StatusCode::UNPROCESSABLE_ENTITY));
+    assert!(err.to_string().contains("INVALID_A; INVALID_B"), "{}", err);
+
+    // seed_id cache는 seed_host의 shard에서 지움
+    crate::SEED_ID_CACHE
+        .put(
+            "cafe.naver.com/admintest".to_string(),
+            "seed-admin".to_string(),
+        )
+        .await;
+    let req = request(
+        Method::DELETE,
+        "/proxy/seedcache?seed_host=cafe.naver.com%2Fadmintest",
+        Some("secret"),
+    );
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(value["seed_host"], "cafe.naver.com/admintest");
+    assert_eq!(value["removed"], "seed-admin");
+    assert_eq!(
+        crate::SEED_ID_CACHE.get("cafe.naver.com/admintest").await,
+        None
+    );
+
+    let req = request(Method::DELETE, SEED_CACHE_PATH, Some("secret"));
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::BAD_REQUEST));
+
+    // collections에 없는 컬렉션
+    let req = request(
+        Method::DELETE,
+        "/proxy/seedcache?seed_host=cafe.naver.com%2Fadmintest&collection=unknown",
+        Some("secret"),
+    );
+    let err = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::BAD_REQUEST));
+
+    // 점검 상태는 POST로 바꾸고 GET으로 조회함
+    let drain_state = |response: Response<Body>| async {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        value["draining"].as_bool().unwrap()
+    };
+    let req = request(Method::GET, DRAIN_PATH, Some("secret"));
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    assert!(!drain_state(response).await);
+    let req = request(Method::POST, DRAIN_PATH, Some("secret"));
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    assert!(drain_state(response).await);
+    assert!(solr.is_update_draining());
+    let req = request(Method::POST, UNDRAIN_PATH, Some("secret"));
+    let response = admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .unwrap();
+    assert!(!drain_state(response).await);
+    assert!(!solr.is_update_draining());
+    for (method, path) in [(Method::GET, UNDRAIN_PATH), (Method::DELETE, DRAIN_PATH)] {
+        let req = request(method, path, Some("secret"));
+        let err = admin_response(req, &ctx, &solr, "secret", &config)
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(&err), Some(StatusCode::METHOD_NOT_ALLOWED));
+    }
+    let req = request(Method::POST, DRAIN_PATH, None);
+    assert!(admin_response(req, &ctx, &solr, "secret", &config)
+        .await
+        .is_err());
+    assert!(!solr.is_update_draining());
+
+    assert_eq!(parse_level("Debug").unwrap(), LevelFilter::Debug);
+    assert_eq!(parse_level("off").unwrap(), LevelFilter::Off);
+}
+
+#[tokio::test]
+async fn seed_debug_test() {
+    use crate::host_rule::HostRule;
+    use crate::media_type::MediaType;
+    use crate::mock::{MockSeedIdStore, MockSharedCache};
+    use crate::seed_store::SeedIdStore;
+    use crate::util::error_status;
+    use hyper::header::HeaderValue;
+    use std::sync::atomic::Ordering;
+
+    let db = MockSeedIdStore::default().with_row(
+        "cafe.naver.com/debugcafe",
+        "seed-cafe",
+        MediaType {
+            media_type_no: 2,
+            site_name: "naver cafe".to_string(),
+        },
+    );
+    // 공유 cache를 사용하지 않는 경우
+    let no_shared: Option<&MockSharedCache> = None;
+    let store = ReadOnlySeedIdStore::new(db);
+    let collection = CollectionSettings {
+        seed_table: "crawlerdb.t_channel_contents_map".to_string(),
+        host_rules: HostRule::defaults(),
+        seed_url_fields: vec!["url".to_string()],
+        ..CollectionSettings::default()
+    };
+
+    // 카페 url은 규칙으로 seed_host를 만들고 DB의 값을 사용함
+    let report = seed_debug_report(
+        "https://cafe.naver.com/debugcafe/9741?art=1",
+        &collection,
+        &store,
+        no_shared,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report["host"], "cafe.naver.com");
+    assert_eq!(report["seed_host"], "cafe.naver.com/debugcafe");
+    assert_eq!(report["host_rule"]["prefix"], "cafe.naver.com");
+    assert_eq!(report["cache_hit"], false);
+    assert_eq!(report["db_row"]["seed_id"], "seed-cafe");
+    assert_eq!(report["db_row"]["site_name"], "naver cafe");
+    assert_eq!(report["db_row"]["media_type_no"], 2);
+    assert_eq!(report["shared_cache_enabled"], false);
+    assert_eq!(report["seed_id"], "seed-cafe");
+    assert!(report["elapsed_us"].is_u64());
+    // 조회한 값을 cache에 넣지 않음
+    assert_eq!(
+        crate::SEED_ID_CACHE.get("cafe.naver.com/debugcafe").await,
+        None
+    );
+
+    // 일반 host는 정규화한 host를 그대로 사용하며 cache에 있으면 cache의 값을 사용함
+    crate::SEED_ID_CACHE
+        .put(
+            "debug-plain.example.com".to_string(),
+            "seed-cached".to_string(),
+        )
+        .await;
+    let report = seed_debug_report(
+        "http://www.Debug-Plain.example.com/a/b",
+        &collection,
+        &store,
+        no_shared,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report["seed_host"], "debug-plain.example.com");
+    assert!(report["host_rule"].is_null());
+    assert_eq!(report["cache_hit"], true);
+    assert_eq!(report["cache_seed_id"], "seed-cached");
+    assert!(report["db_row"].is_null());
+    assert_eq!(report["seed_id"], "seed-cached");
+
+    // cache, DB 모두 없어도 INSERT하지 않음
+    let report = seed_debug_report(
+        "https://debug-absent.example.com/",
+        &collection,
+        &store,
+        no_shared,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report["cache_hit"], false);
+    assert!(report["db_row"].is_null());
+    assert!(report["seed_id"].is_null());
+    assert!(store
+        .insert_seed_id("debug-absent.example.com", &MediaType::default())
+        .await
+        .is_err());
+    assert!(!store.allow_insert());
+    assert!(store.inner().inserted().is_empty());
+
+    // seed_host를 만들 수 없는 url은 400
+    let err = seed_debug_report("not a url", &collection, &store, no_shared)
+        .await
+        .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::BAD_REQUEST));
+
+    // 공유 cache에 있으면 DB보다 먼저 사용하고, 공유 cache 에러는 결과에 남김
+    let shared = MockSharedCache::default();
+    shared.values.lock().unwrap().insert(
+        "debug-shared.example.com".to_string(),
+        "seed-shared".to_string(),
+    );
+    let report = seed_debug_report(
+        "https://debug-shared.example.com/",
+        &collection,
+        &store,
+        Some(&shared),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report["shared_cache_enabled"], true);
+    assert_eq!(report["shared_cache_hit"], true);
+    assert_eq!(report["shared_cache_seed_id"], "seed-shared");
+    assert!(report["db_row"].is_null());
+    assert_eq!(report["seed_id"], "seed-shared");
+    shared.down.store(true, Ordering::Relaxed);
+    let report = seed_debug_report(
+        "https://debug-shared.example.com/",
+        &collection,
+        &store,
+        Some(&shared),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report["shared_cache_hit"], false);
+    assert_eq!(report["shared_cache_error"], "REDIS_TIMEOUT");
+    assert!(report["seed_id"].is_null());
+
+    // admin_secret이 있어야 사용할 수 있고 GET만 사용함
+    let ctx = RequestContext::new(&HeaderMap::new(), "10.0.0.1:5000".parse().unwrap());
+    let solr = Solr::new(
+        "http://127.0.0.1:1".to_string(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let config = Config::builder().build().unwrap();
+    let request = |method: Method, uri: &str, secret: Option<&'static str>| {
+        let mut req = Request::builder().method(method).uri(uri);
+        if let Some(secret) = secret {
+            req = req.header(X_PROXY_ADMIN_SECRET, HeaderValue::from_static(secret));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+    let uri = "/proxy/debug/seed?url=https%3A%2F%2Fdebug-absent.example.com%2F";
+    assert!(is_admin_path(SEED_DEBUG_PATH));
+    let err = admin_response(
+        request(Method::GET, uri, None),
+        &ctx,
+        &solr,
+        "secret",
+        &config,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::UNAUTHORIZED));
+    let err = admin_response(
+        request(Method::POST, uri, Some("secret")),
+        &ctx,
+        &solr,
+        "secret",
+        &config,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::METHOD_NOT_ALLOWED));
+    let err = admin_response(
+        request(Method::GET, SEED_DEBUG_PATH, Some("secret")),
+        &ctx,
+        &solr,
+        "secret",
+        &config,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.to_string(), "MISSING_URL");
+}
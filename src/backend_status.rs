@@ -0,0 +1,145 @@
+use log::{info, warn};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// 연속으로 이 횟수만큼 연결에 실패하면 failing으로 바꿈
+const FAILING_AFTER: u32 = 3;
+/// failing 상태에서 연속으로 이 횟수만큼 응답을 받으면 healthy로 바꿈
+const HEALTHY_AFTER: u32 = 2;
+
+/// 솔라 하나의 연결 상태. 응답을 받으면 성공, 연결 에러로 끝나면 실패로 셈
+/// <br>
+/// 요청 하나마다 상태가 바뀌지 않도록 FAILING_AFTER, HEALTHY_AFTER만큼 연속된 경우에만 바꾸고 로그를 남김
+pub struct BackendStatus {
+    url: String,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    failing: bool,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    /// 연결 자체를 맺지 못한 실패 수. 잘못된 포트 등 설정 문제인 경우가 많음
+    connect_failures: u64,
+    last_error: Option<String>,
+    last_error_at: Option<SystemTime>,
+    last_success_at: Option<SystemTime>,
+    /// 마지막으로 상태가 바뀐 시간
+    since: Option<Instant>,
+}
+
+impl BackendStatus {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 응답을 받은 경우. failing에서 healthy로 바뀌면 true
+    pub fn record_success(&self) -> bool {
+        let mut state = self.lock();
+        state.consecutive_failures = 0;
+        state.consecutive_successes = state.consecutive_successes.saturating_add(1);
+        state.last_success_at = Some(SystemTime::now());
+        if !state.failing || state.consecutive_successes < HEALTHY_AFTER {
+            return false;
+        }
+        info!(
+            "SOLR_BACKEND_RECOVERED: {}, failing for {}s",
+            self.url,
+            state.since.map_or(0, |since| since.elapsed().as_secs())
+        );
+        state.failing = false;
+        state.since = Some(Instant::now());
+        true
+    }
+
+    /// 연결 에러로 끝난 경우. connect는 연결을 맺지 못한 에러인지 여부. healthy에서 failing으로 바뀌면 true
+    pub fn record_failure(&self, err: &str, connect: bool) -> bool {
+        let mut state = self.lock();
+        state.consecutive_successes = 0;
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if connect {
+            state.connect_failures += 1;
+        }
+        state.last_error = Some(err.to_string());
+        state.last_error_at = Some(SystemTime::now());
+        if state.failing || state.consecutive_failures < FAILING_AFTER {
+            return false;
+        }
+        warn!(
+            "SOLR_BACKEND_FAILING: {}, {} consecutive failures, last error: {}",
+            self.url, state.consecutive_failures, err
+        );
+        state.failing = true;
+        state.since = Some(Instant::now());
+        true
+    }
+
+    /// 통계 API에 넣는 상태. 시간은 unix time(초)
+    pub fn to_json(&self) -> serde_json::Value {
+        let state = self.lock();
+        let unix_secs = |time: Option<SystemTime>| {
+            time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_secs())
+        };
+        serde_json::json!({
+            "url": self.url,
+            "healthy": !state.failing,
+            "consecutive_failures": state.consecutive_failures,
+            "connect_failures": state.connect_failures,
+            "last_error": state.last_error,
+            "last_error_at": unix_secs(state.last_error_at),
+            "last_success_at": unix_secs(state.last_success_at),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[test]
+fn backend_status_transition_test() {
+    let status = BackendStatus::new("http://solr:8983".to_string());
+    let healthy = |status: &BackendStatus| status.to_json()["healthy"] == true;
+    assert!(healthy(&status));
+    assert!(status.to_json()["last_success_at"].is_null());
+
+    // FAILING_AFTER번 연속으로 실패해야 failing으로 바뀌고, 이후 실패는 상태를 바꾸지 않음
+    assert!(!status.record_failure("connection refused", true));
+    assert!(!status.record_failure("connection refused", true));
+    assert!(healthy(&status));
+    assert!(status.record_failure("connection refused", true));
+    assert!(!healthy(&status));
+    assert!(!status.record_failure("connection reset", false));
+    let json = status.to_json();
+    assert_eq!(json["healthy"], false);
+    assert_eq!(json["consecutive_failures"], 4);
+    assert_eq!(json["connect_failures"], 3);
+    assert_eq!(json["last_error"], "connection reset");
+
+    // failing 중 한 번 성공하거나 성공, 실패가 번갈아 오면 바뀌지 않음
+    assert!(!status.record_success());
+    assert!(!status.record_failure("connection refused", true));
+    assert!(!status.record_success());
+    assert!(!healthy(&status));
+    assert!(status.record_success());
+    assert!(healthy(&status));
+    let json = status.to_json();
+    assert_eq!(json["consecutive_failures"], 0);
+    assert!(json["last_success_at"].as_u64().unwrap() > 0);
+
+    // healthy 중 성공 사이의 실패는 다시 처음부터 셈
+    status.record_failure("connection refused", true);
+    status.record_failure("connection refused", true);
+    status.record_success();
+    assert!(!status.record_failure("connection refused", true));
+    assert!(healthy(&status));
+}
@@ -0,0 +1,129 @@
+use crate::SyncLazy;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// 응답과 상관없이 실행하는 작업. 종료할 때 끝나기를 기다림
+static TASKS: SyncLazy<TaskTracker> = SyncLazy::new(TaskTracker::new);
+
+/// 요청 처리와 별개로 실행하는 작업(shadow write, audit 저장 등)을 spawn하고 종료할 때까지 셈
+/// <br>
+/// tokio::spawn으로 실행한 작업은 종료할 때 기다리지 않으므로 끝나기 전에 버려질 수 있음. 이런 작업은 이 함수로 실행함
+pub fn spawn_tracked<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    TASKS.spawn(future)
+}
+
+/// 끝나지 않은 spawn_tracked 작업이 모두 끝날 때까지 최대 timeout만큼 기다림. 시간이 지나면 남은 작업 수를 Err로 반환함
+pub async fn wait_tracked(timeout: Duration) -> Result<(), usize> {
+    TASKS.wait_idle(timeout).await
+}
+
+/// 실행중인 작업 수와 모두 끝났을 때의 알림
+pub struct TaskTracker {
+    pending: AtomicUsize,
+    idle: Notify,
+}
+
+/// 작업 하나가 끝날 때까지 가지고 있는 guard. panic으로 끝난 경우에도 drop되어 pending을 감소시킴
+struct TaskGuard(&'static TaskTracker);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if self.0.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+impl Default for TaskTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskTracker {
+    pub fn new() -> Self {
+        Self {
+            pending: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    /// 끝나지 않은 작업 수
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// future를 spawn하고 끝날 때까지 pending으로 셈
+    pub fn spawn<F>(&'static self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        let guard = TaskGuard(self);
+        tokio::spawn(async move {
+            let _guard = guard;
+            future.await
+        })
+    }
+
+    /// pending이 0이 될 때까지 최대 timeout만큼 기다림. 시간이 지나면 남은 작업 수를 Err로 반환함
+    pub async fn wait_idle(&self, timeout: Duration) -> Result<(), usize> {
+        let idle = async {
+            loop {
+                let notified = self.idle.notified();
+                if self.pending() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, idle)
+            .await
+            .map_err(|_| self.pending())
+    }
+}
+
+#[tokio::test]
+async fn task_tracker_test() {
+    static TRACKER: SyncLazy<TaskTracker> = SyncLazy::new(TaskTracker::new);
+
+    // 작업이 없으면 바로 끝남
+    assert_eq!(TRACKER.wait_idle(Duration::ZERO).await, Ok(()));
+
+    // 느린 작업도 timeout 안에 끝나면 기다림
+    let (send, recv) = tokio::sync::oneshot::channel::<()>();
+    let slow = TRACKER.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let _ = send.send(());
+        1
+    });
+    let quick = TRACKER.spawn(async { 2 });
+    assert_eq!(TRACKER.pending(), 2);
+    assert_eq!(TRACKER.wait_idle(Duration::from_secs(5)).await, Ok(()));
+    assert!(recv.await.is_ok());
+    assert_eq!(slow.await.unwrap() + quick.await.unwrap(), 3);
+
+    // timeout이 지나면 남은 작업 수를 반환하고, 작업은 버리지 않고 계속 실행함
+    let (finish_send, finish_recv) = tokio::sync::oneshot::channel::<()>();
+    let stuck = TRACKER.spawn(async move {
+        let _ = finish_recv.await;
+    });
+    TRACKER.spawn(async {});
+    assert_eq!(TRACKER.wait_idle(Duration::from_millis(50)).await, Err(1));
+    let _ = finish_send.send(());
+    stuck.await.unwrap();
+    assert_eq!(TRACKER.pending(), 0);
+
+    // panic으로 끝난 작업도 셈에서 빠짐
+    let panicked = TRACKER.spawn(async { panic!("AUDIT_FAIL") });
+    assert!(panicked.await.is_err());
+    assert_eq!(TRACKER.wait_idle(Duration::from_secs(5)).await, Ok(()));
+}
@@ -0,0 +1,155 @@
+use crate::background;
+use crate::gauge::{Gauge, GaugeGuard};
+use crate::seed_cache::SeedIdCache;
+use std::sync::{Mutex, RwLock};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 보냈지만 cache에 넣지 않은 값이 구간 중 이 개수를 넘으면 경고함
+pub const CACHE_WRITE_BACKLOG_WARN: u64 = 10000;
+
+/// cache에 넣을 값. cache에 넣을 때까지 guard로 depth에 셈
+struct CacheWrite {
+    key: String,
+    seed_id: String,
+    _queued: GaugeGuard<'static>,
+}
+
+/// DB에서 조회한 seed_id를 요청 처리와 별개로 cache에 넣는 writer
+/// <br>
+/// 요청은 값을 channel로 보내기만 하고, 하나의 writer task가 순서대로 cache에 넣음. cache 조회는 지금처럼 요청에서 바로 함
+/// <br>
+/// start 전(transform 명령, 테스트 등)이나 close 후에는 put이 바로 cache에 넣음
+pub struct CacheWriter {
+    cache: &'static SeedIdCache,
+    sender: RwLock<Option<mpsc::UnboundedSender<CacheWrite>>>,
+    /// 실행중인 writer task. drain에서 끝날 때까지 기다림
+    task: Mutex<Option<JoinHandle<()>>>,
+    /// channel에 있는 값의 수
+    pub depth: Gauge,
+}
+
+impl CacheWriter {
+    pub const fn new(cache: &'static SeedIdCache) -> Self {
+        Self {
+            cache,
+            sender: RwLock::new(None),
+            task: Mutex::new(None),
+            depth: Gauge::new(),
+        }
+    }
+
+    /// writer task를 spawn_tracked로 시작함. 종료할 때 close 후 background::wait_tracked로 남은 값을 모두 넣을 때까지 기다림
+    pub fn start(&'static self) {
+        let (send, mut recv) = mpsc::unbounded_channel::<CacheWrite>();
+        *self.sender.write().unwrap_or_else(|e| e.into_inner()) = Some(send);
+        let task = background::spawn_tracked(async move {
+            while let Some(write) = recv.recv().await {
+                self.cache.put(write.key, write.seed_id).await;
+            }
+        });
+        *self.task.lock().unwrap_or_else(|e| e.into_inner()) = Some(task);
+    }
+
+    /// seed_id를 cache에 넣도록 보냄. writer task가 없으면 바로 넣음
+    pub async fn put(&'static self, key: String, seed_id: String) {
+        if let Err((key, seed_id)) = self.send(key, seed_id) {
+            self.cache.put(key, seed_id).await;
+        }
+    }
+
+    /// writer task로 보냄. writer task가 없으면 보내지 못한 값을 반환함
+    fn send(&'static self, key: String, seed_id: String) -> Result<(), (String, String)> {
+        let sender = self.sender.read().unwrap_or_else(|e| e.into_inner());
+        let Some(sender) = sender.as_ref() else {
+            return Err((key, seed_id));
+        };
+        let write = CacheWrite {
+            key,
+            seed_id,
+            _queued: self.depth.enter(),
+        };
+        sender
+            .send(write)
+            .map_err(|mpsc::error::SendError(write)| (write.key, write.seed_id))
+    }
+
+    /// 더 이상 channel로 받지 않음. writer task는 이미 받은 값을 모두 넣은 뒤 끝남
+    pub fn close(&self) {
+        self.sender
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    /// close 후 writer task가 받은 값을 모두 넣고 끝날 때까지 기다림. writer task가 없으면 바로 반환함
+    /// <br>
+    /// wait_tracked가 shutdown_grace로 기다리지 않고 끝난 경우에도 이후 cache를 비우기 전에 사용함
+    pub async fn drain(&self) {
+        self.close();
+        let task = self.task.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn cache_writer_test() {
+    use crate::SyncLazy;
+    use std::time::Duration;
+
+    static CACHE: SyncLazy<SeedIdCache> = SyncLazy::new(|| SeedIdCache::new(100, 1));
+    static WRITER: SyncLazy<CacheWriter> = SyncLazy::new(|| CacheWriter::new(&CACHE));
+
+    // 시작 전에는 바로 넣음
+    WRITER
+        .put("before".to_string(), "seed-before".to_string())
+        .await;
+    assert_eq!(CACHE.get("before").await.as_deref(), Some("seed-before"));
+    assert_eq!(WRITER.depth.high(), 0);
+
+    // 시작 후에는 writer task가 넣으므로 잠시 뒤 조회됨
+    WRITER.start();
+    for i in 0..50 {
+        WRITER
+            .put(format!("host-{}", i), format!("seed-{}", i))
+            .await;
+    }
+    assert!(WRITER.depth.high() > 0);
+    let populated = async {
+        while WRITER.depth.current() > 0 {
+            tokio::task::yield_now().await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), populated)
+        .await
+        .unwrap();
+    assert_eq!(CACHE.get("host-0").await.as_deref(), Some("seed-0"));
+    assert_eq!(CACHE.get("host-49").await.as_deref(), Some("seed-49"));
+
+    // close 후에는 남은 값을 넣고 writer task가 끝나며, 이후 값은 바로 넣음
+    WRITER
+        .put("last".to_string(), "seed-last".to_string())
+        .await;
+    WRITER.close();
+    assert_eq!(
+        background::wait_tracked(Duration::from_secs(5)).await,
+        Ok(())
+    );
+    assert_eq!(CACHE.get("last").await.as_deref(), Some("seed-last"));
+
+    // drain은 writer task가 남은 값을 넣고 끝날 때까지 기다림
+    WRITER.start();
+    WRITER
+        .put("drained".to_string(), "seed-drained".to_string())
+        .await;
+    WRITER.drain().await;
+    assert_eq!(CACHE.get("drained").await.as_deref(), Some("seed-drained"));
+    WRITER.drain().await;
+    WRITER
+        .put("after".to_string(), "seed-after".to_string())
+        .await;
+    assert_eq!(CACHE.get("after").await.as_deref(), Some("seed-after"));
+    assert_eq!(WRITER.depth.current(), 0);
+}
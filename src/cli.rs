@@ -0,0 +1,279 @@
+use crate::proc_xml::{self, ProcOptions, ProcTiming, WriteOk};
+use crate::seed_store::{MySqlSeedIdStore, OfflineSeedIdStore, RetrySeedIdStore};
+use crate::settings::{validate_config, ConfigErrors, Settings};
+use crate::{read_config, setting_log, settings, BoxedError, CON, SOLR};
+use std::io::Write;
+use std::time::Duration;
+
+/// 사용법. 잘못된 인자를 받은 경우 출력함
+pub const USAGE: &str = "usage: solr_proxy [serve]
+       solr_proxy check-config [--log]
+       solr_proxy transform <file.xml> [--offline] [--log]
+
+  serve         서버 실행. 명령이 없는 경우 기본값
+  check-config  config 확인 후 DB, 솔라 연결을 확인함. 문제가 있으면 종료 코드 1
+  transform     xml 파일의 doc을 proxy와 같이 처리한 결과를 stdout으로 출력함
+    --offline   DB를 사용하지 않고 seed_id에 OFFLINE을 넣음
+  --log         log/ 디렉토리에 로그를 남김. 지정하지 않으면 로그를 남기지 않음";
+
+/// DB, 솔라 연결 확인을 기다릴 최대 시간
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 실행할 명령
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    CheckConfig {
+        log: bool,
+    },
+    Transform {
+        path: String,
+        offline: bool,
+        log: bool,
+    },
+    Help,
+}
+
+impl Command {
+    /// 프로그램 이름을 제외한 인자로 명령을 만듦. 잘못된 인자인 경우 에러 메시지 반환
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let Some(name) = args.next() else {
+            return Ok(Self::Serve);
+        };
+
+        let mut log = false;
+        let mut offline = false;
+        let mut positional = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                "--log" => log = true,
+                "--offline" => offline = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let command = match name.as_str() {
+            "serve" => Self::Serve,
+            "check-config" => Self::CheckConfig { log },
+            "transform" => {
+                if positional.len() != 1 {
+                    return Err("transform requires one file".to_string());
+                }
+                Self::Transform {
+                    path: positional.remove(0),
+                    offline,
+                    log,
+                }
+            }
+            "help" | "--help" | "-h" => Self::Help,
+            _ => return Err(format!("unknown command: {}", name)),
+        };
+
+        let allowed_offline = matches!(command, Self::Transform { .. });
+        let allowed_positional = matches!(command, Self::Transform { .. });
+        if (offline && !allowed_offline) || (!positional.is_empty() && !allowed_positional) {
+            return Err(format!("invalid arguments for {}", name));
+        }
+        Ok(command)
+    }
+}
+
+/// 명령을 실행하고 종료 코드를 반환함
+pub async fn execute(command: Command) -> i32 {
+    match command {
+        Command::Serve => {
+            crate::run().await;
+            0
+        }
+        Command::CheckConfig { log } => {
+            if log && !setup_logger() {
+                return 1;
+            }
+            if check_config().await {
+                0
+            } else {
+                1
+            }
+        }
+        Command::Transform { path, offline, log } => {
+            if log && !setup_logger() {
+                return 1;
+            }
+            match transform_file(&path, offline).await {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("TRANSFORM_FAIL: {}", e);
+                    1
+                }
+            }
+        }
+        Command::Help => {
+            println!("{}", USAGE);
+            0
+        }
+    }
+}
+
+/// config가 잘못된 경우 setup_logger에서 panic이 발생하지 않도록 먼저 확인함
+fn setup_logger() -> bool {
+    if let Err(errors) = read_config()
+        .map_err(|e| ConfigErrors(vec![e]))
+        .and_then(|config| Settings::from_config(&config))
+    {
+        eprintln!("CONFIG_INVALID:\n{}", errors);
+        return false;
+    }
+    match setting_log::setup_logger(settings().log_roll_config()) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("LOGGER_SETUP_FAIL: {}", e);
+            false
+        }
+    }
+}
+
+/// config를 확인한 뒤 DB, 솔라에 연결할 수 있는지 확인함. 결과는 stdout으로 출력함
+async fn check_config() -> bool {
+    let config = match read_config() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("CONFIG_INVALID:\n{}", e);
+            return false;
+        }
+    };
+    let settings = match validate_config(&config, |name| std::env::var(name).ok()) {
+        Ok(settings) => settings,
+        Err(errors) => {
+            println!("CONFIG_INVALID:\n{}", errors);
+            return false;
+        }
+    };
+    println!("config: OK");
+
+    // 인증서를 읽을 수 없으면 서버가 시작하지 않으므로 함께 확인함
+    let tls_result = if settings.tls_enabled() {
+        let result = crate::tls::TlsConfig::load(&settings.tls_cert_path, &settings.tls_key_path)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        print_probe("tls", &result);
+        result
+    } else {
+        Ok(())
+    };
+
+    let db_result = tokio::time::timeout(PROBE_TIMEOUT, sqlx::query("SELECT 1").execute(&*CON))
+        .await
+        .map_err(|_| "timeout".to_string())
+        .and_then(|result| result.map(|_| ()).map_err(|e| e.to_string()));
+    print_probe("db", &db_result);
+
+    let solr_result = SOLR
+        .probe(PROBE_TIMEOUT)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+    print_probe("solr", &solr_result);
+
+    tls_result.is_ok() && db_result.is_ok() && solr_result.is_ok()
+}
+
+fn print_probe(name: &str, result: &Result<(), String>) {
+    match result {
+        Ok(()) => println!("{}: OK", name),
+        Err(e) => println!("{}: FAIL {}", name, e),
+    }
+}
+
+/// path의 xml 파일을 처리한 결과를 stdout으로 출력함
+async fn transform_file(path: &str, offline: bool) -> Result<(), BoxedError> {
+    let config = read_config()?;
+    if offline {
+        Settings::from_config(&config)?;
+    } else {
+        validate_config(&config, |name| std::env::var(name).ok())?;
+    }
+
+    let bytes = std::fs::read(path)?;
+    let output = transform(&bytes, offline).await?;
+    std::io::stdout().write_all(&output)?;
+    Ok(())
+}
+
+/// update 요청과 같이 read_xml, proc_xml, write_xml로 처리한 xml. 변경사항이 없는 경우 받은 xml
+async fn transform(bytes: &[u8], offline: bool) -> Result<Vec<u8>, BoxedError> {
+    let settings = settings();
+    let (mut docs, envelope) = proc_xml::read_xml_envelope(bytes, &settings.read_limit())?;
+    let options = ProcOptions::default();
+    let mut timing = ProcTiming::default();
+    if offline {
+        proc_xml::proc_xml_with(&mut docs, &OfflineSeedIdStore, &options, &mut timing).await?;
+    } else {
+        let store = RetrySeedIdStore::new(
+            MySqlSeedIdStore::new(&settings.seed_table),
+            settings.db_retry_policy(),
+        );
+        proc_xml::proc_xml_with(&mut docs, &store, &options, &mut timing).await?;
+    }
+
+    let seed_id_field = options.collection(&settings).seed_id_field;
+    match proc_xml::write_xml_spliced(docs, &envelope, seed_id_field)? {
+        WriteOk::Changed(output, _, _) => Ok(output),
+        WriteOk::NoChanged(_) => Ok(bytes.to_vec()),
+        WriteOk::Split(..) => unreachable!("write_xml_spliced does not split"),
+    }
+}
+
+#[test]
+fn command_parse_test() {
+    let parse = |args: &[&str]| Command::parse(args.iter().map(|arg| arg.to_string()));
+
+    assert_eq!(parse(&[]).unwrap(), Command::Serve);
+    assert_eq!(parse(&["serve"]).unwrap(), Command::Serve);
+    assert_eq!(
+        parse(&["check-config"]).unwrap(),
+        Command::CheckConfig { log: false }
+    );
+    assert_eq!(
+        parse(&["check-config", "--log"]).unwrap(),
+        Command::CheckConfig { log: true }
+    );
+    assert_eq!(
+        parse(&["transform", "--offline", "docs.xml"]).unwrap(),
+        Command::Transform {
+            path: "docs.xml".to_string(),
+            offline: true,
+            log: false,
+        }
+    );
+    assert_eq!(parse(&["--help"]).unwrap(), Command::Help);
+
+    for args in [
+        &["transform"][..],
+        &["transform", "a.xml", "b.xml"],
+        &["check-config", "--offline"],
+        &["serve", "extra"],
+        &["serve", "--verbose"],
+        &["start"],
+    ] {
+        assert!(parse(args).is_err(), "{:?}", args);
+    }
+}
+
+#[tokio::test]
+async fn transform_offline_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="url">http://transform-test.example.com/a</field></doc></add>"#;
+    let output = transform(xml, true).await.unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(
+        output.contains(r#"<field name="seed_id">OFFLINE</field>"#),
+        "{}",
+        output
+    );
+
+    // seed_id가 이미 있으면 그대로 출력함
+    let xml =
+        br#"<add><doc><field name="id">1</field><field name="seed_id">S1</field></doc></add>"#;
+    assert_eq!(transform(xml, true).await.unwrap(), xml.to_vec());
+}
@@ -0,0 +1,353 @@
+use crate::error_kind::ErrorKind;
+use crate::query;
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::http::uri::Uri;
+use hyper::StatusCode;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use std::time::Duration;
+
+/// 솔라가 update를 받은 뒤 commit할 때까지의 최대 시간(ms)
+const PARAM_COMMIT_WITHIN: &str = "commitWithin";
+/// update 후 곧바로 commit하도록 요청하는 파라미터. 값이 true인 경우 allow_commit이 false이면 제거함
+const COMMIT_PARAMS: [&str; 3] = ["commit", "softCommit", "optimize"];
+/// commit하도록 요청하는 update 명령. allow_commit이 false이면 내용까지 제거함
+const COMMIT_ELEMENTS: [&[u8]; 2] = [b"commit", b"optimize"];
+/// commitWithin 속성을 받는 update 명령. 속성을 제거해서 query string의 commitWithin을 사용하게 함
+const COMMIT_WITHIN_ELEMENTS: [&[u8]; 2] = [b"add", b"delete"];
+
+/// update 요청의 commit 시점을 proxy에서 정하는 설정. force_commit_within_ms가 0이면 사용하지 않음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitPolicy {
+    pub commit_within: Duration,
+    /// true인 경우 클라이언트가 요청한 commit을 그대로 보냄
+    pub allow_commit: bool,
+}
+
+/// xml body에 CommitPolicy를 적용한 결과
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct XmlApplied {
+    /// 바꾼 body. 바꿀 내용이 없으면 None
+    pub xml: Option<Vec<u8>>,
+    /// 제거한 commit, optimize 명령 수
+    pub commit_removed: u32,
+}
+
+impl CommitPolicy {
+    /// update 요청의 commitWithin 파라미터를 commit_within으로 넣거나 바꿈. commit, softCommit, optimize=true는 allow_commit이 false면 제거함
+    /// <br>
+    /// key와 값은 split_proxy_params와 같이 decode해서 비교함. commitWithin이 이미 있으면 첫번째 위치의 값을 바꾸고,
+    /// 나머지 파라미터는 받은 그대로 유지함. 제거한 파라미터 수를 반환함
+    pub fn apply(&self, uri: &mut Uri) -> Result<u32, BoxedError> {
+        let forced = format!("{}={}", PARAM_COMMIT_WITHIN, self.commit_within.as_millis());
+        let mut pairs: Vec<&str> = Vec::new();
+        let mut replaced = false;
+        let mut commit_removed = 0;
+        for pair in uri.query().unwrap_or_default().split('&') {
+            if pair.is_empty() {
+                continue;
+            }
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = query::decode(key);
+            if key == PARAM_COMMIT_WITHIN {
+                if !replaced {
+                    pairs.push(&forced);
+                    replaced = true;
+                }
+            } else if !self.allow_commit
+                && COMMIT_PARAMS.contains(&&*key)
+                && query::decode(value).eq_ignore_ascii_case("true")
+            {
+                commit_removed += 1;
+            } else {
+                pairs.push(pair);
+            }
+        }
+        if !replaced {
+            pairs.push(&forced);
+        }
+
+        query::set_query(uri, &pairs.join("&"))?;
+        Ok(commit_removed)
+    }
+
+    /// update xml의 \<add>, \<delete>에서 commitWithin 속성을 제거하고, allow_commit이 false면 \<commit/>, \<optimize/>를 제거함
+    /// <br>
+    /// \<doc> 안의 내용과 주석, CDATA는 바꾸지 않으며 나머지는 원문을 그대로 복사함.
+    /// 스트리밍 중 나눈 doc 밖의 조각에도 사용할 수 있도록 시작, 끝 태그의 짝은 확인하지 않음. xml을 읽을 수 없으면 400 에러
+    pub fn apply_xml(&self, xml: &[u8]) -> Result<XmlApplied, BoxedError> {
+        // 바꿀 수 있는 이름이 없으면 읽지 않음. commitWithin에도 commit이 들어있음
+        if !contains(xml, b"commit") && !contains(xml, b"optimize") {
+            return Ok(XmlApplied::default());
+        }
+
+        let mut reader = Reader::from_reader(xml);
+        reader.check_end_names(false);
+        let mut out: Option<Vec<u8>> = None;
+        let mut commit_removed = 0;
+        let mut copied = 0;
+        let mut doc_depth = 0usize;
+        // 제거하는 명령 안의 깊이
+        let mut skip_depth = 0usize;
+        loop {
+            let start = reader.buffer_position();
+            let event = reader.read_event().map_err(|e| {
+                Box::new(
+                    StrError::with_status(
+                        format!("UPDATE_XML_INVALID: {}", e),
+                        StatusCode::BAD_REQUEST,
+                    )
+                    .with_kind(ErrorKind::ParseError),
+                ) as BoxedError
+            })?;
+            let end = reader.buffer_position();
+            if skip_depth > 0 {
+                match event {
+                    Event::Start(_) => skip_depth += 1,
+                    Event::End(_) => skip_depth -= 1,
+                    Event::Eof => break,
+                    _ => {}
+                }
+                if skip_depth == 0 {
+                    copied = end;
+                }
+                continue;
+            }
+
+            let (tag, empty) = match event {
+                Event::Eof => break,
+                Event::Start(tag) => (tag, false),
+                Event::Empty(tag) => (tag, true),
+                Event::End(_) => {
+                    doc_depth = doc_depth.saturating_sub(1);
+                    continue;
+                }
+                _ => continue,
+            };
+            let name = tag.name();
+            if doc_depth > 0 || name.as_ref() == b"doc" {
+                doc_depth += !empty as usize;
+                continue;
+            }
+
+            if !self.allow_commit && COMMIT_ELEMENTS.contains(&name.as_ref()) {
+                out.get_or_insert_with(Vec::new)
+                    .extend_from_slice(&xml[copied..start]);
+                copied = end;
+                commit_removed += 1;
+                skip_depth = !empty as usize;
+            } else if COMMIT_WITHIN_ELEMENTS.contains(&name.as_ref()) {
+                let Some(rewritten) = without_commit_within(&tag, empty)? else {
+                    continue;
+                };
+                let out = out.get_or_insert_with(Vec::new);
+                out.extend_from_slice(&xml[copied..start]);
+                out.extend_from_slice(&rewritten);
+                copied = end;
+            }
+        }
+
+        let xml = out.map(|mut out| {
+            out.extend_from_slice(&xml[copied.min(xml.len())..]);
+            out
+        });
+        Ok(XmlApplied {
+            xml,
+            commit_removed,
+        })
+    }
+}
+
+/// commit, softCommit, optimize 파라미터를 값과 관계없이 제거한 uri. 나눈 update의 마지막이 아닌 chunk에 사용함
+pub fn without_commit_params(uri: &Uri) -> Result<Uri, BoxedError> {
+    let pairs: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+            !COMMIT_PARAMS.contains(&&*query::decode(key))
+        })
+        .collect();
+    let mut uri = uri.clone();
+    query::set_query(&mut uri, &pairs.join("&"))?;
+    Ok(uri)
+}
+
+/// commitWithin 속성을 제거한 시작 태그. commitWithin 속성이 없으면 None
+fn without_commit_within(tag: &BytesStart, empty: bool) -> Result<Option<Vec<u8>>, BoxedError> {
+    let name = String::from_utf8_lossy(tag.name().as_ref()).into_owned();
+    let mut rewritten = BytesStart::new(name);
+    let mut found = false;
+    for attr in tag.attributes() {
+        let attr = attr?;
+        if attr.key.as_ref() == PARAM_COMMIT_WITHIN.as_bytes() {
+            found = true;
+            continue;
+        }
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        rewritten.push_attribute((key.as_str(), attr.unescape_value()?.as_ref()));
+    }
+    if !found {
+        return Ok(None);
+    }
+
+    let mut writer = Writer::new(Vec::new());
+    match empty {
+        true => writer.write_event(Event::Empty(rewritten))?,
+        false => writer.write_event(Event::Start(rewritten))?,
+    }
+    Ok(Some(writer.into_inner()))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[test]
+fn commit_param_apply_test() {
+    let apply_to = |query: &str, allow_commit: bool| {
+        let mut uri: Uri = query.parse().unwrap();
+        let policy = CommitPolicy {
+            commit_within: Duration::from_millis(10_000),
+            allow_commit,
+        };
+        let removed = policy.apply(&mut uri).unwrap();
+        (uri.to_string(), removed)
+    };
+
+    // 없으면 마지막에 넣고 다른 파라미터는 encoding까지 그대로 유지함
+    assert_eq!(
+        apply_to("/solr/core/update", false),
+        ("/solr/core/update?commitWithin=10000".to_string(), 0)
+    );
+    assert_eq!(
+        apply_to("/solr/core/update?wt=json&q=a%20b+c&overwrite=true", false),
+        (
+            "/solr/core/update?wt=json&q=a%20b+c&overwrite=true&commitWithin=10000".to_string(),
+            0
+        )
+    );
+
+    // 클라이언트가 보낸 값은 같은 위치에서 바꾸고 중복된 값은 제거함
+    assert_eq!(
+        apply_to("/update?commitWithin=1&wt=json&commitWithin=2", false),
+        ("/update?commitWithin=10000&wt=json".to_string(), 0)
+    );
+
+    // commit, softCommit, optimize=true는 제거하고 false는 그대로 보냄
+    assert_eq!(
+        apply_to("/update?commit=true&wt=json", false),
+        ("/update?wt=json&commitWithin=10000".to_string(), 1)
+    );
+    assert_eq!(
+        apply_to("/update?commit=TRUE&softCommit=true&optimize=true", false),
+        ("/update?commitWithin=10000".to_string(), 3)
+    );
+    assert_eq!(
+        apply_to("/update?commit=false", false),
+        ("/update?commit=false&commitWithin=10000".to_string(), 0)
+    );
+
+    // encoding한 key와 값도 decode해서 비교함
+    assert_eq!(
+        apply_to("/update?%63ommit=%74rue&commit%57ithin=1", false),
+        ("/update?commitWithin=10000".to_string(), 1)
+    );
+
+    // allow_commit인 경우 commit=true도 그대로 보냄
+    assert_eq!(
+        apply_to("/update?commit=true&softCommit=true", true),
+        (
+            "/update?commit=true&softCommit=true&commitWithin=10000".to_string(),
+            0
+        )
+    );
+
+    // 절대 uri도 scheme, authority를 유지함
+    assert_eq!(
+        apply_to("http://solr:8983/solr/core/update?commit=true", false),
+        (
+            "http://solr:8983/solr/core/update?commitWithin=10000".to_string(),
+            1
+        )
+    );
+}
+
+#[test]
+fn without_commit_params_test() {
+    let without = |uri: &str| {
+        without_commit_params(&uri.parse().unwrap())
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        without("/update?commit=true&wt=json&softCommit=false&%6Fptimize=true&commitWithin=1"),
+        "/update?wt=json&commitWithin=1"
+    );
+    assert_eq!(without("/update?commit=true"), "/update");
+    assert_eq!(without("/update"), "/update");
+}
+
+#[test]
+fn commit_param_apply_xml_test() {
+    let policy = CommitPolicy {
+        commit_within: Duration::from_millis(10_000),
+        allow_commit: false,
+    };
+    let apply = |policy: &CommitPolicy, xml: &str| {
+        let applied = policy.apply_xml(xml.as_bytes()).unwrap();
+        (
+            applied.xml.map(|xml| String::from_utf8(xml).unwrap()),
+            applied.commit_removed,
+        )
+    };
+
+    // <add>, <delete>의 commitWithin은 제거하고 <commit/>, <optimize>는 내용까지 제거함
+    let xml = "<?xml version=\"1.0\"?>\n<update>\n<add overwrite=\"true\" commitWithin=\"1\"><doc><field name=\"id\">1</field></doc></add>\n<commit softCommit=\"true\"/>\n<delete commitWithin='5'><id>2</id></delete>\n<optimize waitSearcher=\"false\"></optimize>\n</update>";
+    assert_eq!(
+        apply(&policy, xml),
+        (
+            Some("<?xml version=\"1.0\"?>\n<update>\n<add overwrite=\"true\"><doc><field name=\"id\">1</field></doc></add>\n\n<delete><id>2</id></delete>\n\n</update>".to_string()),
+            2
+        )
+    );
+
+    // doc 안의 내용과 주석, CDATA는 바꾸지 않음
+    let xml = "<add><doc><field name=\"text\"><![CDATA[<commit/>]]></field><commit/></doc><!-- <commit/> --></add>";
+    assert_eq!(apply(&policy, xml), (None, 0));
+
+    // allow_commit인 경우 commitWithin 속성만 제거함
+    let allowed = CommitPolicy {
+        allow_commit: true,
+        ..policy
+    };
+    assert_eq!(
+        apply(&allowed, "<add commitWithin=\"1\"/><commit/>"),
+        (Some("<add/><commit/>".to_string()), 0)
+    );
+
+    // 스트리밍 중 나눈 조각도 짝을 확인하지 않고 바꿈
+    assert_eq!(
+        apply(&policy, "\n  <commit/>\n</add>\n"),
+        (Some("\n  \n</add>\n".to_string()), 1)
+    );
+    assert_eq!(
+        apply(
+            &policy,
+            "<add commitWithin=\"1\" overwrite=\"a&amp;b\">\n  "
+        ),
+        (Some("<add overwrite=\"a&amp;b\">\n  ".to_string()), 0)
+    );
+
+    // 바꿀 내용이 없으면 읽지 않음
+    assert_eq!(apply(&policy, "<add><doc/></add"), (None, 0));
+    let err = policy.apply_xml(b"<add><commit a=\"1></add>").unwrap_err();
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::BAD_REQUEST)
+    );
+}
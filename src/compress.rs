@@ -0,0 +1,109 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hyper::body::Bytes;
+use hyper::header::{HeaderValue, CONTENT_ENCODING};
+use hyper::HeaderMap;
+use std::io::Write;
+
+/// 솔라에 보내는 body의 압축 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpstreamCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+impl UpstreamCompression {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Some(Self::None),
+            "gzip" => Some(Self::Gzip),
+            _ => None,
+        }
+    }
+}
+
+/// body가 min_bytes 이상인 경우 압축하고 Content-Encoding 헤더를 설정함
+/// <br>
+/// 압축하지 않은 경우 None. 클라이언트가 이미 Content-Encoding을 지정한 경우엔 다시 압축하지 않음
+pub fn compress_body(
+    bytes: &[u8],
+    compression: UpstreamCompression,
+    min_bytes: usize,
+    header_map: &mut HeaderMap,
+) -> Result<Option<Bytes>, std::io::Error> {
+    if compression == UpstreamCompression::None
+        || bytes.len() < min_bytes
+        || header_map.contains_key(CONTENT_ENCODING)
+    {
+        return Ok(None);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 4), Compression::fast());
+    encoder.write_all(bytes)?;
+    let compressed = encoder.finish()?;
+
+    header_map.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    Ok(Some(Bytes::from(compressed)))
+}
+
+#[test]
+fn compress_body_test() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let xml = "<add><doc><field name=\"id\">1</field></doc></add>".repeat(100);
+
+    // 기준보다 작은 경우 압축하지 않음
+    let mut header_map = HeaderMap::new();
+    let result = compress_body(
+        xml.as_bytes(),
+        UpstreamCompression::Gzip,
+        xml.len() + 1,
+        &mut header_map,
+    )
+    .unwrap();
+    assert!(result.is_none());
+    assert!(!header_map.contains_key(CONTENT_ENCODING));
+
+    // 압축하지 않도록 설정된 경우
+    let result = compress_body(
+        xml.as_bytes(),
+        UpstreamCompression::None,
+        0,
+        &mut header_map,
+    )
+    .unwrap();
+    assert!(result.is_none());
+
+    let compressed = compress_body(
+        xml.as_bytes(),
+        UpstreamCompression::Gzip,
+        0,
+        &mut header_map,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(header_map[CONTENT_ENCODING], "gzip");
+    assert!(compressed.len() < xml.len());
+
+    let mut decompressed = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, xml);
+
+    // 이미 Content-Encoding이 있는 경우 다시 압축하지 않음
+    let result = compress_body(&compressed, UpstreamCompression::Gzip, 0, &mut header_map).unwrap();
+    assert!(result.is_none());
+
+    assert_eq!(
+        UpstreamCompression::parse("GZIP"),
+        Some(UpstreamCompression::Gzip)
+    );
+    assert_eq!(
+        UpstreamCompression::parse("none"),
+        Some(UpstreamCompression::None)
+    );
+    assert_eq!(UpstreamCompression::parse("br"), None);
+}
@@ -0,0 +1,130 @@
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// 동시에 처리하는 요청 수를 제한
+/// <br>
+/// 제한이 없는 경우(max == 0) semaphore를 사용하지 않음
+pub struct ConcurrencyLimit {
+    name: &'static str,
+    semaphore: Option<Semaphore>,
+    max: usize,
+    /// semaphore를 얻기 위해 기다릴 최대 시간
+    wait: Duration,
+    /// semaphore를 기다리고 있는 요청 수
+    queued: AtomicUsize,
+}
+
+/// 대기중인 요청 수를 세기 위한 guard. drop될 때 queued를 감소시킴
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ConcurrencyLimit {
+    pub fn new(name: &'static str, max: usize, wait: Duration) -> Self {
+        Self {
+            name,
+            semaphore: (max > 0).then(|| Semaphore::new(max)),
+            max,
+            wait,
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.semaphore.is_some()
+    }
+
+    /// 현재 처리중인 요청 수
+    pub fn in_flight(&self) -> usize {
+        match &self.semaphore {
+            Some(semaphore) => self.max - semaphore.available_permits(),
+            None => 0,
+        }
+    }
+
+    /// 현재 대기중인 요청 수
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// 처리 권한을 얻음. wait 시간 안에 얻지 못한 경우 503 에러 반환
+    /// <br>
+    /// 반환된 permit이 drop될 때까지 권한을 유지함. 제한이 없는 경우 None
+    pub async fn acquire(&self) -> Result<Option<SemaphorePermit<'_>>, BoxedError> {
+        let Some(semaphore) = &self.semaphore else {
+            return Ok(None);
+        };
+
+        if let Ok(permit) = semaphore.try_acquire() {
+            return Ok(Some(permit));
+        }
+
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        let _queued_guard = QueuedGuard(&self.queued);
+
+        match tokio::time::timeout(self.wait, semaphore.acquire()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => Err(Box::new(self.busy_error())),
+        }
+    }
+
+    fn busy_error(&self) -> StrError {
+        let err_msg = format!(
+            "TOO_MANY_CONCURRENT_{}: {}, wait {}ms",
+            self.name,
+            self.max,
+            self.wait.as_millis()
+        );
+        StrError::with_status(err_msg, StatusCode::SERVICE_UNAVAILABLE)
+    }
+}
+
+#[tokio::test]
+async fn concurrency_limit_test() {
+    use std::sync::Arc;
+
+    let limit = Arc::new(ConcurrencyLimit::new(
+        "UPDATE",
+        1,
+        Duration::from_millis(200),
+    ));
+    let permit = limit.acquire().await.unwrap();
+    assert!(permit.is_some());
+    assert_eq!(limit.in_flight(), 1);
+
+    // 권한을 얻지 못한 채로 기다리는 동안 queued에 집계됨
+    let waiting_limit = limit.clone();
+    let waiting =
+        tokio::spawn(async move { waiting_limit.acquire().await.map(|permit| permit.is_some()) });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(limit.queued(), 1);
+
+    let err = waiting.await.unwrap().unwrap_err();
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::SERVICE_UNAVAILABLE)
+    );
+    assert_eq!(limit.queued(), 0);
+
+    // 권한이 반환되면 다시 얻을 수 있음
+    drop(permit);
+    assert_eq!(limit.in_flight(), 0);
+    assert!(limit.acquire().await.unwrap().is_some());
+
+    // 제한이 없는 경우 항상 통과
+    let unlimited = ConcurrencyLimit::new("SELECT", 0, Duration::ZERO);
+    assert!(!unlimited.is_enabled());
+    assert!(unlimited.acquire().await.unwrap().is_none());
+}
@@ -0,0 +1,116 @@
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::header::CONTENT_TYPE;
+use hyper::{HeaderMap, StatusCode};
+
+/// update에 받는 기본 Content-Type
+pub const DEFAULT_UPDATE_CONTENT_TYPES: &[&str] = &["text/xml", "application/xml"];
+
+/// stream.body를 form으로 보낸 경우의 Content-Type. stream_body에서 xml을 꺼내므로 항상 받음
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// Content-Type의 media type. 파라미터(charset 등)는 제외하고 소문자로 바꿈
+fn media_type(value: &str) -> String {
+    value
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// update 요청의 Content-Type이 allowed에 있는지 확인함. 없으면 body를 읽기 전에 415 에러
+/// <br>
+/// Content-Type이 없는 요청은 xml로 보고 받으며, strict인 경우에만 거부함
+pub fn check_update(
+    header_map: &HeaderMap,
+    allowed: &[String],
+    strict: bool,
+) -> Result<(), BoxedError> {
+    let Some(value) = header_map.get(CONTENT_TYPE) else {
+        if strict {
+            return Err(unsupported("(none)", allowed));
+        }
+        return Ok(());
+    };
+    let value = String::from_utf8_lossy(value.as_bytes());
+    let media_type = media_type(&value);
+    if media_type == FORM_CONTENT_TYPE || allowed.contains(&media_type) {
+        return Ok(());
+    }
+    Err(unsupported(&value, allowed))
+}
+
+fn unsupported(received: &str, allowed: &[String]) -> BoxedError {
+    Box::new(StrError::with_status(
+        format!(
+            "UNSUPPORTED_CONTENT_TYPE: {}, allowed: {}",
+            received,
+            allowed.join(", ")
+        ),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    ))
+}
+
+/// body가 xml인지 확인함. Content-Type이 없는 요청은 check_update와 같이 xml로 봄
+pub fn is_xml(header_map: &HeaderMap) -> bool {
+    let Some(value) = header_map.get(CONTENT_TYPE) else {
+        return true;
+    };
+    let media_type = media_type(&String::from_utf8_lossy(value.as_bytes()));
+    media_type.ends_with("/xml") || media_type.ends_with("+xml")
+}
+
+/// 설정 값을 비교할 수 있도록 media type만 남김
+pub fn normalize(values: Vec<String>) -> Vec<String> {
+    values.iter().map(|value| media_type(value)).collect()
+}
+
+#[test]
+fn check_update_test() {
+    use crate::util::error_status;
+    use hyper::header::HeaderValue;
+
+    let allowed = normalize(vec!["text/xml".to_string(), " Application/XML".to_string()]);
+    assert_eq!(allowed, DEFAULT_UPDATE_CONTENT_TYPES);
+    let header_map = |value: &'static str| {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(CONTENT_TYPE, HeaderValue::from_static(value));
+        header_map
+    };
+
+    // charset 등의 파라미터와 대소문자는 구분하지 않음
+    for value in [
+        "text/xml",
+        "text/xml; charset=utf-8",
+        "APPLICATION/XML;charset=UTF-8",
+        "application/x-www-form-urlencoded",
+    ] {
+        assert!(check_update(&header_map(value), &allowed, false).is_ok());
+        assert!(check_update(&header_map(value), &allowed, true).is_ok());
+    }
+
+    // 받은 Content-Type을 그대로 에러 메시지에 남김
+    let err = check_update(
+        &header_map("multipart/form-data; boundary=x"),
+        &allowed,
+        false,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "UNSUPPORTED_CONTENT_TYPE: multipart/form-data; boundary=x, allowed: text/xml, application/xml"
+    );
+    assert_eq!(error_status(&err), Some(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+    assert!(check_update(&header_map("text/plain"), &allowed, false).is_err());
+    assert!(check_update(&header_map("application/json"), &allowed, false).is_err());
+    let with_json = normalize(vec!["application/json".to_string()]);
+    assert!(check_update(&header_map("application/json"), &with_json, false).is_ok());
+
+    // Content-Type이 없으면 strict인 경우에만 거부함
+    assert!(check_update(&HeaderMap::new(), &allowed, false).is_ok());
+    let err = check_update(&HeaderMap::new(), &allowed, true).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("UNSUPPORTED_CONTENT_TYPE: (none)"));
+}
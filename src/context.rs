@@ -0,0 +1,101 @@
+use hyper::header::HeaderName;
+use hyper::HeaderMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// request id 생성용 카운터
+static REQUEST_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 요청 하나를 처리하는 동안 유지되는 정보
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// 로그, 솔라 요청, 응답에 공통으로 들어가는 요청 식별값
+    pub request_id: String,
+    pub remote_ip: SocketAddr,
+    /// 요청을 받은 listener의 scheme. 솔라에 X-Forwarded-Proto로 전달함
+    pub scheme: Scheme,
+}
+
+/// 요청을 받은 listener의 scheme. TLS listener에서 받은 요청은 hyper 요청 extension으로 Https를 넣음
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Scheme {
+    #[default]
+    Http,
+    Https,
+}
+
+impl Scheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        }
+    }
+}
+
+impl RequestContext {
+    /// 클라이언트가 X-Request-Id를 보낸 경우 그 값을 사용하고, 없거나 잘못된 값이면 새로 생성함
+    pub fn new(header_map: &HeaderMap, remote_ip: SocketAddr) -> Self {
+        let request_id = header_map
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid_request_id(value))
+            .map(|value| value.to_string())
+            .unwrap_or_else(generate_request_id);
+
+        Self {
+            request_id,
+            remote_ip,
+            scheme: Scheme::Http,
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'_' | b'.'))
+}
+
+/// 12자리 hex 형식의 request id 생성
+fn generate_request_id() -> String {
+    let seq = REQUEST_SEQ.fetch_add(1, Ordering::Relaxed);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(seq);
+    format!("{:012x}", hasher.finish() & 0xffff_ffff_ffff)
+}
+
+#[test]
+fn request_id_test() {
+    use hyper::header::HeaderValue;
+
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    let mut header_map = HeaderMap::new();
+    header_map.insert(X_REQUEST_ID, HeaderValue::from_static("client-id_1.2"));
+    assert_eq!(
+        RequestContext::new(&header_map, remote_ip).request_id,
+        "client-id_1.2"
+    );
+
+    // 잘못된 값인 경우 새로 생성
+    header_map.insert(X_REQUEST_ID, HeaderValue::from_static("bad id"));
+    let request_id = RequestContext::new(&header_map, remote_ip).request_id;
+    assert_eq!(request_id.len(), 12);
+    assert!(request_id.bytes().all(|c| c.is_ascii_hexdigit()));
+
+    let first = RequestContext::new(&HeaderMap::new(), remote_ip).request_id;
+    let second = RequestContext::new(&HeaderMap::new(), remote_ip).request_id;
+    assert_ne!(first, second);
+}
@@ -0,0 +1,179 @@
+use crate::method_rule;
+use hyper::header::{
+    HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ORIGIN,
+    VARY,
+};
+use hyper::{HeaderMap, Method};
+use std::time::Duration;
+
+/// 브라우저에서 proxy로 직접 요청할 수 있도록 하는 CORS 설정
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// 허용하는 Origin 목록. "*"는 모든 Origin을 허용하며, 비어있으면 CORS header를 넣지 않음
+    pub allowed_origins: Vec<String>,
+    /// true인 경우 update 요청에도 CORS header를 넣음
+    pub allow_update: bool,
+    /// 브라우저가 preflight 결과를 cache할 시간
+    pub max_age: Duration,
+}
+
+/// 응답에 CORS header를 넣어야 하는 요청
+#[derive(Debug)]
+pub struct CorsRequest {
+    origin: HeaderValue,
+    /// OPTIONS preflight인 경우 허용하는 method와 header
+    preflight: Option<(HeaderValue, Option<HeaderValue>)>,
+    max_age: Duration,
+}
+
+impl CorsConfig {
+    /// CORS header를 넣어야 하는 요청인 경우 Some
+    /// <br>
+    /// select와 allow_update인 경우의 update만 허용하며, update_methods는 update에 사용할 수 있는 method
+    pub fn check(
+        &self,
+        path: &str,
+        method: &Method,
+        header_map: &HeaderMap,
+        update_methods: &[Method],
+    ) -> Option<CorsRequest> {
+        let methods = if path.ends_with("/select") {
+            method_rule::SELECT_METHODS
+        } else if path.ends_with("/update") && self.allow_update {
+            update_methods
+        } else {
+            return None;
+        };
+
+        let origin = header_map.get(ORIGIN)?;
+        let origin = if self.allowed_origins.iter().any(|allowed| allowed == "*") {
+            HeaderValue::from_static("*")
+        } else if self.allowed_origins.iter().any(|allowed| {
+            origin
+                .to_str()
+                .is_ok_and(|o| allowed.eq_ignore_ascii_case(o))
+        }) {
+            origin.clone()
+        } else {
+            return None;
+        };
+
+        let preflight = (method == Method::OPTIONS).then(|| {
+            (
+                method_rule::allow_header(methods),
+                header_map.get(ACCESS_CONTROL_REQUEST_HEADERS).cloned(),
+            )
+        });
+        Some(CorsRequest {
+            origin,
+            preflight,
+            max_age: self.max_age,
+        })
+    }
+}
+
+impl CorsRequest {
+    /// 응답에 CORS header를 넣음
+    pub fn add_headers(self, header_map: &mut HeaderMap) {
+        if self.origin != "*" {
+            header_map.append(VARY, HeaderValue::from_static("Origin"));
+        }
+        header_map.insert(ACCESS_CONTROL_ALLOW_ORIGIN, self.origin);
+
+        let Some((methods, request_headers)) = self.preflight else {
+            return;
+        };
+        header_map.insert(ACCESS_CONTROL_ALLOW_METHODS, methods);
+        if let Some(request_headers) = request_headers {
+            header_map.insert(ACCESS_CONTROL_ALLOW_HEADERS, request_headers);
+        }
+        header_map.insert(ACCESS_CONTROL_MAX_AGE, self.max_age.as_secs().into());
+    }
+}
+
+#[test]
+fn cors_check_test() {
+    let cors = CorsConfig {
+        allowed_origins: vec!["https://dashboard.example.com".to_string()],
+        allow_update: false,
+        max_age: Duration::from_secs(600),
+    };
+    let headers_from = |origin: &'static str| {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(ORIGIN, HeaderValue::from_static(origin));
+        header_map
+    };
+    let update_methods = method_rule::UPDATE_METHODS;
+    let allowed = headers_from("https://dashboard.example.com");
+
+    // 허용한 Origin인 경우 그대로 돌려줌
+    let mut response = HeaderMap::new();
+    cors.check("/solr/core/select", &Method::GET, &allowed, update_methods)
+        .unwrap()
+        .add_headers(&mut response);
+    assert_eq!(
+        response[ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://dashboard.example.com"
+    );
+    assert_eq!(response[VARY], "Origin");
+    assert!(response.get(ACCESS_CONTROL_ALLOW_METHODS).is_none());
+
+    // 허용하지 않은 Origin, Origin이 없는 요청, update는 CORS header를 넣지 않음
+    let denied = headers_from("https://evil.example.com");
+    assert!(cors
+        .check("/solr/core/select", &Method::GET, &denied, update_methods)
+        .is_none());
+    assert!(cors
+        .check(
+            "/solr/core/select",
+            &Method::GET,
+            &HeaderMap::new(),
+            update_methods
+        )
+        .is_none());
+    assert!(cors
+        .check("/solr/core/update", &Method::POST, &allowed, update_methods)
+        .is_none());
+
+    // preflight는 허용하는 method, header와 max-age를 함께 돌려줌
+    let mut preflight = allowed.clone();
+    preflight.insert(
+        ACCESS_CONTROL_REQUEST_HEADERS,
+        HeaderValue::from_static("content-type"),
+    );
+    let mut response = HeaderMap::new();
+    cors.check(
+        "/solr/core/select",
+        &Method::OPTIONS,
+        &preflight,
+        update_methods,
+    )
+    .unwrap()
+    .add_headers(&mut response);
+    assert_eq!(
+        response[ACCESS_CONTROL_ALLOW_METHODS],
+        "GET, POST, HEAD, OPTIONS"
+    );
+    assert_eq!(response[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+    assert_eq!(response[ACCESS_CONTROL_MAX_AGE], "600");
+
+    // "*"와 allow_update를 설정한 경우
+    let cors = CorsConfig {
+        allowed_origins: vec!["*".to_string()],
+        allow_update: true,
+        ..cors
+    };
+    let mut response = HeaderMap::new();
+    cors.check(
+        "/solr/core/update",
+        &Method::OPTIONS,
+        &denied,
+        update_methods,
+    )
+    .unwrap()
+    .add_headers(&mut response);
+    assert_eq!(response[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    assert_eq!(response[ACCESS_CONTROL_ALLOW_METHODS], "POST, OPTIONS");
+    assert!(response.get(VARY).is_none());
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+
+/// postdate 값을 확인한 결과
+#[derive(Debug, PartialEq, Eq)]
+pub enum DateCheck {
+    /// 솔라에서 그대로 사용할 수 있는 형식
+    Valid,
+    /// 솔라 형식(UTC, Z)으로 변환한 값
+    Rewritten(String),
+    /// 날짜로 해석할 수 없는 값
+    Invalid,
+}
+
+/// 솔라 날짜 형식으로 변환. YYYY-MM-DDTHH:MM:SS.mmmZ
+pub fn to_solr_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// tstamp에 넣을 현재 시각
+pub fn now_tstamp() -> String {
+    to_solr_date(Utc::now())
+}
+
+/// 날짜 값을 솔라 형식으로 변환함
+/// <br>
+/// 공백 구분자, millis 생략, +09:00 같은 offset을 허용하며, offset이 없는 값은 UTC로 간주함
+pub fn check_date(value: &str) -> DateCheck {
+    let value = value.trim();
+
+    // 이미 Z로 끝나는 형식인 경우 그대로 사용
+    if value.contains('T') && value.ends_with('Z') && DateTime::parse_from_rfc3339(value).is_ok() {
+        return DateCheck::Valid;
+    }
+
+    let value = value.replacen(' ', "T", 1);
+    if let Ok(date) = DateTime::parse_from_rfc3339(&value) {
+        return DateCheck::Rewritten(to_solr_date(date.with_timezone(&Utc)));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f") {
+        return DateCheck::Rewritten(to_solr_date(date.and_utc()));
+    }
+
+    DateCheck::Invalid
+}
+
+#[test]
+fn check_date_test() {
+    let rewritten = |value: &str| DateCheck::Rewritten(value.to_string());
+
+    // 솔라 형식
+    assert_eq!(check_date("2022-07-28T04:48:00Z"), DateCheck::Valid);
+    assert_eq!(check_date("2022-07-28T04:48:00.123Z"), DateCheck::Valid);
+
+    // 공백 구분자, millis 생략
+    assert_eq!(
+        check_date("2022-07-28 04:48:00"),
+        rewritten("2022-07-28T04:48:00.000Z")
+    );
+    assert_eq!(
+        check_date("2022-07-28 04:48:00.5"),
+        rewritten("2022-07-28T04:48:00.500Z")
+    );
+    assert_eq!(
+        check_date("2022-07-28T04:48:00"),
+        rewritten("2022-07-28T04:48:00.000Z")
+    );
+    assert_eq!(
+        check_date("2022-07-28 04:48:00Z"),
+        rewritten("2022-07-28T04:48:00.000Z")
+    );
+
+    // offset이 있는 경우 UTC로 변환
+    assert_eq!(
+        check_date("2022-07-28T13:48:00+09:00"),
+        rewritten("2022-07-28T04:48:00.000Z")
+    );
+    assert_eq!(
+        check_date("2022-07-28 13:48:00.250+09:00"),
+        rewritten("2022-07-28T04:48:00.250Z")
+    );
+
+    // 해석할 수 없는 값
+    assert_eq!(check_date("yesterday"), DateCheck::Invalid);
+    assert_eq!(check_date("2022-13-28 04:48:00"), DateCheck::Invalid);
+    assert_eq!(check_date(""), DateCheck::Invalid);
+
+    let tstamp = now_tstamp();
+    assert_eq!(tstamp.len(), "2022-07-28T04:48:00.000Z".len());
+    assert_eq!(check_date(&tstamp), DateCheck::Valid);
+}
@@ -0,0 +1,215 @@
+use crate::util::{ResponseWithError, StrError};
+use crate::BoxedError;
+use hyper::StatusCode;
+use serde_json::{json, Map, Value};
+
+/// 통계용 에러 분류
+/// <br>
+/// StrError에 kind를 지정하지 않은 경우 에러 타입과 status로 분류함
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// 받은 xml을 읽을 수 없음
+    ParseError,
+    /// seed_id 조회, 생성 중 DB 에러
+    DbError,
+    /// 솔라에 연결할 수 없거나 응답을 받지 못함
+    SolrConnectError,
+    /// 솔라가 5xx로 응답함. 응답은 클라이언트에 그대로 돌려줌
+    SolrStatusError,
+    /// 처리하지 않는 path
+    UnknownPath,
+    /// DB pool 등을 기다리다 시간을 넘김
+    Timeout,
+    /// 허용하지 않는 ip, method, 크기 제한 등으로 솔라에 보내지 않음
+    Rejected,
+    /// 그 외의 에러
+    Other,
+}
+
+impl ErrorKind {
+    const ALL: [ErrorKind; 8] = [
+        ErrorKind::ParseError,
+        ErrorKind::DbError,
+        ErrorKind::SolrConnectError,
+        ErrorKind::SolrStatusError,
+        ErrorKind::UnknownPath,
+        ErrorKind::Timeout,
+        ErrorKind::Rejected,
+        ErrorKind::Other,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::ParseError => "parse",
+            ErrorKind::DbError => "db",
+            ErrorKind::SolrConnectError => "solr_connect",
+            ErrorKind::SolrStatusError => "solr_status",
+            ErrorKind::UnknownPath => "unknown_path",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Rejected => "rejected",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// 에러의 분류. ResponseWithError인 경우 원인 에러로 분류함
+    pub fn of(err: &BoxedError) -> Self {
+        if let Some(e) = err.downcast_ref::<ResponseWithError>() {
+            return Self::of(&e.err);
+        }
+        if let Some(e) = err.downcast_ref::<StrError>() {
+            return match (e.kind, e.status) {
+                (Some(kind), _) => kind,
+                (None, Some(status)) => Self::from_status(status),
+                (None, None) => ErrorKind::Other,
+            };
+        }
+        if let Some(e) = err.downcast_ref::<sqlx::Error>() {
+            return match e {
+                sqlx::Error::PoolTimedOut => ErrorKind::Timeout,
+                _ => ErrorKind::DbError,
+            };
+        }
+        if err.is::<quick_xml::Error>() || err.is::<std::str::Utf8Error>() {
+            return ErrorKind::ParseError;
+        }
+        if err.is::<tokio::time::error::Elapsed>() {
+            return ErrorKind::Timeout;
+        }
+        ErrorKind::Other
+    }
+
+    /// kind를 지정하지 않은 StrError의 분류
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => ErrorKind::UnknownPath,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ErrorKind::Timeout,
+            StatusCode::BAD_GATEWAY => ErrorKind::SolrConnectError,
+            // 동시 요청 수 제한 등 proxy가 받지 않은 요청
+            StatusCode::SERVICE_UNAVAILABLE => ErrorKind::Rejected,
+            status if status.is_client_error() => ErrorKind::Rejected,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// 분류별 에러 횟수
+#[derive(Debug, Clone)]
+pub struct ErrorKindCnt {
+    cnt: [u32; ErrorKind::ALL.len()],
+}
+
+impl Default for ErrorKindCnt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorKindCnt {
+    pub const fn new() -> Self {
+        Self {
+            cnt: [0; ErrorKind::ALL.len()],
+        }
+    }
+
+    pub fn record(&mut self, kind: ErrorKind) {
+        self.cnt[kind as usize] += 1;
+    }
+
+    pub fn get(&self, kind: ErrorKind) -> u32 {
+        self.cnt[kind as usize]
+    }
+
+    /// previous 이후 증가한 횟수
+    pub fn delta(&self, previous: &Self) -> Self {
+        let mut delta = Self::new();
+        for kind in 0..ErrorKind::ALL.len() {
+            delta.cnt[kind] = self.cnt[kind].saturating_sub(previous.cnt[kind]);
+        }
+        delta
+    }
+
+    /// 분 단위 로그용. 0인 값은 생략함. 예: parse:2 solr_connect:1
+    pub fn summary(&self) -> String {
+        ErrorKind::ALL
+            .iter()
+            .filter(|&&kind| self.get(kind) > 0)
+            .map(|&kind| format!("{}:{}", kind.name(), self.get(kind)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// 통계 endpoint용. 0인 값도 모두 포함함
+    pub fn to_json(&self) -> Value {
+        let mut kinds = Map::new();
+        for kind in ErrorKind::ALL {
+            kinds.insert(kind.name().to_string(), json!(self.get(kind)));
+        }
+        Value::Object(kinds)
+    }
+}
+
+#[test]
+fn error_kind_test() {
+    let str_error = |status: Option<StatusCode>| -> BoxedError {
+        Box::new(StrError {
+            err_msg: "E".to_string(),
+            status,
+            kind: None,
+        })
+    };
+
+    // kind를 지정한 경우 status와 관계없이 kind
+    let err: BoxedError = Box::new(
+        StrError::with_status(
+            "SEED_ID_LOOKUP_SUPPRESSED".to_string(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+        .with_kind(ErrorKind::DbError),
+    );
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DbError);
+    assert_eq!(err.to_string(), "SEED_ID_LOOKUP_SUPPRESSED");
+
+    assert_eq!(
+        ErrorKind::of(&str_error(Some(StatusCode::NOT_FOUND))),
+        ErrorKind::UnknownPath
+    );
+    assert_eq!(
+        ErrorKind::of(&str_error(Some(StatusCode::PAYLOAD_TOO_LARGE))),
+        ErrorKind::Rejected
+    );
+    assert_eq!(
+        ErrorKind::of(&str_error(Some(StatusCode::SERVICE_UNAVAILABLE))),
+        ErrorKind::Rejected
+    );
+    assert_eq!(
+        ErrorKind::of(&str_error(Some(StatusCode::INTERNAL_SERVER_ERROR))),
+        ErrorKind::Other
+    );
+    assert_eq!(ErrorKind::of(&str_error(None)), ErrorKind::Other);
+
+    // 에러 타입으로 분류함
+    let err: BoxedError = Box::new(sqlx::Error::PoolTimedOut);
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Timeout);
+    let err: BoxedError = Box::new(sqlx::Error::RowNotFound);
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DbError);
+    let err: BoxedError = Box::new(quick_xml::Error::UnexpectedEof("doc".to_string()));
+    assert_eq!(ErrorKind::of(&err), ErrorKind::ParseError);
+
+    // ResponseWithError는 원인 에러로 분류함
+    let err: BoxedError = Box::new(ResponseWithError {
+        err: Box::new(sqlx::Error::RowNotFound),
+        response: hyper::Response::new(hyper::Body::empty()),
+    });
+    assert_eq!(ErrorKind::of(&err), ErrorKind::DbError);
+
+    let mut cnt = ErrorKindCnt::new();
+    cnt.record(ErrorKind::ParseError);
+    cnt.record(ErrorKind::ParseError);
+    let previous = cnt.clone();
+    cnt.record(ErrorKind::SolrConnectError);
+    assert_eq!(cnt.summary(), "parse:2 solr_connect:1");
+    assert_eq!(cnt.delta(&previous).summary(), "solr_connect:1");
+    assert_eq!(cnt.to_json()["parse"], 2);
+    assert_eq!(cnt.to_json()["timeout"], 0);
+    assert_eq!(ErrorKindCnt::new().summary(), "");
+}
@@ -0,0 +1,133 @@
+use hyper::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use hyper::{Body, HeaderMap, Response, StatusCode, Uri};
+
+/// proxy에서 발생한 에러임을 나타내기 위해 metadata에 들어가는 값
+const ERROR_SOURCE: &str = "solr_proxy";
+
+/// 에러 응답 형식
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Xml,
+}
+
+impl ResponseFormat {
+    /// wt 파라미터로 응답 형식을 결정. wt가 없거나 알 수 없는 값이면 Accept 헤더를 보고, 그것도 없으면 솔라 기본값인 json
+    pub fn from_request(uri: &Uri, header_map: &HeaderMap) -> Self {
+        let wt = uri.query().and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| *name == "wt")
+                .map(|(_, value)| value)
+        });
+
+        match wt {
+            Some("json") => return Self::Json,
+            Some("xml") => return Self::Xml,
+            _ => (),
+        }
+
+        let accept = header_map
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or("");
+        if accept.contains("xml") && !accept.contains("json") {
+            Self::Xml
+        } else {
+            Self::Json
+        }
+    }
+}
+
+/// 솔라의 에러 응답과 같은 형식으로 proxy 에러 응답을 만듦
+pub fn error_response(status: StatusCode, msg: &str, format: ResponseFormat) -> Response<Body> {
+    let code = status.as_u16();
+    let (body, content_type) = match format {
+        ResponseFormat::Json => {
+            let body = serde_json::json!({
+                "responseHeader": {
+                    "status": code,
+                    "QTime": 0,
+                },
+                "error": {
+                    "metadata": ["error-source", ERROR_SOURCE],
+                    "msg": msg,
+                    "code": code,
+                },
+            });
+            (body.to_string(), "application/json;charset=utf-8")
+        }
+        ResponseFormat::Xml => {
+            let body = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<lst name="responseHeader"><int name="status">{code}</int><int name="QTime">0</int></lst>
+<lst name="error"><lst name="metadata"><str name="error-source">{ERROR_SOURCE}</str></lst><str name="msg">{}</str><int name="code">{code}</int></lst>
+</response>
+"#,
+                quick_xml::escape::escape(msg)
+            );
+            (body, "application/xml;charset=utf-8")
+        }
+    };
+
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+#[tokio::test]
+async fn error_response_test() {
+    let header_map = HeaderMap::new();
+
+    // json
+    let format = ResponseFormat::from_request(
+        &Uri::from_static("/solr/core/select?q=*:*&wt=json"),
+        &header_map,
+    );
+    assert_eq!(format, ResponseFormat::Json);
+    let response = error_response(StatusCode::NOT_FOUND, "UNKNOWN_PATH /a\"b", format);
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers()[CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("application/json"));
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["responseHeader"]["status"], 404);
+    assert_eq!(json["error"]["msg"], "UNKNOWN_PATH /a\"b");
+    assert_eq!(json["error"]["code"], 404);
+    assert_eq!(json["error"]["metadata"][1], ERROR_SOURCE);
+
+    // xml
+    let format =
+        ResponseFormat::from_request(&Uri::from_static("/solr/core/update?wt=xml"), &header_map);
+    assert_eq!(format, ResponseFormat::Xml);
+    let response = error_response(StatusCode::BAD_REQUEST, "MAX_DOCS <10>", format);
+    assert!(response.headers()[CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("application/xml"));
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body.contains(r#"<int name="status">400</int>"#));
+    assert!(body.contains(r#"<str name="msg">MAX_DOCS &lt;10&gt;</str>"#));
+    assert!(body.contains(r#"<str name="error-source">solr_proxy</str>"#));
+
+    // 알 수 없는 wt인 경우 Accept 헤더를 보고, 없으면 json
+    let uri = Uri::from_static("/solr/core/select?wt=javabin");
+    assert_eq!(
+        ResponseFormat::from_request(&uri, &header_map),
+        ResponseFormat::Json
+    );
+    let mut accept_xml = HeaderMap::new();
+    accept_xml.insert(ACCEPT, HeaderValue::from_static("application/xml"));
+    assert_eq!(
+        ResponseFormat::from_request(&uri, &accept_xml),
+        ResponseFormat::Xml
+    );
+}
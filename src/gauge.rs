@@ -0,0 +1,275 @@
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::StatusCode;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 현재 처리중인 작업 수와 구간별 최대값
+pub struct Gauge {
+    current: AtomicU64,
+    high: AtomicU64,
+}
+
+/// Drop될 때 gauge를 줄임. 에러나 panic으로 중간에 끝난 경우에도 줄어듦
+pub struct GaugeGuard<'a> {
+    gauge: &'a Gauge,
+}
+
+impl Gauge {
+    pub const fn new() -> Self {
+        Self {
+            current: AtomicU64::new(0),
+            high: AtomicU64::new(0),
+        }
+    }
+
+    /// 작업을 시작함. 반환된 guard가 Drop될 때 작업이 끝난 것으로 봄
+    pub fn enter(&self) -> GaugeGuard<'_> {
+        let current = self.current.fetch_add(1, Ordering::Relaxed) + 1;
+        self.high.fetch_max(current, Ordering::Relaxed);
+        GaugeGuard { gauge: self }
+    }
+
+    /// fut이 끝날 때까지 작업 중으로 셈
+    pub async fn track<F: Future>(&self, fut: F) -> F::Output {
+        let _guard = self.enter();
+        fut.await
+    }
+
+    pub fn current(&self) -> u64 {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간의 최대값
+    pub fn high(&self) -> u64 {
+        self.high.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간의 최대값을 반환하고 다음 구간의 최대값을 현재 값부터 다시 셈
+    pub fn take_high(&self) -> u64 {
+        self.high.swap(self.current(), Ordering::Relaxed)
+    }
+}
+
+impl Drop for GaugeGuard<'_> {
+    fn drop(&mut self) {
+        self.gauge.current.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 처리중인 요청 수. 솔라, DB는 요청을 보내고 응답을 기다리는 중인 수
+pub struct InFlight {
+    pub select: Gauge,
+    pub update: Gauge,
+    pub solr: Gauge,
+    pub db: Gauge,
+}
+
+impl InFlight {
+    pub const fn new() -> Self {
+        Self {
+            select: Gauge::new(),
+            update: Gauge::new(),
+            solr: Gauge::new(),
+            db: Gauge::new(),
+        }
+    }
+
+    pub fn gauges(&self) -> [(&'static str, &Gauge); 4] {
+        [
+            ("select", &self.select),
+            ("update", &self.update),
+            ("solr", &self.solr),
+            ("db", &self.db),
+        ]
+    }
+}
+
+/// 처리중인 요청들이 메모리에 들고 있는 bytes의 합과 구간별 최대값
+/// <br>
+/// 합이 제한을 넘게 되는 요청은 받지 않음
+pub struct ByteBudget {
+    current: AtomicU64,
+    high: AtomicU64,
+}
+
+/// 요청 하나가 차지한 bytes. Drop될 때 반환함. 에러나 panic으로 중간에 끝난 경우에도 반환됨
+pub struct BudgetGuard<'a> {
+    budget: &'a ByteBudget,
+    bytes: u64,
+    /// reserve_to가 제한을 넘어 실패한 적이 있는 경우 true
+    rejected: bool,
+}
+
+impl ByteBudget {
+    pub const fn new() -> Self {
+        Self {
+            current: AtomicU64::new(0),
+            high: AtomicU64::new(0),
+        }
+    }
+
+    /// 아직 아무것도 차지하지 않은 guard
+    pub fn guard(&self) -> BudgetGuard<'_> {
+        BudgetGuard {
+            budget: self,
+            bytes: 0,
+            rejected: false,
+        }
+    }
+
+    pub fn current(&self) -> u64 {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간의 최대값
+    pub fn high(&self) -> u64 {
+        self.high.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간의 최대값을 반환하고 다음 구간의 최대값을 현재 값부터 다시 셈
+    pub fn take_high(&self) -> u64 {
+        self.high.swap(self.current(), Ordering::Relaxed)
+    }
+}
+
+impl BudgetGuard<'_> {
+    /// 차지한 bytes를 total까지 늘림. 전체 합이 max를 넘게 되면 늘리지 않고 503 에러. max가 0이면 제한 없음
+    pub fn reserve_to(&mut self, total: u64, max: u64) -> Result<(), BoxedError> {
+        let Some(add) = total.checked_sub(self.bytes).filter(|&add| add > 0) else {
+            return Ok(());
+        };
+        let reserved =
+            self.budget
+                .current
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                    let next = current.saturating_add(add);
+                    (max == 0 || next <= max).then_some(next)
+                });
+        match reserved {
+            Ok(previous) => {
+                self.bytes = total;
+                self.budget
+                    .high
+                    .fetch_max(previous + add, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(current) => {
+                self.rejected = true;
+                Err(Box::new(StrError::with_status(
+                    format!(
+                        "MAX_BUFFERED_BYTES_EXCEEDED: {}, buffered {}, request {}",
+                        max, current, total
+                    ),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )))
+            }
+        }
+    }
+
+    /// reserve_to가 제한을 넘어 실패한 적이 있는지. body를 읽는 도중 실패한 경우 다른 에러와 구분하기 위해 사용함
+    pub fn rejected(&self) -> bool {
+        self.rejected
+    }
+}
+
+impl Drop for BudgetGuard<'_> {
+    fn drop(&mut self) {
+        self.budget.current.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+#[tokio::test]
+async fn gauge_test() {
+    let gauge = Gauge::new();
+    {
+        let _first = gauge.enter();
+        let _second = gauge.enter();
+        assert_eq!(gauge.current(), 2);
+    }
+    assert_eq!(gauge.current(), 0);
+    assert_eq!(gauge.high(), 2);
+
+    // 에러를 반환한 경우에도 줄어듦
+    let result = gauge
+        .track(async {
+            assert_eq!(gauge.current(), 1);
+            Err::<(), _>("DB_FAIL")
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(gauge.current(), 0);
+
+    // 끝나기 전에 버려진 future도 줄어듦
+    let pending = gauge.track(std::future::pending::<()>());
+    let timeout = tokio::time::timeout(std::time::Duration::from_millis(10), pending).await;
+    assert!(timeout.is_err());
+    assert_eq!(gauge.current(), 0);
+
+    // 구간이 바뀌면 최대값은 현재 값부터 다시 셈
+    let _guard = gauge.enter();
+    assert_eq!(gauge.take_high(), 2);
+    assert_eq!(gauge.high(), 1);
+}
+
+#[tokio::test]
+async fn byte_budget_test() {
+    use std::sync::Arc;
+
+    let budget = Arc::new(ByteBudget::new());
+    let mut first = budget.guard();
+    // Content-Length로 먼저 차지하고 읽은 뒤 실제 크기로 늘림
+    first.reserve_to(40, 100).unwrap();
+    first.reserve_to(60, 100).unwrap();
+    first.reserve_to(10, 100).unwrap();
+    assert_eq!(budget.current(), 60);
+
+    // 동시에 들어온 큰 요청 중 제한 안에 들어가는 요청만 받음
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            let budget = budget.clone();
+            tokio::spawn(async move {
+                let mut guard = budget.guard();
+                let result = guard.reserve_to(30, 100);
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                result.is_ok()
+            })
+        })
+        .collect();
+    let mut accepted = 0;
+    for task in tasks {
+        accepted += task.await.unwrap() as u32;
+    }
+    assert_eq!(accepted, 1);
+    assert_eq!(budget.current(), 60);
+    assert_eq!(budget.high(), 90);
+
+    let mut rejected = budget.guard();
+    assert!(!rejected.rejected());
+    let err = rejected.reserve_to(41, 100).unwrap_err();
+    assert!(rejected.rejected());
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::SERVICE_UNAVAILABLE)
+    );
+    assert!(err
+        .to_string()
+        .starts_with("MAX_BUFFERED_BYTES_EXCEEDED: 100, buffered 60"));
+
+    // panic으로 끝난 요청도 반환함
+    let panic_budget = budget.clone();
+    let panicked = tokio::spawn(async move {
+        let mut guard = panic_budget.guard();
+        guard.reserve_to(40, 100).unwrap();
+        panic!("SOLR_FAIL");
+    })
+    .await;
+    assert!(panicked.is_err());
+    drop(first);
+    assert_eq!(budget.current(), 0);
+
+    // 제한이 없는 경우 항상 받음
+    let mut unlimited = budget.guard();
+    unlimited.reserve_to(u64::MAX / 2, 0).unwrap();
+    assert_eq!(budget.take_high(), u64::MAX / 2);
+}
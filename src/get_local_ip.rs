@@ -1,28 +1,107 @@
-use local_ip_address::Error;
+use crate::util::StrError;
+use crate::BoxedError;
 use std::net::IpAddr;
 
-pub fn get_local_ip() -> Result<IpAddr, Error> {
-    #[cfg(target_os = "linux")]
-    {
-        local_ip_address::linux::local_ip()
+/// 서버가 받을 ip
+/// <br>
+/// bind_interface, prefer_ipv6를 설정하지 않은 경우 linux는 기본 route의 ip, windows는 Ethernet 어댑터의 v4 ip를 사용하고,
+/// 그 외의 OS는 local_ip_address::local_ip()가 인터페이스 목록에서 찾은 v4 ip를 사용함. 기본 route의 ip라는 보장은 없음
+/// <br>
+/// 설정한 경우 인터페이스 목록에서 고름
+pub fn get_local_ip(bind_interface: &str, prefer_ipv6: bool) -> Result<IpAddr, BoxedError> {
+    if bind_interface.is_empty() && !prefer_ipv6 {
+        if let Ok(ip) = default_local_ip() {
+            return Ok(ip);
+        }
     }
 
+    let ifas = local_ip_address::list_afinet_netifas()?;
+    select_ip(&ifas, bind_interface, prefer_ipv6).ok_or_else(|| -> BoxedError {
+        Box::new(StrError::new(format!(
+            "LOCAL_IP_NOT_FOUND: bind_interface = {:?}, prefer_ipv6 = {}, interfaces: [{}]",
+            bind_interface,
+            prefer_ipv6,
+            ifas.iter()
+                .map(|(name, ip)| format!("{} {}", name, ip))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    })
+}
+
+/// bind_interface, prefer_ipv6를 설정하지 않은 경우의 ip
+fn default_local_ip() -> Result<IpAddr, BoxedError> {
     #[cfg(target_os = "windows")]
     {
-        use std::env;
-
-        let ifas = local_ip_address::windows::list_afinet_netifas()?;
+        let ifas = local_ip_address::list_afinet_netifas()?;
+        ifas.into_iter()
+            .find(|(name, ip)| name.contains("Ethernet") && ip.is_ipv4())
+            .map(|(_, ip)| ip)
+            .ok_or_else(|| "no Ethernet adapter".into())
+    }
 
-        if let Some((_, ipaddr)) = find_ifa(ifas, "Ethernet") {
-            return Ok(ipaddr);
-        }
+    #[cfg(not(target_os = "windows"))]
+    {
+        Ok(local_ip_address::local_ip()?)
+    }
+}
 
-        Err(Error::PlatformNotSupported(env::consts::OS.to_string()))
+/// 인터페이스 목록에서 ip를 고름. 조건에 맞는 ip가 없으면 None
+/// <br>
+/// bind_interface가 있으면 이름이 같은(대소문자 구분 없음) 인터페이스만, 없으면 loopback이 아닌 인터페이스를 사용함.
+/// prefer_ipv6인 경우 link-local이 아닌 첫번째 v6 ip를 사용하고, v6 ip가 없으면 v4 ip를 사용함
+fn select_ip(ifas: &[(String, IpAddr)], bind_interface: &str, prefer_ipv6: bool) -> Option<IpAddr> {
+    let candidates: Vec<IpAddr> = ifas
+        .iter()
+        .filter(|(name, ip)| {
+            if bind_interface.is_empty() {
+                !ip.is_loopback()
+            } else {
+                name.eq_ignore_ascii_case(bind_interface)
+            }
+        })
+        .map(|(_, ip)| *ip)
+        .collect();
+    let v6 = candidates.iter().find(|ip| match ip {
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80,
+        IpAddr::V4(_) => false,
+    });
+    let v4 = candidates.iter().find(|ip| ip.is_ipv4());
+    match prefer_ipv6 {
+        true => v6.or(v4),
+        false => v4.or(v6),
     }
+    .copied()
 }
 
-#[cfg(target_os = "windows")]
-fn find_ifa(ifas: Vec<(String, IpAddr)>, ifa_name: &str) -> Option<(String, IpAddr)> {
-    ifas.into_iter()
-        .find(|(name, ipaddr)| name.contains(ifa_name) && matches!(ipaddr, IpAddr::V4(_)))
+#[test]
+fn select_ip_test() {
+    let ifas: Vec<(String, IpAddr)> = [
+        ("lo", "127.0.0.1"),
+        ("lo", "::1"),
+        ("eth0", "10.0.0.7"),
+        ("eth0", "fe80::1"),
+        ("eth0", "2001:db8::7"),
+        ("eth1", "192.168.0.7"),
+        ("tun0", "fe80::2"),
+    ]
+    .iter()
+    .map(|(name, ip)| (name.to_string(), ip.parse().unwrap()))
+    .collect();
+    let ip = |value: &str| Some(value.parse::<IpAddr>().unwrap());
+
+    // 인터페이스 이름이 같은 ip만 사용함
+    assert_eq!(select_ip(&ifas, "eth1", false), ip("192.168.0.7"));
+    assert_eq!(select_ip(&ifas, "ETH0", false), ip("10.0.0.7"));
+    assert_eq!(select_ip(&ifas, "eth0", true), ip("2001:db8::7"));
+    // v6 ip가 없으면 v4 ip
+    assert_eq!(select_ip(&ifas, "eth1", true), ip("192.168.0.7"));
+    // 지정한 경우 loopback도 사용할 수 있음
+    assert_eq!(select_ip(&ifas, "lo", false), ip("127.0.0.1"));
+    // 인터페이스를 지정하지 않은 경우 loopback이 아닌 첫번째 ip
+    assert_eq!(select_ip(&ifas, "", false), ip("10.0.0.7"));
+    assert_eq!(select_ip(&ifas, "", true), ip("2001:db8::7"));
+    // link-local만 있는 인터페이스는 v6 ip를 사용하지 않음
+    assert_eq!(select_ip(&ifas, "tun0", true), None);
+    assert_eq!(select_ip(&ifas, "wlan0", false), None);
 }
@@ -0,0 +1,269 @@
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 2의 거듭제곱 구간마다 나누는 버킷 수. 값과 버킷 경계의 차이는 최대 12.5%
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKET_CNT: usize = 1 << SUB_BUCKET_BITS;
+/// 2^36us(약 19시간) 이상은 마지막 버킷에 넣음
+const MAX_EXP: u32 = 36;
+const BUCKET_CNT: usize = SUB_BUCKET_CNT + (MAX_EXP - SUB_BUCKET_BITS) as usize * SUB_BUCKET_CNT;
+
+/// 로그와 통계 endpoint에 남기는 백분위수
+const PERCENTILES: [(&str, f64); 4] = [("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("p999", 0.999)];
+
+/// 소요 시간의 분포. 마이크로초 단위로 2의 거듭제곱 구간을 다시 8개로 나눈 고정 버킷을 사용함
+/// <br>
+/// 버킷마다 atomic 카운터를 사용하므로 기록할 때 lock을 잡지 않음
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKET_CNT],
+}
+
+/// Drop될 때 시작한 뒤 지난 시간을 기록함
+pub struct HistogramTimer<'a> {
+    histogram: &'a Histogram,
+    start: Instant,
+}
+
+impl Histogram {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKET_CNT],
+        }
+    }
+
+    pub fn record(&self, duration: Duration) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[bucket_index(micros)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 반환된 timer가 Drop될 때까지 걸린 시간을 기록함
+    pub fn start_timer(&self) -> HistogramTimer<'_> {
+        HistogramTimer {
+            histogram: self,
+            start: Instant::now(),
+        }
+    }
+
+    /// 현재까지 기록된 분포
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .collect(),
+        }
+    }
+
+    /// 이번 구간의 분포를 반환하고 다음 구간은 0부터 다시 셈
+    pub fn take(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|bucket| bucket.swap(0, Ordering::Relaxed))
+                .collect(),
+        }
+    }
+}
+
+impl Drop for HistogramTimer<'_> {
+    fn drop(&mut self) {
+        self.histogram.record(self.start.elapsed());
+    }
+}
+
+/// micros가 들어갈 버킷
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKET_CNT as u64 {
+        return micros as usize;
+    }
+    let exp = (63 - micros.leading_zeros()).min(MAX_EXP - 1);
+    let sub =
+        ((micros >> (exp - SUB_BUCKET_BITS)) as usize).min(SUB_BUCKET_CNT * 2 - 1) - SUB_BUCKET_CNT;
+    SUB_BUCKET_CNT + (exp - SUB_BUCKET_BITS) as usize * SUB_BUCKET_CNT + sub
+}
+
+/// index 버킷에 들어가는 가장 작은 값(us)
+fn bucket_lower(index: usize) -> u64 {
+    if index < SUB_BUCKET_CNT {
+        return index as u64;
+    }
+    let exp = ((index - SUB_BUCKET_CNT) / SUB_BUCKET_CNT) as u32 + SUB_BUCKET_BITS;
+    let sub = ((index - SUB_BUCKET_CNT) % SUB_BUCKET_CNT + SUB_BUCKET_CNT) as u64;
+    sub << (exp - SUB_BUCKET_BITS)
+}
+
+/// index 버킷에 들어가는 가장 큰 값(us)
+fn bucket_upper(index: usize) -> u64 {
+    match index + 1 < BUCKET_CNT {
+        true => bucket_lower(index + 1) - 1,
+        false => u64::MAX,
+    }
+}
+
+/// 한 시점의 Histogram 값
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    buckets: Vec<u64>,
+}
+
+impl HistogramSnapshot {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// quantile(0~1) 위치의 값이 들어있는 버킷의 가장 큰 값. 기록된 값이 없으면 0
+    pub fn percentile(&self, quantile: f64) -> Duration {
+        let count = self.count();
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((quantile * count as f64).ceil() as u64).clamp(1, count);
+        let mut seen = 0;
+        for (index, cnt) in self.buckets.iter().enumerate() {
+            seen += cnt;
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper(index));
+            }
+        }
+        Duration::from_micros(bucket_upper(BUCKET_CNT - 1))
+    }
+
+    /// 분 단위 로그용. 예: n=120 p50 3.2ms p90 8.1ms p99 40.9ms p999 81.9ms
+    pub fn summary(&self) -> String {
+        let percentiles: Vec<String> = PERCENTILES
+            .iter()
+            .map(|&(name, quantile)| {
+                format!(
+                    "{} {:.1}ms",
+                    name,
+                    self.percentile(quantile).as_secs_f64() * 1000f64
+                )
+            })
+            .collect();
+        format!("n={} {}", self.count(), percentiles.join(" "))
+    }
+
+    /// 통계 endpoint용. buckets는 [버킷의 가장 큰 값(us), 횟수] 목록이며 0인 버킷은 생략함
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({ "count": self.count() });
+        for (name, quantile) in PERCENTILES {
+            value[format!("{}_ms", name)] =
+                json!(self.percentile(quantile).as_secs_f64() * 1000f64);
+        }
+        value["buckets"] = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, &cnt)| cnt > 0)
+            .map(|(index, &cnt)| json!([bucket_upper(index), cnt]))
+            .collect();
+        value
+    }
+}
+
+/// 구간별로 집계하는 소요 시간 분포
+pub struct Latency {
+    /// select 요청 전체
+    pub select: Histogram,
+    /// update 요청 전체
+    pub update: Histogram,
+    /// update 요청마다 seed_id 조회, 생성에 DB를 사용한 시간. DB를 사용하지 않은 요청은 기록하지 않음
+    pub db: Histogram,
+    /// 솔라에 요청을 보내고 응답 header를 받을 때까지
+    pub solr: Histogram,
+}
+
+impl Latency {
+    pub const fn new() -> Self {
+        Self {
+            select: Histogram::new(),
+            update: Histogram::new(),
+            db: Histogram::new(),
+            solr: Histogram::new(),
+        }
+    }
+
+    pub fn histograms(&self) -> [(&'static str, &Histogram); 4] {
+        [
+            ("select", &self.select),
+            ("update", &self.update),
+            ("db", &self.db),
+            ("solr", &self.solr),
+        ]
+    }
+}
+
+#[test]
+fn bucket_test() {
+    // 버킷 경계는 연속이고 값은 자신이 들어간 버킷의 범위 안에 있음
+    for index in 0..BUCKET_CNT - 1 {
+        assert_eq!(bucket_upper(index) + 1, bucket_lower(index + 1));
+    }
+    for micros in [0, 1, 7, 8, 9, 15, 16, 1000, 10_000, 123_456, 60_000_000] {
+        let index = bucket_index(micros);
+        assert!(bucket_lower(index) <= micros && micros <= bucket_upper(index));
+        // 버킷 폭은 값의 12.5% 이하
+        assert!((bucket_upper(index) - bucket_lower(index)) * 8 <= micros.max(8));
+    }
+    assert_eq!(bucket_index(u64::MAX), BUCKET_CNT - 1);
+}
+
+#[test]
+fn histogram_percentile_test() {
+    let histogram = Histogram::new();
+    assert_eq!(histogram.snapshot().percentile(0.5), Duration::ZERO);
+
+    // 1ms ~ 1000ms를 한 번씩 기록함
+    for ms in 1..=1000 {
+        histogram.record(Duration::from_millis(ms));
+    }
+    let snapshot = histogram.snapshot();
+    assert_eq!(snapshot.count(), 1000);
+    let within = |quantile: f64, expected_ms: u64| {
+        let value = snapshot.percentile(quantile);
+        let expected = Duration::from_millis(expected_ms);
+        assert!(
+            value >= expected && value <= expected + expected / 8,
+            "{} {:?}",
+            quantile,
+            value
+        );
+    };
+    within(0.5, 500);
+    within(0.9, 900);
+    within(0.99, 990);
+    within(0.999, 999);
+    within(1.0, 1000);
+
+    let json = snapshot.to_json();
+    assert_eq!(json["count"], 1000);
+    let bucket_total: u64 = json["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket[1].as_u64().unwrap())
+        .sum();
+    assert_eq!(bucket_total, 1000);
+
+    // 같은 값만 있는 경우 모든 백분위수가 같은 버킷
+    let histogram = Histogram::new();
+    for _ in 0..99 {
+        histogram.record(Duration::from_micros(3));
+    }
+    histogram.record(Duration::from_secs(2));
+    let snapshot = histogram.take();
+    assert_eq!(snapshot.percentile(0.5), Duration::from_micros(3));
+    assert_eq!(snapshot.percentile(0.99), Duration::from_micros(3));
+    assert!(snapshot.percentile(0.999) >= Duration::from_secs(2));
+    assert!(snapshot.summary().starts_with("n=100 p50 0.0ms"));
+
+    // take 이후에는 0부터 다시 셈
+    assert_eq!(histogram.snapshot().count(), 0);
+    {
+        let _timer = histogram.start_timer();
+    }
+    assert_eq!(histogram.snapshot().count(), 1);
+}
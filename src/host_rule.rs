@@ -0,0 +1,135 @@
+use config::{Config, ConfigError};
+use regex::Regex;
+use serde::Deserialize;
+
+/// 카페/블로그처럼 host 뒤의 path까지 seed_host로 사용하는 경우의 기본 패턴
+const DEFAULT_CAPTURE_REGEX: &str = r#"^([^/]+/[^/]+)"#;
+
+/// 기본 규칙에 해당하는 prefix 목록
+const DEFAULT_PREFIXES: [&str; 4] = [
+    "cafe.naver.com",
+    "m.cafe.daum.net",
+    "cafe.daum.net",
+    "blog.naver.com",
+];
+
+/// url이 prefix로 시작하는 경우 capture_regex의 첫번째 그룹을 seed_host로 사용하는 규칙
+#[derive(Debug, Clone)]
+pub struct HostRule {
+    pub prefix: String,
+    pub capture_regex: Regex,
+}
+
+/// config에 작성된 규칙
+#[derive(Debug, Deserialize)]
+struct HostRuleConfig {
+    prefix: String,
+    capture_regex: String,
+}
+
+impl HostRule {
+    pub fn new(prefix: String, capture_regex: &str) -> Result<Self, ConfigError> {
+        let capture_regex = Regex::new(capture_regex).map_err(|e| {
+            ConfigError::Message(format!(
+                "INVALID_HOST_RULE_REGEX: prefix: {}, capture_regex: {}, {}",
+                prefix, capture_regex, e
+            ))
+        })?;
+
+        Ok(Self {
+            prefix,
+            capture_regex,
+        })
+    }
+
+    /// 기존에 코드로 정해져 있던 카페/블로그 규칙
+    pub fn defaults() -> Vec<Self> {
+        let capture_regex = Regex::new(DEFAULT_CAPTURE_REGEX).unwrap();
+        DEFAULT_PREFIXES
+            .iter()
+            .map(|prefix| Self {
+                prefix: prefix.to_string(),
+                capture_regex: capture_regex.clone(),
+            })
+            .collect()
+    }
+
+    /// config의 host_rules를 순서대로 읽음. 없는 경우 기본 규칙을 사용
+    pub fn from_config(config: &Config) -> Result<Vec<Self>, ConfigError> {
+        Self::from_config_or(config, "host_rules", Self::defaults)
+    }
+
+    /// config의 key에 작성된 규칙을 순서대로 읽음. 없는 경우 default 규칙을 사용
+    pub fn from_config_or(
+        config: &Config,
+        key: &str,
+        default: impl FnOnce() -> Vec<Self>,
+    ) -> Result<Vec<Self>, ConfigError> {
+        match config.get::<Vec<HostRuleConfig>>(key) {
+            Ok(rules) => rules
+                .into_iter()
+                .map(|rule| Self::new(rule.prefix, &rule.capture_regex))
+                .collect(),
+            Err(ConfigError::NotFound(_)) => Ok(default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// url에 해당하는 첫번째 규칙을 찾음
+pub fn find_rule<'a>(rules: &'a [HostRule], url: &str) -> Option<&'a HostRule> {
+    rules
+        .iter()
+        .find(|rule| url.starts_with(rule.prefix.as_str()))
+}
+
+#[test]
+fn host_rule_config_test() {
+    use config::{File, FileFormat};
+
+    let config = Config::builder()
+        .add_source(File::from_str(
+            r#"
+[[host_rules]]
+prefix = "m.blog.naver.com"
+capture_regex = "^([^/]+/[^/]+)"
+
+[[host_rules]]
+prefix = "youtube.com/@"
+capture_regex = "^(youtube\\.com/@[^/?#]+)"
+"#,
+            FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    let rules = HostRule::from_config(&config).unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(
+        find_rule(&rules, "m.blog.naver.com/abc/123")
+            .unwrap()
+            .prefix,
+        "m.blog.naver.com"
+    );
+    assert!(find_rule(&rules, "cafe.naver.com/abc/123").is_none());
+
+    // 설정이 없는 경우 기본 규칙
+    let config = Config::builder().build().unwrap();
+    let rules = HostRule::from_config(&config).unwrap();
+    assert_eq!(rules.len(), DEFAULT_PREFIXES.len());
+
+    // 잘못된 정규식은 해당 패턴을 포함한 에러
+    let config = Config::builder()
+        .add_source(File::from_str(
+            r#"
+[[host_rules]]
+prefix = "instagram.com"
+capture_regex = "^(instagram\\.com/[^/]+"
+"#,
+            FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    let err = HostRule::from_config(&config).unwrap_err().to_string();
+    assert!(err.contains("INVALID_HOST_RULE_REGEX"));
+    assert!(err.contains(r#"^(instagram\.com/[^/]+"#));
+}
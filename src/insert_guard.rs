@@ -0,0 +1,104 @@
+use log::error;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// 통계 구간마다 새로 INSERT하는 seed_id 수를 제한함
+/// <br>
+/// 제한을 넘으면 구간이 끝날 때까지 INSERT하지 않으며, 통계 구간이 바뀔 때 reset으로 다시 셈
+pub struct InsertGuard {
+    /// 구간마다 INSERT할 수 있는 최대 수. 0이면 제한하지 않음
+    limit: u64,
+    inserted: AtomicU64,
+    tripped: AtomicBool,
+    /// 제한을 넘어 INSERT하지 않은 횟수
+    skipped: AtomicU64,
+}
+
+impl InsertGuard {
+    pub const fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            inserted: AtomicU64::new(0),
+            tripped: AtomicBool::new(false),
+            skipped: AtomicU64::new(0),
+        }
+    }
+
+    /// INSERT해도 되는 경우 true. 처음 제한을 넘은 경우 에러 로그를 남김
+    /// <br>
+    /// true인 경우 한 개를 미리 셈. INSERT하지 못한 경우 cancel로 되돌려 실제로 INSERT한 수만 셈
+    pub fn try_insert(&self) -> bool {
+        if self.limit == 0 {
+            return true;
+        }
+        if self.inserted.fetch_add(1, Ordering::Relaxed) < self.limit {
+            return true;
+        }
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        if !self.tripped.swap(true, Ordering::Relaxed) {
+            error!(
+                "SEED_INSERT_LIMIT_TRIPPED: {} new seed_id in this interval, stop inserting until the next interval",
+                self.limit
+            );
+        }
+        false
+    }
+
+    /// try_insert로 센 INSERT를 하지 못한 경우(다른 요청이 먼저 INSERT, DB 에러) 되돌림
+    pub fn cancel(&self) {
+        if self.limit == 0 {
+            return;
+        }
+        let _ = self
+            .inserted
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |inserted| {
+                inserted.checked_sub(1)
+            });
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped.load(Ordering::Relaxed)
+    }
+
+    /// 다음 구간을 시작함. 제한을 넘었던 경우 에러 로그를 남김
+    pub fn reset(&self) {
+        self.inserted.store(0, Ordering::Relaxed);
+        let skipped = self.skipped.swap(0, Ordering::Relaxed);
+        if self.tripped.swap(false, Ordering::Relaxed) {
+            error!(
+                "SEED_INSERT_LIMIT_RESET: {} docs were forwarded without seed_id in the last interval",
+                skipped
+            );
+        }
+    }
+}
+
+#[test]
+fn insert_guard_test() {
+    let guard = InsertGuard::new(2);
+    assert!(guard.try_insert());
+    assert!(guard.try_insert());
+    assert!(!guard.is_tripped());
+    assert!(!guard.try_insert());
+    assert!(!guard.try_insert());
+    assert!(guard.is_tripped());
+
+    // 다음 구간에는 다시 INSERT함
+    guard.reset();
+    assert!(!guard.is_tripped());
+    assert!(guard.try_insert());
+
+    // INSERT하지 못한 경우 되돌리므로 실제로 INSERT한 수만 셈
+    let guard = InsertGuard::new(1);
+    assert!(guard.try_insert());
+    guard.cancel();
+    assert!(guard.try_insert());
+    assert!(!guard.try_insert());
+    guard.reset();
+    guard.cancel();
+    assert!(guard.try_insert());
+
+    // 0이면 제한하지 않음
+    let unlimited = InsertGuard::new(0);
+    assert!((0..1000).all(|_| unlimited.try_insert()));
+    assert!(!unlimited.is_tripped());
+}
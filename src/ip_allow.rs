@@ -0,0 +1,94 @@
+use ipnet::IpNet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// 요청을 허용하는 remote ip 목록. 비어있으면 모든 ip를 허용함
+/// <br>
+/// 겹치는 범위를 합친 뒤 정렬해두고 이진 탐색으로 확인함
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IpAllowList {
+    networks: Vec<IpNet>,
+}
+
+impl IpAllowList {
+    /// "10.0.0.1" 같은 ip 또는 "10.0.0.0/8" 같은 CIDR 목록을 읽음. 잘못된 값인 경우 그 값을 에러로 반환함
+    pub fn parse(values: &[String]) -> Result<Self, String> {
+        let networks = values
+            .iter()
+            .map(|value| {
+                let value = value.trim();
+                value
+                    .parse::<IpNet>()
+                    .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| value.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            networks: IpNet::aggregate(&networks),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        if self.networks.is_empty() {
+            return true;
+        }
+        // IPv6 소켓으로 받은 IPv4 요청은 ::ffff:10.0.0.1 형식이므로 IPv4로 바꿔서 확인함
+        let ip = ip.to_canonical();
+        let index = self.networks.partition_point(|net| net.network() <= ip);
+        index > 0 && self.networks[index - 1].contains(&ip)
+    }
+}
+
+impl fmt::Display for IpAllowList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, net) in self.networks.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", net)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn ip_allow_list_test() {
+    let list_from = |values: &[&str]| {
+        IpAllowList::parse(&values.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+    };
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+    // 비어있으면 모두 허용함
+    let empty = list_from(&[]).unwrap();
+    assert!(empty.is_empty());
+    assert!(empty.allows(ip("203.0.113.7")));
+
+    let list = list_from(&["10.0.0.0/8", "192.168.1.10", "10.1.0.0/16", "2001:db8::/32"]).unwrap();
+    // IPv4 CIDR
+    assert!(list.allows(ip("10.0.0.1")));
+    assert!(list.allows(ip("10.255.255.255")));
+    assert!(!list.allows(ip("11.0.0.0")));
+    assert!(!list.allows(ip("9.255.255.255")));
+    // 하나의 ip
+    assert!(list.allows(ip("192.168.1.10")));
+    assert!(!list.allows(ip("192.168.1.11")));
+    assert!(!list.allows(ip("192.168.1.9")));
+    // IPv6와 IPv4-mapped IPv6
+    assert!(list.allows(ip("2001:db8::1")));
+    assert!(!list.allows(ip("2001:db9::1")));
+    assert!(list.allows(ip("::ffff:10.0.0.1")));
+    assert!(!list.allows(ip("::ffff:203.0.113.7")));
+
+    // 겹치는 범위는 합침
+    assert_eq!(
+        list.to_string(),
+        "10.0.0.0/8, 192.168.1.10/32, 2001:db8::/32"
+    );
+
+    assert_eq!(list_from(&["10.0.0.0/33"]).unwrap_err(), "10.0.0.0/33");
+    assert_eq!(list_from(&["localhost"]).unwrap_err(), "localhost");
+}
@@ -0,0 +1,3646 @@
+mod access_log;
+mod admin;
+mod backend_status;
+pub mod background;
+mod cache_writer;
+pub mod cli;
+mod commit_param;
+mod compress;
+mod concurrency;
+mod content_type;
+mod context;
+mod cors;
+mod date_field;
+mod error_kind;
+mod error_response;
+mod gauge;
+mod get_local_ip;
+mod histogram;
+mod host_rule;
+mod insert_guard;
+mod ip_allow;
+mod listen;
+mod log_roll;
+pub mod media_type;
+mod method_rule;
+#[cfg(test)]
+mod mock;
+mod otel;
+mod panic_policy;
+pub mod proc_xml;
+mod proxy_param;
+mod query;
+mod rate_limit;
+mod reload;
+mod route;
+mod schema_check;
+mod secret;
+pub mod seed_audit;
+mod seed_cache;
+pub mod seed_store;
+mod seed_table;
+mod select_cache;
+mod select_param;
+mod setting_log;
+mod settings;
+mod shared_cache;
+mod shutdown;
+mod single_flight;
+mod slow_log;
+mod solr;
+mod spool;
+mod stats;
+mod status_cnt;
+mod stream_body;
+mod stream_xml;
+mod timing_header;
+mod tls;
+mod util;
+pub mod xml_attr_parser;
+pub mod xml_doc;
+
+use crate::util::StrError;
+use access_log::AccessRecord;
+use cache_writer::CacheWriter;
+use compress::UpstreamCompression;
+use concurrency::ConcurrencyLimit;
+use config::Config;
+use context::{RequestContext, Scheme, X_REQUEST_ID};
+use error_kind::ErrorKind;
+use error_response::ResponseFormat;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use gauge::{ByteBudget, InFlight};
+use histogram::Latency;
+use hyper::body::Bytes;
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use insert_guard::InsertGuard;
+use listen::RouteScope;
+use log::{error, info, warn};
+use method_rule::MethodCheck;
+use proc_xml::{ProcOptions, ProcTiming, WriteOk, WriteReport};
+use proxy_param::ProxyParams;
+use rate_limit::RateLimiter;
+use reload::{ReloadReport, SharedSettings};
+use seed_cache::{LookupFailures, SeedIdCache};
+use select_cache::SelectCache;
+use settings::{ConfigErrors, Settings};
+use shared_cache::RedisSeedCache;
+use shutdown::{Drain, ShutdownReason, ShutdownRequest};
+use single_flight::SingleFlight;
+use slow_log::{SlowRequest, SlowRequestKind};
+use solr::Solr;
+use spool::SpooledBody;
+use sqlx::mysql::MySqlConnectOptions;
+use sqlx::pool::PoolOptions;
+use sqlx::{ConnectOptions, MySqlPool};
+use stats::{ResponseBytes, WorkingCnt};
+use status_cnt::PathClass;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use timing_header::UpdateInfo;
+use tokio::sync::watch;
+use tokio::sync::Mutex;
+use util::ResponseWithError;
+
+type SyncLazy<T> = once_cell::sync::Lazy<T>;
+pub type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// seed_id 필드명. field_seed_id 설정이 없는 경우 사용
+const COL_SEED_ID: &str = "seed_id";
+
+/// url 필드명. field_url, seed_url_fields 설정이 없는 경우 사용
+const COL_URL: &str = "url";
+
+/// id 필드명
+const COL_ID: &[u8] = b"id";
+
+/// tstamp 필드명
+const COL_TSTAMP: &[u8] = b"tstamp";
+
+/// postdate 필드명
+const COL_POSTDATE: &[u8] = b"postdate";
+
+/// host 필드명
+const COL_HOST: &[u8] = b"host";
+
+/// site 필드명
+const COL_SITE: &[u8] = b"site";
+
+/// config 파일과 환경변수에서 config를 읽음
+fn read_config() -> Result<Config, config::ConfigError> {
+    Config::builder()
+        // config 파일이 없어도 기본값으로 동작함. DB, 솔라 주소처럼 기본값이 없는 값은 run에서 시작할 때 확인함
+        .add_source(config::File::with_name("config").required(false))
+        // SOLR_PROXY_DB_PWD 처럼 환경변수로 config 파일의 값을 덮어쓸 수 있음
+        .add_source(config::Environment::with_prefix(secret::ENV_PREFIX).prefix_separator("_"))
+        .build()
+}
+
+/// config와 설정값 전역변수. reload로 바뀔 수 있으므로 config(), settings()로 읽음
+static SHARED_SETTINGS: SyncLazy<SharedSettings> = SyncLazy::new(|| {
+    let config = read_config().expect("CONFIG_READ_FAIL");
+    let settings = Settings::from_config(&config).expect("FAIL_GET_CONFIG: settings");
+    SharedSettings::new(config, settings)
+});
+
+/// 현재 config
+fn config() -> Arc<Config> {
+    SHARED_SETTINGS.config()
+}
+
+/// 현재 설정값. 한 요청 안에서는 같은 값을 사용하도록 처음 읽은 값을 계속 사용함
+pub(crate) fn settings() -> Arc<Settings> {
+    SHARED_SETTINGS.settings()
+}
+
+/// update 요청 동시 처리 제한 전역변수
+static UPDATE_LIMIT: SyncLazy<ConcurrencyLimit> = SyncLazy::new(|| {
+    ConcurrencyLimit::new(
+        "UPDATE",
+        settings().max_concurrent_updates,
+        settings().update_queue_wait,
+    )
+});
+
+/// select 요청 동시 처리 제한 전역변수
+static SELECT_LIMIT: SyncLazy<ConcurrencyLimit> = SyncLazy::new(|| {
+    ConcurrencyLimit::new(
+        "SELECT",
+        settings().max_concurrent_selects,
+        settings().select_queue_wait,
+    )
+});
+
+/// remote ip별 요청 제한 전역변수
+static RATE_LIMITER: SyncLazy<RateLimiter> = SyncLazy::new(|| {
+    RateLimiter::new(
+        settings().rate_limit_per_ip_rps,
+        settings().rate_limit_burst,
+        settings().rate_limit_exempt_ips.clone(),
+    )
+});
+
+/// 서버 중단 요청에 대한 Sender. 모든 listener의 서버가 같은 값을 기다림
+/// <br>
+/// panic 발생시 이를 통해 서버 중단을 요청
+static STOP_SERVER_SENDER: SyncLazy<Mutex<Option<watch::Sender<Option<ShutdownReason>>>>> =
+    SyncLazy::new(|| Mutex::new(None));
+
+/// 처리중인 select, update 요청 수와 그 중 솔라, DB 응답을 기다리는 수
+static IN_FLIGHT: InFlight = InFlight::new();
+
+/// 처리중인 update 요청이 메모리에 들고 있는 body bytes의 합
+static BUFFERED_BYTES: ByteBudget = ByteBudget::new();
+
+/// 클라이언트에 보낸 select, update 응답 body 크기
+static RESPONSE_BYTES: ResponseBytes = ResponseBytes::new();
+
+/// select, update, DB, 솔라 소요 시간의 통계 구간별 분포
+static LATENCY: Latency = Latency::new();
+
+/// 종료 요청 여부와 처리중인 요청 수
+static DRAIN: SyncLazy<Drain> = SyncLazy::new(Drain::new);
+
+/// signal로 받은 프로세스 종료 요청. 다시 시작하기 전 기다리는 중에 받은 경우에도 다시 시작하지 않음
+static SHUTDOWN_REQUEST: SyncLazy<ShutdownRequest> = SyncLazy::new(ShutdownRequest::new);
+
+/// seed_id 캐시 전역변수
+static SEED_ID_CACHE: SyncLazy<SeedIdCache> = SyncLazy::new(|| {
+    SeedIdCache::new(
+        settings().seed_id_cache_capacity,
+        settings().seed_id_cache_shards,
+    )
+});
+
+/// DB에서 조회한 seed_id를 요청과 별개로 SEED_ID_CACHE에 넣는 writer
+static SEED_CACHE_WRITER: SyncLazy<CacheWriter> =
+    SyncLazy::new(|| CacheWriter::new(&SEED_ID_CACHE));
+
+/// 통계 구간마다 새로 INSERT하는 seed_id 수 제한
+static INSERT_GUARD: SyncLazy<InsertGuard> =
+    SyncLazy::new(|| InsertGuard::new(settings().seed_insert_limit()));
+
+/// 여러 proxy가 함께 사용하는 seed_id cache. redis_url이 없으면 None
+static SHARED_SEED_CACHE: SyncLazy<Option<RedisSeedCache>> = SyncLazy::new(|| {
+    let settings = settings();
+    if settings.redis_url.is_empty() {
+        return None;
+    }
+    // 주소는 설정을 읽을 때 확인함
+    let cache = RedisSeedCache::new(
+        &settings.redis_url,
+        settings.redis_ttl,
+        settings.redis_timeout,
+    )
+    .expect("INVALID_CONFIG: redis_url");
+    Some(cache)
+});
+
+/// 여러 요청이 동시에 DB에서 조회하는 seed_host. collection.cache_key로 구분함
+static SEED_LOOKUP_IN_FLIGHT: SyncLazy<SingleFlight<Option<String>>> =
+    SyncLazy::new(SingleFlight::new);
+
+/// 최근 DB 조회에 실패한 seed_host. 컬렉션의 cache_key로 기록함
+static SEED_LOOKUP_FAILURES: SyncLazy<LookupFailures> =
+    SyncLazy::new(|| LookupFailures::new(settings().seed_lookup_failure_ttl));
+
+/// select 응답 cache 전역변수
+static SELECT_CACHE: SyncLazy<SelectCache> =
+    SyncLazy::new(|| SelectCache::new(settings().select_cache_config()));
+
+/// solr 전역변수
+static SOLR: SyncLazy<Solr> = SyncLazy::new(|| {
+    let solr_url = config()
+        .get_string("solr_kr")
+        .expect("FAIL_GET_CONFIG: solr_kr");
+    let (upstream_headers, auth) =
+        solr::upstream_headers(&config(), |name| std::env::var(name).ok())
+            .expect("FAIL_GET_CONFIG: solr_user");
+    match auth {
+        Some(auth) => info!("solr client init. solr_kr: {}, {}", solr_url, auth),
+        None => info!("solr client init. solr_kr: {}", solr_url),
+    }
+    Solr::new(
+        solr_url,
+        settings().preserve_host,
+        settings().solr_client_config(),
+    )
+    .with_upstream_headers(upstream_headers)
+    .with_update_drain(settings().drain_updates)
+    .with_hedge(settings().hedge_config())
+});
+
+/// DB 연결 전역변수
+static CON: SyncLazy<MySqlPool> = SyncLazy::new(|| {
+    let config = config();
+    let settings = settings();
+    let db_host = config
+        .get_string("db_host")
+        .expect("FAIL_GET_CONFIG: db_host");
+    let db_user = config
+        .get_string("db_user")
+        .expect("FAIL_GET_CONFIG: db_user");
+    let (db_pwd, db_pwd_source) =
+        secret::get_secret(&config, "db_pwd", |name| std::env::var(name).ok())
+            .expect("FAIL_GET_CONFIG: db_pwd");
+    let db_schema = config
+        .get_string("db_schema")
+        .expect("FAIL_GET_CONFIG: db_schema");
+
+    info!(
+        "{}",
+        db_init_message(&db_host, &db_user, &db_pwd, &db_pwd_source, &db_schema)
+    );
+
+    let conn = MySqlConnectOptions::new()
+        .host(&db_host)
+        .username(&db_user)
+        .password(&db_pwd)
+        .database(&db_schema)
+        .statement_cache_capacity(settings.db_statement_cache)
+        .log_statements(log::LevelFilter::Debug)
+        .log_slow_statements(log::LevelFilter::Info, Duration::from_secs(5));
+
+    PoolOptions::new()
+        .idle_timeout(settings.db_idle_timeout)
+        .max_lifetime(settings.db_max_lifetime)
+        .min_connections(settings.db_min_connections)
+        .max_connections(settings.db_max_connections)
+        .acquire_timeout(settings.db_acquire_timeout)
+        .connect_lazy_with(conn)
+});
+
+/// DB 연결 설정 log. 비밀번호는 가리고 읽어온 곳만 남김
+fn db_init_message(
+    host: &str,
+    user: &str,
+    pwd: &str,
+    pwd_source: &secret::SecretSource,
+    schema: &str,
+) -> String {
+    format!(
+        "DB INIT: host: {}, user: {}, pwd: {} (from {}), schema: {}",
+        host,
+        user,
+        secret::mask(pwd),
+        pwd_source,
+        schema
+    )
+}
+
+/// 통계 endpoint path. 솔라로 전달하지 않고 proxy에서 응답함
+const STATS_PATH: &str = "/proxy/stats";
+
+/// 클라이언트 연결에 사용하는 인증서. tls_cert_path, tls_key_path가 없으면 비어있으며 HTTP로 받음
+static TLS: once_cell::sync::OnceCell<tls::TlsConfig> = once_cell::sync::OnceCell::new();
+
+/// 작업횟수 카운트 전역변수
+static WORKING_CNT: SyncLazy<Mutex<WorkingCnt>> = SyncLazy::new(|| Mutex::new(WorkingCnt::new()));
+
+/// 서버 실행. 서버가 중단될 때까지 반환하지 않음
+pub async fn run() {
+    // 잘못된 config를 첫 요청이 아닌 시작할 때 모두 보여주고 종료함
+    if let Err(errors) = settings::validate_config(&config(), |name| std::env::var(name).ok()) {
+        eprintln!("CONFIG_INVALID:\n{}", errors);
+        std::process::exit(1);
+    }
+
+    setting_log::setup_logger(settings().log_roll_config()).expect("Setup Logger Failed");
+    if settings().log_level != setting_log::log_level() {
+        setting_log::set_log_level(settings().log_level).expect("Setup Logger Failed");
+    }
+    info!("server starting...");
+    SyncLazy::force(&SOLR);
+    if settings().solr_http2 {
+        // 솔라가 HTTP/2를 지원하지 않으면 모든 요청이 실패하므로 시작하지 않음
+        match SOLR.probe(Duration::from_secs(10)).await {
+            Ok(status) => info!("solr http2: probe {}", status),
+            Err(e) => {
+                error!("SOLR_HTTP2_PROBE_FAIL: {}. set solr_http2 = false if solr does not support h2c", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if settings().verify_schema {
+        let cores = schema_check::expected_fields(&settings());
+        let ok = schema_check::verify(&SOLR, &cores, settings().verify_schema_timeout).await;
+        if !ok && settings().verify_schema_action == schema_check::SchemaCheckAction::Abort {
+            error!("SCHEMA_CHECK_FAIL: set verify_schema_action = \"warn\" to start anyway");
+            std::process::exit(1);
+        }
+    }
+    SyncLazy::force(&CON);
+    if settings().verify_seed_table || settings().create_seed_table {
+        let tables = seed_table::seed_tables(&settings());
+        let db = seed_table::MySqlSeedTableDb;
+        if !seed_table::verify(&db, &tables, settings().create_seed_table).await {
+            error!("SEED_TABLE_CHECK_FAIL: server not started");
+            std::process::exit(1);
+        }
+    }
+
+    let mut effective = Settings::clone(&settings());
+    effective.admin_secret = secret::mask(&effective.admin_secret).to_string();
+    effective.redis_url = secret::mask(&effective.redis_url).to_string();
+    info!("settings: {:?}", effective);
+    info!(
+        "DB POOL: max: {}, min: {}, idle timeout: {}s, max lifetime: {}s, acquire timeout: {}s, statement cache: {}",
+        settings().db_max_connections,
+        settings().db_min_connections,
+        settings().db_idle_timeout.as_secs(),
+        settings().db_max_lifetime.as_secs(),
+        settings().db_acquire_timeout.as_secs(),
+        settings().db_statement_cache
+    );
+    if settings().log_roll != log_roll::LogRoll::Size {
+        info!(
+            "log roll: {:?}, keep {} files",
+            settings().log_roll,
+            settings().log_keep_count
+        );
+    }
+    if settings().access_log {
+        setting_log::enable_access_log().expect("Setup Access Log Failed");
+        info!("access log: log/access.log");
+    }
+
+    info!(
+        "update limits: max_body_bytes: {}, max_docs_per_update: {}, max_fields_per_doc: {}",
+        settings().max_body_bytes,
+        settings().max_docs_per_update,
+        settings().max_fields_per_doc
+    );
+    info!(
+        "concurrency limits: update: {} (wait {}ms), select: {} (wait {}ms)",
+        settings().max_concurrent_updates,
+        settings().update_queue_wait.as_millis(),
+        settings().max_concurrent_selects,
+        settings().select_queue_wait.as_millis()
+    );
+    info!(
+        "host rules: {:?}",
+        settings()
+            .host_rules
+            .iter()
+            .map(|rule| rule.prefix.as_str())
+            .collect::<Vec<_>>()
+    );
+    info!(
+        "seed url fields: {:?}, seed_id field: {}",
+        settings().seed_url_fields,
+        settings().field_seed_id.as_str()
+    );
+    if settings().fill_host_fields {
+        info!("fill missing host/site fields from url");
+    }
+    if settings().normalize_dates {
+        info!("fill missing tstamp and normalize postdate");
+    }
+    if settings().sanitize_xml {
+        info!("remove xml-invalid characters from field text");
+    }
+    if settings().dedup_docs_by_id {
+        info!("remove duplicated docs by id");
+    }
+    if settings().stream_updates {
+        info!("stream update body doc by doc");
+    }
+    if !settings().force_commit_within.is_zero() {
+        info!(
+            "force commitWithin: {}ms, allow client commit: {}",
+            settings().force_commit_within.as_millis(),
+            settings().allow_client_commit
+        );
+    }
+    if settings().spool_threshold_bytes > 0 {
+        info!(
+            "spool update body over {} bytes to {}",
+            settings().spool_threshold_bytes,
+            settings().spool_dir().display()
+        );
+    }
+    if !settings().required_fields.is_empty() {
+        info!(
+            "required fields: {:?}, action: {:?}",
+            settings().required_fields,
+            settings().required_fields_action
+        );
+    }
+    if settings().dedup_single_valued_fields {
+        info!(
+            "single valued fields: {:?}",
+            settings().single_valued_fields
+        );
+    }
+    info!("seed table: {}", settings().seed_table);
+    for collection in settings().collections.values() {
+        info!(
+            "collection {}: seed table: {}, cache namespace: {}, required fields: {:?}",
+            collection.name,
+            collection.seed_table,
+            collection.cache_namespace,
+            collection.required_fields
+        );
+    }
+    if settings().passthrough_unknown_paths {
+        info!(
+            "passthrough unknown paths, deny prefixes: {:?}",
+            settings().passthrough_deny_prefixes
+        );
+    }
+    if settings().compress_upstream != UpstreamCompression::None {
+        info!(
+            "upstream compression: {:?}, min bytes: {}",
+            settings().compress_upstream,
+            settings().compress_upstream_min_bytes
+        );
+    }
+    if !settings().slow_select.is_zero() || !settings().slow_update.is_zero() {
+        info!(
+            "slow request log: select {}ms, update {}ms",
+            settings().slow_select.as_millis(),
+            settings().slow_update.as_millis()
+        );
+    }
+    if settings().restart_on_panic {
+        info!(
+            "restart on panic: max {} times, backoff {}s",
+            settings().max_restarts,
+            settings().restart_backoff.as_secs()
+        );
+    }
+    if settings().solr_pool_max_idle_per_host != 0
+        || settings().solr_pool_idle_timeout != Duration::from_secs(90)
+        || !settings().solr_tcp_keepalive.is_zero()
+    {
+        info!(
+            "solr pool: max idle per host: {}, idle timeout: {}s, tcp keepalive: {}s",
+            settings().solr_pool_max_idle_per_host,
+            settings().solr_pool_idle_timeout.as_secs(),
+            settings().solr_tcp_keepalive.as_secs()
+        );
+    }
+    if !settings().solr_request_timeout.is_zero() {
+        info!(
+            "solr request timeout: {}ms",
+            settings().solr_request_timeout.as_millis()
+        );
+    }
+    if settings().panic_policy != panic_policy::PanicPolicy::Request {
+        info!("panic policy: {:?}", settings().panic_policy);
+    }
+    if settings().stats_interval != Duration::from_secs(60) || !settings().stats_reset {
+        info!(
+            "stats interval: {}s, reset: {}",
+            settings().stats_interval.as_secs(),
+            settings().stats_reset
+        );
+    }
+    if RATE_LIMITER.is_enabled() {
+        info!(
+            "rate limit per ip: {} rps, burst {}, exempt {:?}",
+            settings().rate_limit_per_ip_rps,
+            settings().rate_limit_burst,
+            settings().rate_limit_exempt_ips
+        );
+    }
+    for (name, allowed_ips) in [
+        ("update", &settings().update_allowed_ips),
+        ("select", &settings().select_allowed_ips),
+    ] {
+        if !allowed_ips.is_empty() {
+            info!("{} allowed ips: {}", name, allowed_ips);
+        }
+    }
+    if SHARED_SEED_CACHE.is_some() {
+        info!(
+            "shared seed_id cache: redis, ttl: {}s, timeout: {}ms",
+            settings().redis_ttl.as_secs(),
+            settings().redis_timeout.as_millis()
+        );
+    }
+
+    if !settings().otel_endpoint.is_empty() {
+        // trace를 보내지 못해도 요청 처리에는 영향이 없으므로 경고만 남김
+        match otel::init(&settings().otel_endpoint) {
+            Ok(()) => info!("otel trace: {}", settings().otel_endpoint),
+            Err(e) => warn!("OTEL_INIT_FAIL: {}", e),
+        }
+    }
+    if settings().tls_enabled() {
+        // 인증서를 읽을 수 없으면 HTTP로 받지 않고 시작하지 않음
+        match tls::TlsConfig::load(&settings().tls_cert_path, &settings().tls_key_path) {
+            Ok(tls) => {
+                info!("tls: {}", tls.cert_path());
+                let _ = TLS.set(tls);
+            }
+            Err(e) => {
+                error!("{}", e);
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // auto:<port>로 설정한 listener가 있는 경우에만 ip를 찾음
+    let listen = settings().listeners();
+    let my_local_ip = listen.iter().any(|listen| listen.is_auto()).then(|| {
+        get_local_ip::get_local_ip(&settings().bind_interface, settings().prefer_ipv6)
+            .unwrap_or_else(|e| {
+                error!("get_local_ip FAIL: {}", e);
+                eprintln!("get_local_ip FAIL: {}", e);
+                std::process::exit(1);
+            })
+    });
+    if let Some(my_local_ip) = my_local_ip {
+        info!("my IP address: {}", my_local_ip);
+    }
+
+    // signal을 기다리는 작업은 프로세스가 끝날 때까지 끝나지 않으므로 spawn_tracked로 실행하지 않음
+    tokio::spawn(reload::reload_on_hangup(|| {
+        let _ = reload_config();
+    }));
+    // 다시 시작하는 중에 받은 signal도 처리하도록 계속 기다림. 위와 같이 끝나지 않으므로 spawn_tracked로 실행하지 않음
+    tokio::spawn(async {
+        loop {
+            shutdown::wait_signal().await;
+            info!("shutdown requested");
+            SHUTDOWN_REQUEST.request();
+            request_server_shutdown(ShutdownReason::Signal).await;
+        }
+    });
+
+    let restart_policy = settings().restart_policy();
+    let mut restart_cnt = 0;
+    while !SHUTDOWN_REQUEST.is_requested() {
+        let reason = match listen::bind_all(&listen, my_local_ip).await {
+            Ok(bound) => serve(bound).await,
+            // 시작할 때 bind하지 못하면 종료하고, 다시 시작하는 중이면 restart_policy를 따름
+            Err(e) if restart_cnt == 0 => {
+                error!("{}", e);
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                error!("{}", e);
+                ShutdownReason::ServerError
+            }
+        };
+        let Some(delay) = restart_policy.restart_delay(reason, restart_cnt) else {
+            break;
+        };
+        restart_cnt += 1;
+        warn!(
+            "SERVER_RESTART {}/{}: stopped by {:?}, restarting in {}s",
+            restart_cnt,
+            restart_policy.max_restarts,
+            reason,
+            delay.as_secs()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = SHUTDOWN_REQUEST.wait() => {
+                info!("shutdown requested while waiting to restart");
+                break;
+            }
+        }
+        reset_for_restart().await;
+    }
+    otel::shutdown().await;
+    info!("server shutdown.");
+}
+
+/// 다시 시작하기 전 cache, 카운터 등 다시 만들 수 있는 상태를 초기화함
+async fn reset_for_restart() {
+    // shutdown_grace가 지나 writer task를 기다리지 않은 경우 비운 뒤에 이전 값을 넣지 않도록 먼저 기다림
+    SEED_CACHE_WRITER.drain().await;
+    SEED_ID_CACHE.clear().await;
+    *WORKING_CNT.lock().await = WorkingCnt::new();
+    DRAIN.reset();
+}
+
+/// 종료 요청을 받을 때까지 bind한 모든 listener로 서버를 실행함
+async fn serve(bound: Vec<listen::BoundListener>) -> ShutdownReason {
+    let (send, recv) = watch::channel::<Option<ShutdownReason>>(None);
+    *STOP_SERVER_SENDER.lock().await = Some(send);
+    // bind하는 동안 받은 종료 요청은 보낼 서버가 없었으므로 여기서 보냄
+    if SHUTDOWN_REQUEST.is_requested() {
+        request_server_shutdown(ShutdownReason::Signal).await;
+    }
+
+    // Then bind and serve...
+    // 하나라도 에러로 종료되면 나머지 서버도 graceful shutdown하고 모두 끝날 때까지 기다림
+    let listeners = bound
+        .into_iter()
+        .map(|bound| {
+            let routes = match (bound.scope.admin_routes, bound.scope.proxy_routes) {
+                (true, true) => "",
+                (false, _) => " (admin routes disabled)",
+                (true, false) => " (admin routes only)",
+            };
+            info!("listen: {}{}", bound.addr, routes);
+            serve_listener(bound, recv.clone(), TLS.get(), &SOLR)
+        })
+        .collect();
+    let graceful = join_listeners(listeners, || async {
+        request_server_shutdown(ShutdownReason::ServerError).await;
+    });
+    let (stats_stop_send, stats_stop_recv) = tokio::sync::oneshot::channel::<()>();
+    let stats_task = tokio::spawn(stats::stats_loop(
+        &WORKING_CNT,
+        &INSERT_GUARD,
+        &RESPONSE_BYTES,
+        settings().stats_interval,
+        settings().stats_reset,
+        stats_stop_recv,
+        stats::log_stats,
+        stats::log_final_stats,
+    ));
+
+    SEED_CACHE_WRITER.start();
+    info!("server start.");
+
+    // And run forever...
+    // 종료 요청 후 shutdown_grace 안에 처리중인 요청이 끝나지 않으면 기다리지 않고 종료함
+    let grace_expired = async {
+        DRAIN.wait_started().await;
+        tokio::time::sleep(settings().shutdown_grace).await;
+    };
+    let mut reason = None;
+    tokio::select! {
+        result = graceful => {
+            if let Err(e) = result {
+                error!("server error: {}", e);
+                reason = Some(ShutdownReason::ServerError);
+            }
+        }
+        _ = grace_expired => {
+            warn!(
+                "SHUTDOWN_GRACE_EXPIRED: {}s, {} in-flight requests abandoned",
+                settings().shutdown_grace.as_secs(),
+                DRAIN.in_flight()
+            );
+        }
+    }
+
+    // 응답 후 실행중인 작업(spawn_tracked)도 shutdown_grace 동안 끝나기를 기다림
+    // cache writer는 channel을 닫아야 남은 값을 넣고 끝남
+    SEED_CACHE_WRITER.close();
+    if let Err(pending) = background::wait_tracked(settings().shutdown_grace).await {
+        warn!(
+            "SHUTDOWN_GRACE_EXPIRED: {}s, {} background tasks abandoned",
+            settings().shutdown_grace.as_secs(),
+            pending
+        );
+    }
+
+    // 더 이상 요청을 받지 않으므로 마지막 구간과 누적 통계를 남긴 뒤 종료함
+    let _ = stats_stop_send.send(());
+    let _ = stats_task.await;
+
+    reason
+        .or_else(|| DRAIN.reason())
+        .unwrap_or(ShutdownReason::Signal)
+}
+
+/// listener를 모두 실행하고 모두 끝날 때까지 기다림. 처음 에러로 끝난 listener의 에러를 반환함
+/// <br>
+/// 하나가 에러로 끝나면 stop으로 나머지 listener에 종료를 요청함. 나머지는 drop하지 않으므로 처리 중인 요청을 마친 뒤 끝남
+async fn join_listeners<F, E, S, SFut>(listeners: Vec<F>, stop: S) -> Result<(), E>
+where
+    F: std::future::Future<Output = Result<(), E>>,
+    E: std::fmt::Display,
+    S: FnOnce() -> SFut,
+    SFut: std::future::Future<Output = ()>,
+{
+    let mut listeners: FuturesUnordered<F> = listeners.into_iter().collect();
+    let mut stop = Some(stop);
+    let mut first_err = None;
+    while let Some(result) = listeners.next().await {
+        let Err(e) = result else {
+            continue;
+        };
+        match first_err {
+            None => {
+                if let Some(stop) = stop.take() {
+                    stop().await;
+                }
+                first_err = Some(e);
+            }
+            Some(_) => error!("server error: {}", e),
+        }
+    }
+    first_err.map_or(Ok(()), Err)
+}
+
+/// listener 하나의 hyper 서버. 종료 요청을 받으면 graceful shutdown함
+/// <br>
+/// TLS를 사용하는 경우 listener에서 받은 연결의 handshake를 마친 뒤 hyper로 넘기고, 요청에 Scheme::Https를 넣음
+fn serve_listener(
+    bound: listen::BoundListener,
+    mut stop: watch::Receiver<Option<ShutdownReason>>,
+    tls: Option<&'static tls::TlsConfig>,
+    solr: &'static Solr,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = hyper::Result<()>> + Send>> {
+    let shutdown_signal = async move {
+        // Sender가 먼저 drop된 경우에도 종료함
+        let reason = match stop.wait_for(|reason| reason.is_some()).await {
+            Ok(reason) => reason.unwrap_or(ShutdownReason::Signal),
+            Err(_) => ShutdownReason::Signal,
+        };
+        DRAIN.start(reason);
+    };
+    let scope = bound.scope;
+
+    match tls {
+        Some(tls) => {
+            let make_service = make_service_fn(move |c: &tls::TlsStream| {
+                let remote_ip = tls::remote_addr(c);
+                let service = service_fn(move |mut req: Request<Body>| {
+                    req.extensions_mut().insert(scope);
+                    req.extensions_mut().insert(Scheme::Https);
+                    handle(req, remote_ip, solr)
+                });
+                async move { Ok::<_, BoxedError>(service) }
+            });
+            Box::pin(
+                Server::builder(tls::incoming(bound.listener, tls))
+                    .serve(make_service)
+                    .with_graceful_shutdown(shutdown_signal),
+            )
+        }
+        None => {
+            // A `MakeService` that produces a `Service` to handle each connection.
+            let make_service = make_service_fn(move |c: &AddrStream| {
+                let remote_ip = c.remote_addr();
+
+                // Create a `Service` for responding to the request.
+                let service = service_fn(move |mut req: Request<Body>| {
+                    req.extensions_mut().insert(scope);
+                    handle(req, remote_ip, solr)
+                });
+
+                // Return the service to hyper.
+                async move { Ok::<_, BoxedError>(service) }
+            });
+            let incoming = match AddrIncoming::from_listener(bound.listener) {
+                Ok(incoming) => incoming,
+                Err(e) => return Box::pin(std::future::ready(Err(e))),
+            };
+            Box::pin(
+                Server::builder(incoming)
+                    .serve(make_service)
+                    .with_graceful_shutdown(shutdown_signal),
+            )
+        }
+    }
+}
+
+/// config 파일을 다시 읽고 실행 중 바꿀 수 있는 설정을 적용함. SIGHUP, POST /proxy/reload에서 사용함
+/// <br>
+/// 잘못된 값이 있으면 이전 설정을 그대로 사용함. 인증서는 설정과 별개로 항상 다시 읽음
+pub(crate) fn reload_config() -> Result<ReloadReport, ConfigErrors> {
+    // 갱신한 인증서 파일을 다시 읽음. 읽을 수 없으면 이전 인증서를 계속 사용함
+    // 인증서 경로는 재시작해야 바뀌므로 config에 잘못된 값이 있어도 다시 읽음
+    reload_tls_cert();
+    let result = read_config()
+        .map_err(|e| ConfigErrors(vec![e]))
+        .and_then(|config| SHARED_SETTINGS.reload(config, |name| std::env::var(name).ok()));
+    let report = match result {
+        Ok(report) => report,
+        Err(errors) => {
+            warn!(
+                "CONFIG_RELOAD_FAIL: {}",
+                errors.to_string().replace('\n', "; ")
+            );
+            return Err(errors);
+        }
+    };
+
+    let new = &report.new;
+    RATE_LIMITER.set_limits(
+        new.rate_limit_per_ip_rps,
+        new.rate_limit_burst,
+        new.rate_limit_exempt_ips.clone(),
+    );
+    // PUT /proxy/loglevel로 바꾼 level은 config의 log_level이 바뀐 경우에만 덮어씀
+    if new.log_level != report.old.log_level {
+        if let Err(e) = setting_log::set_log_level(new.log_level) {
+            warn!("CONFIG_RELOAD: log level change failed. {}", e);
+        }
+    }
+    for key in &report.requires_restart {
+        warn!(
+            "CONFIG_REQUIRES_RESTART: {} changed but is not applied",
+            key
+        );
+    }
+    info!("CONFIG_RELOADED");
+    Ok(report)
+}
+
+/// TLS를 사용하는 경우 인증서 파일을 다시 읽음
+fn reload_tls_cert() {
+    if let Some(tls) = TLS.get() {
+        match tls.reload() {
+            Ok(()) => info!("TLS_CERT_RELOADED: {}", tls.cert_path()),
+            Err(e) => warn!("TLS_CERT_RELOAD_FAIL: {}. keep the previous certificate", e),
+        }
+    }
+}
+
+/// graceful shutdown을 요청함. 이미 요청된 경우 false
+pub(crate) async fn request_server_shutdown(reason: ShutdownReason) -> bool {
+    let sender = STOP_SERVER_SENDER.lock().await.take();
+    match sender {
+        Some(sender) => sender.send(Some(reason)).is_ok(),
+        None => false,
+    }
+}
+
+async fn handle(
+    req: Request<Body>,
+    remote_ip: SocketAddr,
+    solr: &Solr,
+) -> Result<Response<Body>, String> {
+    handle_with(req, remote_ip, solr, settings()).await
+}
+
+/// 요청을 처음 받을 때 읽은 settings로 끝까지 처리함. 처리 중 reload되어도 요청 안에서는 설정이 바뀌지 않음
+async fn handle_with(
+    req: Request<Body>,
+    remote_ip: SocketAddr,
+    solr: &Solr,
+    settings: Arc<Settings>,
+) -> Result<Response<Body>, String> {
+    handle_with_limiter(req, remote_ip, solr, settings, &RATE_LIMITER).await
+}
+
+/// handle_with와 같지만 주어진 limiter로 요청 수를 제한함. 테스트에서는 다른 테스트와 limiter를 함께 쓰지 않도록 사용함
+async fn handle_with_limiter(
+    req: Request<Body>,
+    remote_ip: SocketAddr,
+    solr: &Solr,
+    settings: Arc<Settings>,
+    limiter: &RateLimiter,
+) -> Result<Response<Body>, String> {
+    let scheme = req
+        .extensions()
+        .get::<Scheme>()
+        .copied()
+        .unwrap_or_default();
+    let ctx = RequestContext::new(req.headers(), remote_ip).with_scheme(scheme);
+    let span = otel::Span::start_request(req.headers());
+    span.set_str("http.request.method", req.method().as_str());
+    span.set_str("url.path", req.uri().path());
+    span.set_str("solr_proxy.request_id", &ctx.request_id);
+    // proxy에서 에러 응답을 만드는 경우 사용할 형식
+    let format = ResponseFormat::from_request(req.uri(), req.headers());
+    let path_class = path_class(req.uri().path().trim(), &settings);
+    // 브라우저에서 보낸 요청인 경우 에러 응답에도 CORS header를 넣음
+    let cors = settings.cors_config().check(
+        req.uri().path(),
+        req.method(),
+        req.headers(),
+        settings.update_methods(),
+    );
+
+    // 종료 중에 들어온 요청은 로드 밸런서가 다른 서버로 넘길 수 있도록 503으로 응답함
+    let in_flight = DRAIN.enter();
+    let mut response = if in_flight.is_none() {
+        let mut shutting_down_response = error_response::error_response(
+            hyper::StatusCode::SERVICE_UNAVAILABLE,
+            "SHUTTING_DOWN",
+            format,
+        );
+        let headers = shutting_down_response.headers_mut();
+        headers.insert(hyper::header::RETRY_AFTER, settings.retry_after_secs.into());
+        headers.insert(
+            hyper::header::CONNECTION,
+            hyper::header::HeaderValue::from_static("close"),
+        );
+        WORKING_CNT
+            .lock()
+            .await
+            .status_cnt
+            .record(path_class, hyper::StatusCode::SERVICE_UNAVAILABLE);
+        shutting_down_response
+    } else if !limiter.check(remote_ip.ip(), Instant::now()).await {
+        {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.rate_limited_cnt += 1;
+            cnt_lock
+                .status_cnt
+                .record(path_class, hyper::StatusCode::TOO_MANY_REQUESTS);
+        }
+        let err_msg = format!(
+            "RATE_LIMITED: {}, {} rps",
+            remote_ip.ip(),
+            settings.rate_limit_per_ip_rps
+        );
+        error_response::error_response(hyper::StatusCode::TOO_MANY_REQUESTS, &err_msg, format)
+    } else {
+        let worker = span.instrument(handle_worker(req, &ctx, solr, &settings));
+        match panic_policy::catch_request_panic(settings.panic_policy, worker).await {
+            Ok(result) => result,
+            Err(e) => {
+                // 응답을 만들기 전에 분류함. ResponseWithError는 downcast하면 원인을 알 수 없음
+                let kind = ErrorKind::of(&e);
+                let err_str = e.to_string();
+                let status = util::response_status(&e);
+                let is_response = e.is::<ResponseWithError>();
+                {
+                    let mut cnt_lock = WORKING_CNT.lock().await;
+                    cnt_lock.err_cnt += 1;
+                    cnt_lock.error_kind_cnt.record(kind);
+                    // 응답을 담은 에러는 응답을 만든 곳에서 이미 셈
+                    if !is_response {
+                        cnt_lock.status_cnt.record(path_class, status);
+                    }
+                }
+
+                // 에러가 발생했어도 가능한 경우 정상적인 Response를 돌려줌
+                if let Ok(error_response) = e.downcast::<ResponseWithError>() {
+                    warn!("[{}] {}", ctx.request_id, err_str);
+                    warn!("[{}] request from: {}", ctx.request_id, remote_ip);
+                    warn!("");
+                    error_response.response
+                } else {
+                    // 정상적인 Response가 불가능한 경우 솔라와 같은 형식으로 에러 응답을 만듦
+                    warn!("[{}] FAIL_RESPONSE... {}", ctx.request_id, err_str);
+                    warn!("[{}] request from: {}", ctx.request_id, remote_ip);
+                    warn!("");
+                    let mut internal_error_response =
+                        error_response::error_response(status, &err_str, format);
+                    if status == hyper::StatusCode::SERVICE_UNAVAILABLE {
+                        internal_error_response
+                            .headers_mut()
+                            .insert(hyper::header::RETRY_AFTER, settings.retry_after_secs.into());
+                    }
+                    internal_error_response
+                }
+            }
+        }
+    };
+
+    if let Some(cors) = cors {
+        cors.add_headers(response.headers_mut());
+    }
+    // 클라이언트가 요청을 추적할 수 있도록 request id를 돌려줌
+    if let Ok(request_id) = hyper::header::HeaderValue::from_str(&ctx.request_id) {
+        response.headers_mut().insert(X_REQUEST_ID, request_id);
+    }
+    span.set_u64(
+        "http.response.status_code",
+        response.status().as_u16() as u64,
+    );
+    Ok(response)
+}
+
+/// status_cnt에 사용할 path 분류. handle_worker에서 path를 구분하는 방식과 같음
+fn path_class(path: &str, settings: &Settings) -> PathClass {
+    if path == STATS_PATH || admin::is_admin_path(path) {
+        PathClass::Proxy
+    } else if path.ends_with("/select") {
+        PathClass::Select
+    } else if path.ends_with("/update") {
+        PathClass::Update
+    } else if settings.passthrough_unknown_paths {
+        PathClass::Passthrough
+    } else {
+        PathClass::Proxy
+    }
+}
+
+async fn handle_worker(
+    mut req: Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+    settings: &Settings,
+) -> Result<Response<Body>, BoxedError> {
+    // 요청을 솔라로 넘긴 뒤에도 느린 요청 로그에 사용하므로 복사해둠
+    let uri = req.uri().clone();
+    let method = req.method().clone();
+    let path = uri.path().trim();
+    let start = Instant::now();
+
+    // listener에서 받지 않는 route는 없는 path와 같이 응답함
+    let scope = req
+        .extensions()
+        .get::<RouteScope>()
+        .copied()
+        .unwrap_or_default();
+    let is_admin_route = path == STATS_PATH || admin::is_admin_path(path);
+    let allowed = match is_admin_route {
+        true => scope.admin_routes,
+        false => scope.proxy_routes,
+    };
+    if !allowed {
+        return Err(Box::new(
+            StrError::with_status(
+                format!("UNKNOWN_PATH {}", path),
+                hyper::StatusCode::NOT_FOUND,
+            )
+            .with_kind(ErrorKind::UnknownPath),
+        ));
+    }
+    if path == STATS_PATH {
+        return stats_response(solr, settings).await;
+    }
+    if admin::is_admin_path(path) {
+        return admin::admin_response(req, ctx, solr, &settings.admin_secret, &config()).await;
+    }
+
+    // select인 경우 받은 그대로 다시 솔라에 날림
+    if path.ends_with("/select") {
+        let _in_flight = IN_FLIGHT.select.enter();
+        check_remote_ip(ctx, settings, PathClass::Select).await?;
+        if let Some(response) =
+            check_method(&req, PathClass::Select, method_rule::SELECT_METHODS).await?
+        {
+            return Ok(response);
+        }
+        // proxy 파라미터는 솔라에서 알 수 없으므로 cache key를 만들기 전에 제거함
+        let mut solr_uri = uri.clone();
+        let params = ProxyParams::take(&mut solr_uri, req.headers_mut())?;
+        *req.uri_mut() = solr_uri;
+        let clamped = select_param::apply_request(
+            &mut req,
+            &settings.select_default_params,
+            &settings.select_param_caps,
+            settings.max_body_bytes,
+        )
+        .await?;
+        let bytes_in = access_log::content_length(req.headers());
+        let cache_key = SELECT_CACHE.key(&req);
+        // 솔라에 요청하는 동안 update로 cache를 비운 경우 응답을 저장하지 않도록 먼저 읽어둠
+        let cache_generation = SELECT_CACHE.generation();
+        // proxy.cache=refresh인 경우 cache된 응답을 사용하지 않고 새 응답으로 바꿈
+        let cached = match &cache_key {
+            Some(key) if !params.refresh_cache => SELECT_CACHE.get(key, Instant::now()).await,
+            _ => None,
+        };
+        if cache_key.is_some() {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            match cached {
+                Some(_) => cnt_lock.select_cache_hit_cnt += 1,
+                None => cnt_lock.select_cache_miss_cnt += 1,
+            }
+        }
+
+        // cache에 있는 경우 솔라에 요청하지 않음
+        let (mut response, solr_duration) = match cached {
+            Some(response) => (response, Duration::ZERO),
+            None => {
+                let _permit = SELECT_LIMIT.acquire().await?;
+                let solr_start = Instant::now();
+                let response = match solr.is_hedge_enabled() {
+                    true => forward_hedged(req, ctx, solr, settings).await?,
+                    false => forward_request(req, ctx, solr).await?,
+                };
+                let response = match cache_key {
+                    Some(key) => {
+                        SELECT_CACHE
+                            .store(key, cache_generation, response, Instant::now())
+                            .await?
+                    }
+                    None => response,
+                };
+                (response, Instant::now() - solr_start)
+            }
+        };
+
+        let duration = Instant::now() - start;
+        let slow = slow_log::log_if_slow(
+            settings.slow_select,
+            &SlowRequest {
+                ctx,
+                path,
+                kind: SlowRequestKind::Select { query: uri.query() },
+                enrich: Duration::ZERO,
+                solr: solr_duration,
+                total: duration,
+            },
+        );
+        access_log::log_access(&AccessRecord {
+            ctx,
+            method: &method,
+            path,
+            doc_cnt: None,
+            bytes_in,
+            bytes_out: access_log::content_length(response.headers()),
+            status: response.status(),
+            total: duration,
+            solr: solr_duration,
+            db: None,
+            cache: None,
+        });
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock
+            .status_cnt
+            .record(PathClass::Select, response.status());
+        cnt_lock.slow_select_cnt += slow as u32;
+        cnt_lock.select_cnt += 1;
+        LATENCY.select.record(duration);
+        cnt_lock.select_duration_time_total += duration;
+        if cnt_lock.select_duration_time_min > duration {
+            cnt_lock.select_duration_time_min = duration;
+        }
+        if cnt_lock.select_duration_time_max < duration {
+            cnt_lock.select_duration_time_max = duration;
+        }
+        drop(cnt_lock);
+
+        if settings.expose_timing_headers {
+            timing_header::add_timing_headers(
+                response.headers_mut(),
+                duration,
+                solr_duration,
+                None,
+            );
+        }
+        if let Some(value) = select_param::clamped_header(&clamped) {
+            response
+                .headers_mut()
+                .insert(select_param::X_PROXY_PARAM_CLAMPED, value);
+        }
+        Ok(count_response_bytes(response, PathClass::Select))
+    } else if path.ends_with("/update") {
+        let _in_flight = IN_FLIGHT.update.enter();
+        check_remote_ip(ctx, settings, PathClass::Update).await?;
+        // stream.body로 보낸 update는 GET으로도 받음
+        let allowed = if stream_body::in_query(&uri) {
+            method_rule::STREAM_BODY_METHODS
+        } else {
+            settings.update_methods()
+        };
+        if let Some(response) = check_method(&req, PathClass::Update, allowed).await? {
+            return Ok(response);
+        }
+        // 점검 중에는 update만 받지 않음. select는 그대로 솔라로 보냄
+        if solr.is_update_draining() {
+            WORKING_CNT.lock().await.update_drained_cnt += 1;
+            return Ok(update_drain_response(&req, settings));
+        }
+        // xml이 아닌 body를 읽고 파싱에 실패한 뒤 솔라로 보내지 않도록 먼저 거부함
+        // query string의 stream.body로 보낸 update는 body가 없으므로 확인하지 않음
+        if !stream_body::in_query(&uri) {
+            if let Err(e) = content_type::check_update(
+                req.headers(),
+                &settings.update_content_types,
+                settings.update_content_type_strict,
+            ) {
+                WORKING_CNT.lock().await.update_content_type_rejected_cnt += 1;
+                return Err(e);
+            }
+        }
+        // update 또는 add인 경우
+        // 파싱, seed_id 추가, 솔라 요청이 끝날 때까지 권한을 유지함
+        let _permit = UPDATE_LIMIT.acquire().await?;
+        // proxy 파라미터는 솔라에서 알 수 없으므로 전달하기 전에 제거함
+        let mut solr_uri = uri.clone();
+        let params = ProxyParams::take(&mut solr_uri, req.headers_mut())?;
+        *req.uri_mut() = solr_uri;
+        let options = ProcOptions {
+            refresh_cache: params.refresh_cache,
+            remote_ip: Some(ctx.remote_ip.ip()),
+            collection: Some(settings.collection_for_path(path)),
+        };
+        // stream.body로 보낸 update는 xml을 꺼내 일반 update와 같이 처리하고 POST body로 보냄
+        let stream_body = match params.enrich {
+            true => stream_body::take(&mut req, settings.max_body_bytes).await?,
+            false => None,
+        };
+        // 여러 클라이언트가 동시에 commit하지 않도록 commit 시점은 proxy에서 정함
+        // stream.body를 form으로 보낸 경우 나머지 form 파라미터도 query string으로 옮긴 뒤이므로 함께 확인함
+        if let Some(policy) = settings.commit_policy() {
+            let removed = policy.apply(req.uri_mut())?;
+            if removed > 0 {
+                WORKING_CNT.lock().await.client_commit_stripped_cnt += removed;
+            }
+        }
+        if params.enrich && settings.stream_updates && stream_body.is_none() {
+            let result =
+                stream_update_request(req, ctx, solr, start, &options, path, settings).await;
+            SELECT_CACHE.on_update().await;
+            return result;
+        }
+
+        // enrich하는 요청은 spool_threshold_bytes를 넘으면 임시 파일에 받으므로 메모리에는 그만큼만 들고 있음
+        let spool_threshold = match params.enrich {
+            true => settings.spool_threshold_bytes,
+            false => 0,
+        };
+        // body를 메모리에 들고 있는 동안 차지함. Content-Length를 알 수 있으면 읽기 전에 확인하고, 없으면 받는 동안 chunk마다 확인함
+        let mut buffered = BUFFERED_BYTES.guard();
+        let expected_len = match spool_threshold {
+            0 => hyper::body::HttpBody::size_hint(req.body()).lower(),
+            threshold => hyper::body::HttpBody::size_hint(req.body())
+                .lower()
+                .min(threshold),
+        };
+        if let Err(e) = buffered.reserve_to(expected_len, settings.max_buffered_bytes) {
+            WORKING_CNT.lock().await.buffered_rejected_cnt += 1;
+            return Err(e);
+        }
+        let bytes = {
+            let span = otel::Span::start("read_body");
+            let bytes = match stream_body {
+                Some(bytes) => bytes,
+                None => match spool::read_body(
+                    req.body_mut(),
+                    spool_threshold,
+                    settings.max_body_bytes,
+                    &settings.spool_dir(),
+                    &mut buffered,
+                    settings.max_buffered_bytes,
+                )
+                .await
+                {
+                    Err(e) => {
+                        if buffered.rejected() {
+                            WORKING_CNT.lock().await.buffered_rejected_cnt += 1;
+                        }
+                        return Err(e);
+                    }
+                    Ok(SpooledBody::Memory(bytes)) => bytes,
+                    // 임시 파일에 받은 body는 파일에서 읽으며 doc 단위로 처리해서 솔라로 보냄
+                    Ok(SpooledBody::File(spool_file)) => {
+                        span.set_u64("bytes", spool_file.len());
+                        {
+                            let mut cnt_lock = WORKING_CNT.lock().await;
+                            cnt_lock.spool_cnt += 1;
+                            cnt_lock.spool_bytes_total += spool_file.len();
+                        }
+                        *req.body_mut() = spool_file.into_body().await?;
+                        let result =
+                            stream_update_request(req, ctx, solr, start, &options, path, settings)
+                                .await;
+                        SELECT_CACHE.on_update().await;
+                        return result;
+                    }
+                },
+            };
+            span.set_u64("bytes", bytes.len() as u64);
+            bytes
+        };
+        let bytes_len = bytes.len();
+        if let Err(e) = buffered.reserve_to(bytes_len as u64, settings.max_buffered_bytes) {
+            WORKING_CNT.lock().await.buffered_rejected_cnt += 1;
+            return Err(e);
+        }
+        // body의 <add commitWithin>, <commit/> 등도 query string과 같이 commit 정책을 적용함
+        let bytes = match settings.commit_policy() {
+            Some(policy) if params.enrich || content_type::is_xml(req.headers()) => {
+                let applied = policy.apply_xml(&bytes)?;
+                if applied.commit_removed > 0 {
+                    WORKING_CNT.lock().await.client_commit_stripped_cnt += applied.commit_removed;
+                }
+                applied.xml.map_or(bytes, Bytes::from)
+            }
+            _ => bytes,
+        };
+        // 솔라가 거부한 경우 로그에 남길 id를 찾기 위한 원문. Bytes이므로 복사하지 않음
+        let source = bytes.clone();
+
+        let doc_cnt: usize;
+        let mut report = WriteReport::default();
+        let body: Bytes;
+        // split_threshold_docs, split_threshold_bytes를 넘어 나눈 경우 body 대신 사용함
+        let mut chunks: Option<Vec<proc_xml::XmlChunk>> = None;
+        let parse_error: Option<BoxedError>;
+        let (mut req_parts, _) = req.into_parts();
+        // body를 새로 만들어 보내므로 원래 요청의 Content-Length는 사용하지 않음
+        util::remove_resent_body_headers(&mut req_parts.headers);
+
+        // enrich=false인 경우 파싱하지 않고 받은 그대로 보냄
+        let mut timing = ProcTiming::default();
+        let write_result = if params.enrich {
+            update_xml_parse(&bytes, &options, &mut timing, settings).await
+        } else {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.bypass_cnt += 1;
+            Ok(WriteOk::NoChanged(0))
+        };
+
+        match write_result {
+            Ok(WriteOk::Changed(final_xml, doc_cnt_ok, write_report)) => {
+                doc_cnt = doc_cnt_ok;
+                report = write_report;
+                body = Bytes::from(final_xml);
+                parse_error = None;
+            }
+            Ok(WriteOk::Split(xml_chunks, doc_cnt_ok, write_report)) => {
+                doc_cnt = doc_cnt_ok;
+                report = write_report;
+                body = Bytes::new();
+                chunks = Some(xml_chunks);
+                parse_error = None;
+            }
+            Ok(WriteOk::NoChanged(doc_cnt_ok)) => {
+                doc_cnt = doc_cnt_ok;
+                // NoChanged인 경우 전송받은 bytes를 그대로 되돌려줌
+                body = bytes;
+                parse_error = None;
+            }
+            // 요청이 제한을 넘은 경우 솔라에 보내지 않고 곧바로 에러 반환
+            Err(e) if util::error_status(&e).is_some() => return Err(e),
+            Err(e) => {
+                doc_cnt = 0;
+                // 파싱 에러가 발생한 경우 전송받은 bytes를 그대로 되돌려줌
+                body = bytes;
+                parse_error = Some(e);
+            }
+        }
+
+        let solr_start = Instant::now();
+        let mut response = match chunks {
+            Some(chunks) => {
+                send_update_chunks(req_parts, chunks, ctx, path, solr, settings).await?
+            }
+            None => {
+                // 파싱 에러로 원문을 그대로 보내는 경우에도 압축함
+                let body = compress_update_body(body, &mut req_parts.headers, settings).await?;
+                solr.send_bytes(
+                    req_parts.uri,
+                    req_parts.method,
+                    req_parts.headers,
+                    body,
+                    ctx,
+                )
+                .await?
+            }
+        };
+        let solr_duration = Instant::now() - solr_start;
+        // 색인 내용이 바뀌었으므로 이전 select 응답은 사용하지 않음
+        SELECT_CACHE.on_update().await;
+
+        record_add(
+            start,
+            doc_cnt,
+            report.changed_doc_cnt,
+            bytes_len,
+            &timing,
+            solr_duration,
+            &response,
+        )
+        .await;
+        if !response.status().is_success() {
+            let first_id = proc_xml::first_doc_id(&source);
+            warn_rejected_update(ctx, path, response.status(), doc_cnt, first_id.as_deref());
+        }
+        let kind = SlowRequestKind::Update {
+            doc_cnt,
+            bytes_len,
+            changed_doc_cnt: report.changed_doc_cnt,
+            changed_ids: &report.changed_ids,
+        };
+        log_slow_update(ctx, path, kind, &timing, solr_duration, start, settings).await;
+        access_log::log_access(&AccessRecord {
+            ctx,
+            method: &method,
+            path,
+            doc_cnt: Some(doc_cnt),
+            bytes_in: Some(bytes_len as u64),
+            bytes_out: access_log::content_length(response.headers()),
+            status: response.status(),
+            total: Instant::now() - start,
+            solr: solr_duration,
+            db: Some(timing.db),
+            cache: Some((timing.cache_hit, timing.cache_miss)),
+        });
+        add_update_timing_headers(
+            &mut response,
+            start,
+            solr_duration,
+            doc_cnt,
+            &timing,
+            settings,
+        );
+        let response = count_response_bytes(response, PathClass::Update);
+
+        match parse_error {
+            Some(err) => Err(Box::new(ResponseWithError { err, response })),
+            None => Ok(response),
+        }
+    } else if settings.passthrough_unknown_paths {
+        if route::is_denied_path(path, &settings.passthrough_deny_prefixes) {
+            let err_msg = format!("DENIED_PATH {}", path);
+            return Err(Box::new(StrError::with_status(
+                err_msg,
+                hyper::StatusCode::FORBIDDEN,
+            )));
+        }
+        // update handler는 그대로 보내더라도 update_allowed_ips와 점검 상태, query string의 commit 정책을 확인함
+        if route::is_update_handler(path) {
+            check_remote_ip(ctx, settings, PathClass::Update).await?;
+            if solr.is_update_draining() {
+                WORKING_CNT.lock().await.update_drained_cnt += 1;
+                return Ok(update_drain_response(&req, settings));
+            }
+            if let Some(policy) = settings.commit_policy() {
+                let removed = policy.apply(req.uri_mut())?;
+                if removed > 0 {
+                    WORKING_CNT.lock().await.client_commit_stripped_cnt += removed;
+                }
+            }
+        }
+
+        // 그 외의 path는 select와 동일하게 받은 그대로 솔라에 날림
+        let response = forward_request(req, ctx, solr).await?;
+
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock
+            .status_cnt
+            .record(PathClass::Passthrough, response.status());
+        cnt_lock.passthrough_cnt += 1;
+        drop(cnt_lock);
+
+        Ok(response)
+    } else {
+        let err_msg = format!("UNKNOWN_PATH {}", path);
+        Err(Box::new(
+            StrError::with_status(err_msg, hyper::StatusCode::NOT_FOUND)
+                .with_kind(ErrorKind::UnknownPath),
+        ))
+    }
+}
+
+/// update_allowed_ips, select_allowed_ips에 없는 ip인 경우 솔라로 보내지 않고 403 에러 반환
+async fn check_remote_ip(
+    ctx: &RequestContext,
+    settings: &Settings,
+    class: PathClass,
+) -> Result<(), BoxedError> {
+    let (allowed_ips, name) = match class {
+        PathClass::Select => (&settings.select_allowed_ips, "select"),
+        _ => (&settings.update_allowed_ips, "update"),
+    };
+    if allowed_ips.allows(ctx.remote_ip.ip()) {
+        return Ok(());
+    }
+
+    let mut cnt_lock = WORKING_CNT.lock().await;
+    match class {
+        PathClass::Select => cnt_lock.select_ip_rejected_cnt += 1,
+        _ => cnt_lock.update_ip_rejected_cnt += 1,
+    }
+    Err(Box::new(StrError::with_status(
+        format!("IP_NOT_ALLOWED: {} from {}", name, ctx.remote_ip.ip()),
+        hyper::StatusCode::FORBIDDEN,
+    )))
+}
+
+/// path에서 사용할 수 없는 method인 경우 솔라로 보내지 않고 405 에러 반환
+/// <br>
+/// OPTIONS 요청은 사용할 수 있는 method를 204로 응답함
+async fn check_method(
+    req: &Request<Body>,
+    class: PathClass,
+    allowed: &[hyper::Method],
+) -> Result<Option<Response<Body>>, BoxedError> {
+    match method_rule::check(req.method(), allowed) {
+        MethodCheck::Allowed => Ok(None),
+        MethodCheck::Options => {
+            WORKING_CNT.lock().await.options_cnt += 1;
+            Ok(Some(method_rule::options_response(allowed)))
+        }
+        MethodCheck::NotAllowed => {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            match class {
+                PathClass::Select => cnt_lock.select_method_rejected_cnt += 1,
+                _ => cnt_lock.update_method_rejected_cnt += 1,
+            }
+            // 응답을 담은 에러이므로 handle에서 세지 않음
+            cnt_lock
+                .status_cnt
+                .record(class, hyper::StatusCode::METHOD_NOT_ALLOWED);
+            Err(method_rule::not_allowed(req, allowed))
+        }
+    }
+}
+
+/// 점검 중 받은 update 요청의 503 응답. 점검이 끝난 뒤 다시 보낼 수 있도록 Retry-After를 넣음
+fn update_drain_response(req: &Request<Body>, settings: &Settings) -> Response<Body> {
+    let format = ResponseFormat::from_request(req.uri(), req.headers());
+    let mut response = error_response::error_response(
+        hyper::StatusCode::SERVICE_UNAVAILABLE,
+        "DRAINING: update is paused for maintenance, retry later",
+        format,
+    );
+    response
+        .headers_mut()
+        .insert(hyper::header::RETRY_AFTER, settings.retry_after_secs.into());
+    response
+}
+
+/// update 처리 횟수, 시간 기록
+async fn record_add(
+    start: Instant,
+    doc_cnt: usize,
+    changed_doc_cnt: usize,
+    bytes_len: usize,
+    timing: &ProcTiming,
+    solr: Duration,
+    response: &Response<Body>,
+) {
+    let duration = Instant::now() - start;
+    LATENCY.update.record(duration);
+    if timing.cache_miss > 0 {
+        LATENCY.db.record(timing.db);
+    }
+    let mut cnt_lock = WORKING_CNT.lock().await;
+    cnt_lock
+        .status_cnt
+        .record(PathClass::Update, response.status());
+    cnt_lock.add_parse_time.add(timing.parse);
+    cnt_lock.add_enrich_time.add(timing.enrich);
+    cnt_lock.add_solr_time.add(solr);
+    cnt_lock.add_cnt += 1;
+    cnt_lock.add_doc_cnt += doc_cnt;
+    cnt_lock.add_changed_doc_cnt += changed_doc_cnt;
+    if !response.status().is_success() {
+        cnt_lock.add_rejected_cnt += 1;
+        cnt_lock.add_rejected_doc_cnt += doc_cnt;
+    }
+    cnt_lock.add_duration_time_total += duration;
+    cnt_lock.add_bytes_total += bytes_len;
+    if cnt_lock.add_duration_time_min > duration {
+        cnt_lock.add_duration_time_min = duration;
+    }
+    if cnt_lock.add_duration_time_max.0 < duration {
+        cnt_lock.add_duration_time_max = (duration, doc_cnt, bytes_len);
+    }
+}
+
+/// 솔라로 보내는 update body를 compress_upstream 설정에 따라 압축함. 압축한 경우 header_map에 Content-Encoding을 넣음
+async fn compress_update_body(
+    body: Bytes,
+    header_map: &mut hyper::HeaderMap,
+    settings: &Settings,
+) -> Result<Bytes, BoxedError> {
+    let body_len = body.len();
+    match compress::compress_body(
+        &body,
+        settings.compress_upstream,
+        settings.compress_upstream_min_bytes,
+        header_map,
+    )? {
+        Some(compressed) => {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.compress_cnt += 1;
+            cnt_lock.compress_bytes_before_total += body_len;
+            cnt_lock.compress_bytes_after_total += compressed.len();
+            Ok(compressed)
+        }
+        None => Ok(body),
+    }
+}
+
+/// 나눈 update를 순서대로 하나씩 솔라에 보냄
+/// <br>
+/// 솔라가 거부한 chunk가 있으면 남은 chunk는 보내지 않고 그 응답을 반환함. 모두 성공하면 마지막 chunk의 응답을 반환함
+/// <br>
+/// 모든 doc을 보낸 뒤에 한 번만 commit하도록 마지막이 아닌 chunk는 commit, softCommit, optimize 파라미터를 제거하고 보냄
+async fn send_update_chunks(
+    req_parts: hyper::http::request::Parts,
+    chunks: Vec<proc_xml::XmlChunk>,
+    ctx: &RequestContext,
+    path: &str,
+    solr: &Solr,
+    settings: &Settings,
+) -> Result<Response<Body>, BoxedError> {
+    let chunk_cnt = chunks.len();
+    {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.update_split_cnt += 1;
+        cnt_lock.update_split_chunk_cnt += chunk_cnt as u32;
+    }
+    let uncommitted_uri = commit_param::without_commit_params(&req_parts.uri)?;
+    let mut response = None;
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let mut header_map = req_parts.headers.clone();
+        let body = compress_update_body(Bytes::from(chunk.xml), &mut header_map, settings).await?;
+        let uri = match index + 1 == chunk_cnt {
+            true => req_parts.uri.clone(),
+            false => uncommitted_uri.clone(),
+        };
+        let chunk_response = solr
+            .send_bytes(uri, req_parts.method.clone(), header_map, body, ctx)
+            .await
+            .inspect_err(|e| {
+                warn!(
+                    "[{}] SOLR_UPDATE_CHUNK_FAIL {}/{}, path: {}, {} doc, id: {} ~ {}, err: {}",
+                    ctx.request_id,
+                    index + 1,
+                    chunk_cnt,
+                    path,
+                    chunk.doc_cnt,
+                    chunk.first_id,
+                    chunk.last_id,
+                    e
+                )
+            })?;
+        if !chunk_response.status().is_success() {
+            warn!(
+                "[{}] SOLR_UPDATE_CHUNK_REJECTED {} {}/{}, path: {}, {} doc, id: {} ~ {}, {} chunks not sent",
+                ctx.request_id,
+                chunk_response.status(),
+                index + 1,
+                chunk_cnt,
+                path,
+                chunk.doc_cnt,
+                chunk.first_id,
+                chunk.last_id,
+                chunk_cnt - index - 1
+            );
+            WORKING_CNT.lock().await.update_split_chunk_rejected_cnt += 1;
+            return Ok(chunk_response);
+        }
+        response = Some(chunk_response);
+    }
+    response.ok_or_else(|| "UPDATE_CHUNK_EMPTY".into())
+}
+
+/// 솔라가 2xx가 아닌 status로 응답한 update 요청 로그. 응답은 클라이언트에 그대로 돌려줌
+fn warn_rejected_update(
+    ctx: &RequestContext,
+    path: &str,
+    status: hyper::StatusCode,
+    doc_cnt: usize,
+    first_id: Option<&str>,
+) {
+    warn!(
+        "[{}] SOLR_UPDATE_REJECTED {}, path: {}, {} doc, first id: {}",
+        ctx.request_id,
+        status,
+        path,
+        doc_cnt,
+        first_id.unwrap_or("-")
+    );
+}
+
+/// slow_update 이상 걸린 update 요청 로그
+async fn log_slow_update(
+    ctx: &RequestContext,
+    path: &str,
+    kind: SlowRequestKind<'_>,
+    timing: &ProcTiming,
+    solr: Duration,
+    start: Instant,
+    settings: &Settings,
+) {
+    let slow = slow_log::log_if_slow(
+        settings.slow_update,
+        &SlowRequest {
+            ctx,
+            path,
+            kind,
+            enrich: timing.enrich,
+            solr,
+            total: Instant::now() - start,
+        },
+    );
+    if slow {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.slow_update_cnt += 1;
+    }
+}
+
+/// update body를 doc 단위로 처리하면서 동시에 솔라로 보냄
+/// <br>
+/// 스트리밍 중에는 body 크기를 알 수 없으므로 압축하지 않으며, id 중복 제거는 doc 하나 안에서만 적용됨
+async fn stream_update_request(
+    req: Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+    start: Instant,
+    options: &ProcOptions,
+    path: &str,
+    settings: &Settings,
+) -> Result<Response<Body>, BoxedError> {
+    let (mut req_parts, req_body) = req.into_parts();
+    let method = req_parts.method.clone();
+    // 스트리밍 중에는 솔라 요청과 doc 처리가 동시에 진행되므로 솔라 시간은 전체 전송 시간임
+    let solr_start = Instant::now();
+    util::remove_resent_body_headers(&mut req_parts.headers);
+    let (sender, body) = Body::channel();
+    let read_limit = settings.read_limit();
+
+    let (stream_result, solr_result) = tokio::join!(
+        stream_xml::stream_update(
+            req_body,
+            sender,
+            &read_limit,
+            settings.max_body_bytes,
+            options,
+            settings.commit_policy()
+        ),
+        solr.send_request(
+            req_parts.uri,
+            req_parts.method,
+            req_parts.headers,
+            body,
+            ctx
+        )
+    );
+    // 스트리밍 중 에러가 발생한 경우 솔라 요청도 실패하므로 스트리밍 에러를 먼저 확인
+    let stream_result = stream_result?;
+    if stream_result.commit_removed > 0 {
+        WORKING_CNT.lock().await.client_commit_stripped_cnt += stream_result.commit_removed;
+    }
+    let (res_parts, res_body) = solr_result?.into_parts();
+    let mut response = Response::from_parts(res_parts, res_body);
+    let solr_duration = Instant::now() - solr_start;
+
+    record_add(
+        start,
+        stream_result.doc_cnt,
+        stream_result.changed_doc_cnt,
+        stream_result.bytes_len,
+        &stream_result.timing,
+        solr_duration,
+        &response,
+    )
+    .await;
+    if !response.status().is_success() {
+        warn_rejected_update(
+            ctx,
+            path,
+            response.status(),
+            stream_result.doc_cnt,
+            stream_result.first_id.as_deref(),
+        );
+    }
+    let kind = SlowRequestKind::Update {
+        doc_cnt: stream_result.doc_cnt,
+        bytes_len: stream_result.bytes_len,
+        changed_doc_cnt: stream_result.changed_doc_cnt,
+        changed_ids: &[],
+    };
+    log_slow_update(
+        ctx,
+        path,
+        kind,
+        &stream_result.timing,
+        solr_duration,
+        start,
+        settings,
+    )
+    .await;
+    access_log::log_access(&AccessRecord {
+        ctx,
+        method: &method,
+        path,
+        doc_cnt: Some(stream_result.doc_cnt),
+        bytes_in: Some(stream_result.bytes_len as u64),
+        bytes_out: access_log::content_length(response.headers()),
+        status: response.status(),
+        total: Instant::now() - start,
+        solr: solr_duration,
+        db: Some(stream_result.timing.db),
+        cache: Some((
+            stream_result.timing.cache_hit,
+            stream_result.timing.cache_miss,
+        )),
+    });
+    add_update_timing_headers(
+        &mut response,
+        start,
+        solr_duration,
+        stream_result.doc_cnt,
+        &stream_result.timing,
+        settings,
+    );
+    let response = count_response_bytes(response, PathClass::Update);
+
+    match stream_result.parse_error {
+        Some(err) => Err(Box::new(ResponseWithError { err, response })),
+        None => Ok(response),
+    }
+}
+
+/// 클라이언트에 보낸 응답 body 크기를 통계에 남김. body는 그대로 스트리밍함
+fn count_response_bytes(response: Response<Body>, class: PathClass) -> Response<Body> {
+    response.map(|body| {
+        // 스트림이 끝나는 poll 안에서 호출되므로 lock 없이 atomic에 더함
+        util::count_body(body, move |bytes| match class {
+            PathClass::Select => RESPONSE_BYTES.add_select(bytes),
+            PathClass::Update => RESPONSE_BYTES.add_update(bytes),
+            PathClass::Passthrough | PathClass::Proxy => {}
+        })
+    })
+}
+
+/// expose_timing_headers인 경우 update 응답에 처리 시간, doc 수, seed_id cache 사용 결과 header를 추가함
+fn add_update_timing_headers(
+    response: &mut Response<Body>,
+    start: Instant,
+    solr: Duration,
+    doc_cnt: usize,
+    timing: &ProcTiming,
+    settings: &Settings,
+) {
+    if !settings.expose_timing_headers {
+        return;
+    }
+    let update = UpdateInfo {
+        doc_cnt,
+        cache_hit: timing.cache_hit,
+        cache_miss: timing.cache_miss,
+    };
+    timing_header::add_timing_headers(
+        response.headers_mut(),
+        Instant::now() - start,
+        solr,
+        Some(update),
+    );
+}
+
+/// 현재 집계 중인 통계를 json으로 응답
+async fn stats_response(solr: &Solr, settings: &Settings) -> Result<Response<Body>, BoxedError> {
+    let seed_id_cache = serde_json::json!({
+        "len": SEED_ID_CACHE.len().await,
+        "capacity": settings.seed_id_cache_capacity,
+        "entry_bytes": SEED_ID_CACHE.entry_bytes(),
+        "evict_cnt": SEED_ID_CACHE.evict_cnt(),
+        "write_queue_depth": SEED_CACHE_WRITER.depth.current(),
+        "write_queue_high": SEED_CACHE_WRITER.depth.high(),
+    });
+    let body = {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        RESPONSE_BYTES.fold_into(&mut cnt_lock);
+        serde_json::json!({
+            "select_cnt": cnt_lock.select_cnt,
+            "select_bytes_total": cnt_lock.select_bytes_total,
+            "add_cnt": cnt_lock.add_cnt,
+            "add_doc_cnt": cnt_lock.add_doc_cnt,
+            "add_changed_doc_cnt": cnt_lock.add_changed_doc_cnt,
+            "add_rejected_cnt": cnt_lock.add_rejected_cnt,
+            "add_rejected_doc_cnt": cnt_lock.add_rejected_doc_cnt,
+            "err_cnt": cnt_lock.err_cnt,
+            "passthrough_cnt": cnt_lock.passthrough_cnt,
+            "upstream_conn_err_cnt": cnt_lock.upstream_conn_err_cnt,
+            "upstream_connect_fail_cnt": cnt_lock.upstream_connect_fail_cnt,
+            "upstream_unauthorized_cnt": cnt_lock.upstream_unauthorized_cnt,
+            "hedge_fired_cnt": cnt_lock.hedge_fired_cnt,
+            "hedge_won_cnt": cnt_lock.hedge_won_cnt,
+            "select_cache_hit_cnt": cnt_lock.select_cache_hit_cnt,
+            "select_cache_miss_cnt": cnt_lock.select_cache_miss_cnt,
+            "update_drained_cnt": cnt_lock.update_drained_cnt,
+            "update_content_type_rejected_cnt": cnt_lock.update_content_type_rejected_cnt,
+            "client_commit_stripped_cnt": cnt_lock.client_commit_stripped_cnt,
+            "draining": solr.is_update_draining(),
+            "backends": solr.backend_status_json(),
+            "status": cnt_lock.status_cnt.to_json(),
+            "errors": cnt_lock.error_kind_cnt.to_json(),
+            "collections": cnt_lock.collection_cnt.iter().map(|(name, stat)| {
+                (name.clone(), serde_json::json!({
+                    "doc_cnt": stat.doc_cnt,
+                    "cache_hit_cnt": stat.cache_hit_cnt,
+                    "cache_miss_cnt": stat.cache_miss_cnt,
+                    "seed_id_insert_cnt": stat.seed_id_insert_cnt,
+                }))
+            }).collect::<serde_json::Map<_, _>>(),
+            "seed_id_cache": seed_id_cache,
+            "in_flight": in_flight_json(),
+            "buffered_bytes": {
+                "current": BUFFERED_BYTES.current(),
+                "max": BUFFERED_BYTES.high(),
+            },
+            "buffered_rejected_cnt": cnt_lock.buffered_rejected_cnt,
+            "spool_cnt": cnt_lock.spool_cnt,
+            "spool_bytes_total": cnt_lock.spool_bytes_total,
+            "update_split_cnt": cnt_lock.update_split_cnt,
+            "update_split_chunk_cnt": cnt_lock.update_split_chunk_cnt,
+            "update_split_chunk_rejected_cnt": cnt_lock.update_split_chunk_rejected_cnt,
+            "latency": latency_json(),
+        })
+    };
+
+    Ok(Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))?)
+}
+
+/// 처리중인 요청 수와 이번 통계 구간의 최대값
+fn in_flight_json() -> serde_json::Value {
+    let mut in_flight = serde_json::Map::new();
+    for (name, gauge) in IN_FLIGHT.gauges() {
+        in_flight.insert(
+            name.to_string(),
+            serde_json::json!({ "current": gauge.current(), "max": gauge.high() }),
+        );
+    }
+    in_flight.into()
+}
+
+/// 이번 통계 구간의 소요 시간 분포
+fn latency_json() -> serde_json::Value {
+    let mut latency = serde_json::Map::new();
+    for (name, histogram) in LATENCY.histograms() {
+        latency.insert(name.to_string(), histogram.snapshot().to_json());
+    }
+    latency.into()
+}
+
+/// 받은 요청을 그대로 솔라에 전달
+/// <br>
+/// body를 받는 대로 보내므로 Expect 헤더도 그대로 전달함. hyper client는 100 응답을 기다리지 않고 받으면 무시함
+async fn forward_request(
+    req: Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+) -> Result<Response<Body>, BoxedError> {
+    let (req_parts, req_body) = req.into_parts();
+    let (res_parts, res_body) = solr
+        .send_request(
+            req_parts.uri,
+            req_parts.method,
+            req_parts.headers,
+            req_body,
+            ctx,
+        )
+        .await?
+        .into_parts();
+    Ok(Response::from_parts(res_parts, res_body))
+}
+
+/// 다른 솔라에도 보낼 수 있도록 body를 모두 읽은 뒤 보냄
+async fn forward_hedged(
+    req: Request<Body>,
+    ctx: &RequestContext,
+    solr: &Solr,
+    settings: &Settings,
+) -> Result<Response<Body>, BoxedError> {
+    let (req_parts, mut req_body) = req.into_parts();
+    let body = util::to_bytes_limited(&mut req_body, settings.max_body_bytes).await?;
+    solr.send_hedged(
+        req_parts.uri,
+        req_parts.method,
+        req_parts.headers,
+        body,
+        ctx,
+    )
+    .await
+}
+
+async fn update_xml_parse(
+    bytes: &hyper::body::Bytes,
+    options: &ProcOptions,
+    timing: &mut ProcTiming,
+    settings: &Settings,
+) -> Result<WriteOk, BoxedError> {
+    let parse_start = Instant::now();
+    let (mut parse_result, envelope) = {
+        let span = otel::Span::start("parse_xml");
+        span.set_u64("bytes", bytes.len() as u64);
+        let (parse_result, envelope) = proc_xml::read_xml_envelope(bytes, &settings.read_limit())?;
+        span.set_u64("doc_cnt", parse_result.len() as u64);
+        (parse_result, envelope)
+    };
+    timing.parse = Instant::now() - parse_start;
+
+    // 제거된 doc은 envelope의 doc 수와 비교해서 알 수 있음
+    {
+        let span = otel::Span::start("enrich");
+        span.set_u64("doc_cnt", parse_result.len() as u64);
+        span.instrument(proc_xml::proc_xml(&mut parse_result, options, timing))
+            .await?;
+    }
+
+    let write_start = Instant::now();
+    let write_result = proc_xml::write_xml_split(
+        parse_result,
+        &envelope,
+        &settings.split_limit(),
+        options.collection(settings).seed_id_field,
+    );
+    timing.parse += Instant::now() - write_start;
+    write_result
+}
+
+/// 전역 설정 대신 handle_with에 넘길 설정
+#[cfg(test)]
+fn test_settings(toml: &str) -> Arc<Settings> {
+    let config = Config::builder()
+        .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+        .build()
+        .unwrap();
+    Arc::new(Settings::from_config(&config).unwrap())
+}
+
+#[tokio::test]
+async fn request_id_roundtrip_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    // 클라이언트가 보낸 X-Request-Id를 솔라에 전달하고 응답에도 돌려줌
+    let req = Request::get("/solr/core/select?q=*:*")
+        .header(X_REQUEST_ID, "client-req-1")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.headers()[X_REQUEST_ID], "client-req-1");
+    // expose_timing_headers 기본값은 false
+    assert!(response
+        .headers()
+        .get(timing_header::X_PROXY_DURATION_MS)
+        .is_none());
+    assert_eq!(
+        mock.next_request().await.headers[X_REQUEST_ID],
+        "client-req-1"
+    );
+
+    // X-Request-Id가 없는 경우 생성한 값이 솔라 요청과 응답에 동일하게 들어감
+    let req = Request::get("/solr/core/select?q=*:*")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    let request_id = response.headers()[X_REQUEST_ID].clone();
+    assert_eq!(mock.next_request().await.headers[X_REQUEST_ID], request_id);
+
+    // 파싱 에러로 ResponseWithError가 반환되는 경우에도 들어감
+    let req = Request::post("/solr/core/update")
+        .header(X_REQUEST_ID, "client-req-2")
+        .body(Body::from("<add><doc></add>"))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(response.headers()[X_REQUEST_ID], "client-req-2");
+    assert_eq!(
+        mock.next_request().await.headers[X_REQUEST_ID],
+        "client-req-2"
+    );
+
+    // proxy에서 만든 에러 응답에도 들어감
+    let req = Request::get("/solr/core/unknown")
+        .header(X_REQUEST_ID, "client-req-3")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[X_REQUEST_ID], "client-req-3");
+}
+
+#[tokio::test]
+async fn enrich_bypass_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let bypass_cnt = WORKING_CNT.lock().await.bypass_cnt;
+
+    // proxy.enrich=false인 경우 seed_id가 없어도 받은 그대로 전달하고 파라미터는 제거함
+    let req = Request::post("/solr/core/update?proxy.enrich=false&commit=true")
+        .body(Body::from(proc_xml::SAMPLE_XML))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/update?commit=true");
+    assert_eq!(captured.body, proc_xml::SAMPLE_XML.as_bytes());
+
+    // header로 지정한 경우 파싱할 수 없는 body도 에러 없이 그대로 전달함
+    let req = Request::post("/solr/core/update")
+        .header(proxy_param::X_PROXY_ENRICH, "false")
+        .body(Body::from("<add><doc></add>"))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert!(captured.headers.get(proxy_param::X_PROXY_ENRICH).is_none());
+    assert_eq!(captured.body, "<add><doc></add>");
+    assert!(WORKING_CNT.lock().await.bypass_cnt >= bypass_cnt + 2);
+
+    // 잘못된 값은 솔라에 보내지 않고 400 응답
+    let req = Request::post("/solr/core/update?proxy.cache=never")
+        .body(Body::from(proc_xml::SAMPLE_XML))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn request_panic_test() {
+    use panic_policy::{catch_request_panic, in_request_scope, PanicPolicy};
+
+    async fn panic_worker() -> Result<(), BoxedError> {
+        assert!(in_request_scope());
+        panic!("REQUEST_PANIC_TEST");
+    }
+
+    let err = catch_request_panic(PanicPolicy::Request, panic_worker())
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "REQUEST_PANIC: REQUEST_PANIC_TEST");
+    assert_eq!(
+        util::error_status(&err),
+        Some(hyper::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert!(!in_request_scope());
+
+    // panic이 발생한 뒤에도 다음 요청은 정상적으로 처리함
+    let solr = Solr::new(
+        "http://127.0.0.1:1".parse().unwrap(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let req = Request::get(STATS_PATH).body(Body::empty()).unwrap();
+    let response = handle(req, "10.0.0.1:5000".parse().unwrap(), &solr)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn update_rejected_test() {
+    use status_cnt::StatusBucket;
+
+    // commit=bad인 경우 400, commit=down인 경우 503 응답
+    let mut mock = mock::MockSolr::start_with(|req| {
+        let status = match req.uri.query() {
+            Some("commit=bad") => hyper::StatusCode::BAD_REQUEST,
+            Some("commit=down") => hyper::StatusCode::SERVICE_UNAVAILABLE,
+            _ => hyper::StatusCode::OK,
+        };
+        Response::builder()
+            .status(status)
+            .body(Body::from("solr body"))
+            .unwrap()
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    let before = WORKING_CNT.lock().await.clone();
+    for (query, status) in [
+        ("commit=ok", hyper::StatusCode::OK),
+        ("commit=bad", hyper::StatusCode::BAD_REQUEST),
+        ("commit=down", hyper::StatusCode::SERVICE_UNAVAILABLE),
+    ] {
+        let req = Request::post(format!("/solr/core/update?proxy.enrich=false&{}", query))
+            .body(Body::from(
+                r#"<add><doc><field name="id">1</field></doc></add>"#,
+            ))
+            .unwrap();
+        let response = handle(req, remote_ip, &solr).await.unwrap();
+        // 솔라의 응답은 status, body 모두 그대로 돌려줌
+        assert_eq!(response.status(), status);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"solr body");
+        mock.next_request().await;
+    }
+
+    // 다른 테스트와 같은 카운터를 사용하므로 증가한 값 이상인지 확인함
+    let after = WORKING_CNT.lock().await.clone();
+    for (bucket, cnt) in [
+        (StatusBucket::Success, 1),
+        (StatusBucket::ClientError, 1),
+        (StatusBucket::ServerError, 1),
+    ] {
+        assert!(
+            after.status_cnt.get(PathClass::Update, bucket)
+                >= before.status_cnt.get(PathClass::Update, bucket) + cnt
+        );
+    }
+    assert!(after.add_rejected_cnt >= before.add_rejected_cnt + 2);
+}
+
+#[tokio::test]
+async fn status_cnt_request_test() {
+    use status_cnt::StatusBucket;
+
+    // q=fail인 경우 500, q=missing인 경우 404 응답
+    let mut mock = mock::MockSolr::start_with(|req| {
+        let status = match req.uri.query() {
+            Some("q=fail") => hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            Some("q=missing") => hyper::StatusCode::NOT_FOUND,
+            _ => hyper::StatusCode::OK,
+        };
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    let before = WORKING_CNT.lock().await.status_cnt.clone();
+    for query in ["q=*:*", "q=fail", "q=missing", "q=*:*"] {
+        let req = Request::get(format!("/solr/core/select?{}", query))
+            .body(Body::empty())
+            .unwrap();
+        handle(req, remote_ip, &solr).await.unwrap();
+        mock.next_request().await;
+    }
+    let req = Request::post("/solr/core/update?proxy.enrich=false")
+        .body(Body::from("<add></add>"))
+        .unwrap();
+    handle(req, remote_ip, &solr).await.unwrap();
+
+    // 다른 테스트와 같은 카운터를 사용하므로 증가한 값 이상인지 확인함
+    let after = WORKING_CNT.lock().await.status_cnt.clone();
+    for (path, bucket, cnt) in [
+        (PathClass::Select, StatusBucket::Success, 2),
+        (PathClass::Select, StatusBucket::ClientError, 1),
+        (PathClass::Select, StatusBucket::ServerError, 1),
+        (PathClass::Update, StatusBucket::Success, 1),
+    ] {
+        assert!(after.get(path, bucket) >= before.get(path, bucket) + cnt);
+    }
+
+    // 통계 endpoint는 솔라에 전달하지 않고 json으로 응답함
+    let req = Request::get(STATS_PATH).body(Body::empty()).unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(
+        response.headers()[hyper::header::CONTENT_TYPE],
+        "application/json"
+    );
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(stats["status"]["select"]["5xx"].as_u64().unwrap() >= 1);
+    assert!(stats["status"]["update"]["2xx"].as_u64().unwrap() >= 1);
+    assert!(stats["seed_id_cache"]["capacity"].as_u64().unwrap() > 0);
+    // update body를 읽었으므로 최대값이 기록됨
+    assert!(stats["buffered_bytes"]["max"].as_u64().unwrap() >= "<add></add>".len() as u64);
+    // 소요 시간 분포는 버킷까지 응답함
+    for name in ["select", "update", "solr"] {
+        let latency = &stats["latency"][name];
+        assert!(latency["count"].as_u64().unwrap() >= 1, "{}", name);
+        assert!(latency["p99_ms"].is_f64());
+        assert!(!latency["buckets"].as_array().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn db_init_message_test() {
+    let message = db_init_message(
+        "localhost",
+        "proxy",
+        "p@ssw0rd",
+        &secret::SecretSource::Inline,
+        "seed",
+    );
+    assert!(!message.contains("p@ssw0rd"));
+    assert_eq!(
+        message,
+        "DB INIT: host: localhost, user: proxy, pwd: ****** (from config), schema: seed"
+    );
+}
+
+#[tokio::test]
+async fn method_check_request_test() {
+    use hyper::header::ALLOW;
+    use hyper::{Method, StatusCode};
+
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let request = |method: Method, uri: &str, body: &'static str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body))
+            .unwrap()
+    };
+    let before = WORKING_CNT.lock().await.clone();
+
+    // update는 POST만 사용할 수 있으며 솔라로 보내지 않음
+    let req = request(Method::GET, "/solr/core/update?commit=true", "");
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "POST, OPTIONS");
+
+    let req = request(Method::DELETE, "/solr/core/select?q=*:*", "");
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "GET, POST, HEAD, OPTIONS");
+
+    let req = request(Method::OPTIONS, "/solr/core/select", "");
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()[ALLOW], "GET, POST, HEAD, OPTIONS");
+    assert!(mock.requests.try_recv().is_err());
+
+    let after = WORKING_CNT.lock().await.clone();
+    assert!(after.update_method_rejected_cnt > before.update_method_rejected_cnt);
+    assert!(after.select_method_rejected_cnt > before.select_method_rejected_cnt);
+    assert!(after.options_cnt > before.options_cnt);
+
+    // form으로 POST한 select는 body를 그대로 전달함
+    let req = Request::post("/solr/core/select")
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(Body::from("q=*:*&rows=0"))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.method, Method::POST);
+    assert_eq!(captured.body, "q=*:*&rows=0");
+
+    // HEAD는 body가 없는 응답을 기다리지 않고 돌려줌
+    let req = request(Method::HEAD, "/solr/core/select?q=*:*", "");
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = tokio::time::timeout(
+        Duration::from_secs(5),
+        hyper::body::to_bytes(response.into_body()),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert!(body.is_empty());
+    assert_eq!(mock.next_request().await.method, Method::HEAD);
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn otel_trace_test() {
+    use opentelemetry::trace::{SpanId, TraceId};
+    use opentelemetry::Value;
+
+    let exporter = otel::TestExporter::install();
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let attr = |span: &opentelemetry_sdk::export::trace::SpanData, key: &str| {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    };
+
+    // 받은 traceparent에 이어서 request span을 만들고, 솔라에는 solr span을 부모로 보냄
+    let trace_id = TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap();
+    let req = Request::get("/solr/core/select?q=otel")
+        .header(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+
+    let spans = exporter.trace(trace_id);
+    let request = spans.iter().find(|span| span.name == "request").unwrap();
+    assert_eq!(
+        request.parent_span_id,
+        SpanId::from_hex("b7ad6b7169203331").unwrap()
+    );
+    assert_eq!(
+        attr(request, "http.request.method"),
+        Some(Value::from("GET"))
+    );
+    assert_eq!(
+        attr(request, "url.path"),
+        Some(Value::from("/solr/core/select"))
+    );
+    assert_eq!(
+        attr(request, "http.response.status_code"),
+        Some(Value::I64(200))
+    );
+    let solr_span = spans.iter().find(|span| span.name == "solr").unwrap();
+    assert_eq!(solr_span.parent_span_id, request.span_context.span_id());
+    assert_eq!(
+        captured.headers["traceparent"],
+        format!("00-{}-{}-01", trace_id, solr_span.span_context.span_id())
+    );
+
+    // update는 body 읽기, 파싱, seed_id 추가, 솔라 요청 span이 request span 아래에 생김
+    let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+    let body = r#"<add><doc><field name="id">otel-1</field><field name="seed_id">e7531c15-2384-11ed-b560-42010a025a43</field></doc></add>"#;
+    let req = Request::post("/solr/core/update")
+        .header(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    mock.next_request().await;
+
+    let spans = exporter.trace(trace_id);
+    let request = spans.iter().find(|span| span.name == "request").unwrap();
+    for name in ["read_body", "parse_xml", "enrich", "solr"] {
+        let span = spans.iter().find(|span| span.name == name).unwrap();
+        assert_eq!(
+            span.parent_span_id,
+            request.span_context.span_id(),
+            "{}",
+            name
+        );
+    }
+    let read_body = spans.iter().find(|span| span.name == "read_body").unwrap();
+    assert_eq!(
+        attr(read_body, "bytes"),
+        Some(Value::I64(body.len() as i64))
+    );
+    let parse_xml = spans.iter().find(|span| span.name == "parse_xml").unwrap();
+    assert_eq!(attr(parse_xml, "doc_cnt"), Some(Value::I64(1)));
+}
+
+#[tokio::test]
+async fn update_drain_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    )
+    .with_update_drain(true);
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let update = || {
+        Request::post("/solr/core/update?proxy.enrich=false")
+            .body(Body::from(proc_xml::SAMPLE_XML))
+            .unwrap()
+    };
+
+    // 점검 중에는 update를 솔라로 보내지 않고 503 응답
+    let response = handle(update(), remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(hyper::header::RETRY_AFTER));
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(value["error"]["msg"]
+        .as_str()
+        .unwrap()
+        .starts_with("DRAINING"));
+
+    // select는 그대로 솔라로 보냄
+    let req = Request::get("/solr/core/select?q=drain")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.uri, "/solr/core/select?q=drain");
+
+    // 통계에도 점검 상태를 남김
+    let req = Request::get(STATS_PATH).body(Body::empty()).unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(value["draining"], true);
+    assert!(value["update_drained_cnt"].as_u64().unwrap() >= 1);
+
+    // 점검이 끝나면 update를 다시 보냄
+    assert!(solr.set_update_drain(false));
+    let response = handle(update(), remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(
+        mock.next_request().await.body,
+        proc_xml::SAMPLE_XML.as_bytes()
+    );
+}
+
+#[tokio::test]
+async fn stream_body_update_test() {
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    // DB 없이 seed_id를 넣을 수 있도록 cache에 넣어둠
+    SEED_ID_CACHE
+        .put(
+            "stream-body.example.com".to_string(),
+            "e7531c15-2384-11ed-b560-42010a025a43".to_string(),
+        )
+        .await;
+    let xml = r#"<add><doc><field name="id">stream-1</field><field name="url">https://stream-body.example.com/a</field></doc></add>"#;
+    let encoded = utf8_percent_encode(xml, NON_ALPHANUMERIC).to_string();
+
+    // GET의 stream.body는 seed_id를 넣은 뒤 POST body로 보내고 나머지 파라미터는 유지함
+    let req = Request::get(format!(
+        "/solr/core/update?stream.body={}&commit=true",
+        encoded
+    ))
+    .body(Body::empty())
+    .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.method, hyper::Method::POST);
+    assert_eq!(captured.uri, "/solr/core/update?commit=true");
+    assert!(captured.headers[hyper::header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/xml"));
+    let body = std::str::from_utf8(&captured.body).unwrap();
+    assert!(
+        body.contains("e7531c15-2384-11ed-b560-42010a025a43"),
+        "{}",
+        body
+    );
+
+    // form으로 보낸 경우 나머지 form 파라미터는 query string으로 보냄
+    let req = Request::post("/solr/core/update")
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(Body::from(format!(
+            "stream.body={}&commitWithin=1000",
+            encoded
+        )))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/update?commitWithin=1000");
+    let body = std::str::from_utf8(&captured.body).unwrap();
+    assert!(body.starts_with("<add>"), "{}", body);
+    assert!(
+        body.contains("e7531c15-2384-11ed-b560-42010a025a43"),
+        "{}",
+        body
+    );
+
+    // stream.body가 없는 GET update는 받지 않음
+    let req = Request::get("/solr/core/update?commit=true")
+        .body(Body::empty())
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn spool_update_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let dir = std::env::temp_dir().join(format!("solr_proxy-spool-update-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let settings = test_settings(&format!(
+        "spool_threshold_bytes = 64\nspool_dir = \"{}\"",
+        dir.display()
+    ));
+    // DB 없이 seed_id를 넣을 수 있도록 cache에 넣어둠
+    SEED_ID_CACHE
+        .put(
+            "spool-update.example.com".to_string(),
+            "e7531c15-2384-11ed-b560-42010a025a43".to_string(),
+        )
+        .await;
+    let xml = r#"<add><doc><field name="id">spool-1</field><field name="url">https://spool-update.example.com/a</field></doc><doc><field name="id">spool-2</field><field name="url">https://spool-update.example.com/b</field></doc></add>"#;
+    assert!(xml.len() > 64);
+
+    // spool_threshold_bytes를 넘는 body는 임시 파일에 받은 뒤 seed_id를 넣어서 보내고 파일은 지움
+    let before = WORKING_CNT.lock().await.spool_cnt;
+    let req = Request::post("/solr/core/update")
+        .body(Body::from(xml))
+        .unwrap();
+    let response = handle_with(req, remote_ip, &solr, settings).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    let body = std::str::from_utf8(&captured.body).unwrap();
+    assert!(
+        body.contains("spool-1") && body.contains("spool-2"),
+        "{}",
+        body
+    );
+    assert_eq!(
+        body.matches("e7531c15-2384-11ed-b560-42010a025a43").count(),
+        2,
+        "{}",
+        body
+    );
+    assert!(WORKING_CNT.lock().await.spool_cnt > before);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn expect_header_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let body = r#"<add><doc><field name="id">expect-1</field><field name="seed_id">e7531c15-2384-11ed-b560-42010a025a43</field></doc></add>"#;
+
+    // body를 모두 받은 뒤 보내는 update에는 Expect를 넣지 않음
+    for uri in ["/solr/core/update", "/solr/core/update?proxy.enrich=false"] {
+        let req = Request::post(uri)
+            .header(hyper::header::EXPECT, "100-continue")
+            .body(Body::from(body))
+            .unwrap();
+        let response = handle(req, remote_ip, &solr).await.unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        let captured = mock.next_request().await;
+        assert!(
+            !captured.headers.contains_key(hyper::header::EXPECT),
+            "{}",
+            uri
+        );
+        assert_eq!(captured.body, body.as_bytes());
+    }
+
+    // 받는 대로 보내는 select는 그대로 전달함
+    let req = Request::post("/solr/core/select")
+        .header(hyper::header::EXPECT, "100-continue")
+        .body(Body::from("q=expect"))
+        .unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.headers[hyper::header::EXPECT], "100-continue");
+    assert_eq!(captured.body, "q=expect");
+}
+
+#[tokio::test]
+async fn admin_route_scope_test() {
+    let solr = Solr::new(
+        "http://127.0.0.1:1".parse().unwrap(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let request = |path: &str, scope: Option<RouteScope>| {
+        let mut req = Request::get(path).body(Body::empty()).unwrap();
+        if let Some(scope) = scope {
+            req.extensions_mut().insert(scope);
+        }
+        req
+    };
+
+    // admin으로 지정한 listener가 따로 있는 경우 다른 listener에서는 404
+    let proxy_only = Some(RouteScope {
+        admin_routes: false,
+        proxy_routes: true,
+    });
+    for path in [STATS_PATH, "/proxy/loglevel"] {
+        let response = handle(request(path, proxy_only), remote_ip, &solr)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND, "{}", path);
+    }
+
+    // admin listener, listener를 지정하지 않은 요청은 그대로 처리함
+    let admin_only = Some(RouteScope {
+        admin_routes: true,
+        proxy_routes: false,
+    });
+    for scope in [Some(RouteScope::default()), admin_only, None] {
+        let response = handle(request(STATS_PATH, scope), remote_ip, &solr)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+    }
+
+    // admin_port listener는 솔라로 보내는 요청을 받지 않음
+    for path in ["/solr/core/select?q=*:*", "/solr/core/update"] {
+        let response = handle(request(path, admin_only), remote_ip, &solr)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn error_kind_request_test() {
+    // q=fail인 경우 500 응답
+    let mut mock = mock::MockSolr::start_with(|req| {
+        let status = match req.uri.query() {
+            Some("q=fail") => hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            _ => hyper::StatusCode::OK,
+        };
+        Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .unwrap()
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let down_solr = Solr::new(
+        "http://127.0.0.1:1".parse().unwrap(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    // 응답이 request_timeout보다 늦는 솔라
+    let slow_mock =
+        mock::MockSolr::start_delayed(Duration::from_millis(500), |_| Response::new(Body::empty()))
+            .await;
+    let slow_solr = Solr::new(
+        slow_mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig {
+            request_timeout: Duration::from_millis(50),
+            ..Default::default()
+        },
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    // 최근 조회에 실패한 seed_host는 DB에 요청하지 않고 DbError로 실패함
+    let failed_host = "error-kind-db.example.com";
+    SEED_LOOKUP_FAILURES
+        .record_failure(
+            &settings().default_collection.cache_key(failed_host),
+            Instant::now(),
+        )
+        .await;
+    let db_xml = format!(
+        r#"<add><doc><field name="id">db-1</field><field name="url">https://{}/news/1</field></doc></add>"#,
+        failed_host
+    );
+
+    let cases = [
+        (
+            Request::post("/solr/core/update")
+                .body(Body::from("<add><doc><field name=\"id\">1</doc></add>")),
+            &solr,
+            ErrorKind::ParseError,
+            hyper::StatusCode::OK,
+        ),
+        (
+            Request::post("/solr/core/update").body(Body::from(db_xml)),
+            &solr,
+            ErrorKind::DbError,
+            hyper::StatusCode::SERVICE_UNAVAILABLE,
+        ),
+        (
+            Request::get("/solr/core/select?q=*:*").body(Body::empty()),
+            &slow_solr,
+            ErrorKind::Timeout,
+            hyper::StatusCode::GATEWAY_TIMEOUT,
+        ),
+        (
+            Request::get("/solr/core/select?q=*:*").body(Body::empty()),
+            &down_solr,
+            ErrorKind::SolrConnectError,
+            hyper::StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+        (
+            Request::get("/solr/core/select?q=fail").body(Body::empty()),
+            &solr,
+            ErrorKind::SolrStatusError,
+            hyper::StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+        (
+            Request::get("/solr/core/unknown").body(Body::empty()),
+            &solr,
+            ErrorKind::UnknownPath,
+            hyper::StatusCode::NOT_FOUND,
+        ),
+        (
+            Request::delete("/solr/core/select").body(Body::empty()),
+            &solr,
+            ErrorKind::Rejected,
+            hyper::StatusCode::METHOD_NOT_ALLOWED,
+        ),
+    ];
+    for (req, solr, kind, status) in cases {
+        let before = WORKING_CNT.lock().await.error_kind_cnt.get(kind);
+        let response = handle(req.unwrap(), remote_ip, solr).await.unwrap();
+        assert_eq!(response.status(), status, "{:?}", kind);
+        // 다른 테스트와 같은 카운터를 사용하므로 증가했는지만 확인함
+        let after = WORKING_CNT.lock().await.error_kind_cnt.get(kind);
+        assert!(after > before, "{:?}", kind);
+    }
+    // 파싱 에러, 5xx는 솔라로 보낸 요청
+    mock.next_request().await;
+    mock.next_request().await;
+
+    let req = Request::get(STATS_PATH).body(Body::empty()).unwrap();
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    for name in [
+        "parse",
+        "db",
+        "timeout",
+        "solr_connect",
+        "solr_status",
+        "unknown_path",
+        "rejected",
+    ] {
+        assert!(stats["errors"][name].as_u64().unwrap() >= 1, "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn split_update_test() {
+    // 두번째 chunk(id 3, 4)는 거부하고, 그 외에는 받은 chunk의 첫 id를 응답함
+    let mut mock = mock::MockSolr::start_with(|req| {
+        let body = String::from_utf8_lossy(&req.body);
+        let first_id = body
+            .split("<field name=\"id\">")
+            .nth(1)
+            .and_then(|rest| rest.split('<').next())
+            .unwrap_or_default()
+            .to_string();
+        let status = match first_id.as_str() {
+            "3" if req.uri.query() == Some("fail=middle") => hyper::StatusCode::BAD_REQUEST,
+            _ => hyper::StatusCode::OK,
+        };
+        Response::builder()
+            .status(status)
+            .body(Body::from(first_id))
+            .unwrap()
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let ctx = RequestContext {
+        request_id: "split-test".to_string(),
+        remote_ip: "10.0.0.1:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+    let xml = br#"<add><doc><field name="id">1</field></doc><doc><field name="id">2</field></doc><doc><field name="id">3</field></doc><doc><field name="id">4</field></doc><doc><field name="id">5</field></doc></add>"#;
+    let limit = proc_xml::SplitLimit {
+        max_docs: 2,
+        max_bytes: 0,
+    };
+    let chunks = || {
+        let (docs, envelope) =
+            proc_xml::read_xml_envelope(xml, &proc_xml::ReadLimit::default()).unwrap();
+        let WriteOk::Split(chunks, _, _) = proc_xml::write_xml_split(
+            docs,
+            &envelope,
+            &limit,
+            crate::settings::SeedIdField::default(),
+        )
+        .unwrap() else {
+            panic!("not split");
+        };
+        assert_eq!(chunks.len(), 3);
+        chunks
+    };
+    let parts = |query: &str| {
+        Request::post(format!("/solr/core/update?{}", query))
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    };
+
+    // 모두 성공하면 순서대로 보내고 마지막 chunk의 응답을 돌려줌
+    let before = WORKING_CNT.lock().await.clone();
+    let response = send_update_chunks(
+        parts("fail=none"),
+        chunks(),
+        &ctx,
+        "/solr/core/update",
+        &solr,
+        &settings(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "5");
+    for expected in ["1", "3", "5"] {
+        let captured = mock.next_request().await;
+        assert_eq!(captured.uri.query(), Some("fail=none"));
+        assert!(String::from_utf8_lossy(&captured.body)
+            .starts_with(&format!("<add><doc><field name=\"id\">{}<", expected)));
+    }
+
+    // 가운데 chunk를 거부하면 그 응답을 돌려주고 남은 chunk는 보내지 않음
+    let response = send_update_chunks(
+        parts("fail=middle"),
+        chunks(),
+        &ctx,
+        "/solr/core/update",
+        &solr,
+        &settings(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "3");
+    mock.next_request().await;
+    mock.next_request().await;
+    assert!(mock.requests.try_recv().is_err());
+
+    // 다른 테스트와 같은 카운터를 사용하므로 증가한 값 이상인지 확인함
+    let after = WORKING_CNT.lock().await.clone();
+    assert!(after.update_split_cnt >= before.update_split_cnt + 2);
+    assert!(after.update_split_chunk_cnt >= before.update_split_chunk_cnt + 6);
+    assert!(after.update_split_chunk_rejected_cnt > before.update_split_chunk_rejected_cnt);
+}
+
+#[tokio::test]
+async fn select_proxy_param_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let select = |query: &str| {
+        Request::get(format!("/solr/core/select?{}", query))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // proxy 파라미터만 제거하고 나머지는 받은 encoding 그대로 솔라에 보냄
+    let req = select("q=a+b&proxy.cache=refresh&fq=c%20d&fq=e&proxy._=1700000000");
+    let response = handle(req, remote_ip, &solr).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri.query(), Some("q=a+b&fq=c%20d&fq=e"));
+
+    // proxy 파라미터만 있으면 ?도 보내지 않음
+    let response = handle(select("proxy.enrich=false"), remote_ip, &solr)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri.to_string(), "/solr/core/select");
+
+    // 잘못된 값은 솔라에 보내지 않고 400 응답
+    let response = handle(select("q=*:*&proxy.cache=never"), remote_ip, &solr)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+    assert!(mock.requests.try_recv().is_err());
+
+    // form으로 보낸 select도 최대값을 적용하고, form에 있는 파라미터는 기본값을 추가하지 않음
+    let settings = test_settings(
+        r#"
+[select_default_params]
+rows = "10"
+timeAllowed = "5000"
+
+[select_param_caps]
+rows = 1000
+"#,
+    );
+    let req = Request::post("/solr/core/select")
+        .header(
+            hyper::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(Body::from("q=*:*&rows=5000"))
+        .unwrap();
+    let response = handle_with(req, remote_ip, &solr, settings).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(
+        response.headers()[select_param::X_PROXY_PARAM_CLAMPED],
+        "rows=5000->1000"
+    );
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/select?timeAllowed=5000");
+    assert_eq!(&captured.body[..], b"q=*:*&rows=1000");
+}
+
+#[tokio::test]
+async fn proxy_error_status_test() {
+    use hyper::StatusCode;
+    use status_cnt::StatusBucket;
+
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let down_solr = Solr::new(
+        "http://127.0.0.1:1".parse().unwrap(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    // 모두 솔라의 응답 없이 proxy가 만든 에러 응답
+    let cases = [
+        (
+            Request::get("/solr/core/unknown").body(Body::empty()),
+            &solr,
+            StatusCode::NOT_FOUND,
+            PathClass::Proxy,
+        ),
+        (
+            Request::post("/solr/core/update?proxy.cache=never")
+                .body(Body::from(proc_xml::SAMPLE_XML)),
+            &solr,
+            StatusCode::BAD_REQUEST,
+            PathClass::Update,
+        ),
+        (
+            Request::post("/solr/core/update")
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .body(Body::from("{}")),
+            &solr,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PathClass::Update,
+        ),
+        (
+            Request::delete("/solr/core/select").body(Body::empty()),
+            &solr,
+            StatusCode::METHOD_NOT_ALLOWED,
+            PathClass::Select,
+        ),
+        (
+            Request::get("/solr/core/select?q=*:*").body(Body::empty()),
+            &down_solr,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            PathClass::Select,
+        ),
+    ];
+    for (req, solr, status, class) in cases {
+        let bucket = StatusBucket::from_status(status);
+        let before = WORKING_CNT.lock().await.status_cnt.get(class, bucket);
+        let response = handle(req.unwrap(), remote_ip, solr).await.unwrap();
+        assert_eq!(response.status(), status);
+        // 다른 테스트와 같은 카운터를 사용하므로 증가했는지만 확인함
+        let after = WORKING_CNT.lock().await.status_cnt.get(class, bucket);
+        assert!(after > before, "{}", status);
+    }
+    assert!(mock.requests.try_recv().is_err());
+
+    // 요청 크기 제한, 요청 수 제한, 솔라 응답 시간 제한도 proxy가 만든 에러 응답으로 셈
+    let slow_mock = mock::MockSolr::start_delayed(Duration::from_millis(500), |_| {
+        Response::new(Body::from("OK"))
+    })
+    .await;
+    let settings = test_settings("max_body_bytes = 16\nsolr_request_timeout_ms = 50");
+    assert_eq!(settings.solr_request_timeout, Duration::from_millis(50));
+    let slow_solr = Solr::new(slow_mock.url.clone(), false, settings.solr_client_config());
+    // 다른 테스트와 함께 쓰지 않는 limiter. remote_ip는 제한하지 않고, limited_ip는 두번째 요청부터 제한됨
+    let limiter = RateLimiter::new(0.001, 1f64, vec![remote_ip.ip()]);
+    let limited_ip: SocketAddr = "10.0.0.9:5000".parse().unwrap();
+    let cases = [
+        (
+            Request::post("/solr/core/update").body(Body::from(proc_xml::SAMPLE_XML)),
+            remote_ip,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            PathClass::Update,
+        ),
+        (
+            Request::get("/solr/core/select?q=*:*").body(Body::empty()),
+            remote_ip,
+            StatusCode::GATEWAY_TIMEOUT,
+            PathClass::Select,
+        ),
+        (
+            Request::get("/solr/core/select?q=*:*").body(Body::empty()),
+            limited_ip,
+            StatusCode::TOO_MANY_REQUESTS,
+            PathClass::Select,
+        ),
+    ];
+    assert!(limiter.check(limited_ip.ip(), Instant::now()).await);
+    for (req, remote_ip, status, class) in cases {
+        let bucket = StatusBucket::from_status(status);
+        let before = WORKING_CNT.lock().await.status_cnt.get(class, bucket);
+        let response = handle_with_limiter(
+            req.unwrap(),
+            remote_ip,
+            &slow_solr,
+            settings.clone(),
+            &limiter,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), status);
+        let after = WORKING_CNT.lock().await.status_cnt.get(class, bucket);
+        assert!(after > before, "{}", status);
+    }
+}
+
+#[tokio::test]
+async fn content_type_request_test() {
+    use hyper::header::CONTENT_TYPE;
+    use hyper::StatusCode;
+
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let update = |content_type: Option<&'static str>| {
+        let mut builder = Request::post("/solr/core/update");
+        if let Some(content_type) = content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+        builder.body(Body::from("<commit/>")).unwrap()
+    };
+    let before = WORKING_CNT.lock().await.clone();
+
+    // xml이 아닌 body는 솔라로 보내지 않고 415로 응답함
+    for content_type in ["multipart/form-data; boundary=x", "text/plain"] {
+        let response = handle(update(Some(content_type)), remote_ip, &solr)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains(content_type), "{}", body);
+    }
+    assert!(mock.requests.try_recv().is_err());
+    let after = WORKING_CNT.lock().await.clone();
+    assert!(after.update_content_type_rejected_cnt >= before.update_content_type_rejected_cnt + 2);
+
+    // xml과 Content-Type이 없는 요청은 솔라로 보냄
+    for content_type in [Some("text/xml; charset=utf-8"), None] {
+        let response = handle(update(content_type), remote_ip, &solr)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(mock.next_request().await.body, "<commit/>");
+    }
+}
+
+#[tokio::test]
+async fn passthrough_update_ip_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let settings = test_settings(
+        r#"
+        passthrough_unknown_paths = true
+        update_allowed_ips = ["10.0.0.0/8"]
+        "#,
+    );
+    let request = |path: &str| {
+        Request::post(path)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"[{"id":"1"}]"#))
+            .unwrap()
+    };
+    let outside: SocketAddr = "192.168.0.1:5000".parse().unwrap();
+    let inside: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+
+    // 허용하지 않은 ip의 update handler 요청은 솔라로 보내지 않음
+    let response = handle_with(
+        request("/solr/core/update/json"),
+        outside,
+        &solr,
+        settings.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::FORBIDDEN);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("IP_NOT_ALLOWED: update"));
+
+    // update가 아닌 passthrough path는 그대로 보냄
+    let response = handle_with(
+        request("/solr/core/schema"),
+        outside,
+        &solr,
+        settings.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.uri, "/solr/core/schema");
+
+    // 허용한 ip는 update handler도 그대로 보냄
+    let response = handle_with(request("/solr/core/update/json"), inside, &solr, settings)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.uri, "/solr/core/update/json");
+}
+
+#[tokio::test]
+async fn passthrough_update_drain_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    )
+    .with_update_drain(true);
+    let settings = test_settings("passthrough_unknown_paths = true");
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let request = |path: &str| {
+        Request::post(path)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"[{"id":"1"}]"#))
+            .unwrap()
+    };
+
+    // 점검 중에는 update handler도 솔라로 보내지 않음
+    let before = WORKING_CNT.lock().await.update_drained_cnt;
+    let response = handle_with(
+        request("/solr/core/update/json"),
+        remote_ip,
+        &solr,
+        settings.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(hyper::header::RETRY_AFTER));
+    assert!(WORKING_CNT.lock().await.update_drained_cnt > before);
+
+    // update가 아닌 passthrough path는 그대로 보냄
+    let response = handle_with(
+        request("/solr/core/schema"),
+        remote_ip,
+        &solr,
+        settings.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.uri, "/solr/core/schema");
+
+    // 점검이 끝나면 다시 보냄
+    assert!(solr.set_update_drain(false));
+    let response = handle_with(
+        request("/solr/core/update/extract"),
+        remote_ip,
+        &solr,
+        settings,
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.uri, "/solr/core/update/extract");
+}
+
+#[tokio::test]
+async fn buffered_chunked_update_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let settings = test_settings("max_buffered_bytes = 1000");
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let chunked_update = || {
+        let (sender, body) = Body::channel();
+        let req = Request::post("/solr/core/update?proxy.enrich=false")
+            .header(hyper::header::CONTENT_TYPE, "application/xml")
+            .body(body)
+            .unwrap();
+        (sender, req)
+    };
+    let chunk = || Bytes::from(format!("<add>{}", " ".repeat(595)));
+
+    // Content-Length가 없는 두 요청이 동시에 들어오면 받는 동안 합이 제한을 넘는 요청은 503
+    let (mut first_sender, first_req) = chunked_update();
+    let first = handle_with(first_req, remote_ip, &solr, settings.clone());
+    let rejected = async {
+        first_sender.send_data(chunk()).await.unwrap();
+        while BUFFERED_BYTES.current() < 600 {
+            tokio::task::yield_now().await;
+        }
+
+        let before = WORKING_CNT.lock().await.buffered_rejected_cnt;
+        let (mut second_sender, second_req) = chunked_update();
+        let (response, _) = tokio::join!(
+            handle_with(second_req, remote_ip, &solr, settings.clone()),
+            second_sender.send_data(chunk())
+        );
+        let response = response.unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("MAX_BUFFERED_BYTES_EXCEEDED"));
+        assert!(WORKING_CNT.lock().await.buffered_rejected_cnt > before);
+
+        // 먼저 받은 요청은 나머지를 받아 그대로 솔라로 보냄
+        first_sender
+            .send_data(Bytes::from_static(b"</add>"))
+            .await
+            .unwrap();
+        drop(first_sender);
+    };
+    let (response, ()) = tokio::join!(first, rejected);
+    assert_eq!(response.unwrap().status(), hyper::StatusCode::OK);
+    assert_eq!(mock.next_request().await.body.len(), 606);
+}
+
+#[tokio::test]
+async fn commit_policy_update_test() {
+    let mut mock = mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let settings = test_settings(
+        r#"
+force_commit_within_ms = 5000
+passthrough_unknown_paths = true
+update_content_types = ["application/xml", "application/json"]
+"#,
+    );
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    let post = |uri: &str, content_type: &str, body: &'static str| {
+        Request::post(uri)
+            .header(hyper::header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // 파싱하지 않는 update도 query string과 body의 commit 요청을 제거함. key를 encoding해도 찾음
+    let before = WORKING_CNT.lock().await.client_commit_stripped_cnt;
+    let req = post(
+        "/solr/core/update?proxy.enrich=false&%63ommit=true&commitWithin=1",
+        "application/xml",
+        r#"<update><add commitWithin="1"><doc><field name="id">1</field></doc></add><commit/></update>"#,
+    );
+    let response = handle_with(req, remote_ip, &solr, settings.clone())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/update?commitWithin=5000");
+    assert_eq!(
+        captured.body,
+        r#"<update><add><doc><field name="id">1</field></doc></add></update>"#
+    );
+    assert!(WORKING_CNT.lock().await.client_commit_stripped_cnt >= before + 2);
+
+    // xml이 아닌 body는 바꾸지 않음
+    let req = post(
+        "/solr/core/update?proxy.enrich=false",
+        "application/json",
+        r#"{"commit":{}}"#,
+    );
+    handle_with(req, remote_ip, &solr, settings.clone())
+        .await
+        .unwrap();
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/update?commitWithin=5000");
+    assert_eq!(captured.body, r#"{"commit":{}}"#);
+
+    // 그대로 보내는 update handler도 query string의 commit 요청을 제거함
+    let req = post(
+        "/solr/core/update/json?softCommit=true&optimize=true&wt=json",
+        "application/json",
+        r#"[{"id":"1"}]"#,
+    );
+    handle_with(req, remote_ip, &solr, settings.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.next_request().await.uri,
+        "/solr/core/update/json?wt=json&commitWithin=5000"
+    );
+
+    // update handler가 아닌 path는 바꾸지 않음
+    let req = post("/solr/core/schema?commit=true", "application/json", "{}");
+    handle_with(req, remote_ip, &solr, settings.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.next_request().await.uri,
+        "/solr/core/schema?commit=true"
+    );
+}
+
+#[tokio::test]
+async fn split_update_commit_test() {
+    // 받은 chunk의 doc 수를 응답함
+    let mut mock = mock::MockSolr::start_with(|req| {
+        let doc_cnt = String::from_utf8_lossy(&req.body).matches("<doc>").count();
+        Response::new(Body::from(doc_cnt.to_string()))
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let settings = test_settings("split_threshold_docs = 2");
+    let remote_ip: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+    // DB 없이 seed_id를 넣을 수 있도록 cache에 넣어둠
+    SEED_ID_CACHE
+        .put(
+            "split-commit.example.com".to_string(),
+            "9b6c1a2e-4f1d-11ee-be56-0242ac120002".to_string(),
+        )
+        .await;
+    let mut xml = String::from("<add>");
+    for id in 1..=5 {
+        xml.push_str(&format!(
+            r#"<doc><field name="id">{}</field><field name="url">https://split-commit.example.com/{}</field></doc>"#,
+            id, id
+        ));
+    }
+    xml.push_str("</add>");
+    let req = Request::post("/solr/core/update?commit=true&wt=json&softCommit=true")
+        .header(hyper::header::CONTENT_TYPE, "application/xml")
+        .body(Body::from(xml))
+        .unwrap();
+
+    // 마지막 chunk만 commit을 요청해서 모든 doc을 보낸 뒤에 한 번만 commit함
+    let response = handle_with(req, remote_ip, &solr, settings).await.unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "1");
+    for (query, doc_cnt) in [
+        ("wt=json", 2),
+        ("wt=json", 2),
+        ("commit=true&wt=json&softCommit=true", 1),
+    ] {
+        let captured = mock.next_request().await;
+        assert_eq!(captured.uri.query(), Some(query));
+        assert_eq!(
+            String::from_utf8_lossy(&captured.body)
+                .matches("<doc>")
+                .count(),
+            doc_cnt
+        );
+    }
+}
+
+#[tokio::test]
+async fn join_listeners_test() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    type Listener<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + 'a>>;
+
+    // 하나가 에러로 끝나면 stop으로 나머지에 종료를 요청하고, 나머지가 끝날 때까지 기다림
+    let (stop_send, stop_recv) = watch::channel(false);
+    let finished = AtomicBool::new(false);
+    let failing: Listener = Box::pin(async { Err("BIND_LOST".to_string()) });
+    let draining: Listener = Box::pin(async {
+        let mut stop_recv = stop_recv;
+        stop_recv.wait_for(|stop| *stop).await.unwrap();
+        // 처리 중인 요청을 마치는 동안 drop하지 않음
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        finished.store(true, Ordering::Relaxed);
+        Ok(())
+    });
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        join_listeners(vec![failing, draining], || async {
+            stop_send.send(true).unwrap();
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, Err("BIND_LOST".to_string()));
+    assert!(finished.load(Ordering::Relaxed));
+
+    // 모두 정상 종료한 경우
+    let ok: Vec<Listener> = vec![Box::pin(async { Ok(()) }), Box::pin(async { Ok(()) })];
+    assert_eq!(
+        join_listeners(ok, || async { panic!("stop without error") }).await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn serve_listener_tls_test() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+
+    let dir =
+        std::env::temp_dir().join(format!("solr_proxy_serve_tls_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_path = dir.join("cert.pem").to_string_lossy().into_owned();
+    let key_path = dir.join("key.pem").to_string_lossy().into_owned();
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+    std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+    let tls: &'static tls::TlsConfig = Box::leak(Box::new(
+        tls::TlsConfig::load(&cert_path, &key_path).unwrap(),
+    ));
+
+    let mut mock = mock::MockSolr::start().await;
+    let solr: &'static Solr = Box::leak(Box::new(Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    )));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let bound = listen::BoundListener {
+        addr,
+        scope: listen::RouteScope::default(),
+        listener,
+    };
+    // 종료 요청을 보내면 DRAIN이 시작되어 다른 테스트에 영향을 주므로 server task를 abort함
+    let (stop_send, stop_recv) = watch::channel(None);
+    let server = tokio::spawn(serve_listener(bound, stop_recv, Some(tls), solr));
+
+    let mut roots = RootCertStore::empty();
+    roots
+        .add(&Certificate(cert.serialize_der().unwrap()))
+        .unwrap();
+    let client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+        .unwrap();
+    stream
+        .write_all(
+            b"GET /solr/core/select?q=1 HTTP/1.1\r\nHost: localhost\r\nX-Forwarded-Proto: http\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    // TLS listener에서 받은 요청은 솔라에 https로 전달함
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/select?q=1");
+    assert_eq!(captured.headers["x-forwarded-proto"], "https");
+
+    server.abort();
+    let _ = server.await;
+    drop(stop_send);
+    let _ = std::fs::remove_dir_all(&dir);
+}
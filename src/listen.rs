@@ -0,0 +1,222 @@
+use crate::BoxedError;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use tokio::net::TcpListener;
+
+/// listen 설정이 없는 경우 사용하는 주소
+pub const DEFAULT_LISTEN: &str = "auto:3000";
+
+/// 서버가 받을 주소 하나. "<ip>:<port>" 또는 자동으로 찾은 ip를 사용하는 "auto:<port>"
+/// <br>
+/// 뒤에 " admin"을 붙이면 관리용 endpoint를 받는 listener로 지정함
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenAddr {
+    /// None인 경우 get_local_ip로 찾은 ip
+    pub ip: Option<IpAddr>,
+    pub port: u16,
+    pub admin: bool,
+    /// admin_port로 만든 listener. 관리용 endpoint만 받음
+    pub admin_only: bool,
+}
+
+impl ListenAddr {
+    /// admin_port 설정으로 만드는 관리용 listener
+    pub fn admin_port(ip: IpAddr, port: u16) -> Self {
+        Self {
+            ip: Some(ip),
+            port,
+            admin: true,
+            admin_only: true,
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.ip.is_none()
+    }
+
+    /// 실제로 bind할 주소. auto인 경우 local_ip를 사용함
+    pub fn resolve(&self, local_ip: Option<IpAddr>) -> Option<SocketAddr> {
+        Some(SocketAddr::new(self.ip.or(local_ip)?, self.port))
+    }
+}
+
+impl FromStr for ListenAddr {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = value.split_whitespace();
+        let addr = words.next().ok_or(())?;
+        let admin = match (words.next(), words.next()) {
+            (None, _) => false,
+            (Some(flag), None) if flag.eq_ignore_ascii_case("admin") => true,
+            _ => return Err(()),
+        };
+        let (ip, port) = match addr.strip_prefix("auto:") {
+            Some(port) => (None, port.parse().map_err(|_| ())?),
+            None => {
+                let addr = SocketAddr::from_str(addr).map_err(|_| ())?;
+                (Some(addr.ip()), addr.port())
+            }
+        };
+        Ok(Self {
+            ip,
+            port,
+            admin,
+            admin_only: false,
+        })
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ip {
+            Some(ip) => write!(f, "{}", SocketAddr::new(ip, self.port))?,
+            None => write!(f, "auto:{}", self.port)?,
+        }
+        if self.admin_only {
+            write!(f, " admin only")?;
+        } else if self.admin {
+            write!(f, " admin")?;
+        }
+        Ok(())
+    }
+}
+
+/// listener마다 받을 수 있는 route. 요청의 extension으로 handle에 넘김
+/// <br>
+/// extension이 없는 요청(테스트 등)은 모든 route를 받음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteScope {
+    /// /proxy/stats, /proxy/reload 등 관리용 endpoint를 받는지 여부
+    pub admin_routes: bool,
+    /// select, update 등 솔라로 보내는 요청을 받는지 여부
+    pub proxy_routes: bool,
+}
+
+impl Default for RouteScope {
+    fn default() -> Self {
+        Self {
+            admin_routes: true,
+            proxy_routes: true,
+        }
+    }
+}
+
+impl RouteScope {
+    /// admin으로 지정한 listener가 있으면 그 listener에서만 관리용 endpoint를 받음
+    /// <br>
+    /// admin_port로 만든 listener는 관리용 endpoint만 받음
+    pub fn for_listener(listen: &ListenAddr, all: &[ListenAddr]) -> Self {
+        Self {
+            admin_routes: listen.admin || !all.iter().any(|listen| listen.admin),
+            proxy_routes: !listen.admin_only,
+        }
+    }
+}
+
+/// bind한 listener
+pub struct BoundListener {
+    pub addr: SocketAddr,
+    pub scope: RouteScope,
+    pub listener: TcpListener,
+}
+
+/// 모든 주소를 bind함. 하나라도 실패하면 실패한 주소를 포함한 에러
+pub async fn bind_all(
+    listen: &[ListenAddr],
+    local_ip: Option<IpAddr>,
+) -> Result<Vec<BoundListener>, BoxedError> {
+    let mut bound = Vec::with_capacity(listen.len());
+    for listen_addr in listen {
+        let addr = listen_addr
+            .resolve(local_ip)
+            .ok_or_else(|| format!("SERVER_BIND_FAIL: {}, local ip not found", listen_addr))?;
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("SERVER_BIND_FAIL: {}, {}", addr, e))?;
+        bound.push(BoundListener {
+            addr,
+            scope: RouteScope::for_listener(listen_addr, listen),
+            listener,
+        });
+    }
+    Ok(bound)
+}
+
+#[test]
+fn listen_addr_parse_test() {
+    let listen: ListenAddr = "auto:3000".parse().unwrap();
+    assert!(listen.is_auto());
+    assert_eq!(listen.port, 3000);
+    assert_eq!(
+        listen.resolve(Some("10.0.0.7".parse().unwrap())),
+        Some("10.0.0.7:3000".parse().unwrap())
+    );
+    assert_eq!(listen.resolve(None), None);
+
+    let listen: ListenAddr = "127.0.0.1:3001 admin".parse().unwrap();
+    assert_eq!(listen.ip, Some("127.0.0.1".parse().unwrap()));
+    assert!(listen.admin);
+    assert_eq!(listen.to_string(), "127.0.0.1:3001 admin");
+    let listen: ListenAddr = "[::1]:3002".parse().unwrap();
+    assert_eq!(listen.to_string(), "[::1]:3002");
+    assert_eq!(
+        listen.resolve(Some("10.0.0.7".parse().unwrap())),
+        Some("[::1]:3002".parse().unwrap())
+    );
+
+    for invalid in [
+        "",
+        "3000",
+        "127.0.0.1",
+        "auto:",
+        "auto:70000",
+        "localhost:3000",
+        "127.0.0.1:3000 public",
+        "127.0.0.1:3000 admin admin",
+    ] {
+        assert!(invalid.parse::<ListenAddr>().is_err(), "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn bind_all_test() {
+    let listen: Vec<ListenAddr> = ["127.0.0.1:0", "127.0.0.1:0 admin"]
+        .iter()
+        .map(|value| value.parse().unwrap())
+        .collect();
+    let bound = bind_all(&listen, None).await.unwrap();
+    assert_eq!(bound.len(), 2);
+    assert!(!bound[0].scope.admin_routes);
+    assert!(bound[1].scope.admin_routes);
+
+    // admin으로 지정한 listener가 없으면 모든 listener에서 관리용 endpoint를 받음
+    let plain: ListenAddr = "auto:3000".parse().unwrap();
+    assert_eq!(
+        RouteScope::for_listener(&plain, &[plain]),
+        RouteScope::default()
+    );
+
+    // admin_port listener가 있으면 다른 listener는 관리용 endpoint를 받지 않음
+    let admin_port = ListenAddr::admin_port("127.0.0.1".parse().unwrap(), 3001);
+    assert_eq!(admin_port.to_string(), "127.0.0.1:3001 admin only");
+    let all = [plain, admin_port];
+    let scope = RouteScope::for_listener(&plain, &all);
+    assert!(!scope.admin_routes && scope.proxy_routes);
+    let scope = RouteScope::for_listener(&admin_port, &all);
+    assert!(scope.admin_routes && !scope.proxy_routes);
+
+    // 이미 사용 중인 주소가 있으면 그 주소를 포함한 에러
+    let used = bound[0].listener.local_addr().unwrap();
+    let listen = [listen[1], used.to_string().parse().unwrap()];
+    let err = bind_all(&listen, None).await.err().unwrap().to_string();
+    assert!(
+        err.starts_with(&format!("SERVER_BIND_FAIL: {}", used)),
+        "{}",
+        err
+    );
+
+    let err = bind_all(&[plain], None).await.err().unwrap().to_string();
+    assert!(err.contains("auto:3000"), "{}", err);
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Local};
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::roll::Roll;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::LogFile;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// log 파일을 나누는 기준
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRoll {
+    /// log_roll_size_bytes를 넘으면 나눔. 나눈 파일은 숫자를 붙임
+    #[default]
+    Size,
+    /// 날짜가 바뀌면 나눔. 나눈 파일은 날짜를 붙임
+    Daily,
+    /// 시간이 바뀌면 나눔. 나눈 파일은 날짜와 시간을 붙임
+    Hourly,
+}
+
+impl LogRoll {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "size" => Some(Self::Size),
+            "daily" => Some(Self::Daily),
+            "hourly" => Some(Self::Hourly),
+            _ => None,
+        }
+    }
+
+    /// 나눈 파일 이름에 붙이는 기간 형식. 이름순으로 정렬하면 시간순이 됨
+    fn period_format(self) -> Option<&'static str> {
+        match self {
+            Self::Size => None,
+            Self::Daily => Some("%Y-%m-%d"),
+            Self::Hourly => Some("%Y-%m-%d-%H"),
+        }
+    }
+}
+
+/// log 파일을 나누는 설정
+#[derive(Debug, Clone, Copy)]
+pub struct LogRollConfig {
+    pub roll: LogRoll,
+    pub size_bytes: u64,
+    /// 나눈 파일을 남길 최대 개수
+    pub keep_count: u32,
+}
+
+impl Default for LogRollConfig {
+    fn default() -> Self {
+        Self {
+            roll: LogRoll::Size,
+            size_bytes: 500_0000, // 대략 5MB
+            keep_count: 5,
+        }
+    }
+}
+
+impl LogRollConfig {
+    /// path의 log 파일에 사용할 RollingFileAppender policy
+    pub fn policy(&self, path: &str) -> Result<CompoundPolicy, Box<dyn Error + Send + Sync>> {
+        let Some(format) = self.roll.period_format() else {
+            let fixed_window_roller =
+                FixedWindowRoller::builder().build(&format!("{}.{{}}", path), self.keep_count)?;
+            return Ok(CompoundPolicy::new(
+                Box::new(SizeTrigger::new(self.size_bytes)),
+                Box::new(fixed_window_roller),
+            ));
+        };
+
+        let state = Arc::new(Mutex::new(PeriodState::default()));
+        Ok(CompoundPolicy::new(
+            Box::new(TimeTrigger {
+                format,
+                state: state.clone(),
+            }),
+            Box::new(DateRoller {
+                state,
+                keep_count: self.keep_count,
+            }),
+        ))
+    }
+}
+
+/// TimeTrigger와 DateRoller가 함께 사용하는, 현재 파일의 기간
+#[derive(Debug, Default)]
+struct PeriodState {
+    /// 현재 파일에 기록중인 기간
+    current: Option<String>,
+    /// 나눠야 하는 이전 기간. DateRoller에서 파일 이름에 사용함
+    rolled: Option<String>,
+}
+
+/// 현재 시간의 기간이 파일을 만든 기간과 다르면 파일을 나눔
+/// <br>
+/// log4rs는 기록한 뒤 trigger를 확인하므로 기간이 바뀐 뒤 첫 줄은 이전 파일에 남음
+#[derive(Debug)]
+struct TimeTrigger {
+    format: &'static str,
+    state: Arc<Mutex<PeriodState>>,
+}
+
+impl TimeTrigger {
+    fn check(&self, path: &Path, now: DateTime<Local>) -> bool {
+        let now = now.format(self.format).to_string();
+        let mut state = self.state.lock().unwrap();
+        let current = state
+            .current
+            .get_or_insert_with(|| file_period(path, self.format).unwrap_or_else(|| now.clone()));
+        if *current == now {
+            return false;
+        }
+
+        let rolled = std::mem::replace(current, now);
+        state.rolled = Some(rolled);
+        true
+    }
+}
+
+impl Trigger for TimeTrigger {
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        Ok(self.check(file.path(), Local::now()))
+    }
+}
+
+/// 파일을 만든 시간의 기간. 만든 시간을 알 수 없는 파일시스템인 경우 None
+fn file_period(path: &Path, format: &str) -> Option<String> {
+    let created = fs::metadata(path).ok()?.created().ok()?;
+    Some(DateTime::<Local>::from(created).format(format).to_string())
+}
+
+/// 파일 이름에 이전 기간을 붙이고, keep_count를 넘는 오래된 파일은 삭제함
+#[derive(Debug)]
+struct DateRoller {
+    state: Arc<Mutex<PeriodState>>,
+    keep_count: u32,
+}
+
+impl Roll for DateRoller {
+    fn roll(&self, file: &Path) -> anyhow::Result<()> {
+        let period = self.state.lock().unwrap().rolled.take();
+        let period = period.unwrap_or_else(|| Local::now().format("%Y-%m-%d-%H").to_string());
+
+        fs::rename(file, rolled_path(file, &period))?;
+        remove_old_files(file, self.keep_count)?;
+        Ok(())
+    }
+}
+
+/// 같은 기간의 파일이 이미 있는 경우 뒤에 숫자를 붙임
+fn rolled_path(file: &Path, period: &str) -> PathBuf {
+    let base = format!("{}.{}", file.display(), period);
+    let mut path = PathBuf::from(&base);
+    let mut i = 1;
+    while path.exists() {
+        path = PathBuf::from(format!("{}.{}", base, i));
+        i += 1;
+    }
+    path
+}
+
+fn remove_old_files(file: &Path, keep_count: u32) -> std::io::Result<()> {
+    let (Some(dir), Some(file_name)) = (file.parent(), file.file_name()) else {
+        return Ok(());
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let prefix = format!("{}.", file_name.to_string_lossy());
+
+    let mut rolled: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    rolled.sort();
+
+    let remove_cnt = rolled.len().saturating_sub(keep_count as usize);
+    for path in &rolled[..remove_cnt] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn time_roll_test() {
+    use chrono::Duration;
+
+    let dir = std::env::temp_dir().join(format!("solr_proxy_log_roll_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("solr_proxy.log");
+
+    let state = Arc::new(Mutex::new(PeriodState::default()));
+    let trigger = TimeTrigger {
+        format: LogRoll::Daily.period_format().unwrap(),
+        state: state.clone(),
+    };
+    let roller = DateRoller {
+        state,
+        keep_count: 2,
+    };
+
+    // 날짜가 바뀔 때만 나눔
+    let now = Local::now();
+    fs::write(&file, "day 0").unwrap();
+    assert!(!trigger.check(&file, now));
+    assert!(!trigger.check(&file, now));
+
+    for day in 1..=3 {
+        assert!(trigger.check(&file, now + Duration::days(day)));
+        roller.roll(&file).unwrap();
+        assert!(!file.exists());
+        fs::write(&file, format!("day {}", day)).unwrap();
+        assert!(!trigger.check(&file, now + Duration::days(day)));
+    }
+
+    // 이전 날짜를 붙인 파일을 keep_count개만 남김
+    let mut names: Vec<String> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    let period = |day| {
+        (now + Duration::days(day))
+            .format("solr_proxy.log.%Y-%m-%d")
+            .to_string()
+    };
+    assert_eq!(names, ["solr_proxy.log".to_string(), period(1), period(2)]);
+    assert_eq!(fs::read_to_string(dir.join(period(2))).unwrap(), "day 2");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
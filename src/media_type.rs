@@ -0,0 +1,195 @@
+use config::{Config, ConfigError};
+use regex::Regex;
+use serde::Deserialize;
+
+/// 새 seed row에 넣는 media_type_no, site_name. 맞는 규칙이 없는 경우 기본값(0, 빈 문자열)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaType {
+    pub media_type_no: u32,
+    pub site_name: String,
+}
+
+/// seed_host가 pattern에 맞는 경우 media_type_no와 site_name_template으로 만든 site_name을 사용하는 규칙
+#[derive(Debug, Clone)]
+pub struct MediaTypeRule {
+    pub pattern: Regex,
+    pub media_type_no: u32,
+    /// pattern의 그룹을 $1, ${name} 형식으로 사용할 수 있음. 비어있으면 site_name은 빈 문자열
+    pub site_name_template: String,
+}
+
+/// config에 작성된 규칙
+#[derive(Debug, Deserialize)]
+struct MediaTypeRuleConfig {
+    pattern: String,
+    media_type_no: u32,
+    #[serde(default)]
+    site_name_template: String,
+}
+
+impl MediaTypeRule {
+    pub fn new(
+        pattern: &str,
+        media_type_no: u32,
+        site_name_template: String,
+    ) -> Result<Self, ConfigError> {
+        let pattern = Regex::new(pattern).map_err(|e| {
+            ConfigError::Message(format!(
+                "INVALID_MEDIA_TYPE_RULE_REGEX: pattern: {}, {}",
+                pattern, e
+            ))
+        })?;
+        if let Some(group) = unknown_group(&pattern, &site_name_template) {
+            return Err(ConfigError::Message(format!(
+                "INVALID_MEDIA_TYPE_RULE_TEMPLATE: pattern: {}, site_name_template: {}, no group {}",
+                pattern, site_name_template, group
+            )));
+        }
+
+        Ok(Self {
+            pattern,
+            media_type_no,
+            site_name_template,
+        })
+    }
+
+    /// config의 media_type_rules를 순서대로 읽음. 없는 경우 규칙 없음
+    pub fn from_config(config: &Config) -> Result<Vec<Self>, ConfigError> {
+        Self::from_config_or(config, "media_type_rules", Vec::new)
+    }
+
+    /// config의 key에 작성된 규칙을 순서대로 읽음. 없는 경우 default 규칙을 사용
+    pub fn from_config_or(
+        config: &Config,
+        key: &str,
+        default: impl FnOnce() -> Vec<Self>,
+    ) -> Result<Vec<Self>, ConfigError> {
+        match config.get::<Vec<MediaTypeRuleConfig>>(key) {
+            Ok(rules) => rules
+                .into_iter()
+                .map(|rule| Self::new(&rule.pattern, rule.media_type_no, rule.site_name_template))
+                .collect(),
+            Err(ConfigError::NotFound(_)) => Ok(default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// template에서 pattern에 없는 그룹을 찾음. 잘못 쓴 그룹은 Regex가 빈 문자열로 바꾸므로 설정을 읽을 때 확인함
+fn unknown_group(pattern: &Regex, template: &str) -> Option<String> {
+    let mut rest = template;
+    while let Some(pos) = rest.find('$') {
+        rest = &rest[pos + 1..];
+        // $$는 $ 문자
+        if let Some(after) = rest.strip_prefix('$') {
+            rest = after;
+            continue;
+        }
+        let (group, after) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.split_once('}') {
+                Some((group, after)) => (group, after),
+                None => return Some(format!("{{{}", braced)),
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        rest = after;
+        let known = match group.parse::<usize>() {
+            Ok(index) => index < pattern.captures_len(),
+            Err(_) => pattern.capture_names().flatten().any(|name| name == group),
+        };
+        if !known {
+            return Some(group.to_string());
+        }
+    }
+    None
+}
+
+/// seed_host에 맞는 첫번째 규칙으로 분류함. 맞는 규칙이 없으면 기본값
+pub fn classify(rules: &[MediaTypeRule], seed_host: &str) -> MediaType {
+    for rule in rules {
+        let Some(captures) = rule.pattern.captures(seed_host) else {
+            continue;
+        };
+        let mut site_name = String::new();
+        captures.expand(&rule.site_name_template, &mut site_name);
+        return MediaType {
+            media_type_no: rule.media_type_no,
+            site_name,
+        };
+    }
+    MediaType::default()
+}
+
+#[test]
+fn media_type_rule_test() {
+    use config::{File, FileFormat};
+
+    let config = Config::builder()
+        .add_source(File::from_str(
+            r#"
+[[media_type_rules]]
+pattern = "^cafe\\.naver\\.com/(?P<cafe>[^/]+)$"
+media_type_no = 2
+site_name_template = "naver cafe ${cafe}"
+
+[[media_type_rules]]
+pattern = "^blog\\.naver\\.com/([^/]+)$"
+media_type_no = 3
+site_name_template = "$1"
+
+[[media_type_rules]]
+pattern = "^(twitter|x)\\.com$"
+media_type_no = 4
+"#,
+            FileFormat::Toml,
+        ))
+        .build()
+        .unwrap();
+    let rules = MediaTypeRule::from_config(&config).unwrap();
+    assert_eq!(rules.len(), 3);
+    let media_type = |media_type_no, site_name: &str| MediaType {
+        media_type_no,
+        site_name: site_name.to_string(),
+    };
+    assert_eq!(
+        classify(&rules, "cafe.naver.com/abc"),
+        media_type(2, "naver cafe abc")
+    );
+    assert_eq!(classify(&rules, "blog.naver.com/xyz"), media_type(3, "xyz"));
+    assert_eq!(classify(&rules, "x.com"), media_type(4, ""));
+    assert_eq!(classify(&rules, "news.example.com"), MediaType::default());
+
+    // 규칙은 순서대로 확인함
+    let rules = vec![
+        MediaTypeRule::new("^cafe\\.", 2, String::new()).unwrap(),
+        MediaTypeRule::new(".", 9, "$$0 $0".to_string()).unwrap(),
+    ];
+    assert_eq!(classify(&rules, "cafe.daum.net/a").media_type_no, 2);
+    assert_eq!(classify(&rules, "a.com"), media_type(9, "$0 a"));
+
+    // 설정이 없으면 규칙 없음
+    let config = Config::builder().build().unwrap();
+    assert!(MediaTypeRule::from_config(&config).unwrap().is_empty());
+
+    // 잘못된 정규식과 pattern에 없는 그룹은 에러
+    let err = MediaTypeRule::new("^(cafe", 2, String::new())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("INVALID_MEDIA_TYPE_RULE_REGEX"));
+    for template in ["$2", "${name}", "${1", "$cafe_name"] {
+        let err = MediaTypeRule::new(
+            "^cafe\\.naver\\.com/(?P<cafe>[^/]+)",
+            2,
+            template.to_string(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("INVALID_MEDIA_TYPE_RULE_TEMPLATE"), "{}", err);
+    }
+    assert!(MediaTypeRule::new("^(?P<cafe>.+)$", 2, "${cafe}-$1".to_string()).is_ok());
+}
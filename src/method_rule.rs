@@ -0,0 +1,104 @@
+use crate::error_response::{self, ResponseFormat};
+use crate::util::{ResponseWithError, StrError};
+use crate::BoxedError;
+use hyper::header::{HeaderValue, ALLOW};
+use hyper::{Body, Method, Request, Response, StatusCode};
+
+/// select에 사용할 수 있는 method. 솔라는 form으로 POST한 query도 처리함
+pub const SELECT_METHODS: &[Method] = &[Method::GET, Method::POST, Method::HEAD];
+
+/// update에 사용할 수 있는 method
+pub const UPDATE_METHODS: &[Method] = &[Method::POST];
+
+/// update_allow_put인 경우 update에 사용할 수 있는 method
+pub const UPDATE_METHODS_WITH_PUT: &[Method] = &[Method::POST, Method::PUT];
+
+/// query string에 stream.body가 있는 update에 사용할 수 있는 method
+pub const STREAM_BODY_METHODS: &[Method] = &[Method::GET, Method::POST];
+
+/// 요청 method 확인 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodCheck {
+    Allowed,
+    /// 솔라로 보내지 않고 proxy에서 204로 응답함
+    Options,
+    NotAllowed,
+}
+
+pub fn check(method: &Method, allowed: &[Method]) -> MethodCheck {
+    if allowed.contains(method) {
+        MethodCheck::Allowed
+    } else if method == Method::OPTIONS {
+        MethodCheck::Options
+    } else {
+        MethodCheck::NotAllowed
+    }
+}
+
+/// Allow header 값. OPTIONS는 항상 포함함
+pub fn allow_header(allowed: &[Method]) -> HeaderValue {
+    let mut methods: Vec<&str> = allowed.iter().map(|method| method.as_str()).collect();
+    methods.push(Method::OPTIONS.as_str());
+    HeaderValue::from_str(&methods.join(", ")).unwrap()
+}
+
+/// OPTIONS 요청에 대한 응답
+pub fn options_response(allowed: &[Method]) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    response.headers_mut().insert(ALLOW, allow_header(allowed));
+    response
+}
+
+/// 사용할 수 없는 method인 경우의 에러. Allow header를 넣은 405 응답을 함께 돌려줌
+pub fn not_allowed(req: &Request<Body>, allowed: &[Method]) -> BoxedError {
+    let err_msg = format!("METHOD_NOT_ALLOWED: {} {}", req.method(), req.uri().path());
+    let format = ResponseFormat::from_request(req.uri(), req.headers());
+    let mut response =
+        error_response::error_response(StatusCode::METHOD_NOT_ALLOWED, &err_msg, format);
+    response.headers_mut().insert(ALLOW, allow_header(allowed));
+    Box::new(ResponseWithError {
+        err: Box::new(StrError::with_status(
+            err_msg,
+            StatusCode::METHOD_NOT_ALLOWED,
+        )),
+        response,
+    })
+}
+
+#[test]
+fn method_check_test() {
+    assert_eq!(check(&Method::GET, SELECT_METHODS), MethodCheck::Allowed);
+    assert_eq!(check(&Method::HEAD, SELECT_METHODS), MethodCheck::Allowed);
+    assert_eq!(
+        check(&Method::DELETE, SELECT_METHODS),
+        MethodCheck::NotAllowed
+    );
+    assert_eq!(check(&Method::GET, UPDATE_METHODS), MethodCheck::NotAllowed);
+    assert_eq!(check(&Method::PUT, UPDATE_METHODS), MethodCheck::NotAllowed);
+    assert_eq!(
+        check(&Method::PUT, UPDATE_METHODS_WITH_PUT),
+        MethodCheck::Allowed
+    );
+    assert_eq!(
+        check(&Method::OPTIONS, UPDATE_METHODS),
+        MethodCheck::Options
+    );
+
+    let response = options_response(UPDATE_METHODS_WITH_PUT);
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()[ALLOW], "POST, PUT, OPTIONS");
+
+    let req = Request::get("/solr/core/update?wt=xml")
+        .body(Body::empty())
+        .unwrap();
+    let err = not_allowed(&req, UPDATE_METHODS);
+    assert_eq!(err.to_string(), "METHOD_NOT_ALLOWED: GET /solr/core/update");
+    let response = err.downcast::<ResponseWithError>().unwrap().response;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[ALLOW], "POST, OPTIONS");
+    assert!(response.headers()[hyper::header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .contains("xml"));
+}
@@ -0,0 +1,349 @@
+//! 테스트용 mock 솔라 서버와 seed_id 저장소
+
+use crate::media_type::MediaType;
+use crate::seed_audit::SeedAudit;
+use crate::seed_store::{SeedIdStore, SeedRow, SeedRowStore};
+use crate::shared_cache::SharedSeedCache;
+use crate::BoxedError;
+use hashbrown::HashMap;
+use hyper::body::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Response, Server, Uri};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// mock 솔라가 받은 요청
+#[derive(Debug)]
+pub struct CapturedRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+pub struct MockSolr {
+    /// mock 솔라 주소. http://127.0.0.1:port 형식
+    pub url: String,
+    pub requests: UnboundedReceiver<CapturedRequest>,
+}
+
+impl MockSolr {
+    /// 모든 요청에 200 OK를 응답하는 mock 솔라 시작
+    pub async fn start() -> Self {
+        Self::start_with(|_| Response::new(Body::from("OK"))).await
+    }
+
+    /// 받은 요청마다 responder가 만든 응답을 돌려주는 mock 솔라 시작
+    pub async fn start_with<F>(responder: F) -> Self
+    where
+        F: Fn(&CapturedRequest) -> Response<Body> + Send + Sync + 'static,
+    {
+        Self::start_delayed(Duration::ZERO, responder).await
+    }
+
+    /// 요청을 받은 뒤 delay만큼 기다렸다가 응답하는 mock 솔라 시작
+    pub async fn start_delayed<F>(delay: Duration, responder: F) -> Self
+    where
+        F: Fn(&CapturedRequest) -> Response<Body> + Send + Sync + 'static,
+    {
+        let (sender, requests) = unbounded_channel();
+        let responder = Arc::new(responder);
+
+        let make_service = make_service_fn(move |_| {
+            let sender = sender.clone();
+            let responder = responder.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| {
+                    let sender = sender.clone();
+                    let responder = responder.clone();
+                    async move {
+                        let (parts, body) = req.into_parts();
+                        let captured = CapturedRequest {
+                            method: parts.method,
+                            uri: parts.uri,
+                            headers: parts.headers,
+                            body: hyper::body::to_bytes(body).await.unwrap_or_default(),
+                        };
+                        let response = responder(&captured);
+                        let _ = sender.send(captured);
+                        tokio::time::sleep(delay).await;
+                        Ok::<_, Infallible>(response)
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        Self { url, requests }
+    }
+
+    /// 다음으로 받은 요청을 반환
+    pub async fn next_request(&mut self) -> CapturedRequest {
+        tokio::time::timeout(Duration::from_secs(5), self.requests.recv())
+            .await
+            .expect("mock solr request timeout")
+            .expect("mock solr closed")
+    }
+}
+
+/// MockSeedIdStore가 select_seed_id에 주는 값
+enum MockSeedIds {
+    /// 미리 넣은 row와 INSERT한 row만 찾음
+    Rows,
+    /// 모든 seed_host에 "<prefix><seed_host>"를 줌
+    EveryHost,
+    /// 모든 seed_host에 같은 seed_id를 줌
+    Fixed(String),
+}
+
+/// 받은 호출을 모두 기록하는 테스트용 seed_id 저장소
+/// <br>
+/// 기본값은 row가 없는 DB와 같아서 INSERT한 seed_host에만 "<prefix><seed_host>"를 줌. prefix의 기본값은 "seed-"
+pub struct MockSeedIdStore {
+    seed_ids: MockSeedIds,
+    prefix: String,
+    rows: Mutex<HashMap<String, (String, MediaType)>>,
+    delay: Duration,
+    /// 다른 요청이 먼저 INSERT한 것처럼 INSERT해도 false를 반환함
+    lose_insert: bool,
+    fail_audit: bool,
+    /// 처음 fail_cnt번의 조회, INSERT는 err로 실패함
+    fail_cnt: usize,
+    err: fn() -> sqlx::Error,
+    /// true인 동안 조회, INSERT가 err로 실패함
+    pub down: AtomicBool,
+    calls: Mutex<Vec<Instant>>,
+    selected: Mutex<Vec<String>>,
+    inserted: Mutex<Vec<(String, MediaType)>>,
+    audits: Mutex<Vec<SeedAudit>>,
+    running: AtomicUsize,
+    max_running: AtomicUsize,
+}
+
+impl Default for MockSeedIdStore {
+    fn default() -> Self {
+        Self::with_seed_ids(MockSeedIds::Rows)
+    }
+}
+
+impl MockSeedIdStore {
+    fn with_seed_ids(seed_ids: MockSeedIds) -> Self {
+        Self {
+            seed_ids,
+            prefix: "seed-".to_string(),
+            rows: Mutex::new(HashMap::new()),
+            delay: Duration::ZERO,
+            lose_insert: false,
+            fail_audit: false,
+            fail_cnt: 0,
+            err: || sqlx::Error::PoolTimedOut,
+            down: AtomicBool::new(false),
+            calls: Mutex::new(Vec::new()),
+            selected: Mutex::new(Vec::new()),
+            inserted: Mutex::new(Vec::new()),
+            audits: Mutex::new(Vec::new()),
+            running: AtomicUsize::new(0),
+            max_running: AtomicUsize::new(0),
+        }
+    }
+
+    /// 모든 seed_host를 찾을 수 있는 저장소
+    pub fn every_host() -> Self {
+        Self::with_seed_ids(MockSeedIds::EveryHost)
+    }
+
+    /// 모든 seed_host에 seed_id를 주는 저장소
+    pub fn fixed(seed_id: &str) -> Self {
+        Self::with_seed_ids(MockSeedIds::Fixed(seed_id.to_string()))
+    }
+
+    /// 만드는 seed_id 앞에 붙일 값
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// 미리 넣어둔 row
+    pub fn with_row(self, seed_host: &str, seed_id: &str, media_type: MediaType) -> Self {
+        self.rows
+            .lock()
+            .unwrap()
+            .insert(seed_host.to_string(), (seed_id.to_string(), media_type));
+        self
+    }
+
+    /// 조회마다 delay만큼 걸림
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// INSERT는 하지만 다른 요청이 먼저 INSERT한 것처럼 false를 반환함
+    pub fn losing_insert(mut self) -> Self {
+        self.lose_insert = true;
+        self
+    }
+
+    /// 기록 저장은 항상 실패함
+    pub fn failing_audit(mut self) -> Self {
+        self.fail_audit = true;
+        self
+    }
+
+    /// 처음 fail_cnt번의 조회, INSERT와 down인 동안의 조회, INSERT는 err로 실패함
+    pub fn failing(mut self, fail_cnt: usize, err: fn() -> sqlx::Error) -> Self {
+        self.fail_cnt = fail_cnt;
+        self.err = err;
+        self
+    }
+
+    /// 조회, INSERT를 호출한 시각
+    pub fn calls(&self) -> Vec<Instant> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// 조회한 seed_host. 호출한 순서
+    pub fn selected(&self) -> Vec<String> {
+        self.selected.lock().unwrap().clone()
+    }
+
+    /// INSERT한 seed_host와 media_type. 호출한 순서
+    pub fn inserted(&self) -> Vec<(String, MediaType)> {
+        self.inserted.lock().unwrap().clone()
+    }
+
+    /// INSERT한 seed_host. 호출한 순서
+    pub fn inserted_hosts(&self) -> Vec<String> {
+        self.inserted().into_iter().map(|(host, _)| host).collect()
+    }
+
+    /// 저장한 기록
+    pub fn audits(&self) -> Vec<SeedAudit> {
+        self.audits.lock().unwrap().clone()
+    }
+
+    /// 동시에 실행한 조회의 최대 수
+    pub fn max_running(&self) -> usize {
+        self.max_running.load(Ordering::Relaxed)
+    }
+
+    fn record_call(&self) -> Result<(), BoxedError> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(Instant::now());
+        if calls.len() <= self.fail_cnt || self.down.load(Ordering::Relaxed) {
+            return Err(Box::new((self.err)()));
+        }
+        Ok(())
+    }
+}
+
+impl SeedRowStore for MockSeedIdStore {
+    async fn select_row(&self, seed_host: &str) -> Result<Option<SeedRow>, BoxedError> {
+        let media_type = self
+            .rows
+            .lock()
+            .unwrap()
+            .get(seed_host)
+            .map(|(_, media_type)| media_type.clone())
+            .unwrap_or_default();
+        let seed_id = self.select_seed_id(seed_host).await?;
+        Ok(seed_id.map(|seed_id| SeedRow {
+            seed_id,
+            media_type,
+        }))
+    }
+}
+
+impl SeedIdStore for MockSeedIdStore {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.record_call()?;
+        let running = self.running.fetch_add(1, Ordering::Relaxed) + 1;
+        self.max_running.fetch_max(running, Ordering::Relaxed);
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        self.running.fetch_sub(1, Ordering::Relaxed);
+        self.selected.lock().unwrap().push(seed_host.to_string());
+
+        Ok(match &self.seed_ids {
+            MockSeedIds::Rows => self
+                .rows
+                .lock()
+                .unwrap()
+                .get(seed_host)
+                .map(|(seed_id, _)| seed_id.clone()),
+            MockSeedIds::EveryHost => Some(format!("{}{}", self.prefix, seed_host)),
+            MockSeedIds::Fixed(seed_id) => Some(seed_id.clone()),
+        })
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        self.record_call()?;
+        self.inserted
+            .lock()
+            .unwrap()
+            .push((seed_host.to_string(), media_type.clone()));
+        let mut rows = self.rows.lock().unwrap();
+        if rows.contains_key(seed_host) {
+            return Ok(false);
+        }
+        let seed_id = format!("{}{}", self.prefix, seed_host);
+        rows.insert(seed_host.to_string(), (seed_id, media_type.clone()));
+        Ok(!self.lose_insert)
+    }
+
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        if self.fail_audit {
+            return Err("AUDIT_TABLE_DOWN".into());
+        }
+        self.audits.lock().unwrap().push(audit.clone());
+        Ok(())
+    }
+}
+
+/// 메모리에 저장하는 공유 cache. down이 true인 동안 실패함
+#[derive(Default)]
+pub struct MockSharedCache {
+    pub values: Mutex<HashMap<String, String>>,
+    pub down: AtomicBool,
+}
+
+impl MockSharedCache {
+    fn check(&self) -> Result<(), BoxedError> {
+        if self.down.load(Ordering::Relaxed) {
+            return Err("REDIS_TIMEOUT".into());
+        }
+        Ok(())
+    }
+}
+
+impl SharedSeedCache for MockSharedCache {
+    async fn get(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.check()?;
+        Ok(self.values.lock().unwrap().get(seed_host).cloned())
+    }
+
+    async fn set(&self, seed_host: &str, seed_id: &str) -> Result<(), BoxedError> {
+        self.check()?;
+        let mut values = self.values.lock().unwrap();
+        values.insert(seed_host.to_string(), seed_id.to_string());
+        Ok(())
+    }
+
+    async fn del(&self, seed_host: &str) -> Result<(), BoxedError> {
+        self.check()?;
+        self.values.lock().unwrap().remove(seed_host);
+        Ok(())
+    }
+}
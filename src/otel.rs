@@ -0,0 +1,287 @@
+//! OTLP trace. otel feature로 빌드하고 otel_endpoint를 설정한 경우에만 span을 만듦
+//! <br>
+//! 꺼져 있는 경우 Span은 크기가 0인 값이며 모든 함수가 아무것도 하지 않음
+
+use crate::BoxedError;
+use hyper::HeaderMap;
+use std::future::Future;
+
+#[cfg(feature = "otel")]
+use once_cell::sync::OnceCell;
+#[cfg(feature = "otel")]
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    trace::{FutureExt, SpanKind, TraceContextExt, Tracer, TracerProvider as _},
+    Context, KeyValue,
+};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::TracerProvider, Resource};
+
+/// trace의 service.name
+#[cfg(feature = "otel")]
+const SERVICE_NAME: &str = "solr_proxy";
+
+#[cfg(feature = "otel")]
+struct Otel {
+    provider: TracerProvider,
+    tracer: opentelemetry_sdk::trace::Tracer,
+}
+
+/// init에 성공한 경우에만 값이 있음
+#[cfg(feature = "otel")]
+static OTEL: OnceCell<Otel> = OnceCell::new();
+
+/// endpoint(예: http://10.0.0.7:4317)로 OTLP(gRPC) trace를 보내기 시작함
+#[cfg(feature = "otel")]
+pub fn init(endpoint: &str) -> Result<(), BoxedError> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    install(
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+            .build(),
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init(_endpoint: &str) -> Result<(), BoxedError> {
+    Err("OTEL_DISABLED: built without the otel feature".into())
+}
+
+/// 이미 설치된 경우 무시함
+#[cfg(feature = "otel")]
+fn install(provider: TracerProvider) {
+    let tracer = provider.tracer(SERVICE_NAME);
+    let _ = OTEL.set(Otel { provider, tracer });
+}
+
+/// 보내지 않은 span을 모두 보내고 종료함
+pub async fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(otel) = OTEL.get() {
+        // batch exporter가 끝날 때까지 blocking으로 기다리므로 별도 thread에서 실행함
+        let provider = otel.provider.clone();
+        let _ = tokio::task::spawn_blocking(move || provider.shutdown()).await;
+    }
+}
+
+/// 끝날 때(Drop) exporter로 보내는 span. otel이 꺼진 경우 아무것도 하지 않음
+pub struct Span {
+    #[cfg(feature = "otel")]
+    cx: Option<Context>,
+}
+
+#[cfg(feature = "otel")]
+impl Span {
+    /// 현재 span의 하위 span
+    pub fn start(name: &'static str) -> Span {
+        Self::start_with(name, SpanKind::Internal, Context::current)
+    }
+
+    /// 다른 서비스로 보내는 요청의 span
+    pub fn start_client(name: &'static str) -> Span {
+        Self::start_with(name, SpanKind::Client, Context::current)
+    }
+
+    /// 받은 요청의 root span. traceparent header가 있으면 그 trace에 이어서 만듦
+    pub fn start_request(headers: &HeaderMap) -> Span {
+        Self::start_with("request", SpanKind::Server, || {
+            TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+        })
+    }
+
+    fn start_with(name: &'static str, kind: SpanKind, parent: impl FnOnce() -> Context) -> Span {
+        let Some(otel) = OTEL.get() else {
+            return Span { cx: None };
+        };
+        let parent = parent();
+        let span = otel
+            .tracer
+            .span_builder(name)
+            .with_kind(kind)
+            .start_with_context(&otel.tracer, &parent);
+        Span {
+            cx: Some(parent.with_span(span)),
+        }
+    }
+
+    pub fn set_u64(&self, key: &'static str, value: u64) {
+        if let Some(cx) = &self.cx {
+            cx.span()
+                .set_attribute(KeyValue::new(key, value.min(i64::MAX as u64) as i64));
+        }
+    }
+
+    pub fn set_str(&self, key: &'static str, value: &str) {
+        if let Some(cx) = &self.cx {
+            cx.span()
+                .set_attribute(KeyValue::new(key, value.to_string()));
+        }
+    }
+
+    /// fut을 실행하는 동안 이 span을 현재 span으로 둠. fut 안에서 만든 span은 이 span의 하위 span이 됨
+    pub fn instrument<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        let cx = self.cx.clone();
+        async move {
+            match cx {
+                Some(cx) => fut.with_context(cx).await,
+                None => fut.await,
+            }
+        }
+    }
+
+    /// 이 span을 부모로 하는 traceparent를 header에 넣음
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if let Some(cx) = &self.cx {
+            TraceContextPropagator::new().inject_context(cx, &mut HeaderInjector(headers));
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(cx) = &self.cx {
+            cx.span().end();
+        }
+    }
+}
+
+// otel feature 없이 빌드한 경우 모두 inline되어 사라짐
+#[cfg(not(feature = "otel"))]
+impl Span {
+    #[inline(always)]
+    pub fn start(_name: &'static str) -> Span {
+        Span {}
+    }
+
+    #[inline(always)]
+    pub fn start_client(_name: &'static str) -> Span {
+        Span {}
+    }
+
+    #[inline(always)]
+    pub fn start_request(_headers: &HeaderMap) -> Span {
+        Span {}
+    }
+
+    #[inline(always)]
+    pub fn set_u64(&self, _key: &'static str, _value: u64) {}
+
+    #[inline(always)]
+    pub fn set_str(&self, _key: &'static str, _value: &str) {}
+
+    #[inline(always)]
+    pub fn instrument<F: Future>(&self, fut: F) -> F {
+        fut
+    }
+
+    #[inline(always)]
+    pub fn inject(&self, _headers: &mut HeaderMap) {}
+}
+
+#[cfg(feature = "otel")]
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+#[cfg(feature = "otel")]
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+#[cfg(feature = "otel")]
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+#[cfg(feature = "otel")]
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        let name = hyper::header::HeaderName::from_bytes(key.as_bytes());
+        let value = hyper::header::HeaderValue::from_str(&value);
+        if let (Ok(name), Ok(value)) = (name, value) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// 테스트용 exporter. 끝난 span을 모두 보관함
+#[cfg(all(test, feature = "otel"))]
+#[derive(Debug, Clone, Default)]
+pub struct TestExporter {
+    spans: std::sync::Arc<std::sync::Mutex<Vec<opentelemetry_sdk::export::trace::SpanData>>>,
+}
+
+#[cfg(all(test, feature = "otel"))]
+impl opentelemetry_sdk::export::trace::SpanExporter for TestExporter {
+    fn export(
+        &mut self,
+        batch: Vec<opentelemetry_sdk::export::trace::SpanData>,
+    ) -> futures_util::future::BoxFuture<'static, opentelemetry_sdk::export::trace::ExportResult>
+    {
+        self.spans.lock().unwrap().extend(batch);
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+impl TestExporter {
+    /// 모든 테스트가 같은 exporter를 사용함. 다른 테스트의 span과 섞이지 않도록 trace_id로 구분함
+    pub fn install() -> TestExporter {
+        static EXPORTER: once_cell::sync::Lazy<TestExporter> = once_cell::sync::Lazy::new(|| {
+            let exporter = TestExporter::default();
+            install(
+                TracerProvider::builder()
+                    .with_simple_exporter(exporter.clone())
+                    .build(),
+            );
+            exporter
+        });
+        EXPORTER.clone()
+    }
+
+    /// trace_id가 같은 span
+    pub fn trace(
+        &self,
+        trace_id: opentelemetry::trace::TraceId,
+    ) -> Vec<opentelemetry_sdk::export::trace::SpanData> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.span_context.trace_id() == trace_id)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+#[test]
+fn disabled_span_test() {
+    // otel feature 없이 빌드한 경우 traceparent를 바꾸지 않음
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        hyper::header::HeaderValue::from_static(
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        ),
+    );
+    let span = Span::start_request(&headers);
+    span.set_u64("doc_cnt", 3);
+    span.inject(&mut headers);
+    assert_eq!(
+        headers["traceparent"],
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"
+    );
+    assert_eq!(std::mem::size_of::<Span>(), 0);
+    assert!(init("http://127.0.0.1:4317").is_err());
+}
@@ -0,0 +1,71 @@
+use crate::util::StrError;
+use crate::BoxedError;
+use futures_util::FutureExt;
+use hyper::StatusCode;
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+/// 요청 처리 중 panic이 발생한 경우의 처리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// 해당 요청만 500으로 응답하고 서버는 계속 동작함
+    #[default]
+    Request,
+    /// 서버 전체를 종료함
+    Shutdown,
+}
+
+impl PanicPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "request" => Some(Self::Request),
+            "shutdown" => Some(Self::Shutdown),
+            _ => None,
+        }
+    }
+}
+
+tokio::task_local! {
+    /// PanicPolicy::Request로 처리중인 요청 안에서 발생한 panic인지 확인하기 위한 값
+    static REQUEST_SCOPE: ();
+}
+
+/// panic hook에서 사용. 요청 안에서 발생하여 해당 요청만 실패시키는 panic인 경우 true
+pub fn in_request_scope() -> bool {
+    REQUEST_SCOPE.try_with(|_| ()).is_ok()
+}
+
+/// policy가 Request인 경우 future에서 발생한 panic을 500 에러로 바꿈
+/// <br>
+/// Shutdown인 경우 그대로 실행하므로 panic hook에서 서버 종료를 요청함
+pub async fn catch_request_panic<T>(
+    policy: PanicPolicy,
+    future: impl Future<Output = Result<T, BoxedError>>,
+) -> Result<T, BoxedError> {
+    if policy == PanicPolicy::Shutdown {
+        return future.await;
+    }
+
+    match REQUEST_SCOPE
+        .scope((), AssertUnwindSafe(future).catch_unwind())
+        .await
+    {
+        Ok(result) => result,
+        Err(payload) => Err(Box::new(StrError::with_status(
+            format!("REQUEST_PANIC: {}", panic_message(&*payload)),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))),
+    }
+}
+
+/// panic!에 넘긴 메시지. 문자열이 아닌 경우 빈 문자열
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        ""
+    }
+}
@@ -1,28 +1,213 @@
+use crate::cache_writer::CacheWriter;
+use crate::date_field::{self, DateCheck};
+use crate::error_kind::ErrorKind;
+use crate::host_rule::{self, HostRule};
+use crate::media_type;
+use crate::seed_audit::{self, SeedAudit};
+use crate::seed_store::{
+    InsertGuardStore, MySqlSeedIdStore, RetrySeedIdStore, SeedIdStore, SharedCacheStore,
+    SuppressFailureStore,
+};
+/// write_xml 등에 넘기는 seed_id 필드. settings는 crate 밖에 공개하지 않으므로 여기서 공개함
+pub use crate::settings::SeedIdField;
+use crate::settings::{CollectionSettings, Settings};
 use crate::util::StrError;
 use crate::xml_attr_parser::AttrParser;
 use crate::xml_doc::*;
 use crate::*;
+use futures_util::{StreamExt, TryStreamExt};
+use hyper::StatusCode;
 use quick_xml::events::attributes::Attribute;
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::name::QName;
 use quick_xml::{Reader, Writer};
-use sqlx::Row;
+use regex::Regex;
 use std::borrow::Cow;
+use std::future::Future;
 use std::io::{Cursor, Write};
+use std::net::IpAddr;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
-pub fn read_xml<'xml>(xml: &'xml [u8]) -> Result<Vec<Doc<'xml>>, BoxedError> {
+/// read_xml에서 허용하는 최대 크기. 0이면 제한 없음
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadLimit {
+    pub max_docs: usize,
+    pub max_fields_per_doc: usize,
+}
+
+/// read_xml로 읽은 원문과 doc마다의 위치. write_xml_spliced에서 doc 사이의 원문을 그대로 사용함
+#[derive(Debug, Clone)]
+pub struct XmlEnvelope<'xml> {
+    xml: &'xml [u8],
+    /// 최상위 태그 앞의 xml 선언, 주석 등의 길이
+    prologue_len: usize,
+    /// 원문에서 <doc>부터 </doc>까지의 위치. 원문 순서
+    doc_ranges: Vec<Range<usize>>,
+}
+
+impl<'xml> XmlEnvelope<'xml> {
+    /// 최상위 태그(<add>) 앞의 원문. xml 선언, 주석, 공백 등
+    pub fn prologue(&self) -> &'xml [u8] {
+        &self.xml[..self.prologue_len]
+    }
+
+    /// doc의 원문이 시작하는 위치. 이 원문에서 읽은 doc이 아니면 None
+    fn doc_start(&self, doc: &Doc) -> Option<usize> {
+        let start = (doc.ori_str().as_ptr() as usize).checked_sub(self.xml.as_ptr() as usize)?;
+        self.doc_ranges
+            .binary_search_by_key(&start, |range| range.start)
+            .ok()
+            .filter(|&index| self.doc_ranges[index].len() == doc.ori_str().len())
+            .map(|_| start)
+    }
+
+    /// 원문이 <add> 하나에 doc만 있는 경우 <add> 시작 태그. 속성을 포함함
+    /// <br>
+    /// doc 사이나 앞뒤에 공백이 아닌 내용(<commit/>, <delete>, 주석 등)이 있거나 <update> 등으로 감싼 경우 None
+    fn add_start_tag(&self) -> Option<&'xml [u8]> {
+        let first = self.doc_ranges.first()?;
+        let last = self.doc_ranges.last()?;
+        let head = self.xml[self.prologue_len..first.start].trim_ascii();
+        let tail = self.xml[last.end..].trim_ascii();
+        let is_add = head.starts_with(b"<add")
+            && matches!(head.get(4), Some(b'>' | b' ' | b'\t' | b'\r' | b'\n'))
+            && memchr::memchr(b'<', &head[1..]).is_none();
+        let only_docs = self
+            .doc_ranges
+            .windows(2)
+            .all(|pair| self.xml[pair[0].end..pair[1].start].trim_ascii().is_empty());
+        (is_add && tail == b"</add>" && only_docs).then_some(head)
+    }
+
+    /// 모든 doc이 이 원문에서 읽은 doc이고 원문 순서인지 확인함
+    fn is_spliceable(&self, docs: &[Doc]) -> bool {
+        let mut previous = None;
+        docs.iter().all(|doc| {
+            let start = self.doc_start(doc);
+            let in_order = start.is_some() && (previous.is_none() || previous < start);
+            previous = start;
+            in_order
+        })
+    }
+}
+
+/// 필드 하나의 텍스트. 필드의 End 이벤트까지 모아서 DocField에 넣음
+/// <br>
+/// Text 이벤트가 하나뿐인 경우 원문을 그대로 참조하며, 주석 등으로 나뉘어 있거나 CDATA가 있는 경우에만
+/// 필드 원문을 다시 읽어 합친 값을 할당함
+#[derive(Default)]
+struct FieldText<'xml> {
+    single: Option<BytesText<'xml>>,
+    fragmented: bool,
+}
+
+impl<'xml> FieldText<'xml> {
+    fn push_text(&mut self, text: BytesText<'xml>) {
+        if self.single.is_none() && !self.fragmented {
+            self.single = Some(text);
+        } else {
+            self.set_fragmented();
+        }
+    }
+
+    /// CDATA는 unescape하지 않고 그대로 사용해야 하므로 나뉜 텍스트와 같이 다시 읽음
+    fn push_cdata(&mut self) {
+        self.set_fragmented();
+    }
+
+    fn set_fragmented(&mut self) {
+        self.single = None;
+        self.fragmented = true;
+    }
+
+    /// 모은 값. raw는 필드의 시작 태그와 끝 태그 사이의 원문
+    fn finish(self, raw: &'xml [u8]) -> Result<Option<BytesOrStr<'xml>>, BoxedError> {
+        if !self.fragmented {
+            return Ok(self.single.map(BytesOrStr::Bytes));
+        }
+
+        // 나뉜 텍스트 사이의 공백도 값에 포함되도록 trim하지 않고 다시 읽음
+        // (원문 텍스트, CDATA 여부)
+        let mut pieces: Vec<(Cow<'_, [u8]>, bool)> = Vec::new();
+        let mut reader = Reader::from_reader(raw);
+        loop {
+            match reader.read_event()? {
+                Event::Text(e) => pieces.push((e.into_inner(), false)),
+                Event::CData(e) => pieces.push((e.into_inner(), true)),
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+
+        // Text 이벤트가 하나인 경우(trim_text)와 같도록 값의 앞뒤 텍스트 공백만 제거함. CDATA는 그대로 사용함
+        let xml_ws: &[char] = &[' ', '\t', '\r', '\n'];
+        let last = pieces.len().saturating_sub(1);
+        let mut joined = String::new();
+        for (i, (piece, cdata)) in pieces.iter().enumerate() {
+            let mut text = std::str::from_utf8(piece)?;
+            if *cdata {
+                joined.push_str(text);
+                continue;
+            }
+            if i == 0 {
+                text = text.trim_start_matches(xml_ws);
+            }
+            if i == last {
+                text = text.trim_end_matches(xml_ws);
+            }
+            joined.push_str(&quick_xml::escape::unescape(text)?);
+        }
+        Ok(Some(BytesOrStr::Str(
+            Cow::Owned(joined),
+            Some(BytesText::from_escaped(std::str::from_utf8(raw)?)),
+        )))
+    }
+}
+
+pub fn read_xml<'xml>(xml: &'xml [u8], limit: &ReadLimit) -> Result<Vec<Doc<'xml>>, BoxedError> {
+    read_xml_envelope(xml, limit).map(|(docs, _)| docs)
+}
+
+/// read_xml과 같이 읽고 doc 사이의 원문을 다시 사용할 수 있도록 doc의 위치를 함께 반환함
+pub fn read_xml_envelope<'xml>(
+    xml: &'xml [u8],
+    limit: &ReadLimit,
+) -> Result<(Vec<Doc<'xml>>, XmlEnvelope<'xml>), BoxedError> {
     let mut ret_docs: Vec<Doc<'xml>> = Vec::new();
+    let mut doc_ranges: Vec<Range<usize>> = Vec::new();
     let mut reader = Reader::from_reader(xml);
     reader.trim_text(true);
     let mut field = DocField::new();
+    let mut field_cnt: usize = 0;
     let mut previous_field_name: Option<&'xml [u8]> = None;
+    let mut field_text = FieldText::default();
+    let mut field_text_start: usize = 0;
     let mut doc_start_position: Option<usize> = None;
+    // 1이면 최상위 doc 안, 2 이상이면 자식 doc 안
+    let mut doc_depth: usize = 0;
+    let mut child_start: Option<usize> = None;
+    let mut field_tag_start: usize = 0;
+    let mut field_has_child = false;
+    let mut children: Vec<&'xml [u8]> = Vec::new();
+    let mut comments: Vec<&'xml [u8]> = Vec::new();
+    let mut prologue_len: Option<usize> = None;
 
     loop {
         match reader.read_event() {
             Ok(Event::Start(e)) => {
                 let buffer_position = reader.buffer_position();
                 let name = e.name().0;
+                let tag_start = buffer_position - e.len() - 2;
+                prologue_len.get_or_insert(tag_start);
+
+                // 자식 doc은 원문을 그대로 유지하므로 안의 내용은 읽지 않음
+                if doc_depth > 1 {
+                    if name == b"doc" {
+                        doc_depth += 1;
+                    }
+                    continue;
+                }
 
                 match name {
                     b"field" => {
@@ -35,350 +220,1652 @@ pub fn read_xml<'xml>(xml: &'xml [u8]) -> Result<Vec<Doc<'xml>>, BoxedError> {
                                 break;
                             }
                         }
+                        field_text = FieldText::default();
+                        field_text_start = buffer_position;
+                        field_tag_start = tag_start;
                     }
-                    b"doc" => {
-                        doc_start_position = Some(buffer_position - e.len() - 2);
+                    b"doc" if doc_depth == 0 => {
+                        doc_depth = 1;
+                        doc_start_position = Some(tag_start);
                         field.try_reserve(36).map_err(|_| {
                             Box::new(StrError::new("HashMap::try_reserve FAIL".to_string()))
                         })?;
                     }
+                    // 자식 doc. 필드 안에 있는 경우 필드 전체를 자식 doc의 원문으로 유지함
+                    b"doc" => {
+                        doc_depth += 1;
+                        match previous_field_name {
+                            Some(_) => field_has_child = true,
+                            None => child_start = Some(tag_start),
+                        }
+                    }
                     _ => (),
                 }
             }
-            Ok(Event::Text(e)) => {
-                if let Some(pre_name) = previous_field_name {
-                    field.push_field_borrowed(pre_name, e);
-                }
-                previous_field_name = None;
+            // 필드의 텍스트는 End 이벤트에서 DocField에 넣음
+            Ok(Event::Text(e)) if previous_field_name.is_some() && doc_depth == 1 => {
+                field_text.push_text(e)
+            }
+            Ok(Event::CData(_)) if previous_field_name.is_some() && doc_depth == 1 => {
+                field_text.push_cdata()
             }
             Ok(Event::End(e)) => {
+                let buffer_position = reader.buffer_position();
+                if doc_depth > 1 {
+                    if e.name().0 == b"doc" {
+                        doc_depth -= 1;
+                        if let (1, Some(start)) = (doc_depth, child_start) {
+                            children.push(&xml[start..buffer_position]);
+                            child_start = None;
+                        }
+                    }
+                    continue;
+                }
+                if field_has_child && e.name().0 == b"field" {
+                    children.push(&xml[field_tag_start..buffer_position]);
+                    field_has_child = false;
+                    field_text = FieldText::default();
+                    previous_field_name = None;
+                    continue;
+                }
+
+                let body = std::mem::take(&mut field_text)
+                    .finish(&xml[field_text_start..buffer_position - e.len() - 3])?;
+                if let (Some(pre_name), Some(body)) = (previous_field_name, body) {
+                    if limit.max_fields_per_doc > 0 && field_cnt >= limit.max_fields_per_doc {
+                        return Err(Box::new(StrError::with_status(
+                            format!(
+                                "MAX_FIELDS_PER_DOC_EXCEEDED: {} (doc index {})",
+                                limit.max_fields_per_doc,
+                                ret_docs.len()
+                            ),
+                            StatusCode::BAD_REQUEST,
+                        )));
+                    }
+                    field.push_field(pre_name, body);
+                    field_cnt += 1;
+                }
+
                 // doc의 파싱이 끝난 경우 ret_docs에 추가하여 넣음
                 if e.name().0 == b"doc" {
                     let Some(doc_start_position_value) = doc_start_position else {
                         // 이 위치에서 doc_start_position이 None이면 안됨. 에러 반환
-                        return Err(Box::new(StrError::new(
-                            "DOC_START_POSITION_EMPTY".to_string(),
-                        )));
+                        return Err(Box::new(
+                            StrError::new("DOC_START_POSITION_EMPTY".to_string())
+                                .with_kind(ErrorKind::ParseError),
+                        ));
                     };
 
-                    let ori_str = &xml[doc_start_position_value..reader.buffer_position()];
+                    let ori_range = doc_start_position_value..buffer_position;
+                    let ori_str = &xml[ori_range.clone()];
 
                     // ori_str의 유효성 체크
                     // <doc> 태그로 시작하고 </doc> 태그로 끝나야 함.
                     if !ori_str.starts_with(b"<doc") || !ori_str.ends_with(b"</doc>") {
-                        return Err(Box::new(StrError::new(
-                            "ORI_STR_VALIDATION_FAIL".to_string(),
+                        return Err(Box::new(
+                            StrError::new("ORI_STR_VALIDATION_FAIL".to_string())
+                                .with_kind(ErrorKind::ParseError),
+                        ));
+                    }
+
+                    if limit.max_docs > 0 && ret_docs.len() >= limit.max_docs {
+                        return Err(Box::new(StrError::with_status(
+                            format!("MAX_DOCS_PER_UPDATE_EXCEEDED: {}", limit.max_docs),
+                            StatusCode::BAD_REQUEST,
                         )));
                     }
 
-                    let doc = Doc::new(field, ori_str);
+                    let doc = Doc::with_raw(
+                        field,
+                        ori_str,
+                        std::mem::take(&mut comments),
+                        std::mem::take(&mut children),
+                    );
                     ret_docs.push(doc);
+                    doc_ranges.push(ori_range);
                     field = DocField::new();
+                    field_cnt = 0;
                     doc_start_position = None;
+                    doc_depth = 0;
                 }
                 previous_field_name = None;
             }
+            Ok(Event::Empty(e)) => {
+                let buffer_position = reader.buffer_position();
+                let tag_start = buffer_position - e.len() - 3;
+                prologue_len.get_or_insert(tag_start);
+                // 내용이 없는 자식 doc. <doc/>
+                if doc_depth == 1 && !field_has_child && e.name().0 == b"doc" {
+                    children.push(&xml[tag_start..buffer_position]);
+                }
+            }
+            // doc 안의 주석은 doc을 다시 작성할 때 유지함. <!-- -->
+            Ok(Event::Comment(e)) if doc_depth == 1 => {
+                let buffer_position = reader.buffer_position();
+                comments.push(&xml[buffer_position - e.len() - 7..buffer_position]);
+            }
             Ok(Event::Eof) => break,
             Err(e) => return Err(Box::new(e)),
             _ => (),
         }
     }
 
-    Ok(ret_docs)
+    let envelope = XmlEnvelope {
+        xml,
+        prologue_len: prologue_len.unwrap_or(0),
+        doc_ranges,
+    };
+    Ok((ret_docs, envelope))
 }
 
-pub async fn proc_xml(docs: &mut Vec<Doc<'_>>) -> Result<(), BoxedError> {
-    for doc in docs {
+/// 요청마다 지정할 수 있는 proc_xml 처리 방식
+#[derive(Debug, Clone, Default)]
+pub struct ProcOptions {
+    /// cache에 seed_id가 있어도 DB에서 다시 조회하고 cache를 갱신함
+    pub refresh_cache: bool,
+    /// 요청한 클라이언트 ip. 새로 만든 seed_id 기록에 남김
+    pub remote_ip: Option<IpAddr>,
+    /// 요청 path의 컬렉션 설정. None이면 전역 설정을 사용함
+    pub collection: Option<Arc<CollectionSettings>>,
+}
+
+impl ProcOptions {
+    /// 이 요청에 사용할 컬렉션 설정
+    pub fn collection(&self, settings: &Settings) -> Arc<CollectionSettings> {
+        self.collection
+            .clone()
+            .unwrap_or_else(|| settings.default_collection.clone())
+    }
+}
+
+/// update 요청 하나의 처리 단계별 소요 시간, seed_id cache 사용 횟수
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcTiming {
+    /// read_xml, write_xml에 걸린 시간
+    pub parse: Duration,
+    /// cache, DB에서 seed_id를 조회하는 데 걸린 시간
+    pub enrich: Duration,
+    /// enrich 중 DB 조회, INSERT에 걸린 시간
+    pub db: Duration,
+    pub cache_hit: u32,
+    pub cache_miss: u32,
+}
+
+/// doc마다 seed_id 등을 채워넣음. 중복되거나 필수 필드가 없어 제거된 doc의 수 반환
+/// <br>
+/// seed_id 조회에 걸린 시간은 timing.enrich에 더함
+pub async fn proc_xml(
+    docs: &mut Vec<Doc<'_>>,
+    options: &ProcOptions,
+    timing: &mut ProcTiming,
+) -> Result<usize, BoxedError> {
+    let settings = settings();
+    let collection = options.collection(&settings);
+    let store = InsertGuardStore::new(
+        SharedCacheStore::new(
+            SuppressFailureStore::new(
+                RetrySeedIdStore::new(
+                    MySqlSeedIdStore::new(&collection.seed_table),
+                    settings.db_retry_policy(),
+                ),
+                &SEED_LOOKUP_FAILURES,
+            )
+            .with_collection(&collection),
+            SHARED_SEED_CACHE.as_ref(),
+        )
+        .with_collection(&collection),
+        &INSERT_GUARD,
+    );
+    proc_xml_with(docs, &store, options, timing).await
+}
+
+/// store에서 seed_id를 조회하는 proc_xml
+pub async fn proc_xml_with<S: SeedIdStore>(
+    docs: &mut Vec<Doc<'_>>,
+    store: &S,
+    options: &ProcOptions,
+    timing: &mut ProcTiming,
+) -> Result<usize, BoxedError> {
+    proc_xml_with_writer(docs, store, options, timing, &SEED_CACHE_WRITER).await
+}
+
+/// DB에서 조회한 seed_id를 writer로 cache에 넣는 proc_xml_with. 테스트에서는 다른 테스트와 writer를 함께 쓰지 않도록 사용함
+async fn proc_xml_with_writer<S: SeedIdStore>(
+    docs: &mut Vec<Doc<'_>>,
+    store: &S,
+    options: &ProcOptions,
+    timing: &mut ProcTiming,
+    writer: &'static CacheWriter,
+) -> Result<usize, BoxedError> {
+    let settings = settings();
+    let collection = options.collection(&settings);
+    let seed_id_field = collection.seed_id_field.as_bytes();
+
+    // 제거될 doc에 대해 DB 조회를 하지 않도록 가장 먼저 처리함
+    let duplicated = if collection.dedup_docs_by_id {
+        dedup_docs(docs)?
+    } else {
+        0
+    };
+    if duplicated > 0 {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.duplicated_doc_cnt += duplicated as u32;
+    }
+
+    let dropped = check_required_fields(
+        docs,
+        &collection.required_fields,
+        collection.required_fields_action,
+    )?;
+    if dropped > 0 {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.dropped_doc_cnt += dropped as u32;
+    }
+    if let Some(stat) = WORKING_CNT.lock().await.collection(&collection.name) {
+        stat.doc_cnt += docs.len();
+    }
+
+    // cache에 없는 seed_host. 같은 요청의 doc끼리는 seed_host마다 한 번만 조회함
+    let mut lookups: Vec<Lookup> = Vec::new();
+    let mut lookup_index: hashbrown::HashMap<String, usize> = hashbrown::HashMap::new();
+    let mut cache_time = Duration::ZERO;
+
+    for (index, doc) in docs.iter_mut().enumerate() {
+        let doc_start = Instant::now();
+        if collection.sanitize_xml && sanitize_doc(doc)? {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.sanitized_doc_cnt += 1;
+        }
+
+        if !collection.field_max_bytes.is_empty()
+            && truncate_fields(doc, &collection.field_max_bytes)?
+        {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.truncated_doc_cnt += 1;
+        }
+
+        if collection.dedup_single_valued_fields
+            && dedup_fields(doc, &collection.single_valued_fields)?
+        {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.dedup_doc_cnt += 1;
+        }
+
+        if collection.normalize_dates {
+            let date_result = normalize_dates(doc)?;
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.tstamp_fill_cnt += date_result.tstamp_filled as u32;
+            cnt_lock.postdate_rewrite_cnt += date_result.postdate_rewritten;
+            cnt_lock.postdate_invalid_cnt += date_result.postdate_invalid;
+        }
+
+        if collection.validate_incoming_seed_id
+            && remove_invalid_seed_ids(doc, seed_id_field, &settings.seed_id_regex)? > 0
+        {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.invalid_seed_id_cnt += 1;
+        }
+
         // seed_id가 없는 경우 넣어야 함
-        if doc.field().get(COL_SEED_ID).is_none() {
-            let seed_host = seed_host(doc)?;
-
-            let not_found_cache_flag = {
-                let mut seed_id_cache_lock = SEED_ID_CACHE.lock().await;
-                match seed_id_cache_lock.get(&seed_host) {
-                    Some(seed_id) => {
-                        doc.field_as_mut()
-                            .push_field_owned(COL_SEED_ID, seed_id.to_string());
+        let need_seed_id = doc.field().get(seed_id_field).is_none();
+        let need_host_fields = collection.fill_host_fields
+            && (doc.field().get(COL_HOST).is_none() || doc.field().get(COL_SITE).is_none());
+        if !need_seed_id && !need_host_fields {
+            continue;
+        }
+
+        // 비정상적으로 긴 url은 seed_host를 만드는 데 오래 걸리므로 enrich하지 않고 그대로 보냄
+        let url_len = longest_url_len(doc, &collection.seed_url_fields);
+        if collection.max_url_bytes > 0 && url_len > collection.max_url_bytes {
+            warn!(
+                "URL_TOO_LONG: id {}, {} bytes (max {})",
+                doc_id(doc)?,
+                url_len,
+                collection.max_url_bytes
+            );
+            WORKING_CNT.lock().await.url_too_long_cnt += 1;
+            continue;
+        }
+
+        let SeedHost {
+            host,
+            seed_host,
+            url,
+        } = seed_host(doc, &collection.host_rules, &collection.seed_url_fields)?;
+
+        // 한 doc 때문에 요청 전체가 늦어지지 않도록 시간을 넘은 doc은 enrich하지 않고 그대로 보냄
+        // 여기까지 doc을 읽고 seed_host를 만든 시간만 셈. cache, DB 조회는 아래에서 doc들을 모아 처리하므로 포함하지 않음
+        let doc_elapsed = doc_start.elapsed();
+        if !collection.doc_enrich_budget.is_zero() && doc_elapsed > collection.doc_enrich_budget {
+            warn!(
+                "DOC_ENRICH_BUDGET_EXCEEDED: id {}, {}ms (budget {}ms)",
+                doc_id(doc)?,
+                doc_elapsed.as_millis(),
+                collection.doc_enrich_budget.as_millis()
+            );
+            WORKING_CNT.lock().await.doc_budget_exceeded_cnt += 1;
+            continue;
+        }
+
+        if need_host_fields && fill_host_fields(doc, &host) {
+            let mut cnt_lock = WORKING_CNT.lock().await;
+            cnt_lock.host_fill_cnt += 1;
+        }
+
+        if need_seed_id {
+            let cache_start = Instant::now();
+            // refresh_cache인 경우 cache를 확인하지 않고 DB에서 조회한 값으로 cache를 갱신함
+            let cached = if options.refresh_cache {
+                None
+            } else {
+                SEED_ID_CACHE.get(&collection.cache_key(&seed_host)).await
+            };
+            cache_time += cache_start.elapsed();
+
+            let not_found_cache_flag = match cached {
+                Some(seed_id) => {
+                    doc.field_as_mut().push_field_owned(seed_id_field, seed_id);
+                    false
+                }
+                None => match lookup_index.get(&seed_host) {
+                    // 앞의 doc에서 이미 조회하는 seed_host는 cache를 사용한 것으로 셈
+                    Some(&i) => {
+                        lookups[i].doc_indexes.push(index);
                         false
                     }
-                    None => true,
-                }
+                    None => {
+                        lookup_index.insert(seed_host.clone(), lookups.len());
+                        lookups.push(Lookup {
+                            seed_host,
+                            url,
+                            doc_id: doc_id(doc).ok().filter(|id| !id.is_empty()),
+                            doc_indexes: vec![index],
+                        });
+                        true
+                    }
+                },
             };
 
             {
                 let mut cnt_lock = WORKING_CNT.lock().await;
-                if not_found_cache_flag {
+                if options.refresh_cache {
+                    cnt_lock.cache_refresh_cnt += 1;
+                } else if not_found_cache_flag {
                     cnt_lock.cache_miss_cnt += 1;
                 } else {
                     cnt_lock.cache_hit_cnt += 1;
                 }
+                if let Some(stat) = cnt_lock.collection(&collection.name) {
+                    match not_found_cache_flag {
+                        true => stat.cache_miss_cnt += 1,
+                        false => stat.cache_hit_cnt += 1,
+                    }
+                }
             }
-
-            // cache에서 seed_id를 찾지 못한 경우
             if not_found_cache_flag {
-                // db에서 검색 시도
-                let rows = select_seed_id(&seed_host).await?;
+                timing.cache_miss += 1;
+            } else {
+                timing.cache_hit += 1;
+            }
+        }
+    }
+
+    if lookups.is_empty() {
+        timing.enrich += cache_time;
+        return Ok(duplicated + dropped);
+    }
 
-                // db에서 찾은 경우
-                if let Some(row) = rows {
-                    let seed_id = row.try_get::<&str, _>("seed_id")?;
+    // DB 조회는 enrich_concurrency개까지 동시에 처리하고, 끝난 뒤 doc에 넣음
+    // 다른 요청이 같은 seed_host를 조회하는 중이면 새로 조회하지 않고 그 결과를 사용함
+    let db_start = Instant::now();
+    let collection_ref: &CollectionSettings = &collection;
+    let resolved: Vec<(usize, Option<String>)> = futures_util::stream::iter(0..lookups.len())
+        .map(|i| {
+            let lookup = &lookups[i];
+            async move {
+                let key = collection_ref.cache_key(&lookup.seed_host);
+                SEED_LOOKUP_IN_FLIGHT
+                    .run(&key, || {
+                        resolve_seed_id(store, lookup, collection_ref, options)
+                    })
+                    .await
+                    .map(|seed_id| (i, seed_id))
+            }
+        })
+        .buffer_unordered(settings.enrich_concurrency())
+        .try_collect()
+        .await?;
+    for (i, seed_id) in resolved {
+        let Some(seed_id) = seed_id else {
+            continue;
+        };
+        let lookup = &lookups[i];
+        for &index in &lookup.doc_indexes {
+            docs[index]
+                .field_as_mut()
+                .push_field_owned(seed_id_field, seed_id.clone());
+        }
+        // cache에는 writer task가 넣으므로 다음 요청은 잠시 동안 DB에서 다시 조회할 수 있음
+        // 같은 요청의 doc은 lookup_index로 한 번만 조회함
+        writer
+            .put(collection.cache_key(&lookup.seed_host), seed_id)
+            .await;
+    }
+    timing.db += db_start.elapsed();
+    timing.enrich += cache_time + db_start.elapsed();
 
-                    doc.field_as_mut()
-                        .push_field_owned(COL_SEED_ID, seed_id.to_string());
+    Ok(duplicated + dropped)
+}
 
-                    let mut seed_id_cache_lock = SEED_ID_CACHE.lock().await;
-                    seed_id_cache_lock.put(seed_host, seed_id.to_string());
-                } else {
-                    {
-                        let mut cnt_lock = WORKING_CNT.lock().await;
-                        cnt_lock.seed_id_insert_cnt += 1;
-                    }
-                    // db에서 찾지 못한 경우 INSERT 후 다시 SELECT
-                    let sql = "INSERT IGNORE INTO crawlerdb.t_channel_contents_map
-(seed_id, site_name, media_url, media_type_no)
-VALUES
-(uuid(), '', ?, '0');";
-                    sqlx::query(sql).bind(&seed_host).execute(&*CON).await?;
-                    let rows = select_seed_id(&seed_host).await?;
-                    let Some(row) = rows else {
-                        // INSERT 후 다시 SELECT했는데 찾지 못한 경우. 정상적인 경우 발생할 수 없음
-                        return Err(Box::new(StrError::new(
-                            "SEED_ID_SELECT_AFTER_INSERT_FAIL".to_string(),
-                        )));
-                    };
+/// cache에 없어서 DB에서 조회할 seed_host와 이 seed_host를 사용하는 doc
+struct Lookup {
+    seed_host: String,
+    /// seed_id를 새로 만든 경우 기록에 남길 첫번째 doc의 url, id
+    url: String,
+    doc_id: Option<String>,
+    doc_indexes: Vec<usize>,
+}
+
+/// DB에서 seed_id를 조회하고 없는 경우 INSERT함
+/// <br>
+/// 새 seed_id를 INSERT할 수 없는 경우 None을 반환하며, 해당 doc은 seed_id 없이 솔라로 보냄
+async fn resolve_seed_id<S: SeedIdStore>(
+    store: &S,
+    lookup: &Lookup,
+    collection: &CollectionSettings,
+    options: &ProcOptions,
+) -> Result<Option<String>, BoxedError> {
+    let seed_host = &lookup.seed_host;
+    // db에서 찾은 경우
+    if let Some(seed_id) = traced(
+        "db.select_seed_id",
+        seed_host,
+        store.select_seed_id(seed_host),
+    )
+    .await?
+    {
+        return Ok(Some(seed_id));
+    }
+
+    if !store.allow_insert() {
+        // 새 seed_id가 너무 많이 만들어진 경우 INSERT하지 않고 seed_id 없이 보냄
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.seed_insert_skipped_cnt += lookup.doc_indexes.len() as u32;
+        return Ok(None);
+    }
+
+    // db에서 찾지 못한 경우 INSERT 후 다시 SELECT
+    let media_type = media_type::classify(&collection.media_type_rules, seed_host);
+    let inserted = traced(
+        "db.insert_seed_id",
+        seed_host,
+        store.insert_seed_id(seed_host, &media_type),
+    )
+    .await?;
+    let Some(seed_id) = traced(
+        "db.select_seed_id",
+        seed_host,
+        store.select_seed_id(seed_host),
+    )
+    .await?
+    else {
+        // INSERT 후 다시 SELECT했는데 찾지 못한 경우. 정상적인 경우 발생할 수 없음
+        return Err(Box::new(
+            StrError::new("SEED_ID_SELECT_AFTER_INSERT_FAIL".to_string())
+                .with_kind(ErrorKind::DbError),
+        ));
+    };
+    // 동시에 다른 요청이 먼저 INSERT한 경우 그 요청에서 기록함
+    if !inserted {
+        return Ok(Some(seed_id));
+    }
+
+    {
+        let mut cnt_lock = WORKING_CNT.lock().await;
+        cnt_lock.seed_id_insert_cnt += 1;
+        if let Some(stat) = cnt_lock.collection(&collection.name) {
+            stat.seed_id_insert_cnt += 1;
+        }
+    }
+    let audit = SeedAudit {
+        seed_host: seed_host.clone(),
+        seed_id: seed_id.clone(),
+        doc_id: lookup.doc_id.clone(),
+        url: lookup.url.clone(),
+        remote_ip: options.remote_ip,
+        media_type,
+    };
+    seed_audit::log_audit(&audit);
+    // 기록을 저장하지 못해도 seed_id는 이미 만들었으므로 계속 처리함
+    if let Err(e) = traced("db.insert_audit", seed_host, store.insert_audit(&audit)).await {
+        warn!("SEED_AUDIT_FAIL {}, err: {}", audit.line(), e);
+    }
+    Ok(Some(seed_id))
+}
+
+/// store 조회 하나의 span. shared cache에서 찾은 경우에도 DB 조회로 남김
+async fn traced<F: Future>(name: &'static str, seed_host: &str, fut: F) -> F::Output {
+    let span = otel::Span::start(name);
+    span.set_str("seed_host", seed_host);
+    fut.await
+}
 
-                    let seed_id = row.try_get::<&str, _>("seed_id")?;
+/// xml 1.0에서 사용할 수 없는 문자. tab, 줄바꿈을 제외한 C0 제어 문자와 U+FFFE, U+FFFF
+/// <br>서로게이트는 rust 문자열에 들어갈 수 없으므로 unescape 단계에서 에러가 발생함
+fn is_invalid_xml_char(c: char) -> bool {
+    matches!(c, '\u{0}'..='\u{8}' | '\u{B}' | '\u{C}' | '\u{E}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}')
+}
 
-                    doc.field_as_mut()
-                        .push_field_owned(COL_SEED_ID, seed_id.to_string());
+/// field 값에서 xml에서 사용할 수 없는 문자를 제거함. 변경된 값이 있는 경우 true
+fn sanitize_doc(doc: &mut Doc) -> Result<bool, BoxedError> {
+    let mut replaces = Vec::new();
 
-                    let mut seed_id_cache_lock = SEED_ID_CACHE.lock().await;
-                    seed_id_cache_lock.put(seed_host, seed_id.to_string());
+    for (field_name, body_list) in doc.field().iter() {
+        for (index, body) in body_list.iter().enumerate() {
+            // 원문 참조인 경우 제어 문자나 문자 참조(&#..;)가 있을 때만 unescape하여 확인
+            if let BytesOrStr::Bytes(bytes) = body {
+                // U+FFFE, U+FFFF는 utf-8에서 0xEF로 시작함
+                let has_candidate = bytes.iter().any(|c| {
+                    (c.is_ascii_control() && !matches!(c, b'\t' | b'\n' | b'\r')) || *c == 0xEF
+                }) || bytes.windows(2).any(|w| w == b"&#");
+                if !has_candidate {
+                    continue;
                 }
             }
+
+            let value = body.to_unescape_str()?;
+            if value.chars().any(is_invalid_xml_char) {
+                let sanitized: String =
+                    value.chars().filter(|c| !is_invalid_xml_char(*c)).collect();
+                replaces.push((field_name, index, sanitized));
+            }
         }
     }
 
-    Ok(())
+    let changed = !replaces.is_empty();
+    for (field_name, index, value) in replaces {
+        doc.field_as_mut()
+            .replace_field_owned(field_name, index, value);
+    }
+    Ok(changed)
 }
 
-async fn select_seed_id(seed_host: &str) -> Result<Option<sqlx::mysql::MySqlRow>, BoxedError> {
-    Ok(
-        sqlx::query("SELECT seed_id FROM crawlerdb.t_channel_contents_map WHERE media_url = ?;")
-            .bind(seed_host)
-            .fetch_optional(&*CON)
-            .await?,
-    )
-}
+/// id가 같은 doc이 여러개 있는 경우 솔라와 같이 마지막 doc만 남김. 제거된 doc의 수 반환
+/// <br>id가 없는 doc은 그대로 유지함
+fn dedup_docs(docs: &mut Vec<Doc>) -> Result<usize, BoxedError> {
+    let mut ids = Vec::with_capacity(docs.len());
+    let mut last_index = hashbrown::HashMap::with_capacity(docs.len());
+    for (index, doc) in docs.iter().enumerate() {
+        let id = match doc.field().get(COL_ID).and_then(|ids| ids.first()) {
+            Some(id) => Some(id.to_unescape_str()?.into_owned()),
+            None => None,
+        };
+        if let Some(id) = &id {
+            last_index.insert(id.clone(), index);
+        }
+        ids.push(id);
+    }
 
-fn seed_host(doc: &Doc) -> Result<String, BoxedError> {
-    let Some(url) = doc.field().get(COL_URL) else {
-        return Err(Box::new(StrError::new("NOT_FOUND_URL".to_string())));
-    };
+    if last_index.len() == ids.iter().filter(|id| id.is_some()).count() {
+        return Ok(0);
+    }
 
-    let Some(first) = url.first() else {
-        return Err(Box::new(StrError::new("NOT_FOUND_URL".to_string())));
-    };
+    let before = docs.len();
+    let mut index = 0;
+    docs.retain(|_| {
+        let keep = match &ids[index] {
+            Some(id) => last_index.get(id) == Some(&index),
+            None => true,
+        };
+        index += 1;
+        keep
+    });
+    Ok(before - docs.len())
+}
 
-    let url = first.to_unescape_str()?;
-    Ok(seed_host_str(&url)?.into_owned())
+/// 필수 필드가 없는 doc의 처리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequiredFieldsAction {
+    /// 해당 doc만 제거하고 나머지 doc은 솔라로 보냄
+    #[default]
+    Drop,
+    /// 요청 전체를 400으로 거부함
+    Reject,
 }
 
-fn seed_host_str(mut url: &str) -> Result<Cow<str>, BoxedError> {
-    const HTTPS: &str = "https://";
-    const HTTP: &str = "http://";
+impl RequiredFieldsAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "drop" => Some(Self::Drop),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
 
-    // https 및 http를 잘라냄
-    if url.starts_with(HTTPS) {
-        url = &url[HTTPS.len()..];
-    } else if url.starts_with(HTTP) {
-        url = &url[HTTP.len()..];
+/// 필수 필드 중 없거나 값이 비어있는 필드 목록
+fn missing_fields<'a>(doc: &Doc, fields: &'a [String]) -> Result<Vec<&'a str>, BoxedError> {
+    let mut missing = Vec::new();
+    for field_name in fields {
+        let mut has_value = false;
+        if let Some(body_list) = doc.field().get(field_name.as_bytes()) {
+            for body in body_list {
+                if !body.to_unescape_str()?.trim().is_empty() {
+                    has_value = true;
+                    break;
+                }
+            }
+        }
+        if !has_value {
+            missing.push(field_name.as_str());
+        }
     }
+    Ok(missing)
+}
 
-    // www.으로 시작하는 경우 잘라냄
-    const WWW: &str = "www.";
-    if url.starts_with(WWW) {
-        url = &url[WWW.len()..];
+/// 필수 필드가 없는 doc을 action에 따라 제거하거나 에러 반환. 제거된 doc의 수 반환
+fn check_required_fields(
+    docs: &mut Vec<Doc>,
+    fields: &[String],
+    action: RequiredFieldsAction,
+) -> Result<usize, BoxedError> {
+    if fields.is_empty() {
+        return Ok(0);
     }
 
-    if url.starts_with("cafe.naver.com")
-        || url.starts_with("m.cafe.daum.net")
-        || url.starts_with("cafe.daum.net")
-        || url.starts_with("blog.naver.com")
-    {
-        match CAFEBLOG_PTRN.captures(url) {
-            Some(cap) => {
-                let value = cap.get(1).unwrap().as_str();
-                Ok(Cow::Owned(value.to_string()))
+    let mut keep = Vec::with_capacity(docs.len());
+    for doc in docs.iter() {
+        let missing = missing_fields(doc, fields)?;
+        if missing.is_empty() {
+            keep.push(true);
+            continue;
+        }
+
+        // 확인할 수 있도록 가지고 있는 필수 필드 값을 함께 남김
+        let mut identity = Vec::new();
+        for field_name in fields {
+            if let Some(value) = doc
+                .field()
+                .get(field_name.as_bytes())
+                .and_then(|body_list| body_list.first())
+            {
+                identity.push(format!("{}: {}", field_name, value.to_unescape_str()?));
             }
-            None => Err(Box::new(StrError::new(format!(
-                "CAFE_PTRN_NOT_MATCH: {}",
-                url
-            )))),
         }
-    } else {
-        Ok(Cow::Borrowed(cut_host(url)))
+        let err_msg = format!(
+            "MISSING_REQUIRED_FIELD {:?}, doc: [{}]",
+            missing,
+            identity.join(", ")
+        );
+
+        match action {
+            RequiredFieldsAction::Reject => {
+                return Err(Box::new(StrError::with_status(
+                    err_msg,
+                    StatusCode::BAD_REQUEST,
+                )));
+            }
+            RequiredFieldsAction::Drop => {
+                warn!("DROP_DOC {}", err_msg);
+                keep.push(false);
+            }
+        }
+    }
+
+    let before = docs.len();
+    let mut keep = keep.into_iter();
+    docs.retain(|_| keep.next().unwrap_or(true));
+    Ok(before - docs.len())
+}
+
+/// 로그에 남길 doc의 id. 없는 경우 빈 문자열
+fn doc_id(doc: &Doc) -> Result<String, BoxedError> {
+    match doc.field().get(COL_ID).and_then(|ids| ids.first()) {
+        Some(id) => Ok(id.to_unescape_str()?.into_owned()),
+        None => Ok(String::new()),
     }
 }
 
-fn cut_host(mut url: &str) -> &str {
-    let pos = url.find(|c| matches!(c, '/' | '#'));
+/// seed_id_field의 값 중 seed_id_regex에 맞지 않는 값을 제거함. 제거된 값의 수 반환
+/// <br>
+/// 모두 제거된 경우 seed_id가 없는 doc과 같이 cache, DB에서 다시 조회함
+fn remove_invalid_seed_ids(
+    doc: &mut Doc,
+    seed_id_field: &[u8],
+    seed_id_regex: &Regex,
+) -> Result<usize, BoxedError> {
+    let Some(seed_ids) = doc.field().get(seed_id_field) else {
+        return Ok(0);
+    };
+    let mut invalid = Vec::new();
+    for seed_id in seed_ids {
+        let seed_id = seed_id.to_unescape_str()?;
+        if !seed_id_regex.is_match(seed_id.trim()) {
+            invalid.push(seed_id.into_owned());
+        }
+    }
+    if invalid.is_empty() {
+        return Ok(0);
+    }
+
+    warn!(
+        "INVALID_SEED_ID id: {}, seed_id: {:?}",
+        doc_id(doc)?,
+        invalid
+    );
+    let removed = doc.field_as_mut().retain_field(seed_id_field, |seed_id| {
+        seed_id
+            .to_unescape_str()
+            .is_ok_and(|seed_id| seed_id_regex.is_match(seed_id.trim()))
+    });
+    Ok(removed)
+}
 
-    if let Some(pos) = pos {
-        url = &url[0..pos];
+/// limits의 필드 값이 최대 길이(bytes)를 넘으면 utf-8 문자 경계에서 자름. 변경된 경우 true
+/// <br>
+/// 원문 참조인 값은 unescape한 값을 잘라서 바꾸므로 write_xml에서 다시 escape됨. 길이는 unescape한 값 기준
+fn truncate_fields(doc: &mut Doc, limits: &[(String, u64)]) -> Result<bool, BoxedError> {
+    let mut replaces = Vec::new();
+    for (field_name, max_bytes) in limits {
+        let max_bytes = usize::try_from(*max_bytes).unwrap_or(usize::MAX);
+        let Some(body_list) = doc.field().get(field_name.as_bytes()) else {
+            continue;
+        };
+        for (index, body) in body_list.iter().enumerate() {
+            // escape된 원문은 unescape한 값보다 짧지 않으므로 원문이 짧으면 확인하지 않음
+            if let BytesOrStr::Bytes(bytes) = body {
+                if bytes.len() <= max_bytes {
+                    continue;
+                }
+            }
+            let value = body.to_unescape_str()?;
+            if value.len() <= max_bytes {
+                continue;
+            }
+            let mut end = max_bytes;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            replaces.push((field_name, index, value.len(), value[..end].to_string()));
+        }
+    }
+
+    let changed = !replaces.is_empty();
+    for (field_name, index, before, value) in replaces {
+        log::debug!(
+            "FIELD_TRUNCATED id: {}, field: {}, {} bytes -> {} bytes",
+            doc_id(doc)?,
+            field_name,
+            before,
+            value.len()
+        );
+        doc.field_as_mut()
+            .replace_field_owned(field_name.as_bytes(), index, value);
     }
-    url
+    Ok(changed)
 }
 
-/// 변경 사항이 없는 경우 메모리의 기존 데이터를 재사용하며, 변경 사항이 있는 경우에만 메모리 할당 발생
-pub enum WriteOk {
-    /// 변경사항이 없는 경우 doc 사이즈만 반환. 기존 데이터를 재사용함.
-    NoChanged(usize),
-    /// 변경 사항이 있는 경우 bytes 배열과 doc 사이즈 반환
-    Changed(Vec<u8>, usize),
+/// fields에 값이 여러개 있는 경우 첫번째 값만 남김. 변경된 경우 true
+fn dedup_fields(doc: &mut Doc, fields: &[String]) -> Result<bool, BoxedError> {
+    let mut changed = false;
+    for field_name in fields {
+        let removed = doc.field_as_mut().truncate_field(field_name.as_bytes(), 1);
+        if removed > 0 {
+            changed = true;
+            warn!(
+                "DEDUP_FIELD id: {}, field: {}, removed: {}",
+                doc_id(doc)?,
+                field_name,
+                removed
+            );
+        }
+    }
+    Ok(changed)
 }
 
-pub fn write_xml(docs: Vec<Doc>) -> Result<WriteOk, BoxedError> {
-    let doc_cnt = docs.len();
-    let any_changed = docs.iter().any(|doc| doc.field().has_changed());
+/// normalize_dates의 처리 결과
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DateResult {
+    tstamp_filled: bool,
+    postdate_rewritten: u32,
+    postdate_invalid: u32,
+}
 
-    // doc 목록중에 하나도 변경사항이 없는 경우 NoChanged return
-    // 이렇게 할 경우 전송받은 데이터를 그대로 재사용하게 됨
-    if !any_changed {
-        return Ok(WriteOk::NoChanged(doc_cnt));
+/// tstamp가 없는 경우 현재 시각을 넣고, postdate를 솔라 날짜 형식으로 변환함
+/// <br>변환할 수 없는 postdate는 그대로 두고 doc id를 로그로 남김
+fn normalize_dates(doc: &mut Doc) -> Result<DateResult, BoxedError> {
+    let mut result = DateResult::default();
+
+    if doc.field().get(COL_TSTAMP).is_none() {
+        doc.field_as_mut()
+            .push_field_owned(COL_TSTAMP, date_field::now_tstamp());
+        result.tstamp_filled = true;
+    }
+
+    let mut rewrites = Vec::new();
+    if let Some(postdates) = doc.field().get(COL_POSTDATE) {
+        for (index, postdate) in postdates.iter().enumerate() {
+            let postdate = postdate.to_unescape_str()?;
+            match date_field::check_date(&postdate) {
+                DateCheck::Valid => (),
+                DateCheck::Rewritten(value) => rewrites.push((index, value)),
+                DateCheck::Invalid => {
+                    result.postdate_invalid += 1;
+                    warn!(
+                        "INVALID_POSTDATE id: {}, postdate: {}",
+                        doc_id(doc)?,
+                        postdate
+                    );
+                }
+            }
+        }
     }
 
-    let xml_cap = docs.iter().fold(0, |sum, doc| sum + doc.ori_str().len());
+    for (index, value) in rewrites {
+        doc.field_as_mut()
+            .replace_field_owned(COL_POSTDATE, index, value);
+        result.postdate_rewritten += 1;
+    }
 
-    // 파싱된 doc이 없는 경우 NoChanged return
-    if xml_cap == 0 {
-        return Ok(WriteOk::NoChanged(doc_cnt));
+    Ok(result)
+}
+
+/// host, site 필드가 없는 경우 host로 채움. 채운 필드가 있는 경우 true
+fn fill_host_fields(doc: &mut Doc, host: &str) -> bool {
+    let mut filled = false;
+    for field_name in [COL_HOST, COL_SITE] {
+        if doc.field().get(field_name).is_none() {
+            doc.field_as_mut()
+                .push_field_owned(field_name, host.to_string());
+            filled = true;
+        }
     }
+    filled
+}
 
-    let mut writer = Writer::new(Cursor::new(Vec::with_capacity(xml_cap * 2)));
+/// doc의 url에서 추출한 값
+#[derive(Debug)]
+struct SeedHost {
+    /// 정규화된 url의 host. 카페/블로그 규칙을 적용하기 전의 값
+    host: String,
+    /// seed_id 조회에 사용하는 값
+    seed_host: String,
+    /// seed_host를 만든 url 값
+    url: String,
+}
 
-    writer.write_event(Event::Start(BytesStart::new("add")))?;
+/// url_fields 값 중 가장 긴 값의 길이(bytes). unescape하기 전의 길이
+fn longest_url_len(doc: &Doc, url_fields: &[String]) -> usize {
+    url_fields
+        .iter()
+        .filter_map(|field_name| doc.field().get(field_name.as_bytes()))
+        .flatten()
+        .map(|url| match url {
+            BytesOrStr::Bytes(bytes) => bytes.len(),
+            BytesOrStr::Str(str, _) => str.len(),
+        })
+        .max()
+        .unwrap_or(0)
+}
 
-    for doc in docs {
-        let (doc_field, ori_str) = doc.into_inner();
-        let (field, has_changed) = doc_field.into_inner();
-
-        if has_changed {
-            // doc에 변경 사항이 있는 경우 field를 순회하며 write
-            writer.write_event(Event::Start(BytesStart::new("doc")))?;
-            for (field_name, body_list) in field {
-                for body in body_list {
-                    let mut field_event = BytesStart::new("field");
-                    let attr = Attribute {
-                        key: QName(b"name"),
-                        value: Cow::Borrowed(field_name),
-                    };
-                    field_event.push_attribute(attr);
-                    writer.write_event(Event::Start(field_event))?;
+/// url_fields 순서대로 비어있지 않은 url 값을 확인하여 처음으로 seed_host를 만들 수 있는 값을 사용
+/// <br>모든 값이 실패한 경우 처음 발생한 에러 반환
+fn seed_host(doc: &Doc, rules: &[HostRule], url_fields: &[String]) -> Result<SeedHost, BoxedError> {
+    let mut first_err: Option<BoxedError> = None;
 
-                    match body {
-                        BytesOrStr::Bytes(bytes) => writer.write_event(Event::Text(bytes))?,
-                        BytesOrStr::Str(str, _) => {
-                            writer.write_event(Event::Text(BytesText::new(&str)))?
-                        }
-                    }
+    for field_name in url_fields {
+        let Some(urls) = doc.field().get(field_name.as_bytes()) else {
+            continue;
+        };
 
-                    writer.write_event(Event::End(BytesEnd::new("field")))?;
-                }
+        for url in urls {
+            let url = url.to_unescape_str()?;
+            if url.trim().is_empty() {
+                continue;
             }
 
-            writer.write_event(Event::End(BytesEnd::new("doc")))?;
-        } else {
-            // doc에 변경사항이 없는 경우 기존 doc 데이터를 그대로 다시 write
-            writer.get_mut().write_all(ori_str)?;
+            let result = split_host(&url).and_then(|(host, rest)| {
+                let seed_host = apply_host_rule(host.clone(), &rest, rules)?;
+                Ok(SeedHost {
+                    host: host.into_owned(),
+                    seed_host: seed_host.into_owned(),
+                    url: url.to_string(),
+                })
+            });
+            match result {
+                Ok(seed_host) => return Ok(seed_host),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
         }
     }
 
-    writer.write_event(Event::End(BytesEnd::new("add")))?;
+    Err(first_err.unwrap_or_else(|| Box::new(StrError::new("NOT_FOUND_URL".to_string()))))
+}
 
-    Ok(WriteOk::Changed(writer.into_inner().into_inner(), doc_cnt))
+/// url 하나로 seed_host를 만든 과정
+pub struct SeedHostTrace {
+    /// 정규화된 url의 host. 카페/블로그 규칙을 적용하기 전의 값
+    pub host: String,
+    pub seed_host: String,
+    /// 적용한 카페/블로그 규칙. host를 그대로 사용한 경우 None
+    pub rule: Option<HostRule>,
 }
 
-#[test]
-fn get_host_test() {
-    assert_eq!(
-        seed_host_str("http://m.cafe.daum.net/clzkzlck332/5cUp/7606").unwrap(),
-        "m.cafe.daum.net/clzkzlck332"
-    );
-    assert_eq!(
-        seed_host_str("http://cafe.daum.net/clzkzlck332/5cUp/7606").unwrap(),
-        "cafe.daum.net/clzkzlck332"
-    );
-    assert_eq!(
-        seed_host_str("http://m.cafe.daum.net/clzkzlck332/5cUp").unwrap(),
-        "m.cafe.daum.net/clzkzlck332"
-    );
-    assert_eq!(
-        seed_host_str("http://cafe.daum.net/clzkzlck332/5cUp").unwrap(),
+/// seed_url_fields의 첫번째 필드에 url만 있는 doc을 만들어 enrich의 seed_host로 seed_host를 만들고, 적용한 규칙을 함께 반환함
+pub fn trace_seed_host(
+    url: &str,
+    collection: &CollectionSettings,
+) -> Result<SeedHostTrace, BoxedError> {
+    let Some(url_field) = collection.seed_url_fields.first() else {
+        return Err(Box::new(StrError::new("NOT_FOUND_URL".to_string())));
+    };
+    let mut field = DocField::new();
+    field.push_field_owned(url_field.as_bytes(), url.to_string());
+    let doc = Doc::new(field, b"");
+    let SeedHost {
+        host, seed_host, ..
+    } = seed_host(&doc, &collection.host_rules, &collection.seed_url_fields)?;
+    let rule = split_host(url).ok().and_then(|(host, rest)| {
+        host_rule::find_rule(&collection.host_rules, &format!("{}{}", host, rest)).cloned()
+    });
+    Ok(SeedHostTrace {
+        host,
+        seed_host,
+        rule,
+    })
+}
+
+/// url 하나로 seed_host를 만듦
+#[cfg(test)]
+fn seed_host_str<'a>(url: &'a str, rules: &[HostRule]) -> Result<Cow<'a, str>, BoxedError> {
+    let (host, rest) = split_host(url)?;
+    apply_host_rule(host, &rest, rules)
+}
+
+/// url을 정규화된 host와 decode된 나머지 부분으로 나눔
+fn split_host(mut url: &str) -> Result<(Cow<'_, str>, Cow<'_, str>), BoxedError> {
+    // 빈 값이나 공백, 제어 문자가 포함된 값은 url이 아니므로 매핑 테이블에 넣지 않음
+    if url.is_empty() || has_invalid_char(url) {
+        return Err(Box::new(StrError::new(format!("INVALID_URL: {:?}", url))));
+    }
+    let original_url = url;
+
+    // https, http 및 //를 잘라냄. scheme은 대소문자를 구분하지 않음
+    for prefix in ["https://", "http://", "//"] {
+        if let Some(head) = url.get(..prefix.len()) {
+            if head.eq_ignore_ascii_case(prefix) {
+                url = &url[prefix.len()..];
+                break;
+            }
+        }
+    }
+
+    // host 부분만 정규화하고 뒤의 path는 대소문자를 그대로 유지함
+    let authority_end = memchr::memchr3(b'/', b'?', b'#', url.as_bytes()).unwrap_or(url.len());
+    let (authority, rest) = url.split_at(authority_end);
+    let host = normalize_host(percent_decode(authority));
+    if host.is_empty() || has_invalid_char(&host) {
+        return Err(Box::new(StrError::new(format!(
+            "INVALID_URL: {:?}",
+            original_url
+        ))));
+    }
+
+    // www.으로 시작하는 경우 잘라냄
+    let host = map_cow(host, |host| host.strip_prefix("www.").unwrap_or(host));
+
+    // 카페/블로그 path도 decode한 뒤 규칙을 적용함
+    Ok((host, percent_decode(rest)))
+}
+
+/// host에 해당하는 규칙이 있는 경우 규칙으로 추출한 값, 없는 경우 host를 seed_host로 사용
+fn apply_host_rule<'a>(
+    host: Cow<'a, str>,
+    rest: &str,
+    rules: &[HostRule],
+) -> Result<Cow<'a, str>, BoxedError> {
+    let url = format!("{}{}", host, rest);
+    if let Some(rule) = host_rule::find_rule(rules, &url) {
+        match rule.capture_regex.captures(&url) {
+            Some(cap) => {
+                // 그룹이 없는 패턴인 경우 매칭된 전체를 사용
+                let value = cap.get(1).or_else(|| cap.get(0)).unwrap().as_str();
+                Ok(Cow::Owned(value.to_string()))
+            }
+            None => Err(Box::new(StrError::new(format!(
+                "CAFE_PTRN_NOT_MATCH: {}",
+                url
+            )))),
+        }
+    } else {
+        Ok(host)
+    }
+}
+
+fn has_invalid_char(value: &str) -> bool {
+    value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// percent-encoding된 값을 decode함. decode 결과가 utf-8이 아닌 경우 원래 값을 그대로 사용
+fn percent_decode(value: &str) -> Cow<'_, str> {
+    if !value.contains('%') {
+        return Cow::Borrowed(value);
+    }
+    percent_encoding::percent_decode_str(value)
+        .decode_utf8()
+        .unwrap_or(Cow::Borrowed(value))
+}
+
+/// host를 정규화함. user:pass@ 및 :port를 제거하고, 끝의 .을 제거한 뒤 소문자로 변환
+/// <br>유니코드 host는 punycode로 변환하며, 변환에 실패한 경우 그대로 사용
+fn normalize_host(authority: Cow<'_, str>) -> Cow<'_, str> {
+    let host = map_cow(authority, |authority| {
+        // user[:pass]@ 제거
+        let mut host = match authority.rfind('@') {
+            Some(pos) => &authority[pos + 1..],
+            None => authority,
+        };
+
+        // :port 제거. [::1]:8080 처럼 ipv6인 경우 ] 뒤의 :만 port로 봄
+        let port_search_start = host.rfind(']').unwrap_or(0);
+        if let Some(pos) = host[port_search_start..].rfind(':') {
+            host = &host[..port_search_start + pos];
+        }
+
+        host.trim_end_matches('.')
+    });
+
+    if !host.is_ascii() {
+        if let Ok(ascii) = idna::domain_to_ascii(&host) {
+            return Cow::Owned(ascii);
+        }
+    }
+
+    if host.bytes().any(|c| c.is_ascii_uppercase()) {
+        Cow::Owned(host.to_ascii_lowercase())
+    } else {
+        host
+    }
+}
+
+/// Cow 안의 문자열을 잘라냄. 빌린 값인 경우 메모리 할당 없이 잘라냄
+fn map_cow<'a>(value: Cow<'a, str>, f: impl for<'b> Fn(&'b str) -> &'b str) -> Cow<'a, str> {
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(f(value)),
+        Cow::Owned(value) => {
+            let mapped = f(&value);
+            if mapped.len() == value.len() {
+                Cow::Owned(value)
+            } else {
+                Cow::Owned(mapped.to_string())
+            }
+        }
+    }
+}
+
+/// 변경 사항이 없는 경우 메모리의 기존 데이터를 재사용하며, 변경 사항이 있는 경우에만 메모리 할당 발생
+pub enum WriteOk {
+    /// 변경사항이 없는 경우 doc 사이즈만 반환. 기존 데이터를 재사용함.
+    NoChanged(usize),
+    /// 변경 사항이 있는 경우 bytes 배열과 doc 사이즈, 변경 내역 반환
+    Changed(Vec<u8>, usize, WriteReport),
+    /// write_xml_split에서 여러 <add>로 나눈 경우. 원문 순서의 chunk와 전체 doc 사이즈, 변경 내역 반환
+    Split(Vec<XmlChunk>, usize, WriteReport),
+}
+
+/// write_xml_split에서 update를 나누는 기준. 0이면 해당 기준은 사용하지 않음
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SplitLimit {
+    /// chunk 하나의 최대 doc 수
+    pub max_docs: usize,
+    /// chunk 하나의 최대 크기(bytes). doc 하나가 이보다 크면 그 doc만 chunk 하나로 보냄
+    pub max_bytes: usize,
+}
+
+impl SplitLimit {
+    pub fn is_enabled(&self) -> bool {
+        self.max_docs > 0 || self.max_bytes > 0
+    }
+
+    fn is_exceeded(&self, doc_cnt: usize, bytes_len: usize) -> bool {
+        (self.max_docs > 0 && doc_cnt > self.max_docs)
+            || (self.max_bytes > 0 && bytes_len > self.max_bytes)
+    }
+}
+
+/// 나누어 작성한 <add> 하나
+#[derive(Debug, Clone)]
+pub struct XmlChunk {
+    pub xml: Vec<u8>,
+    pub doc_cnt: usize,
+    /// 첫번째, 마지막 doc의 id. 로그용이므로 없으면 빈 문자열
+    pub first_id: String,
+    pub last_id: String,
+}
+
+/// WriteReport::changed_ids에 저장하는 최대 id 수
+pub const CHANGED_ID_SAMPLE_LEN: usize = 5;
+
+/// 작성한 xml의 변경 내역
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteReport {
+    /// 변경된 doc 수. 제거된 doc은 포함하지 않음
+    pub changed_doc_cnt: usize,
+    /// 새로 추가된 seed_id 값의 수
+    pub seed_id_added_cnt: usize,
+    /// 변경된 doc의 id. 앞에서부터 CHANGED_ID_SAMPLE_LEN개만 저장함
+    pub changed_ids: Vec<String>,
+    /// 작성한 xml 크기
+    pub bytes_len: usize,
+}
+
+impl WriteReport {
+    /// 작성하기 전의 docs로 변경 내역을 만듦. bytes_len은 작성 후에 채움
+    /// <br>
+    /// seed_id_added_cnt는 컬렉션의 seed_id 필드에 새로 넣은 값만 셈
+    fn from_docs(docs: &[Doc], seed_id_field: SeedIdField) -> Self {
+        let mut report = Self::default();
+        for doc in docs.iter().filter(|doc| doc.field().has_changed()) {
+            report.changed_doc_cnt += 1;
+            report.seed_id_added_cnt +=
+                doc.field()
+                    .get(seed_id_field.as_bytes())
+                    .map_or(0, |body_list| {
+                        body_list
+                            .iter()
+                            .filter(|body| matches!(body, BytesOrStr::Str(_, None)))
+                            .count()
+                    });
+            if report.changed_ids.len() < CHANGED_ID_SAMPLE_LEN {
+                if let Some(id) = doc
+                    .field()
+                    .get(COL_ID)
+                    .and_then(|body_list| body_list.first())
+                    .and_then(|body| body.to_unescape_str().ok())
+                {
+                    report.changed_ids.push(id.into_owned());
+                }
+            }
+        }
+        report
+    }
+
+    fn finish(mut self, xml: Vec<u8>, doc_cnt: usize) -> WriteOk {
+        self.bytes_len = xml.len();
+        WriteOk::Changed(xml, doc_cnt, self)
+    }
+}
+
+/// docs_removed가 true인 경우 원문에서 제거된 doc이 있으므로 변경 사항이 없어도 새로 작성함
+/// <br>
+/// seed_id_field는 WriteReport에서 새로 넣은 seed_id를 셀 컬렉션의 seed_id 필드
+pub fn write_xml(
+    docs: Vec<Doc>,
+    docs_removed: bool,
+    seed_id_field: SeedIdField,
+) -> Result<WriteOk, BoxedError> {
+    write_xml_with_prologue(docs, docs_removed, b"", seed_id_field)
+}
+
+/// write_xml과 같이 작성하고 <add> 앞에 prologue(xml 선언, 주석 등)를 그대로 넣음
+fn write_xml_with_prologue(
+    docs: Vec<Doc>,
+    docs_removed: bool,
+    prologue: &[u8],
+    seed_id_field: SeedIdField,
+) -> Result<WriteOk, BoxedError> {
+    let doc_cnt = docs.len();
+    let any_changed = docs_removed || docs.iter().any(|doc| doc.field().has_changed());
+
+    // doc 목록중에 하나도 변경사항이 없는 경우 NoChanged return
+    // 이렇게 할 경우 전송받은 데이터를 그대로 재사용하게 됨
+    if !any_changed {
+        return Ok(WriteOk::NoChanged(doc_cnt));
+    }
+
+    // 파싱된 doc이 없는 경우 NoChanged return. 모든 doc이 제거된 경우엔 빈 add를 작성함
+    if docs.iter().all(|doc| doc.ori_str().is_empty()) && !docs_removed {
+        return Ok(WriteOk::NoChanged(doc_cnt));
+    }
+
+    let report = WriteReport::from_docs(&docs, seed_id_field);
+    // 예상 크기만큼만 미리 할당하고 부족한 경우 늘려서 사용함
+    let xml_cap =
+        prologue.len() + b"<add></add>".len() + docs.iter().map(estimate_doc_len).sum::<usize>();
+    let mut writer = Writer::new(Cursor::new(Vec::with_capacity(xml_cap)));
+
+    writer.get_mut().write_all(prologue)?;
+    writer.write_event(Event::Start(BytesStart::new("add")))?;
+
+    for doc in docs {
+        write_doc(&mut writer, doc)?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("add")))?;
+
+    Ok(report.finish(writer.into_inner().into_inner(), doc_cnt))
+}
+
+/// envelope의 원문에서 doc 부분만 바꿔서 작성함
+/// <br>
+/// <add>의 속성, xml 선언, doc 사이의 공백과 <commit/> 등 doc이 아닌 부분과 변경 사항이 없는 doc은 원문을 그대로 복사하며,
+/// 변경된 doc만 다시 작성함. 제거된 doc의 원문은 복사하지 않음
+/// <br>
+/// 변경 사항이 없는 경우 write_xml과 같이 NoChanged. envelope에서 읽지 않은 doc이 있거나 순서가 바뀐 경우
+/// write_xml로 새로 작성하며, 이 경우 prologue만 유지하고 doc 사이의 주석 등은 유지하지 않음
+pub fn write_xml_spliced(
+    docs: Vec<Doc>,
+    envelope: &XmlEnvelope,
+    seed_id_field: SeedIdField,
+) -> Result<WriteOk, BoxedError> {
+    let doc_cnt = docs.len();
+    let docs_removed = doc_cnt < envelope.doc_ranges.len();
+    if !docs_removed && !docs.iter().any(|doc| doc.field().has_changed()) {
+        return Ok(WriteOk::NoChanged(doc_cnt));
+    }
+    if !envelope.is_spliceable(&docs) {
+        return write_xml_with_prologue(docs, docs_removed, envelope.prologue(), seed_id_field);
+    }
+
+    let report = WriteReport::from_docs(&docs, seed_id_field);
+    // 원문 크기에 변경된 doc이 늘어난 크기만큼 더해서 할당함
+    let xml_cap = envelope.xml.len()
+        + docs
+            .iter()
+            .map(|doc| estimate_doc_len(doc).saturating_sub(doc.ori_str().len()))
+            .sum::<usize>();
+    let mut writer = Writer::new(Cursor::new(Vec::with_capacity(xml_cap)));
+
+    let mut docs = docs.into_iter().peekable();
+    let mut copied = 0;
+    for range in &envelope.doc_ranges {
+        writer
+            .get_mut()
+            .write_all(&envelope.xml[copied..range.start])?;
+        copied = range.end;
+        // docs는 원문 순서이므로 다음 doc의 위치가 아니면 제거된 doc
+        if docs
+            .peek()
+            .is_some_and(|doc| envelope.doc_start(doc) == Some(range.start))
+        {
+            write_doc(&mut writer, docs.next().unwrap())?;
+        }
+    }
+    writer.get_mut().write_all(&envelope.xml[copied..])?;
+
+    Ok(report.finish(writer.into_inner().into_inner(), doc_cnt))
+}
+
+/// write_xml_spliced와 같이 작성하고, doc 수나 크기가 limit을 넘으면 limit 이하의 <add> 여러 개로 나눔
+/// <br>
+/// chunk마다 원문의 prologue와 <add> 시작 태그(속성 포함)를 그대로 사용하며, 변경 사항이 없는 doc은 원문을 그대로 복사함.
+/// <add> 하나에 doc만 있는 원문만 나누며, <commit/> 등 doc이 아닌 내용이 함께 있으면 나누지 않고 write_xml_spliced로 작성함
+pub fn write_xml_split(
+    docs: Vec<Doc>,
+    envelope: &XmlEnvelope,
+    limit: &SplitLimit,
+    seed_id_field: SeedIdField,
+) -> Result<WriteOk, BoxedError> {
+    let doc_lens: Vec<usize> = docs.iter().map(estimate_doc_len).collect();
+    let add_start = match envelope.add_start_tag() {
+        Some(add_start) if limit.is_exceeded(docs.len(), doc_lens.iter().sum()) => add_start,
+        _ => return write_xml_spliced(docs, envelope, seed_id_field),
+    };
+
+    let doc_cnt = docs.len();
+    let mut report = WriteReport::from_docs(&docs, seed_id_field);
+    let overhead = envelope.prologue().len() + add_start.len() + b"</add>".len();
+    let mut groups: Vec<(Vec<Doc>, usize)> = Vec::new();
+    for (doc, doc_len) in docs.into_iter().zip(doc_lens) {
+        let fits = groups.last().is_some_and(|(group, group_len)| {
+            (limit.max_docs == 0 || group.len() < limit.max_docs)
+                && (limit.max_bytes == 0 || group_len + doc_len <= limit.max_bytes)
+        });
+        match groups.last_mut() {
+            Some((group, group_len)) if fits => {
+                group.push(doc);
+                *group_len += doc_len;
+            }
+            _ => groups.push((vec![doc], overhead + doc_len)),
+        }
+    }
+
+    let mut chunks = Vec::with_capacity(groups.len());
+    for (group, group_len) in groups {
+        let first_id = group.first().map(doc_id).transpose()?.unwrap_or_default();
+        let last_id = group.last().map(doc_id).transpose()?.unwrap_or_default();
+        let chunk_doc_cnt = group.len();
+        let mut writer = Writer::new(Cursor::new(Vec::with_capacity(group_len)));
+        writer.get_mut().write_all(envelope.prologue())?;
+        writer.get_mut().write_all(add_start)?;
+        for doc in group {
+            write_doc(&mut writer, doc)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("add")))?;
+        chunks.push(XmlChunk {
+            xml: writer.into_inner().into_inner(),
+            doc_cnt: chunk_doc_cnt,
+            first_id,
+            last_id,
+        });
+    }
+    report.bytes_len = chunks.iter().map(|chunk| chunk.xml.len()).sum();
+    Ok(WriteOk::Split(chunks, doc_cnt, report))
+}
+
+/// write_doc으로 작성될 doc의 예상 크기
+/// <br>
+/// 변경 사항이 없는 doc은 원문 크기, 변경된 doc은 원문 크기에 추가/변경된 값의 크기를 더함
+pub fn estimate_doc_len(doc: &Doc) -> usize {
+    let ori_len = doc.ori_str().len();
+    if !doc.field().has_changed() {
+        return ori_len;
+    }
+
+    // <field name=""></field>
+    const FIELD_OVERHEAD: usize = 22;
+    let mut added: usize = 0;
+    for (field_name, body_list) in doc.field().iter() {
+        for body in body_list {
+            match body {
+                BytesOrStr::Bytes(_) => (),
+                BytesOrStr::Str(value, None) => {
+                    added += FIELD_OVERHEAD + field_name.len() + value.len();
+                }
+                BytesOrStr::Str(value, Some(ori)) => {
+                    added += value.len().saturating_sub(ori.len());
+                }
+            }
+        }
+    }
+    ori_len + added
+}
+
+/// 첫번째 doc의 id. 로그용이므로 읽을 수 없거나 id가 없으면 None
+pub fn first_doc_id(xml: &[u8]) -> Option<String> {
+    let mut reader = Reader::from_reader(xml);
+    reader.trim_text(true);
+    let mut doc_depth: usize = 0;
+    let mut in_id = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) if e.name().0 == b"doc" => doc_depth += 1,
+            Event::Start(e) if doc_depth == 1 && e.name().0 == b"field" => {
+                in_id = AttrParser::new(&e[b"field".len()..])
+                    .any(|attr| attr.name == b"name" && attr.value == COL_ID);
+            }
+            Event::Text(e) if in_id => return e.unescape().ok().map(Cow::into_owned),
+            Event::End(e) if e.name().0 == b"doc" && doc_depth == 1 => return None,
+            Event::End(e) => {
+                if e.name().0 == b"doc" {
+                    doc_depth -= 1;
+                }
+                in_id = false;
+            }
+            Event::Eof => return None,
+            _ => (),
+        }
+    }
+}
+
+/// doc 하나를 write. 변경 사항이 없는 경우 원문을 그대로 사용함
+pub fn write_doc<W: Write>(writer: &mut Writer<W>, doc: Doc) -> Result<(), BoxedError> {
+    if doc.field().has_changed() {
+        // doc에 변경 사항이 있는 경우 field를 순회하며 write
+        writer.write_event(Event::Start(BytesStart::new("doc")))?;
+        // 주석의 원래 위치는 알 수 없으므로 <doc> 바로 뒤에 넣음
+        for comment in doc.comments() {
+            writer.get_mut().write_all(comment)?;
+        }
+        let children = doc.children().to_vec();
+        let (doc_field, _) = doc.into_inner();
+        let (field, _) = doc_field.into_inner();
+        for (field_name, body_list) in field {
+            for body in body_list {
+                let mut field_event = BytesStart::new("field");
+                let attr = Attribute {
+                    key: QName(b"name"),
+                    value: Cow::Borrowed(field_name),
+                };
+                field_event.push_attribute(attr);
+                writer.write_event(Event::Start(field_event))?;
+
+                match body {
+                    BytesOrStr::Bytes(bytes) => writer.write_event(Event::Text(bytes))?,
+                    BytesOrStr::Str(str, _) => {
+                        writer.write_event(Event::Text(BytesText::new(&str)))?
+                    }
+                }
+
+                writer.write_event(Event::End(BytesEnd::new("field")))?;
+            }
+        }
+        // 자식 doc은 원문 그대로 작성함
+        for child in children {
+            writer.get_mut().write_all(child)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("doc")))?;
+    } else {
+        // doc에 변경사항이 없는 경우 기존 doc 데이터를 그대로 다시 write
+        writer.get_mut().write_all(doc.ori_str())?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn get_host_test() {
+    let rules = HostRule::defaults();
+    assert_eq!(
+        seed_host_str("http://m.cafe.daum.net/clzkzlck332/5cUp/7606", &rules).unwrap(),
+        "m.cafe.daum.net/clzkzlck332"
+    );
+    assert_eq!(
+        seed_host_str("http://cafe.daum.net/clzkzlck332/5cUp/7606", &rules).unwrap(),
+        "cafe.daum.net/clzkzlck332"
+    );
+    assert_eq!(
+        seed_host_str("http://m.cafe.daum.net/clzkzlck332/5cUp", &rules).unwrap(),
+        "m.cafe.daum.net/clzkzlck332"
+    );
+    assert_eq!(
+        seed_host_str("http://cafe.daum.net/clzkzlck332/5cUp", &rules).unwrap(),
         "cafe.daum.net/clzkzlck332"
     );
     assert_eq!(
-        seed_host_str("https://m.cafe.daum.net/clzkzlck332/5cUp/7606").unwrap(),
+        seed_host_str("https://m.cafe.daum.net/clzkzlck332/5cUp/7606", &rules).unwrap(),
         "m.cafe.daum.net/clzkzlck332"
     );
     assert_eq!(
-        seed_host_str("https://cafe.daum.net/clzkzlck332/5cUp/7606").unwrap(),
+        seed_host_str("https://cafe.daum.net/clzkzlck332/5cUp/7606", &rules).unwrap(),
         "cafe.daum.net/clzkzlck332"
     );
     assert_eq!(
-        seed_host_str("https://m.cafe.daum.net/clzkzlck332/5cUp").unwrap(),
+        seed_host_str("https://m.cafe.daum.net/clzkzlck332/5cUp", &rules).unwrap(),
         "m.cafe.daum.net/clzkzlck332"
     );
     assert_eq!(
-        seed_host_str("https://cafe.daum.net/clzkzlck332/5cUp").unwrap(),
+        seed_host_str("https://cafe.daum.net/clzkzlck332/5cUp", &rules).unwrap(),
         "cafe.daum.net/clzkzlck332"
     );
     assert_eq!(
-        seed_host_str("https://cafe.naver.com/paincare/9741").unwrap(),
+        seed_host_str("https://cafe.naver.com/paincare/9741", &rules).unwrap(),
         "cafe.naver.com/paincare"
     );
     assert_eq!(
-        seed_host_str("https://cafe.naver.com/paincare").unwrap(),
+        seed_host_str("https://cafe.naver.com/paincare", &rules).unwrap(),
         "cafe.naver.com/paincare"
     );
     assert_eq!(
-        seed_host_str("http://cafe.naver.com/paincare/9741").unwrap(),
+        seed_host_str("http://cafe.naver.com/paincare/9741", &rules).unwrap(),
         "cafe.naver.com/paincare"
     );
     assert_eq!(
-        seed_host_str("http://cafe.naver.com/paincare").unwrap(),
+        seed_host_str("http://cafe.naver.com/paincare", &rules).unwrap(),
         "cafe.naver.com/paincare"
     );
     assert_eq!(
-        seed_host_str("https://blog.naver.com/kimeunha99/222856865611").unwrap(),
+        seed_host_str("https://blog.naver.com/kimeunha99/222856865611", &rules).unwrap(),
         "blog.naver.com/kimeunha99"
     );
     assert_eq!(
-        seed_host_str("http://blog.naver.com/kimeunha99/222856865611").unwrap(),
+        seed_host_str("http://blog.naver.com/kimeunha99/222856865611", &rules).unwrap(),
         "blog.naver.com/kimeunha99"
     );
     assert_eq!(
-        seed_host_str("http://twitter.com/yutaaaaaaaa1103/statuses/1559878365196468224").unwrap(),
+        seed_host_str(
+            "http://twitter.com/yutaaaaaaaa1103/statuses/1559878365196468224",
+            &rules
+        )
+        .unwrap(),
         "twitter.com"
     );
     assert_eq!(
-        seed_host_str("http://www.fomos.kr/game/news_view?lurl=%2Fgame%2Fnews_list%3Fnews_cate_id%3D2&entry_id=113622#111").unwrap(),
+        seed_host_str("http://www.fomos.kr/game/news_view?lurl=%2Fgame%2Fnews_list%3Fnews_cate_id%3D2&entry_id=113622#111", &rules).unwrap(),
+        "fomos.kr"
+    );
+    assert_eq!(
+        seed_host_str("http://www.fomos.kr#111", &rules).unwrap(),
+        "fomos.kr"
+    );
+
+    // host는 소문자로 정규화하고 port, userinfo, 끝의 .은 제거
+    assert_eq!(
+        seed_host_str("http://Example.COM:8080/Page", &rules).unwrap(),
+        "example.com"
+    );
+    assert_eq!(
+        seed_host_str("https://WWW.Fomos.kr./game", &rules).unwrap(),
         "fomos.kr"
     );
     assert_eq!(
-        seed_host_str("http://www.fomos.kr#111").unwrap(),
+        seed_host_str("http://user:pa:ss@twitter.com:443/abc", &rules).unwrap(),
+        "twitter.com"
+    );
+    assert_eq!(
+        seed_host_str("http://[::1]:8080/abc", &rules).unwrap(),
+        "[::1]"
+    );
+    assert_eq!(
+        seed_host_str("http://fomos.kr?entry_id=1", &rules).unwrap(),
         "fomos.kr"
     );
+
+    // scheme이 대문자이거나 없는 경우, //로 시작하는 경우
+    assert_eq!(
+        seed_host_str("HTTP://twitter.com/abc", &rules).unwrap(),
+        "twitter.com"
+    );
+    assert_eq!(
+        seed_host_str("Https://cafe.naver.com/paincare/9741", &rules).unwrap(),
+        "cafe.naver.com/paincare"
+    );
+    assert_eq!(
+        seed_host_str("//cdn.example.com/x", &rules).unwrap(),
+        "cdn.example.com"
+    );
+    assert_eq!(
+        seed_host_str("example.com/page", &rules).unwrap(),
+        "example.com"
+    );
+
+    // url이 아닌 값은 에러
+    for junk in [
+        "",
+        "http://",
+        "//",
+        "not a url",
+        "example.com/\tpage",
+        "\u{0}abc",
+        "http://:80/x",
+    ] {
+        let err = seed_host_str(junk, &rules).unwrap_err().to_string();
+        assert!(err.starts_with("INVALID_URL"), "{}: {}", junk, err);
+    }
+
+    // 한글 도메인은 유니코드, percent-encoding, punycode 모두 같은 seed_host
+    for url in [
+        "http://한국.kr/abc",
+        "http://%ED%95%9C%EA%B5%AD.kr/abc",
+        "http://xn--3e0b707e.kr/abc",
+        "http://XN--3E0B707E.KR/abc",
+    ] {
+        assert_eq!(
+            seed_host_str(url, &rules).unwrap(),
+            "xn--3e0b707e.kr",
+            "{}",
+            url
+        );
+    }
+
+    // 카페/블로그 path도 decode 후 추출
+    assert_eq!(
+        seed_host_str("https://cafe.naver.com/%ED%95%9C%EA%B5%AD/1", &rules).unwrap(),
+        "cafe.naver.com/한국"
+    );
+
+    // decode에 실패한 경우 원래 값을 사용
+    assert_eq!(
+        seed_host_str("https://cafe.naver.com/ab%FFcd/1", &rules).unwrap(),
+        "cafe.naver.com/ab%FFcd"
+    );
+    assert_eq!(
+        seed_host_str("http://ab%FF.com/x", &rules).unwrap(),
+        "ab%ff.com"
+    );
+
+    // 카페/블로그는 host만 소문자로 바꾸고 path의 대소문자는 유지
+    assert_eq!(
+        seed_host_str("https://CAFE.Naver.com:443/PainCare/9741", &rules).unwrap(),
+        "cafe.naver.com/PainCare"
+    );
+    assert_eq!(
+        seed_host_str("http://user@Blog.Naver.Com./KimEunha99/1", &rules).unwrap(),
+        "blog.naver.com/KimEunha99"
+    );
+}
+
+#[test]
+fn custom_host_rule_test() {
+    let mut rules = HostRule::defaults();
+    rules.insert(
+        0,
+        HostRule::new("m.blog.naver.com".to_string(), r#"^([^/]+/[^/]+)"#).unwrap(),
+    );
+    rules.push(HostRule::new("youtube.com/@".to_string(), r#"^(youtube\.com/@[^/?#]+)"#).unwrap());
+
+    assert_eq!(
+        seed_host_str("https://m.blog.naver.com/kimeunha99/222856865611", &rules).unwrap(),
+        "m.blog.naver.com/kimeunha99"
+    );
+    assert_eq!(
+        seed_host_str("https://www.youtube.com/@channel?tab=videos", &rules).unwrap(),
+        "youtube.com/@channel"
+    );
+    // 규칙에 없는 경우 host만 사용
+    assert_eq!(
+        seed_host_str("https://youtube.com/watch?v=abc", &rules).unwrap(),
+        "youtube.com"
+    );
+    // 기본 규칙은 그대로 유지됨
+    assert_eq!(
+        seed_host_str("https://cafe.naver.com/paincare/9741", &rules).unwrap(),
+        "cafe.naver.com/paincare"
+    );
 }
 
+/// 테스트용 update 요청 예시
+#[cfg(test)]
+pub const SAMPLE_XML: &str = include_str!("../fixtures/sample_update.xml");
+
 #[tokio::test]
 async fn doc_read_test() {
-    let xml = r#"
-<add><doc boost="1.0"><field name="id">a77b3908fb67bd1b</field><field name="crawler_type">crawler</field><field name="crawl_runtime_key">127.0.0.1</field><field name="host">www.lenews.co.kr</field><field name="site">www.lenews.co.kr</field><field name="url">https://cafe.naver.com/moonlightriverside/185</field><field name="title">삼성ENG, 2분기 영업이익 1535억</field><field name="content">[국토경제신 
-문 박태선 기자] 삼성엔지니어링이 2분기 영업이익 1535억 원을 달성했다. </field><field name="postdate">2022-07-28T04:48:00.000Z</field><field name="doc_version">10</field><field name="etc_exact1">1</field><field name="tstamp">2022-07-28T06:56:30.487Z</field></doc><doc boost="1.0"><field name="id">c0046e9c36e35a60</field><field name="crawler_type">crawler</field><field name="crawl_runtime_key">127.0.0.1</field><field name="host">www.lenews.co.kr</field><field name="site">www.lenews.co.kr</field><field name="url">http://www.lenews.co.kr/news/articleView.html?idxno=90124</field><field name="title">현대제철, 전기안전공사와 철강부문 전기안전 기술협력</field><field name="content">[국토경제신문 박태선 기자] 현대제철은 27일 한국전기안전공사와 ‘철강부문 전기안전 기술교류 업무 협약’을 체결했다.</field><field name="postdate">2022-07-28T03:54:00.000Z</field><field name="etc_array_text1">https://cdn.lenews.co.kr/news/photo/202207/90124_70053_2859.jpg</field><field name="seed_id">f371ba73-7e23-11ea-9ea0-fa163e9f6f72</field><field name="seed_id">SECOND</field><field name="doc_version">10</field><field name="etc_exact1">1</field><field name="tstamp">2022-07-28T06:56:30.487Z</field></doc></add>
-   "#;
+    let xml = SAMPLE_XML;
 
-    let mut docs = read_xml(xml.as_bytes()).unwrap();
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
 
     for doc in &docs {
         let cut_ori_str = String::from_utf8_lossy(&doc.ori_str()[1..]);
@@ -394,7 +1881,7 @@ async fn doc_read_test() {
     );
 
     assert_eq!(
-        doc.field().get(COL_URL).unwrap()[0]
+        doc.field().get(COL_URL.as_bytes()).unwrap()[0]
             .to_unescape_str()
             .unwrap(),
         "https://cafe.naver.com/moonlightriverside/185"
@@ -409,27 +1896,1833 @@ async fn doc_read_test() {
     );
 
     assert_eq!(
-        doc.field().get(COL_URL).unwrap()[0]
+        doc.field().get(COL_URL.as_bytes()).unwrap()[0]
             .to_unescape_str()
             .unwrap(),
         "http://www.lenews.co.kr/news/articleView.html?idxno=90124"
     );
 
-    proc_xml(&mut docs).await.unwrap();
-    let result = write_xml(docs).unwrap();
-    let WriteOk::Changed(final_xml, size) = result else {
+    proc_xml(
+        &mut docs,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    let result = write_xml(docs, false, SeedIdField::default()).unwrap();
+    let WriteOk::Changed(final_xml, size, report) = result else {
         panic!("result is not WriteOk::Changed");
     };
+    // seed_id가 없던 첫번째 doc만 변경됨
+    assert_eq!(
+        report,
+        WriteReport {
+            changed_doc_cnt: 1,
+            seed_id_added_cnt: 1,
+            changed_ids: vec!["a77b3908fb67bd1b".to_string()],
+            bytes_len: final_xml.len(),
+        }
+    );
 
     assert!(final_xml.starts_with(b"<add><doc"));
     assert!(final_xml.ends_with(b"</field></doc></add>"));
     assert_eq!(size, 2);
-    let final_read = read_xml(&final_xml).unwrap();
+    let final_read = read_xml(&final_xml, &ReadLimit::default()).unwrap();
     assert_eq!(final_read.len(), 2);
     assert_eq!(
-        final_read[0].field().get(COL_SEED_ID).unwrap()[0]
+        final_read[0].field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
             .to_unescape_str()
             .unwrap(),
         "e7531c15-2384-11ed-b560-42010a025a43"
     );
 }
+
+#[tokio::test]
+async fn cache_writer_dedup_test() {
+    use crate::mock::MockSeedIdStore;
+
+    static WRITER: SyncLazy<CacheWriter> = SyncLazy::new(|| CacheWriter::new(&SEED_ID_CACHE));
+    WRITER.start();
+
+    // writer task가 cache에 넣기 전이라도 같은 요청의 doc은 같은 seed_host를 한 번만 조회함
+    // DB에 있는 seed_host는 SELECT 한 번, 새 seed_host는 SELECT, INSERT, SELECT 한 번씩
+    let xml = br#"<add><doc><field name="url">https://writer-dedup.example.com/news/1</field></doc><doc><field name="url">https://writer-known.example.com/news/1</field></doc><doc><field name="url">https://writer-dedup.example.com/news/2</field></doc><doc><field name="url">https://writer-known.example.com/news/2</field></doc></add>"#;
+    let store = MockSeedIdStore::default().with_row(
+        "writer-known.example.com",
+        "seed-writer-known",
+        media_type::MediaType::default(),
+    );
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with_writer(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+        &WRITER,
+    )
+    .await
+    .unwrap();
+    let mut selected = store.selected();
+    selected.sort();
+    assert_eq!(
+        selected,
+        vec![
+            "writer-dedup.example.com",
+            "writer-dedup.example.com",
+            "writer-known.example.com"
+        ]
+    );
+    assert_eq!(store.inserted_hosts(), vec!["writer-dedup.example.com"]);
+    let seed_ids: Vec<String> = docs
+        .iter()
+        .map(|doc| {
+            doc.field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+                .to_unescape_str()
+                .unwrap()
+                .into_owned()
+        })
+        .collect();
+    assert_eq!(
+        seed_ids,
+        [
+            "seed-writer-dedup.example.com",
+            "seed-writer-known",
+            "seed-writer-dedup.example.com",
+            "seed-writer-known"
+        ]
+    );
+
+    // writer task가 넣은 값은 이후 cache에서 조회됨
+    WRITER.drain().await;
+    assert_eq!(
+        SEED_ID_CACHE
+            .get("writer-dedup.example.com")
+            .await
+            .as_deref(),
+        Some("seed-writer-dedup.example.com")
+    );
+    assert_eq!(
+        SEED_ID_CACHE
+            .get("writer-known.example.com")
+            .await
+            .as_deref(),
+        Some("seed-writer-known")
+    );
+}
+
+#[tokio::test]
+async fn seed_audit_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let xml = br#"<add><doc><field name="id">a1</field><field name="url">https://audit-test.example.com/news/1</field></doc><doc><field name="url">https://AUDIT-TEST.example.com/news/2</field></doc></add>"#;
+    let options = ProcOptions {
+        remote_ip: Some("10.0.0.7".parse().unwrap()),
+        ..ProcOptions::default()
+    };
+    let store = MockSeedIdStore::default();
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(&mut docs, &store, &options, &mut ProcTiming::default())
+        .await
+        .unwrap();
+
+    // 같은 seed_host의 두번째 doc은 cache를 사용하므로 기록이 하나만 남음
+    assert_eq!(
+        store.audits(),
+        vec![SeedAudit {
+            seed_host: "audit-test.example.com".to_string(),
+            seed_id: "seed-audit-test.example.com".to_string(),
+            doc_id: Some("a1".to_string()),
+            url: "https://audit-test.example.com/news/1".to_string(),
+            remote_ip: options.remote_ip,
+            media_type: media_type::MediaType::default(),
+        }]
+    );
+
+    // 기록을 저장하지 못해도 seed_id는 넣음
+    let xml = br#"<add><doc><field name="url">https://audit-fail.example.com/</field></doc></add>"#;
+    let store = MockSeedIdStore::default().failing_audit();
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        docs[0].field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "seed-audit-fail.example.com"
+    );
+
+    // 다른 요청이 먼저 INSERT한 경우 seed_id는 넣지만 기록은 그 요청에서 남김
+    let xml = br#"<add><doc><field name="url">https://audit-lost.example.com/</field></doc></add>"#;
+    let store = MockSeedIdStore::default().losing_insert();
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        docs[0].field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "seed-audit-lost.example.com"
+    );
+    assert!(store.audits().is_empty());
+
+    // id를 읽을 수 없어도 기록의 doc_id만 비우고 seed_id는 넣음
+    let xml = br#"<add><doc><field name="id">a&bogus;</field><field name="url">https://audit-bad-id.example.com/</field></doc></add>"#;
+    let store = MockSeedIdStore::default();
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    let audits = store.audits();
+    assert_eq!(audits.len(), 1);
+    assert_eq!(audits[0].doc_id, None);
+    assert_eq!(audits[0].seed_id, "seed-audit-bad-id.example.com");
+}
+
+#[tokio::test]
+async fn media_type_insert_test() {
+    use crate::media_type::{MediaType, MediaTypeRule};
+    use crate::mock::MockSeedIdStore;
+
+    let rules = vec![
+        MediaTypeRule::new(
+            r"^cafe\.naver\.com/(?P<cafe>[^/]+)$",
+            2,
+            "${cafe}".to_string(),
+        )
+        .unwrap(),
+        MediaTypeRule::new(r"^blog\.naver\.com/([^/]+)$", 3, "blog $1".to_string()).unwrap(),
+        MediaTypeRule::new(r"^(twitter|x)\.com$", 4, "twitter".to_string()).unwrap(),
+    ];
+    let options = ProcOptions {
+        collection: Some(Arc::new(CollectionSettings {
+            name: "media_type_test".to_string(),
+            cache_namespace: "media_type_test".to_string(),
+            host_rules: HostRule::defaults(),
+            media_type_rules: rules,
+            seed_url_fields: vec!["url".to_string()],
+            ..CollectionSettings::default()
+        })),
+        ..ProcOptions::default()
+    };
+    let xml = br#"<add><doc><field name="url">https://cafe.naver.com/mtcafe/12</field></doc><doc><field name="url">https://blog.naver.com/mtblog/34</field></doc><doc><field name="url">https://twitter.com/someone/status/1</field></doc><doc><field name="url">https://media-type-news.example.com/a</field></doc></add>"#;
+    let store = MockSeedIdStore::default();
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(&mut docs, &store, &options, &mut ProcTiming::default())
+        .await
+        .unwrap();
+
+    // 처음 맞는 규칙의 media_type_no와 site_name으로 INSERT하고, 맞는 규칙이 없으면 기본값
+    let media_type = |media_type_no, site_name: &str| MediaType {
+        media_type_no,
+        site_name: site_name.to_string(),
+    };
+    let mut inserted = store.inserted();
+    inserted.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        inserted,
+        vec![
+            (
+                "blog.naver.com/mtblog".to_string(),
+                media_type(3, "blog mtblog")
+            ),
+            ("cafe.naver.com/mtcafe".to_string(), media_type(2, "mtcafe")),
+            (
+                "media-type-news.example.com".to_string(),
+                MediaType::default()
+            ),
+            ("twitter.com".to_string(), media_type(4, "twitter")),
+        ]
+    );
+
+    // 기록에도 INSERT한 분류를 남김
+    let audits = store.audits();
+    assert_eq!(audits.len(), 4);
+    let cafe = audits
+        .iter()
+        .find(|audit| audit.seed_host == "cafe.naver.com/mtcafe")
+        .unwrap();
+    assert_eq!(cafe.media_type, media_type(2, "mtcafe"));
+    assert!(cafe.line().ends_with("media_type_no: 2, site_name: mtcafe"));
+}
+
+#[tokio::test]
+async fn insert_guard_test() {
+    use crate::insert_guard::InsertGuard;
+    use crate::mock::MockSeedIdStore;
+    use crate::seed_store::InsertGuardStore;
+
+    let seed_ids = |docs: &[Doc]| -> Vec<Option<String>> {
+        docs.iter()
+            .map(|doc| {
+                doc.field()
+                    .get(COL_SEED_ID.as_bytes())
+                    .map(|values| values[0].to_unescape_str().unwrap().into_owned())
+            })
+            .collect()
+    };
+    let guard = InsertGuard::new(1);
+    let store = InsertGuardStore::new(MockSeedIdStore::default(), &guard);
+
+    // 두번째 새 seed_host부터 INSERT하지 않고, 이미 만든 seed_host는 cache를 사용함
+    let xml = br#"<add><doc><field name="url">https://guard-a.example.com/1</field></doc><doc><field name="url">https://guard-b.example.com/1</field></doc><doc><field name="url">https://guard-a.example.com/2</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let mut timing = ProcTiming::default();
+    proc_xml_with(&mut docs, &store, &ProcOptions::default(), &mut timing)
+        .await
+        .unwrap();
+    assert_eq!(
+        seed_ids(&docs),
+        vec![
+            Some("seed-guard-a.example.com".to_string()),
+            None,
+            Some("seed-guard-a.example.com".to_string()),
+        ]
+    );
+    assert_eq!(timing.cache_hit, 1);
+    assert!(guard.is_tripped());
+    assert_eq!(store.inner().inserted_hosts(), ["guard-a.example.com"]);
+
+    // 다음 구간에는 다시 INSERT함
+    guard.reset();
+    let xml = br#"<add><doc><field name="url">https://guard-b.example.com/2</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        seed_ids(&docs),
+        vec![Some("seed-guard-b.example.com".to_string())]
+    );
+
+    // 다른 요청이 먼저 INSERT한 seed_host는 새로 INSERT한 수로 세지 않음
+    let guard = InsertGuard::new(1);
+    let store = InsertGuardStore::new(MockSeedIdStore::default().losing_insert(), &guard);
+    for host in ["guard-lost-a.example.com", "guard-lost-b.example.com"] {
+        let xml = format!(
+            r#"<add><doc><field name="url">https://{}/1</field></doc></add>"#,
+            host
+        );
+        let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+        proc_xml_with(
+            &mut docs,
+            &store,
+            &ProcOptions::default(),
+            &mut ProcTiming::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(seed_ids(&docs), vec![Some(format!("seed-{}", host))]);
+    }
+    assert!(!guard.is_tripped());
+    assert!(guard.try_insert());
+}
+
+#[tokio::test]
+async fn invalid_seed_id_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let store = MockSeedIdStore::fixed("0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d");
+    let xml = br#"<add><doc><field name="id">1</field><field name="url">https://invalid-seed.example.com/</field><field name="seed_id">f371ba73-7e23-11ea-9ea0-fa163e9f6f72</field><field name="seed_id">SECOND</field></doc><doc><field name="id">2</field><field name="url">https://invalid-seed.example.com/</field><field name="seed_id">null</field></doc><doc><field name="id">3</field><field name="seed_id">e7531c15-2384-11ed-b560-42010a025a43</field></doc></add>"#;
+    let seed_ids = |doc: &Doc| -> Vec<String> {
+        doc.field()
+            .get(COL_SEED_ID.as_bytes())
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|value| value.to_unescape_str().unwrap().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // 기본값은 확인하지 않으므로 그대로 솔라로 보냄
+    assert!(!settings().validate_incoming_seed_id);
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(seed_ids(&docs[1]), vec!["null"]);
+    assert!(matches!(
+        write_xml(docs, false, SeedIdField::default()).unwrap(),
+        WriteOk::NoChanged(3)
+    ));
+
+    // 맞지 않는 값만 지우고, 모두 지운 doc은 다시 조회함
+    let regex = &settings().seed_id_regex;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(
+        remove_invalid_seed_ids(&mut docs[0], COL_SEED_ID.as_bytes(), regex).unwrap(),
+        1
+    );
+    assert_eq!(
+        seed_ids(&docs[0]),
+        vec!["f371ba73-7e23-11ea-9ea0-fa163e9f6f72"]
+    );
+    assert_eq!(
+        remove_invalid_seed_ids(&mut docs[1], COL_SEED_ID.as_bytes(), regex).unwrap(),
+        1
+    );
+    assert!(docs[1].field().get(COL_SEED_ID.as_bytes()).is_none());
+    assert!(docs[1].field().has_changed());
+    assert_eq!(
+        remove_invalid_seed_ids(&mut docs[2], COL_SEED_ID.as_bytes(), regex).unwrap(),
+        0
+    );
+    assert!(!docs[2].field().has_changed());
+
+    proc_xml_with(
+        &mut docs,
+        &store,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        seed_ids(&docs[1]),
+        vec!["0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"]
+    );
+    let WriteOk::Changed(final_xml, _, report) =
+        write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    // 1번 doc은 seed_id가 제거되기만 했으므로 추가된 seed_id는 2번 doc의 값 하나
+    assert_eq!(report.changed_doc_cnt, 2);
+    assert_eq!(report.seed_id_added_cnt, 1);
+    assert_eq!(report.changed_ids, vec!["1", "2"]);
+    assert_eq!(report.bytes_len, final_xml.len());
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(!final_xml.contains("SECOND"));
+    assert!(!final_xml.contains("null"));
+
+    // validate_incoming_seed_id를 켠 컬렉션은 proc_xml_with에서 맞지 않는 값을 지우고 doc 수를 셈
+    let options = ProcOptions {
+        collection: Some(Arc::new(CollectionSettings {
+            name: "invalid_seed_test".to_string(),
+            cache_namespace: "invalid_seed_test".to_string(),
+            host_rules: HostRule::defaults(),
+            seed_url_fields: vec!["url".to_string()],
+            validate_incoming_seed_id: true,
+            ..CollectionSettings::default()
+        })),
+        ..ProcOptions::default()
+    };
+    let before = WORKING_CNT.lock().await.invalid_seed_id_cnt;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(&mut docs, &store, &options, &mut ProcTiming::default())
+        .await
+        .unwrap();
+    // 다른 테스트와 같은 카운터를 사용하므로 증가한 만큼 이상인지만 확인함
+    assert!(WORKING_CNT.lock().await.invalid_seed_id_cnt >= before + 2);
+    assert_eq!(
+        seed_ids(&docs[0]),
+        vec!["f371ba73-7e23-11ea-9ea0-fa163e9f6f72"]
+    );
+    assert_eq!(
+        seed_ids(&docs[1]),
+        vec!["0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"]
+    );
+    assert_eq!(
+        seed_ids(&docs[2]),
+        vec!["e7531c15-2384-11ed-b560-42010a025a43"]
+    );
+}
+
+#[tokio::test]
+async fn concurrent_enrich_test() {
+    use crate::mock::MockSeedIdStore;
+
+    /// 조회마다 걸리는 시간
+    const DELAY: Duration = Duration::from_millis(50);
+
+    // 서로 다른 seed_host 8개와 같은 seed_host를 사용하는 doc
+    let mut xml = String::from("<add>");
+    for i in 0..8 {
+        xml.push_str(&format!(
+            r#"<doc><field name="id">{}</field><field name="url">https://concurrent-{}.example.com/</field></doc>"#,
+            i, i
+        ));
+    }
+    xml.push_str(r#"<doc><field name="id">8</field><field name="url">https://concurrent-3.example.com/other</field></doc></add>"#);
+
+    let store = MockSeedIdStore::every_host().with_delay(DELAY);
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    let mut timing = ProcTiming::default();
+    let start = Instant::now();
+    proc_xml_with(&mut docs, &store, &ProcOptions::default(), &mut timing)
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    // 하나씩 조회하면 DELAY * 8 이상 걸림
+    assert!(elapsed < DELAY * 4, "elapsed: {:?}", elapsed);
+    assert!(store.max_running() > 1);
+    // 같은 seed_host는 한 번만 조회함
+    assert_eq!(store.selected().len(), 8);
+    assert_eq!((timing.cache_miss, timing.cache_hit), (8, 1));
+    for (i, doc) in docs.iter().enumerate() {
+        let host = if i == 8 { 3 } else { i };
+        let expected = format!("seed-concurrent-{}.example.com", host);
+        assert_eq!(
+            doc.field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+                .to_unescape_str()
+                .unwrap(),
+            expected
+        );
+    }
+}
+
+#[tokio::test]
+async fn single_flight_enrich_test() {
+    use crate::mock::MockSeedIdStore;
+
+    // 새 seed_host를 동시에 받은 두 요청 중 하나만 DB에서 조회하고 INSERT함
+    let store = MockSeedIdStore::default().with_delay(Duration::from_millis(50));
+    let xml = |id: &str| {
+        format!(
+            r#"<add><doc><field name="id">{}</field><field name="url">https://single-flight.example.com/{}</field></doc></add>"#,
+            id, id
+        )
+    };
+    let (first, second) = (xml("1"), xml("2"));
+    let mut first_docs = read_xml(first.as_bytes(), &ReadLimit::default()).unwrap();
+    let mut second_docs = read_xml(second.as_bytes(), &ReadLimit::default()).unwrap();
+    let options = ProcOptions::default();
+    let (mut first_timing, mut second_timing) = (ProcTiming::default(), ProcTiming::default());
+    let (first_result, second_result) = tokio::join!(
+        proc_xml_with(&mut first_docs, &store, &options, &mut first_timing),
+        proc_xml_with(&mut second_docs, &store, &options, &mut second_timing),
+    );
+    first_result.unwrap();
+    second_result.unwrap();
+
+    assert_eq!(store.inserted_hosts(), ["single-flight.example.com"]);
+    // SELECT, INSERT 후 다시 SELECT만 함
+    assert_eq!(store.selected().len(), 2);
+    assert_eq!(store.audits().len(), 1);
+    for docs in [&first_docs, &second_docs] {
+        assert_eq!(
+            docs[0].field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+                .to_unescape_str()
+                .unwrap(),
+            "seed-single-flight.example.com"
+        );
+    }
+}
+
+#[test]
+fn read_limit_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="url">http://a.com</field></doc><doc><field name="id">2</field></doc></add>"#;
+
+    let limit = ReadLimit {
+        max_docs: 2,
+        max_fields_per_doc: 2,
+    };
+    assert_eq!(read_xml(xml, &limit).unwrap().len(), 2);
+
+    let limit = ReadLimit {
+        max_docs: 1,
+        max_fields_per_doc: 0,
+    };
+    let err = read_xml(xml, &limit).unwrap_err();
+    assert!(err.to_string().starts_with("MAX_DOCS_PER_UPDATE_EXCEEDED"));
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::BAD_REQUEST)
+    );
+
+    let limit = ReadLimit {
+        max_docs: 0,
+        max_fields_per_doc: 1,
+    };
+    let err = read_xml(xml, &limit).unwrap_err();
+    assert!(err.to_string().starts_with("MAX_FIELDS_PER_DOC_EXCEEDED"));
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::BAD_REQUEST)
+    );
+}
+
+#[test]
+fn unquoted_field_name_test() {
+    let xml = br#"<add><doc><field name=id>1</field><field name='url'>http://a.com</field><field name=media_type >cafe</field></doc></add>"#;
+    let docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let field = docs[0].field();
+    for (name, value) in [("id", "1"), ("url", "http://a.com"), ("media_type", "cafe")] {
+        let body_list = field.get(name.as_bytes()).unwrap();
+        assert_eq!(body_list[0].to_unescape_str().unwrap(), value);
+    }
+}
+
+#[tokio::test]
+async fn changed_content_length_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="url">http://a.com</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    docs[0].field_as_mut().push_field_owned(
+        COL_SEED_ID.as_bytes(),
+        "f371ba73-7e23-11ea-9ea0-fa163e9f6f72".to_string(),
+    );
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert!(final_xml.len() > xml.len());
+
+    // 원래 요청의 Content-Length를 지운 뒤 변경된 body를 보냄
+    let mut header_map = hyper::HeaderMap::new();
+    header_map.insert(hyper::header::CONTENT_LENGTH, xml.len().into());
+    crate::util::remove_resent_body_headers(&mut header_map);
+
+    let mut mock = crate::mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let final_len = final_xml.len();
+    solr.send_request(
+        hyper::Uri::from_static("/solr/core/update"),
+        hyper::Method::POST,
+        header_map,
+        hyper::Body::from(final_xml),
+        &crate::context::RequestContext {
+            request_id: "test-request".to_string(),
+            remote_ip: "127.0.0.1:5000".parse().unwrap(),
+            scheme: crate::context::Scheme::Http,
+        },
+    )
+    .await
+    .unwrap();
+
+    let captured = mock.next_request().await;
+    assert_eq!(
+        captured.headers[hyper::header::CONTENT_LENGTH],
+        final_len.to_string().as_str()
+    );
+    assert_eq!(captured.body.len(), final_len);
+}
+
+#[tokio::test]
+async fn junk_url_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="url">//</field></doc></add>"#;
+
+    // DB에 접근하기 전에 에러가 발생해야 함. 테스트 환경에서는 DB 접근 시 panic 발생
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let err = proc_xml(
+        &mut docs,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap_err()
+    .to_string();
+    assert!(err.starts_with("INVALID_URL"));
+
+    let (err_cnt, seed_id_insert_cnt) = {
+        let cnt_lock = WORKING_CNT.lock().await;
+        (cnt_lock.err_cnt, cnt_lock.seed_id_insert_cnt)
+    };
+
+    // 요청 전체로 보냈을 때도 INSERT 없이 err_cnt만 증가하고 원문이 그대로 솔라에 전달됨
+    let mut mock = crate::mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let req = hyper::Request::post("/solr/core/update")
+        .body(hyper::Body::from(&xml[..]))
+        .unwrap();
+    let response = crate::handle(req, "127.0.0.1:5000".parse().unwrap(), &solr)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(&mock.next_request().await.body[..], &xml[..]);
+
+    let cnt_lock = WORKING_CNT.lock().await;
+    assert!(cnt_lock.err_cnt > err_cnt);
+    assert_eq!(cnt_lock.seed_id_insert_cnt, seed_id_insert_cnt);
+}
+
+#[test]
+fn multi_url_test() {
+    let rules = HostRule::defaults();
+    let url_fields = vec![COL_URL.to_string()];
+    let seed_host_of = |xml: &[u8], url_fields: &[String]| {
+        let docs = read_xml(xml, &ReadLimit::default()).unwrap();
+        seed_host(&docs[0], &rules, url_fields)
+    };
+
+    // 첫번째 url이 비어있는 경우 두번째 url 사용
+    let xml = br#"<add><doc><field name="url"></field><field name="url">http://twitter.com/abc</field></doc></add>"#;
+    assert_eq!(
+        seed_host_of(xml, &url_fields).unwrap().seed_host,
+        "twitter.com"
+    );
+
+    // 두번째 url만 카페 패턴에 맞는 경우
+    let xml = br#"<add><doc><field name="url">https://cafe.naver.com</field><field name="url">https://cafe.naver.com/paincare/9741</field></doc></add>"#;
+    assert_eq!(
+        seed_host_of(xml, &url_fields).unwrap().seed_host,
+        "cafe.naver.com/paincare"
+    );
+
+    // 모두 실패한 경우 처음 발생한 에러
+    let xml = br#"<add><doc><field name="url">https://cafe.naver.com</field><field name="url">//</field></doc></add>"#;
+    let err = seed_host_of(xml, &url_fields).unwrap_err().to_string();
+    assert!(err.starts_with("CAFE_PTRN_NOT_MATCH"));
+
+    // url이 없거나 모두 빈 값인 경우
+    let xml = br#"<add><doc><field name="url"> </field></doc></add>"#;
+    let err = seed_host_of(xml, &url_fields).unwrap_err().to_string();
+    assert_eq!(err, "NOT_FOUND_URL");
+
+    // canonical_url이 있는 경우 우선 사용하고, 없으면 url 사용
+    let url_fields = vec!["canonical_url".to_string(), COL_URL.to_string()];
+    let xml = br#"<add><doc><field name="url">http://amp.example.com/a</field><field name="canonical_url">http://example.com/a</field></doc></add>"#;
+    assert_eq!(
+        seed_host_of(xml, &url_fields).unwrap().seed_host,
+        "example.com"
+    );
+    let xml = br#"<add><doc><field name="url">http://amp.example.com/a</field></doc></add>"#;
+    assert_eq!(
+        seed_host_of(xml, &url_fields).unwrap().seed_host,
+        "amp.example.com"
+    );
+}
+
+#[test]
+fn fill_host_fields_test() {
+    let xml = br#"<add>
+<doc><field name="url">https://www.Cafe.naver.com/paincare/1</field><field name="site">cafe</field></doc>
+<doc><field name="url">http://twitter.com/abc</field></doc>
+<doc><field name="url">http://twitter.com/abc</field><field name="host">a</field><field name="site">b</field></doc>
+</add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let rules = HostRule::defaults();
+    let url_fields = vec![COL_URL.to_string()];
+
+    let filled: Vec<bool> = docs
+        .iter_mut()
+        .map(|doc| {
+            let SeedHost { host, .. } = seed_host(doc, &rules, &url_fields).unwrap();
+            fill_host_fields(doc, &host)
+        })
+        .collect();
+    assert_eq!(filled, [true, true, false]);
+
+    let field_str = |doc: &Doc, name: &[u8]| -> Vec<String> {
+        doc.field()
+            .get(name)
+            .unwrap()
+            .iter()
+            .map(|value| value.to_unescape_str().unwrap().into_owned())
+            .collect()
+    };
+
+    // host만 없는 경우. 카페 규칙을 적용하기 전의 host를 사용
+    assert_eq!(field_str(&docs[0], COL_HOST), ["cafe.naver.com"]);
+    assert_eq!(field_str(&docs[0], COL_SITE), ["cafe"]);
+    // 둘 다 없는 경우
+    assert_eq!(field_str(&docs[1], COL_HOST), ["twitter.com"]);
+    assert_eq!(field_str(&docs[1], COL_SITE), ["twitter.com"]);
+    // 둘 다 있는 경우 그대로 유지
+    assert_eq!(field_str(&docs[2], COL_HOST), ["a"]);
+    assert_eq!(field_str(&docs[2], COL_SITE), ["b"]);
+
+    // 변경된 doc은 다시 작성됨
+    let WriteOk::Changed(final_xml, doc_cnt, _) =
+        write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 3);
+    let final_docs = read_xml(&final_xml, &ReadLimit::default()).unwrap();
+    assert_eq!(field_str(&final_docs[1], COL_HOST), ["twitter.com"]);
+}
+
+#[test]
+fn normalize_dates_test() {
+    let xml = br#"<add>
+<doc><field name="id">1</field><field name="postdate">2022-07-28 04:48:00</field></doc>
+<doc><field name="id">2</field><field name="tstamp">2022-07-28T00:00:00.000Z</field><field name="postdate">2022-07-28T13:48:00+09:00</field><field name="postdate">2022-07-28T04:48:00Z</field></doc>
+<doc><field name="id">3</field><field name="tstamp">2022-07-28T00:00:00.000Z</field><field name="postdate">yesterday</field></doc>
+<doc><field name="id">4</field><field name="tstamp">2022-07-28T00:00:00.000Z</field><field name="postdate">2022-07-28T04:48:00.123Z</field></doc>
+</add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let results: Vec<DateResult> = docs
+        .iter_mut()
+        .map(|doc| normalize_dates(doc).unwrap())
+        .collect();
+
+    // tstamp가 없는 경우 추가하고 postdate 변환
+    assert_eq!(
+        results[0],
+        DateResult {
+            tstamp_filled: true,
+            postdate_rewritten: 1,
+            postdate_invalid: 0
+        }
+    );
+    assert!(docs[0].field().has_changed());
+    let tstamp = docs[0].field().get(COL_TSTAMP).unwrap()[0]
+        .to_unescape_str()
+        .unwrap()
+        .into_owned();
+    assert_eq!(date_field::check_date(&tstamp), DateCheck::Valid);
+
+    // 여러 값 중 변환이 필요한 값만 변경
+    assert_eq!(results[1].postdate_rewritten, 1);
+    let postdates: Vec<String> = docs[1]
+        .field()
+        .get(COL_POSTDATE)
+        .unwrap()
+        .iter()
+        .map(|value| value.to_unescape_str().unwrap().into_owned())
+        .collect();
+    assert_eq!(
+        postdates,
+        ["2022-07-28T04:48:00.000Z", "2022-07-28T04:48:00Z"]
+    );
+
+    // 변환할 수 없는 값은 그대로 둠
+    assert_eq!(results[2].postdate_invalid, 1);
+    assert!(!docs[2].field().has_changed());
+
+    // 변경 사항이 없는 doc은 원문 그대로 write
+    assert_eq!(results[3], DateResult::default());
+    assert!(!docs[3].field().has_changed());
+
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(final_xml.contains(r#"<field name="postdate">2022-07-28T04:48:00.000Z</field>"#));
+    assert!(final_xml.contains(r#"<field name="postdate">yesterday</field>"#));
+    assert!(!final_xml.contains("2022-07-28 04:48:00"));
+}
+
+#[tokio::test]
+async fn sanitize_doc_test() {
+    let xml = b"<add>\
+<doc><field name=\"id\">1</field><field name=\"content\">a\x0Bb\x1Fc</field></doc>\
+<doc><field name=\"id\">2</field><field name=\"content\">a&#11;b&amp;c\tline\nend</field></doc>\
+</add>";
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert!(sanitize_doc(&mut docs[0]).unwrap());
+    assert!(sanitize_doc(&mut docs[1]).unwrap());
+    assert_eq!(
+        docs[0].field().get(b"content").unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "abc"
+    );
+    assert_eq!(
+        docs[1].field().get(b"content").unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "ab&c\tline\nend"
+    );
+
+    // 다시 작성된 xml은 솔라로 그대로 전달할 수 있음
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert!(!final_xml.contains(&0x0B));
+    assert!(!final_xml.contains(&0x1F));
+    let final_docs = read_xml(&final_xml, &ReadLimit::default()).unwrap();
+    assert_eq!(final_docs.len(), 2);
+
+    let mut mock = crate::mock::MockSolr::start().await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    solr.send_request(
+        hyper::Uri::from_static("/solr/core/update"),
+        hyper::Method::POST,
+        hyper::HeaderMap::new(),
+        hyper::Body::from(final_xml.clone()),
+        &crate::context::RequestContext {
+            request_id: "test-request".to_string(),
+            remote_ip: "127.0.0.1:5000".parse().unwrap(),
+            scheme: crate::context::Scheme::Http,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(&mock.next_request().await.body[..], &final_xml[..]);
+
+    // U+FFFF도 제거
+    let xml = "<add><doc><field name=\"content\">a\u{FFFF}b（c</field></doc></add>";
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    assert!(sanitize_doc(&mut docs[0]).unwrap());
+    assert_eq!(
+        docs[0].field().get(b"content").unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "ab（c"
+    );
+
+    // 정상적인 doc은 원문을 그대로 사용
+    let xml = br#"<add><doc><field name="id">1</field><field name="content">a&amp;b&#x41;</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert!(!sanitize_doc(&mut docs[0]).unwrap());
+    assert!(matches!(
+        write_xml(docs, false, SeedIdField::default()).unwrap(),
+        WriteOk::NoChanged(1)
+    ));
+}
+
+#[test]
+fn dedup_fields_test() {
+    let xml = br#"<add><doc>
+<field name="id">1</field>
+<field name="seed_id">f371ba73-7e23-11ea-9ea0-fa163e9f6f72</field>
+<field name="seed_id">SECOND</field>
+<field name="etc_array_text1">a</field>
+<field name="etc_array_text1">b</field>
+</doc></add>"#;
+    let fields = vec!["id".to_string(), "seed_id".to_string()];
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+
+    assert!(dedup_fields(&mut docs[0], &fields).unwrap());
+    let seed_ids = docs[0].field().get(COL_SEED_ID.as_bytes()).unwrap();
+    assert_eq!(seed_ids.len(), 1);
+    assert_eq!(
+        seed_ids[0].to_unescape_str().unwrap(),
+        "f371ba73-7e23-11ea-9ea0-fa163e9f6f72"
+    );
+    // 목록에 없는 필드는 그대로 유지
+    assert_eq!(docs[0].field().get(b"etc_array_text1").unwrap().len(), 2);
+
+    // 중복이 없는 경우 변경 없음
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert!(!dedup_fields(&mut docs[0], &["id".to_string()]).unwrap());
+    assert!(!docs[0].field().has_changed());
+
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    dedup_fields(&mut docs[0], &fields).unwrap();
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(!final_xml.contains("SECOND"));
+    assert_eq!(final_xml.matches("etc_array_text1").count(), 2);
+}
+
+#[test]
+fn required_fields_test() {
+    let xml = br#"<add>
+<doc><field name="id">1</field><field name="url">http://a.com</field></doc>
+<doc><field name="url">http://b.com</field></doc>
+<doc><field name="id">3</field><field name="url">http://c.com</field></doc>
+<doc><field name="id"> </field></doc>
+</add>"#;
+    let fields = vec!["id".to_string(), COL_URL.to_string()];
+
+    // drop인 경우 필수 필드가 없는 doc만 제거
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let dropped = check_required_fields(&mut docs, &fields, RequiredFieldsAction::Drop).unwrap();
+    assert_eq!(dropped, 2);
+    assert_eq!(docs.len(), 2);
+    assert_eq!(doc_id(&docs[0]).unwrap(), "1");
+    assert_eq!(doc_id(&docs[1]).unwrap(), "3");
+
+    // 남은 doc에 변경 사항이 없어도 새로 작성함
+    let WriteOk::Changed(final_xml, doc_cnt, _) =
+        write_xml(docs, dropped > 0, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 2);
+    let final_docs = read_xml(&final_xml, &ReadLimit::default()).unwrap();
+    assert_eq!(final_docs.len(), 2);
+    assert!(!String::from_utf8_lossy(&final_xml).contains("b.com"));
+
+    // reject인 경우 400 에러
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let err = check_required_fields(&mut docs, &fields, RequiredFieldsAction::Reject).unwrap_err();
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::BAD_REQUEST)
+    );
+    assert!(err.to_string().contains("MISSING_REQUIRED_FIELD"));
+    assert!(err.to_string().contains("url: http://b.com"));
+    assert_eq!(docs.len(), 4);
+
+    // 모든 doc이 제거된 경우 빈 add를 작성
+    let xml = br#"<add><doc><field name="url">http://b.com</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let dropped = check_required_fields(&mut docs, &fields, RequiredFieldsAction::Drop).unwrap();
+    assert_eq!(dropped, 1);
+    let WriteOk::Changed(final_xml, doc_cnt, _) =
+        write_xml(docs, true, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 0);
+    assert_eq!(final_xml, b"<add></add>");
+
+    // 필수 필드가 없는 경우 확인하지 않음
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(
+        check_required_fields(&mut docs, &[], RequiredFieldsAction::Reject).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn dedup_docs_test() {
+    let xml = br#"<add>
+<doc><field name="id">1</field><field name="seed_id">f371ba73-7e23-11ea-9ea0-fa163e9f6f72</field><field name="title">first</field></doc>
+<doc><field name="id">2</field><field name="title">only</field></doc>
+<doc><field name="title">no id</field></doc>
+<doc><field name="id">1</field><field name="url">http://a.com</field><field name="title">last</field></doc>
+</add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(dedup_docs(&mut docs).unwrap(), 1);
+    assert_eq!(docs.len(), 3);
+
+    // seed_id가 없더라도 마지막 doc을 남김
+    let title = |doc: &Doc| {
+        doc.field().get(b"title").unwrap()[0]
+            .to_unescape_str()
+            .unwrap()
+            .into_owned()
+    };
+    assert_eq!(title(&docs[0]), "only");
+    assert_eq!(title(&docs[1]), "no id");
+    assert_eq!(title(&docs[2]), "last");
+    assert!(docs[2].field().get(COL_SEED_ID.as_bytes()).is_none());
+
+    // 남은 doc은 원문을 그대로 사용
+    let ori_strs: Vec<&[u8]> = docs.iter().map(|doc| doc.ori_str()).collect();
+    let WriteOk::Changed(final_xml, doc_cnt, _) =
+        write_xml(docs, true, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 3);
+    let expected: Vec<u8> = [&b"<add>"[..], &ori_strs.concat(), &b"</add>"[..]].concat();
+    assert_eq!(final_xml, expected);
+
+    // 중복이 없는 경우
+    let xml =
+        br#"<add><doc><field name="id">1</field></doc><doc><field name="id">2</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(dedup_docs(&mut docs).unwrap(), 0);
+    assert_eq!(docs.len(), 2);
+}
+
+#[test]
+fn write_buffer_capacity_test() {
+    // 2000개 중 일부 doc에만 seed_id가 추가된 경우
+    let doc = r#"<doc boost="1.0"><field name="id">ID</field><field name="url">http://a.com/</field><field name="content">본문 내용</field></doc>"#;
+    let xml = format!(
+        "<add>{}</add>",
+        (0..2000)
+            .map(|i| doc.replace("ID", &i.to_string()))
+            .collect::<String>()
+    );
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    for doc in docs.iter_mut().step_by(10) {
+        doc.field_as_mut().push_field_owned(
+            COL_SEED_ID.as_bytes(),
+            "f371ba73-7e23-11ea-9ea0-fa163e9f6f72".to_string(),
+        );
+    }
+
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert!(
+        final_xml.capacity() * 100 <= final_xml.len() * 120,
+        "capacity: {}, len: {}",
+        final_xml.capacity(),
+        final_xml.len()
+    );
+
+    // 하나만 변경된 경우에도 원문 크기의 2배를 할당하지 않음
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    docs[0]
+        .field_as_mut()
+        .push_field_owned(COL_SEED_ID.as_bytes(), "SEED".to_string());
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert!(final_xml.capacity() * 100 <= final_xml.len() * 120);
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn otel_db_span_test() {
+    use crate::mock::MockSeedIdStore;
+    use opentelemetry::trace::TraceId;
+
+    let exporter = otel::TestExporter::install();
+    let mut headers = hyper::HeaderMap::new();
+    headers.insert(
+        "traceparent",
+        hyper::header::HeaderValue::from_static(
+            "00-5bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ),
+    );
+    let root = otel::Span::start_request(&headers);
+    let xml = r#"<add><doc><field name="id">1</field><field name="url">https://otel-db.example.com/</field></doc></add>"#;
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    let mut timing = ProcTiming::default();
+    root.instrument(proc_xml_with(
+        &mut docs,
+        &MockSeedIdStore::default(),
+        &ProcOptions::default(),
+        &mut timing,
+    ))
+    .await
+    .unwrap();
+    drop(root);
+
+    // 조회, INSERT, 다시 조회, 기록 저장마다 span이 생김
+    let spans = exporter.trace(TraceId::from_hex("5bf92f3577b34da6a3ce929d0e0e4736").unwrap());
+    let root = spans.iter().find(|span| span.name == "request").unwrap();
+    let mut db_spans: Vec<_> = spans
+        .iter()
+        .filter(|span| span.name.starts_with("db."))
+        .collect();
+    db_spans.sort_by_key(|span| span.start_time);
+    let names: Vec<_> = db_spans.iter().map(|span| span.name.as_ref()).collect();
+    assert_eq!(
+        names,
+        [
+            "db.select_seed_id",
+            "db.insert_seed_id",
+            "db.select_seed_id",
+            "db.insert_audit"
+        ]
+    );
+    for span in db_spans {
+        assert_eq!(span.parent_span_id, root.span_context.span_id());
+        assert!(
+            span.attributes
+                .iter()
+                .any(|kv| kv.key.as_str() == "seed_host"
+                    && kv.value.as_str() == "otel-db.example.com")
+        );
+    }
+}
+
+#[tokio::test]
+async fn collection_isolation_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let ko = Arc::new(CollectionSettings {
+        name: "isolation_ko".to_string(),
+        seed_table: "crawlerdb.seed_ko".to_string(),
+        cache_namespace: "isolation_ko".to_string(),
+        host_rules: HostRule::defaults(),
+        seed_url_fields: vec![COL_URL.to_string()],
+        ..CollectionSettings::default()
+    });
+    let ja = Arc::new(CollectionSettings {
+        name: "isolation_ja".to_string(),
+        seed_table: "crawlerdb.seed_ja".to_string(),
+        cache_namespace: "isolation_ja".to_string(),
+        required_fields: vec!["title".to_string()],
+        ..(*ko).clone()
+    });
+    let xml = br#"<add><doc><field name="id">c1</field><field name="url">https://collection-test.example.com/1</field></doc><doc><field name="id">c2</field><field name="title">t</field><field name="url">https://collection-test.example.com/2</field></doc></add>"#;
+    let run = |collection: &Arc<CollectionSettings>| {
+        let collection = collection.clone();
+        async move {
+            // seed_table마다 다른 seed_id("<seed_table>/<seed_host>")를 줌
+            let store =
+                MockSeedIdStore::every_host().with_prefix(&format!("{}/", collection.seed_table));
+            let options = ProcOptions {
+                collection: Some(collection.clone()),
+                ..ProcOptions::default()
+            };
+            let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+            proc_xml_with(&mut docs, &store, &options, &mut ProcTiming::default())
+                .await
+                .unwrap();
+            let seed_ids: Vec<String> = docs
+                .iter()
+                .map(|doc| {
+                    doc.field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+                        .to_unescape_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
+            (seed_ids, store.selected().len())
+        }
+    };
+
+    // 컬렉션마다 자기 seed_table에서 조회함
+    let (seed_ids, selects) = run(&ko).await;
+    assert_eq!(
+        seed_ids,
+        ["crawlerdb.seed_ko/collection-test.example.com"; 2]
+    );
+    assert_eq!(selects, 1);
+
+    // 다른 컬렉션의 cache를 사용하지 않으며, 필수 필드도 컬렉션 설정을 따름
+    let (seed_ids, selects) = run(&ja).await;
+    assert_eq!(seed_ids, ["crawlerdb.seed_ja/collection-test.example.com"]);
+    assert_eq!(selects, 1);
+
+    // cache key는 컬렉션마다 나뉘어 있음
+    assert_eq!(
+        SEED_ID_CACHE
+            .get("isolation_ko:collection-test.example.com")
+            .await
+            .as_deref(),
+        Some("crawlerdb.seed_ko/collection-test.example.com")
+    );
+    assert_eq!(
+        SEED_ID_CACHE
+            .get("isolation_ja:collection-test.example.com")
+            .await
+            .as_deref(),
+        Some("crawlerdb.seed_ja/collection-test.example.com")
+    );
+    assert_eq!(SEED_ID_CACHE.get("collection-test.example.com").await, None);
+
+    let (_, selects) = run(&ko).await;
+    assert_eq!(selects, 0);
+
+    let cnt_lock = WORKING_CNT.lock().await;
+    let ko_stat = cnt_lock.collection_cnt["isolation_ko"];
+    assert_eq!(ko_stat.doc_cnt, 4);
+    assert_eq!((ko_stat.cache_hit_cnt, ko_stat.cache_miss_cnt), (3, 1));
+    let ja_stat = cnt_lock.collection_cnt["isolation_ja"];
+    assert_eq!(ja_stat.doc_cnt, 1);
+    assert_eq!((ja_stat.cache_hit_cnt, ja_stat.cache_miss_cnt), (0, 1));
+}
+
+#[test]
+fn write_xml_spliced_test() {
+    let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add overwrite=\"true\" commitWithin=\"1000\">\n  <doc><field name=\"id\">1</field></doc>\n  <doc><field name=\"id\">2</field></doc>\n  <commit/>\n  <doc><field name=\"id\">3</field></doc>\n</add>\n";
+    let read = || read_xml_envelope(xml, &ReadLimit::default()).unwrap();
+    let spliced = |docs: Vec<Doc>, envelope: &XmlEnvelope| match write_xml_spliced(
+        docs,
+        envelope,
+        SeedIdField::default(),
+    )
+    .unwrap()
+    {
+        WriteOk::Changed(bytes, _, _) => String::from_utf8(bytes).unwrap(),
+        _ => panic!("result is not WriteOk::Changed"),
+    };
+
+    // 변경 사항이 없으면 원문을 그대로 사용함
+    let (docs, envelope) = read();
+    assert!(matches!(
+        write_xml_spliced(docs, &envelope, SeedIdField::default()).unwrap(),
+        WriteOk::NoChanged(3)
+    ));
+
+    // 변경된 doc만 다시 작성하고 나머지는 원문 그대로
+    let (mut docs, envelope) = read();
+    docs[1]
+        .field_as_mut()
+        .replace_field_owned(b"id", 0, "2&b".to_string());
+    assert_eq!(
+        spliced(docs, &envelope),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add overwrite=\"true\" commitWithin=\"1000\">\n  <doc><field name=\"id\">1</field></doc>\n  <doc><field name=\"id\">2&amp;b</field></doc>\n  <commit/>\n  <doc><field name=\"id\">3</field></doc>\n</add>\n"
+    );
+
+    // 제거된 doc의 원문은 복사하지 않음. 변경된 doc이 없어도 새로 작성함
+    let (mut docs, envelope) = read();
+    docs.remove(0);
+    assert_eq!(
+        spliced(docs, &envelope),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add overwrite=\"true\" commitWithin=\"1000\">\n  \n  <doc><field name=\"id\">2</field></doc>\n  <commit/>\n  <doc><field name=\"id\">3</field></doc>\n</add>\n"
+    );
+
+    // 모든 doc이 제거되어도 doc이 아닌 부분은 남김
+    let (mut docs, envelope) = read();
+    docs.clear();
+    assert_eq!(
+        spliced(docs, &envelope),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add overwrite=\"true\" commitWithin=\"1000\">\n  \n  \n  <commit/>\n  \n</add>\n"
+    );
+
+    // 순서가 바뀐 경우 write_xml로 새로 작성함. xml 선언은 유지함
+    let (mut docs, envelope) = read();
+    docs.swap(0, 2);
+    docs[1]
+        .field_as_mut()
+        .replace_field_owned(b"id", 0, "20".to_string());
+    assert_eq!(
+        spliced(docs, &envelope),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add><doc><field name=\"id\">3</field></doc><doc><field name=\"id\">20</field></doc><doc><field name=\"id\">1</field></doc></add>"
+    );
+
+    // 다른 원문에서 읽은 doc이 있는 경우에도 새로 작성함
+    let other = br#"<add><doc><field name="id">9</field></doc></add>"#;
+    let (mut docs, envelope) = read();
+    docs[0] = read_xml(other, &ReadLimit::default()).unwrap().remove(0);
+    docs[0]
+        .field_as_mut()
+        .replace_field_owned(b"id", 0, "10".to_string());
+    assert_eq!(
+        spliced(docs, &envelope),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<add><doc><field name=\"id\">10</field></doc><doc><field name=\"id\">2</field></doc><doc><field name=\"id\">3</field></doc></add>"
+    );
+}
+
+#[test]
+fn write_xml_split_test() {
+    let xml = b"<?xml version=\"1.0\"?>\n<add overwrite=\"true\">\n  <doc boost=\"2\"><field name=\"id\">1</field></doc>\n  <doc><field name=\"id\">2</field></doc>\n  <doc><field name=\"id\">3</field></doc>\n  <doc><field name=\"id\">4</field></doc>\n  <doc><field name=\"id\">5</field></doc>\n</add>\n";
+    let read = || read_xml_envelope(xml, &ReadLimit::default()).unwrap();
+    let split = |docs: Vec<Doc>, envelope: &XmlEnvelope, limit: SplitLimit| match write_xml_split(
+        docs,
+        envelope,
+        &limit,
+        SeedIdField::default(),
+    )
+    .unwrap()
+    {
+        WriteOk::Split(chunks, doc_cnt, report) => (chunks, doc_cnt, report),
+        _ => panic!("result is not WriteOk::Split"),
+    };
+    let by_docs = SplitLimit {
+        max_docs: 2,
+        max_bytes: 0,
+    };
+
+    // 나눈 chunk마다 prologue와 <add> 속성을 유지하고, 변경 사항이 없는 doc은 원문 그대로 복사함
+    let (mut docs, envelope) = read();
+    docs[2]
+        .field_as_mut()
+        .replace_field_owned(b"id", 0, "3&c".to_string());
+    let (chunks, doc_cnt, report) = split(docs, &envelope, by_docs);
+    assert_eq!(doc_cnt, 5);
+    assert_eq!(report.changed_doc_cnt, 1);
+    assert_eq!(report.changed_ids, vec!["3&c".to_string()]);
+    let chunk_xml: Vec<_> = chunks
+        .iter()
+        .map(|chunk| String::from_utf8(chunk.xml.clone()).unwrap())
+        .collect();
+    assert_eq!(
+        chunk_xml,
+        vec![
+            "<?xml version=\"1.0\"?>\n<add overwrite=\"true\"><doc boost=\"2\"><field name=\"id\">1</field></doc><doc><field name=\"id\">2</field></doc></add>",
+            "<?xml version=\"1.0\"?>\n<add overwrite=\"true\"><doc><field name=\"id\">3&amp;c</field></doc><doc><field name=\"id\">4</field></doc></add>",
+            "<?xml version=\"1.0\"?>\n<add overwrite=\"true\"><doc><field name=\"id\">5</field></doc></add>",
+        ]
+    );
+    assert_eq!(
+        chunks
+            .iter()
+            .map(|chunk| (
+                chunk.doc_cnt,
+                chunk.first_id.as_str(),
+                chunk.last_id.as_str()
+            ))
+            .collect::<Vec<_>>(),
+        vec![(2, "1", "2"), (2, "3&c", "4"), (1, "5", "5")]
+    );
+    assert_eq!(
+        report.bytes_len,
+        chunks.iter().map(|chunk| chunk.xml.len()).sum::<usize>()
+    );
+    // 변경 사항이 없는 doc은 원문을 그대로 참조해서 복사함
+    let (docs, envelope) = read();
+    assert!(docs.iter().all(|doc| !doc.field().has_changed()));
+    let (chunks, _, report) = split(docs, &envelope, by_docs);
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(report.changed_doc_cnt, 0);
+
+    // 크기 기준. <add> 시작 태그와 prologue도 크기에 포함함
+    let (docs, envelope) = read();
+    let doc_len = b"<doc><field name=\"id\">2</field></doc>".len();
+    let overhead = b"<?xml version=\"1.0\"?>\n<add overwrite=\"true\"></add>".len();
+    let by_bytes = SplitLimit {
+        max_docs: 0,
+        max_bytes: overhead + doc_len * 3,
+    };
+    let (chunks, _, _) = split(docs, &envelope, by_bytes);
+    assert_eq!(
+        chunks.iter().map(|chunk| chunk.doc_cnt).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert!(chunks
+        .iter()
+        .all(|chunk| chunk.xml.len() <= by_bytes.max_bytes));
+    // doc 하나가 기준보다 크면 그 doc만 보냄
+    let (docs, envelope) = read();
+    let tiny = SplitLimit {
+        max_docs: 0,
+        max_bytes: 1,
+    };
+    assert_eq!(split(docs, &envelope, tiny).0.len(), 5);
+
+    // 기준 이하이거나 사용하지 않으면 나누지 않음
+    let (docs, envelope) = read();
+    let under = SplitLimit {
+        max_docs: 5,
+        max_bytes: 0,
+    };
+    assert!(matches!(
+        write_xml_split(docs, &envelope, &under, SeedIdField::default()).unwrap(),
+        WriteOk::NoChanged(5)
+    ));
+    let (docs, envelope) = read();
+    assert!(matches!(
+        write_xml_split(
+            docs,
+            &envelope,
+            &SplitLimit::default(),
+            SeedIdField::default()
+        )
+        .unwrap(),
+        WriteOk::NoChanged(5)
+    ));
+
+    // doc이 아닌 명령이 있거나 <add>가 여러 개인 원문은 나누지 않음
+    for other in [
+        &b"<add><doc><field name=\"id\">1</field></doc><doc><field name=\"id\">2</field></doc><doc><field name=\"id\">3</field></doc><commit/></add>"[..],
+        &b"<update><add><doc><field name=\"id\">1</field></doc><doc><field name=\"id\">2</field></doc><doc><field name=\"id\">3</field></doc></add></update>"[..],
+        &b"<add><doc><field name=\"id\">1</field></doc><doc><field name=\"id\">2</field></doc></add><add><doc><field name=\"id\">3</field></doc></add>"[..],
+    ] {
+        let (docs, envelope) = read_xml_envelope(other, &ReadLimit::default()).unwrap();
+        assert!(matches!(
+            write_xml_split(docs, &envelope, &by_docs, SeedIdField::default()).unwrap(),
+            WriteOk::NoChanged(3)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn comment_and_prologue_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let xml = b"<?xml version=\"1.0\"?>\n<!-- generated by crawler -->\n<add>\n<!-- batch 1 -->\n<doc><!-- no seed_id --><field name=\"id\">1</field><field name=\"url\">https://comment.example.com/</field></doc>\n<!-- batch 2 -->\n<doc><field name=\"id\">2</field><!-- </doc> --><field name=\"seed_id\">e7531c15-2384-11ed-b560-42010a025a43</field></doc>\n</add>";
+    async fn enrich(docs: &mut Vec<Doc<'_>>) {
+        proc_xml_with(
+            docs,
+            &MockSeedIdStore::fixed("0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"),
+            &ProcOptions::default(),
+            &mut ProcTiming::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    // doc 안의 주석은 ori_str 확인에 영향을 주지 않음
+    let (mut docs, envelope) = read_xml_envelope(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0].comments(), [&b"<!-- no seed_id -->"[..]]);
+    assert_eq!(docs[1].comments(), [&b"<!-- </doc> -->"[..]]);
+    assert!(docs[1].ori_str().ends_with(b"</field></doc>"));
+    assert_eq!(
+        envelope.prologue(),
+        b"<?xml version=\"1.0\"?>\n<!-- generated by crawler -->\n"
+    );
+
+    // 선언, doc 사이의 주석, 변경된 doc 안의 주석이 모두 유지됨
+    enrich(&mut docs).await;
+    let WriteOk::Changed(final_xml, _, _) =
+        write_xml_spliced(docs, &envelope, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(final_xml.starts_with("<?xml version=\"1.0\"?>\n<!-- generated by crawler -->\n<add>\n<!-- batch 1 -->\n<doc><!-- no seed_id --><field "));
+    assert!(final_xml.contains("0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"));
+    assert!(final_xml.ends_with("</doc>\n<!-- batch 2 -->\n<doc><field name=\"id\">2</field><!-- </doc> --><field name=\"seed_id\">e7531c15-2384-11ed-b560-42010a025a43</field></doc>\n</add>"));
+    let reread = read_xml(final_xml.as_bytes(), &ReadLimit::default()).unwrap();
+    assert_eq!(reread.len(), 2);
+
+    // 새로 작성하는 경우에도 prologue는 유지함
+    let (mut docs, envelope) = read_xml_envelope(xml, &ReadLimit::default()).unwrap();
+    enrich(&mut docs).await;
+    docs.swap(0, 1);
+    let WriteOk::Changed(final_xml, _, _) =
+        write_xml_spliced(docs, &envelope, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(final_xml.starts_with("<?xml version=\"1.0\"?>\n<!-- generated by crawler -->\n<add><doc><field name=\"id\">2</field>"));
+    assert!(final_xml.contains("<doc><!-- no seed_id --><field "));
+
+    // 최상위 태그만 있는 경우 prologue는 비어있음
+    let (_, envelope) = read_xml_envelope(b"<add/>", &ReadLimit::default()).unwrap();
+    assert_eq!(envelope.prologue(), b"");
+}
+
+#[test]
+fn multi_fragment_text_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="title">Tom &amp; Jerry &lt;3</field><field name="content">first <!-- cut -->second &amp; third</field><field name="code"><![CDATA[a < b]]></field><field name="mixed">x &gt; <![CDATA[<y>]]> z</field></doc></add>"#;
+    let docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let field = docs[0].field();
+    let value = |name: &str| -> String {
+        let body_list = field.get(name.as_bytes()).unwrap();
+        assert_eq!(body_list.len(), 1, "{}", name);
+        body_list[0].to_unescape_str().unwrap().into_owned()
+    };
+
+    // Text 이벤트가 하나인 경우 원문을 그대로 참조함
+    assert_eq!(value("title"), "Tom & Jerry <3");
+    assert!(matches!(
+        field.get(b"title".as_slice()).unwrap()[0],
+        BytesOrStr::Bytes(_)
+    ));
+    // 주석, CDATA로 나뉜 텍스트는 모두 합침
+    assert_eq!(value("content"), "first second & third");
+    assert_eq!(value("code"), "a < b");
+    assert_eq!(value("mixed"), "x > <y> z");
+    // 원문에서 읽은 값이므로 변경 사항으로 보지 않음
+    assert!(!field.has_changed());
+    assert!(matches!(
+        write_xml(docs, false, SeedIdField::default()).unwrap(),
+        WriteOk::NoChanged(1)
+    ));
+
+    // 다시 작성하는 경우 합친 값을 escape해서 작성함
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    docs[0]
+        .field_as_mut()
+        .replace_field_owned(b"id", 0, "2".to_string());
+    let WriteOk::Changed(final_xml, _, _) = write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    let final_xml = String::from_utf8(final_xml).unwrap();
+    assert!(final_xml.contains(r#"<field name="content">first second &amp; third</field>"#));
+    assert!(final_xml.contains(r#"<field name="code">a &lt; b</field>"#));
+    assert!(final_xml.contains(r#"<field name="mixed">x &gt; &lt;y&gt; z</field>"#));
+
+    // 나뉜 텍스트도 Text 이벤트가 하나인 경우와 같이 값의 앞뒤 공백만 제거함. CDATA 안의 공백은 유지함
+    let xml_ws = b"<add><doc><field name=\"id\">\n  1  \n</field><field name=\"content\">\n  first <!-- cut --> second\n</field><field name=\"code\"> <![CDATA[ a ]]> </field></doc></add>";
+    let docs = read_xml(xml_ws, &ReadLimit::default()).unwrap();
+    let field = docs[0].field();
+    let value = |name: &str| {
+        field.get(name.as_bytes()).unwrap()[0]
+            .to_unescape_str()
+            .unwrap()
+            .into_owned()
+    };
+    assert_eq!(value("id"), "1");
+    assert_eq!(value("content"), "first  second");
+    assert_eq!(value("code"), " a ");
+
+    // 필드 수 제한은 나뉜 텍스트도 필드 하나로 셈
+    let limit = ReadLimit {
+        max_docs: 0,
+        max_fields_per_doc: 5,
+    };
+    assert_eq!(read_xml(xml, &limit).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn nested_doc_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let child_1 = r#"<doc><field name="id">1-1</field><field name="url">https://child.example.com/1</field></doc>"#;
+    let child_2 =
+        r#"<doc><field name="id">1-2</field><doc><field name="id">1-2-1</field></doc></doc>"#;
+    let labelled = r#"<field name="replies"><doc><field name="id">1-3</field></doc></field>"#;
+    let xml = format!(
+        r#"<add><doc><field name="id">1</field>{}<field name="url">https://parent.example.com/</field>{}{}</doc><doc><field name="id">2</field><field name="seed_id">e7531c15-2384-11ed-b560-42010a025a43</field></doc></add>"#,
+        child_1, child_2, labelled
+    );
+
+    // 최상위 doc만 나누고 자식 doc의 필드는 부모 doc에 넣지 않음
+    let (mut docs, envelope) = read_xml_envelope(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(
+        docs[0].children(),
+        [child_1.as_bytes(), child_2.as_bytes(), labelled.as_bytes()]
+    );
+    let parent = docs[0].field();
+    assert_eq!(parent.get(COL_ID).unwrap().len(), 1);
+    assert_eq!(
+        parent.get(COL_URL.as_bytes()).unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "https://parent.example.com/"
+    );
+    assert!(parent.get(b"replies".as_slice()).is_none());
+    assert!(docs[1].children().is_empty());
+
+    // 변경 사항이 없으면 원문 그대로
+    assert!(matches!(
+        write_xml(
+            read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap(),
+            false,
+            SeedIdField::default()
+        )
+        .unwrap(),
+        WriteOk::NoChanged(2)
+    ));
+
+    // 부모 doc에만 seed_id를 넣고 자식 doc은 원문 그대로 다시 작성함
+    proc_xml_with(
+        &mut docs,
+        &MockSeedIdStore::fixed("0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"),
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    let WriteOk::Changed(final_xml, doc_cnt, report) =
+        write_xml_spliced(docs, &envelope, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 2);
+    assert_eq!(report.changed_ids, vec!["1"]);
+    assert_eq!(report.seed_id_added_cnt, 1);
+    let final_str = String::from_utf8(final_xml.clone()).unwrap();
+    assert!(
+        final_str.contains(&format!("{}{}{}</doc>", child_1, child_2, labelled)),
+        "{}",
+        final_str
+    );
+    assert_eq!(
+        final_str
+            .matches("0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d")
+            .count(),
+        1
+    );
+
+    // 다시 읽어도 같은 구조
+    let reread = read_xml(&final_xml, &ReadLimit::default()).unwrap();
+    assert_eq!(reread.len(), 2);
+    assert_eq!(
+        reread[0].children(),
+        [child_1.as_bytes(), child_2.as_bytes(), labelled.as_bytes()]
+    );
+    assert_eq!(
+        reread[0].field().get(COL_SEED_ID.as_bytes()).unwrap()[0]
+            .to_unescape_str()
+            .unwrap(),
+        "0b7c1bd2-9a3e-4f6b-8c1d-2e5f7a9b0c3d"
+    );
+}
+
+#[test]
+fn first_doc_id_test() {
+    let xml = br#"<add><doc><field name="url">https://a.com/</field><doc><field name="id">child</field></doc><field name=id>A&amp;1</field></doc><doc><field name="id">2</field></doc></add>"#;
+    // 자식 doc의 id는 사용하지 않음
+    assert_eq!(first_doc_id(xml).as_deref(), Some("A&1"));
+    assert_eq!(
+        first_doc_id(br#"<add><doc><field name="url">x</field></doc><doc><field name="id">2</field></doc></add>"#),
+        None
+    );
+    assert_eq!(first_doc_id(b"<add></add>"), None);
+    assert_eq!(first_doc_id(b"<add><doc><field name=\"id\">"), None);
+}
+
+#[tokio::test]
+async fn doc_guard_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let collection = CollectionSettings {
+        name: "guard_test".to_string(),
+        cache_namespace: "guard_test".to_string(),
+        host_rules: HostRule::defaults(),
+        seed_url_fields: vec![COL_URL.to_string()],
+        max_url_bytes: 1000,
+        ..CollectionSettings::default()
+    };
+    let run = |collection: CollectionSettings, xml: String| async move {
+        let options = ProcOptions {
+            collection: Some(Arc::new(collection)),
+            ..ProcOptions::default()
+        };
+        let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+        proc_xml_with(
+            &mut docs,
+            &MockSeedIdStore::every_host(),
+            &options,
+            &mut ProcTiming::default(),
+        )
+        .await
+        .unwrap();
+        docs.iter()
+            .map(|doc| {
+                doc.field()
+                    .get(COL_SEED_ID.as_bytes())
+                    .map(|seed_ids| seed_ids[0].to_unescape_str().unwrap().into_owned())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // 너무 긴 url의 doc만 seed_id 없이 보내고 앞뒤 doc은 그대로 처리함
+    let long_url = format!("https://guard-long.example.com/{}", "x".repeat(2000));
+    let xml = format!(
+        r#"<add><doc><field name="id">1</field><field name="url">https://guard-a.example.com/</field></doc><doc><field name="id">2</field><field name="url">{}</field></doc><doc><field name="id">3</field><field name="url">https://guard-b.example.com/</field></doc></add>"#,
+        long_url
+    );
+    let url_too_long_before = WORKING_CNT.lock().await.url_too_long_cnt;
+    let seed_ids = run(collection.clone(), xml.clone()).await;
+    assert_eq!(
+        seed_ids,
+        [
+            Some("seed-guard-a.example.com".to_string()),
+            None,
+            Some("seed-guard-b.example.com".to_string()),
+        ]
+    );
+    assert!(WORKING_CNT.lock().await.url_too_long_cnt > url_too_long_before);
+
+    // 0이면 제한하지 않음
+    let unlimited = CollectionSettings {
+        max_url_bytes: 0,
+        ..collection.clone()
+    };
+    assert!(run(unlimited, xml).await.iter().all(Option::is_some));
+
+    // 시간을 넘은 doc은 seed_id 없이 보냄
+    let budget = CollectionSettings {
+        doc_enrich_budget: Duration::from_nanos(1),
+        ..collection
+    };
+    let xml = r#"<add><doc><field name="id">4</field><field name="url">https://guard-c.example.com/</field></doc></add>"#;
+    assert_eq!(run(budget, xml.to_string()).await, [None]);
+}
+
+#[test]
+fn truncate_fields_test() {
+    // "가나다"는 글자마다 3 bytes. &amp;는 unescape하면 1 byte
+    let xml = r#"<add><doc><field name="id">1</field><field name="content">가나다&amp;라</field><field name="content">가</field><field name="title">짧은 제목</field></doc><doc><field name="id">2</field><field name="content">a&lt;b&gt;c</field></doc></add>"#;
+    let mut docs = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+    let limits = vec![("content".to_string(), 8), ("title".to_string(), 100)];
+    let field_str = |doc: &Doc, name: &str| -> Vec<String> {
+        doc.field()
+            .get(name.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|value| value.to_unescape_str().unwrap().into_owned())
+            .collect()
+    };
+
+    // 8 bytes는 "가나" 다음 "다"의 중간이므로 "가나"까지 남김
+    assert!(truncate_fields(&mut docs[0], &limits).unwrap());
+    assert_eq!(field_str(&docs[0], "content"), ["가나", "가"]);
+    assert_eq!(field_str(&docs[0], "title"), ["짧은 제목"]);
+    // escape된 원문은 8 bytes를 넘지만 unescape한 값은 넘지 않음
+    assert!(!truncate_fields(&mut docs[1], &limits).unwrap());
+    assert!(!docs[1].field().has_changed());
+
+    let limits = vec![("content".to_string(), 3)];
+    assert!(truncate_fields(&mut docs[1], &limits).unwrap());
+    assert_eq!(field_str(&docs[1], "content"), ["a<b"]);
+
+    // 다시 작성한 xml도 올바른 utf-8이고 escape가 유지됨
+    let WriteOk::Changed(final_xml, doc_cnt, _) =
+        write_xml(docs, false, SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+    assert_eq!(doc_cnt, 2);
+    let final_str = std::str::from_utf8(&final_xml).unwrap();
+    assert!(final_str.contains("a&lt;b<"), "{}", final_str);
+    let final_docs = read_xml(&final_xml, &ReadLimit::default()).unwrap();
+    assert_eq!(field_str(&final_docs[0], "content"), ["가나", "가"]);
+    assert_eq!(field_str(&final_docs[1], "content"), ["a<b"]);
+}
+
+#[tokio::test]
+async fn renamed_fields_test() {
+    use crate::mock::MockSeedIdStore;
+
+    // url은 link, seed_id는 channel_id 필드를 사용하는 컬렉션
+    let options = ProcOptions {
+        collection: Some(Arc::new(CollectionSettings {
+            name: "renamed_test".to_string(),
+            cache_namespace: "renamed_test".to_string(),
+            host_rules: HostRule::defaults(),
+            seed_url_fields: vec!["link".to_string()],
+            seed_id_field: SeedIdField::new("channel_id"),
+            ..CollectionSettings::default()
+        })),
+        ..ProcOptions::default()
+    };
+    let xml = br#"<add><doc><field name="id">1</field><field name="link">https://renamed-a.example.com/</field><field name="seed_id">OTHER</field></doc><doc><field name="id">2</field><field name="link">https://renamed-b.example.com/</field><field name="channel_id">KEEP</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    proc_xml_with(
+        &mut docs,
+        &MockSeedIdStore::every_host(),
+        &options,
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    let field_str = |doc: &Doc, name: &str| {
+        doc.field()
+            .get(name.as_bytes())
+            .map(|values| values[0].to_unescape_str().unwrap().into_owned())
+    };
+
+    // seed_id 필드는 다른 필드로 보고 channel_id에 넣음
+    assert_eq!(
+        field_str(&docs[0], "channel_id").as_deref(),
+        Some("seed-renamed-a.example.com")
+    );
+    assert_eq!(field_str(&docs[0], "seed_id").as_deref(), Some("OTHER"));
+    // 이미 channel_id가 있으면 그대로 둠
+    assert_eq!(field_str(&docs[1], "channel_id").as_deref(), Some("KEEP"));
+
+    let WriteOk::Changed(final_xml, _, report) =
+        write_xml(docs, false, SeedIdField::new("channel_id")).unwrap()
+    else {
+        panic!("not changed");
+    };
+    assert_eq!(report.seed_id_added_cnt, 1);
+    assert!(std::str::from_utf8(&final_xml)
+        .unwrap()
+        .contains(r#"<field name="channel_id">seed-renamed-a.example.com</field>"#));
+
+    // link가 없으면 url 필드가 있어도 seed_host를 만들지 않음
+    let xml = br#"<add><doc><field name="id">3</field><field name="url">https://renamed-c.example.com/</field></doc></add>"#;
+    let mut docs = read_xml(xml, &ReadLimit::default()).unwrap();
+    let err = proc_xml_with(
+        &mut docs,
+        &MockSeedIdStore::every_host(),
+        &options,
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.to_string(), "NOT_FOUND_URL");
+}
@@ -0,0 +1,149 @@
+use crate::query;
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::header::HeaderName;
+use hyper::http::uri::Uri;
+use hyper::{HeaderMap, StatusCode};
+
+pub const X_PROXY_ENRICH: HeaderName = HeaderName::from_static("x-proxy-enrich");
+
+/// query string으로 seed_id 추가 등의 처리 여부를 지정하는 파라미터
+const PARAM_ENRICH: &str = "proxy.enrich";
+/// query string으로 cache 사용 방식을 지정하는 파라미터
+const PARAM_CACHE: &str = "proxy.cache";
+
+/// 요청마다 지정할 수 있는 proxy 동작
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyParams {
+    /// false인 경우 파싱하지 않고 받은 그대로 솔라에 전달함
+    pub enrich: bool,
+    /// true인 경우 cache에 seed_id가 있어도 DB에서 다시 조회하고 cache를 갱신함. select는 cache된 응답을 사용하지 않음
+    pub refresh_cache: bool,
+}
+
+impl Default for ProxyParams {
+    fn default() -> Self {
+        Self {
+            enrich: true,
+            refresh_cache: false,
+        }
+    }
+}
+
+impl ProxyParams {
+    /// query string과 header에서 proxy 파라미터를 읽고, 솔라에 전달되지 않도록 제거함
+    /// <br>
+    /// query string과 header가 모두 있는 경우 query string을 우선함
+    pub fn take(uri: &mut Uri, header_map: &mut HeaderMap) -> Result<Self, BoxedError> {
+        let mut params = Self::default();
+
+        if let Some(value) = header_map.remove(X_PROXY_ENRICH) {
+            let value = value.to_str().unwrap_or_default();
+            params.enrich = parse_enrich(value)?;
+        }
+
+        let Some(query) = uri.query() else {
+            return Ok(params);
+        };
+        let (params, rest) = query::split_proxy_params(query, params)?;
+        if rest.len() != query.len() {
+            query::set_query(uri, &rest)?;
+        }
+        Ok(params)
+    }
+
+    /// decode한 proxy 파라미터 하나를 읽음. 알 수 없는 파라미터는 무시함
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<(), BoxedError> {
+        match key {
+            PARAM_ENRICH => self.enrich = parse_enrich(value)?,
+            PARAM_CACHE => self.refresh_cache = parse_cache(value)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_enrich(value: &str) -> Result<bool, BoxedError> {
+    if value.eq_ignore_ascii_case("true") {
+        Ok(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Ok(false)
+    } else {
+        Err(invalid_param(PARAM_ENRICH, value))
+    }
+}
+
+fn parse_cache(value: &str) -> Result<bool, BoxedError> {
+    if value.eq_ignore_ascii_case("refresh") {
+        Ok(true)
+    } else {
+        Err(invalid_param(PARAM_CACHE, value))
+    }
+}
+
+fn invalid_param(name: &str, value: &str) -> BoxedError {
+    Box::new(StrError::with_status(
+        format!("INVALID_PROXY_PARAM {}: {:?}", name, value),
+        StatusCode::BAD_REQUEST,
+    ))
+}
+
+#[test]
+fn proxy_params_test() {
+    use hyper::header::HeaderValue;
+
+    let mut uri: Uri = "/solr/core/update?commit=true&proxy.enrich=false&wt=json"
+        .parse()
+        .unwrap();
+    let mut header_map = HeaderMap::new();
+    let params = ProxyParams::take(&mut uri, &mut header_map).unwrap();
+    assert!(!params.enrich);
+    assert!(!params.refresh_cache);
+    assert_eq!(uri, "/solr/core/update?commit=true&wt=json");
+
+    // 파라미터만 있는 경우 ?도 제거함
+    let mut uri: Uri = "/solr/core/update?proxy.cache=refresh".parse().unwrap();
+    let params = ProxyParams::take(&mut uri, &mut header_map).unwrap();
+    assert!(params.enrich);
+    assert!(params.refresh_cache);
+    assert_eq!(uri, "/solr/core/update");
+
+    // header로 지정한 경우 header도 제거함
+    let mut uri: Uri = "/solr/core/update?commit=true".parse().unwrap();
+    header_map.insert(X_PROXY_ENRICH, HeaderValue::from_static("False"));
+    let params = ProxyParams::take(&mut uri, &mut header_map).unwrap();
+    assert!(!params.enrich);
+    assert!(header_map.get(X_PROXY_ENRICH).is_none());
+    assert_eq!(uri, "/solr/core/update?commit=true");
+
+    // 알 수 없는 proxy 파라미터도 솔라에 보내지 않음
+    let mut uri: Uri = "/solr/core/update?proxy._=1700000000&wt=json"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ProxyParams::take(&mut uri, &mut header_map).unwrap(),
+        ProxyParams::default()
+    );
+    assert_eq!(uri, "/solr/core/update?wt=json");
+
+    // 파라미터가 없는 경우 uri를 그대로 유지함
+    let mut uri: Uri = "http://solr:8983/solr/core/update?commit=true"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        ProxyParams::take(&mut uri, &mut header_map).unwrap(),
+        ProxyParams::default()
+    );
+    assert_eq!(uri, "http://solr:8983/solr/core/update?commit=true");
+
+    // 잘못된 값은 400 에러
+    let mut uri: Uri = "/solr/core/update?proxy.enrich=no".parse().unwrap();
+    let err = ProxyParams::take(&mut uri, &mut header_map).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("INVALID_PROXY_PARAM proxy.enrich"));
+    assert_eq!(
+        crate::util::error_status(&err),
+        Some(StatusCode::BAD_REQUEST)
+    );
+}
@@ -0,0 +1,162 @@
+use crate::proxy_param::ProxyParams;
+use crate::BoxedError;
+use hyper::http::uri::{PathAndQuery, Uri};
+use percent_encoding::percent_decode_str;
+use std::borrow::Cow;
+
+/// proxy에서만 사용하는 파라미터의 prefix. 이 prefix로 시작하는 파라미터는 솔라에 전달하지 않음
+pub const PROXY_PARAM_PREFIX: &str = "proxy.";
+
+/// query string에서 proxy. 로 시작하는 파라미터를 읽고 나머지 파라미터로 query string을 다시 만듦
+/// <br>
+/// defaults는 header 등 query string이 아닌 곳에서 읽은 값이며 query string에 있는 값으로 덮어씀.
+/// 알 수 없는 proxy 파라미터(cache buster 등)는 읽지 않고 제거만 함
+/// <br>
+/// 나머지 파라미터는 encoding, 순서, 중복된 key를 받은 그대로 유지함. 모두 proxy 파라미터인 경우 빈 문자열
+pub fn split_proxy_params(
+    query: &str,
+    defaults: ProxyParams,
+) -> Result<(ProxyParams, String), BoxedError> {
+    let mut params = defaults;
+    // proxy가 없어도 encoding한 key(%70roxy.enrich 등)는 decode해야 알 수 있으므로 %가 없는 경우에만 건너뜀
+    if !query.contains("proxy") && !query.contains('%') {
+        return Ok((params, query.to_string()));
+    }
+
+    let mut rest = Vec::new();
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = decode(key);
+        if key.starts_with(PROXY_PARAM_PREFIX) {
+            params.set(&key, &decode(value))?;
+        } else {
+            rest.push(pair);
+        }
+    }
+    Ok((params, rest.join("&")))
+}
+
+/// query string의 key, value를 decode함. +는 공백으로 바꿈
+pub fn decode(value: &str) -> Cow<'_, str> {
+    if !value.contains(['%', '+']) {
+        return Cow::Borrowed(value);
+    }
+    let value = value.replace('+', " ");
+    Cow::Owned(percent_decode_str(&value).decode_utf8_lossy().into_owned())
+}
+
+/// uri의 query string을 바꿈. 빈 문자열이면 ?도 제거함
+pub fn set_query(uri: &mut Uri, query: &str) -> Result<(), BoxedError> {
+    let path_and_query = if query.is_empty() {
+        uri.path().to_string()
+    } else {
+        format!("{}?{}", uri.path(), query)
+    };
+    let mut uri_parts = std::mem::take(uri).into_parts();
+    uri_parts.path_and_query = Some(PathAndQuery::try_from(path_and_query)?);
+    *uri = Uri::from_parts(uri_parts)?;
+    Ok(())
+}
+
+#[test]
+fn split_proxy_params_test() {
+    let split = |query: &str| split_proxy_params(query, ProxyParams::default()).unwrap();
+    let not_enriched = ProxyParams {
+        enrich: false,
+        refresh_cache: false,
+    };
+
+    // proxy 파라미터만 제거하고 나머지는 순서, 중복된 key까지 그대로 유지함
+    assert_eq!(
+        split("fq=a&proxy.enrich=false&fq=b&wt=json&fq=a"),
+        (not_enriched, "fq=a&fq=b&wt=json&fq=a".to_string())
+    );
+
+    // 나머지 파라미터는 decode하지 않고 받은 encoding 그대로 보냄. +와 %20도 구분해서 유지함
+    assert_eq!(
+        split("q=a+b&proxy.enrich=false&fq=c%20d&q2=%EA%B0%80%26"),
+        (not_enriched, "q=a+b&fq=c%20d&q2=%EA%B0%80%26".to_string())
+    );
+
+    // proxy 파라미터의 key, value는 decode한 뒤 읽음
+    assert_eq!(
+        split("proxy%2Eenrich=%66alse&q=1"),
+        (not_enriched, "q=1".to_string())
+    );
+    // prefix 전체를 encoding한 key도 찾음
+    assert_eq!(
+        split("%70%72%6F%78%79%2Eenrich=false&q=%2A"),
+        (not_enriched, "q=%2A".to_string())
+    );
+    assert_eq!(
+        split("%70roxy.enrich=false&q=1"),
+        (not_enriched, "q=1".to_string())
+    );
+    assert_eq!(
+        split("proxy.cache=%72efresh").0,
+        ProxyParams {
+            enrich: true,
+            refresh_cache: true,
+        }
+    );
+
+    // 빈 값과 값이 없는 파라미터도 그대로 유지함
+    assert_eq!(
+        split("q=&debug&proxy.enrich=false&&fl="),
+        (not_enriched, "q=&debug&&fl=".to_string())
+    );
+    // proxy 파라미터의 빈 값은 잘못된 값
+    let err = split_proxy_params("proxy.enrich=&q=1", ProxyParams::default()).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("INVALID_PROXY_PARAM proxy.enrich"));
+
+    // 같은 proxy 파라미터가 여러 번 있으면 마지막 값을 사용함
+    assert_eq!(
+        split("proxy.enrich=false&proxy.enrich=true").0,
+        ProxyParams::default()
+    );
+
+    // 알 수 없는 proxy 파라미터는 제거만 함. prefix가 같지 않으면 그대로 보냄
+    assert_eq!(
+        split("proxy._=1700000000&q=1&proxy.nocache=x&proxyfoo=1&xproxy.a=1"),
+        (
+            ProxyParams::default(),
+            "q=1&proxyfoo=1&xproxy.a=1".to_string()
+        )
+    );
+
+    // 모두 proxy 파라미터면 빈 문자열. proxy 파라미터가 없으면 그대로
+    assert_eq!(
+        split("proxy.enrich=false&proxy.cache=refresh"),
+        (
+            ProxyParams {
+                enrich: false,
+                refresh_cache: true,
+            },
+            String::new()
+        )
+    );
+    assert_eq!(split("q=*:*&rows=10").1, "q=*:*&rows=10");
+    assert_eq!(split("").1, "");
+
+    // query string에 없는 값은 defaults를 사용함
+    assert_eq!(
+        split_proxy_params("proxy.cache=refresh", not_enriched)
+            .unwrap()
+            .0,
+        ProxyParams {
+            enrich: false,
+            refresh_cache: true,
+        }
+    );
+
+    // 빈 query string으로 바꾸면 ?도 제거함
+    let mut uri: Uri = "http://solr:8983/solr/core/select?proxy.enrich=false"
+        .parse()
+        .unwrap();
+    set_query(&mut uri, "").unwrap();
+    assert_eq!(uri, "http://solr:8983/solr/core/select");
+    set_query(&mut uri, "q=a+b").unwrap();
+    assert_eq!(uri, "http://solr:8983/solr/core/select?q=a+b");
+}
@@ -0,0 +1,145 @@
+use hashbrown::HashMap;
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// remote ip별 token bucket 방식의 요청 제한
+/// <br>
+/// rps가 0인 경우 제한하지 않음
+pub struct RateLimiter {
+    /// config reload로 바뀔 수 있음
+    limits: RwLock<Limits>,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Limits {
+    /// 초당 채워지는 token 수
+    rps: f64,
+    /// bucket에 담길 수 있는 최대 token 수
+    burst: f64,
+    /// 제한하지 않는 ip 목록
+    exempt_ips: Vec<IpAddr>,
+}
+
+impl Limits {
+    fn new(rps: f64, burst: f64, exempt_ips: Vec<IpAddr>) -> Self {
+        Self {
+            rps,
+            burst: burst.max(1f64),
+            exempt_ips,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rps: f64, burst: f64, exempt_ips: Vec<IpAddr>) -> Self {
+        Self {
+            limits: RwLock::new(Limits::new(rps, burst, exempt_ips)),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 제한을 바꿈. 이미 있는 bucket의 token은 새 burst를 넘지 않도록 줄어듦
+    pub fn set_limits(&self, rps: f64, burst: f64, exempt_ips: Vec<IpAddr>) {
+        *self.limits.write().unwrap() = Limits::new(rps, burst, exempt_ips);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.limits.read().unwrap().rps > 0f64
+    }
+
+    /// 요청을 허용하는 경우 true. 허용하는 경우 token 하나를 소모함
+    pub async fn check(&self, ip: IpAddr, now: Instant) -> bool {
+        let (rps, burst) = {
+            let limits = self.limits.read().unwrap();
+            if limits.rps <= 0f64 || limits.exempt_ips.contains(&ip) {
+                return true;
+            }
+            (limits.rps, limits.burst)
+        };
+
+        let mut buckets_lock = self.buckets.lock().await;
+        let bucket = buckets_lock.entry(ip).or_insert(Bucket {
+            tokens: burst,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * rps).min(burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1f64 {
+            bucket.tokens -= 1f64;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// bucket이 가득 찰 만큼 요청이 없었던 ip를 제거함. 이런 ip는 새로 bucket을 만든 것과 같음
+    /// <br>
+    /// 제거 후 남은 ip 수를 반환
+    pub async fn cleanup(&self, now: Instant) -> usize {
+        let refill_time = {
+            let limits = self.limits.read().unwrap();
+            Duration::from_secs_f64(limits.burst / limits.rps.max(f64::MIN_POSITIVE))
+        };
+        let mut buckets_lock = self.buckets.lock().await;
+        buckets_lock
+            .retain(|_, bucket| now.saturating_duration_since(bucket.last_refill) < refill_time);
+        buckets_lock.len()
+    }
+}
+
+#[tokio::test]
+async fn rate_limit_test() {
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let other_ip: IpAddr = "10.0.0.2".parse().unwrap();
+    let exempt_ip: IpAddr = "10.0.0.3".parse().unwrap();
+    let limiter = RateLimiter::new(2f64, 3f64, vec![exempt_ip]);
+    let start = Instant::now();
+
+    // burst만큼은 곧바로 허용됨
+    for _ in 0..3 {
+        assert!(limiter.check(ip, start).await);
+    }
+    assert!(!limiter.check(ip, start).await);
+
+    // 다른 ip는 영향을 받지 않음
+    assert!(limiter.check(other_ip, start).await);
+
+    // 제외된 ip는 제한하지 않음
+    for _ in 0..10 {
+        assert!(limiter.check(exempt_ip, start).await);
+    }
+
+    // 0.5초가 지나면 token 하나가 채워짐
+    let later = start + Duration::from_millis(500);
+    assert!(limiter.check(ip, later).await);
+    assert!(!limiter.check(ip, later).await);
+
+    // bucket이 가득 찰 만큼 시간이 지나면 정리됨
+    assert_eq!(limiter.cleanup(later).await, 2);
+    assert_eq!(limiter.cleanup(later + Duration::from_secs(2)).await, 0);
+
+    // reload로 제한을 바꿀 수 있음
+    limiter.set_limits(1f64, 1f64, Vec::new());
+    assert!(limiter.check(exempt_ip, later).await);
+    assert!(!limiter.check(exempt_ip, later).await);
+    limiter.set_limits(0f64, 0f64, Vec::new());
+    assert!(!limiter.is_enabled());
+    assert!(limiter.check(ip, later).await);
+
+    // rps가 0이면 제한 없음
+    let disabled = RateLimiter::new(0f64, 0f64, Vec::new());
+    assert!(!disabled.is_enabled());
+    for _ in 0..10 {
+        assert!(disabled.check(ip, start).await);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::secret;
+use crate::settings::{self, ConfigErrors, Settings};
+use crate::solr;
+use config::Config;
+use log::info;
+use std::sync::{Arc, RwLock};
+
+/// 실행 중 다시 읽을 수 있는 config와 설정값
+/// <br>
+/// 요청마다 settings()로 현재 값을 읽으며, reload 중에도 이전 값을 읽는 요청은 그대로 이전 값을 사용함
+pub struct SharedSettings {
+    config: RwLock<Arc<Config>>,
+    settings: RwLock<Arc<Settings>>,
+}
+
+/// reload 결과. 재시작해야 적용되는 설정은 바뀌어도 이전 값을 유지함
+#[derive(Debug)]
+pub struct ReloadReport {
+    pub old: Arc<Settings>,
+    pub new: Arc<Settings>,
+    /// 값이 바뀌었지만 재시작해야 적용되는 key 목록
+    pub requires_restart: Vec<&'static str>,
+}
+
+/// config에는 있지만 Settings에는 없는 값 중 재시작해야 적용되는 key
+const RESTART_CONFIG_KEYS: [&str; 4] = ["solr_kr", "db_host", "db_user", "db_schema"];
+
+impl SharedSettings {
+    pub fn new(config: Config, settings: Settings) -> Self {
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            settings: RwLock::new(Arc::new(settings)),
+        }
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    pub fn settings(&self) -> Arc<Settings> {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// new_config를 확인한 뒤 실행 중 바꿀 수 있는 설정만 적용함
+    /// <br>
+    /// 잘못된 값이 있으면 아무것도 바꾸지 않고 에러 반환
+    pub fn reload(
+        &self,
+        new_config: Config,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<ReloadReport, ConfigErrors> {
+        let new_settings = settings::validate_config(&new_config, &env)?;
+
+        let old_config = self.config();
+        let old = self.settings();
+        let (new, mut requires_restart) = old.reload(new_settings);
+        for key in RESTART_CONFIG_KEYS {
+            if old_config.get_string(key).ok() != new_config.get_string(key).ok() {
+                requires_restart.push(key);
+            }
+        }
+        let old_pwd = secret::get_secret(&old_config, "db_pwd", &env).ok();
+        let new_pwd = secret::get_secret(&new_config, "db_pwd", &env).ok();
+        if old_pwd.map(|(pwd, _)| pwd) != new_pwd.map(|(pwd, _)| pwd) {
+            requires_restart.push("db_pwd");
+        }
+        let old_headers = solr::upstream_headers(&old_config, &env).ok();
+        let new_headers = solr::upstream_headers(&new_config, &env).ok();
+        if old_headers.map(|(headers, _)| headers) != new_headers.map(|(headers, _)| headers) {
+            requires_restart.push("solr_user, solr_pwd, solr_extra_headers");
+        }
+
+        let new = Arc::new(new);
+        *self.config.write().unwrap() = Arc::new(new_config);
+        *self.settings.write().unwrap() = new.clone();
+        Ok(ReloadReport {
+            old,
+            new,
+            requires_restart,
+        })
+    }
+}
+
+/// SIGHUP을 받을 때마다 reload를 실행함. unix가 아닌 경우 아무것도 하지 않음
+pub async fn reload_on_hangup(reload: impl Fn()) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sighup = signal(SignalKind::hangup()).expect("SIGHUP handler FAIL");
+        while sighup.recv().await.is_some() {
+            info!("SIGHUP received");
+            reload();
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = reload;
+    }
+}
+
+#[test]
+fn reload_test() {
+    use config::{File, FileFormat};
+    use std::time::Duration;
+
+    const BASE: &str = r#"
+        solr_kr = "http://localhost:8983"
+        db_host = "localhost"
+        db_user = "proxy"
+        db_pwd = "pwd"
+        db_schema = "seed"
+    "#;
+    let config_with = |base: &str, toml: &str| {
+        Config::builder()
+            .add_source(File::from_str(
+                &format!("{}\n{}", base, toml),
+                FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+    };
+    let config_from = |toml: &str| config_with(BASE, toml);
+    let no_env = |_: &str| None;
+
+    let config = config_from("slow_select_ms = 100");
+    let settings = Settings::from_config(&config).unwrap();
+    let shared = SharedSettings::new(config, settings);
+    assert_eq!(shared.settings().slow_select, Duration::from_millis(100));
+
+    // 실행 중 바꿀 수 있는 값은 적용하고, 재시작해야 하는 값은 이전 값을 유지함
+    let report = shared
+        .reload(
+            config_from(
+                r#"
+                slow_select_ms = 500
+                normalize_dates = true
+                db_max_connections = 20
+                "#,
+            ),
+            no_env,
+        )
+        .unwrap();
+    assert_eq!(report.old.slow_select, Duration::from_millis(100));
+    assert_eq!(report.requires_restart, ["db_max_connections"]);
+    let settings = shared.settings();
+    assert_eq!(settings.slow_select, Duration::from_millis(500));
+    assert!(settings.normalize_dates);
+    assert_eq!(settings.db_max_connections, 10);
+    assert_eq!(shared.config().get_int("slow_select_ms").unwrap(), 500);
+
+    // 잘못된 config는 적용하지 않음
+    let errors = shared
+        .reload(
+            config_from("slow_select_ms = -1\nlog_level = \"loud\""),
+            no_env,
+        )
+        .unwrap_err();
+    assert_eq!(errors.0.len(), 2, "{}", errors);
+    assert_eq!(shared.settings().slow_select, Duration::from_millis(500));
+    assert_eq!(shared.config().get_int("slow_select_ms").unwrap(), 500);
+
+    // config에만 있는 솔라, DB 접속 정보도 재시작해야 적용됨
+    let base = BASE
+        .replace("localhost:8983", "solr2:8983")
+        .replace(r#""pwd""#, r#""changed""#);
+    let config = config_with(&base, "slow_select_ms = 500");
+    let report = shared.reload(config, no_env).unwrap();
+    assert_eq!(report.requires_restart, ["solr_kr", "db_pwd"]);
+}
@@ -0,0 +1,66 @@
+/// path가 차단 목록에 해당하는지 확인
+/// <br>
+/// 차단 목록은 솔라 기준의 path prefix이며, /solr로 시작하는 path는 /solr를 뗀 뒤 비교함
+pub fn is_denied_path(path: &str, deny_prefixes: &[String]) -> bool {
+    let solr_path = path.strip_prefix("/solr").unwrap_or(path);
+
+    deny_prefixes
+        .iter()
+        .any(|prefix| path.starts_with(prefix.as_str()) || solr_path.starts_with(prefix.as_str()))
+}
+
+/// /solr/<collection>/update 형식의 path에서 컬렉션 이름을 찾음
+/// <br>
+/// /solr가 없는 /<collection>/update도 사용할 수 있으며, /update처럼 컬렉션이 없는 경우 None
+pub fn collection_name(path: &str) -> Option<&str> {
+    let solr_path = path
+        .strip_prefix("/solr/")
+        .unwrap_or_else(|| path.trim_start_matches('/'));
+    let (name, rest) = solr_path.split_once('/')?;
+    Some(name).filter(|name| !name.is_empty() && !rest.is_empty())
+}
+
+/// /update/json, /update/extract, /update/csv처럼 proxy에서 처리하지 않는 update handler의 path인지 확인
+/// <br>
+/// passthrough로 그대로 보내도 색인을 바꾸므로 /update와 같이 확인해야 함
+pub fn is_update_handler(path: &str) -> bool {
+    path.contains("/update/")
+}
+
+#[test]
+fn denied_path_test() {
+    let deny_prefixes = vec!["/admin/cores".to_string()];
+
+    assert!(is_denied_path("/admin/cores", &deny_prefixes));
+    assert!(is_denied_path("/solr/admin/cores", &deny_prefixes));
+    assert!(is_denied_path(
+        "/solr/admin/cores?action=UNLOAD",
+        &deny_prefixes
+    ));
+    assert!(!is_denied_path("/solr/core/admin/ping", &deny_prefixes));
+    assert!(!is_denied_path("/solr/core/schema", &deny_prefixes));
+    assert!(!is_denied_path("/solr/admin/info/system", &deny_prefixes));
+    assert!(!is_denied_path("/solr/admin/cores", &[]));
+}
+
+#[test]
+fn collection_name_test() {
+    assert_eq!(collection_name("/solr/ko/update"), Some("ko"));
+    assert_eq!(collection_name("/solr/ja/update/json"), Some("ja"));
+    assert_eq!(collection_name("/ja/update"), Some("ja"));
+    assert_eq!(collection_name("/solr/update"), None);
+    assert_eq!(collection_name("/update"), None);
+    assert_eq!(collection_name("/solr//update"), None);
+}
+
+#[test]
+fn update_handler_test() {
+    assert!(is_update_handler("/solr/core/update/json"));
+    assert!(is_update_handler("/solr/core/update/json/docs"));
+    assert!(is_update_handler("/core/update/extract"));
+    assert!(is_update_handler("/update/csv"));
+    assert!(!is_update_handler("/solr/core/update"));
+    assert!(!is_update_handler("/solr/core/select"));
+    assert!(!is_update_handler("/solr/core/updates/json"));
+    assert!(!is_update_handler("/solr/admin/cores"));
+}
@@ -0,0 +1,262 @@
+use crate::context::{RequestContext, Scheme};
+use crate::settings::Settings;
+use crate::solr::Solr;
+use crate::BoxedError;
+use hyper::{Body, HeaderMap, Method, Uri};
+use log::{error, info, warn};
+use std::time::Duration;
+
+/// 솔라 schema에 필요한 필드가 없는 경우의 처리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaCheckAction {
+    /// 에러 로그를 남기고 계속 시작함
+    #[default]
+    Warn,
+    /// 서버를 시작하지 않고 종료함
+    Abort,
+}
+
+impl SchemaCheckAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" => Some(Self::Warn),
+            "abort" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// 확인할 core와 core마다 있어야 하는 필드 목록
+/// <br>
+/// verify_schema_cores가 없으면 collections의 컬렉션을 확인함. seed_id와 seed_url_fields, required_fields가 있어야 함
+pub fn expected_fields(settings: &Settings) -> Vec<(String, Vec<String>)> {
+    let cores: Vec<String> = match settings.verify_schema_cores.is_empty() {
+        true => {
+            let mut names: Vec<String> = settings.collections.keys().cloned().collect();
+            names.sort();
+            names
+        }
+        false => settings.verify_schema_cores.clone(),
+    };
+    cores
+        .into_iter()
+        .map(|core| {
+            let collection = settings.collection_for_path(&format!("/solr/{}/update", core));
+            let mut fields = vec![collection.seed_id_field.as_str().to_string()];
+            for field in collection
+                .seed_url_fields
+                .iter()
+                .chain(&collection.required_fields)
+            {
+                if !fields.contains(field) {
+                    fields.push(field.clone());
+                }
+            }
+            (core, fields)
+        })
+        .collect()
+}
+
+/// /schema/fields 응답의 필드 이름 목록
+pub fn parse_field_names(body: &[u8]) -> Result<Vec<String>, BoxedError> {
+    let value: serde_json::Value = serde_json::from_slice(body)?;
+    let fields = value["fields"]
+        .as_array()
+        .ok_or("SCHEMA_FIELDS_NOT_FOUND")?;
+    Ok(fields
+        .iter()
+        .filter_map(|field| field["name"].as_str())
+        .map(str::to_string)
+        .collect())
+}
+
+/// core의 schema에서 expected 중 없는 필드 목록. 솔라가 2xx가 아닌 status로 응답하면 에러
+pub async fn missing_schema_fields(
+    solr: &Solr,
+    core: &str,
+    expected: &[String],
+) -> Result<Vec<String>, BoxedError> {
+    let ctx = RequestContext {
+        request_id: "schema-check".to_string(),
+        remote_ip: ([127, 0, 0, 1], 0).into(),
+        scheme: Scheme::Http,
+    };
+    let uri: Uri = format!("/solr/{}/schema/fields", core).parse()?;
+    let response = solr
+        .send_request(uri, Method::GET, HeaderMap::new(), Body::empty(), &ctx)
+        .await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    if !status.is_success() {
+        return Err(format!("SCHEMA_FIELDS_STATUS: {}", status).into());
+    }
+    let names = parse_field_names(&body)?;
+    Ok(expected
+        .iter()
+        .filter(|field| !names.contains(field))
+        .cloned()
+        .collect())
+}
+
+/// 시작할 때 core마다 필요한 필드가 있는지 확인함. 없는 필드가 있는 core가 하나라도 있으면 false
+/// <br>
+/// 솔라에 연결할 수 없거나 timeout 안에 끝나지 않으면 경고만 남기고 true
+pub async fn verify(solr: &Solr, cores: &[(String, Vec<String>)], timeout: Duration) -> bool {
+    if cores.is_empty() {
+        warn!("SCHEMA_CHECK_SKIPPED: no core to check. set verify_schema_cores");
+        return true;
+    }
+    let check = async {
+        let mut ok = true;
+        for (core, expected) in cores {
+            match missing_schema_fields(solr, core, expected).await {
+                Ok(missing) if missing.is_empty() => {
+                    info!("schema check: {} has {}", core, expected.join(", "));
+                }
+                Ok(missing) => {
+                    error!(
+                        "SCHEMA_FIELD_MISSING: {} has no field {}",
+                        core,
+                        missing.join(", ")
+                    );
+                    ok = false;
+                }
+                Err(e) => warn!("SCHEMA_CHECK_SKIPPED: {}, {}", core, e),
+            }
+        }
+        ok
+    };
+    match tokio::time::timeout(timeout, check).await {
+        Ok(ok) => ok,
+        Err(_) => {
+            warn!("SCHEMA_CHECK_SKIPPED: timeout {}ms", timeout.as_millis());
+            true
+        }
+    }
+}
+
+#[test]
+fn parse_field_names_test() {
+    let body = br#"{"responseHeader":{"status":0},"fields":[{"name":"id","type":"string"},{"name":"url"}]}"#;
+    assert_eq!(parse_field_names(body).unwrap(), ["id", "url"]);
+    assert!(parse_field_names(br#"{"error":{}}"#).is_err());
+    assert!(parse_field_names(b"<html>").is_err());
+    assert_eq!(
+        SchemaCheckAction::parse(" Abort"),
+        Some(SchemaCheckAction::Abort)
+    );
+    assert_eq!(SchemaCheckAction::parse("exit"), None);
+}
+
+#[tokio::test]
+async fn verify_schema_test() {
+    use crate::mock::MockSolr;
+    use crate::solr::SolrClientConfig;
+    use hyper::Response;
+
+    // ko에는 seed_id가 없고 ja에는 모두 있음. 없는 core는 404
+    let mut mock = MockSolr::start_with(|req| {
+        let fields = match req.uri.path() {
+            "/solr/ko/schema/fields" => r#"[{"name":"id"},{"name":"url"}]"#,
+            "/solr/ja/schema/fields" => r#"[{"name":"id"},{"name":"url"},{"name":"seed_id"}]"#,
+            _ => {
+                return Response::builder()
+                    .status(404)
+                    .body(Body::from("not found"))
+                    .unwrap()
+            }
+        };
+        Response::new(Body::from(format!(r#"{{"fields":{}}}"#, fields)))
+    })
+    .await;
+    let solr = Solr::new(mock.url.clone(), false, SolrClientConfig::default());
+    let expected = vec!["seed_id".to_string(), "url".to_string()];
+
+    assert_eq!(
+        missing_schema_fields(&solr, "ko", &expected).await.unwrap(),
+        ["seed_id"]
+    );
+    assert_eq!(mock.next_request().await.method, Method::GET);
+    assert!(missing_schema_fields(&solr, "ja", &expected)
+        .await
+        .unwrap()
+        .is_empty());
+    let err = missing_schema_fields(&solr, "en", &expected)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "SCHEMA_FIELDS_STATUS: 404 Not Found");
+
+    let timeout = Duration::from_secs(2);
+    let cores = |names: &[&str]| -> Vec<(String, Vec<String>)> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), expected.clone()))
+            .collect()
+    };
+    assert!(!verify(&solr, &cores(&["ja", "ko"]), timeout).await);
+    // 확인할 수 없는 core는 건너뜀
+    assert!(verify(&solr, &cores(&["ja", "en"]), timeout).await);
+
+    // 솔라가 늦게 응답하면 timeout만 기다리고 계속 시작함
+    let slow = MockSolr::start_delayed(Duration::from_secs(5), |_| {
+        Response::new(Body::from(r#"{"fields":[]}"#))
+    })
+    .await;
+    let slow_solr = Solr::new(slow.url.clone(), false, SolrClientConfig::default());
+    let start = std::time::Instant::now();
+    assert!(verify(&slow_solr, &cores(&["ko"]), Duration::from_millis(100)).await);
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // 연결할 수 없는 솔라도 계속 시작함
+    let closed = Solr::new(
+        "http://127.0.0.1:1".to_string(),
+        false,
+        SolrClientConfig::default(),
+    );
+    assert!(verify(&closed, &cores(&["ko"]), timeout).await);
+}
+
+#[test]
+fn expected_fields_test() {
+    use config::{Config, File, FileFormat};
+
+    let config_from = |toml: &str| {
+        let config = Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap();
+        Settings::from_config(&config).unwrap()
+    };
+
+    let settings = config_from(
+        r#"
+        required_fields = ["id", "seed_id"]
+
+        [collections.ko]
+
+        [collections.ja]
+        required_fields = ["id", "title"]
+        "#,
+    );
+    assert!(!settings.verify_schema);
+    assert_eq!(settings.verify_schema_action, SchemaCheckAction::Warn);
+    // 지정한 core가 없으면 컬렉션 이름 순서. seed_id는 한 번만 넣음
+    let cores = expected_fields(&settings);
+    let names: Vec<&str> = cores.iter().map(|(core, _)| core.as_str()).collect();
+    assert_eq!(names, ["ja", "ko"]);
+    assert!(cores[0].1.starts_with(&["seed_id".to_string()]));
+    assert!(cores[0].1.contains(&"title".to_string()));
+    assert_eq!(cores[1].1.iter().filter(|f| *f == "seed_id").count(), 1);
+
+    let settings = config_from(
+        r#"
+        verify_schema = true
+        verify_schema_cores = ["news"]
+        verify_schema_action = "abort"
+        verify_schema_timeout_ms = 500
+        "#,
+    );
+    assert_eq!(settings.verify_schema_action, SchemaCheckAction::Abort);
+    assert_eq!(settings.verify_schema_timeout, Duration::from_millis(500));
+    assert_eq!(expected_fields(&settings)[0].0, "news");
+}
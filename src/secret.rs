@@ -0,0 +1,211 @@
+use config::{Config, ConfigError};
+use std::fmt;
+use std::fs;
+
+/// 환경변수 이름 앞에 붙는 값. db_pwd인 경우 SOLR_PROXY_DB_PWD
+pub const ENV_PREFIX: &str = "SOLR_PROXY";
+
+/// 비밀번호 등의 값을 읽어온 곳. 로그에는 값 대신 이것을 남김
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretSource {
+    Env(String),
+    File(String),
+    Inline,
+}
+
+impl fmt::Display for SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretSource::Env(name) => write!(f, "env {}", name),
+            SecretSource::File(path) => write!(f, "file {}", path),
+            SecretSource::Inline => write!(f, "config"),
+        }
+    }
+}
+
+/// key에 해당하는 비밀번호 등의 값을 읽음
+/// <br>
+/// 환경변수(SOLR_PROXY_KEY), key_file에 지정한 파일의 내용, config의 key 순서로 먼저 있는 값을 사용함.
+/// 파일 끝의 줄바꿈은 제거함
+pub fn get_secret(
+    config: &Config,
+    key: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(String, SecretSource), ConfigError> {
+    let env_name = format!("{}_{}", ENV_PREFIX, key.to_ascii_uppercase());
+    if let Some(value) = env(&env_name) {
+        return Ok((value, SecretSource::Env(env_name)));
+    }
+
+    let file_key = format!("{}_file", key);
+    match config.get_string(&file_key) {
+        Ok(path) => {
+            let value = fs::read_to_string(&path).map_err(|e| {
+                ConfigError::Message(format!(
+                    "SECRET_FILE_READ_FAIL: {} = {}, {}",
+                    file_key, path, e
+                ))
+            })?;
+            let value = value.trim_end_matches(['\r', '\n']).to_string();
+            return Ok((value, SecretSource::File(path)));
+        }
+        Err(ConfigError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    Ok((config.get_string(key)?, SecretSource::Inline))
+}
+
+/// 로그에 남길 수 있도록 값을 가림
+pub fn mask(value: &str) -> &'static str {
+    if value.is_empty() {
+        ""
+    } else {
+        "******"
+    }
+}
+
+/// 비밀번호 등 로그나 응답에 값을 남기면 안 되는 config key인지 확인함
+/// <br>
+/// 파일 경로인 key_file은 제외함
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    // redis_url에는 redis://:pwd@host 형식으로 비밀번호가 들어갈 수 있음
+    !key.ends_with("_file")
+        && [
+            "pwd",
+            "password",
+            "secret",
+            "token",
+            "authorization",
+            "redis_url",
+        ]
+        .iter()
+        .any(|word| key.contains(word))
+}
+
+/// config를 json으로 바꾸고 is_secret_key에 해당하는 값을 가림
+pub fn redacted_config(config: &Config) -> Result<serde_json::Value, ConfigError> {
+    let mut value: serde_json::Value = config.clone().try_deserialize()?;
+    redact(&mut value);
+    Ok(value)
+}
+
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) {
+                    let masked = mask(value.as_str().unwrap_or("-"));
+                    *value = serde_json::Value::String(masked.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[test]
+fn get_secret_test() {
+    let dir = std::env::temp_dir().join(format!("solr_proxy_secret_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pwd_file = dir.join("db_pwd");
+    fs::write(&pwd_file, "from_file\n").unwrap();
+    let pwd_file = pwd_file.to_string_lossy().into_owned();
+
+    let config_with = |values: &[(&str, &str)]| {
+        let mut builder = Config::builder();
+        for (key, value) in values {
+            builder = builder.set_override(*key, *value).unwrap();
+        }
+        builder.build().unwrap()
+    };
+    let no_env = |_: &str| None;
+    let env = |name: &str| (name == "SOLR_PROXY_DB_PWD").then(|| "from_env".to_string());
+
+    // 환경변수 > 파일 > config
+    let all = config_with(&[("db_pwd", "inline"), ("db_pwd_file", &pwd_file)]);
+    assert_eq!(
+        get_secret(&all, "db_pwd", env).unwrap(),
+        (
+            "from_env".to_string(),
+            SecretSource::Env("SOLR_PROXY_DB_PWD".to_string())
+        )
+    );
+    assert_eq!(
+        get_secret(&all, "db_pwd", no_env).unwrap(),
+        (
+            "from_file".to_string(),
+            SecretSource::File(pwd_file.clone())
+        )
+    );
+
+    let inline = config_with(&[("db_pwd", "inline")]);
+    assert_eq!(
+        get_secret(&inline, "db_pwd", env).unwrap().1,
+        SecretSource::Env("SOLR_PROXY_DB_PWD".to_string())
+    );
+    assert_eq!(
+        get_secret(&inline, "db_pwd", no_env).unwrap(),
+        ("inline".to_string(), SecretSource::Inline)
+    );
+
+    let file_only = config_with(&[("db_pwd_file", &pwd_file)]);
+    assert_eq!(get_secret(&file_only, "db_pwd", env).unwrap().0, "from_env");
+    assert_eq!(
+        get_secret(&file_only, "db_pwd", no_env).unwrap().0,
+        "from_file"
+    );
+
+    // 값이 없거나 파일을 읽을 수 없는 경우 에러
+    let empty = config_with(&[]);
+    assert!(get_secret(&empty, "db_pwd", no_env).is_err());
+    let missing_file = config_with(&[("db_pwd_file", "/nonexistent/solr_proxy/db_pwd")]);
+    let err = get_secret(&missing_file, "db_pwd", no_env).unwrap_err();
+    assert!(err.to_string().starts_with("SECRET_FILE_READ_FAIL"));
+
+    assert_eq!(mask("secret"), "******");
+    assert_eq!(mask(""), "");
+    assert_eq!(
+        SecretSource::Env("SOLR_PROXY_DB_PWD".to_string()).to_string(),
+        "env SOLR_PROXY_DB_PWD"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn redacted_config_test() {
+    let config = Config::builder()
+        .set_override("db_host", "localhost")
+        .unwrap()
+        .set_override("db_pwd", "p@ssw0rd")
+        .unwrap()
+        .set_override("db_pwd_file", "/run/secrets/db_pwd")
+        .unwrap()
+        .set_override("admin_secret", "s3cret")
+        .unwrap()
+        .set_override("stats_interval_secs", 30)
+        .unwrap()
+        .build()
+        .unwrap();
+
+    let value = redacted_config(&config).unwrap();
+    assert_eq!(value["db_host"], "localhost");
+    assert_eq!(value["db_pwd"], "******");
+    assert_eq!(value["db_pwd_file"], "/run/secrets/db_pwd");
+    assert_eq!(value["admin_secret"], "******");
+    assert_eq!(value["stats_interval_secs"], 30);
+
+    let text = value.to_string();
+    assert!(!text.contains("p@ssw0rd"));
+    assert!(!text.contains("s3cret"));
+
+    assert!(is_secret_key("DB_PWD"));
+    assert!(is_secret_key("solr_password"));
+    assert!(is_secret_key("redis_url"));
+    assert!(!is_secret_key("db_user"));
+}
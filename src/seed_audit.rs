@@ -0,0 +1,75 @@
+use crate::media_type::MediaType;
+use std::net::IpAddr;
+
+/// 새로 만든 seed_id 기록을 남기는 logger target
+pub const SEED_AUDIT_TARGET: &str = "seed_audit";
+
+/// DB에 새로 INSERT한 seed_id와 INSERT하게 만든 doc, 요청
+/// <br>
+/// 잘못된 매핑이 발견된 경우 어떤 요청에서 만들어졌는지 찾는 데 사용함
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedAudit {
+    pub seed_host: String,
+    pub seed_id: String,
+    /// INSERT하게 만든 doc의 id. 없는 경우 None
+    pub doc_id: Option<String>,
+    /// seed_host를 만든 url 값
+    pub url: String,
+    /// 요청한 클라이언트 ip. transform 명령 등 요청이 없는 경우 None
+    pub remote_ip: Option<IpAddr>,
+    /// INSERT할 때 media_type_rules로 정한 media_type_no, site_name
+    pub media_type: MediaType,
+}
+
+impl SeedAudit {
+    /// 한 줄의 기록. 값이 없는 경우 "-"
+    pub fn line(&self) -> String {
+        format!(
+            "SEED_ID_CREATED seed_host: {}, seed_id: {}, doc_id: {}, url: {}, from: {}, media_type_no: {}, site_name: {}",
+            self.seed_host,
+            self.seed_id,
+            self.doc_id.as_deref().unwrap_or("-"),
+            self.url,
+            self.remote_ip
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            self.media_type.media_type_no,
+            match self.media_type.site_name.is_empty() {
+                true => "-",
+                false => &self.media_type.site_name,
+            }
+        )
+    }
+}
+
+pub fn log_audit(audit: &SeedAudit) {
+    log::info!(target: SEED_AUDIT_TARGET, "{}", audit.line());
+}
+
+#[test]
+fn seed_audit_line_test() {
+    let mut audit = SeedAudit {
+        seed_host: "cafe.naver.com/abc".to_string(),
+        seed_id: "e7531c15-2384-11ed-b560-42010a025a43".to_string(),
+        doc_id: Some("a77b3908fb67bd1b".to_string()),
+        url: "https://cafe.naver.com/abc/185".to_string(),
+        remote_ip: Some("10.0.0.1".parse().unwrap()),
+        media_type: MediaType {
+            media_type_no: 2,
+            site_name: "abc".to_string(),
+        },
+    };
+    assert_eq!(
+        audit.line(),
+        "SEED_ID_CREATED seed_host: cafe.naver.com/abc, seed_id: e7531c15-2384-11ed-b560-42010a025a43, \
+doc_id: a77b3908fb67bd1b, url: https://cafe.naver.com/abc/185, from: 10.0.0.1, media_type_no: 2, site_name: abc"
+    );
+
+    audit.doc_id = None;
+    audit.remote_ip = None;
+    audit.media_type = MediaType::default();
+    assert!(audit.line().contains("doc_id: -, "));
+    assert!(audit
+        .line()
+        .ends_with("from: -, media_type_no: 0, site_name: -"));
+}
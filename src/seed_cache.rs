@@ -0,0 +1,314 @@
+use hashbrown::hash_map::DefaultHashBuilder;
+use hashbrown::HashMap;
+use lru::LruCache;
+use std::hash::BuildHasher;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard};
+
+type Shard = LruCache<String, String, DefaultHashBuilder>;
+
+/// seed_host별 seed_id cache
+/// <br>
+/// 하나의 lock을 모든 요청이 기다리지 않도록 seed_host의 hash로 shard를 나누고 shard마다 lock을 둠
+/// <br>
+/// LRU는 shard 안에서만 적용하므로 전체 용량은 shard 수로 나눈 만큼씩 사용함
+pub struct SeedIdCache {
+    shards: Box<[Mutex<Shard>]>,
+    /// shard를 고를 때 사용함. LruCache 안의 hash와 다른 값이 나오도록 따로 둠
+    hasher: DefaultHashBuilder,
+    /// lock을 얻은 횟수
+    lock_cnt: AtomicU64,
+    /// 다른 요청이 lock을 잡고 있어 기다린 횟수
+    lock_wait_cnt: AtomicU64,
+    /// 저장된 seed_host와 seed_id 길이의 합. 넣고 지울 때마다 갱신함
+    entry_bytes: AtomicU64,
+    /// 용량이 차서 지운 횟수
+    evict_cnt: AtomicU64,
+}
+
+/// key와 value가 차지하는 bytes
+fn entry_len(seed_host: &str, seed_id: &str) -> u64 {
+    (seed_host.len() + seed_id.len()) as u64
+}
+
+impl SeedIdCache {
+    /// shard_cnt는 2의 거듭제곱이어야 함
+    pub fn new(capacity: usize, shard_cnt: usize) -> Self {
+        assert!(shard_cnt.is_power_of_two(), "shard_cnt: {}", shard_cnt);
+        let shard_capacity = NonZeroUsize::new(capacity.div_ceil(shard_cnt).max(1)).unwrap();
+        let shards = (0..shard_cnt)
+            .map(|_| {
+                Mutex::new(LruCache::with_hasher(
+                    shard_capacity,
+                    DefaultHashBuilder::default(),
+                ))
+            })
+            .collect();
+        Self {
+            shards,
+            hasher: DefaultHashBuilder::default(),
+            lock_cnt: AtomicU64::new(0),
+            lock_wait_cnt: AtomicU64::new(0),
+            entry_bytes: AtomicU64::new(0),
+            evict_cnt: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn get(&self, seed_host: &str) -> Option<String> {
+        self.lock_shard(seed_host).await.get(seed_host).cloned()
+    }
+
+    /// LRU 순서를 바꾸지 않고 조회함
+    pub async fn peek(&self, seed_host: &str) -> Option<String> {
+        self.lock_shard(seed_host).await.peek(seed_host).cloned()
+    }
+
+    pub async fn put(&self, seed_host: String, seed_id: String) {
+        let added = entry_len(&seed_host, &seed_id);
+        let mut shard = self.lock_shard(&seed_host).await;
+        let is_new = !shard.contains(&seed_host);
+        let removed = shard.push(seed_host, seed_id);
+        self.entry_bytes.fetch_add(added, Ordering::Relaxed);
+        if let Some((removed_host, removed_id)) = removed {
+            self.entry_bytes
+                .fetch_sub(entry_len(&removed_host, &removed_id), Ordering::Relaxed);
+            // 같은 seed_host의 값을 바꾼 경우가 아니면 용량이 차서 지운 것
+            if is_new {
+                self.evict_cnt.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// cache에서 seed_host를 지우고 지운 seed_id를 반환함
+    pub async fn pop(&self, seed_host: &str) -> Option<String> {
+        let (removed_host, removed_id) = self.lock_shard(seed_host).await.pop_entry(seed_host)?;
+        self.entry_bytes
+            .fetch_sub(entry_len(&removed_host, &removed_id), Ordering::Relaxed);
+        Some(removed_id)
+    }
+
+    /// 모든 shard의 cache 수 합계
+    pub async fn len(&self) -> usize {
+        let mut len = 0;
+        for shard in self.shards.iter() {
+            len += shard.lock().await.len();
+        }
+        len
+    }
+
+    pub async fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock().await;
+            let removed: u64 = shard
+                .iter()
+                .map(|(seed_host, seed_id)| entry_len(seed_host, seed_id))
+                .sum();
+            shard.clear();
+            self.entry_bytes.fetch_sub(removed, Ordering::Relaxed);
+        }
+    }
+
+    /// 저장된 seed_host와 seed_id 길이의 합. LruCache 자체가 사용하는 메모리는 포함하지 않는 추정치
+    pub fn entry_bytes(&self) -> u64 {
+        self.entry_bytes.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간에 용량이 차서 지운 횟수
+    pub fn evict_cnt(&self) -> u64 {
+        self.evict_cnt.load(Ordering::Relaxed)
+    }
+
+    /// 이번 구간에 용량이 차서 지운 횟수를 반환하고 0부터 다시 셈
+    pub fn take_evict_cnt(&self) -> u64 {
+        self.evict_cnt.swap(0, Ordering::Relaxed)
+    }
+
+    /// 이번 구간에 lock을 얻은 횟수와 그 중 기다린 횟수를 반환하고 0부터 다시 셈
+    pub fn take_lock_cnt(&self) -> (u64, u64) {
+        (
+            self.lock_cnt.swap(0, Ordering::Relaxed),
+            self.lock_wait_cnt.swap(0, Ordering::Relaxed),
+        )
+    }
+
+    async fn lock_shard(&self, seed_host: &str) -> MutexGuard<'_, Shard> {
+        let index = self.hasher.hash_one(seed_host) as usize & (self.shards.len() - 1);
+        let shard = &self.shards[index];
+        self.lock_cnt.fetch_add(1, Ordering::Relaxed);
+        match shard.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.lock_wait_cnt.fetch_add(1, Ordering::Relaxed);
+                shard.lock().await
+            }
+        }
+    }
+}
+
+/// LookupFailures에 기록하는 최대 seed_host 수
+const MAX_LOOKUP_FAILURES: usize = 10_000;
+
+/// 최근 DB 조회에 실패한 seed_host
+/// <br>
+/// ttl 동안은 같은 seed_host를 DB에서 다시 조회하지 않음. ttl이 0이면 기록하지 않음
+pub struct LookupFailures {
+    ttl: Duration,
+    failed_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl LookupFailures {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            failed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ttl 안에 조회에 실패한 seed_host인 경우 true. ttl이 지난 기록은 지움
+    pub async fn is_suppressed(&self, seed_host: &str, now: Instant) -> bool {
+        let mut failed_at = self.failed_at.lock().await;
+        match failed_at.get(seed_host) {
+            Some(at) if now.saturating_duration_since(*at) < self.ttl => true,
+            Some(_) => {
+                failed_at.remove(seed_host);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub async fn record_failure(&self, seed_host: &str, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut failed_at = self.failed_at.lock().await;
+        if failed_at.len() >= MAX_LOOKUP_FAILURES {
+            failed_at.retain(|_, at| now.saturating_duration_since(*at) < self.ttl);
+        }
+        // DB 장애로 모든 seed_host가 실패하는 경우에도 일정 크기 이상 커지지 않도록 함
+        if failed_at.len() < MAX_LOOKUP_FAILURES {
+            failed_at.insert(seed_host.to_string(), now);
+        }
+    }
+
+    /// 조회에 성공한 경우 기록을 지움
+    pub async fn clear(&self, seed_host: &str) {
+        self.failed_at.lock().await.remove(seed_host);
+    }
+}
+
+#[tokio::test]
+async fn seed_id_cache_test() {
+    let cache = SeedIdCache::new(1000, 16);
+    for i in 0..32 {
+        cache.put(format!("host{}", i), format!("seed{}", i)).await;
+    }
+    assert_eq!(cache.len().await, 32);
+    assert_eq!(cache.get("host7").await.as_deref(), Some("seed7"));
+    assert_eq!(cache.pop("host7").await.as_deref(), Some("seed7"));
+    assert_eq!(cache.get("host7").await, None);
+    assert_eq!(cache.len().await, 31);
+
+    // 용량은 shard 수로 나눠서 사용함
+    let cache = SeedIdCache::new(4, 4);
+    for i in 0..100 {
+        cache.put(format!("host{}", i), format!("seed{}", i)).await;
+    }
+    assert_eq!(cache.len().await, 4);
+    cache.clear().await;
+    assert_eq!(cache.len().await, 0);
+}
+
+#[tokio::test]
+async fn seed_id_cache_bytes_test() {
+    // 모든 seed_host가 같은 shard에 들어가도록 shard는 1개
+    let cache = SeedIdCache::new(2, 1);
+    cache.put("a.com".to_string(), "seed-1".to_string()).await;
+    cache.put("bb.com".to_string(), "seed-2".to_string()).await;
+    assert_eq!(cache.entry_bytes(), 11 + 12);
+
+    // 같은 seed_host의 값을 바꾸면 이전 값만큼 줄이고 지운 횟수로 세지 않음
+    cache.put("a.com".to_string(), "seed-100".to_string()).await;
+    assert_eq!(cache.entry_bytes(), 13 + 12);
+    assert_eq!(cache.evict_cnt(), 0);
+
+    // 용량이 차면 오래 사용하지 않은 bb.com을 지움
+    cache.put("ccc.com".to_string(), "seed-3".to_string()).await;
+    assert_eq!(cache.get("bb.com").await, None);
+    assert_eq!(cache.entry_bytes(), 13 + 13);
+    assert_eq!(cache.take_evict_cnt(), 1);
+    assert_eq!(cache.evict_cnt(), 0);
+
+    assert_eq!(cache.pop("a.com").await.as_deref(), Some("seed-100"));
+    assert_eq!(cache.pop("a.com").await, None);
+    assert_eq!(cache.entry_bytes(), 13);
+
+    cache.clear().await;
+    assert_eq!(cache.entry_bytes(), 0);
+}
+
+#[tokio::test]
+async fn seed_id_cache_lock_wait_test() {
+    // 한 요청이 lock을 잡고 있는 동안 다른 seed_host를 조회한 요청 중 기다린 수
+    async fn lock_wait_cnt(cache: &SeedIdCache) -> u64 {
+        let held = cache.lock_shard("cafe.naver.com/held").await;
+        cache.take_lock_cnt();
+        let lookups = futures_util::future::join_all((0..160).map(|i| {
+            let seed_host = format!("blog.naver.com/user{}", i);
+            async move { cache.get(&seed_host).await }
+        }));
+        let release = async {
+            tokio::task::yield_now().await;
+            drop(held);
+        };
+        tokio::join!(lookups, release);
+        let (lock_cnt, lock_wait_cnt) = cache.take_lock_cnt();
+        assert_eq!(lock_cnt, 160);
+        lock_wait_cnt
+    }
+
+    // lock이 하나인 경우 모든 요청이 기다림
+    let single = lock_wait_cnt(&SeedIdCache::new(1000, 1)).await;
+    assert_eq!(single, 160);
+
+    // shard를 나누면 같은 shard의 요청만 기다림
+    let sharded = lock_wait_cnt(&SeedIdCache::new(1000, 16)).await;
+    assert!(sharded < single / 4, "sharded: {}", sharded);
+}
+
+#[tokio::test]
+async fn lookup_failures_test() {
+    let failures = LookupFailures::new(Duration::from_secs(5));
+    let now = Instant::now();
+    assert!(!failures.is_suppressed("host", now).await);
+
+    // ttl 동안은 다시 조회하지 않음
+    failures.record_failure("host", now).await;
+    assert!(failures.is_suppressed("host", now).await);
+    assert!(
+        failures
+            .is_suppressed("host", now + Duration::from_millis(4999))
+            .await
+    );
+    assert!(!failures.is_suppressed("other", now).await);
+
+    // ttl이 지나면 다시 조회함
+    assert!(
+        !failures
+            .is_suppressed("host", now + Duration::from_secs(5))
+            .await
+    );
+    assert!(!failures.is_suppressed("host", now).await);
+
+    // 조회에 성공하면 기록을 지움
+    failures.record_failure("host", now).await;
+    failures.clear("host").await;
+    assert!(!failures.is_suppressed("host", now).await);
+
+    // ttl이 0이면 기록하지 않음
+    let disabled = LookupFailures::new(Duration::ZERO);
+    disabled.record_failure("host", now).await;
+    assert!(!disabled.is_suppressed("host", now).await);
+}
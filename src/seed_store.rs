@@ -0,0 +1,730 @@
+use crate::error_kind::ErrorKind;
+use crate::insert_guard::InsertGuard;
+use crate::media_type::MediaType;
+use crate::seed_audit::SeedAudit;
+use crate::seed_cache::LookupFailures;
+use crate::settings::CollectionSettings;
+use crate::shared_cache::SharedSeedCache;
+use crate::util::StrError;
+use crate::{settings, BoxedError, CON, IN_FLIGHT, WORKING_CNT};
+use hyper::StatusCode;
+use log::debug;
+use sqlx::mysql::MySqlDatabaseError;
+use sqlx::Row;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// seed_host에 해당하는 seed_id를 조회/생성하는 저장소
+pub trait SeedIdStore {
+    /// seed_host에 해당하는 seed_id 조회. 없는 경우 None
+    fn select_seed_id(
+        &self,
+        seed_host: &str,
+    ) -> impl Future<Output = Result<Option<String>, BoxedError>> + Send;
+
+    /// seed_host에 해당하는 seed_id를 새로 만듦. 이미 있는 경우 아무 작업도 하지 않음
+    /// <br>
+    /// 새로 만든 경우 true. 동시에 다른 요청이 먼저 만든 경우 false
+    /// <br>
+    /// media_type은 media_type_rules로 정한 새 row의 media_type_no, site_name
+    fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> impl Future<Output = Result<bool, BoxedError>> + Send;
+
+    /// 새로 만든 seed_id 기록을 저장함. 저장하지 않는 저장소는 아무 작업도 하지 않음
+    fn insert_audit(
+        &self,
+        _audit: &SeedAudit,
+    ) -> impl Future<Output = Result<(), BoxedError>> + Send {
+        std::future::ready(Ok(()))
+    }
+
+    /// 새 seed_id를 INSERT해도 되는 경우 true. false인 경우 seed_id 없이 솔라로 보냄
+    fn allow_insert(&self) -> bool {
+        true
+    }
+}
+
+/// seed table의 row 하나
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedRow {
+    pub seed_id: String,
+    /// row의 media_type_no, site_name
+    pub media_type: MediaType,
+}
+
+/// seed_id 외의 컬럼도 함께 조회하는 저장소. /proxy/debug/seed에서 사용함
+pub trait SeedRowStore {
+    fn select_row(
+        &self,
+        seed_host: &str,
+    ) -> impl Future<Output = Result<Option<SeedRow>, BoxedError>> + Send;
+}
+
+/// seed_table(기본값 crawlerdb.t_channel_contents_map)을 사용하는 저장소
+pub struct MySqlSeedIdStore<'a> {
+    /// table 이름은 설정을 읽을 때 확인함
+    table: &'a str,
+}
+
+impl<'a> MySqlSeedIdStore<'a> {
+    pub fn new(table: &'a str) -> Self {
+        Self { table }
+    }
+}
+
+impl SeedRowStore for MySqlSeedIdStore<'_> {
+    async fn select_row(&self, seed_host: &str) -> Result<Option<SeedRow>, BoxedError> {
+        let sql = format!(
+            "SELECT seed_id, site_name, media_type_no FROM {} WHERE media_url = ?;",
+            self.table
+        );
+        let query = sqlx::query(&sql).bind(seed_host);
+        let Some(row) = IN_FLIGHT.db.track(query.fetch_optional(&*CON)).await? else {
+            return Ok(None);
+        };
+        Ok(Some(SeedRow {
+            seed_id: row.try_get::<&str, _>("seed_id")?.to_string(),
+            media_type: MediaType {
+                media_type_no: u32::try_from(row.try_get::<i64, _>("media_type_no")?)
+                    .unwrap_or_default(),
+                site_name: row.try_get::<&str, _>("site_name")?.to_string(),
+            },
+        }))
+    }
+}
+
+impl SeedIdStore for MySqlSeedIdStore<'_> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        let sql = format!("SELECT seed_id FROM {} WHERE media_url = ?;", self.table);
+        let query = sqlx::query(&sql).bind(seed_host);
+        let row = IN_FLIGHT.db.track(query.fetch_optional(&*CON)).await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get::<&str, _>("seed_id")?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        let sql = format!(
+            "INSERT IGNORE INTO {}
+(seed_id, site_name, media_url, media_type_no)
+VALUES
+(uuid(), ?, ?, ?);",
+            self.table
+        );
+        let query = sqlx::query(&sql)
+            .bind(&media_type.site_name)
+            .bind(seed_host)
+            .bind(media_type.media_type_no.to_string());
+        let result = IN_FLIGHT.db.track(query.execute(&*CON)).await?;
+        // INSERT IGNORE는 이미 있는 경우 에러 없이 0 row를 반환함
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// seed_audit_table이 설정된 경우에만 저장함
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        let table = &settings().seed_audit_table;
+        if table.is_empty() {
+            return Ok(());
+        }
+        // table 이름은 설정을 읽을 때 확인함
+        let sql = format!(
+            "INSERT INTO {} (seed_host, seed_id, doc_id, url, remote_ip, created_at)
+VALUES
+(?, ?, ?, ?, ?, NOW());",
+            table
+        );
+        let query = sqlx::query(&sql)
+            .bind(&audit.seed_host)
+            .bind(&audit.seed_id)
+            .bind(&audit.doc_id)
+            .bind(&audit.url)
+            .bind(audit.remote_ip.map(|ip| ip.to_string()));
+        IN_FLIGHT.db.track(query.execute(&*CON)).await?;
+        Ok(())
+    }
+}
+
+/// DB 없이 모든 seed_host에 OFFLINE_SEED_ID를 주는 저장소. transform --offline에서 사용함
+pub struct OfflineSeedIdStore;
+
+/// OfflineSeedIdStore가 주는 seed_id
+pub const OFFLINE_SEED_ID: &str = "OFFLINE";
+
+impl SeedIdStore for OfflineSeedIdStore {
+    async fn select_seed_id(&self, _seed_host: &str) -> Result<Option<String>, BoxedError> {
+        Ok(Some(OFFLINE_SEED_ID.to_string()))
+    }
+
+    async fn insert_seed_id(
+        &self,
+        _seed_host: &str,
+        _media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        Ok(false)
+    }
+}
+
+/// 일시적인 DB 에러를 다시 시도하는 방식
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 다시 시도하는 최대 횟수. 0이면 다시 시도하지 않음
+    pub count: u32,
+    /// 처음 다시 시도하기 전 기다릴 시간. 다시 시도할 때마다 2배로 늘어남
+    pub base: Duration,
+    /// 다시 시도하기 전 기다릴 최대 시간
+    pub max: Duration,
+}
+
+impl RetryPolicy {
+    /// attempt번째(0부터) 다시 시도하기 전 기다릴 시간
+    /// <br>
+    /// 여러 요청이 동시에 다시 시도하지 않도록 절반은 jitter(0 이상 1 미만)에 비례함
+    pub fn delay(&self, attempt: u32, jitter: f64) -> Duration {
+        let cap = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max);
+        cap / 2 + cap.mul_f64(jitter.clamp(0f64, 1f64)) / 2
+    }
+}
+
+/// 일시적인 DB 에러인 경우 policy에 따라 다시 시도하는 저장소
+pub struct RetrySeedIdStore<S> {
+    inner: S,
+    policy: RetryPolicy,
+}
+
+impl<S: SeedIdStore + Sync> RetrySeedIdStore<S> {
+    pub fn new(inner: S, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    async fn retry<T, Fut>(
+        &self,
+        name: &str,
+        seed_host: &str,
+        mut op: impl FnMut() -> Fut,
+    ) -> Result<T, BoxedError>
+    where
+        Fut: Future<Output = Result<T, BoxedError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if attempt < self.policy.count && is_retryable(&e) => {
+                    let delay = self.policy.delay(attempt, jitter());
+                    attempt += 1;
+                    debug!(
+                        "DB_RETRY {} {}/{} after {}ms, seed_host: {}, err: {}",
+                        name,
+                        attempt,
+                        self.policy.count,
+                        delay.as_millis(),
+                        seed_host,
+                        e
+                    );
+                    WORKING_CNT.lock().await.db_retry_cnt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<S: SeedIdStore + Sync> SeedIdStore for RetrySeedIdStore<S> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.retry("SELECT", seed_host, || self.inner.select_seed_id(seed_host))
+            .await
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        self.retry("INSERT", seed_host, || {
+            self.inner.insert_seed_id(seed_host, media_type)
+        })
+        .await
+    }
+
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        self.retry("AUDIT", &audit.seed_host, || self.inner.insert_audit(audit))
+            .await
+    }
+
+    fn allow_insert(&self) -> bool {
+        self.inner.allow_insert()
+    }
+}
+
+/// 최근 조회에 실패한 seed_host는 DB에 요청하지 않고 곧바로 실패하는 저장소
+/// <br>
+/// DB 장애 중 같은 seed_host의 doc마다 DB에 요청하지 않도록 함
+pub struct SuppressFailureStore<'a, S> {
+    inner: S,
+    failures: &'a LookupFailures,
+    /// 실패 기록 key를 만드는 컬렉션 설정. None이면 seed_host를 그대로 key로 사용함
+    collection: Option<&'a CollectionSettings>,
+}
+
+impl<'a, S: SeedIdStore + Sync> SuppressFailureStore<'a, S> {
+    pub fn new(inner: S, failures: &'a LookupFailures) -> Self {
+        Self {
+            inner,
+            failures,
+            collection: None,
+        }
+    }
+
+    /// 컬렉션마다 seed table이 다르므로 실패 기록도 컬렉션마다 나눔
+    pub fn with_collection(mut self, collection: &'a CollectionSettings) -> Self {
+        self.collection = Some(collection);
+        self
+    }
+
+    fn key(&self, seed_host: &str) -> String {
+        match self.collection {
+            Some(collection) => collection.cache_key(seed_host),
+            None => seed_host.to_string(),
+        }
+    }
+
+    async fn suppress<T, Fut>(&self, seed_host: &str, op: Fut) -> Result<T, BoxedError>
+    where
+        Fut: Future<Output = Result<T, BoxedError>>,
+    {
+        let key = self.key(seed_host);
+        if self.failures.is_suppressed(&key, Instant::now()).await {
+            WORKING_CNT.lock().await.lookup_suppressed_cnt += 1;
+            // DB 에러가 이어져 조회를 멈춘 경우
+            return Err(Box::new(
+                StrError::with_status(
+                    format!("SEED_ID_LOOKUP_SUPPRESSED: {}", seed_host),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )
+                .with_kind(ErrorKind::DbError),
+            ));
+        }
+        match op.await {
+            Ok(value) => {
+                self.failures.clear(&key).await;
+                Ok(value)
+            }
+            Err(e) => {
+                self.failures.record_failure(&key, Instant::now()).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<S: SeedIdStore + Sync> SeedIdStore for SuppressFailureStore<'_, S> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.suppress(seed_host, self.inner.select_seed_id(seed_host))
+            .await
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        self.suppress(seed_host, self.inner.insert_seed_id(seed_host, media_type))
+            .await
+    }
+
+    /// 기록 저장 실패는 seed_id 조회 실패로 보지 않음
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        self.inner.insert_audit(audit).await
+    }
+
+    fn allow_insert(&self) -> bool {
+        self.inner.allow_insert()
+    }
+}
+
+/// 새로 INSERT하는 seed_id 수를 guard로 제한하는 저장소
+pub struct InsertGuardStore<'a, S> {
+    inner: S,
+    guard: &'a InsertGuard,
+}
+
+impl<'a, S: SeedIdStore + Sync> InsertGuardStore<'a, S> {
+    pub fn new(inner: S, guard: &'a InsertGuard) -> Self {
+        Self { inner, guard }
+    }
+
+    #[cfg(test)]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: SeedIdStore + Sync> SeedIdStore for InsertGuardStore<'_, S> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.inner.select_seed_id(seed_host).await
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        let result = self.inner.insert_seed_id(seed_host, media_type).await;
+        // 새로 INSERT한 경우만 셈
+        if !matches!(result, Ok(true)) {
+            self.guard.cancel();
+        }
+        result
+    }
+
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        self.inner.insert_audit(audit).await
+    }
+
+    fn allow_insert(&self) -> bool {
+        self.inner.allow_insert() && self.guard.try_insert()
+    }
+}
+
+/// 조회만 하는 저장소. insert_seed_id를 호출해도 INSERT하지 않고 에러를 반환함
+/// <br>
+/// 디버그 endpoint처럼 seed_id를 새로 만들면 안 되는 조회에 사용함
+pub struct ReadOnlySeedIdStore<S> {
+    inner: S,
+}
+
+impl<S: SeedIdStore + Sync> ReadOnlySeedIdStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    #[cfg(test)]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: SeedRowStore + Sync> SeedRowStore for ReadOnlySeedIdStore<S> {
+    async fn select_row(&self, seed_host: &str) -> Result<Option<SeedRow>, BoxedError> {
+        self.inner.select_row(seed_host).await
+    }
+}
+
+impl<S: SeedIdStore + Sync> SeedIdStore for ReadOnlySeedIdStore<S> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        self.inner.select_seed_id(seed_host).await
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        _media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        Err(Box::new(StrError::new(format!(
+            "SEED_STORE_READ_ONLY: {}",
+            seed_host
+        ))))
+    }
+
+    async fn insert_audit(&self, _audit: &SeedAudit) -> Result<(), BoxedError> {
+        Ok(())
+    }
+
+    fn allow_insert(&self) -> bool {
+        false
+    }
+}
+
+/// DB보다 먼저 공유 cache에서 조회하고, DB에서 찾은 값은 공유 cache에도 저장하는 저장소
+/// <br>
+/// 공유 cache를 사용하지 않는 경우나 공유 cache 에러인 경우 DB만 사용함
+pub struct SharedCacheStore<'a, S, C> {
+    inner: S,
+    cache: Option<&'a C>,
+    /// 공유 cache key를 만드는 컬렉션 설정. None이면 seed_host를 그대로 key로 사용함
+    collection: Option<&'a CollectionSettings>,
+}
+
+impl<'a, S: SeedIdStore + Sync, C: SharedSeedCache + Sync> SharedCacheStore<'a, S, C> {
+    pub fn new(inner: S, cache: Option<&'a C>) -> Self {
+        Self {
+            inner,
+            cache,
+            collection: None,
+        }
+    }
+
+    /// 컬렉션마다 공유 cache key를 나눔
+    pub fn with_collection(mut self, collection: &'a CollectionSettings) -> Self {
+        self.collection = Some(collection);
+        self
+    }
+
+    fn key(&self, seed_host: &str) -> String {
+        match self.collection {
+            Some(collection) => collection.cache_key(seed_host),
+            None => seed_host.to_string(),
+        }
+    }
+}
+
+impl<S: SeedIdStore + Sync, C: SharedSeedCache + Sync> SeedIdStore for SharedCacheStore<'_, S, C> {
+    async fn select_seed_id(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        let Some(cache) = self.cache else {
+            return self.inner.select_seed_id(seed_host).await;
+        };
+
+        let key = self.key(seed_host);
+        match cache.get(&key).await {
+            Ok(Some(seed_id)) => {
+                WORKING_CNT.lock().await.shared_cache_hit_cnt += 1;
+                return Ok(Some(seed_id));
+            }
+            Ok(None) => WORKING_CNT.lock().await.shared_cache_miss_cnt += 1,
+            Err(e) => {
+                debug!("SHARED_CACHE_GET_FAIL seed_host: {}, err: {}", seed_host, e);
+                WORKING_CNT.lock().await.shared_cache_err_cnt += 1;
+            }
+        }
+
+        let seed_id = self.inner.select_seed_id(seed_host).await?;
+        if let Some(seed_id) = &seed_id {
+            if let Err(e) = cache.set(&key, seed_id).await {
+                debug!("SHARED_CACHE_SET_FAIL seed_host: {}, err: {}", seed_host, e);
+                WORKING_CNT.lock().await.shared_cache_err_cnt += 1;
+            }
+        }
+        Ok(seed_id)
+    }
+
+    async fn insert_seed_id(
+        &self,
+        seed_host: &str,
+        media_type: &MediaType,
+    ) -> Result<bool, BoxedError> {
+        self.inner.insert_seed_id(seed_host, media_type).await
+    }
+
+    async fn insert_audit(&self, audit: &SeedAudit) -> Result<(), BoxedError> {
+        self.inner.insert_audit(audit).await
+    }
+
+    fn allow_insert(&self) -> bool {
+        self.inner.allow_insert()
+    }
+}
+
+/// 다시 시도하면 성공할 수 있는 에러인지 확인
+/// <br>
+/// 연결 에러, pool timeout과 lock 대기 시간 초과(1205), deadlock(1213)은 다시 시도함.
+/// 같은 host를 동시에 INSERT IGNORE하는 경우 deadlock이 발생할 수 있음
+fn is_retryable(err: &BoxedError) -> bool {
+    let Some(err) = err.downcast_ref::<sqlx::Error>() else {
+        return false;
+    };
+    match err {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(db_err) => db_err
+            .try_downcast_ref::<MySqlDatabaseError>()
+            .is_some_and(|mysql_err| matches!(mysql_err.number(), 1205 | 1213)),
+        _ => false,
+    }
+}
+
+/// 0 이상 1 미만의 임의의 값
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[tokio::test]
+async fn retry_seed_id_store_test() {
+    use crate::mock::MockSeedIdStore;
+
+    let policy = RetryPolicy {
+        count: 3,
+        base: Duration::from_millis(10),
+        max: Duration::from_millis(30),
+    };
+    // 처음 fail_cnt번은 err로 실패하는 저장소
+    let flaky = |fail_cnt, err| {
+        RetrySeedIdStore::new(
+            MockSeedIdStore::fixed("seed").failing(fail_cnt, err),
+            policy,
+        )
+    };
+
+    // 3번 실패 후 성공. 기다리는 시간은 2배씩 늘어나고 max를 넘지 않음
+    let store = flaky(3, || sqlx::Error::PoolTimedOut);
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap(),
+        Some("seed".to_string())
+    );
+    let calls = store.inner.calls();
+    assert_eq!(calls.len(), 4);
+    for (attempt, pair) in calls.windows(2).enumerate() {
+        let min_delay = policy.delay(attempt as u32, 0f64);
+        assert!(pair[1] - pair[0] >= min_delay);
+    }
+
+    // 최대 횟수를 넘으면 마지막 에러 반환
+    let store = flaky(10, || {
+        sqlx::Error::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset))
+    });
+    assert!(store
+        .insert_seed_id("host", &MediaType::default())
+        .await
+        .is_err());
+    assert_eq!(store.inner.calls().len(), 4);
+
+    // 다시 시도해도 성공할 수 없는 에러는 곧바로 반환
+    let store = flaky(10, || sqlx::Error::RowNotFound);
+    assert!(store.select_seed_id("host").await.is_err());
+    assert_eq!(store.inner.calls().len(), 1);
+
+    assert_eq!(policy.delay(0, 0f64), Duration::from_millis(5));
+    assert_eq!(policy.delay(1, 0.5), Duration::from_millis(15));
+    assert_eq!(policy.delay(5, 0f64), Duration::from_millis(15));
+    assert!(policy.delay(5, 0.999_999) < policy.max);
+}
+
+#[tokio::test]
+async fn suppress_failure_store_test() {
+    use crate::mock::MockSeedIdStore;
+    use crate::util::error_status;
+    use std::sync::atomic::Ordering;
+
+    let failures = LookupFailures::new(Duration::from_millis(50));
+    let db = MockSeedIdStore::fixed("seed");
+    db.down.store(true, Ordering::Relaxed);
+    let store = SuppressFailureStore::new(db, &failures);
+    let calls = || store.inner.calls().len();
+
+    assert!(store.select_seed_id("host").await.is_err());
+    assert_eq!(calls(), 1);
+
+    // ttl 동안은 DB에 요청하지 않고 곧바로 실패함
+    let err = store.select_seed_id("host").await.unwrap_err();
+    assert!(err.to_string().starts_with("SEED_ID_LOOKUP_SUPPRESSED"));
+    assert_eq!(error_status(&err), Some(StatusCode::SERVICE_UNAVAILABLE));
+    assert!(store
+        .insert_seed_id("host", &MediaType::default())
+        .await
+        .is_err());
+    assert_eq!(calls(), 1);
+    // 다른 seed_host는 DB에 요청함
+    assert!(store.select_seed_id("other").await.is_err());
+    assert_eq!(calls(), 2);
+
+    // ttl이 지나면 다시 조회하고, 성공하면 기록을 지움
+    store.inner.down.store(false, Ordering::Relaxed);
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap(),
+        Some("seed".to_string())
+    );
+    assert_eq!(calls(), 3);
+    assert!(!failures.is_suppressed("host", Instant::now()).await);
+
+    // 컬렉션마다 기록을 나누므로 다른 컬렉션에서 실패한 seed_host도 DB에 요청함
+    let ja = CollectionSettings {
+        name: "ja".to_string(),
+        cache_namespace: "ja".to_string(),
+        ..CollectionSettings::default()
+    };
+    let ja_store =
+        SuppressFailureStore::new(MockSeedIdStore::fixed("seed"), &failures).with_collection(&ja);
+    ja_store.inner.down.store(true, Ordering::Relaxed);
+    assert!(ja_store.select_seed_id("host").await.is_err());
+    assert!(failures.is_suppressed("ja:host", Instant::now()).await);
+    assert!(!failures.is_suppressed("host", Instant::now()).await);
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap(),
+        Some("seed".to_string())
+    );
+    assert_eq!(calls(), 4);
+}
+
+#[tokio::test]
+async fn shared_cache_store_test() {
+    use crate::mock::{MockSeedIdStore, MockSharedCache};
+    use std::sync::atomic::Ordering;
+
+    // "host", "other"만 있는 DB
+    let db = || {
+        MockSeedIdStore::default()
+            .with_row("host", "db-host", MediaType::default())
+            .with_row("other", "db-other", MediaType::default())
+    };
+
+    let shared = MockSharedCache::default();
+    let store = SharedCacheStore::new(db(), Some(&shared));
+    let selects = || store.inner.selected().len();
+
+    // 공유 cache에 없으면 DB에서 조회하고 공유 cache에 저장함
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap().as_deref(),
+        Some("db-host")
+    );
+    assert_eq!(selects(), 1);
+    assert_eq!(shared.values.lock().unwrap()["host"], "db-host");
+    // 다른 proxy가 저장한 값은 DB에서 조회하지 않음
+    shared
+        .values
+        .lock()
+        .unwrap()
+        .insert("other".to_string(), "shared-other".to_string());
+    assert_eq!(
+        store.select_seed_id("other").await.unwrap().as_deref(),
+        Some("shared-other")
+    );
+    assert_eq!(selects(), 1);
+    // DB에 없는 값은 저장하지 않음
+    assert_eq!(store.select_seed_id("absent").await.unwrap(), None);
+    assert!(!shared.values.lock().unwrap().contains_key("absent"));
+
+    // 공유 cache 에러인 경우 DB만 사용함
+    shared.down.store(true, Ordering::Relaxed);
+    assert_eq!(
+        store.select_seed_id("other").await.unwrap().as_deref(),
+        Some("db-other")
+    );
+    assert_eq!(selects(), 3);
+
+    // 컬렉션마다 공유 cache key를 나누므로 다른 컬렉션이 저장한 값은 사용하지 않음
+    shared.down.store(false, Ordering::Relaxed);
+    let ja = CollectionSettings {
+        name: "ja".to_string(),
+        cache_namespace: "ja".to_string(),
+        ..CollectionSettings::default()
+    };
+    let store = SharedCacheStore::new(db(), Some(&shared)).with_collection(&ja);
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap().as_deref(),
+        Some("db-host")
+    );
+    assert_eq!(store.inner.selected().len(), 1);
+    assert_eq!(shared.values.lock().unwrap()["ja:host"], "db-host");
+
+    // 공유 cache를 사용하지 않는 경우
+    let store = SharedCacheStore::<_, MockSharedCache>::new(db(), None);
+    assert_eq!(
+        store.select_seed_id("host").await.unwrap().as_deref(),
+        Some("db-host")
+    );
+}
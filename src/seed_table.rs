@@ -0,0 +1,270 @@
+use crate::settings::{is_table_name, Settings};
+use crate::{BoxedError, CON};
+use log::{error, info, warn};
+use sqlx::mysql::MySqlDatabaseError;
+use std::future::Future;
+
+/// create_seed_table로 만드는 seed table의 DDL. {table}만 설정의 table 이름으로 바꿈
+/// <br>
+/// MySqlSeedIdStore가 사용하는 컬럼과 media_url의 unique index가 있어야 INSERT IGNORE가 중복된 seed_id를 만들지 않음
+pub const SEED_TABLE_DDL: &str = "CREATE TABLE IF NOT EXISTS {table} (
+  seed_id VARCHAR(36) NOT NULL,
+  site_name VARCHAR(255) NOT NULL DEFAULT '',
+  media_url VARCHAR(512) NOT NULL,
+  media_type_no INT NOT NULL DEFAULT 0,
+  PRIMARY KEY (seed_id),
+  UNIQUE KEY uk_media_url (media_url)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;";
+
+/// table이 없는 경우의 MySQL 에러 번호(ER_NO_SUCH_TABLE)와 SQLSTATE
+const NO_SUCH_TABLE: u16 = 1146;
+const NO_SUCH_TABLE_STATE: &str = "42S02";
+
+/// seed table을 확인할 때 sql을 실행하는 DB. 테스트에서는 mock을 사용함
+pub trait SeedTableDb {
+    fn execute(&self, sql: &str) -> impl Future<Output = Result<(), sqlx::Error>> + Send;
+}
+
+/// CON을 사용하는 DB
+pub struct MySqlSeedTableDb;
+
+impl SeedTableDb for MySqlSeedTableDb {
+    async fn execute(&self, sql: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(sql).execute(&*CON).await?;
+        Ok(())
+    }
+}
+
+/// seed table 확인 결과
+#[derive(Debug, PartialEq, Eq)]
+pub enum SeedTableStatus {
+    Exists,
+    /// 없어서 SEED_TABLE_DDL로 만든 경우
+    Created,
+    /// 없고 create_seed_table = false인 경우
+    Missing,
+}
+
+/// 확인할 seed table 목록. seed_table과 collections의 seed_table 중 중복을 제거함
+pub fn seed_tables(settings: &Settings) -> Vec<String> {
+    let mut tables = vec![settings.seed_table.clone()];
+    let mut names: Vec<&String> = settings.collections.keys().collect();
+    names.sort();
+    for name in names {
+        let table = &settings.collections[name].seed_table;
+        if !tables.contains(table) {
+            tables.push(table.clone());
+        }
+    }
+    tables
+}
+
+/// table이 없어서 난 에러인지 확인함
+pub fn is_table_missing(err: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_err) = err else {
+        return false;
+    };
+    if let Some(mysql_err) = db_err.try_downcast_ref::<MySqlDatabaseError>() {
+        return mysql_err.number() == NO_SUCH_TABLE;
+    }
+    db_err.code().as_deref() == Some(NO_SUCH_TABLE_STATE)
+}
+
+/// table에 MySqlSeedIdStore가 사용하는 컬럼이 있는지 확인하는 sql
+fn probe_sql(table: &str) -> String {
+    format!(
+        "SELECT seed_id, site_name, media_url, media_type_no FROM {} LIMIT 1;",
+        table
+    )
+}
+
+/// table 이름을 넣은 SEED_TABLE_DDL. 설정을 읽을 때 확인하지만 DDL이므로 한 번 더 확인함
+pub fn create_sql(table: &str) -> Result<String, BoxedError> {
+    if table.is_empty() || !is_table_name(table) {
+        return Err(format!("INVALID_SEED_TABLE: {}", table).into());
+    }
+    Ok(SEED_TABLE_DDL.replace("{table}", table))
+}
+
+/// table을 SELECT ... LIMIT 1로 확인하고, 없으면 create인 경우에만 만듦
+pub async fn check<D: SeedTableDb>(
+    db: &D,
+    table: &str,
+    create: bool,
+) -> Result<SeedTableStatus, BoxedError> {
+    match db.execute(&probe_sql(table)).await {
+        Ok(()) => Ok(SeedTableStatus::Exists),
+        Err(e) if is_table_missing(&e) => {
+            if !create {
+                return Ok(SeedTableStatus::Missing);
+            }
+            db.execute(&create_sql(table)?).await?;
+            db.execute(&probe_sql(table)).await?;
+            Ok(SeedTableStatus::Created)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 시작할 때 seed table을 모두 확인함. 없는 table이 하나라도 있으면 false
+/// <br>
+/// DB에 연결할 수 없는 등 table이 없는지 알 수 없는 에러는 경고만 남기고 건너뜀
+pub async fn verify<D: SeedTableDb>(db: &D, tables: &[String], create: bool) -> bool {
+    let mut ok = true;
+    for table in tables {
+        match check(db, table, create).await {
+            Ok(SeedTableStatus::Exists) => info!("seed table check: {} exists", table),
+            Ok(SeedTableStatus::Created) => warn!("SEED_TABLE_CREATED: {}", table),
+            Ok(SeedTableStatus::Missing) => {
+                error!(
+                    "SEED_TABLE_NOT_FOUND: {}. run the seed table migration in README or set create_seed_table = true",
+                    table
+                );
+                ok = false;
+            }
+            Err(e) => warn!("SEED_TABLE_CHECK_SKIPPED: {}, {}", table, e),
+        }
+    }
+    ok
+}
+
+/// 실행한 sql을 기록하고, tables에 없는 table을 SELECT하면 table이 없다는 에러를 주는 mock
+#[cfg(test)]
+struct MockSeedTableDb {
+    tables: std::sync::Mutex<Vec<String>>,
+    executed: std::sync::Mutex<Vec<String>>,
+}
+
+/// MySQL의 ER_NO_SUCH_TABLE과 같은 SQLSTATE를 주는 에러
+#[cfg(test)]
+#[derive(Debug)]
+struct NoSuchTable(String);
+
+#[cfg(test)]
+impl std::fmt::Display for NoSuchTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Table '{}' doesn't exist", self.0)
+    }
+}
+
+#[cfg(test)]
+impl std::error::Error for NoSuchTable {}
+
+#[cfg(test)]
+impl sqlx::error::DatabaseError for NoSuchTable {
+    fn message(&self) -> &str {
+        "Table doesn't exist"
+    }
+
+    fn code(&self) -> Option<std::borrow::Cow<'_, str>> {
+        Some(NO_SUCH_TABLE_STATE.into())
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn kind(&self) -> sqlx::error::ErrorKind {
+        sqlx::error::ErrorKind::Other
+    }
+}
+
+#[cfg(test)]
+impl SeedTableDb for MockSeedTableDb {
+    async fn execute(&self, sql: &str) -> Result<(), sqlx::Error> {
+        self.executed.lock().unwrap().push(sql.to_string());
+        let mut tables = self.tables.lock().unwrap();
+        if let Some(table) = sql.strip_prefix("CREATE TABLE IF NOT EXISTS ") {
+            tables.push(table.split_whitespace().next().unwrap().to_string());
+            return Ok(());
+        }
+        let table = sql
+            .split(" FROM ")
+            .nth(1)
+            .unwrap()
+            .split(' ')
+            .next()
+            .unwrap();
+        if table == "offline" {
+            return Err(sqlx::Error::PoolTimedOut);
+        }
+        match tables.iter().any(|name| name == table) {
+            true => Ok(()),
+            false => Err(sqlx::Error::Database(Box::new(NoSuchTable(
+                table.to_string(),
+            )))),
+        }
+    }
+}
+
+#[tokio::test]
+async fn seed_table_check_test() {
+    let db = MockSeedTableDb {
+        tables: std::sync::Mutex::new(vec!["crawlerdb.t_channel_contents_map".to_string()]),
+        executed: std::sync::Mutex::new(Vec::new()),
+    };
+    let executed = || std::mem::take(&mut *db.executed.lock().unwrap());
+
+    // 있는 table은 SELECT ... LIMIT 1만 실행함
+    assert_eq!(
+        check(&db, "crawlerdb.t_channel_contents_map", false)
+            .await
+            .unwrap(),
+        SeedTableStatus::Exists
+    );
+    assert_eq!(
+        executed(),
+        ["SELECT seed_id, site_name, media_url, media_type_no FROM crawlerdb.t_channel_contents_map LIMIT 1;"]
+    );
+
+    // table이 없다는 에러만 Missing으로 봄. create가 아니면 만들지 않음
+    assert!(is_table_missing(&sqlx::Error::Database(Box::new(
+        NoSuchTable("x".to_string())
+    ))));
+    assert!(!is_table_missing(&sqlx::Error::PoolTimedOut));
+    assert_eq!(
+        check(&db, "crawlerdb.map_ja", false).await.unwrap(),
+        SeedTableStatus::Missing
+    );
+    assert_eq!(executed().len(), 1);
+
+    // create인 경우 DDL을 실행하고 다시 확인함
+    assert_eq!(
+        check(&db, "crawlerdb.map_ja", true).await.unwrap(),
+        SeedTableStatus::Created
+    );
+    let sqls = executed();
+    assert_eq!(sqls.len(), 3);
+    assert!(sqls[1].starts_with("CREATE TABLE IF NOT EXISTS crawlerdb.map_ja (\n"));
+    assert!(sqls[1].contains("UNIQUE KEY uk_media_url (media_url)"));
+    assert_eq!(
+        check(&db, "crawlerdb.map_ja", true).await.unwrap(),
+        SeedTableStatus::Exists
+    );
+
+    // DDL에는 확인한 table 이름만 넣음
+    assert!(create_sql("t; DROP TABLE x").is_err());
+    assert!(create_sql("").is_err());
+
+    // 연결 에러는 table이 없는지 알 수 없으므로 에러로 반환하고, verify는 건너뜀
+    assert!(check(&db, "offline", true).await.is_err());
+    let tables = |names: &[&str]| -> Vec<String> { names.iter().map(|s| s.to_string()).collect() };
+    assert!(verify(&db, &tables(&["crawlerdb.map_ja", "offline"]), false).await);
+    assert!(
+        !verify(
+            &db,
+            &tables(&["crawlerdb.map_ja", "crawlerdb.map_en"]),
+            false
+        )
+        .await
+    );
+    assert!(verify(&db, &tables(&["crawlerdb.map_en"]), true).await);
+}
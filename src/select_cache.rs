@@ -0,0 +1,395 @@
+use crate::BoxedError;
+use futures_util::stream::{self, StreamExt};
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{
+    HeaderName, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+    PROXY_AUTHORIZATION,
+};
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode};
+use lru::LruCache;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// select 응답을 cache에서 돌려줬는지 알려주는 header. HIT 또는 MISS
+pub const X_PROXY_CACHE: HeaderName = HeaderName::from_static("x-proxy-cache");
+
+/// cache에 함께 저장하는 응답 header
+const CACHED_HEADERS: [HeaderName; 1] = [CONTENT_TYPE];
+
+/// 요청한 사용자를 알 수 있는 header. 사용자마다 응답이 다를 수 있으므로 이 header가 있는 요청은 cache를 사용하지 않음
+const CREDENTIAL_HEADERS: [HeaderName; 3] = [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE];
+
+/// select 응답 cache 설정
+#[derive(Debug, Clone)]
+pub struct SelectCacheConfig {
+    /// cache한 응답을 사용할 시간. 0이면 cache를 사용하지 않음
+    pub ttl: Duration,
+    /// cache에 저장하는 응답 전체의 최대 크기(bytes)
+    pub max_bytes: usize,
+    /// 이 크기를 넘는 응답은 cache에 저장하지 않음(bytes)
+    pub max_entry_bytes: usize,
+    /// cache key를 만들 때 제외하는 파라미터. 클라이언트가 cache를 피하려고 붙이는 값
+    pub bust_param: String,
+    /// true인 경우 update 요청을 받으면 cache를 비움
+    pub flush_on_update: bool,
+}
+
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    expires: Instant,
+    /// total_bytes에 더한 크기
+    size: usize,
+}
+
+struct Entries {
+    lru: LruCache<String, CachedResponse>,
+    total_bytes: usize,
+}
+
+/// 같은 select 요청에 솔라 대신 응답하는 cache
+/// <br>
+/// GET 요청의 path와 정렬한 query string을 key로 사용하며, 200 응답만 저장함.
+/// 전체 크기가 max_bytes를 넘으면 오래 사용하지 않은 응답부터 지움
+pub struct SelectCache {
+    config: SelectCacheConfig,
+    entries: Mutex<Entries>,
+    /// cache를 비울 때마다 1씩 늘어남. 솔라에 요청하는 동안 cache를 비운 경우 받은 응답을 저장하지 않기 위해 사용함
+    generation: AtomicU64,
+}
+
+impl SelectCache {
+    pub fn new(config: SelectCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(Entries {
+                lru: LruCache::unbounded(),
+                total_bytes: 0,
+            }),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// req의 cache key. cache를 사용하지 않거나 GET 요청이 아닌 경우, 인증 정보가 있는 경우 None
+    pub fn key(&self, req: &Request<Body>) -> Option<String> {
+        if self.config.ttl.is_zero() || req.method() != Method::GET {
+            return None;
+        }
+        if CREDENTIAL_HEADERS
+            .iter()
+            .any(|name| req.headers().contains_key(name))
+        {
+            return None;
+        }
+
+        let mut params: Vec<&str> = req
+            .uri()
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| {
+                let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+                !key.is_empty() && key != self.config.bust_param
+            })
+            .collect();
+        params.sort_unstable();
+        Some(format!("{}?{}", req.uri().path(), params.join("&")))
+    }
+
+    /// cache한 응답. 없거나 ttl이 지난 경우 None
+    pub async fn get(&self, key: &str, now: Instant) -> Option<Response<Body>> {
+        let mut entries = self.entries.lock().await;
+        let cached = entries.lru.get(key)?;
+        if cached.expires <= now {
+            if let Some(expired) = entries.lru.pop(key) {
+                entries.total_bytes -= expired.size;
+            }
+            return None;
+        }
+
+        let mut response = Response::new(Body::from(cached.body.clone()));
+        *response.status_mut() = cached.status;
+        *response.headers_mut() = cached.headers.clone();
+        // 응답 body 크기를 세는 동안 크기를 알 수 없으므로 Content-Length를 직접 넣음
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_LENGTH, cached.body.len().into());
+        headers.insert(X_PROXY_CACHE, HeaderValue::from_static("HIT"));
+        Some(response)
+    }
+
+    /// 지금의 generation. 솔라에 요청하기 전에 읽어서 store에 넘김
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// 솔라 응답을 cache에 저장하고 클라이언트에 돌려줄 응답을 반환함
+    /// <br>
+    /// 200이 아니거나 압축된 응답, max_entry_bytes를 넘는 응답은 저장하지 않고 그대로 돌려줌.
+    /// 요청 전에 읽은 generation이 지금과 다른 경우 update 전의 응답일 수 있으므로 저장하지 않음
+    pub async fn store(
+        &self,
+        key: String,
+        generation: u64,
+        response: Response<Body>,
+        now: Instant,
+    ) -> Result<Response<Body>, BoxedError> {
+        if response.status() != StatusCode::OK || response.headers().contains_key(CONTENT_ENCODING)
+        {
+            return Ok(response);
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let body = match read_limited(body, self.config.max_entry_bytes).await? {
+            Ok(bytes) => bytes,
+            Err(body) => return Ok(Response::from_parts(parts, body)),
+        };
+
+        let mut headers = HeaderMap::new();
+        for name in CACHED_HEADERS {
+            if let Some(value) = parts.headers.get(&name) {
+                headers.insert(name, value.clone());
+            }
+        }
+        let size = key.len()
+            + body.len()
+            + headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>();
+        let mut entries = self.entries.lock().await;
+        // on_update는 entries를 잠근 상태로 generation을 바꾸므로 잠근 뒤에 확인함
+        if size <= self.config.max_bytes && generation == self.generation() {
+            let cached = CachedResponse {
+                status: parts.status,
+                headers,
+                body: body.clone(),
+                expires: now + self.config.ttl,
+                size,
+            };
+            entries.total_bytes += size;
+            if let Some(replaced) = entries.lru.put(key, cached) {
+                entries.total_bytes -= replaced.size;
+            }
+            while entries.total_bytes > self.config.max_bytes {
+                let Some((_, evicted)) = entries.lru.pop_lru() else {
+                    break;
+                };
+                entries.total_bytes -= evicted.size;
+            }
+        }
+        drop(entries);
+
+        parts.headers.insert(CONTENT_LENGTH, body.len().into());
+        parts
+            .headers
+            .insert(X_PROXY_CACHE, HeaderValue::from_static("MISS"));
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+
+    /// update 요청을 받은 경우 호출함. flush_on_update인 경우 cache를 비우고 generation을 늘림
+    pub async fn on_update(&self) {
+        if self.config.flush_on_update && !self.config.ttl.is_zero() {
+            let mut entries = self.entries.lock().await;
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            entries.lru.clear();
+            entries.total_bytes = 0;
+        }
+    }
+}
+
+/// body를 max_bytes까지 읽음
+/// <br>
+/// max_bytes를 넘는 경우 더 읽지 않고, 읽은 부분과 나머지를 이어붙인 body를 Err로 반환함
+async fn read_limited(mut body: Body, max_bytes: usize) -> Result<Result<Bytes, Body>, BoxedError> {
+    if body.size_hint().lower() > max_bytes as u64 {
+        return Ok(Err(body));
+    }
+
+    let mut chunks = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > max_bytes {
+            let read = stream::iter(chunks.into_iter().map(Ok::<_, hyper::Error>));
+            return Ok(Err(Body::wrap_stream(read.chain(body))));
+        }
+    }
+    Ok(Ok(chunks.concat().into()))
+}
+
+#[cfg(test)]
+fn test_cache(max_bytes: usize, max_entry_bytes: usize) -> SelectCache {
+    SelectCache::new(SelectCacheConfig {
+        ttl: Duration::from_secs(10),
+        max_bytes,
+        max_entry_bytes,
+        bust_param: "_".to_string(),
+        flush_on_update: true,
+    })
+}
+
+#[tokio::test]
+async fn select_cache_test() {
+    let cache = test_cache(1024, 256);
+    let now = Instant::now();
+    let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+    let solr_response = |status: StatusCode, body: &'static str| {
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .header("x-solr-node", "solr1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // 파라미터 순서와 cache-buster는 key에 영향을 주지 않음
+    let key = cache
+        .key(&get("/solr/core/select?q=a&rows=10&_=1700000000"))
+        .unwrap();
+    assert_eq!(key, "/solr/core/select?q=a&rows=10");
+    assert_eq!(
+        cache.key(&get("/solr/core/select?rows=10&q=a")).unwrap(),
+        key
+    );
+    let post = Request::post("/solr/core/select?q=a")
+        .body(Body::empty())
+        .unwrap();
+    assert!(cache.key(&post).is_none());
+
+    // 인증 정보가 있는 요청은 사용자마다 응답이 다를 수 있으므로 cache를 사용하지 않음
+    for name in CREDENTIAL_HEADERS {
+        let req = Request::get("/solr/core/select?q=a&rows=10")
+            .header(name, "Basic dXNlcjpwdw==")
+            .body(Body::empty())
+            .unwrap();
+        assert!(cache.key(&req).is_none());
+    }
+
+    assert!(cache.get(&key, now).await.is_none());
+    let response = cache
+        .store(key.clone(), 0, solr_response(StatusCode::OK, "{}"), now)
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[X_PROXY_CACHE], "MISS");
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "{}");
+
+    let response = cache.get(&key, now + Duration::from_secs(9)).await.unwrap();
+    assert_eq!(response.headers()[X_PROXY_CACHE], "HIT");
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    assert!(response.headers().get("x-solr-node").is_none());
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "{}");
+
+    // ttl이 지나면 다시 솔라에 요청해야 함
+    assert!(cache
+        .get(&key, now + Duration::from_secs(10))
+        .await
+        .is_none());
+    assert!(cache.get(&key, now).await.is_none());
+
+    // 200이 아니거나 max_entry_bytes를 넘는 응답은 저장하지 않지만 body는 그대로 돌려줌
+    let response = cache
+        .store(
+            key.clone(),
+            0,
+            solr_response(StatusCode::BAD_REQUEST, "bad"),
+            now,
+        )
+        .await
+        .unwrap();
+    assert!(response.headers().get(X_PROXY_CACHE).is_none());
+    assert!(cache.get(&key, now).await.is_none());
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for _ in 0..4 {
+            sender
+                .send_data(Bytes::from(vec![b'a'; 100]))
+                .await
+                .unwrap();
+        }
+    });
+    let response = cache
+        .store(key.clone(), 0, Response::new(body), now)
+        .await
+        .unwrap();
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap().len(), 400);
+    assert!(cache.get(&key, now).await.is_none());
+
+    // 전체 크기가 max_bytes를 넘으면 오래 사용하지 않은 응답부터 지움
+    let body = "a".repeat(200);
+    for q in 0..4 {
+        let key = format!("/solr/core/select?q={}", q);
+        let response = Response::new(Body::from(body.clone()));
+        cache.store(key, 0, response, now).await.unwrap();
+    }
+    assert!(cache.get("/solr/core/select?q=0", now).await.is_some());
+    let response = Response::new(Body::from(body.clone()));
+    cache
+        .store("/solr/core/select?q=4".to_string(), 0, response, now)
+        .await
+        .unwrap();
+    assert!(cache.get("/solr/core/select?q=0", now).await.is_some());
+    assert!(cache.get("/solr/core/select?q=1", now).await.is_none());
+    assert!(cache.entries.lock().await.total_bytes <= 1024);
+}
+
+#[tokio::test]
+async fn select_cache_flush_test() {
+    let cache = test_cache(1024, 256);
+    let now = Instant::now();
+    let key = "/solr/core/select?q=a".to_string();
+    let response = Response::new(Body::from("{}"));
+    cache.store(key.clone(), 0, response, now).await.unwrap();
+    assert!(cache.get(&key, now).await.is_some());
+
+    // update 요청을 받으면 색인 내용이 바뀌었으므로 cache를 비움
+    cache.on_update().await;
+    assert!(cache.get(&key, now).await.is_none());
+    assert_eq!(cache.entries.lock().await.total_bytes, 0);
+
+    // 솔라에 요청하는 동안 cache를 비운 경우 update 전의 응답일 수 있으므로 저장하지 않음
+    let generation = cache.generation();
+    cache.on_update().await;
+    assert_eq!(cache.generation(), generation + 1);
+    let response = cache
+        .store(
+            key.clone(),
+            generation,
+            Response::new(Body::from("old")),
+            now,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[X_PROXY_CACHE], "MISS");
+    assert_eq!(hyper::body::to_bytes(response).await.unwrap(), "old");
+    assert!(cache.get(&key, now).await.is_none());
+    let response = Response::new(Body::from("new"));
+    cache
+        .store(key.clone(), cache.generation(), response, now)
+        .await
+        .unwrap();
+    assert!(cache.get(&key, now).await.is_some());
+
+    let cache = SelectCache::new(SelectCacheConfig {
+        flush_on_update: false,
+        ..test_cache(1024, 256).config
+    });
+    let response = Response::new(Body::from("{}"));
+    cache.store(key.clone(), 0, response, now).await.unwrap();
+    cache.on_update().await;
+    assert!(cache.get(&key, now).await.is_some());
+
+    // ttl이 0이면 cache를 사용하지 않음
+    let cache = SelectCache::new(SelectCacheConfig {
+        ttl: Duration::ZERO,
+        ..test_cache(1024, 256).config
+    });
+    let req = Request::get("/solr/core/select?q=a")
+        .body(Body::empty())
+        .unwrap();
+    assert!(cache.key(&req).is_none());
+}
@@ -0,0 +1,283 @@
+use crate::query::{self, decode};
+use crate::{stream_body, util, BoxedError};
+use hyper::body::Bytes;
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::http::uri::Uri;
+use hyper::{Body, Method, Request};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::borrow::Cow;
+
+/// select_param_caps로 값을 줄인 파라미터. 예: rows=5000->1000
+pub const X_PROXY_PARAM_CLAMPED: HeaderName = HeaderName::from_static("x-proxy-param-clamped");
+
+/// 기본 파라미터 값을 query string에 넣을 때 encoding하지 않는 문자
+const PARAM_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b':');
+
+/// 최대값을 넘어 줄인 파라미터
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clamped {
+    pub name: String,
+    pub requested: u64,
+    pub cap: u64,
+}
+
+/// select 요청의 query string에 없는 defaults 파라미터를 추가하고, caps를 넘는 값은 최대값으로 줄임
+/// <br>
+/// 바꾸지 않은 파라미터는 받은 encoding 그대로 유지함. 숫자가 아닌 값은 줄이지 않고 솔라에 그대로 보냄
+pub fn apply(
+    uri: &mut Uri,
+    defaults: &[(String, String)],
+    caps: &[(String, u64)],
+) -> Result<Vec<Clamped>, BoxedError> {
+    apply_params(uri, None, defaults, caps)
+}
+
+/// form으로 보낸 select 요청은 body의 파라미터에도 caps를 적용함
+/// <br>
+/// 솔라는 query string과 form의 파라미터를 합쳐서 사용하므로 defaults는 둘 다에 없는 경우에만 query string에 추가함
+pub fn apply_form(
+    uri: &mut Uri,
+    form: &mut Bytes,
+    defaults: &[(String, String)],
+    caps: &[(String, u64)],
+) -> Result<Vec<Clamped>, BoxedError> {
+    apply_params(uri, Some(form), defaults, caps)
+}
+
+/// POST form인 경우 body를 max_bytes까지 읽어 apply_form을 적용하고, 아니면 query string에만 apply를 적용함
+/// <br>
+/// body를 바꾼 경우 길이 헤더는 hyper가 새 body로 설정하도록 제거함
+pub async fn apply_request(
+    req: &mut Request<Body>,
+    defaults: &[(String, String)],
+    caps: &[(String, u64)],
+    max_bytes: usize,
+) -> Result<Vec<Clamped>, BoxedError> {
+    let is_form = req.method() == Method::POST && stream_body::is_form(req.headers());
+    if !is_form || (defaults.is_empty() && caps.is_empty()) {
+        return apply(req.uri_mut(), defaults, caps);
+    }
+
+    let form = util::to_bytes_limited(req.body_mut(), max_bytes).await?;
+    let mut changed = form.clone();
+    let clamped = apply_form(req.uri_mut(), &mut changed, defaults, caps)?;
+    if changed != form {
+        util::remove_resent_body_headers(req.headers_mut());
+    }
+    *req.body_mut() = Body::from(changed);
+    Ok(clamped)
+}
+
+fn apply_params(
+    uri: &mut Uri,
+    form: Option<&mut Bytes>,
+    defaults: &[(String, String)],
+    caps: &[(String, u64)],
+) -> Result<Vec<Clamped>, BoxedError> {
+    let mut clamped = Vec::new();
+    if defaults.is_empty() && caps.is_empty() {
+        return Ok(clamped);
+    }
+
+    let mut present = Vec::new();
+    let query = uri.query().unwrap_or_default().to_string();
+    let mut pairs = clamp_pairs(&query, caps, &mut clamped, &mut present);
+    let query_clamped = !clamped.is_empty();
+    if let Some(form) = form {
+        let form_str = std::str::from_utf8(form).map_err(|_| stream_body::invalid_form())?;
+        let form_clamped = clamped.len();
+        let form_pairs = clamp_pairs(form_str, caps, &mut clamped, &mut present);
+        if clamped.len() > form_clamped {
+            *form = Bytes::from(form_pairs.join("&"));
+        }
+    }
+
+    let mut added = false;
+    for (name, value) in defaults {
+        if !present.iter().any(|present| present == name) {
+            pairs.push(Cow::Owned(format!(
+                "{}={}",
+                utf8_percent_encode(name, PARAM_VALUE),
+                utf8_percent_encode(value, PARAM_VALUE)
+            )));
+            added = true;
+        }
+    }
+    if !added && !query_clamped {
+        return Ok(clamped);
+    }
+
+    query::set_query(uri, &pairs.join("&"))?;
+    Ok(clamped)
+}
+
+/// params의 pair 중 caps를 넘는 값만 최대값으로 바꾸고, 받은 파라미터 이름을 present에 넣음
+fn clamp_pairs<'a>(
+    params: &'a str,
+    caps: &[(String, u64)],
+    clamped: &mut Vec<Clamped>,
+    present: &mut Vec<String>,
+) -> Vec<Cow<'a, str>> {
+    let mut pairs = Vec::new();
+    for pair in params.split('&') {
+        if pair.is_empty() {
+            continue;
+        }
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let name = decode(key);
+        let cap = caps.iter().find(|(cap_name, _)| *cap_name == name);
+        match cap.and_then(|(_, cap)| Some((decode(value).trim().parse::<u64>().ok()?, *cap))) {
+            Some((requested, cap)) if requested > cap => {
+                pairs.push(Cow::Owned(format!("{}={}", key, cap)));
+                clamped.push(Clamped {
+                    name: name.to_string(),
+                    requested,
+                    cap,
+                });
+            }
+            _ => pairs.push(Cow::Borrowed(pair)),
+        }
+        present.push(name.into_owned());
+    }
+    pairs
+}
+
+/// X-Proxy-Param-Clamped 값. 여러개인 경우 ", "로 구분하며 줄인 값이 없으면 None
+pub fn clamped_header(clamped: &[Clamped]) -> Option<HeaderValue> {
+    if clamped.is_empty() {
+        return None;
+    }
+    let value = clamped
+        .iter()
+        .map(|clamped| format!("{}={}->{}", clamped.name, clamped.requested, clamped.cap))
+        .collect::<Vec<_>>()
+        .join(", ");
+    HeaderValue::from_str(&value).ok()
+}
+
+#[test]
+fn select_param_apply_test() {
+    let defaults = vec![
+        ("timeAllowed".to_string(), "5000".to_string()),
+        ("omitHeader".to_string(), "false".to_string()),
+        ("fl".to_string(), "id score".to_string()),
+    ];
+    let caps = vec![("rows".to_string(), 1000)];
+
+    // 없는 파라미터만 추가하고 받은 파라미터의 encoding은 유지함
+    let mut uri: Uri = "/solr/core/select?q=title%3A%22a+b%22&timeAllowed=100&rows=10"
+        .parse()
+        .unwrap();
+    let clamped = apply(&mut uri, &defaults, &caps).unwrap();
+    assert!(clamped.is_empty());
+    assert_eq!(
+        uri,
+        "/solr/core/select?q=title%3A%22a+b%22&timeAllowed=100&rows=10&omitHeader=false&fl=id%20score"
+    );
+
+    // encoding된 key도 같은 파라미터로 봄
+    let mut uri: Uri = "/solr/core/select?q=*:*&omit%48eader=true&%72ows=5000&rows=20"
+        .parse()
+        .unwrap();
+    let clamped = apply(&mut uri, &defaults[..2], &caps).unwrap();
+    assert_eq!(
+        clamped,
+        [Clamped {
+            name: "rows".to_string(),
+            requested: 5000,
+            cap: 1000,
+        }]
+    );
+    assert_eq!(
+        uri,
+        "/solr/core/select?q=*:*&omit%48eader=true&%72ows=1000&rows=20&timeAllowed=5000"
+    );
+    assert_eq!(
+        clamped_header(&clamped).unwrap(),
+        HeaderValue::from_static("rows=5000->1000")
+    );
+    assert_eq!(clamped_header(&[]), None);
+
+    // query string이 없는 경우 기본 파라미터만 넣음
+    let mut uri: Uri = "http://solr:8983/solr/core/select".parse().unwrap();
+    apply(&mut uri, &defaults[..1], &caps).unwrap();
+    assert_eq!(uri, "http://solr:8983/solr/core/select?timeAllowed=5000");
+
+    // 바꿀 값이 없거나 숫자가 아닌 값은 uri를 그대로 유지함
+    for query in [
+        "/select?rows=abc&timeAllowed=1",
+        "/select?rows=1000&timeAllowed=1",
+        "/select",
+    ] {
+        let mut uri: Uri = query.parse().unwrap();
+        let defaults = match query {
+            "/select" => &[][..],
+            _ => &defaults[..1],
+        };
+        assert!(apply(&mut uri, defaults, &caps).unwrap().is_empty());
+        assert_eq!(uri, query);
+    }
+}
+
+#[tokio::test]
+async fn select_param_form_test() {
+    use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+
+    let defaults = vec![
+        ("timeAllowed".to_string(), "5000".to_string()),
+        ("rows".to_string(), "10".to_string()),
+    ];
+    let caps = vec![("rows".to_string(), 1000)];
+    let form_request = |query: &str, form: &'static str| {
+        Request::post(query)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(CONTENT_LENGTH, form.len())
+            .body(Body::from(form))
+            .unwrap()
+    };
+
+    // form의 값도 줄이고, form에 있는 파라미터는 query string에 기본값을 추가하지 않음
+    let mut req = form_request("/solr/core/select?wt=json", "q=*:*&%72ows=5000");
+    let clamped = apply_request(&mut req, &defaults, &caps, 0).await.unwrap();
+    assert_eq!(clamped_header(&clamped).unwrap(), "rows=5000->1000");
+    assert_eq!(req.uri(), "/solr/core/select?wt=json&timeAllowed=5000");
+    assert!(req.headers().get(CONTENT_LENGTH).is_none());
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"q=*:*&%72ows=1000");
+
+    // 바꾸지 않은 form은 받은 그대로 보내고 길이 헤더도 유지함
+    let mut req = form_request("/solr/core/select", "q=*:*&rows=20&timeAllowed=1");
+    assert!(apply_request(&mut req, &defaults, &caps, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(req.uri(), "/solr/core/select");
+    assert_eq!(req.headers()[CONTENT_LENGTH], "27");
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"q=*:*&rows=20&timeAllowed=1");
+
+    // form이 아닌 POST body는 읽지 않음
+    let mut req = Request::post("/solr/core/select?rows=5000")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"query\":\"*:*\",\"limit\":5000}"))
+        .unwrap();
+    let clamped = apply_request(&mut req, &defaults[..1], &caps, 0)
+        .await
+        .unwrap();
+    assert_eq!(clamped.len(), 1);
+    assert_eq!(req.uri(), "/solr/core/select?rows=1000&timeAllowed=5000");
+
+    // 큰 form과 utf-8이 아닌 form은 에러
+    let mut req = form_request("/solr/core/select", "q=*:*&rows=5000");
+    assert!(apply_request(&mut req, &defaults, &caps, 4).await.is_err());
+    let mut uri: Uri = "/solr/core/select".parse().unwrap();
+    let mut form = Bytes::from_static(b"q=\xff");
+    let err = apply_form(&mut uri, &mut form, &defaults, &caps).unwrap_err();
+    assert!(err.to_string().starts_with("INVALID_FORM_BODY"));
+}
@@ -1,52 +1,105 @@
 use log::{error, info, LevelFilter};
 use log4rs::append::console::ConsoleAppender;
-use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
-use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
-use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
 use log4rs::append::rolling_file::RollingFileAppender;
-use log4rs::config::{Appender, Root};
+use log4rs::config::{Appender, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::{Config, Handle};
+use once_cell::sync::OnceCell;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
-use crate::STOP_SERVER_SENDER;
+use crate::access_log::ACCESS_TARGET;
+use crate::log_roll::LogRollConfig;
+use crate::panic_policy::{in_request_scope, panic_message};
+use crate::request_server_shutdown;
+use crate::shutdown::ShutdownReason;
 
-pub fn setup_logger() -> Result<Handle, Box<dyn Error + Send + Sync>> {
+/// setup_logger에서 초기화한 logger. 실행 중 log level을 바꿀 때 사용함
+static LOG_HANDLE: OnceCell<Handle> = OnceCell::new();
+/// 현재 root log level. access log는 root와 별도의 level을 사용하므로 log::max_level()과 다를 수 있음
+static ROOT_LEVEL: Mutex<LevelFilter> = Mutex::new(LevelFilter::Info);
+/// log 파일을 나누는 설정. setup_logger에서 지정함
+static LOG_ROLL: Mutex<Option<LogRollConfig>> = Mutex::new(None);
+/// true인 경우 access log를 별도 파일에 남김
+static ACCESS_LOG: AtomicBool = AtomicBool::new(false);
+
+fn log_config(level: LevelFilter) -> Result<Config, Box<dyn Error + Send + Sync>> {
     let stdout = ConsoleAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             "[{d(%Y-%m-%d %H:%M:%S)}] [{l}] {m}{n}",
         )))
         .build();
-    let fixed_window_roller = FixedWindowRoller::builder().build("log/solr_proxy.log.{}", 5)?;
-
-    let size_trigger = SizeTrigger::new(500_0000); // 대략 5MB
-    let compound_policy =
-        CompoundPolicy::new(Box::new(size_trigger), Box::new(fixed_window_roller));
+    let roll_config = LOG_ROLL.lock().unwrap().unwrap_or_default();
     let file_appender = RollingFileAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             "[{d(%Y-%m-%d %H:%M:%S)}] [{l}] {m}{n}",
         )))
-        .build("log/solr_proxy.log", Box::new(compound_policy))?;
-
-    let config = Config::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .appender(Appender::builder().build("file_appender", Box::new(file_appender)))
         .build(
-            Root::builder()
-                .appenders(["stdout", "file_appender"])
-                .build(LevelFilter::Info),
+            "log/solr_proxy.log",
+            Box::new(roll_config.policy("log/solr_proxy.log")?),
         )?;
 
-    let handle = log4rs::init_config(config)?;
+    let mut builder = Config::builder()
+        .appender(Appender::builder().build("stdout", Box::new(stdout)))
+        .appender(Appender::builder().build("file_appender", Box::new(file_appender)));
+
+    // access log는 application log에 섞이지 않도록 additive를 사용하지 않음
+    let access_logger = if ACCESS_LOG.load(Ordering::Relaxed) {
+        let access_appender = RollingFileAppender::builder()
+            .encoder(Box::new(PatternEncoder::new(
+                "{d(%Y-%m-%d %H:%M:%S%.3f)}\t{m}{n}",
+            )))
+            .build(
+                "log/access.log",
+                Box::new(roll_config.policy("log/access.log")?),
+            )?;
+        builder = builder
+            .appender(Appender::builder().build("access_appender", Box::new(access_appender)));
+        Logger::builder()
+            .appender("access_appender")
+            .additive(false)
+            .build(ACCESS_TARGET, LevelFilter::Info)
+    } else {
+        Logger::builder()
+            .additive(false)
+            .build(ACCESS_TARGET, LevelFilter::Off)
+    };
+
+    let config = builder.logger(access_logger).build(
+        Root::builder()
+            .appenders(["stdout", "file_appender"])
+            .build(level),
+    )?;
+    Ok(config)
+}
+
+/// 현재 root log level
+pub fn log_level() -> LevelFilter {
+    *ROOT_LEVEL.lock().unwrap()
+}
+
+/// root log level을 바꿈. setup_logger를 실행하지 않은 경우 에러
+pub fn set_log_level(level: LevelFilter) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let handle = LOG_HANDLE.get().ok_or("LOGGER_NOT_INITIALIZED")?;
+    handle.set_config(log_config(level)?);
+    *ROOT_LEVEL.lock().unwrap() = level;
+    Ok(())
+}
+
+/// access log를 별도 파일에 남기기 시작함
+pub fn enable_access_log() -> Result<(), Box<dyn Error + Send + Sync>> {
+    ACCESS_LOG.store(true, Ordering::Relaxed);
+    set_log_level(log_level())
+}
+
+pub fn setup_logger(roll_config: LogRollConfig) -> Result<Handle, Box<dyn Error + Send + Sync>> {
+    *LOG_ROLL.lock().unwrap() = Some(roll_config);
+    let handle = log4rs::init_config(log_config(log_level())?)?;
+    let _ = LOG_HANDLE.set(handle.clone());
 
     std::panic::set_hook(Box::new(|panic_info| {
-        if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
-            error!("panic occurred: {s:?}");
-        } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
-            error!("panic occurred: {s:?}");
-        } else {
-            error!("panic occurred");
-        }
+        error!("panic occurred: {:?}", panic_message(panic_info.payload()));
 
         if let Some(location) = panic_info.location() {
             error!(
@@ -58,15 +111,15 @@ pub fn setup_logger() -> Result<Handle, Box<dyn Error + Send + Sync>> {
 
         error!("panic debug info: {:?}", panic_info);
 
-        // panic이 발생한 경우 서버 종료를 요청함
-        tokio::spawn(async move {
-            let mut server_sender_lock = STOP_SERVER_SENDER.lock().await;
-            let sender_option = (*server_sender_lock).take();
+        // 요청 안에서 발생한 panic은 해당 요청만 500으로 응답하므로 서버를 종료하지 않음
+        if in_request_scope() {
+            return;
+        }
 
-            if let Some(sender) = sender_option {
-                if sender.send(()).is_ok() {
-                    info!("server shutdown starting...");
-                }
+        // panic이 발생한 경우 서버 종료를 요청함. 종료 요청을 보내기 전에 버려지지 않도록 spawn_tracked로 실행함
+        crate::background::spawn_tracked(async move {
+            if request_server_shutdown(ShutdownReason::Panic).await {
+                info!("server shutdown starting...");
             }
         });
     }));
@@ -0,0 +1,1726 @@
+use crate::commit_param::CommitPolicy;
+use crate::compress::UpstreamCompression;
+use crate::content_type;
+use crate::cors::CorsConfig;
+use crate::host_rule::HostRule;
+use crate::ip_allow::IpAllowList;
+use crate::listen::{ListenAddr, DEFAULT_LISTEN};
+use crate::log_roll::{LogRoll, LogRollConfig};
+use crate::media_type::MediaTypeRule;
+use crate::method_rule;
+use crate::panic_policy::PanicPolicy;
+use crate::proc_xml::{ReadLimit, RequiredFieldsAction, SplitLimit};
+use crate::route;
+use crate::schema_check::SchemaCheckAction;
+use crate::secret;
+use crate::seed_store::RetryPolicy;
+use crate::select_cache::SelectCacheConfig;
+use crate::shutdown::RestartPolicy;
+use crate::solr::{HedgeConfig, SolrClientConfig};
+use config::{Config, ConfigError};
+use hyper::{Method, Uri};
+use log::LevelFilter;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// seed_id를 넣는 필드 이름. doc에 그대로 넣을 수 있도록 'static으로 만듦
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeedIdField(&'static str);
+
+impl SeedIdField {
+    /// 기본 이름이 아닌 경우 설정을 읽을 때마다 새로 만듦. 설정은 시작, reload할 때만 읽으므로 크기가 작음
+    pub fn new(name: &str) -> Self {
+        if name == crate::COL_SEED_ID {
+            return Self::default();
+        }
+        Self(Box::leak(name.to_string().into_boxed_str()))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+
+    pub fn as_bytes(self) -> &'static [u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl Default for SeedIdField {
+    fn default() -> Self {
+        Self(crate::COL_SEED_ID)
+    }
+}
+
+/// seed_id_regex의 기본값. MySQL uuid()가 만드는 v1과 v4 형식
+const DEFAULT_SEED_ID_REGEX: &str =
+    r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[14][0-9a-fA-F]{3}-[89abAB][0-9a-fA-F]{3}-[0-9a-fA-F]{12}$";
+
+/// seed_table의 기본값
+const DEFAULT_SEED_TABLE: &str = "crawlerdb.t_channel_contents_map";
+
+/// admin_bind_ip의 기본값. 같은 서버에서만 관리용 endpoint에 접근할 수 있음
+const DEFAULT_ADMIN_BIND_IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+/// config에서 읽어온 동작 설정값
+/// <br>
+/// 값이 없는 경우 기본값을 사용하며, 0은 제한 없음을 의미함
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// update 요청 body의 최대 크기(bytes)
+    pub max_body_bytes: usize,
+    /// 처리중인 update 요청 body를 모두 합쳐 메모리에 들고 있을 수 있는 최대 크기(bytes)
+    pub max_buffered_bytes: u64,
+    /// update 요청 body가 이 크기(bytes)를 넘으면 메모리 대신 임시 파일에 받음. 0이면 사용하지 않음
+    pub spool_threshold_bytes: u64,
+    /// 임시 파일을 만들 디렉터리. 비어있으면 OS의 임시 디렉터리
+    pub spool_dir: String,
+    /// update 요청 하나에 들어갈 수 있는 최대 doc 수
+    pub max_docs_per_update: usize,
+    /// 파싱한 update의 doc 수가 이보다 많으면 이 수 이하로 나누어 솔라에 보냄. 0이면 나누지 않음
+    pub split_threshold_docs: usize,
+    /// 작성한 update가 이 크기(bytes)를 넘으면 이 크기 이하로 나누어 솔라에 보냄. 0이면 나누지 않음
+    pub split_threshold_bytes: usize,
+    /// doc 하나에 들어갈 수 있는 최대 field 수
+    pub max_fields_per_doc: usize,
+    /// 동시에 처리할 수 있는 update 요청 수
+    pub max_concurrent_updates: usize,
+    /// update 요청이 처리 순서를 기다릴 최대 시간
+    pub update_queue_wait: Duration,
+    /// 동시에 처리할 수 있는 select 요청 수
+    pub max_concurrent_selects: usize,
+    /// select 요청이 처리 순서를 기다릴 최대 시간
+    pub select_queue_wait: Duration,
+    /// 503 응답시 Retry-After 헤더 값(초)
+    pub retry_after_secs: u64,
+    /// remote ip별 초당 허용 요청 수
+    pub rate_limit_per_ip_rps: f64,
+    /// remote ip별 순간적으로 허용하는 최대 요청 수
+    pub rate_limit_burst: f64,
+    /// 요청 수를 제한하지 않는 ip 목록
+    pub rate_limit_exempt_ips: Vec<IpAddr>,
+    /// update 요청을 허용하는 ip, CIDR 목록. 비어있으면 모두 허용
+    pub update_allowed_ips: IpAllowList,
+    /// select 요청을 허용하는 ip, CIDR 목록. 비어있으면 모두 허용
+    pub select_allowed_ips: IpAllowList,
+    /// true인 경우 클라이언트의 Host 헤더를 그대로 솔라에 전달
+    pub preserve_host: bool,
+    /// 솔라로 보내는 update body 압축 방식
+    pub compress_upstream: UpstreamCompression,
+    /// 이 크기 이상의 update body만 압축함(bytes)
+    pub compress_upstream_min_bytes: usize,
+    /// true인 경우 select, update 외의 path도 그대로 솔라에 전달
+    pub passthrough_unknown_paths: bool,
+    /// passthrough하지 않고 403으로 차단할 path prefix 목록
+    pub passthrough_deny_prefixes: Vec<String>,
+    /// 카페/블로그처럼 path까지 seed_host로 사용하는 규칙. 순서대로 확인함
+    pub host_rules: Vec<HostRule>,
+    /// 새 seed row의 media_type_no, site_name을 seed_host로 정하는 규칙. 순서대로 확인하며 맞는 규칙이 없으면 0, 빈 문자열
+    pub media_type_rules: Vec<MediaTypeRule>,
+    /// url 필드 이름. seed_url_fields가 없는 경우 이 필드로 seed_host를 만듦
+    pub field_url: String,
+    /// seed_url_fields 순서대로 확인하여 처음으로 사용 가능한 값을 사용
+    pub seed_url_fields: Vec<String>,
+    /// seed_id를 확인하고 넣는 필드 이름
+    pub field_seed_id: SeedIdField,
+    /// true인 경우 host, site 필드가 없는 doc에 url의 host를 채워넣음
+    pub fill_host_fields: bool,
+    /// true인 경우 tstamp가 없는 doc에 현재 시각을 넣고, postdate를 솔라 날짜 형식으로 변환함
+    pub normalize_dates: bool,
+    /// true인 경우 field 값에서 xml 1.0에서 사용할 수 없는 문자를 제거함
+    pub sanitize_xml: bool,
+    /// true인 경우 single_valued_fields에 값이 여러개 있을 때 첫번째 값만 남김
+    pub dedup_single_valued_fields: bool,
+    /// 값이 하나만 있어야 하는 필드 목록
+    pub single_valued_fields: Vec<String>,
+    /// true인 경우 update body 전체를 읽지 않고 doc 단위로 처리하면서 솔라로 보냄
+    pub stream_updates: bool,
+    /// true인 경우 id가 같은 doc이 여러개 있으면 마지막 doc만 남김
+    pub dedup_docs_by_id: bool,
+    /// doc에 반드시 있어야 하는 필드 목록. 비어있으면 확인하지 않음
+    pub required_fields: Vec<String>,
+    /// 필수 필드가 없는 doc의 처리 방식
+    pub required_fields_action: RequiredFieldsAction,
+    /// 이 시간 이상 걸린 select 요청은 warn 로그를 남김. 0이면 사용하지 않음
+    pub slow_select: Duration,
+    /// 이 시간 이상 걸린 update 요청은 warn 로그를 남김. 0이면 사용하지 않음
+    pub slow_update: Duration,
+    /// 통계 로그를 남기는 주기
+    pub stats_interval: Duration,
+    /// true인 경우 통계 로그를 남길 때마다 카운터를 초기화함. false인 경우 카운터는 계속 증가하고 이전 로그와의 차이를 남김
+    pub stats_reset: bool,
+    /// 종료 요청 후 처리중인 요청이 끝나기를 기다릴 최대 시간
+    pub shutdown_grace: Duration,
+    /// 요청 처리 중 panic이 발생한 경우의 처리 방식
+    pub panic_policy: PanicPolicy,
+    /// true인 경우 panic 등으로 서버가 종료되면 backoff만큼 기다린 뒤 다시 시작함
+    pub restart_on_panic: bool,
+    /// 다시 시작할 수 있는 최대 횟수
+    pub max_restarts: u32,
+    /// 처음 다시 시작하기 전 기다릴 시간. 다시 시작할 때마다 2배로 늘어남
+    pub restart_backoff: Duration,
+    /// 관리용 endpoint에 X-Proxy-Admin-Secret header로 보내야 하는 값. 비어있으면 관리용 endpoint를 사용하지 않음
+    pub admin_secret: String,
+    /// true인 경우 select, update 요청마다 log/access.log에 한 줄씩 남김
+    pub access_log: bool,
+    /// log 파일을 나누는 기준
+    pub log_roll: LogRoll,
+    /// log_roll이 size인 경우 log 파일을 나누는 크기(bytes)
+    pub log_roll_size_bytes: u64,
+    /// 나눈 log 파일을 남길 최대 개수
+    pub log_keep_count: u32,
+    /// DB 연결 최대 개수
+    pub db_max_connections: u32,
+    /// 미사용 중에도 유지하는 DB 연결 개수
+    pub db_min_connections: u32,
+    /// 이 시간동안 미사용시 연결 끊음
+    pub db_idle_timeout: Duration,
+    /// 연결 후 이 시간이 지나면 연결 끊음
+    pub db_max_lifetime: Duration,
+    /// DB 연결을 얻기 위해 기다릴 최대 시간
+    pub db_acquire_timeout: Duration,
+    /// 연결마다 캐시하는 prepared statement 개수
+    pub db_statement_cache: usize,
+    /// 일시적인 DB 에러를 다시 시도하는 최대 횟수. 0이면 다시 시도하지 않음
+    pub db_retry_count: u32,
+    /// 다시 시도하기 전 기다릴 최대 시간
+    pub db_retry_max: Duration,
+    /// root log level
+    pub log_level: LevelFilter,
+    /// 솔라 host별로 유지하는 idle 연결 최대 개수
+    pub solr_pool_max_idle_per_host: usize,
+    /// 이 시간동안 사용하지 않은 솔라 idle 연결은 끊음
+    pub solr_pool_idle_timeout: Duration,
+    /// 솔라 연결의 TCP keepalive 주기. 0이면 사용하지 않음
+    pub solr_tcp_keepalive: Duration,
+    /// true인 경우 솔라에 HTTP/2(h2c)로 요청함. 클라이언트와는 계속 HTTP/1.1을 사용함
+    pub solr_http2: bool,
+    /// 솔라 요청마다 응답 header를 기다릴 최대 시간. 0이면 제한 없음
+    pub solr_request_timeout: Duration,
+    /// true인 경우 시작할 때 솔라 schema에 seed_id 등 필요한 필드가 있는지 확인함
+    pub verify_schema: bool,
+    /// schema를 확인할 core 목록. 비어있으면 collections의 컬렉션을 확인함
+    pub verify_schema_cores: Vec<String>,
+    /// 필요한 필드가 없는 경우의 처리 방식
+    pub verify_schema_action: SchemaCheckAction,
+    /// schema 확인 전체를 기다릴 최대 시간. 넘으면 경고만 남기고 시작함
+    pub verify_schema_timeout: Duration,
+    /// true인 경우 시작할 때 seed table이 있는지 확인하고, 없으면 시작하지 않음
+    pub verify_seed_table: bool,
+    /// true인 경우 시작할 때 없는 seed table을 만듦. verify_seed_table이 false여도 확인함
+    pub create_seed_table: bool,
+    /// 늦게 응답하는 select 요청을 다시 보낼 솔라 주소 목록. 비어있으면 사용하지 않음
+    pub hedge_urls: Vec<String>,
+    /// select 요청이 이 시간 안에 응답하지 않으면 hedge_urls에도 보냄. 0이면 사용하지 않음
+    pub hedge_after: Duration,
+    /// 동시에 hedge_urls로 보낼 수 있는 요청 수
+    pub hedge_max_concurrent: usize,
+    /// select 응답을 cache에서 사용할 시간. 0이면 cache를 사용하지 않음
+    pub select_cache_ttl: Duration,
+    /// cache에 저장하는 select 응답 전체의 최대 크기(bytes)
+    pub select_cache_max_bytes: usize,
+    /// 이 크기를 넘는 select 응답은 cache에 저장하지 않음(bytes)
+    pub select_cache_max_entry_bytes: usize,
+    /// select cache key에서 제외하는 파라미터
+    pub select_cache_bust_param: String,
+    /// true인 경우 update 요청을 받으면 select cache를 비움
+    pub select_cache_flush_on_update: bool,
+    /// select 요청에 없으면 추가하는 파라미터. 이름 순서로 추가함
+    pub select_default_params: Vec<(String, String)>,
+    /// select 요청 파라미터의 최대값. 넘는 경우 최대값으로 줄여서 보냄
+    pub select_param_caps: Vec<(String, u64)>,
+    /// true인 경우 update 요청에 POST 외에 PUT도 사용할 수 있음
+    pub update_allow_put: bool,
+    /// update 요청에 받는 Content-Type. 없는 요청은 body를 읽기 전에 415로 거부함
+    pub update_content_types: Vec<String>,
+    /// true인 경우 Content-Type이 없는 update 요청도 거부함. false면 xml로 봄
+    pub update_content_type_strict: bool,
+    /// 0이 아니면 update 요청의 commitWithin 파라미터를 이 값으로 넣거나 바꿈
+    pub force_commit_within: Duration,
+    /// force_commit_within을 사용할 때 클라이언트가 보낸 commit=true를 그대로 보냄
+    pub allow_client_commit: bool,
+    /// CORS header를 넣어 응답하는 Origin 목록. "*"는 모든 Origin. 비어있으면 사용하지 않음
+    pub cors_allowed_origins: Vec<String>,
+    /// true인 경우 update 요청에도 CORS header를 넣음
+    pub cors_allow_update: bool,
+    /// 브라우저가 CORS preflight 결과를 cache할 시간
+    pub cors_max_age: Duration,
+    /// true인 경우 select, update 응답에 X-Proxy-Duration-Ms 등 처리 시간 header를 추가함
+    pub expose_timing_headers: bool,
+    /// seed_id cache에 저장하는 최대 seed_host 수
+    pub seed_id_cache_capacity: usize,
+    /// seed_id cache를 나누는 shard 수. 2의 거듭제곱이어야 함
+    pub seed_id_cache_shards: usize,
+    /// DB 조회에 실패한 seed_host를 다시 조회하지 않는 시간. 0이면 사용하지 않음
+    pub seed_lookup_failure_ttl: Duration,
+    /// 새로 만든 seed_id 기록을 저장할 table. 비어있으면 로그로만 남김
+    pub seed_audit_table: String,
+    /// 1분동안 새로 INSERT할 수 있는 seed_id 수. 넘으면 통계 구간이 끝날 때까지 INSERT하지 않음. 0이면 제한하지 않음
+    pub max_new_seeds_per_minute: u64,
+    /// true인 경우 요청에 있는 seed_id가 seed_id_regex에 맞지 않으면 지우고 다시 조회함
+    pub validate_incoming_seed_id: bool,
+    /// seed_url_fields 값이 이 길이(bytes)를 넘는 doc은 seed_id를 넣지 않고 그대로 보냄. 0이면 제한하지 않음
+    pub max_url_bytes: usize,
+    /// doc 하나를 읽고 seed_host를 만들 때까지 사용할 수 있는 최대 시간. 넘은 doc은 seed_id를 넣지 않고 그대로 보냄. 0이면 제한하지 않음
+    /// <br>
+    /// seed_id DB 조회는 doc들을 모아 나중에 처리하므로 포함하지 않음. DB 조회 시간은 db_acquire_timeout, db_retry_count, db_retry_max로 제한함
+    pub doc_enrich_budget: Duration,
+    /// 필드 이름별 값의 최대 길이(bytes). 넘는 값은 utf-8 문자 단위로 잘라서 보냄
+    pub field_max_bytes: Vec<(String, u64)>,
+    /// 올바른 seed_id 형식. 기본값은 UUID v1/v4
+    pub seed_id_regex: Regex,
+    /// 한 update 요청에서 동시에 DB로 조회하는 seed_host 수. 0이면 db_max_connections와 같음
+    pub enrich_concurrency: usize,
+    /// 여러 proxy가 함께 사용하는 seed_id cache(Redis) 주소. 비어있으면 사용하지 않음
+    pub redis_url: String,
+    /// Redis에 저장한 seed_id를 유지할 시간
+    pub redis_ttl: Duration,
+    /// Redis 연결, 명령마다 기다릴 최대 시간
+    pub redis_timeout: Duration,
+    /// true인 경우 update 요청을 받지 않는 점검 상태로 시작함. 실행 중에는 /proxy/drain, /proxy/undrain으로 바꿈
+    pub drain_updates: bool,
+    /// trace를 보낼 OTLP(gRPC) 주소. 비어있거나 otel feature 없이 빌드한 경우 보내지 않음
+    pub otel_endpoint: String,
+    /// seed_id를 조회, 생성하는 table. schema.table 형식
+    pub seed_table: String,
+    /// 클라이언트 연결에 사용할 PEM 인증서 파일. tls_key_path와 함께 설정하면 HTTPS로만 받음
+    pub tls_cert_path: String,
+    /// tls_cert_path 인증서의 PEM key 파일
+    pub tls_key_path: String,
+    /// 서버가 받을 ip를 고를 네트워크 인터페이스 이름. 비어있으면 기본 인터페이스를 사용함
+    pub bind_interface: String,
+    /// true인 경우 v6 ip가 있으면 v6 ip로 받음
+    pub prefer_ipv6: bool,
+    /// 서버가 받을 주소 목록. 모든 주소를 동시에 받음
+    pub listen: Vec<ListenAddr>,
+    /// 관리용 endpoint만 받는 port. 0인 경우 listen의 listener에서 받음
+    pub admin_port: u16,
+    /// admin_port를 받을 ip
+    pub admin_bind_ip: IpAddr,
+    /// 전역 설정을 그대로 사용하는 컬렉션 설정. collections에 없는 컬렉션에 사용함
+    pub default_collection: Arc<CollectionSettings>,
+    /// config의 collections.<name>에서 읽은 컬렉션별 설정. key는 소문자 컬렉션 이름
+    pub collections: HashMap<String, Arc<CollectionSettings>>,
+}
+
+/// 컬렉션마다 다르게 설정할 수 있는 enrich 설정
+/// <br>
+/// collections.<name>에 없는 값은 전역 설정의 값을 사용함
+#[derive(Debug, Clone, Default)]
+pub struct CollectionSettings {
+    /// 컬렉션 이름. 전역 설정인 경우 빈 문자열
+    pub name: String,
+    /// seed_id를 조회, 생성하는 table. schema.table 형식
+    pub seed_table: String,
+    /// seed_id cache key 앞에 붙이는 값. 없으면 컬렉션 이름이며, 전역 설정은 seed_host를 그대로 key로 사용함
+    pub cache_namespace: String,
+    pub host_rules: Vec<HostRule>,
+    pub media_type_rules: Vec<MediaTypeRule>,
+    pub seed_url_fields: Vec<String>,
+    pub seed_id_field: SeedIdField,
+    pub fill_host_fields: bool,
+    pub normalize_dates: bool,
+    pub sanitize_xml: bool,
+    pub dedup_single_valued_fields: bool,
+    pub single_valued_fields: Vec<String>,
+    pub dedup_docs_by_id: bool,
+    pub required_fields: Vec<String>,
+    pub required_fields_action: RequiredFieldsAction,
+    pub validate_incoming_seed_id: bool,
+    pub max_url_bytes: usize,
+    pub doc_enrich_budget: Duration,
+    pub field_max_bytes: Vec<(String, u64)>,
+}
+
+impl CollectionSettings {
+    /// settings의 전역 값을 사용하는 설정
+    fn global(settings: &Settings) -> Self {
+        Self {
+            name: String::new(),
+            seed_table: settings.seed_table.clone(),
+            cache_namespace: String::new(),
+            host_rules: settings.host_rules.clone(),
+            media_type_rules: settings.media_type_rules.clone(),
+            seed_url_fields: settings.seed_url_fields.clone(),
+            seed_id_field: settings.field_seed_id,
+            fill_host_fields: settings.fill_host_fields,
+            normalize_dates: settings.normalize_dates,
+            sanitize_xml: settings.sanitize_xml,
+            dedup_single_valued_fields: settings.dedup_single_valued_fields,
+            single_valued_fields: settings.single_valued_fields.clone(),
+            dedup_docs_by_id: settings.dedup_docs_by_id,
+            required_fields: settings.required_fields.clone(),
+            required_fields_action: settings.required_fields_action,
+            validate_incoming_seed_id: settings.validate_incoming_seed_id,
+            max_url_bytes: settings.max_url_bytes,
+            doc_enrich_budget: settings.doc_enrich_budget,
+            field_max_bytes: settings.field_max_bytes.clone(),
+        }
+    }
+
+    /// collections.<name>의 값을 읽음. 없는 값은 base의 값을 사용함
+    fn from_config(
+        config: &Config,
+        name: &str,
+        base: &Self,
+        errors: &mut Vec<ConfigError>,
+    ) -> Self {
+        let key = |field: &str| format!("collections.{}.{}", name, field);
+        let get_list = |field: &str, base: &[String]| {
+            let default: Vec<&str> = base.iter().map(String::as_str).collect();
+            get_string_list_or(config, &key(field), &default)
+        };
+        let seed_id_field = SeedIdField::new(
+            &get_string(config, &key("field_seed_id"), base.seed_id_field.as_str())
+                .collect_err(errors),
+        );
+        // base의 seed_id 필드는 컬렉션에서 바꾼 seed_id 필드로 바꿔서 사용함
+        let base_single_valued: Vec<String> = base
+            .single_valued_fields
+            .iter()
+            .map(|field| match field == base.seed_id_field.as_str() {
+                true => seed_id_field.as_str().to_string(),
+                false => field.clone(),
+            })
+            .collect();
+        Self {
+            name: name.to_string(),
+            seed_table: get_string(config, &key("seed_table"), &base.seed_table)
+                .collect_err(errors),
+            cache_namespace: get_string(config, &key("cache_namespace"), name).collect_err(errors),
+            host_rules: HostRule::from_config_or(config, &key("host_rules"), || {
+                base.host_rules.clone()
+            })
+            .collect_err(errors),
+            media_type_rules: MediaTypeRule::from_config_or(
+                config,
+                &key("media_type_rules"),
+                || base.media_type_rules.clone(),
+            )
+            .collect_err(errors),
+            // field_url만 있으면 그 필드로 seed_host를 만듦
+            seed_url_fields: match get_string(config, &key("field_url"), "").collect_err(errors) {
+                field_url if field_url.is_empty() => {
+                    get_list("seed_url_fields", &base.seed_url_fields)
+                }
+                field_url => get_list("seed_url_fields", &[field_url]),
+            }
+            .collect_err(errors),
+            seed_id_field,
+            fill_host_fields: get_bool(config, &key("fill_host_fields"), base.fill_host_fields)
+                .collect_err(errors),
+            normalize_dates: get_bool(config, &key("normalize_dates"), base.normalize_dates)
+                .collect_err(errors),
+            sanitize_xml: get_bool(config, &key("sanitize_xml"), base.sanitize_xml)
+                .collect_err(errors),
+            dedup_single_valued_fields: get_bool(
+                config,
+                &key("dedup_single_valued_fields"),
+                base.dedup_single_valued_fields,
+            )
+            .collect_err(errors),
+            single_valued_fields: get_list("single_valued_fields", &base_single_valued)
+                .collect_err(errors),
+            dedup_docs_by_id: get_bool(config, &key("dedup_docs_by_id"), base.dedup_docs_by_id)
+                .collect_err(errors),
+            required_fields: get_list("required_fields", &base.required_fields).collect_err(errors),
+            required_fields_action: get_parsed(
+                config,
+                &key("required_fields_action"),
+                base.required_fields_action,
+                RequiredFieldsAction::parse,
+            )
+            .collect_err(errors),
+            validate_incoming_seed_id: get_bool(
+                config,
+                &key("validate_incoming_seed_id"),
+                base.validate_incoming_seed_id,
+            )
+            .collect_err(errors),
+            max_url_bytes: get_uint(config, &key("max_url_bytes"), base.max_url_bytes)
+                .collect_err(errors),
+            doc_enrich_budget: Duration::from_millis(
+                get_uint(
+                    config,
+                    &key("doc_enrich_budget_ms"),
+                    base.doc_enrich_budget.as_millis() as u64,
+                )
+                .collect_err(errors),
+            ),
+            // 컬렉션에 table이 있으면 전역 table 대신 사용함
+            field_max_bytes: match get_uint_table(config, &key("field_max_bytes"))
+                .collect_err(errors)
+            {
+                table if table.is_empty() => base.field_max_bytes.clone(),
+                table => table,
+            },
+        }
+    }
+
+    /// seed_id cache key. cache_namespace가 있으면 "<cache_namespace>:<seed_host>"
+    pub fn cache_key(&self, seed_host: &str) -> String {
+        if self.cache_namespace.is_empty() {
+            seed_host.to_string()
+        } else {
+            format!("{}:{}", self.cache_namespace, seed_host)
+        }
+    }
+}
+
+/// config의 collections 아래 컬렉션마다 설정을 읽음
+fn read_collections(
+    config: &Config,
+    base: &CollectionSettings,
+    errors: &mut Vec<ConfigError>,
+) -> HashMap<String, Arc<CollectionSettings>> {
+    let names: Vec<String> = match config.get_table("collections") {
+        Ok(table) => table.into_keys().collect(),
+        Err(ConfigError::NotFound(_)) => Vec::new(),
+        Err(e) => {
+            errors.push(invalid_type("collections", e));
+            Vec::new()
+        }
+    };
+    names
+        .into_iter()
+        .map(|name| {
+            let collection = CollectionSettings::from_config(config, &name, base, errors);
+            (name.to_ascii_lowercase(), Arc::new(collection))
+        })
+        .collect()
+}
+
+impl Settings {
+    pub fn from_config(config: &Config) -> Result<Self, ConfigErrors> {
+        let mut errors = Vec::new();
+        let rate_limit_per_ip_rps =
+            get_f64(config, "rate_limit_per_ip_rps", 0f64).collect_err(&mut errors);
+        let field_url = get_string(config, "field_url", crate::COL_URL).collect_err(&mut errors);
+        let field_seed_id = SeedIdField::new(
+            &get_string(config, "field_seed_id", crate::COL_SEED_ID).collect_err(&mut errors),
+        );
+
+        let mut settings = Self {
+            max_body_bytes: get_uint(config, "max_body_bytes", 0).collect_err(&mut errors),
+            max_buffered_bytes: get_uint(config, "max_buffered_bytes", 0).collect_err(&mut errors),
+            spool_threshold_bytes: get_uint(config, "spool_threshold_bytes", 0)
+                .collect_err(&mut errors),
+            spool_dir: get_string(config, "spool_dir", "").collect_err(&mut errors),
+            max_docs_per_update: get_uint(config, "max_docs_per_update", 0)
+                .collect_err(&mut errors),
+            split_threshold_docs: get_uint(config, "split_threshold_docs", 0)
+                .collect_err(&mut errors),
+            split_threshold_bytes: get_uint(config, "split_threshold_bytes", 0)
+                .collect_err(&mut errors),
+            max_fields_per_doc: get_uint(config, "max_fields_per_doc", 0).collect_err(&mut errors),
+            max_concurrent_updates: get_uint(config, "max_concurrent_updates", 0)
+                .collect_err(&mut errors),
+            update_queue_wait: Duration::from_millis(
+                get_uint(config, "update_queue_wait_ms", 30_000).collect_err(&mut errors),
+            ),
+            max_concurrent_selects: get_uint(config, "max_concurrent_selects", 0)
+                .collect_err(&mut errors),
+            select_queue_wait: Duration::from_millis(
+                get_uint(config, "select_queue_wait_ms", 30_000).collect_err(&mut errors),
+            ),
+            retry_after_secs: get_uint(config, "retry_after_secs", 1).collect_err(&mut errors),
+            rate_limit_per_ip_rps,
+            rate_limit_burst: get_f64(config, "rate_limit_burst", rate_limit_per_ip_rps)
+                .collect_err(&mut errors),
+            rate_limit_exempt_ips: get_ip_list(config, "rate_limit_exempt_ips")
+                .collect_err(&mut errors),
+            update_allowed_ips: get_ip_allow_list(config, "update_allowed_ips")
+                .collect_err(&mut errors),
+            select_allowed_ips: get_ip_allow_list(config, "select_allowed_ips")
+                .collect_err(&mut errors),
+            preserve_host: get_bool(config, "preserve_host", false).collect_err(&mut errors),
+            compress_upstream: get_parsed(
+                config,
+                "compress_upstream",
+                UpstreamCompression::None,
+                UpstreamCompression::parse,
+            )
+            .collect_err(&mut errors),
+            compress_upstream_min_bytes: get_uint(config, "compress_upstream_min_bytes", 32_768)
+                .collect_err(&mut errors),
+            passthrough_unknown_paths: get_bool(config, "passthrough_unknown_paths", false)
+                .collect_err(&mut errors),
+            passthrough_deny_prefixes: get_string_list_or(
+                config,
+                "passthrough_deny_prefixes",
+                &["/admin/cores"],
+            )
+            .collect_err(&mut errors),
+            host_rules: HostRule::from_config(config).collect_err(&mut errors),
+            media_type_rules: MediaTypeRule::from_config(config).collect_err(&mut errors),
+            seed_url_fields: get_string_list_or(config, "seed_url_fields", &[field_url.as_str()])
+                .collect_err(&mut errors),
+            field_url,
+            field_seed_id,
+            fill_host_fields: get_bool(config, "fill_host_fields", false).collect_err(&mut errors),
+            normalize_dates: get_bool(config, "normalize_dates", false).collect_err(&mut errors),
+            sanitize_xml: get_bool(config, "sanitize_xml", false).collect_err(&mut errors),
+            dedup_single_valued_fields: get_bool(config, "dedup_single_valued_fields", false)
+                .collect_err(&mut errors),
+            // field_seed_id를 바꾼 경우 기본값도 그 필드를 사용함
+            single_valued_fields: get_string_list_or(
+                config,
+                "single_valued_fields",
+                &["id", field_seed_id.as_str()],
+            )
+            .collect_err(&mut errors),
+            stream_updates: get_bool(config, "stream_updates", false).collect_err(&mut errors),
+            dedup_docs_by_id: get_bool(config, "dedup_docs_by_id", false).collect_err(&mut errors),
+            required_fields: get_string_list(config, "required_fields").collect_err(&mut errors),
+            required_fields_action: get_parsed(
+                config,
+                "required_fields_action",
+                RequiredFieldsAction::Drop,
+                RequiredFieldsAction::parse,
+            )
+            .collect_err(&mut errors),
+            slow_select: Duration::from_millis(
+                get_uint(config, "slow_select_ms", 0).collect_err(&mut errors),
+            ),
+            slow_update: Duration::from_millis(
+                get_uint(config, "slow_update_ms", 0).collect_err(&mut errors),
+            ),
+            stats_interval: Duration::from_secs(
+                get_uint(config, "stats_interval_secs", 60).collect_err(&mut errors),
+            ),
+            stats_reset: get_bool(config, "stats_reset", true).collect_err(&mut errors),
+            shutdown_grace: Duration::from_secs(
+                get_uint(config, "shutdown_grace_secs", 30).collect_err(&mut errors),
+            ),
+            panic_policy: get_parsed(
+                config,
+                "panic_policy",
+                PanicPolicy::Request,
+                PanicPolicy::parse,
+            )
+            .collect_err(&mut errors),
+            restart_on_panic: get_bool(config, "restart_on_panic", true).collect_err(&mut errors),
+            max_restarts: get_uint(config, "max_restarts", 5).collect_err(&mut errors),
+            restart_backoff: Duration::from_secs(
+                get_uint(config, "restart_backoff_secs", 5).collect_err(&mut errors),
+            ),
+            admin_secret: get_string(config, "admin_secret", "").collect_err(&mut errors),
+            access_log: get_bool(config, "access_log", false).collect_err(&mut errors),
+            log_roll: get_parsed(config, "log_roll", LogRoll::Size, LogRoll::parse)
+                .collect_err(&mut errors),
+            log_roll_size_bytes: get_uint(
+                config,
+                "log_roll_size_bytes",
+                LogRollConfig::default().size_bytes,
+            )
+            .collect_err(&mut errors),
+            log_keep_count: get_uint(
+                config,
+                "log_keep_count",
+                LogRollConfig::default().keep_count,
+            )
+            .collect_err(&mut errors),
+            db_max_connections: get_uint(config, "db_max_connections", 10).collect_err(&mut errors),
+            db_min_connections: get_uint(config, "db_min_connections", 0).collect_err(&mut errors),
+            db_idle_timeout: Duration::from_secs(
+                get_uint(config, "db_idle_timeout_secs", 60 * 10).collect_err(&mut errors),
+            ),
+            db_max_lifetime: Duration::from_secs(
+                get_uint(config, "db_max_lifetime_secs", 60 * 30).collect_err(&mut errors),
+            ),
+            db_acquire_timeout: Duration::from_secs(
+                get_uint(config, "db_acquire_timeout_secs", 60 * 5).collect_err(&mut errors),
+            ),
+            db_statement_cache: get_uint(config, "db_statement_cache", 100)
+                .collect_err(&mut errors),
+            db_retry_count: get_uint(config, "db_retry_count", 3).collect_err(&mut errors),
+            db_retry_max: Duration::from_millis(
+                get_uint(config, "db_retry_max_ms", 1000).collect_err(&mut errors),
+            ),
+            // LevelFilter는 Default가 없으므로 에러인 경우 직접 기본값을 넣음
+            log_level: get_parsed(config, "log_level", LevelFilter::Info, |value| {
+                value.trim().parse().ok()
+            })
+            .unwrap_or_else(|e| {
+                errors.push(e);
+                LevelFilter::Info
+            }),
+            solr_pool_max_idle_per_host: get_uint(config, "solr_pool_max_idle_per_host", 0)
+                .collect_err(&mut errors),
+            solr_pool_idle_timeout: Duration::from_secs(
+                get_uint(config, "solr_pool_idle_timeout_secs", 90).collect_err(&mut errors),
+            ),
+            solr_tcp_keepalive: Duration::from_secs(
+                get_uint(config, "solr_tcp_keepalive_secs", 0).collect_err(&mut errors),
+            ),
+            solr_http2: get_bool(config, "solr_http2", false).collect_err(&mut errors),
+            solr_request_timeout: Duration::from_millis(
+                get_uint(config, "solr_request_timeout_ms", 0).collect_err(&mut errors),
+            ),
+            verify_schema: get_bool(config, "verify_schema", false).collect_err(&mut errors),
+            verify_schema_cores: get_string_list(config, "verify_schema_cores")
+                .collect_err(&mut errors),
+            verify_schema_action: get_parsed(
+                config,
+                "verify_schema_action",
+                SchemaCheckAction::Warn,
+                SchemaCheckAction::parse,
+            )
+            .collect_err(&mut errors),
+            verify_schema_timeout: Duration::from_millis(
+                get_uint(config, "verify_schema_timeout_ms", 3000).collect_err(&mut errors),
+            ),
+            verify_seed_table: get_bool(config, "verify_seed_table", false)
+                .collect_err(&mut errors),
+            create_seed_table: get_bool(config, "create_seed_table", false)
+                .collect_err(&mut errors),
+            hedge_urls: get_string_list(config, "hedge_urls").collect_err(&mut errors),
+            hedge_after: Duration::from_millis(
+                get_uint(config, "hedge_after_ms", 0).collect_err(&mut errors),
+            ),
+            hedge_max_concurrent: get_uint(config, "hedge_max_concurrent", 10)
+                .collect_err(&mut errors),
+            select_cache_ttl: Duration::from_secs(
+                get_uint(config, "select_cache_ttl_secs", 0).collect_err(&mut errors),
+            ),
+            select_cache_max_bytes: get_uint(config, "select_cache_max_bytes", 64 * 1024 * 1024)
+                .collect_err(&mut errors),
+            select_cache_max_entry_bytes: get_uint(
+                config,
+                "select_cache_max_entry_bytes",
+                1024 * 1024,
+            )
+            .collect_err(&mut errors),
+            select_cache_bust_param: get_string(config, "select_cache_bust_param", "_")
+                .collect_err(&mut errors),
+            select_cache_flush_on_update: get_bool(config, "select_cache_flush_on_update", true)
+                .collect_err(&mut errors),
+            select_default_params: get_string_table(config, "select_default_params")
+                .collect_err(&mut errors),
+            select_param_caps: get_uint_table(config, "select_param_caps").collect_err(&mut errors),
+            update_allow_put: get_bool(config, "update_allow_put", false).collect_err(&mut errors),
+            update_content_types: get_string_list_or(
+                config,
+                "update_content_types",
+                content_type::DEFAULT_UPDATE_CONTENT_TYPES,
+            )
+            .map(content_type::normalize)
+            .collect_err(&mut errors),
+            update_content_type_strict: get_bool(config, "update_content_type_strict", false)
+                .collect_err(&mut errors),
+            force_commit_within: Duration::from_millis(
+                get_uint(config, "force_commit_within_ms", 0).collect_err(&mut errors),
+            ),
+            allow_client_commit: get_bool(config, "allow_client_commit", false)
+                .collect_err(&mut errors),
+            cors_allowed_origins: get_string_list(config, "cors_allowed_origins")
+                .collect_err(&mut errors),
+            cors_allow_update: get_bool(config, "cors_allow_update", false)
+                .collect_err(&mut errors),
+            cors_max_age: Duration::from_secs(
+                get_uint(config, "cors_max_age_secs", 600).collect_err(&mut errors),
+            ),
+            expose_timing_headers: get_bool(config, "expose_timing_headers", false)
+                .collect_err(&mut errors),
+            seed_id_cache_capacity: get_uint(config, "seed_id_cache_capacity", 100_000)
+                .collect_err(&mut errors),
+            seed_id_cache_shards: get_uint(config, "seed_id_cache_shards", 16)
+                .collect_err(&mut errors),
+            seed_lookup_failure_ttl: Duration::from_secs(
+                get_uint(config, "seed_lookup_failure_ttl_secs", 5).collect_err(&mut errors),
+            ),
+            seed_audit_table: get_string(config, "seed_audit_table", "").collect_err(&mut errors),
+            max_new_seeds_per_minute: get_uint(config, "max_new_seeds_per_minute", 0)
+                .collect_err(&mut errors),
+            enrich_concurrency: get_uint(config, "enrich_concurrency", 0).collect_err(&mut errors),
+            redis_url: get_string(config, "redis_url", "").collect_err(&mut errors),
+            redis_ttl: Duration::from_secs(
+                get_uint(config, "redis_ttl_secs", 86400).collect_err(&mut errors),
+            ),
+            redis_timeout: Duration::from_millis(
+                get_uint(config, "redis_timeout_ms", 100).collect_err(&mut errors),
+            ),
+            otel_endpoint: get_string(config, "otel_endpoint", "").collect_err(&mut errors),
+            drain_updates: get_bool(config, "drain_updates", false).collect_err(&mut errors),
+            seed_table: get_string(config, "seed_table", DEFAULT_SEED_TABLE)
+                .collect_err(&mut errors),
+            tls_cert_path: get_string(config, "tls_cert_path", "").collect_err(&mut errors),
+            tls_key_path: get_string(config, "tls_key_path", "").collect_err(&mut errors),
+            bind_interface: get_string(config, "bind_interface", "").collect_err(&mut errors),
+            prefer_ipv6: get_bool(config, "prefer_ipv6", false).collect_err(&mut errors),
+            listen: get_listen_list(config, "listen").collect_err(&mut errors),
+            admin_port: get_uint(config, "admin_port", 0).collect_err(&mut errors),
+            admin_bind_ip: get_parsed(config, "admin_bind_ip", DEFAULT_ADMIN_BIND_IP, |value| {
+                value.trim().parse().ok()
+            })
+            .unwrap_or_else(|e| {
+                errors.push(e);
+                DEFAULT_ADMIN_BIND_IP
+            }),
+            // 다른 값을 모두 읽은 뒤 채움
+            default_collection: Arc::default(),
+            collections: HashMap::new(),
+            validate_incoming_seed_id: get_bool(config, "validate_incoming_seed_id", false)
+                .collect_err(&mut errors),
+            max_url_bytes: get_uint(config, "max_url_bytes", 65536).collect_err(&mut errors),
+            doc_enrich_budget: Duration::from_millis(
+                get_uint(config, "doc_enrich_budget_ms", 1000).collect_err(&mut errors),
+            ),
+            field_max_bytes: get_uint_table(config, "field_max_bytes").collect_err(&mut errors),
+            // Regex는 Default가 없으므로 에러인 경우 직접 기본값을 넣음
+            seed_id_regex: get_parsed(
+                config,
+                "seed_id_regex",
+                Regex::new(DEFAULT_SEED_ID_REGEX).unwrap(),
+                |value| Regex::new(value).ok(),
+            )
+            .unwrap_or_else(|e| {
+                errors.push(e);
+                Regex::new(DEFAULT_SEED_ID_REGEX).unwrap()
+            }),
+        };
+        settings.default_collection = Arc::new(CollectionSettings::global(&settings));
+        settings.collections = read_collections(config, &settings.default_collection, &mut errors);
+        errors.extend(settings.validate());
+        if !errors.is_empty() {
+            return Err(ConfigErrors(errors));
+        }
+        Ok(settings)
+    }
+
+    /// 함께 사용해야 의미가 있는 값 확인
+    fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        if self.db_max_connections == 0 {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: db_max_connections must be greater than 0".to_string(),
+            ));
+        } else if self.db_max_connections < self.db_min_connections {
+            errors.push(ConfigError::Message(format!(
+                "INVALID_CONFIG: db_max_connections({}) < db_min_connections({})",
+                self.db_max_connections, self.db_min_connections
+            )));
+        }
+        if self.db_acquire_timeout.is_zero() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: db_acquire_timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        // 통계 task의 interval이 0이면 panic이 발생함
+        if self.stats_interval.is_zero() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: stats_interval_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.field_url.is_empty() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: field_url must not be empty".to_string(),
+            ));
+        }
+        if self.field_seed_id.as_str().is_empty() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: field_seed_id must not be empty".to_string(),
+            ));
+        }
+        if self.update_content_types.is_empty() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: update_content_types must not be empty".to_string(),
+            ));
+        }
+        // SQL에 그대로 넣으므로 schema.table 형식의 이름만 허용함
+        if !is_table_name(&self.seed_audit_table) {
+            errors.push(ConfigError::Message(format!(
+                "INVALID_CONFIG: seed_audit_table = {}",
+                self.seed_audit_table
+            )));
+        }
+        if self.seed_table.is_empty() || !is_table_name(&self.seed_table) {
+            errors.push(ConfigError::Message(format!(
+                "INVALID_CONFIG: seed_table = {}",
+                self.seed_table
+            )));
+        }
+        for (name, collection) in &self.collections {
+            // path에서 찾을 수 있는 이름만 허용함
+            if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                errors.push(ConfigError::Message(format!(
+                    "INVALID_CONFIG: collections.{} has an invalid collection name",
+                    name
+                )));
+            }
+            if collection.seed_table.is_empty() || !is_table_name(&collection.seed_table) {
+                errors.push(ConfigError::Message(format!(
+                    "INVALID_CONFIG: collections.{}.seed_table = {}",
+                    name, collection.seed_table
+                )));
+            }
+            if collection.seed_id_field.as_str().is_empty() {
+                errors.push(ConfigError::Message(format!(
+                    "INVALID_CONFIG: collections.{}.field_seed_id must not be empty",
+                    name
+                )));
+            }
+        }
+        if !self.redis_url.is_empty() && redis::Client::open(self.redis_url.as_str()).is_err() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: redis_url is not a redis url".to_string(),
+            ));
+        }
+        if self.admin_port != 0
+            && self.listen.iter().any(|listen| {
+                listen.port == self.admin_port
+                    && listen
+                        .ip
+                        .is_some_and(|ip| ip == self.admin_bind_ip || ip.is_unspecified())
+            })
+        {
+            errors.push(ConfigError::Message(format!(
+                "INVALID_CONFIG: admin_port({}) is already used by listen",
+                self.admin_port
+            )));
+        }
+        for url in &self.hedge_urls {
+            if !is_valid_url(url) {
+                errors.push(ConfigError::Message(format!(
+                    "INVALID_CONFIG: hedge_urls = {}",
+                    url
+                )));
+            }
+        }
+        if self.tls_cert_path.is_empty() != self.tls_key_path.is_empty() {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: tls_cert_path and tls_key_path must be set together".to_string(),
+            ));
+        }
+        if self.seed_id_cache_capacity == 0 {
+            errors.push(ConfigError::Message(
+                "INVALID_CONFIG: seed_id_cache_capacity must be greater than 0".to_string(),
+            ));
+        }
+        if !self.seed_id_cache_shards.is_power_of_two() {
+            errors.push(ConfigError::Message(format!(
+                "INVALID_CONFIG: seed_id_cache_shards({}) must be a power of two",
+                self.seed_id_cache_shards
+            )));
+        }
+        errors
+    }
+
+    /// reload로 읽은 new 중 실행 중 바꿀 수 있는 값만 적용한 설정을 반환함
+    /// <br>
+    /// 재시작해야 적용되는 값이 바뀐 경우 이전 값을 유지하고 해당 key 목록을 함께 반환함
+    pub fn reload(&self, new: Settings) -> (Settings, Vec<&'static str>) {
+        let mut applied = new;
+        let mut requires_restart = Vec::new();
+        macro_rules! keep {
+            ($($field:ident),* $(,)?) => {
+                $(
+                    if applied.$field != self.$field {
+                        requires_restart.push(stringify!($field));
+                        applied.$field = self.$field.clone();
+                    }
+                )*
+            };
+        }
+        // 시작할 때 만든 전역변수, task에서 사용하는 값
+        keep!(
+            max_concurrent_updates,
+            update_queue_wait,
+            max_concurrent_selects,
+            select_queue_wait,
+            preserve_host,
+            stats_interval,
+            stats_reset,
+            restart_on_panic,
+            max_restarts,
+            restart_backoff,
+            access_log,
+            log_roll,
+            log_roll_size_bytes,
+            log_keep_count,
+            db_max_connections,
+            db_min_connections,
+            db_idle_timeout,
+            db_max_lifetime,
+            db_acquire_timeout,
+            db_statement_cache,
+            solr_pool_max_idle_per_host,
+            solr_pool_idle_timeout,
+            solr_tcp_keepalive,
+            solr_http2,
+            solr_request_timeout,
+            verify_schema,
+            verify_schema_cores,
+            verify_schema_action,
+            verify_schema_timeout,
+            verify_seed_table,
+            create_seed_table,
+            hedge_urls,
+            hedge_after,
+            hedge_max_concurrent,
+            select_cache_ttl,
+            select_cache_max_bytes,
+            select_cache_max_entry_bytes,
+            select_cache_bust_param,
+            select_cache_flush_on_update,
+            seed_id_cache_capacity,
+            seed_id_cache_shards,
+            seed_lookup_failure_ttl,
+            max_new_seeds_per_minute,
+            redis_url,
+            redis_ttl,
+            redis_timeout,
+            otel_endpoint,
+            drain_updates,
+            tls_cert_path,
+            tls_key_path,
+            bind_interface,
+            prefer_ipv6,
+            listen,
+            admin_port,
+            admin_bind_ip,
+        );
+        (applied, requires_restart)
+    }
+
+    pub fn log_roll_config(&self) -> LogRollConfig {
+        LogRollConfig {
+            roll: self.log_roll,
+            size_bytes: self.log_roll_size_bytes,
+            keep_count: self.log_keep_count,
+        }
+    }
+
+    pub fn solr_client_config(&self) -> SolrClientConfig {
+        SolrClientConfig {
+            pool_max_idle_per_host: match self.solr_pool_max_idle_per_host {
+                0 => usize::MAX,
+                max => max,
+            },
+            pool_idle_timeout: self.solr_pool_idle_timeout,
+            tcp_keepalive: Some(self.solr_tcp_keepalive).filter(|keepalive| !keepalive.is_zero()),
+            http2: self.solr_http2,
+            request_timeout: self.solr_request_timeout,
+        }
+    }
+
+    /// update body를 받을 임시 파일의 디렉터리
+    pub fn spool_dir(&self) -> std::path::PathBuf {
+        match self.spool_dir.is_empty() {
+            true => std::env::temp_dir(),
+            false => std::path::PathBuf::from(&self.spool_dir),
+        }
+    }
+
+    pub fn hedge_config(&self) -> HedgeConfig {
+        HedgeConfig {
+            urls: self.hedge_urls.clone(),
+            after: self.hedge_after,
+            max_concurrent: self.hedge_max_concurrent,
+        }
+    }
+
+    /// listen과 admin_port로 만든 listener를 포함한 모든 listener
+    pub fn listeners(&self) -> Vec<ListenAddr> {
+        let mut listeners = self.listen.clone();
+        if self.admin_port != 0 {
+            listeners.push(ListenAddr::admin_port(self.admin_bind_ip, self.admin_port));
+        }
+        listeners
+    }
+
+    /// tls_cert_path, tls_key_path가 모두 설정된 경우 true
+    pub fn tls_enabled(&self) -> bool {
+        !self.tls_cert_path.is_empty() && !self.tls_key_path.is_empty()
+    }
+
+    /// path의 컬렉션 설정. collections에 없는 컬렉션은 전역 설정을 사용함
+    pub fn collection_for_path(&self, path: &str) -> Arc<CollectionSettings> {
+        route::collection_name(path)
+            .and_then(|name| self.collections.get(&name.to_ascii_lowercase()))
+            .unwrap_or(&self.default_collection)
+            .clone()
+    }
+
+    /// 한 update 요청에서 동시에 DB로 조회하는 seed_host 수
+    pub fn enrich_concurrency(&self) -> usize {
+        match self.enrich_concurrency {
+            0 => self.db_max_connections as usize,
+            concurrency => concurrency,
+        }
+    }
+
+    /// 통계 구간마다 새로 INSERT할 수 있는 seed_id 수. 0이면 제한하지 않음
+    pub fn seed_insert_limit(&self) -> u64 {
+        if self.max_new_seeds_per_minute == 0 {
+            return 0;
+        }
+        let per_interval =
+            self.max_new_seeds_per_minute as f64 * self.stats_interval.as_secs_f64() / 60f64;
+        (per_interval.ceil() as u64).max(1)
+    }
+
+    pub fn select_cache_config(&self) -> SelectCacheConfig {
+        SelectCacheConfig {
+            ttl: self.select_cache_ttl,
+            max_bytes: self.select_cache_max_bytes,
+            max_entry_bytes: self.select_cache_max_entry_bytes,
+            bust_param: self.select_cache_bust_param.clone(),
+            flush_on_update: self.select_cache_flush_on_update,
+        }
+    }
+
+    pub fn cors_config(&self) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: self.cors_allowed_origins.clone(),
+            allow_update: self.cors_allow_update,
+            max_age: self.cors_max_age,
+        }
+    }
+
+    pub fn update_methods(&self) -> &'static [Method] {
+        if self.update_allow_put {
+            method_rule::UPDATE_METHODS_WITH_PUT
+        } else {
+            method_rule::UPDATE_METHODS
+        }
+    }
+
+    pub fn db_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            count: self.db_retry_count,
+            base: Duration::from_millis(50),
+            max: self.db_retry_max,
+        }
+    }
+
+    pub fn restart_policy(&self) -> RestartPolicy {
+        RestartPolicy {
+            enabled: self.restart_on_panic,
+            max_restarts: self.max_restarts,
+            backoff: self.restart_backoff,
+        }
+    }
+
+    pub fn read_limit(&self) -> ReadLimit {
+        ReadLimit {
+            max_docs: self.max_docs_per_update,
+            max_fields_per_doc: self.max_fields_per_doc,
+        }
+    }
+
+    /// force_commit_within이 0이면 None
+    pub fn commit_policy(&self) -> Option<CommitPolicy> {
+        (!self.force_commit_within.is_zero()).then_some(CommitPolicy {
+            commit_within: self.force_commit_within,
+            allow_commit: self.allow_client_commit,
+        })
+    }
+
+    pub fn split_limit(&self) -> SplitLimit {
+        SplitLimit {
+            max_docs: self.split_threshold_docs,
+            max_bytes: self.split_threshold_bytes,
+        }
+    }
+}
+
+/// config 확인 중 발견한 에러 목록
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// 에러를 errors에 모으고 기본값으로 계속 확인함. 잘못된 값을 한번에 모두 보여주기 위해 사용
+trait CollectError<T> {
+    fn collect_err(self, errors: &mut Vec<ConfigError>) -> T;
+}
+
+impl<T: Default> CollectError<T> for Result<T, ConfigError> {
+    fn collect_err(self, errors: &mut Vec<ConfigError>) -> T {
+        self.unwrap_or_else(|e| {
+            errors.push(e);
+            T::default()
+        })
+    }
+}
+
+/// 서버 시작 전 config 전체를 확인함. 잘못된 값이 있으면 모든 에러를 모아서 반환함
+/// <br>
+/// 기본값이 없는 솔라 주소, DB 접속 정보도 함께 확인함. env는 환경변수를 읽는 함수
+pub fn validate_config(
+    config: &Config,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Settings, ConfigErrors> {
+    let mut errors = Vec::new();
+
+    match config.get_string("solr_kr") {
+        Ok(solr_url) => {
+            if !is_valid_url(&solr_url) {
+                errors.push(ConfigError::Message(format!(
+                    "INVALID_CONFIG: solr_kr = {}",
+                    solr_url
+                )));
+            }
+        }
+        Err(e) => errors.push(e),
+    }
+    for key in ["db_host", "db_user", "db_schema"] {
+        if let Err(e) = config.get_string(key) {
+            errors.push(e);
+        }
+    }
+    if let Err(e) = secret::get_secret(config, "db_pwd", &env) {
+        errors.push(e);
+    }
+    if let Err(e) = crate::solr::upstream_headers(config, &env) {
+        errors.push(e);
+    }
+
+    match Settings::from_config(config) {
+        Ok(settings) if errors.is_empty() => Ok(settings),
+        Ok(_) => Err(ConfigErrors(errors)),
+        Err(ConfigErrors(settings_errors)) => {
+            errors.extend(settings_errors);
+            Err(ConfigErrors(errors))
+        }
+    }
+}
+
+/// SQL에 그대로 넣을 수 있는 schema.table 형식의 이름인지 확인함. 빈 문자열은 true
+pub(crate) fn is_table_name(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// scheme과 host가 있는 url인지 확인함
+fn is_valid_url(url: &str) -> bool {
+    url.parse::<Uri>()
+        .map(|uri| uri.scheme().is_some() && uri.authority().is_some())
+        .unwrap_or(false)
+}
+
+/// 형식이 잘못된 값의 에러에 key를 붙임
+fn invalid_type(key: &str, e: ConfigError) -> ConfigError {
+    ConfigError::Message(format!("INVALID_CONFIG: {}, {}", key, e))
+}
+
+/// key가 없는 경우 default를 반환하며, 값이 있지만 잘못된 경우 에러 반환
+fn get_uint<T: TryFrom<i64>>(config: &Config, key: &str, default: T) -> Result<T, ConfigError> {
+    match config.get_int(key) {
+        Ok(value) => T::try_from(value)
+            .map_err(|_| ConfigError::Message(format!("INVALID_CONFIG: {} = {}", key, value))),
+        Err(ConfigError::NotFound(_)) => Ok(default),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+/// 문자열 값을 parse로 변환함. 변환할 수 없는 값인 경우 에러 반환
+fn get_parsed<T>(
+    config: &Config,
+    key: &str,
+    default: T,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<T, ConfigError> {
+    match config.get_string(key) {
+        Ok(value) => parse(&value)
+            .ok_or_else(|| ConfigError::Message(format!("INVALID_CONFIG: {} = {}", key, value))),
+        Err(ConfigError::NotFound(_)) => Ok(default),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+fn get_bool(config: &Config, key: &str, default: bool) -> Result<bool, ConfigError> {
+    match config.get_bool(key) {
+        Ok(value) => Ok(value),
+        Err(ConfigError::NotFound(_)) => Ok(default),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+fn get_string(config: &Config, key: &str, default: &str) -> Result<String, ConfigError> {
+    match config.get_string(key) {
+        Ok(value) => Ok(value),
+        Err(ConfigError::NotFound(_)) => Ok(default.to_string()),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+fn get_f64(config: &Config, key: &str, default: f64) -> Result<f64, ConfigError> {
+    match config.get_float(key) {
+        Ok(value) if value >= 0f64 => Ok(value),
+        Ok(value) => Err(ConfigError::Message(format!(
+            "INVALID_CONFIG: {} = {}",
+            key, value
+        ))),
+        Err(ConfigError::NotFound(_)) => Ok(default),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+/// 문자열 목록을 읽음. key가 없는 경우 빈 목록
+fn get_string_list(config: &Config, key: &str) -> Result<Vec<String>, ConfigError> {
+    get_string_list_or(config, key, &[])
+}
+
+/// 문자열 목록을 읽음. key가 없는 경우 default 목록
+fn get_string_list_or(
+    config: &Config,
+    key: &str,
+    default: &[&str],
+) -> Result<Vec<String>, ConfigError> {
+    match config.get_array(key) {
+        Ok(values) => values
+            .into_iter()
+            .map(|value| value.into_string())
+            .collect(),
+        Err(ConfigError::NotFound(_)) => Ok(default.iter().map(|s| s.to_string()).collect()),
+        Err(e) => Err(invalid_type(key, e)),
+    }
+}
+
+/// 이름과 문자열 값의 table을 이름 순서로 읽음. key가 없는 경우 빈 목록
+fn get_string_table(config: &Config, key: &str) -> Result<Vec<(String, String)>, ConfigError> {
+    let table = match config.get_table(key) {
+        Ok(table) => table,
+        Err(ConfigError::NotFound(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(invalid_type(key, e)),
+    };
+    let mut values = table
+        .into_iter()
+        .map(|(name, value)| {
+            let value = value
+                .into_string()
+                .map_err(|e| invalid_type(&format!("{}.{}", key, name), e))?;
+            Ok((name, value))
+        })
+        .collect::<Result<Vec<_>, ConfigError>>()?;
+    values.sort();
+    Ok(values)
+}
+
+/// 이름과 0 이상의 정수 값의 table을 이름 순서로 읽음. key가 없는 경우 빈 목록
+fn get_uint_table(config: &Config, key: &str) -> Result<Vec<(String, u64)>, ConfigError> {
+    get_string_table(config, key)?
+        .into_iter()
+        .map(|(name, value)| match value.parse() {
+            Ok(cap) => Ok((name, cap)),
+            Err(_) => Err(ConfigError::Message(format!(
+                "INVALID_CONFIG: {}.{} = {}",
+                key, name, value
+            ))),
+        })
+        .collect()
+}
+
+fn get_ip_list(config: &Config, key: &str) -> Result<Vec<IpAddr>, ConfigError> {
+    get_string_list(config, key)?
+        .into_iter()
+        .map(|ip| {
+            ip.trim()
+                .parse()
+                .map_err(|_| ConfigError::Message(format!("INVALID_CONFIG: {} = {}", key, ip)))
+        })
+        .collect()
+}
+
+/// 서버가 받을 주소 목록. 빈 목록이나 같은 주소가 두 번 있는 경우 에러
+fn get_listen_list(config: &Config, key: &str) -> Result<Vec<ListenAddr>, ConfigError> {
+    let values = get_string_list_or(config, key, &[DEFAULT_LISTEN])?;
+    let invalid =
+        |value: &str| ConfigError::Message(format!("INVALID_CONFIG: {} = {}", key, value));
+    if values.is_empty() {
+        return Err(invalid("[]"));
+    }
+    let mut listen: Vec<ListenAddr> = Vec::with_capacity(values.len());
+    for value in &values {
+        let listen_addr: ListenAddr = value.trim().parse().map_err(|_| invalid(value))?;
+        if listen
+            .iter()
+            .any(|other| (other.ip, other.port) == (listen_addr.ip, listen_addr.port))
+        {
+            return Err(invalid(value));
+        }
+        listen.push(listen_addr);
+    }
+    Ok(listen)
+}
+
+fn get_ip_allow_list(config: &Config, key: &str) -> Result<IpAllowList, ConfigError> {
+    IpAllowList::parse(&get_string_list(config, key)?)
+        .map_err(|value| ConfigError::Message(format!("INVALID_CONFIG: {} = {}", key, value)))
+}
+
+#[test]
+fn db_pool_settings_test() {
+    let config_with = |values: &[(&str, i64)]| {
+        let mut builder = Config::builder();
+        for (key, value) in values {
+            builder = builder.set_override(*key, *value).unwrap();
+        }
+        builder.build().unwrap()
+    };
+
+    // 값이 없으면 기존 값을 사용함
+    let settings = Settings::from_config(&config_with(&[])).unwrap();
+    assert_eq!(settings.db_max_connections, 10);
+    assert_eq!(settings.db_min_connections, 0);
+    assert_eq!(settings.db_idle_timeout, Duration::from_secs(600));
+    assert_eq!(settings.db_max_lifetime, Duration::from_secs(1800));
+    assert_eq!(settings.db_acquire_timeout, Duration::from_secs(300));
+    assert_eq!(settings.db_statement_cache, 100);
+
+    let settings = Settings::from_config(&config_with(&[
+        ("db_max_connections", 32),
+        ("db_min_connections", 4),
+        ("db_acquire_timeout_secs", 3),
+    ]))
+    .unwrap();
+    assert_eq!(settings.db_max_connections, 32);
+    assert_eq!(settings.db_min_connections, 4);
+    assert_eq!(settings.db_acquire_timeout, Duration::from_secs(3));
+
+    for (values, expected) in [
+        (
+            vec![("db_max_connections", 2), ("db_min_connections", 4)],
+            "db_max_connections(2) < db_min_connections(4)",
+        ),
+        (vec![("db_max_connections", 0)], "db_max_connections"),
+        (
+            vec![("db_acquire_timeout_secs", 0)],
+            "db_acquire_timeout_secs",
+        ),
+        (vec![("db_max_connections", -1)], "db_max_connections"),
+    ] {
+        let err = Settings::from_config(&config_with(&values)).unwrap_err();
+        assert!(err.to_string().contains(expected), "{}", err);
+    }
+}
+
+#[test]
+fn validate_config_test() {
+    use config::{File, FileFormat};
+
+    let config_from = |toml: &str| {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+    };
+    let no_env = |_: &str| None;
+
+    let config = config_from(
+        r#"
+        solr_kr = "http://localhost:8983"
+        db_host = "localhost"
+        db_user = "proxy"
+        db_pwd = "pwd"
+        db_schema = "seed"
+        "#,
+    );
+    let settings = validate_config(&config, no_env).unwrap();
+    assert_eq!(settings.seed_id_cache_capacity, 100_000);
+    let config = config_from("seed_id_cache_capacity = 5000");
+    assert_eq!(
+        Settings::from_config(&config)
+            .unwrap()
+            .seed_id_cache_capacity,
+        5000
+    );
+    // listen이 없으면 자동으로 찾은 ip의 3000 port
+    assert_eq!(settings.listen, [DEFAULT_LISTEN.parse().unwrap()]);
+
+    // 잘못된 값을 모두 모아서 반환함
+    let config = config_from(
+        r#"
+        solr_kr = "not a url"
+        db_host = "localhost"
+        max_body_bytes = -1
+        stats_reset = "maybe"
+        log_roll = "weekly"
+        db_max_connections = 0
+        db_acquire_timeout_secs = 0
+        stats_interval_secs = 0
+        update_allowed_ips = ["10.0.0.0/8", "10.0.0.300"]
+        seed_id_cache_shards = 12
+        seed_id_cache_capacity = 0
+        seed_audit_table = "audit; DROP TABLE x"
+        seed_id_regex = "[0-9"
+        redis_url = "localhost:6379"
+        tls_cert_path = "cert.pem"
+        listen = ["auto:3000", "127.0.0.1:3000 admin", "localhost:3001"]
+        "#,
+    );
+    let errors = validate_config(&config, no_env).unwrap_err();
+    let messages: Vec<String> = errors.0.iter().map(|e| e.to_string()).collect();
+    for expected in [
+        "solr_kr = not a url",
+        "db_user",
+        "db_schema",
+        "db_pwd",
+        "max_body_bytes = -1",
+        "stats_reset",
+        "log_roll = weekly",
+        "db_max_connections must be greater than 0",
+        "db_acquire_timeout_secs",
+        "stats_interval_secs must be greater than 0",
+        "update_allowed_ips = 10.0.0.300",
+        "seed_id_cache_shards(12) must be a power of two",
+        "seed_id_cache_capacity must be greater than 0",
+        "seed_audit_table = audit; DROP TABLE x",
+        "seed_id_regex = [0-9",
+        "redis_url is not a redis url",
+        "tls_cert_path and tls_key_path must be set together",
+        "listen = localhost:3001",
+    ] {
+        assert!(
+            messages.iter().any(|message| message.contains(expected)),
+            "{}: {:?}",
+            expected,
+            messages
+        );
+    }
+    assert_eq!(messages.len(), 18, "{:?}", messages);
+    assert_eq!(errors.to_string().lines().count(), 18);
+
+    // 환경변수의 비밀번호도 사용함
+    let config = config_from(
+        r#"
+        solr_kr = "http://localhost:8983"
+        db_host = "localhost"
+        db_user = "proxy"
+        db_schema = "seed"
+        "#,
+    );
+    assert!(validate_config(&config, no_env).is_err());
+    let env = |name: &str| (name == "SOLR_PROXY_DB_PWD").then(|| "pwd".to_string());
+    assert!(validate_config(&config, env).is_ok());
+
+    // flag만 다른 같은 주소는 사용할 수 없음
+    let config = config_from(r#"listen = ["127.0.0.1:3000", "127.0.0.1:3000 admin"]"#);
+    let messages = Settings::from_config(&config).unwrap_err().to_string();
+    assert!(
+        messages.contains("listen = 127.0.0.1:3000 admin"),
+        "{}",
+        messages
+    );
+    let config = config_from(r#"listen = []"#);
+    assert!(Settings::from_config(&config).is_err());
+
+    // admin_port는 기본적으로 127.0.0.1에서 받음
+    let config = config_from(
+        r#"
+        listen = ["auto:3000", "127.0.0.1:3000"]
+        admin_port = 3001
+        "#,
+    );
+    let settings = Settings::from_config(&config).unwrap();
+    assert_eq!(
+        settings.listeners().last(),
+        Some(&ListenAddr::admin_port(DEFAULT_ADMIN_BIND_IP, 3001))
+    );
+    assert_eq!(settings.listeners().len(), 3);
+    let config = config_from(
+        r#"
+        listen = ["0.0.0.0:3000"]
+        admin_port = 3000
+        admin_bind_ip = "10.0.0.7"
+        "#,
+    );
+    let messages = Settings::from_config(&config).unwrap_err().to_string();
+    assert!(messages.contains("admin_port(3000) is already used by listen"));
+}
+
+#[test]
+fn collection_settings_test() {
+    use config::{File, FileFormat};
+
+    let config_from = |toml: &str| {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+    };
+
+    let config = config_from(
+        r#"
+        required_fields = ["id", "url"]
+        fill_host_fields = true
+        field_url = "doc_url"
+
+        [field_max_bytes]
+        content = 100000
+
+        [collections.ko]
+
+        [collections.news]
+        field_url = "link"
+        field_seed_id = "channel_id"
+
+        [collections.ja]
+        seed_table = "crawlerdb.t_channel_contents_map_ja"
+        cache_namespace = "jp"
+        required_fields = ["id"]
+        fill_host_fields = false
+        normalize_dates = true
+        doc_enrich_budget_ms = 50
+
+        [collections.ja.field_max_bytes]
+        title = 1000
+
+        [[collections.ja.host_rules]]
+        prefix = "ameblo.jp"
+        capture_regex = "^(ameblo\\.jp/[^/]+)"
+        "#,
+    );
+    let settings = Settings::from_config(&config).unwrap();
+
+    // 설정하지 않은 값은 전역 설정을 사용하고, cache_namespace는 컬렉션 이름
+    let ko = settings.collection_for_path("/solr/ko/update");
+    assert_eq!(ko.name, "ko");
+    assert_eq!(ko.seed_table, DEFAULT_SEED_TABLE);
+    // 시작할 때 확인하는 seed table은 중복을 제거함
+    assert_eq!(
+        crate::seed_table::seed_tables(&settings),
+        [DEFAULT_SEED_TABLE, "crawlerdb.t_channel_contents_map_ja"]
+    );
+    assert_eq!(ko.cache_key("cafe.naver.com/abc"), "ko:cafe.naver.com/abc");
+    assert_eq!(ko.required_fields, ["id", "url"]);
+    assert!(ko.fill_host_fields);
+    assert_eq!(ko.host_rules.len(), settings.host_rules.len());
+    assert_eq!(ko.max_url_bytes, 65536);
+    assert_eq!(ko.doc_enrich_budget, Duration::from_secs(1));
+    assert_eq!(ko.field_max_bytes, [("content".to_string(), 100000)]);
+    // field_url이 seed_url_fields의 기본값
+    assert_eq!(ko.seed_url_fields, ["doc_url"]);
+    assert_eq!(ko.seed_id_field.as_str(), "seed_id");
+
+    let news = settings.collection_for_path("/solr/news/update");
+    assert_eq!(news.seed_url_fields, ["link"]);
+    assert_eq!(news.seed_id_field.as_str(), "channel_id");
+    // 바꾼 seed_id 필드도 기본 single_valued_fields로 사용함
+    assert_eq!(news.single_valued_fields, ["id", "channel_id"]);
+    assert_eq!(ko.single_valued_fields, ["id", "seed_id"]);
+
+    let ja = settings.collection_for_path("/solr/JA/update");
+    assert_eq!(ja.seed_table, "crawlerdb.t_channel_contents_map_ja");
+    assert_eq!(ja.cache_key("ameblo.jp/abc"), "jp:ameblo.jp/abc");
+    assert_eq!(ja.required_fields, ["id"]);
+    assert!(!ja.fill_host_fields);
+    assert!(ja.normalize_dates);
+    assert_eq!(ja.doc_enrich_budget, Duration::from_millis(50));
+    assert_eq!(ja.field_max_bytes, [("title".to_string(), 1000)]);
+    assert_eq!(ja.host_rules.len(), 1);
+    assert!(ja.media_type_rules.is_empty());
+
+    // collections에 없는 컬렉션은 전역 설정이며 cache key는 seed_host 그대로
+    let other = settings.collection_for_path("/solr/en/update");
+    assert!(other.name.is_empty());
+    assert_eq!(other.cache_key("cafe.naver.com/abc"), "cafe.naver.com/abc");
+    assert!(!settings.normalize_dates);
+    assert!(!settings.collection_for_path("/update").normalize_dates);
+
+    // 전역 field_seed_id를 바꾼 경우에도 기본 single_valued_fields는 그 필드를 사용함
+    let settings = Settings::from_config(&config_from(r#"field_seed_id = "channel_id""#)).unwrap();
+    assert_eq!(settings.single_valued_fields, ["id", "channel_id"]);
+    assert_eq!(
+        settings.default_collection.single_valued_fields,
+        ["id", "channel_id"]
+    );
+
+    let config = config_from(
+        r#"
+        field_seed_id = ""
+
+        [collections.ja]
+        seed_table = "t; DROP TABLE x"
+        required_fields_action = "ignore"
+        "#,
+    );
+    let messages = Settings::from_config(&config).unwrap_err().to_string();
+    assert!(messages.contains("INVALID_CONFIG: field_seed_id must not be empty"));
+    assert!(messages.contains("collections.ja.seed_table = t; DROP TABLE x"));
+    assert!(messages.contains("collections.ja.required_fields_action = ignore"));
+}
+
+#[test]
+fn select_param_settings_test() {
+    use config::{File, FileFormat};
+
+    let config_from = |toml: &str| {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+    };
+
+    let settings = Settings::from_config(&config_from("")).unwrap();
+    assert!(settings.select_default_params.is_empty());
+    assert_eq!(
+        settings.update_content_types,
+        content_type::DEFAULT_UPDATE_CONTENT_TYPES
+    );
+    assert!(!settings.update_content_type_strict);
+    assert!(!settings.split_limit().is_enabled());
+    assert!(!settings.verify_seed_table && !settings.create_seed_table);
+    assert!(settings.select_param_caps.is_empty());
+
+    // 숫자, bool 값도 문자열로 읽고 이름 순서로 정렬함
+    let config = config_from(
+        r#"
+        [select_default_params]
+        timeAllowed = 5000
+        omitHeader = false
+        rows = "100"
+
+        [select_param_caps]
+        rows = 1000
+        "#,
+    );
+    let settings = Settings::from_config(&config).unwrap();
+    assert_eq!(
+        settings.select_default_params,
+        [
+            ("omitHeader".to_string(), "false".to_string()),
+            ("rows".to_string(), "100".to_string()),
+            ("timeAllowed".to_string(), "5000".to_string()),
+        ]
+    );
+    assert_eq!(settings.select_param_caps, [("rows".to_string(), 1000)]);
+
+    let config = config_from(
+        r#"
+        select_default_params = "rows=10"
+        update_content_types = []
+
+        [select_param_caps]
+        rows = -1
+        "#,
+    );
+    let messages = Settings::from_config(&config).unwrap_err().to_string();
+    assert!(messages.contains("select_default_params"), "{}", messages);
+    assert!(messages.contains("update_content_types"), "{}", messages);
+    assert!(
+        messages.contains("select_param_caps.rows = -1"),
+        "{}",
+        messages
+    );
+}
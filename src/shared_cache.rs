@@ -0,0 +1,178 @@
+use crate::util::StrError;
+use crate::BoxedError;
+use log::{info, warn};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// 연결할 수 없는 동안 GET, SET을 Redis로 보내지 않고 다시 확인할 때까지 기다리는 시간
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 여러 proxy가 함께 사용하는 seed_id cache
+pub trait SharedSeedCache {
+    fn get(
+        &self,
+        seed_host: &str,
+    ) -> impl Future<Output = Result<Option<String>, BoxedError>> + Send;
+
+    fn set(
+        &self,
+        seed_host: &str,
+        seed_id: &str,
+    ) -> impl Future<Output = Result<(), BoxedError>> + Send;
+
+    fn del(&self, seed_host: &str) -> impl Future<Output = Result<(), BoxedError>> + Send;
+}
+
+/// Redis를 사용하는 공유 cache. key는 "seed:<seed_host>"
+/// <br>
+/// 처음 사용할 때 연결하며, 연결이 끊어진 경우 ConnectionManager가 다시 연결함
+pub struct RedisSeedCache {
+    client: redis::Client,
+    conn: OnceCell<ConnectionManager>,
+    /// 저장한 seed_id를 유지할 시간
+    ttl: Duration,
+    /// 연결, 명령마다 기다릴 최대 시간. 넘으면 에러로 보고 DB에서 조회함
+    timeout: Duration,
+    /// 마지막 명령이 성공했는지 여부. 상태가 바뀔 때만 로그를 남김
+    healthy: AtomicBool,
+    /// 연결할 수 없는 동안 마지막으로 Redis에 보낸 시간. retry_interval마다 요청 하나만 보내 회복했는지 확인함
+    last_attempt: Mutex<Option<Instant>>,
+    retry_interval: Duration,
+}
+
+impl RedisSeedCache {
+    pub fn new(url: &str, ttl: Duration, timeout: Duration) -> Result<Self, redis::RedisError> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            conn: OnceCell::new(),
+            ttl,
+            timeout,
+            healthy: AtomicBool::new(true),
+            last_attempt: Mutex::new(None),
+            retry_interval: RETRY_INTERVAL,
+        })
+    }
+
+    /// 연결할 수 없는 동안 Redis로 보내지 않는 경우 true. retry_interval이 지난 요청 하나는 보냄
+    fn skip_while_unhealthy(&self) -> bool {
+        if self.healthy.load(Ordering::Relaxed) {
+            return false;
+        }
+        let mut last_attempt = self.last_attempt.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if last_attempt.is_some_and(|at| now.saturating_duration_since(at) < self.retry_interval) {
+            return true;
+        }
+        *last_attempt = Some(now);
+        false
+    }
+
+    /// GET, SET은 연결할 수 없는 동안 timeout을 기다리지 않고 곧바로 실패함
+    async fn run_if_healthy<T, F, Fut>(&self, op: F) -> Result<T, BoxedError>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = redis::RedisResult<T>>,
+    {
+        if self.skip_while_unhealthy() {
+            return Err(Box::new(StrError::new(
+                "REDIS_UNAVAILABLE: skipped until retry".to_string(),
+            )));
+        }
+        self.run(op).await
+    }
+
+    /// timeout 안에 연결하고 op를 실행함
+    async fn run<T, F, Fut>(&self, op: F) -> Result<T, BoxedError>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = redis::RedisResult<T>>,
+    {
+        let result = tokio::time::timeout(self.timeout, async {
+            let conn = self
+                .conn
+                .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+                .await?;
+            op(conn.clone()).await
+        })
+        .await;
+        let result: Result<T, BoxedError> = match result {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(Box::new(e)),
+            Err(_) => Err(Box::new(StrError::new(format!(
+                "REDIS_TIMEOUT: {}ms",
+                self.timeout.as_millis()
+            )))),
+        };
+
+        match &result {
+            Ok(_) if !self.healthy.swap(true, Ordering::Relaxed) => info!("REDIS_RECOVERED"),
+            Err(e) if self.healthy.swap(false, Ordering::Relaxed) => {
+                *self.last_attempt.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+                warn!("REDIS_UNAVAILABLE: {}. use DB until redis recovers", e)
+            }
+            _ => {}
+        }
+        result
+    }
+}
+
+fn key(seed_host: &str) -> String {
+    format!("seed:{}", seed_host)
+}
+
+impl SharedSeedCache for RedisSeedCache {
+    async fn get(&self, seed_host: &str) -> Result<Option<String>, BoxedError> {
+        let key = key(seed_host);
+        self.run_if_healthy(|mut conn| async move { conn.get(key).await })
+            .await
+    }
+
+    async fn set(&self, seed_host: &str, seed_id: &str) -> Result<(), BoxedError> {
+        let key = key(seed_host);
+        let seed_id = seed_id.to_string();
+        let ttl = self.ttl.as_secs().max(1);
+        self.run_if_healthy(|mut conn| async move { conn.set_ex(key, seed_id, ttl).await })
+            .await
+    }
+
+    async fn del(&self, seed_host: &str) -> Result<(), BoxedError> {
+        let key = key(seed_host);
+        self.run(|mut conn| async move { conn.del(key).await })
+            .await
+    }
+}
+
+#[tokio::test]
+async fn redis_unavailable_test() {
+    // 연결할 수 없는 경우 timeout 안에 에러를 반환함
+    let mut cache = RedisSeedCache::new(
+        "redis://127.0.0.1:1/",
+        Duration::from_secs(60),
+        Duration::from_millis(200),
+    )
+    .unwrap();
+    let start = std::time::Instant::now();
+    assert!(cache.get("host").await.is_err());
+    assert!(!cache.healthy.load(Ordering::Relaxed));
+
+    // 연결할 수 없는 동안 GET, SET은 timeout을 기다리지 않고 곧바로 실패함
+    let skipped = std::time::Instant::now();
+    let err = cache.set("host", "seed").await.unwrap_err();
+    assert!(err.to_string().starts_with("REDIS_UNAVAILABLE"));
+    assert!(cache.get("host").await.is_err());
+    assert!(skipped.elapsed() < Duration::from_millis(100));
+
+    // retry_interval이 지나면 다시 Redis로 보내 회복했는지 확인함
+    cache.retry_interval = Duration::from_millis(10);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let err = cache.get("host").await.unwrap_err();
+    assert!(!err.to_string().contains("skipped"));
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    assert!(RedisSeedCache::new("not a url", Duration::ZERO, Duration::ZERO).is_err());
+}
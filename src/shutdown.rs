@@ -0,0 +1,272 @@
+use log::info;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// 서버가 종료된 이유
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// SIGTERM, SIGINT 등 운영자가 종료를 요청함
+    Signal,
+    /// 요청 처리 밖에서 panic이 발생함
+    Panic,
+    /// hyper 서버가 에러로 종료됨
+    ServerError,
+}
+
+/// panic 등으로 서버가 종료된 경우 다시 시작하는 방식
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// false인 경우 다시 시작하지 않고 프로세스를 종료함
+    pub enabled: bool,
+    /// 프로세스 실행 후 다시 시작할 수 있는 최대 횟수
+    pub max_restarts: u32,
+    /// 처음 다시 시작하기 전 기다릴 시간. 다시 시작할 때마다 2배로 늘어남
+    pub backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// reason으로 종료된 뒤 다시 시작하기 전 기다릴 시간. 다시 시작하지 않는 경우 None
+    /// <br>
+    /// restart_cnt는 지금까지 다시 시작한 횟수
+    pub fn restart_delay(&self, reason: ShutdownReason, restart_cnt: u32) -> Option<Duration> {
+        if !self.enabled || reason == ShutdownReason::Signal || restart_cnt >= self.max_restarts {
+            return None;
+        }
+        Some(
+            self.backoff
+                .saturating_mul(2u32.saturating_pow(restart_cnt)),
+        )
+    }
+}
+
+/// 종료 요청 여부와 처리중인 요청 수
+/// <br>
+/// 종료가 시작된 뒤 들어온 요청은 enter()가 None을 반환하므로 503으로 응답함
+pub struct Drain {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    started: Notify,
+    reason: std::sync::Mutex<Option<ShutdownReason>>,
+}
+
+/// 처리중인 요청 수를 세기 위한 guard. drop될 때 in_flight를 감소시킴
+pub struct InFlightGuard<'a>(&'a Drain);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drain {
+    pub fn new() -> Self {
+        Self {
+            draining: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            started: Notify::new(),
+            reason: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// 현재 처리중인 요청 수
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// 요청 처리를 시작함. 종료가 시작된 경우 None
+    /// <br>
+    /// 반환된 guard가 drop될 때까지 처리중인 요청으로 셈
+    pub fn enter(&self) -> Option<InFlightGuard<'_>> {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        let guard = InFlightGuard(self);
+        if self.is_draining() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    /// 종료를 시작함. 이미 시작된 경우 false
+    pub fn start(&self, reason: ShutdownReason) -> bool {
+        if self.draining.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        *self.reason.lock().unwrap() = Some(reason);
+        self.started.notify_waiters();
+        true
+    }
+
+    /// 종료를 시작한 이유. 시작하지 않은 경우 None
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        *self.reason.lock().unwrap()
+    }
+
+    /// 서버를 다시 시작할 때 사용. 이전 서버에서 처리중인 요청 수는 유지함
+    pub fn reset(&self) {
+        *self.reason.lock().unwrap() = None;
+        self.draining.store(false, Ordering::Release);
+    }
+
+    /// 종료가 시작될 때까지 기다림
+    pub async fn wait_started(&self) {
+        loop {
+            let notified = self.started.notified();
+            if self.is_draining() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// 프로세스 종료 요청 여부
+/// <br>
+/// 서버를 다시 시작하기 전 기다리는 중에는 종료를 요청할 서버가 없으므로, 받은 signal을 서버와 별개로 기록함
+pub struct ShutdownRequest {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Default for ShutdownRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShutdownRequest {
+    pub fn new() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            notify: Notify::new(),
+        }
+    }
+
+    /// 종료를 요청함. 다시 시작하지 않고 되돌릴 수 없음
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Release);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// 종료가 요청될 때까지 기다림
+    pub async fn wait(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// SIGTERM, SIGINT를 받을 때까지 기다림. unix가 아닌 경우 ctrl_c
+pub async fn wait_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).expect("SIGTERM handler FAIL");
+        let mut sigint = signal(SignalKind::interrupt()).expect("SIGINT handler FAIL");
+        tokio::select! {
+            _ = sigterm.recv() => info!("SIGTERM received"),
+            _ = sigint.recv() => info!("SIGINT received"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.expect("ctrl_c handler FAIL");
+        info!("ctrl_c received");
+    }
+}
+
+#[tokio::test]
+async fn drain_test() {
+    let drain = Drain::new();
+    assert!(!drain.is_draining());
+
+    let first = drain.enter().unwrap();
+    let second = drain.enter().unwrap();
+    assert_eq!(drain.in_flight(), 2);
+
+    // 종료를 기다리던 쪽이 깨어남
+    let (_, started) = tokio::join!(drain.wait_started(), async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drain.start(ShutdownReason::Panic)
+    });
+    assert!(started);
+    assert!(!drain.start(ShutdownReason::Signal));
+    assert_eq!(drain.reason(), Some(ShutdownReason::Panic));
+    // 이미 시작된 경우 바로 끝남
+    drain.wait_started().await;
+
+    // 종료가 시작된 뒤의 요청은 받지 않고, 처리중인 요청으로 세지도 않음
+    assert!(drain.enter().is_none());
+    assert_eq!(drain.in_flight(), 2);
+
+    drop(first);
+    assert_eq!(drain.in_flight(), 1);
+    drop(second);
+    assert_eq!(drain.in_flight(), 0);
+
+    // 다시 시작한 뒤에는 요청을 받음
+    drain.reset();
+    assert_eq!(drain.reason(), None);
+    assert!(drain.enter().is_some());
+}
+
+#[test]
+fn restart_delay_test() {
+    let policy = RestartPolicy {
+        enabled: true,
+        max_restarts: 3,
+        backoff: Duration::from_secs(5),
+    };
+    assert_eq!(
+        policy.restart_delay(ShutdownReason::Panic, 0),
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(
+        policy.restart_delay(ShutdownReason::ServerError, 2),
+        Some(Duration::from_secs(20))
+    );
+    // 최대 횟수를 넘은 경우, 운영자가 종료한 경우 다시 시작하지 않음
+    assert_eq!(policy.restart_delay(ShutdownReason::Panic, 3), None);
+    assert_eq!(policy.restart_delay(ShutdownReason::Signal, 0), None);
+
+    let disabled = RestartPolicy {
+        enabled: false,
+        ..policy
+    };
+    assert_eq!(disabled.restart_delay(ShutdownReason::Panic, 0), None);
+}
+
+#[tokio::test]
+async fn shutdown_request_test() {
+    let request = ShutdownRequest::new();
+    assert!(!request.is_requested());
+
+    // 기다리던 쪽이 깨어나고, 이후에 기다리면 바로 끝남
+    tokio::join!(request.wait(), async {
+        tokio::task::yield_now().await;
+        request.request();
+    });
+    assert!(request.is_requested());
+    tokio::time::timeout(Duration::from_secs(1), request.wait())
+        .await
+        .unwrap();
+}
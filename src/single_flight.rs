@@ -0,0 +1,98 @@
+use hashbrown::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// 같은 key를 동시에 조회하는 요청들이 하나의 조회 결과를 함께 사용하게 함
+/// <br>
+/// 처음 들어온 요청만 조회하고 나머지는 그 결과를 기다림. 조회가 끝나면 key를 지우므로 결과를 저장해두지는 않음.
+/// 에러는 함께 사용하지 않으며, 기다리던 요청 중 하나가 이어서 조회함
+pub struct SingleFlight<T> {
+    calls: Mutex<HashMap<String, Arc<OnceCell<T>>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// key를 조회중인 요청이 없으면 f로 조회하고, 있으면 그 결과를 기다림
+    pub async fn run<E, F, Fut>(&self, key: &str, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let cell = self
+            .lock()
+            .entry_ref(key)
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+        let result = cell.get_or_try_init(f).await.cloned();
+
+        // 조회가 끝난 뒤 들어온 요청은 새로 조회하도록 지움. 그 사이 다른 요청이 새로 넣은 경우 지우지 않음
+        let mut calls = self.lock();
+        if calls
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, &cell))
+        {
+            calls.remove(key);
+        }
+        result
+    }
+
+    /// 조회중인 key의 수
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<OnceCell<T>>>> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[tokio::test]
+async fn single_flight_test() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    let flight = SingleFlight::<String>::new();
+    let calls = AtomicUsize::new(0);
+    let lookup = |value: &'static str| {
+        let calls = &calls;
+        move || async move {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, String>(value.to_string())
+        }
+    };
+
+    // 동시에 들어온 같은 key는 한 번만 조회함
+    let (first, second, other) = tokio::join!(
+        flight.run("a", lookup("seed-a")),
+        flight.run("a", lookup("unused")),
+        flight.run("b", lookup("seed-b")),
+    );
+    assert_eq!(first.unwrap(), "seed-a");
+    assert_eq!(second.unwrap(), "seed-a");
+    assert_eq!(other.unwrap(), "seed-b");
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    assert_eq!(flight.len(), 0);
+
+    // 끝난 뒤에는 다시 조회함
+    assert_eq!(flight.run("a", lookup("seed-a2")).await.unwrap(), "seed-a2");
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+    // 에러는 함께 사용하지 않고 기다리던 요청이 이어서 조회함
+    let failing = || async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Err::<String, _>("DB_FAIL".to_string())
+    };
+    let (failed, retried) =
+        tokio::join!(flight.run("c", failing), flight.run("c", lookup("seed-c")));
+    assert_eq!(failed.unwrap_err(), "DB_FAIL");
+    assert_eq!(retried.unwrap(), "seed-c");
+    assert_eq!(flight.len(), 0);
+}
@@ -0,0 +1,117 @@
+use crate::context::RequestContext;
+use log::warn;
+use std::time::Duration;
+
+/// 느린 요청의 종류별 정보
+pub enum SlowRequestKind<'a> {
+    Select {
+        query: Option<&'a str>,
+    },
+    Update {
+        doc_cnt: usize,
+        bytes_len: usize,
+        changed_doc_cnt: usize,
+        /// 변경된 doc의 id 일부. 스트리밍 모드에서는 비어있음
+        changed_ids: &'a [String],
+    },
+}
+
+/// 느린 요청 로그에 남길 정보
+pub struct SlowRequest<'a> {
+    pub ctx: &'a RequestContext,
+    pub path: &'a str,
+    pub kind: SlowRequestKind<'a>,
+    /// cache, DB에서 seed_id를 조회하는 데 걸린 시간
+    pub enrich: Duration,
+    /// 솔라 응답을 기다린 시간
+    pub solr: Duration,
+    pub total: Duration,
+}
+
+/// total이 threshold 이상인 경우 warn 로그를 남김. threshold가 0이면 사용하지 않음
+/// <br>
+/// 로그를 남긴 경우 true
+pub fn log_if_slow(threshold: Duration, req: &SlowRequest) -> bool {
+    if threshold.is_zero() || req.total < threshold {
+        return false;
+    }
+
+    match req.kind {
+        SlowRequestKind::Select { query } => warn!(
+            "[{}] SLOW_SELECT {}ms (solr {}ms), path: {}, query: {}, from: {}",
+            req.ctx.request_id,
+            req.total.as_millis(),
+            req.solr.as_millis(),
+            req.path,
+            query.unwrap_or_default(),
+            req.ctx.remote_ip
+        ),
+        SlowRequestKind::Update {
+            doc_cnt,
+            bytes_len,
+            changed_doc_cnt,
+            changed_ids,
+        } => warn!(
+            "[{}] SLOW_UPDATE {}ms (enrich {}ms, solr {}ms), path: {}, {} doc ({} changed [{}]), {} bytes, from: {}",
+            req.ctx.request_id,
+            req.total.as_millis(),
+            req.enrich.as_millis(),
+            req.solr.as_millis(),
+            req.path,
+            doc_cnt,
+            changed_doc_cnt,
+            changed_ids.join(", "),
+            bytes_len,
+            req.ctx.remote_ip
+        ),
+    }
+    true
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_request_log_test() {
+    use crate::mock::MockSolr;
+    use crate::solr::Solr;
+    use hyper::{Body, HeaderMap, Method, Response};
+    use std::time::Instant;
+
+    // mock 솔라가 응답을 늦게 돌려주도록 함
+    let mock = MockSolr::start_with(|_| {
+        std::thread::sleep(Duration::from_millis(50));
+        Response::new(Body::from("OK"))
+    })
+    .await;
+    let solr = Solr::new(
+        mock.url.clone(),
+        false,
+        crate::solr::SolrClientConfig::default(),
+    );
+    let ctx = RequestContext::new(&HeaderMap::new(), "10.0.0.1:5000".parse().unwrap());
+
+    let start = Instant::now();
+    solr.send_request(
+        "/solr/core/select?q=*:*".parse().unwrap(),
+        Method::GET,
+        HeaderMap::new(),
+        Body::empty(),
+        &ctx,
+    )
+    .await
+    .unwrap();
+    let duration = start.elapsed();
+
+    let req = SlowRequest {
+        ctx: &ctx,
+        path: "/solr/core/select",
+        kind: SlowRequestKind::Select {
+            query: Some("q=*:*"),
+        },
+        enrich: Duration::ZERO,
+        solr: duration,
+        total: duration,
+    };
+    assert!(log_if_slow(Duration::from_millis(20), &req));
+    assert!(!log_if_slow(Duration::from_secs(60), &req));
+    // 0이면 사용하지 않음
+    assert!(!log_if_slow(Duration::ZERO, &req));
+}
@@ -1,45 +1,1073 @@
+use crate::backend_status::BackendStatus;
+use crate::context::{RequestContext, Scheme, X_REQUEST_ID};
+use crate::error_kind::ErrorKind;
+use crate::otel;
+use crate::secret;
+use crate::util::{self, StrError};
 use crate::BoxedError;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use config::{Config, ConfigError};
+use hyper::body::{Bytes, HttpBody};
 use hyper::client::HttpConnector;
+use hyper::header::{HeaderName, AUTHORIZATION, HOST, VIA};
 use hyper::http::HeaderValue;
-use hyper::{Body, Client, HeaderMap, Method, Request, Response, Uri};
+use hyper::{Body, Client, HeaderMap, Method, Request, Response, StatusCode, Uri};
+use log::{debug, warn};
+use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Via 헤더에 들어갈 proxy 식별값
+const VIA_VALUE: &str = concat!("solr_proxy/", env!("CARGO_PKG_VERSION"));
+
+/// 솔라 client의 연결 pool 설정
+#[derive(Debug, Clone, Copy)]
+pub struct SolrClientConfig {
+    /// host별로 유지하는 idle 연결 최대 개수
+    pub pool_max_idle_per_host: usize,
+    /// 이 시간동안 사용하지 않은 idle 연결은 끊음
+    pub pool_idle_timeout: Duration,
+    /// TCP keepalive 주기. None이면 사용하지 않음
+    pub tcp_keepalive: Option<Duration>,
+    /// true인 경우 솔라에 HTTP/2(h2c prior knowledge)로 요청함
+    pub http2: bool,
+    /// 이 시간 안에 응답 header를 받지 못하면 504로 응답함. 0이면 제한 없음
+    pub request_timeout: Duration,
+}
+
+impl Default for SolrClientConfig {
+    /// hyper 기본값
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: None,
+            http2: false,
+            request_timeout: Duration::ZERO,
+        }
+    }
+}
+
+/// 늦게 응답하는 select 요청을 다른 솔라에도 보내는 설정
+#[derive(Debug, Clone, Default)]
+pub struct HedgeConfig {
+    /// 두번째 요청을 보낼 솔라 주소 목록. 순서대로 돌아가며 사용하며, 비어있으면 사용하지 않음
+    pub urls: Vec<String>,
+    /// 첫 요청이 이 시간 안에 응답하지 않으면 두번째 요청을 보냄. 0이면 사용하지 않음
+    pub after: Duration,
+    /// 동시에 보낼 수 있는 두번째 요청 수. 전체적으로 느려진 경우 솔라 부하가 2배가 되지 않도록 제한함
+    pub max_concurrent: usize,
+}
 
 pub struct Solr {
     solr_url: String,
     client: Client<HttpConnector>,
+    /// true인 경우 클라이언트가 보낸 Host 헤더를 그대로 솔라에 전달함
+    preserve_host: bool,
+    /// 모든 솔라 요청에 넣는 헤더. 클라이언트가 보낸 같은 이름의 헤더는 덮어씀
+    upstream_headers: HeaderMap,
+    /// true인 경우 update 요청을 솔라로 보내지 않음. 솔라를 재시작하는 등 점검 중에 사용함
+    update_drain: AtomicBool,
+    /// 솔라 요청마다 응답 header를 기다릴 최대 시간. 0이면 제한 없음
+    request_timeout: Duration,
+    hedge: HedgeConfig,
+    /// 동시에 보낸 두번째 요청 수 제한
+    hedge_limit: Semaphore,
+    /// 다음 두번째 요청을 보낼 hedge.urls의 위치
+    hedge_next: AtomicUsize,
+    /// solr_url, hedge.urls 순서의 솔라별 연결 상태
+    backends: Vec<BackendStatus>,
 }
 
 impl Solr {
-    pub fn new(solr_url: String) -> Solr {
+    pub fn new(solr_url: String, preserve_host: bool, client_config: SolrClientConfig) -> Solr {
+        let mut connector = HttpConnector::new();
+        connector.set_nodelay(true);
+        connector.set_keepalive(client_config.tcp_keepalive);
+        let client = Client::builder()
+            .pool_max_idle_per_host(client_config.pool_max_idle_per_host)
+            .pool_idle_timeout(client_config.pool_idle_timeout)
+            .http2_only(client_config.http2)
+            .build(connector);
+
         Solr {
+            backends: vec![BackendStatus::new(solr_url.clone())],
             solr_url,
-            client: Client::new(),
+            client,
+            preserve_host,
+            upstream_headers: HeaderMap::new(),
+            update_drain: AtomicBool::new(false),
+            request_timeout: client_config.request_timeout,
+            hedge: HedgeConfig::default(),
+            hedge_limit: Semaphore::new(0),
+            hedge_next: AtomicUsize::new(0),
         }
     }
 
+    /// select 요청에 hedge 설정을 사용함
+    pub fn with_hedge(mut self, hedge: HedgeConfig) -> Solr {
+        self.hedge_limit = Semaphore::new(hedge.max_concurrent);
+        self.backends.truncate(1);
+        for url in &hedge.urls {
+            self.backends.push(BackendStatus::new(url.clone()));
+        }
+        self.hedge = hedge;
+        self
+    }
+
+    /// send_hedged가 두번째 요청을 보낼 수 있는 설정인지 여부
+    pub fn is_hedge_enabled(&self) -> bool {
+        !self.hedge.urls.is_empty() && !self.hedge.after.is_zero() && self.hedge.max_concurrent > 0
+    }
+
+    /// update 요청을 받지 않는 점검 상태로 시작함
+    pub fn with_update_drain(self, drain: bool) -> Solr {
+        self.update_drain.store(drain, Ordering::Relaxed);
+        self
+    }
+
+    /// 점검 상태를 바꾸고 이전 상태를 반환함
+    pub fn set_update_drain(&self, drain: bool) -> bool {
+        self.update_drain.swap(drain, Ordering::Relaxed)
+    }
+
+    pub fn is_update_draining(&self) -> bool {
+        self.update_drain.load(Ordering::Relaxed)
+    }
+
+    /// 모든 솔라 요청에 upstream_headers를 넣음
+    pub fn with_upstream_headers(mut self, upstream_headers: HeaderMap) -> Solr {
+        self.upstream_headers = upstream_headers;
+        self
+    }
+
+    /// 솔라별 연결 상태. 통계 API에서 사용함
+    pub fn backend_status_json(&self) -> serde_json::Value {
+        self.backends.iter().map(BackendStatus::to_json).collect()
+    }
+
+    /// base_url 솔라의 연결 상태
+    fn backend(&self, base_url: &str) -> Option<&BackendStatus> {
+        self.backends
+            .iter()
+            .find(|backend| backend.url() == base_url)
+    }
+
+    /// 솔라 주소로 요청을 보내 응답을 받을 수 있는지 확인함. 응답의 status code는 확인하지 않음
+    /// <br>
+    /// http2인 경우 솔라가 HTTP/2를 지원하지 않으면 에러
+    pub async fn probe(&self, timeout: Duration) -> Result<StatusCode, BoxedError> {
+        let uri: Uri = self.solr_url.parse()?;
+        match tokio::time::timeout(timeout, self.client.get(uri)).await {
+            Ok(response) => Ok(response?.status()),
+            Err(_) => Err("timeout".into()),
+        }
+    }
+
+    /// 솔라에 요청을 보냄. body가 비어있는 GET, HEAD 요청은 끊어진 idle 연결로 보냈으면 한번 더 보냄
     pub async fn send_request(
         &self,
         uri: Uri,
         method: Method,
         header_map: HeaderMap<HeaderValue>,
         body: Body,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        let replay = (body.size_hint().exact() == Some(0)).then(Bytes::new);
+        self.send(&self.solr_url, uri, method, header_map, body, replay, ctx)
+            .await
+    }
+
+    /// body를 다시 보낼 수 있으므로 GET, HEAD 요청은 끊어진 idle 연결로 보냈으면 한번 더 보냄
+    pub async fn send_bytes(
+        &self,
+        uri: Uri,
+        method: Method,
+        header_map: HeaderMap<HeaderValue>,
+        body: Bytes,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        self.send_bytes_to(&self.solr_url, uri, method, header_map, body, ctx)
+            .await
+    }
+
+    /// select 요청을 솔라에 보내고 hedge.after 안에 응답하지 않으면 hedge.urls의 솔라에도 보냄
+    /// <br>
+    /// 먼저 응답한 쪽을 반환하고 다른 요청은 취소함. 한쪽이 에러인 경우 다른 쪽의 응답을 기다림.
+    /// 동시에 보낸 두번째 요청이 hedge.max_concurrent 이상이면 보내지 않고 첫 요청을 기다림
+    pub async fn send_hedged(
+        &self,
+        uri: Uri,
+        method: Method,
+        header_map: HeaderMap<HeaderValue>,
+        body: Bytes,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        if !self.is_hedge_enabled() {
+            return self.send_bytes(uri, method, header_map, body, ctx).await;
+        }
+        let primary = self.send_bytes_to(
+            &self.solr_url,
+            uri.clone(),
+            method.clone(),
+            header_map.clone(),
+            body.clone(),
+            ctx,
+        );
+        tokio::pin!(primary);
+        tokio::select! {
+            response = &mut primary => return response,
+            _ = tokio::time::sleep(self.hedge.after) => (),
+        }
+        let Ok(_permit) = self.hedge_limit.try_acquire() else {
+            return primary.await;
+        };
+
+        let index = self.hedge_next.fetch_add(1, Ordering::Relaxed) % self.hedge.urls.len();
+        debug!(
+            "[{}] SOLR_HEDGE: no response in {:?}, sending to {}",
+            ctx.request_id, self.hedge.after, self.hedge.urls[index]
+        );
+        crate::WORKING_CNT.lock().await.hedge_fired_cnt += 1;
+        let hedge = self.send_bytes_to(&self.hedge.urls[index], uri, method, header_map, body, ctx);
+        tokio::pin!(hedge);
+        let (response, won) = tokio::select! {
+            response = &mut primary => match response {
+                Ok(response) => (Ok(response), false),
+                Err(_) => (hedge.await, true),
+            },
+            response = &mut hedge => match response {
+                Ok(response) => (Ok(response), true),
+                Err(_) => (primary.await, false),
+            },
+        };
+        if won && response.is_ok() {
+            crate::WORKING_CNT.lock().await.hedge_won_cnt += 1;
+        }
+        response
+    }
+
+    /// body를 다시 보낼 수 있으므로 GET, HEAD 요청은 끊어진 idle 연결로 보냈으면 한번 더 보냄
+    async fn send_bytes_to(
+        &self,
+        base_url: &str,
+        uri: Uri,
+        method: Method,
+        header_map: HeaderMap<HeaderValue>,
+        body: Bytes,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        let replay = Some(body.clone());
+        self.send(
+            base_url,
+            uri,
+            method,
+            header_map,
+            Body::from(body),
+            replay,
+            ctx,
+        )
+        .await
+    }
+
+    /// base_url에 uri의 path를 붙여 요청함
+    /// <br>
+    /// replay가 있는 GET, HEAD 요청은 솔라가 끊은 idle 연결로 보내서 실패하면 replay로 한번 더 보냄.
+    /// 솔라가 요청을 받아 처리한 뒤 끊었을 수 있으므로 update 등 나머지 method는 다시 보내지 않음
+    #[allow(clippy::too_many_arguments)]
+    async fn send(
+        &self,
+        base_url: &str,
+        uri: Uri,
+        method: Method,
+        mut header_map: HeaderMap<HeaderValue>,
+        body: Body,
+        replay: Option<Bytes>,
+        ctx: &RequestContext,
     ) -> Result<Response<Body>, BoxedError> {
         // solr_url에 path를 붙여 전체 url을 생성
         let path_and_query = uri.path_and_query().ok_or("Empty PathAndQuery Error")?;
-        let mut new_url = self.solr_url.to_string();
+        let mut new_url = base_url.to_string();
         new_url.push_str(path_and_query.as_str());
         let new_url = Uri::from_str(new_url.as_str())?;
 
-        let mut builder = Request::builder().method(method).uri(new_url);
+        let backend_host = match self.preserve_host {
+            true => None,
+            false => new_url.authority().map(|authority| authority.as_str()),
+        };
+        set_forward_headers(
+            &mut header_map,
+            ctx.remote_ip.ip(),
+            ctx.scheme,
+            backend_host,
+        )?;
+        header_map.insert(X_REQUEST_ID, HeaderValue::from_str(&ctx.request_id)?);
+        // HTTP/2에서는 hop-by-hop 헤더가 있으면 hyper에서 에러가 발생함
+        util::remove_hop_by_hop_headers(&mut header_map);
+        // 클라이언트가 보낸 인증 정보가 솔라로 전달되지 않도록 같은 이름의 헤더는 제거함
+        for name in self.upstream_headers.keys() {
+            header_map.remove(name);
+        }
+        for (name, value) in &self.upstream_headers {
+            header_map.append(name, value.clone());
+        }
+
+        // 솔라 요청 span. trace가 이어지도록 traceparent를 이 span으로 바꿔서 보냄
+        let span = otel::Span::start_client("solr");
+        span.set_str("http.request.method", method.as_str());
+        span.set_str("url.path", new_url.path());
+        if let Some(size) = body.size_hint().exact() {
+            span.set_u64("http.request.body.size", size);
+        }
+        span.inject(&mut header_map);
+
+        let replay = replay.filter(|_| is_replayable(&method));
+        let build = |body: Body| -> Result<Request<Body>, BoxedError> {
+            let mut builder = Request::builder().method(method.clone()).uri(&new_url);
+            // 같은 이름의 헤더가 여러개 있는 경우 모두 보냄
+            for (name, value) in &header_map {
+                builder = builder.header(name, value);
+            }
+            Ok(builder.body(body)?)
+        };
+
+        let response = self
+            .request(build, body, replay, self.backend(base_url), ctx)
+            .await;
+        if let Ok(response) = &response {
+            span.set_u64(
+                "http.response.status_code",
+                response.status().as_u16() as u64,
+            );
+        }
+        response
+    }
+
+    /// 솔라에 요청. 응답 header를 받을 때까지 처리중인 솔라 요청으로 셈
+    /// <br>
+    /// 응답을 받았는지, 연결 에러로 끝났는지를 backend 상태에 남김.
+    /// request_timeout 안에 응답 header를 받지 못하면 다시 시도한 요청까지 중단하고 504 에러
+    async fn request(
+        &self,
+        build: impl Fn(Body) -> Result<Request<Body>, BoxedError>,
+        body: Body,
+        replay: Option<Bytes>,
+        backend: Option<&BackendStatus>,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        let _in_flight = crate::IN_FLIGHT.solr.enter();
+        let _timer = crate::LATENCY.solr.start_timer();
+        let response = self.request_once(build, body, replay, backend, ctx);
+        if self.request_timeout.is_zero() {
+            return response.await;
+        }
+        match tokio::time::timeout(self.request_timeout, response).await {
+            Ok(response) => response,
+            Err(_) => {
+                let message = format!("SOLR_TIMEOUT: {}ms", self.request_timeout.as_millis());
+                if let Some(backend) = backend {
+                    backend.record_failure(&message, false);
+                }
+                Err(Box::new(
+                    StrError::with_status(message, StatusCode::GATEWAY_TIMEOUT)
+                        .with_kind(ErrorKind::Timeout),
+                ))
+            }
+        }
+    }
+
+    /// 솔라에 요청하고, 끊어진 idle 연결로 보내 실패한 경우 replay로 한 번 더 보냄
+    async fn request_once(
+        &self,
+        build: impl Fn(Body) -> Result<Request<Body>, BoxedError>,
+        body: Body,
+        replay: Option<Bytes>,
+        backend: Option<&BackendStatus>,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        let err = match self.client.request(build(body)?).await {
+            Ok(response) => return Ok(checked_response(response, backend, ctx).await),
+            Err(err) => err,
+        };
+        count_conn_err(false).await;
+
+        match replay {
+            Some(replay) if is_stale_connection(&err) => {
+                debug!(
+                    "[{}] SOLR_STALE_CONNECTION: {}, retrying once",
+                    ctx.request_id, err
+                );
+                count_conn_err(true).await;
+                let response = self.client.request(build(Body::from(replay))?).await;
+                match response {
+                    Ok(response) => Ok(checked_response(response, backend, ctx).await),
+                    Err(err) => {
+                        count_conn_err(false).await;
+                        Err(conn_error(err, backend).await)
+                    }
+                }
+            }
+            _ => Err(conn_error(err, backend).await),
+        }
+    }
+}
+
+/// 솔라 연결 에러. 메시지는 hyper 에러 그대로 사용함
+/// <br>
+/// 연결 자체를 맺지 못한 에러는 따로 셈
+async fn conn_error(err: hyper::Error, backend: Option<&BackendStatus>) -> BoxedError {
+    let message = err.to_string();
+    if err.is_connect() {
+        crate::WORKING_CNT.lock().await.upstream_connect_fail_cnt += 1;
+    }
+    if let Some(backend) = backend {
+        backend.record_failure(&message, err.is_connect());
+    }
+    Box::new(StrError::new(message).with_kind(ErrorKind::SolrConnectError))
+}
+
+/// 솔라 응답을 클라이언트에 그대로 돌려줄 수 있도록 hop-by-hop 헤더를 제거함
+/// <br>
+/// 401은 솔라 인증 설정이 잘못된 경우이므로 따로 남기고, 5xx는 solr_status 에러로 셈
+async fn checked_response(
+    mut response: Response<Body>,
+    backend: Option<&BackendStatus>,
+    ctx: &RequestContext,
+) -> Response<Body> {
+    if let Some(backend) = backend {
+        backend.record_success();
+    }
+    util::remove_hop_by_hop_headers(response.headers_mut());
+    if response.status() == StatusCode::UNAUTHORIZED {
+        warn!(
+            "[{}] SOLR_UNAUTHORIZED: check solr_user, solr_pwd",
+            ctx.request_id
+        );
+        let mut cnt_lock = crate::WORKING_CNT.lock().await;
+        cnt_lock.upstream_unauthorized_cnt += 1;
+    }
+    // 응답은 그대로 돌려주지만 에러 분류에는 셈
+    if response.status().is_server_error() {
+        let mut cnt_lock = crate::WORKING_CNT.lock().await;
+        cnt_lock.error_kind_cnt.record(ErrorKind::SolrStatusError);
+    }
+    response
+}
 
-        for (header_name, header_value) in header_map {
-            if let Some(name) = header_name {
-                builder = builder.header(name, header_value);
+/// 솔라 요청마다 넣는 헤더를 config에서 읽음. 두번째 값은 로그에 남길 인증 정보
+/// <br>
+/// solr_extra_headers table의 헤더를 넣고, solr_user가 있는 경우 solr_pwd로 만든 Basic 인증 헤더를 넣음.
+/// solr_pwd는 db_pwd와 같이 환경변수, solr_pwd_file도 사용할 수 있음
+pub fn upstream_headers(
+    config: &Config,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(HeaderMap, Option<String>), ConfigError> {
+    let mut header_map = HeaderMap::new();
+    let invalid =
+        |key: &str| ConfigError::Message(format!("INVALID_CONFIG: solr_extra_headers.{}", key));
+    match config.get_table("solr_extra_headers") {
+        Ok(table) => {
+            for (key, value) in table {
+                let name = HeaderName::from_bytes(key.as_bytes()).map_err(|_| invalid(&key))?;
+                let value = value.into_string().map_err(|_| invalid(&key))?;
+                let value = HeaderValue::from_str(&value).map_err(|_| invalid(&key))?;
+                header_map.insert(name, value);
             }
         }
+        Err(ConfigError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let user = match config.get_string("solr_user") {
+        Ok(user) => user,
+        Err(ConfigError::NotFound(_)) => return Ok((header_map, None)),
+        Err(e) => return Err(e),
+    };
+    let (pwd, pwd_source) = secret::get_secret(config, "solr_pwd", env)?;
+    let credentials = BASE64_STANDARD.encode(format!("{}:{}", user, pwd));
+    let mut value = HeaderValue::from_str(&format!("Basic {}", credentials))
+        .map_err(|_| ConfigError::Message("INVALID_CONFIG: solr_user".to_string()))?;
+    value.set_sensitive(true);
+    header_map.insert(AUTHORIZATION, value);
+
+    let auth = format!(
+        "user: {}, pwd: {} (from {})",
+        user,
+        secret::mask(&pwd),
+        pwd_source
+    );
+    Ok((header_map, Some(auth)))
+}
+
+/// 끊어진 idle 연결로 보내 실패한 경우 다시 보낼 수 있는 method인지 확인함
+fn is_replayable(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+/// 솔라가 idle keep-alive 연결을 끊은 뒤 그 연결로 요청을 보낸 경우 발생하는 에러인지 확인함
+fn is_stale_connection(err: &hyper::Error) -> bool {
+    err.is_incomplete_message()
+}
+
+async fn count_conn_err(retry: bool) {
+    let mut cnt_lock = crate::WORKING_CNT.lock().await;
+    if retry {
+        cnt_lock.upstream_retry_cnt += 1;
+    } else {
+        cnt_lock.upstream_conn_err_cnt += 1;
+    }
+}
+
+/// 솔라에서 실제 클라이언트를 알 수 있도록 X-Forwarded-*, Via 헤더를 추가함
+/// <br>
+/// backend_host가 있는 경우 Host 헤더를 backend_host로 교체함
+fn set_forward_headers(
+    header_map: &mut HeaderMap<HeaderValue>,
+    remote_ip: IpAddr,
+    scheme: Scheme,
+    backend_host: Option<&str>,
+) -> Result<(), BoxedError> {
+    // 클라이언트가 이미 X-Forwarded-For를 보낸 경우 뒤에 remote_ip를 덧붙임
+    let mut forwarded_for = header_map
+        .get_all(&X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !forwarded_for.is_empty() {
+        forwarded_for.push_str(", ");
+    }
+    forwarded_for.push_str(&remote_ip.to_string());
+    header_map.insert(X_FORWARDED_FOR, HeaderValue::from_str(&forwarded_for)?);
+
+    // 클라이언트가 보낸 X-Forwarded-Proto는 proxy가 받은 scheme으로 교체함
+    header_map.insert(X_FORWARDED_PROTO, HeaderValue::from_static(scheme.as_str()));
+
+    let mut via = header_map
+        .get_all(VIA)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !via.is_empty() {
+        via.push_str(", ");
+    }
+    via.push_str(VIA_VALUE);
+    header_map.insert(VIA, HeaderValue::from_str(&via)?);
+
+    if let Some(backend_host) = backend_host {
+        header_map.insert(HOST, HeaderValue::from_str(backend_host)?);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn forward_header_test() {
+    let mut mock = crate::mock::MockSolr::start().await;
+    let mock_host = mock.url.trim_start_matches("http://").to_string();
+    let ctx = RequestContext {
+        request_id: "test-request".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+
+    // X-Forwarded-For가 없는 경우 새로 만들고 Host는 솔라 주소로 교체
+    let solr = Solr::new(mock.url.clone(), false, SolrClientConfig::default());
+    let mut header_map = HeaderMap::new();
+    header_map.insert(HOST, HeaderValue::from_static("proxy.local:3000"));
+    solr.send_request(
+        Uri::from_static("/solr/core/select?q=*:*"),
+        Method::GET,
+        header_map,
+        Body::empty(),
+        &ctx,
+    )
+    .await
+    .unwrap();
+
+    let captured = mock.next_request().await;
+    assert_eq!(captured.uri, "/solr/core/select?q=*:*");
+    assert_eq!(captured.headers[&X_FORWARDED_FOR], "10.0.0.7");
+    assert_eq!(captured.headers[&X_FORWARDED_PROTO], "http");
+    assert_eq!(captured.headers[VIA], VIA_VALUE);
+    assert_eq!(captured.headers[HOST], mock_host.as_str());
+    assert_eq!(captured.headers[X_REQUEST_ID], "test-request");
+
+    // 이미 X-Forwarded-For가 있는 경우 뒤에 덧붙이고, preserve_host인 경우 Host 유지
+    let solr = Solr::new(mock.url.clone(), true, SolrClientConfig::default());
+    let mut header_map = HeaderMap::new();
+    header_map.insert(HOST, HeaderValue::from_static("proxy.local:3000"));
+    header_map.insert(X_FORWARDED_FOR, HeaderValue::from_static("1.1.1.1"));
+    header_map.append(X_FORWARDED_FOR, HeaderValue::from_static("2.2.2.2"));
+    solr.send_request(
+        Uri::from_static("/solr/core/update"),
+        Method::POST,
+        header_map,
+        Body::from("<add></add>"),
+        &ctx,
+    )
+    .await
+    .unwrap();
+
+    let captured = mock.next_request().await;
+    assert_eq!(captured.method, Method::POST);
+    assert_eq!(&captured.body[..], b"<add></add>");
+    assert_eq!(
+        captured.headers[&X_FORWARDED_FOR],
+        "1.1.1.1, 2.2.2.2, 10.0.0.7"
+    );
+    assert_eq!(captured.headers.get_all(&X_FORWARDED_FOR).iter().count(), 1);
+    assert_eq!(captured.headers[HOST], "proxy.local:3000");
+
+    // TLS listener에서 받은 요청은 https로 전달함
+    let https_ctx = ctx.clone().with_scheme(Scheme::Https);
+    let mut header_map = HeaderMap::new();
+    header_map.insert(X_FORWARDED_PROTO, HeaderValue::from_static("http"));
+    solr.send_request(
+        Uri::from_static("/solr/core/select"),
+        Method::GET,
+        header_map,
+        Body::empty(),
+        &https_ctx,
+    )
+    .await
+    .unwrap();
+    let captured = mock.next_request().await;
+    assert_eq!(captured.headers[&X_FORWARDED_PROTO], "https");
+    assert_eq!(
+        captured.headers.get_all(&X_FORWARDED_PROTO).iter().count(),
+        1
+    );
+
+    // 같은 이름의 헤더는 모두 전달하고, hop-by-hop 헤더는 전달하지 않음
+    let mut header_map = HeaderMap::new();
+    header_map.insert("accept", HeaderValue::from_static("text/xml"));
+    header_map.append("accept", HeaderValue::from_static("application/json"));
+    header_map.insert("connection", HeaderValue::from_static("x-hop"));
+    header_map.insert("x-hop", HeaderValue::from_static("1"));
+    header_map.insert("upgrade", HeaderValue::from_static("websocket"));
+    solr.send_request(
+        Uri::from_static("/solr/core/select"),
+        Method::GET,
+        header_map,
+        Body::empty(),
+        &ctx,
+    )
+    .await
+    .unwrap();
+
+    let captured = mock.next_request().await;
+    let accept: Vec<_> = captured.headers.get_all("accept").iter().collect();
+    assert_eq!(accept, ["text/xml", "application/json"]);
+    assert!(!captured.headers.contains_key("x-hop"));
+    assert!(!captured.headers.contains_key("upgrade"));
+}
+
+#[tokio::test]
+async fn stale_connection_retry_test() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // 첫 연결은 요청을 읽은 뒤 응답하지 않고 끊고, 다음 연결에는 응답함. 받은 요청을 반환함
+    async fn stale_server() -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for i in 0..2 {
+                let accept = listener.accept();
+                let Ok(Ok((mut stream, _))) =
+                    tokio::time::timeout(Duration::from_millis(500), accept).await
+                else {
+                    break;
+                };
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).into_owned());
+                if i == 1 {
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nOK")
+                        .await
+                        .unwrap();
+                }
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    let ctx = RequestContext {
+        request_id: "stale-test".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+
+    // GET 요청은 한 번 더 보내고, 다시 보낸 요청에도 같은 body가 들어감
+    let (url, server) = stale_server().await;
+    let solr = Solr::new(url, false, SolrClientConfig::default());
+    let response = solr
+        .send_bytes(
+            Uri::from_static("/solr/core/select"),
+            Method::GET,
+            HeaderMap::new(),
+            Bytes::from_static(b"q=*:*"),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), hyper::StatusCode::OK);
+    let requests = server.await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.ends_with("q=*:*")));
+
+    // 솔라가 처리했을 수 있으므로 POST 요청은 다시 보내지 않음
+    let (url, server) = stale_server().await;
+    let solr = Solr::new(url, false, SolrClientConfig::default());
+    let err = solr
+        .send_bytes(
+            Uri::from_static("/solr/core/update"),
+            Method::POST,
+            HeaderMap::new(),
+            Bytes::from_static(b"<add></add>"),
+            &ctx,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorKind::of(&err), ErrorKind::SolrConnectError);
+    assert_eq!(server.await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn http2_test() {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::Server;
+    use std::convert::Infallible;
+
+    // HTTP/2만 지원하는 솔라에 연결 헤더가 있는 요청을 보내도 에러가 발생하지 않음
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+            Ok::<_, Infallible>(Response::new(Body::from(format!("{:?}", req.version()))))
+        }))
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap())
+        .http2_only(true)
+        .serve(make_service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+
+    let ctx = RequestContext {
+        request_id: "h2-test".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+    let client_config = SolrClientConfig {
+        http2: true,
+        ..SolrClientConfig::default()
+    };
+    let solr = Solr::new(url.clone(), false, client_config);
+    assert!(solr.probe(Duration::from_secs(5)).await.is_ok());
+
+    let mut header_map = HeaderMap::new();
+    header_map.insert(
+        hyper::header::CONNECTION,
+        HeaderValue::from_static("keep-alive"),
+    );
+    header_map.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+    let response = solr
+        .send_request(
+            Uri::from_static("/solr/core/select?q=*:*"),
+            Method::GET,
+            header_map,
+            Body::empty(),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"HTTP/2.0");
+
+    // HTTP/1.1 client는 HTTP/2만 지원하는 솔라에 연결할 수 없음
+    let solr = Solr::new(url, false, SolrClientConfig::default());
+    assert!(solr.probe(Duration::from_secs(5)).await.is_err());
+}
+
+#[tokio::test]
+async fn request_timeout_test() {
+    async fn send(solr: &Solr) -> Result<Response<Body>, BoxedError> {
+        let ctx = RequestContext {
+            request_id: "timeout-test".to_string(),
+            remote_ip: "10.0.0.7:5000".parse().unwrap(),
+            scheme: Scheme::Http,
+        };
+        solr.send_request(
+            Uri::from_static("/solr/core/select?q=*:*"),
+            Method::GET,
+            HeaderMap::new(),
+            Body::empty(),
+            &ctx,
+        )
+        .await
+    }
+    let mock = crate::mock::MockSolr::start_delayed(Duration::from_millis(300), |_| {
+        Response::new(Body::from("OK"))
+    })
+    .await;
+
+    // 응답이 request_timeout보다 늦으면 504와 Timeout으로 분류되는 에러
+    let client_config = SolrClientConfig {
+        request_timeout: Duration::from_millis(50),
+        ..SolrClientConfig::default()
+    };
+    let solr = Solr::new(mock.url.clone(), false, client_config);
+    let started = std::time::Instant::now();
+    let err = send(&solr).await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_millis(300));
+    assert_eq!(util::error_status(&err), Some(StatusCode::GATEWAY_TIMEOUT));
+    assert_eq!(ErrorKind::of(&err), ErrorKind::Timeout);
+    assert!(err.to_string().starts_with("SOLR_TIMEOUT: 50ms"));
+
+    // 0이면 제한하지 않고 응답을 기다림
+    let solr = Solr::new(mock.url.clone(), false, SolrClientConfig::default());
+    let response = send(&solr).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn upstream_headers_test() {
+    use config::{File, FileFormat};
+
+    let mut mock = crate::mock::MockSolr::start().await;
+    let ctx = RequestContext {
+        request_id: "auth-test".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+    let config_from = |toml: &str| {
+        Config::builder()
+            .add_source(File::from_str(toml, FileFormat::Toml))
+            .build()
+            .unwrap()
+    };
+    let no_env = |_: &str| None;
+    let ctx = &ctx;
+    let send = |solr: Solr| async move {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Basic Y3Jhd2xlcjp4"),
+        );
+        solr.send_request(
+            Uri::from_static("/solr/core/select"),
+            Method::GET,
+            header_map,
+            Body::empty(),
+            ctx,
+        )
+        .await
+        .unwrap();
+    };
+
+    // 설정하지 않은 경우 클라이언트가 보낸 헤더를 그대로 전달함
+    let (headers, auth) = upstream_headers(&config_from(""), no_env).unwrap();
+    assert!(headers.is_empty());
+    assert!(auth.is_none());
+    send(
+        Solr::new(mock.url.clone(), false, SolrClientConfig::default())
+            .with_upstream_headers(headers),
+    )
+    .await;
+    let captured = mock.next_request().await;
+    assert_eq!(captured.headers[AUTHORIZATION], "Basic Y3Jhd2xlcjp4");
+
+    // 설정한 경우 클라이언트가 보낸 인증 정보를 덮어씀
+    let config = config_from(
+        r#"
+        solr_user = "solr"
+        solr_pwd = "SolrRocks"
+        [solr_extra_headers]
+        x-tenant = "crawler"
+        "#,
+    );
+    let (headers, auth) = upstream_headers(&config, no_env).unwrap();
+    let auth = auth.unwrap();
+    assert!(!auth.contains("SolrRocks"));
+    assert_eq!(auth, "user: solr, pwd: ****** (from config)");
+    send(
+        Solr::new(mock.url.clone(), false, SolrClientConfig::default())
+            .with_upstream_headers(headers),
+    )
+    .await;
+    let captured = mock.next_request().await;
+    assert_eq!(
+        captured.headers[AUTHORIZATION],
+        "Basic c29scjpTb2xyUm9ja3M="
+    );
+    assert_eq!(captured.headers.get_all(AUTHORIZATION).iter().count(), 1);
+    assert_eq!(captured.headers["x-tenant"], "crawler");
+
+    // 비밀번호가 없거나 헤더 이름이 잘못된 경우 에러
+    assert!(upstream_headers(&config_from(r#"solr_user = "solr""#), no_env).is_err());
+    let config = config_from("[solr_extra_headers]\n\"bad header\" = \"1\"");
+    assert!(upstream_headers(&config, no_env).is_err());
+}
+
+#[tokio::test]
+async fn hedge_test() {
+    use crate::mock::MockSolr;
+
+    let ctx = RequestContext {
+        request_id: "hedge-test".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+    let body_of = |name: &'static str| move |_: &_| Response::new(Body::from(name));
+    async fn send(solr: &Solr, ctx: &RequestContext) -> Bytes {
+        let response = solr
+            .send_hedged(
+                Uri::from_static("/solr/core/select?q=*:*"),
+                Method::GET,
+                HeaderMap::new(),
+                Bytes::new(),
+                ctx,
+            )
+            .await
+            .unwrap();
+        hyper::body::to_bytes(response.into_body()).await.unwrap()
+    }
+    let hedged = |url: &str, hedge: &MockSolr, after_ms: u64| {
+        Solr::new(url.to_string(), false, SolrClientConfig::default()).with_hedge(HedgeConfig {
+            urls: vec![hedge.url.clone()],
+            after: Duration::from_millis(after_ms),
+            max_concurrent: 1,
+        })
+    };
+
+    // hedge_after 안에 응답하면 다른 솔라에 보내지 않음
+    let mut fast = MockSolr::start_with(body_of("primary")).await;
+    let mut hedge = MockSolr::start_with(body_of("hedge")).await;
+    let solr = hedged(&fast.url, &hedge, 200);
+    assert!(solr.is_hedge_enabled());
+    assert_eq!(&send(&solr, &ctx).await[..], b"primary");
+    fast.next_request().await;
+    assert!(hedge.requests.try_recv().is_err());
+
+    // 늦게 응답하는 경우 같은 요청을 다른 솔라에 보내고 먼저 온 응답을 사용함
+    let before = crate::WORKING_CNT.lock().await.clone();
+    let slow = MockSolr::start_delayed(Duration::from_millis(1000), body_of("primary")).await;
+    let solr = hedged(&slow.url, &hedge, 50);
+    assert_eq!(&send(&solr, &ctx).await[..], b"hedge");
+    assert_eq!(hedge.next_request().await.uri, "/solr/core/select?q=*:*");
+    let after = crate::WORKING_CNT.lock().await.clone();
+    assert!(after.hedge_fired_cnt > before.hedge_fired_cnt);
+    assert!(after.hedge_won_cnt > before.hedge_won_cnt);
+
+    // 동시에 보낼 수 있는 두번째 요청 수를 넘으면 첫 요청의 응답을 기다림
+    let slow = MockSolr::start_delayed(Duration::from_millis(500), body_of("primary")).await;
+    let mut slow_hedge =
+        MockSolr::start_delayed(Duration::from_millis(200), body_of("hedge")).await;
+    let solr = hedged(&slow.url, &slow_hedge, 50);
+    let (first, second) = tokio::join!(send(&solr, &ctx), send(&solr, &ctx));
+    let mut bodies = [first, second];
+    bodies.sort();
+    assert_eq!(bodies, [&b"hedge"[..], &b"primary"[..]]);
+    slow_hedge.next_request().await;
+    assert!(slow_hedge.requests.try_recv().is_err());
+
+    // 주소가 없거나 hedge_after가 0이면 사용하지 않음
+    let solr = Solr::new(fast.url.clone(), false, SolrClientConfig::default());
+    assert!(!solr.is_hedge_enabled());
+    assert!(!hedged(&fast.url, &hedge, 0).is_hedge_enabled());
+}
+
+#[tokio::test]
+async fn backend_status_test() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // 처음 세 연결은 요청을 읽은 뒤 응답하지 않고 끊고, 다음 연결부터 응답함
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        for i in 0..5 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            if i >= 3 {
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 2\r\n\r\nOK",
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+    });
+
+    let ctx = RequestContext {
+        request_id: "backend-test".to_string(),
+        remote_ip: "10.0.0.7:5000".parse().unwrap(),
+        scheme: Scheme::Http,
+    };
+    let hedge = HedgeConfig {
+        urls: vec!["http://127.0.0.1:1".to_string()],
+        ..HedgeConfig::default()
+    };
+    let solr = Solr::new(url.clone(), false, SolrClientConfig::default()).with_hedge(hedge);
+    async fn send(
+        solr: &Solr,
+        base_url: &str,
+        ctx: &RequestContext,
+    ) -> Result<Response<Body>, BoxedError> {
+        let uri = Uri::from_static("/solr/core/update");
+        let body = Body::from("<add></add>");
+        solr.send(
+            base_url,
+            uri,
+            Method::POST,
+            HeaderMap::new(),
+            body,
+            None,
+            ctx,
+        )
+        .await
+    }
+    let status = |solr: &Solr, index: usize| solr.backend_status_json()[index].clone();
+    assert_eq!(status(&solr, 0)["url"], url);
+    assert_eq!(status(&solr, 1)["url"], "http://127.0.0.1:1");
+
+    for _ in 0..3 {
+        assert!(send(&solr, &url, &ctx).await.is_err());
+    }
+    let failing = status(&solr, 0);
+    assert_eq!(failing["healthy"], false);
+    assert_eq!(failing["consecutive_failures"], 3);
+    // 연결은 맺었으므로 connect 실패로 세지 않음
+    assert_eq!(failing["connect_failures"], 0);
+    assert!(!failing["last_error"].as_str().unwrap().is_empty());
+    assert!(failing["last_success_at"].is_null());
+
+    // 한 번 응답한 것으로는 돌아오지 않음
+    assert!(send(&solr, &url, &ctx).await.is_ok());
+    assert_eq!(status(&solr, 0)["healthy"], false);
+    assert!(send(&solr, &url, &ctx).await.is_ok());
+    let recovered = status(&solr, 0);
+    assert_eq!(recovered["healthy"], true);
+    assert_eq!(recovered["consecutive_failures"], 0);
+    assert!(recovered["last_success_at"].is_u64());
 
-        // 솔라에 요청
-        let req = builder.body(body)?;
-        Ok(self.client.request(req).await?)
+    // 열려있지 않은 포트는 연결을 맺지 못한 실패로 셈
+    for _ in 0..3 {
+        let err = send(&solr, "http://127.0.0.1:1", &ctx).await.unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::SolrConnectError);
     }
+    let closed = status(&solr, 1);
+    assert_eq!(closed["healthy"], false);
+    assert_eq!(closed["connect_failures"], 3);
+    assert_eq!(status(&solr, 0)["healthy"], true);
 }
@@ -0,0 +1,273 @@
+use crate::gauge::BudgetGuard;
+use crate::util::{self, StrError};
+use crate::BoxedError;
+use hyper::body::{Bytes, HttpBody};
+use hyper::Body;
+use log::warn;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// 임시 파일에서 한 번에 읽어 보내는 크기
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 임시 파일 이름이 겹치지 않도록 붙이는 번호
+static SPOOL_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 모두 받은 update body. threshold를 넘은 body는 임시 파일에 있음
+pub enum SpooledBody {
+    Memory(Bytes),
+    File(SpoolFile),
+}
+
+/// update body를 저장한 임시 파일. Drop될 때 파일을 지움
+pub struct SpoolFile {
+    path: PathBuf,
+    len: u64,
+}
+
+impl SpoolFile {
+    async fn create(dir: &Path) -> Result<(Self, File), BoxedError> {
+        let name = format!(
+            "solr_proxy-{}-{}.spool",
+            std::process::id(),
+            SPOOL_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.join(name);
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // 다른 사용자가 update 내용을 읽지 못하도록 만든 사용자만 읽고 쓸 수 있게 만듦
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options
+            .open(&path)
+            .await
+            .map_err(|e| spool_error("SPOOL_CREATE_FAIL", &path, e))?;
+        Ok((Self { path, len: 0 }, file))
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// 파일 내용을 READ_CHUNK_BYTES씩 읽는 body. body를 끝까지 읽거나 중간에 버리면 파일을 지움
+    pub async fn into_body(self) -> Result<Body, BoxedError> {
+        let file = File::open(&self.path)
+            .await
+            .map_err(|e| spool_error("SPOOL_OPEN_FAIL", &self.path, e))?;
+        let stream = futures_util::stream::try_unfold((self, file), |(spool, mut file)| async {
+            let mut buf = vec![0u8; READ_CHUNK_BYTES];
+            let read = file.read(&mut buf).await?;
+            if read == 0 {
+                return Ok::<_, std::io::Error>(None);
+            }
+            buf.truncate(read);
+            Ok(Some((Bytes::from(buf), (spool, file))))
+        });
+        Ok(Body::wrap_stream(stream))
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("SPOOL_REMOVE_FAIL: {}, {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// body를 모두 읽음. threshold를 넘으면 그때까지 받은 내용과 나머지를 dir의 임시 파일에 씀
+/// <br>
+/// 읽는 도중 max_bytes를 넘으면 413 에러. 에러로 끝난 경우 임시 파일은 지움. threshold가 0이면 모두 메모리에 읽음
+/// <br>
+/// 메모리에 들고 있는 크기는 chunk를 받을 때마다 buffered에 차지하므로, Content-Length가 없는 body도 max_buffered를 넘는 순간 503 에러
+pub async fn read_body(
+    body: &mut Body,
+    threshold: u64,
+    max_bytes: usize,
+    dir: &Path,
+    buffered: &mut BudgetGuard<'_>,
+    max_buffered: u64,
+) -> Result<SpooledBody, BoxedError> {
+    if max_bytes > 0 && body.size_hint().lower() > max_bytes as u64 {
+        return Err(util::body_too_large(max_bytes));
+    }
+
+    let mut buf = Vec::new();
+    let mut spool: Option<(SpoolFile, File)> = None;
+    let mut total = 0u64;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        total += chunk.len() as u64;
+        if max_bytes > 0 && total > max_bytes as u64 {
+            return Err(util::body_too_large(max_bytes));
+        }
+        match &mut spool {
+            Some((spool, file)) => write(spool, file, &chunk).await?,
+            None if threshold > 0 && total > threshold => {
+                let (new_spool, mut file) = SpoolFile::create(dir).await?;
+                write(&new_spool, &mut file, &buf).await?;
+                write(&new_spool, &mut file, &chunk).await?;
+                buf = Vec::new();
+                spool = Some((new_spool, file));
+            }
+            None => {
+                buffered.reserve_to(total, max_buffered)?;
+                buf.extend_from_slice(&chunk);
+            }
+        }
+    }
+
+    match spool {
+        Some((mut spool, mut file)) => {
+            file.flush()
+                .await
+                .map_err(|e| spool_error("SPOOL_WRITE_FAIL", &spool.path, e))?;
+            spool.len = total;
+            Ok(SpooledBody::File(spool))
+        }
+        None => Ok(SpooledBody::Memory(Bytes::from(buf))),
+    }
+}
+
+async fn write(spool: &SpoolFile, file: &mut File, bytes: &[u8]) -> Result<(), BoxedError> {
+    file.write_all(bytes)
+        .await
+        .map_err(|e| spool_error("SPOOL_WRITE_FAIL", &spool.path, e))
+}
+
+fn spool_error(code: &str, path: &Path, e: std::io::Error) -> BoxedError {
+    Box::new(StrError::new(format!(
+        "{}: {}, {}",
+        code,
+        path.display(),
+        e
+    )))
+}
+
+#[tokio::test]
+async fn spool_read_body_test() {
+    use crate::gauge::ByteBudget;
+    use futures_util::stream;
+
+    let dir = std::env::temp_dir().join(format!("solr_proxy-spool-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let chunked = |chunks: &[&'static str]| {
+        let chunks: Vec<Result<&'static str, std::io::Error>> =
+            chunks.iter().map(|chunk| Ok(*chunk)).collect();
+        Body::wrap_stream(stream::iter(chunks))
+    };
+    let spool_cnt = || std::fs::read_dir(&dir).unwrap().count();
+    let budget = ByteBudget::new();
+
+    // threshold 이하는 메모리에 읽음
+    let mut body = chunked(&["<add>", "</add>"]);
+    match read_body(&mut body, 11, 0, &dir, &mut budget.guard(), 0)
+        .await
+        .unwrap()
+    {
+        SpooledBody::Memory(bytes) => assert_eq!(bytes, "<add></add>"),
+        SpooledBody::File(_) => panic!("spooled"),
+    }
+    assert_eq!(spool_cnt(), 0);
+
+    // threshold를 넘으면 앞서 받은 내용까지 파일에 씀
+    let mut body = chunked(&["<add>", "<doc/>", "</add>"]);
+    let SpooledBody::File(spool) = read_body(&mut body, 8, 0, &dir, &mut budget.guard(), 0)
+        .await
+        .unwrap()
+    else {
+        panic!("not spooled");
+    };
+    assert_eq!(spool.len(), 17);
+    assert_eq!(std::fs::read(&spool.path).unwrap(), b"<add><doc/></add>");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&spool.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let path = spool.path.clone();
+    let body = spool.into_body().await.unwrap();
+    assert_eq!(
+        hyper::body::to_bytes(body).await.unwrap(),
+        "<add><doc/></add>"
+    );
+    // 끝까지 읽은 body는 파일을 지움
+    assert!(!path.exists());
+
+    // 읽지 않고 버린 경우에도 지움
+    let mut body = chunked(&["<add>", "<doc/>", "</add>"]);
+    let SpooledBody::File(spool) = read_body(&mut body, 8, 0, &dir, &mut budget.guard(), 0)
+        .await
+        .unwrap()
+    else {
+        panic!("not spooled");
+    };
+    drop(spool.into_body().await.unwrap());
+    assert_eq!(spool_cnt(), 0);
+
+    // 파일에 쓰는 도중 max_bytes를 넘거나 body 에러가 발생한 경우 파일을 지움
+    let mut body = chunked(&["<add>", "<doc/>", "<doc/>", "</add>"]);
+    let err = read_body(&mut body, 8, 20, &dir, &mut budget.guard(), 0)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "MAX_BODY_BYTES_EXCEEDED: 20");
+    let chunks: Vec<Result<&'static str, std::io::Error>> = vec![
+        Ok("<add>"),
+        Ok("<doc/>"),
+        Err(std::io::Error::other("BODY_RESET")),
+    ];
+    let mut body = Body::wrap_stream(stream::iter(chunks));
+    assert!(read_body(&mut body, 8, 0, &dir, &mut budget.guard(), 0)
+        .await
+        .is_err());
+    assert_eq!(spool_cnt(), 0);
+
+    // 임시 파일을 만들 수 없으면 에러
+    let mut body = chunked(&["<add>", "<doc/>", "</add>"]);
+    let err = read_body(
+        &mut body,
+        8,
+        0,
+        &dir.join("missing"),
+        &mut budget.guard(),
+        0,
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(err.to_string().starts_with("SPOOL_CREATE_FAIL"));
+
+    // 메모리에 받는 크기는 chunk마다 차지하므로 Content-Length가 없어도 max_buffered를 넘으면 더 읽지 않음
+    let mut guard = budget.guard();
+    let mut body = chunked(&["<add>", "<doc/>", "</add>"]);
+    let err = read_body(&mut body, 0, 0, &dir, &mut guard, 10)
+        .await
+        .err()
+        .unwrap();
+    assert!(err
+        .to_string()
+        .starts_with("MAX_BUFFERED_BYTES_EXCEEDED: 10"));
+    assert!(guard.rejected());
+    assert_eq!(budget.current(), 5);
+    drop(guard);
+    // 임시 파일에 쓰는 부분은 차지하지 않음
+    let mut guard = budget.guard();
+    let mut body = chunked(&["<add>", "<doc/>", "<doc/>", "</add>"]);
+    let SpooledBody::File(spool) = read_body(&mut body, 8, 0, &dir, &mut guard, 10)
+        .await
+        .unwrap()
+    else {
+        panic!("not spooled");
+    };
+    assert_eq!(budget.current(), 5);
+    drop(spool);
+    drop(guard);
+
+    std::fs::remove_dir(&dir).unwrap();
+}
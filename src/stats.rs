@@ -0,0 +1,1089 @@
+use crate::cache_writer::CACHE_WRITE_BACKLOG_WARN;
+use crate::error_kind::ErrorKindCnt;
+use crate::insert_guard::InsertGuard;
+use crate::status_cnt::StatusCnt;
+use crate::{
+    settings, BUFFERED_BYTES, CON, IN_FLIGHT, LATENCY, RATE_LIMITER, SEED_CACHE_WRITER,
+    SEED_ID_CACHE, SELECT_LIMIT, SOLR, UPDATE_LIMIT,
+};
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot::Receiver;
+use tokio::sync::Mutex;
+
+/// 작업횟수 카운트
+#[derive(Clone)]
+pub struct WorkingCnt {
+    pub select_cnt: u32,
+    pub add_cnt: u32,
+    pub add_doc_cnt: usize,
+    /// 필드가 추가/변경되어 다시 작성한 doc 수
+    pub add_changed_doc_cnt: usize,
+    /// 솔라가 2xx가 아닌 status로 응답한 update 요청 수와 해당 요청의 doc 수
+    pub add_rejected_cnt: u32,
+    pub add_rejected_doc_cnt: usize,
+    pub err_cnt: u32,
+    pub add_duration_time_total: Duration,
+    pub add_duration_time_min: Duration,
+    pub add_duration_time_max: (Duration, usize, usize),
+    pub add_bytes_total: usize,
+    /// update 처리 단계별 시간. add_duration_time_*은 전체 시간
+    pub add_parse_time: DurationStat,
+    pub add_enrich_time: DurationStat,
+    pub add_solr_time: DurationStat,
+    pub select_duration_time_total: Duration,
+    pub select_duration_time_min: Duration,
+    pub select_duration_time_max: Duration,
+    /// 클라이언트에 보낸 select 응답 body 크기의 합계, 최대
+    pub select_bytes_total: u64,
+    pub select_bytes_max: u64,
+    /// 클라이언트에 보낸 update 응답 body 크기의 합계
+    pub add_response_bytes_total: u64,
+    pub cache_hit_cnt: u32,
+    pub cache_miss_cnt: u32,
+    pub seed_id_insert_cnt: u32,
+    pub rate_limited_cnt: u32,
+    /// 점검 중이라 503으로 응답한 update 요청 수
+    pub update_drained_cnt: u32,
+    /// force_commit_within을 사용해서 제거한 commit 요청(파라미터, body의 명령) 수
+    pub client_commit_stripped_cnt: u32,
+    pub compress_cnt: u32,
+    pub compress_bytes_before_total: usize,
+    pub compress_bytes_after_total: usize,
+    pub passthrough_cnt: u32,
+    pub host_fill_cnt: u32,
+    pub tstamp_fill_cnt: u32,
+    pub postdate_rewrite_cnt: u32,
+    pub postdate_invalid_cnt: u32,
+    pub sanitized_doc_cnt: u32,
+    /// field_max_bytes를 넘는 값을 잘라낸 doc 수
+    pub truncated_doc_cnt: u32,
+    pub dedup_doc_cnt: u32,
+    pub dropped_doc_cnt: u32,
+    pub duplicated_doc_cnt: u32,
+    pub bypass_cnt: u32,
+    pub cache_refresh_cnt: u32,
+    /// 최근 DB 조회에 실패해서 DB에 요청하지 않고 실패한 횟수
+    pub lookup_suppressed_cnt: u32,
+    /// max_new_seeds_per_minute를 넘어 seed_id 없이 보낸 doc 수
+    pub seed_insert_skipped_cnt: u32,
+    /// seed_id_regex에 맞지 않는 seed_id를 지운 doc 수
+    pub invalid_seed_id_cnt: u32,
+    /// url이 max_url_bytes를 넘어 seed_id를 넣지 않은 doc 수
+    pub url_too_long_cnt: u32,
+    /// doc_enrich_budget을 넘어 seed_id를 넣지 않은 doc 수
+    pub doc_budget_exceeded_cnt: u32,
+    /// 공유 cache(Redis)에서 찾은 횟수, 찾지 못한 횟수, 에러 횟수
+    pub shared_cache_hit_cnt: u32,
+    pub shared_cache_miss_cnt: u32,
+    pub shared_cache_err_cnt: u32,
+    /// 사용할 수 없는 method로 요청해서 405로 응답한 횟수
+    pub select_method_rejected_cnt: u32,
+    pub update_method_rejected_cnt: u32,
+    /// update_content_types에 없는 Content-Type으로 보내서 거부한 update 요청 수
+    pub update_content_type_rejected_cnt: u32,
+    /// 솔라로 보내지 않고 응답한 OPTIONS 요청 횟수
+    pub options_cnt: u32,
+    /// 허용하지 않은 ip에서 요청해서 403으로 응답한 횟수
+    pub select_ip_rejected_cnt: u32,
+    pub update_ip_rejected_cnt: u32,
+    /// select 응답 cache에서 응답한 횟수
+    pub select_cache_hit_cnt: u32,
+    /// select 응답 cache에 없어서 솔라에 요청한 횟수
+    pub select_cache_miss_cnt: u32,
+    pub slow_select_cnt: u32,
+    pub slow_update_cnt: u32,
+    /// 일시적인 DB 에러로 다시 시도한 횟수
+    pub db_retry_cnt: u32,
+    /// 솔라 요청 중 연결 에러가 발생한 횟수. 다시 시도한 요청도 포함함
+    pub upstream_conn_err_cnt: u32,
+    /// 솔라가 끊은 idle 연결로 보내서 다시 시도한 횟수
+    pub upstream_retry_cnt: u32,
+    /// 솔라와 연결을 맺지 못해 실패한 요청 수. 솔라가 내려갔거나 주소가 잘못된 경우
+    pub upstream_connect_fail_cnt: u32,
+    /// 솔라가 401로 응답한 횟수
+    pub upstream_unauthorized_cnt: u32,
+    /// select 요청을 다른 솔라에도 보낸 횟수와 그 중 다른 솔라가 먼저 응답한 횟수
+    pub hedge_fired_cnt: u32,
+    pub hedge_won_cnt: u32,
+    /// max_buffered_bytes를 넘어 받지 않은 update 요청 수
+    pub buffered_rejected_cnt: u32,
+    /// spool_threshold_bytes를 넘어 임시 파일에 받은 update 요청 수와 body 크기의 합
+    pub spool_cnt: u32,
+    pub spool_bytes_total: u64,
+    /// split_threshold_docs, split_threshold_bytes를 넘어 나누어 보낸 update 요청 수
+    pub update_split_cnt: u32,
+    /// 나누어 보낸 chunk 수
+    pub update_split_chunk_cnt: u32,
+    /// 솔라가 거부해서 남은 chunk를 보내지 않은 경우
+    pub update_split_chunk_rejected_cnt: u32,
+    /// path 분류, status code 구간별 솔라 응답 횟수
+    pub status_cnt: StatusCnt,
+    /// 분류별 에러 횟수. err_cnt에 솔라의 5xx 응답 횟수를 더한 값
+    pub error_kind_cnt: ErrorKindCnt,
+    /// collections에 설정한 컬렉션별 통계. 전역 설정을 사용하는 요청은 포함하지 않음
+    pub collection_cnt: BTreeMap<String, CollectionStat>,
+}
+
+/// 컬렉션 하나의 enrich 통계
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CollectionStat {
+    pub doc_cnt: usize,
+    pub cache_hit_cnt: u32,
+    pub cache_miss_cnt: u32,
+    pub seed_id_insert_cnt: u32,
+}
+
+impl CollectionStat {
+    fn delta(&self, previous: &Self) -> Self {
+        Self {
+            doc_cnt: self.doc_cnt.saturating_sub(previous.doc_cnt),
+            cache_hit_cnt: self.cache_hit_cnt.saturating_sub(previous.cache_hit_cnt),
+            cache_miss_cnt: self.cache_miss_cnt.saturating_sub(previous.cache_miss_cnt),
+            seed_id_insert_cnt: self
+                .seed_id_insert_cnt
+                .saturating_sub(previous.seed_id_insert_cnt),
+        }
+    }
+}
+
+/// 소요 시간의 합계, 최소, 최대
+#[derive(Debug, Clone, Copy)]
+pub struct DurationStat {
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Default for DurationStat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DurationStat {
+    pub const fn new() -> Self {
+        Self {
+            total: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+        }
+    }
+
+    pub fn add(&mut self, duration: Duration) {
+        self.total += duration;
+        if self.min > duration {
+            self.min = duration;
+        }
+        if self.max < duration {
+            self.max = duration;
+        }
+    }
+
+    /// cnt번 기록된 경우의 평균, 최소, 최대
+    pub fn summary(&self, cnt: u32) -> String {
+        format!(
+            "Average {:.2}ms, MIN: {}ms, MAX: {}ms",
+            self.total.as_secs_f32() * 1000f32 / cnt as f32,
+            self.min.as_millis(),
+            self.max.as_millis()
+        )
+    }
+}
+
+impl Default for WorkingCnt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkingCnt {
+    pub const fn new() -> Self {
+        Self {
+            select_cnt: 0,
+            add_cnt: 0,
+            add_doc_cnt: 0,
+            add_changed_doc_cnt: 0,
+            add_rejected_cnt: 0,
+            add_rejected_doc_cnt: 0,
+            err_cnt: 0,
+            add_duration_time_total: Duration::ZERO,
+            add_duration_time_min: Duration::MAX,
+            add_duration_time_max: (Duration::ZERO, 0, 0),
+            add_bytes_total: 0,
+            add_parse_time: DurationStat::new(),
+            add_enrich_time: DurationStat::new(),
+            add_solr_time: DurationStat::new(),
+            select_duration_time_total: Duration::ZERO,
+            select_duration_time_min: Duration::MAX,
+            select_duration_time_max: Duration::ZERO,
+            select_bytes_total: 0,
+            select_bytes_max: 0,
+            add_response_bytes_total: 0,
+            cache_hit_cnt: 0,
+            cache_miss_cnt: 0,
+            seed_id_insert_cnt: 0,
+            rate_limited_cnt: 0,
+            update_drained_cnt: 0,
+            client_commit_stripped_cnt: 0,
+            compress_cnt: 0,
+            compress_bytes_before_total: 0,
+            compress_bytes_after_total: 0,
+            passthrough_cnt: 0,
+            host_fill_cnt: 0,
+            tstamp_fill_cnt: 0,
+            postdate_rewrite_cnt: 0,
+            postdate_invalid_cnt: 0,
+            sanitized_doc_cnt: 0,
+            truncated_doc_cnt: 0,
+            dedup_doc_cnt: 0,
+            dropped_doc_cnt: 0,
+            duplicated_doc_cnt: 0,
+            bypass_cnt: 0,
+            cache_refresh_cnt: 0,
+            lookup_suppressed_cnt: 0,
+            seed_insert_skipped_cnt: 0,
+            invalid_seed_id_cnt: 0,
+            url_too_long_cnt: 0,
+            doc_budget_exceeded_cnt: 0,
+            shared_cache_hit_cnt: 0,
+            shared_cache_miss_cnt: 0,
+            shared_cache_err_cnt: 0,
+            select_method_rejected_cnt: 0,
+            update_method_rejected_cnt: 0,
+            update_content_type_rejected_cnt: 0,
+            options_cnt: 0,
+            select_ip_rejected_cnt: 0,
+            update_ip_rejected_cnt: 0,
+            select_cache_hit_cnt: 0,
+            select_cache_miss_cnt: 0,
+            slow_select_cnt: 0,
+            slow_update_cnt: 0,
+            db_retry_cnt: 0,
+            upstream_conn_err_cnt: 0,
+            upstream_retry_cnt: 0,
+            upstream_connect_fail_cnt: 0,
+            upstream_unauthorized_cnt: 0,
+            hedge_fired_cnt: 0,
+            hedge_won_cnt: 0,
+            buffered_rejected_cnt: 0,
+            spool_cnt: 0,
+            spool_bytes_total: 0,
+            update_split_cnt: 0,
+            update_split_chunk_cnt: 0,
+            update_split_chunk_rejected_cnt: 0,
+            status_cnt: StatusCnt::new(),
+            error_kind_cnt: ErrorKindCnt::new(),
+            collection_cnt: BTreeMap::new(),
+        }
+    }
+}
+
+impl DurationStat {
+    /// total은 previous와의 차이, 최소/최대는 현재 값
+    fn delta(&self, previous: &Self) -> Self {
+        Self {
+            total: self.total.saturating_sub(previous.total),
+            min: self.min,
+            max: self.max,
+        }
+    }
+
+    fn reset_min_max(&mut self) {
+        self.min = Duration::MAX;
+        self.max = Duration::ZERO;
+    }
+}
+
+impl WorkingCnt {
+    /// stats_reset이 false인 경우 사용하는, previous 이후 증가한 값
+    /// <br>
+    /// 최소/최대 시간은 reset_min_max()로 구간마다 초기화하므로 현재 값을 그대로 사용함
+    pub fn delta(&self, previous: &Self) -> Self {
+        Self {
+            select_cnt: self.select_cnt.saturating_sub(previous.select_cnt),
+            add_cnt: self.add_cnt.saturating_sub(previous.add_cnt),
+            add_doc_cnt: self.add_doc_cnt.saturating_sub(previous.add_doc_cnt),
+            add_changed_doc_cnt: self
+                .add_changed_doc_cnt
+                .saturating_sub(previous.add_changed_doc_cnt),
+            add_rejected_cnt: self
+                .add_rejected_cnt
+                .saturating_sub(previous.add_rejected_cnt),
+            add_rejected_doc_cnt: self
+                .add_rejected_doc_cnt
+                .saturating_sub(previous.add_rejected_doc_cnt),
+            err_cnt: self.err_cnt.saturating_sub(previous.err_cnt),
+            add_duration_time_total: self
+                .add_duration_time_total
+                .saturating_sub(previous.add_duration_time_total),
+            add_duration_time_min: self.add_duration_time_min,
+            add_duration_time_max: self.add_duration_time_max,
+            add_bytes_total: self
+                .add_bytes_total
+                .saturating_sub(previous.add_bytes_total),
+            add_parse_time: self.add_parse_time.delta(&previous.add_parse_time),
+            add_enrich_time: self.add_enrich_time.delta(&previous.add_enrich_time),
+            add_solr_time: self.add_solr_time.delta(&previous.add_solr_time),
+            select_duration_time_total: self
+                .select_duration_time_total
+                .saturating_sub(previous.select_duration_time_total),
+            select_duration_time_min: self.select_duration_time_min,
+            select_duration_time_max: self.select_duration_time_max,
+            select_bytes_total: self
+                .select_bytes_total
+                .saturating_sub(previous.select_bytes_total),
+            select_bytes_max: self.select_bytes_max,
+            add_response_bytes_total: self
+                .add_response_bytes_total
+                .saturating_sub(previous.add_response_bytes_total),
+            cache_hit_cnt: self.cache_hit_cnt.saturating_sub(previous.cache_hit_cnt),
+            cache_miss_cnt: self.cache_miss_cnt.saturating_sub(previous.cache_miss_cnt),
+            seed_id_insert_cnt: self
+                .seed_id_insert_cnt
+                .saturating_sub(previous.seed_id_insert_cnt),
+            rate_limited_cnt: self
+                .rate_limited_cnt
+                .saturating_sub(previous.rate_limited_cnt),
+            update_drained_cnt: self
+                .update_drained_cnt
+                .saturating_sub(previous.update_drained_cnt),
+            client_commit_stripped_cnt: self
+                .client_commit_stripped_cnt
+                .saturating_sub(previous.client_commit_stripped_cnt),
+            compress_cnt: self.compress_cnt.saturating_sub(previous.compress_cnt),
+            compress_bytes_before_total: self
+                .compress_bytes_before_total
+                .saturating_sub(previous.compress_bytes_before_total),
+            compress_bytes_after_total: self
+                .compress_bytes_after_total
+                .saturating_sub(previous.compress_bytes_after_total),
+            passthrough_cnt: self
+                .passthrough_cnt
+                .saturating_sub(previous.passthrough_cnt),
+            host_fill_cnt: self.host_fill_cnt.saturating_sub(previous.host_fill_cnt),
+            tstamp_fill_cnt: self
+                .tstamp_fill_cnt
+                .saturating_sub(previous.tstamp_fill_cnt),
+            postdate_rewrite_cnt: self
+                .postdate_rewrite_cnt
+                .saturating_sub(previous.postdate_rewrite_cnt),
+            postdate_invalid_cnt: self
+                .postdate_invalid_cnt
+                .saturating_sub(previous.postdate_invalid_cnt),
+            sanitized_doc_cnt: self
+                .sanitized_doc_cnt
+                .saturating_sub(previous.sanitized_doc_cnt),
+            truncated_doc_cnt: self
+                .truncated_doc_cnt
+                .saturating_sub(previous.truncated_doc_cnt),
+            dedup_doc_cnt: self.dedup_doc_cnt.saturating_sub(previous.dedup_doc_cnt),
+            dropped_doc_cnt: self
+                .dropped_doc_cnt
+                .saturating_sub(previous.dropped_doc_cnt),
+            duplicated_doc_cnt: self
+                .duplicated_doc_cnt
+                .saturating_sub(previous.duplicated_doc_cnt),
+            bypass_cnt: self.bypass_cnt.saturating_sub(previous.bypass_cnt),
+            cache_refresh_cnt: self
+                .cache_refresh_cnt
+                .saturating_sub(previous.cache_refresh_cnt),
+            lookup_suppressed_cnt: self
+                .lookup_suppressed_cnt
+                .saturating_sub(previous.lookup_suppressed_cnt),
+            seed_insert_skipped_cnt: self
+                .seed_insert_skipped_cnt
+                .saturating_sub(previous.seed_insert_skipped_cnt),
+            invalid_seed_id_cnt: self
+                .invalid_seed_id_cnt
+                .saturating_sub(previous.invalid_seed_id_cnt),
+            url_too_long_cnt: self
+                .url_too_long_cnt
+                .saturating_sub(previous.url_too_long_cnt),
+            doc_budget_exceeded_cnt: self
+                .doc_budget_exceeded_cnt
+                .saturating_sub(previous.doc_budget_exceeded_cnt),
+            shared_cache_hit_cnt: self
+                .shared_cache_hit_cnt
+                .saturating_sub(previous.shared_cache_hit_cnt),
+            shared_cache_miss_cnt: self
+                .shared_cache_miss_cnt
+                .saturating_sub(previous.shared_cache_miss_cnt),
+            shared_cache_err_cnt: self
+                .shared_cache_err_cnt
+                .saturating_sub(previous.shared_cache_err_cnt),
+            select_method_rejected_cnt: self
+                .select_method_rejected_cnt
+                .saturating_sub(previous.select_method_rejected_cnt),
+            update_method_rejected_cnt: self
+                .update_method_rejected_cnt
+                .saturating_sub(previous.update_method_rejected_cnt),
+            update_content_type_rejected_cnt: self
+                .update_content_type_rejected_cnt
+                .saturating_sub(previous.update_content_type_rejected_cnt),
+            options_cnt: self.options_cnt.saturating_sub(previous.options_cnt),
+            select_ip_rejected_cnt: self
+                .select_ip_rejected_cnt
+                .saturating_sub(previous.select_ip_rejected_cnt),
+            update_ip_rejected_cnt: self
+                .update_ip_rejected_cnt
+                .saturating_sub(previous.update_ip_rejected_cnt),
+            select_cache_hit_cnt: self
+                .select_cache_hit_cnt
+                .saturating_sub(previous.select_cache_hit_cnt),
+            select_cache_miss_cnt: self
+                .select_cache_miss_cnt
+                .saturating_sub(previous.select_cache_miss_cnt),
+            slow_select_cnt: self
+                .slow_select_cnt
+                .saturating_sub(previous.slow_select_cnt),
+            slow_update_cnt: self
+                .slow_update_cnt
+                .saturating_sub(previous.slow_update_cnt),
+            db_retry_cnt: self.db_retry_cnt.saturating_sub(previous.db_retry_cnt),
+            upstream_conn_err_cnt: self
+                .upstream_conn_err_cnt
+                .saturating_sub(previous.upstream_conn_err_cnt),
+            upstream_retry_cnt: self
+                .upstream_retry_cnt
+                .saturating_sub(previous.upstream_retry_cnt),
+            upstream_connect_fail_cnt: self
+                .upstream_connect_fail_cnt
+                .saturating_sub(previous.upstream_connect_fail_cnt),
+            upstream_unauthorized_cnt: self
+                .upstream_unauthorized_cnt
+                .saturating_sub(previous.upstream_unauthorized_cnt),
+            hedge_fired_cnt: self
+                .hedge_fired_cnt
+                .saturating_sub(previous.hedge_fired_cnt),
+            hedge_won_cnt: self.hedge_won_cnt.saturating_sub(previous.hedge_won_cnt),
+            buffered_rejected_cnt: self
+                .buffered_rejected_cnt
+                .saturating_sub(previous.buffered_rejected_cnt),
+            spool_cnt: self.spool_cnt.saturating_sub(previous.spool_cnt),
+            spool_bytes_total: self
+                .spool_bytes_total
+                .saturating_sub(previous.spool_bytes_total),
+            update_split_cnt: self
+                .update_split_cnt
+                .saturating_sub(previous.update_split_cnt),
+            update_split_chunk_cnt: self
+                .update_split_chunk_cnt
+                .saturating_sub(previous.update_split_chunk_cnt),
+            update_split_chunk_rejected_cnt: self
+                .update_split_chunk_rejected_cnt
+                .saturating_sub(previous.update_split_chunk_rejected_cnt),
+            status_cnt: self.status_cnt.delta(&previous.status_cnt),
+            error_kind_cnt: self.error_kind_cnt.delta(&previous.error_kind_cnt),
+            collection_cnt: self
+                .collection_cnt
+                .iter()
+                .map(|(name, stat)| {
+                    let previous = previous.collection_cnt.get(name).copied();
+                    (name.clone(), stat.delta(&previous.unwrap_or_default()))
+                })
+                .collect(),
+        }
+    }
+
+    /// 컬렉션 name의 통계. 전역 설정(빈 이름)인 경우 None
+    pub fn collection(&mut self, name: &str) -> Option<&mut CollectionStat> {
+        if name.is_empty() {
+            return None;
+        }
+        if !self.collection_cnt.contains_key(name) {
+            self.collection_cnt
+                .insert(name.to_string(), CollectionStat::default());
+        }
+        self.collection_cnt.get_mut(name)
+    }
+
+    /// 구간별 최소/최대 시간을 초기화함
+    fn reset_min_max(&mut self) {
+        self.add_duration_time_min = Duration::MAX;
+        self.add_duration_time_max = (Duration::ZERO, 0, 0);
+        self.add_parse_time.reset_min_max();
+        self.add_enrich_time.reset_min_max();
+        self.add_solr_time.reset_min_max();
+        self.select_duration_time_min = Duration::MAX;
+        self.select_duration_time_max = Duration::ZERO;
+        self.select_bytes_max = 0;
+    }
+}
+
+/// 클라이언트에 보낸 select, update 응답 body 크기. body가 끝나는 poll 안에서 lock 없이 셈
+/// <br>
+/// stats_loop가 구간을 나눌 때, /proxy/stats에서 조회할 때 WorkingCnt에 합침
+pub struct ResponseBytes {
+    select_total: AtomicU64,
+    select_max: AtomicU64,
+    update_total: AtomicU64,
+}
+
+impl ResponseBytes {
+    pub const fn new() -> Self {
+        Self {
+            select_total: AtomicU64::new(0),
+            select_max: AtomicU64::new(0),
+            update_total: AtomicU64::new(0),
+        }
+    }
+
+    pub fn add_select(&self, bytes: u64) {
+        self.select_total.fetch_add(bytes, Ordering::Relaxed);
+        self.select_max.fetch_max(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_update(&self, bytes: u64) {
+        self.update_total.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// 지금까지 센 값을 cnt에 더하고 0으로 초기화함
+    pub fn fold_into(&self, cnt: &mut WorkingCnt) {
+        cnt.select_bytes_total += self.select_total.swap(0, Ordering::Relaxed);
+        cnt.select_bytes_max = cnt
+            .select_bytes_max
+            .max(self.select_max.swap(0, Ordering::Relaxed));
+        cnt.add_response_bytes_total += self.update_total.swap(0, Ordering::Relaxed);
+    }
+}
+
+/// stats_loop를 시작한 뒤 누적한 주요 카운터. 종료할 때 마지막 구간과 함께 남김
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifetimeCnt {
+    pub select_cnt: u64,
+    pub add_cnt: u64,
+    pub add_doc_cnt: u64,
+    pub add_rejected_cnt: u64,
+    pub err_cnt: u64,
+    pub started: Instant,
+}
+
+impl LifetimeCnt {
+    pub fn new() -> Self {
+        Self {
+            select_cnt: 0,
+            add_cnt: 0,
+            add_doc_cnt: 0,
+            add_rejected_cnt: 0,
+            err_cnt: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// 구간 통계를 더함
+    fn add(&mut self, window: &WorkingCnt) {
+        self.select_cnt += u64::from(window.select_cnt);
+        self.add_cnt += u64::from(window.add_cnt);
+        self.add_doc_cnt += window.add_doc_cnt as u64;
+        self.add_rejected_cnt += u64::from(window.add_rejected_cnt);
+        self.err_cnt += u64::from(window.err_cnt);
+    }
+}
+
+impl Default for LifetimeCnt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// interval마다 집계한 통계를 report로 넘김. stop을 받으면 마지막 구간과 누적 카운터를 finish로 넘긴 뒤 종료함
+/// <br>
+/// reset이 true인 경우 구간마다 카운터를 초기화하고, false인 경우 카운터는 계속 증가하며
+/// 이전 구간과의 차이를 넘김. 최소/최대 시간은 두 경우 모두 구간별 값임
+/// <br>
+/// insert_guard는 구간마다 다시 셈. report를 바꿔도 구간과 같이 초기화되도록 여기서 reset함.
+/// response_bytes는 구간을 나누기 전에 working_cnt에 합침
+#[allow(clippy::too_many_arguments)]
+pub async fn stats_loop<F, Fut, G, GFut>(
+    working_cnt: &Mutex<WorkingCnt>,
+    insert_guard: &InsertGuard,
+    response_bytes: &ResponseBytes,
+    interval: Duration,
+    reset: bool,
+    mut stop: Receiver<()>,
+    mut report: F,
+    finish: G,
+) where
+    F: FnMut(WorkingCnt) -> Fut,
+    Fut: Future<Output = ()>,
+    G: FnOnce(WorkingCnt, LifetimeCnt) -> GFut,
+    GFut: Future<Output = ()>,
+{
+    let mut previous = WorkingCnt::new();
+    let mut lifetime = LifetimeCnt::new();
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        let stopped = tokio::select! {
+            _ = ticker.tick() => false,
+            _ = &mut stop => true,
+        };
+
+        let window = {
+            let mut cnt_lock = working_cnt.lock().await;
+            response_bytes.fold_into(&mut cnt_lock);
+            take_window(&mut cnt_lock, &mut previous, reset)
+        };
+        lifetime.add(&window);
+        insert_guard.reset();
+
+        if stopped {
+            finish(window, lifetime).await;
+            return;
+        }
+        report(window).await;
+    }
+}
+
+/// 이번 구간의 통계를 반환하고 다음 구간을 준비함
+fn take_window(cnt: &mut WorkingCnt, previous: &mut WorkingCnt, reset: bool) -> WorkingCnt {
+    if reset {
+        return std::mem::take(cnt);
+    }
+
+    let window = cnt.delta(previous);
+    cnt.reset_min_max();
+    *previous = cnt.clone();
+    window
+}
+
+/// 구간 통계를 로그로 남김
+pub async fn log_stats(cnt: WorkingCnt) {
+    let cache_len = SEED_ID_CACHE.len().await;
+    let (cache_lock_cnt, cache_lock_wait_cnt) = SEED_ID_CACHE.take_lock_cnt();
+    let cache_evict_cnt = SEED_ID_CACHE.take_evict_cnt();
+    let cache_write_high = SEED_CACHE_WRITER.depth.take_high();
+
+    info!(
+        "SELECT {}, ADD {}[{} doc, {} changed], ERROR {}",
+        cnt.select_cnt, cnt.add_cnt, cnt.add_doc_cnt, cnt.add_changed_doc_cnt, cnt.err_cnt
+    );
+    // 솔라가 거부한 update는 proxy 에러가 아니므로 ERROR에 포함되지 않음
+    if cnt.add_rejected_cnt > 0 {
+        warn!(
+            "ADD REJECTED {}[{} doc]",
+            cnt.add_rejected_cnt, cnt.add_rejected_doc_cnt
+        );
+    }
+    if cnt.passthrough_cnt > 0 {
+        info!("PASSTHROUGH {}", cnt.passthrough_cnt);
+    }
+    if SOLR.is_update_draining() || cnt.update_drained_cnt > 0 {
+        info!(
+            "UPDATE DRAIN: {}, rejected {}",
+            if SOLR.is_update_draining() {
+                "on"
+            } else {
+                "off"
+            },
+            cnt.update_drained_cnt
+        );
+    }
+    if cnt.client_commit_stripped_cnt > 0 {
+        info!("CLIENT COMMIT STRIPPED {}", cnt.client_commit_stripped_cnt);
+    }
+    let error_summary = cnt.error_kind_cnt.summary();
+    if !error_summary.is_empty() {
+        info!("ERROR KIND {}", error_summary);
+    }
+    let status_summary = cnt.status_cnt.summary();
+    if !status_summary.is_empty() {
+        info!("SOLR STATUS {}", status_summary);
+    }
+    if cnt.slow_select_cnt > 0 || cnt.slow_update_cnt > 0 {
+        info!(
+            "SLOW SELECT {}, SLOW UPDATE {}",
+            cnt.slow_select_cnt, cnt.slow_update_cnt
+        );
+    }
+    if cnt.select_method_rejected_cnt > 0 || cnt.update_method_rejected_cnt > 0 {
+        info!(
+            "METHOD NOT ALLOWED: select {}, update {}",
+            cnt.select_method_rejected_cnt, cnt.update_method_rejected_cnt
+        );
+    }
+    if cnt.update_content_type_rejected_cnt > 0 {
+        info!(
+            "UNSUPPORTED CONTENT TYPE {}",
+            cnt.update_content_type_rejected_cnt
+        );
+    }
+    if cnt.options_cnt > 0 {
+        info!("OPTIONS {}", cnt.options_cnt);
+    }
+    if cnt.select_ip_rejected_cnt > 0 || cnt.update_ip_rejected_cnt > 0 {
+        info!(
+            "IP NOT ALLOWED: select {}, update {}",
+            cnt.select_ip_rejected_cnt, cnt.update_ip_rejected_cnt
+        );
+    }
+    if cnt.bypass_cnt > 0 {
+        info!("ENRICH BYPASSED {}", cnt.bypass_cnt);
+    }
+    if cnt.host_fill_cnt > 0 {
+        info!("HOST FIELDS FILLED {} doc", cnt.host_fill_cnt);
+    }
+    if cnt.sanitized_doc_cnt > 0 {
+        info!("SANITIZED {} doc", cnt.sanitized_doc_cnt);
+    }
+    if cnt.truncated_doc_cnt > 0 {
+        info!("TRUNCATED {} doc", cnt.truncated_doc_cnt);
+    }
+    if cnt.duplicated_doc_cnt > 0 {
+        info!("DUPLICATED {} doc", cnt.duplicated_doc_cnt);
+    }
+    if cnt.dropped_doc_cnt > 0 {
+        info!("DROPPED {} doc", cnt.dropped_doc_cnt);
+    }
+    if cnt.dedup_doc_cnt > 0 {
+        info!("DEDUPLICATED {} doc", cnt.dedup_doc_cnt);
+    }
+    if settings().normalize_dates {
+        info!(
+            "DATE FIELDS: tstamp filled {}, postdate rewritten {}, postdate invalid {}",
+            cnt.tstamp_fill_cnt, cnt.postdate_rewrite_cnt, cnt.postdate_invalid_cnt
+        );
+    }
+    if cnt.select_cnt > 0 {
+        info!(
+            "SELECT: Average {:.2}ms, MIN: {}ms, MAX: {}ms",
+            cnt.select_duration_time_total.as_millis() as f32 / cnt.select_cnt as f32,
+            cnt.select_duration_time_min.as_millis(),
+            cnt.select_duration_time_max.as_millis(),
+        );
+        info!(
+            "SELECT RESPONSE: Total {} bytes, MAX: {} bytes",
+            cnt.select_bytes_total, cnt.select_bytes_max
+        );
+    }
+    if cnt.add_cnt > 0 && cnt.add_doc_cnt > 0 {
+        info!(
+        "ADD: Average {:.2}ms, Average per doc: {:.2}ms, MIN: {}ms, MAX: {}ms[{} doc, {} bytes], Total {} bytes",
+        cnt.add_duration_time_total.as_millis() as f32 / cnt.add_cnt as f32,
+        cnt.add_duration_time_total.as_millis() as f32 / cnt.add_doc_cnt as f32,
+        cnt.add_duration_time_min.as_millis(),
+        cnt.add_duration_time_max.0.as_millis(),
+        cnt.add_duration_time_max.1,
+        cnt.add_duration_time_max.2,
+        cnt.add_bytes_total
+    );
+    }
+    // 분포는 stats_reset과 관계없이 구간마다 새로 셈
+    for (name, histogram) in LATENCY.histograms() {
+        let snapshot = histogram.take();
+        if snapshot.count() > 0 {
+            info!("LATENCY {}: {}", name, snapshot.summary());
+        }
+    }
+    if cnt.add_response_bytes_total > 0 {
+        info!("ADD RESPONSE: Total {} bytes", cnt.add_response_bytes_total);
+    }
+    if cnt.add_cnt > 0 {
+        info!(
+            "ADD PARSE: {} / ENRICH: {} / SOLR: {}",
+            cnt.add_parse_time.summary(cnt.add_cnt),
+            cnt.add_enrich_time.summary(cnt.add_cnt),
+            cnt.add_solr_time.summary(cnt.add_cnt)
+        );
+    }
+
+    if cnt.cache_hit_cnt > 0 || cnt.cache_miss_cnt > 0 {
+        let hit_percent: f32;
+
+        if cnt.cache_hit_cnt == 0 {
+            hit_percent = 0f32;
+        } else if cnt.cache_miss_cnt == 0 {
+            hit_percent = 100f32;
+        } else {
+            hit_percent =
+                cnt.cache_hit_cnt as f32 / (cnt.cache_hit_cnt + cnt.cache_miss_cnt) as f32 * 100f32;
+        }
+
+        info!(
+        "seed_id cache: Hit {}, Miss {}, Cache Hit Rate {:.2}%, New seed_id Insert: {}, Cache Len: {}, {} bytes, Evicted: {}",
+        cnt.cache_hit_cnt, cnt.cache_miss_cnt, hit_percent, cnt.seed_id_insert_cnt, cache_len,
+        SEED_ID_CACHE.entry_bytes(), cache_evict_cnt
+    );
+    }
+    for (name, stat) in &cnt.collection_cnt {
+        info!(
+            "COLLECTION {}: {} doc, seed_id cache Hit {}, Miss {}, New seed_id Insert: {}",
+            name, stat.doc_cnt, stat.cache_hit_cnt, stat.cache_miss_cnt, stat.seed_id_insert_cnt
+        );
+    }
+    if cnt.cache_refresh_cnt > 0 {
+        info!("seed_id cache refreshed: {}", cnt.cache_refresh_cnt);
+    }
+    if cnt.lookup_suppressed_cnt > 0 {
+        info!("seed_id lookup suppressed: {}", cnt.lookup_suppressed_cnt);
+    }
+    if cnt.shared_cache_hit_cnt > 0 || cnt.shared_cache_miss_cnt > 0 || cnt.shared_cache_err_cnt > 0
+    {
+        info!(
+            "shared seed_id cache: Hit {}, Miss {}, Error {}",
+            cnt.shared_cache_hit_cnt, cnt.shared_cache_miss_cnt, cnt.shared_cache_err_cnt
+        );
+    }
+    if cnt.invalid_seed_id_cnt > 0 {
+        info!("invalid seed_id removed: {}", cnt.invalid_seed_id_cnt);
+    }
+    if cnt.url_too_long_cnt > 0 || cnt.doc_budget_exceeded_cnt > 0 {
+        info!(
+            "ENRICH SKIPPED: url too long {} doc, budget exceeded {} doc",
+            cnt.url_too_long_cnt, cnt.doc_budget_exceeded_cnt
+        );
+    }
+    if cnt.seed_insert_skipped_cnt > 0 {
+        info!("seed_id insert skipped: {}", cnt.seed_insert_skipped_cnt);
+    }
+    if cache_lock_wait_cnt > 0 {
+        info!(
+            "seed_id cache lock: {}, Wait {}",
+            cache_lock_cnt, cache_lock_wait_cnt
+        );
+        // 10% 넘게 기다린 경우 shard 수를 늘리도록 알림
+        if cache_lock_wait_cnt * 10 > cache_lock_cnt {
+            warn!(
+                "SEED_ID_CACHE_CONTENDED: {} / {} lock waited, seed_id_cache_shards: {}",
+                cache_lock_wait_cnt,
+                cache_lock_cnt,
+                settings().seed_id_cache_shards
+            );
+        }
+    }
+    if cache_write_high > 0 {
+        info!(
+            "seed_id cache write queue: {}, High {}",
+            SEED_CACHE_WRITER.depth.current(),
+            cache_write_high
+        );
+        if cache_write_high > CACHE_WRITE_BACKLOG_WARN {
+            warn!(
+                "SEED_ID_CACHE_WRITE_BACKLOG: {} queued (warn over {})",
+                cache_write_high, CACHE_WRITE_BACKLOG_WARN
+            );
+        }
+    }
+    if cnt.select_cache_hit_cnt > 0 || cnt.select_cache_miss_cnt > 0 {
+        info!(
+            "select cache: Hit {}, Miss {}",
+            cnt.select_cache_hit_cnt, cnt.select_cache_miss_cnt
+        );
+    }
+    let in_flight: Vec<String> = IN_FLIGHT
+        .gauges()
+        .iter()
+        .map(|(name, gauge)| format!("{} {} (max {})", name, gauge.current(), gauge.take_high()))
+        .collect();
+    info!("IN-FLIGHT {}", in_flight.join(", "));
+    info!(
+        "BUFFERED {} bytes (max {}), rejected {}",
+        BUFFERED_BYTES.current(),
+        BUFFERED_BYTES.take_high(),
+        cnt.buffered_rejected_cnt
+    );
+    if cnt.spool_cnt > 0 {
+        info!(
+            "SPOOLED {}, Total {} bytes",
+            cnt.spool_cnt, cnt.spool_bytes_total
+        );
+    }
+    if cnt.update_split_cnt > 0 {
+        info!(
+            "SPLIT UPDATE {}, {} chunks, rejected {}",
+            cnt.update_split_cnt, cnt.update_split_chunk_cnt, cnt.update_split_chunk_rejected_cnt
+        );
+    }
+    for limit in [&*UPDATE_LIMIT, &*SELECT_LIMIT] {
+        if limit.is_enabled() {
+            info!(
+                "{} concurrency: in-flight {}, queued {}",
+                limit.name(),
+                limit.in_flight(),
+                limit.queued()
+            );
+        }
+    }
+    if cnt.compress_cnt > 0 {
+        info!(
+            "upstream gzip: {} requests, {} bytes -> {} bytes ({:.2}%)",
+            cnt.compress_cnt,
+            cnt.compress_bytes_before_total,
+            cnt.compress_bytes_after_total,
+            cnt.compress_bytes_after_total as f32 / cnt.compress_bytes_before_total as f32 * 100f32
+        );
+    }
+    if RATE_LIMITER.is_enabled() {
+        let tracked_ip_cnt = RATE_LIMITER.cleanup(Instant::now()).await;
+        info!(
+            "RATE LIMITED {}, tracked ip: {}",
+            cnt.rate_limited_cnt, tracked_ip_cnt
+        );
+    }
+    if cnt.db_retry_cnt > 0 {
+        info!("DB RETRY {}", cnt.db_retry_cnt);
+    }
+    if cnt.upstream_conn_err_cnt > 0 {
+        info!(
+            "SOLR CONNECTION ERROR {}, retried {}, connect failed {}",
+            cnt.upstream_conn_err_cnt, cnt.upstream_retry_cnt, cnt.upstream_connect_fail_cnt
+        );
+    }
+    if cnt.upstream_unauthorized_cnt > 0 {
+        info!("SOLR UNAUTHORIZED {}", cnt.upstream_unauthorized_cnt);
+    }
+    if cnt.hedge_fired_cnt > 0 {
+        info!(
+            "SOLR HEDGE {}, won {}",
+            cnt.hedge_fired_cnt, cnt.hedge_won_cnt
+        );
+    }
+    let idle_cnt = CON.num_idle();
+    info!(
+        "DB connection pool cnt: {} (idle {}, active {})",
+        CON.size(),
+        idle_cnt,
+        (CON.size() as usize).saturating_sub(idle_cnt)
+    );
+    info!("");
+}
+
+/// 종료할 때 마지막 구간의 통계와 누적 카운터, seed_id cache 크기를 로그로 남김
+pub async fn log_final_stats(cnt: WorkingCnt, lifetime: LifetimeCnt) {
+    info!("STATS final interval");
+    log_stats(cnt).await;
+    info!(
+        "LIFETIME: SELECT {}, ADD {}[{} doc], REJECTED {}, ERROR {}, uptime {}s",
+        lifetime.select_cnt,
+        lifetime.add_cnt,
+        lifetime.add_doc_cnt,
+        lifetime.add_rejected_cnt,
+        lifetime.err_cnt,
+        lifetime.started.elapsed().as_secs()
+    );
+    info!(
+        "seed_id cache: Len {}, {} bytes",
+        SEED_ID_CACHE.len().await,
+        SEED_ID_CACHE.entry_bytes()
+    );
+}
+
+#[tokio::test]
+async fn stats_loop_test() {
+    use tokio::sync::{mpsc, oneshot};
+
+    for reset in [true, false] {
+        let working_cnt = Mutex::new(WorkingCnt::new());
+        let (stop_send, stop_recv) = oneshot::channel();
+        let (report_send, mut report_recv) = mpsc::unbounded_channel();
+        let (finish_send, finish_recv) = oneshot::channel();
+        let insert_guard = InsertGuard::new(1);
+        let response_bytes = ResponseBytes::new();
+        assert!(insert_guard.try_insert());
+        assert!(!insert_guard.try_insert());
+
+        let stats = stats_loop(
+            &working_cnt,
+            &insert_guard,
+            &response_bytes,
+            Duration::from_secs(1),
+            reset,
+            stop_recv,
+            |window| {
+                let report_send = report_send.clone();
+                async move {
+                    report_send.send(window).unwrap();
+                }
+            },
+            |window, lifetime| async move {
+                let _ = finish_send.send((window, lifetime));
+            },
+        );
+        let work = async {
+            for select_cnt in [3, 5] {
+                {
+                    let mut cnt_lock = working_cnt.lock().await;
+                    cnt_lock.select_cnt += select_cnt;
+                    cnt_lock.select_duration_time_total += Duration::from_millis(select_cnt as u64);
+                    cnt_lock.select_duration_time_max = Duration::from_millis(select_cnt as u64);
+                }
+                response_bytes.add_select(select_cnt as u64 * 10);
+                response_bytes.add_select(select_cnt as u64);
+                let window = report_recv.recv().await.unwrap();
+                assert_eq!(window.select_cnt, select_cnt);
+                // 응답 bytes도 구간에 합치며 최대값은 구간별 값임
+                assert_eq!(window.select_bytes_total, select_cnt as u64 * 11);
+                assert_eq!(window.select_bytes_max, select_cnt as u64 * 10);
+                // 구간마다 insert_guard도 다시 셈
+                assert!(!insert_guard.is_tripped());
+                assert!(insert_guard.try_insert());
+                assert!(!insert_guard.try_insert());
+                assert_eq!(
+                    window.select_duration_time_total,
+                    Duration::from_millis(select_cnt as u64)
+                );
+                assert_eq!(
+                    window.select_duration_time_max,
+                    Duration::from_millis(select_cnt as u64)
+                );
+            }
+
+            // reset하지 않는 경우 카운터는 누적됨
+            let expected = if reset { 0 } else { 8 };
+            assert_eq!(working_cnt.lock().await.select_cnt, expected);
+
+            // 종료 시 interval을 기다리지 않고 마지막 구간과 누적 카운터를 finish로 넘김
+            working_cnt.lock().await.add_cnt += 1;
+            response_bytes.add_update(7);
+            stop_send.send(()).unwrap();
+            let (window, lifetime) = finish_recv.await.unwrap();
+            assert_eq!(window.add_cnt, 1);
+            assert_eq!(window.add_response_bytes_total, 7);
+            assert_eq!(window.select_bytes_total, 0);
+            assert_eq!(window.select_cnt, 0);
+            assert_eq!(lifetime.select_cnt, 8);
+            assert_eq!(lifetime.add_cnt, 1);
+        };
+
+        let start = Instant::now();
+        tokio::join!(stats, work);
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert!(report_recv.try_recv().is_err());
+    }
+}
+
+#[test]
+fn duration_stat_test() {
+    let mut stat = DurationStat::new();
+    for ms in [30, 10, 20] {
+        stat.add(Duration::from_millis(ms));
+    }
+    assert_eq!(stat.total, Duration::from_millis(60));
+    assert_eq!(stat.min, Duration::from_millis(10));
+    assert_eq!(stat.max, Duration::from_millis(30));
+    assert_eq!(stat.summary(3), "Average 20.00ms, MIN: 10ms, MAX: 30ms");
+}
+
+#[test]
+fn collection_stat_test() {
+    let mut previous = WorkingCnt::new();
+    // 전역 설정은 컬렉션별 통계에 포함하지 않음
+    assert!(previous.collection("").is_none());
+    previous.collection("ko").unwrap().doc_cnt += 3;
+
+    let mut cnt = previous.clone();
+    cnt.collection("ko").unwrap().doc_cnt += 2;
+    cnt.collection("ja").unwrap().cache_miss_cnt += 1;
+    let window = cnt.delta(&previous);
+    assert_eq!(window.collection_cnt["ko"].doc_cnt, 2);
+    assert_eq!(
+        window.collection_cnt["ja"],
+        CollectionStat {
+            cache_miss_cnt: 1,
+            ..CollectionStat::default()
+        }
+    );
+}
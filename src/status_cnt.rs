@@ -0,0 +1,183 @@
+use hyper::StatusCode;
+use serde_json::{json, Map, Value};
+
+/// 통계용 path 분류
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathClass {
+    Select,
+    Update,
+    Passthrough,
+    /// admin, 통계, 없는 path 등 솔라로 보내지 않는 요청
+    Proxy,
+}
+
+impl PathClass {
+    const ALL: [PathClass; 4] = [
+        PathClass::Select,
+        PathClass::Update,
+        PathClass::Passthrough,
+        PathClass::Proxy,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PathClass::Select => "select",
+            PathClass::Update => "update",
+            PathClass::Passthrough => "passthrough",
+            PathClass::Proxy => "proxy",
+        }
+    }
+}
+
+/// status code를 백의 자리로 묶은 값. 1xx 등 그 외의 값은 other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusBucket {
+    Success,
+    Redirect,
+    ClientError,
+    ServerError,
+    Other,
+}
+
+impl StatusBucket {
+    const ALL: [StatusBucket; 5] = [
+        StatusBucket::Success,
+        StatusBucket::Redirect,
+        StatusBucket::ClientError,
+        StatusBucket::ServerError,
+        StatusBucket::Other,
+    ];
+
+    pub fn from_status(status: StatusCode) -> Self {
+        match status.as_u16() {
+            200..=299 => StatusBucket::Success,
+            300..=399 => StatusBucket::Redirect,
+            400..=499 => StatusBucket::ClientError,
+            500..=599 => StatusBucket::ServerError,
+            _ => StatusBucket::Other,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            StatusBucket::Success => "2xx",
+            StatusBucket::Redirect => "3xx",
+            StatusBucket::ClientError => "4xx",
+            StatusBucket::ServerError => "5xx",
+            StatusBucket::Other => "other",
+        }
+    }
+}
+
+/// path 분류, status code 구간별 응답 횟수. 솔라의 응답과 proxy가 만든 에러 응답을 모두 셈
+/// <br>
+/// 고정 크기 배열을 사용하므로 요청 종류와 관계없이 메모리 사용량이 일정함
+#[derive(Debug, Clone)]
+pub struct StatusCnt {
+    cnt: [[u32; StatusBucket::ALL.len()]; PathClass::ALL.len()],
+}
+
+impl Default for StatusCnt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusCnt {
+    pub const fn new() -> Self {
+        Self {
+            cnt: [[0; StatusBucket::ALL.len()]; PathClass::ALL.len()],
+        }
+    }
+
+    pub fn record(&mut self, path: PathClass, status: StatusCode) {
+        self.cnt[path as usize][StatusBucket::from_status(status) as usize] += 1;
+    }
+
+    pub fn get(&self, path: PathClass, bucket: StatusBucket) -> u32 {
+        self.cnt[path as usize][bucket as usize]
+    }
+
+    /// previous 이후 증가한 횟수
+    pub fn delta(&self, previous: &Self) -> Self {
+        let mut delta = Self::new();
+        for path in 0..PathClass::ALL.len() {
+            for bucket in 0..StatusBucket::ALL.len() {
+                delta.cnt[path][bucket] =
+                    self.cnt[path][bucket].saturating_sub(previous.cnt[path][bucket]);
+            }
+        }
+        delta
+    }
+
+    /// 분 단위 로그용. 0인 값은 생략함. 예: select 2xx:10 5xx:1, update 2xx:3
+    pub fn summary(&self) -> String {
+        PathClass::ALL
+            .iter()
+            .filter_map(|&path| {
+                let buckets: Vec<String> = StatusBucket::ALL
+                    .iter()
+                    .filter(|&&bucket| self.get(path, bucket) > 0)
+                    .map(|&bucket| format!("{}:{}", bucket.name(), self.get(path, bucket)))
+                    .collect();
+                if buckets.is_empty() {
+                    None
+                } else {
+                    Some(format!("{} {}", path.name(), buckets.join(" ")))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// 통계 endpoint용. 0인 값도 모두 포함함
+    pub fn to_json(&self) -> Value {
+        let mut paths = Map::new();
+        for path in PathClass::ALL {
+            let mut buckets = Map::new();
+            for bucket in StatusBucket::ALL {
+                buckets.insert(bucket.name().to_string(), json!(self.get(path, bucket)));
+            }
+            paths.insert(path.name().to_string(), Value::Object(buckets));
+        }
+        Value::Object(paths)
+    }
+}
+
+#[test]
+fn status_cnt_test() {
+    let mut status_cnt = StatusCnt::new();
+    for status in [200, 200, 204, 400, 503] {
+        status_cnt.record(PathClass::Select, StatusCode::from_u16(status).unwrap());
+    }
+    status_cnt.record(PathClass::Update, StatusCode::OK);
+    status_cnt.record(PathClass::Passthrough, StatusCode::MOVED_PERMANENTLY);
+    status_cnt.record(PathClass::Passthrough, StatusCode::CONTINUE);
+    status_cnt.record(PathClass::Proxy, StatusCode::NOT_FOUND);
+
+    assert_eq!(status_cnt.get(PathClass::Select, StatusBucket::Success), 3);
+    assert_eq!(
+        status_cnt.get(PathClass::Select, StatusBucket::ClientError),
+        1
+    );
+    assert_eq!(
+        status_cnt.get(PathClass::Select, StatusBucket::ServerError),
+        1
+    );
+    assert_eq!(
+        status_cnt.get(PathClass::Update, StatusBucket::ServerError),
+        0
+    );
+    assert_eq!(
+        status_cnt.summary(),
+        "select 2xx:3 4xx:1 5xx:1, update 2xx:1, passthrough 3xx:1 other:1, proxy 4xx:1"
+    );
+
+    let value = status_cnt.to_json();
+    assert_eq!(value["select"]["2xx"], 3);
+    assert_eq!(value["update"]["4xx"], 0);
+    assert_eq!(value["passthrough"]["other"], 1);
+    assert_eq!(value["proxy"]["4xx"], 1);
+
+    assert_eq!(StatusCnt::new().summary(), "");
+}
@@ -0,0 +1,198 @@
+use crate::util::{self, StrError};
+use crate::{query, BoxedError};
+use hyper::body::Bytes;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::http::uri::Uri;
+use hyper::{Body, HeaderMap, Method, Request, StatusCode};
+
+/// update 내용을 body 대신 파라미터로 보내는 솔라 파라미터
+const PARAM_STREAM_BODY: &str = "stream.body";
+
+/// stream.body에서 꺼낸 xml을 솔라로 보낼 때 사용하는 Content-Type
+const XML_CONTENT_TYPE: &str = "text/xml; charset=utf-8";
+
+/// query string에 stream.body가 있는지 확인함. 이 경우 GET으로 보낸 update도 처리함
+pub fn in_query(uri: &Uri) -> bool {
+    uri.query()
+        .is_some_and(|query| query.split('&').any(is_stream_body))
+}
+
+/// stream.body로 보낸 update인 경우 xml을 꺼내 decode하고, 요청을 xml body를 보내는 POST로 바꿈
+/// <br>
+/// query string의 stream.body는 제거하고, form으로 보낸 경우 나머지 form 파라미터는 query string에 붙임
+/// <br>
+/// decode한 xml이 max_bytes를 넘으면 413 에러. stream.body가 없는 경우 요청을 바꾸지 않고 None
+pub async fn take(req: &mut Request<Body>, max_bytes: usize) -> Result<Option<Bytes>, BoxedError> {
+    let xml = if in_query(req.uri()) {
+        let query = req.uri().query().unwrap_or_default().to_string();
+        let (xml, rest) = split_stream_body(&query, max_bytes)?;
+        query::set_query(req.uri_mut(), &rest.join("&"))?;
+        xml
+    } else if is_form(req.headers()) {
+        // percent encoding으로 최대 3배까지 커질 수 있으므로 decode한 뒤 다시 확인함
+        let form = util::to_bytes_limited(req.body_mut(), max_bytes.saturating_mul(3)).await?;
+        let form_str = std::str::from_utf8(&form).map_err(|_| invalid_form())?;
+        let (xml, rest) = split_stream_body(form_str, max_bytes)?;
+        let Some(xml) = xml else {
+            // stream.body가 없는 form은 받은 그대로 보냄
+            *req.body_mut() = Body::from(form);
+            return Ok(None);
+        };
+        let query = req.uri().query().unwrap_or_default().to_string();
+        let merged: Vec<&str> = query
+            .split('&')
+            .chain(rest.iter().copied())
+            .filter(|pair| !pair.is_empty())
+            .collect();
+        query::set_query(req.uri_mut(), &merged.join("&"))?;
+        Some(xml)
+    } else {
+        None
+    };
+
+    let Some(xml) = xml else {
+        return Ok(None);
+    };
+    *req.method_mut() = Method::POST;
+    *req.body_mut() = Body::empty();
+    req.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(XML_CONTENT_TYPE));
+    Ok(Some(Bytes::from(xml)))
+}
+
+/// key를 encoding한 경우에도 찾도록 decode한 key로 확인함. 예: stream%2Ebody
+fn is_stream_body(pair: &str) -> bool {
+    query::decode(pair.split_once('=').map_or(pair, |(key, _)| key)) == PARAM_STREAM_BODY
+}
+
+pub fn is_form(header_map: &HeaderMap) -> bool {
+    header_map
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| {
+            mime.trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+/// stream.body 값을 decode하고 나머지 파라미터를 반환함. 솔라와 달리 stream.body는 하나만 사용할 수 있음
+fn split_stream_body(
+    params: &str,
+    max_bytes: usize,
+) -> Result<(Option<Vec<u8>>, Vec<&str>), BoxedError> {
+    let mut xml = None;
+    let mut rest = Vec::new();
+    for pair in params.split('&') {
+        if !is_stream_body(pair) {
+            rest.push(pair);
+            continue;
+        }
+        if xml.is_some() {
+            return Err(Box::new(StrError::with_status(
+                "MULTIPLE_STREAM_BODY".to_string(),
+                StatusCode::BAD_REQUEST,
+            )));
+        }
+        let value = pair.split_once('=').map_or("", |(_, value)| value);
+        let decoded = form_decode(value);
+        if max_bytes > 0 && decoded.len() > max_bytes {
+            return Err(Box::new(StrError::with_status(
+                format!("MAX_BODY_BYTES_EXCEEDED: {}", max_bytes),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            )));
+        }
+        xml = Some(decoded);
+    }
+    Ok((xml, rest))
+}
+
+/// application/x-www-form-urlencoded 값 decode. +는 공백으로 바꿈
+fn form_decode(value: &str) -> Vec<u8> {
+    let value = value.replace('+', " ");
+    percent_encoding::percent_decode_str(&value).collect()
+}
+
+pub fn invalid_form() -> BoxedError {
+    Box::new(StrError::with_status(
+        "INVALID_FORM_BODY: not utf-8".to_string(),
+        StatusCode::BAD_REQUEST,
+    ))
+}
+
+#[tokio::test]
+async fn stream_body_take_test() {
+    // query string의 stream.body는 꺼내서 POST body로 바꾸고 나머지 파라미터는 유지함
+    let mut req = Request::get(
+        "/solr/core/update?commit=true&stream.body=%3Cadd%3E%3Cdoc%3E%3Cfield+name%3D%22id%22%3E1%3C%2Ffield%3E%3C%2Fdoc%3E%3C%2Fadd%3E&wt=json",
+    )
+    .body(Body::empty())
+    .unwrap();
+    assert!(in_query(req.uri()));
+    let xml = take(&mut req, 1000).await.unwrap().unwrap();
+    assert_eq!(xml, r#"<add><doc><field name="id">1</field></doc></add>"#);
+    assert_eq!(req.method(), Method::POST);
+    assert_eq!(req.uri(), "/solr/core/update?commit=true&wt=json");
+    assert_eq!(req.headers()[CONTENT_TYPE], XML_CONTENT_TYPE);
+
+    // form으로 보낸 경우 나머지 form 파라미터는 query string에 붙임
+    let mut req = Request::post("/solr/core/update?wt=json")
+        .header(
+            CONTENT_TYPE,
+            "application/x-www-form-urlencoded; charset=UTF-8",
+        )
+        .body(Body::from("stream.body=%3Cadd%2F%3E&commitWithin=1000"))
+        .unwrap();
+    let xml = take(&mut req, 1000).await.unwrap().unwrap();
+    assert_eq!(xml, "<add/>");
+    assert_eq!(req.uri(), "/solr/core/update?wt=json&commitWithin=1000");
+
+    // key를 encoding한 stream.body도 꺼냄
+    let mut req = Request::get("/solr/core/update?stream%2Ebody=%3Cadd%2F%3E")
+        .body(Body::empty())
+        .unwrap();
+    assert!(in_query(req.uri()));
+    assert_eq!(take(&mut req, 1000).await.unwrap().unwrap(), "<add/>");
+    assert_eq!(req.uri(), "/solr/core/update");
+
+    // stream.body가 없는 form은 그대로 둠
+    let mut req = Request::post("/solr/core/update")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from("commit=true"))
+        .unwrap();
+    assert!(take(&mut req, 1000).await.unwrap().is_none());
+    assert_eq!(req.uri(), "/solr/core/update");
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+    assert_eq!(body, "commit=true");
+
+    // 일반 xml body는 그대로 둠
+    let mut req = Request::post("/solr/core/update?stream.bodyx=1")
+        .body(Body::from("<add/>"))
+        .unwrap();
+    assert!(take(&mut req, 1000).await.unwrap().is_none());
+    assert_eq!(req.method(), Method::POST);
+
+    // decode한 크기로 제한함
+    let mut req = Request::get("/solr/core/update?stream.body=%3Cadd%2F%3E")
+        .body(Body::empty())
+        .unwrap();
+    let err = take(&mut req, 5).await.unwrap_err();
+    assert_eq!(
+        util::error_status(&err),
+        Some(StatusCode::PAYLOAD_TOO_LARGE)
+    );
+    assert!(take(
+        &mut Request::get("/solr/core/update?stream.body=%3Cadd%2F%3E")
+            .body(Body::empty())
+            .unwrap(),
+        6
+    )
+    .await
+    .is_ok());
+
+    let mut req = Request::get("/u?stream.body=a&stream.body=b")
+        .body(Body::empty())
+        .unwrap();
+    let err = take(&mut req, 0).await.unwrap_err();
+    assert_eq!(util::error_status(&err), Some(StatusCode::BAD_REQUEST));
+}
@@ -0,0 +1,535 @@
+use crate::commit_param::CommitPolicy;
+use crate::error_kind::ErrorKind;
+use crate::proc_xml::{self, ProcOptions, ProcTiming, ReadLimit};
+use crate::util::StrError;
+use crate::BoxedError;
+use hyper::body::{Bytes, HttpBody, Sender};
+use hyper::{Body, StatusCode};
+use quick_xml::Writer;
+use std::time::Instant;
+
+/// 스트리밍 처리 결과
+pub struct StreamResult {
+    pub doc_cnt: usize,
+    /// 다시 작성해서 보낸 doc 수
+    pub changed_doc_cnt: usize,
+    pub bytes_len: usize,
+    /// 첫번째 doc의 id. 솔라가 거부한 요청의 로그용
+    pub first_id: Option<String>,
+    /// 처리하지 못한 doc이 있는 경우 처음 발생한 에러. 해당 doc은 원문 그대로 솔라에 전달됨
+    pub parse_error: Option<BoxedError>,
+    /// doc마다 처리에 걸린 시간의 합
+    pub timing: ProcTiming,
+    /// commit_policy로 doc 밖에서 제거한 commit, optimize 명령 수
+    pub commit_removed: u32,
+}
+
+/// 입력 bytes를 \<doc>...\</doc> 단위로 나눔
+/// <br>
+/// doc 하나와 아직 처리하지 않은 chunk만 메모리에 유지함
+#[derive(Default)]
+pub struct DocSplitter {
+    buf: Vec<u8>,
+    /// buf에서 다음에 확인할 위치
+    scan_pos: usize,
+    /// 현재 열려있는 doc 태그의 깊이
+    depth: usize,
+    /// 가장 바깥 doc의 시작 위치
+    doc_start: usize,
+    in_cdata: bool,
+    in_comment: bool,
+}
+
+/// DocSplitter가 나눈 조각
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// \<add> 등 doc 밖의 데이터. 그대로 전달함
+    Raw(Vec<u8>),
+    /// \<doc>으로 시작해서 \</doc>으로 끝나는 데이터
+    Doc(Vec<u8>),
+}
+
+impl DocSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// 완성된 doc이 있는 경우 doc 앞의 데이터와 doc을 반환함
+    /// <br>
+    /// eof가 true인 경우 더 이상 데이터가 없으므로 태그 판단을 위해 기다리지 않음
+    pub fn next_segment(&mut self, eof: bool) -> Option<(Segment, Segment)> {
+        let buf = &self.buf;
+        let mut pos = self.scan_pos;
+
+        while pos < buf.len() {
+            if self.in_cdata || self.in_comment {
+                let end: &[u8] = if self.in_cdata { b"]]>" } else { b"-->" };
+                match find(&buf[pos..], end) {
+                    Some(found) => {
+                        pos += found + end.len();
+                        self.in_cdata = false;
+                        self.in_comment = false;
+                    }
+                    None => {
+                        // 끝 표시가 chunk 경계에 걸쳐있을 수 있으므로 조금 앞에서 다시 확인
+                        pos = buf.len().saturating_sub(end.len() - 1).max(pos);
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let Some(found) = buf[pos..].iter().position(|c| *c == b'<') else {
+                pos = buf.len();
+                break;
+            };
+            pos += found;
+            let rest = &buf[pos..];
+
+            // 태그를 판단할 수 있을 만큼 데이터가 없는 경우 다음 chunk를 기다림
+            const LOOKAHEAD: usize = b"<![CDATA[".len();
+            if rest.len() < LOOKAHEAD && !eof {
+                break;
+            }
+
+            if rest.starts_with(b"<!--") {
+                self.in_comment = true;
+                pos += 4;
+            } else if rest.starts_with(b"<![CDATA[") {
+                self.in_cdata = true;
+                pos += LOOKAHEAD;
+            } else if is_tag(rest, b"</doc") {
+                let Some(tag_end) = rest.iter().position(|c| *c == b'>') else {
+                    if eof {
+                        pos = buf.len();
+                    }
+                    break;
+                };
+                pos += tag_end + 1;
+                if self.depth > 0 {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        return Some(self.take_doc(pos));
+                    }
+                }
+            } else if is_tag(rest, b"<doc") {
+                let Some(tag_end) = rest.iter().position(|c| *c == b'>') else {
+                    if eof {
+                        pos = buf.len();
+                    }
+                    break;
+                };
+                // <doc/> 처럼 내용이 없는 경우 doc으로 보지 않음
+                if rest[tag_end - 1] != b'/' {
+                    if self.depth == 0 {
+                        self.doc_start = pos;
+                    }
+                    self.depth += 1;
+                }
+                pos += tag_end + 1;
+            } else {
+                pos += 1;
+            }
+        }
+
+        self.scan_pos = pos;
+        None
+    }
+
+    /// doc이 시작되기 전의 데이터와 doc을 buf에서 꺼냄
+    fn take_doc(&mut self, doc_end: usize) -> (Segment, Segment) {
+        let rest = self.buf.split_off(doc_end);
+        let doc = self.buf.split_off(self.doc_start);
+        let raw = std::mem::replace(&mut self.buf, rest);
+        self.scan_pos = 0;
+        self.doc_start = 0;
+        (Segment::Raw(raw), Segment::Doc(doc))
+    }
+
+    /// 남은 데이터를 반환함. 완성되지 않은 doc이 있는 경우 true
+    pub fn finish(self) -> (Vec<u8>, bool) {
+        (self.buf, self.depth > 0)
+    }
+}
+
+fn is_tag(rest: &[u8], tag: &[u8]) -> bool {
+    rest.starts_with(tag)
+        && matches!(
+            rest.get(tag.len()),
+            Some(b'>' | b'/' | b' ' | b'\t' | b'\r' | b'\n')
+        )
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// doc 하나를 처리함. 변경 사항이 없는 경우 None을 반환하며 원문을 그대로 사용함
+async fn process_doc(
+    doc: &[u8],
+    limit: &ReadLimit,
+    options: &ProcOptions,
+    timing: &mut ProcTiming,
+) -> Result<Option<Vec<u8>>, BoxedError> {
+    let parse_start = Instant::now();
+    let mut docs = proc_xml::read_xml(doc, limit)?;
+    timing.parse += parse_start.elapsed();
+    let removed = proc_xml::proc_xml(&mut docs, options, timing).await?;
+    if removed == 0 && !docs.iter().any(|doc| doc.field().has_changed()) {
+        return Ok(None);
+    }
+
+    let write_start = Instant::now();
+    let cap = docs.iter().map(proc_xml::estimate_doc_len).sum();
+    let mut writer = Writer::new(Vec::with_capacity(cap));
+    for doc in docs {
+        proc_xml::write_doc(&mut writer, doc)?;
+    }
+    timing.parse += write_start.elapsed();
+    Ok(Some(writer.into_inner()))
+}
+
+async fn send(sender: &mut Sender, data: Vec<u8>) -> Result<(), BoxedError> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    sender.send_data(Bytes::from(data)).await.map_err(|_| {
+        // 솔라가 요청 body를 받는 도중 연결을 끊음
+        Box::new(
+            StrError::new("STREAM_BODY_CLOSED".to_string()).with_kind(ErrorKind::SolrConnectError),
+        ) as BoxedError
+    })
+}
+
+/// update body를 doc 단위로 읽어 처리하고 곧바로 sender로 보냄
+/// <br>
+/// 처리할 수 없는 doc은 원문 그대로 보내며, 요청을 거절해야 하는 에러(status가 있는 에러)가 발생한 경우
+/// sender를 중단시켜 솔라 요청이 실패하도록 함
+/// <br>
+/// commit_policy가 있으면 doc 밖의 조각에 CommitPolicy::apply_xml을 적용해서 보냄
+pub async fn stream_update(
+    body: Body,
+    mut sender: Sender,
+    limit: &ReadLimit,
+    max_body_bytes: usize,
+    options: &ProcOptions,
+    commit_policy: Option<CommitPolicy>,
+) -> Result<StreamResult, BoxedError> {
+    let result = stream_update_worker(
+        body,
+        &mut sender,
+        limit,
+        max_body_bytes,
+        options,
+        commit_policy,
+    )
+    .await;
+    if result.is_err() {
+        sender.abort();
+    }
+    result
+}
+
+async fn stream_update_worker(
+    mut body: Body,
+    sender: &mut Sender,
+    limit: &ReadLimit,
+    max_body_bytes: usize,
+    options: &ProcOptions,
+    commit_policy: Option<CommitPolicy>,
+) -> Result<StreamResult, BoxedError> {
+    let mut splitter = DocSplitter::new();
+    let mut result = StreamResult {
+        doc_cnt: 0,
+        changed_doc_cnt: 0,
+        bytes_len: 0,
+        first_id: None,
+        parse_error: None,
+        timing: ProcTiming::default(),
+        commit_removed: 0,
+    };
+    // doc 밖의 조각은 태그 중간에서 나뉘지 않으므로 조각마다 commit 정책을 적용할 수 있음
+    let apply_policy = |raw: Vec<u8>, result: &mut StreamResult| match commit_policy {
+        Some(policy) => {
+            let applied = policy.apply_xml(&raw)?;
+            result.commit_removed += applied.commit_removed;
+            Ok::<_, BoxedError>(applied.xml.unwrap_or(raw))
+        }
+        None => Ok(raw),
+    };
+    // doc 수 제한은 요청 전체를 기준으로 확인하므로 doc마다 다시 확인하지 않음
+    let doc_limit = ReadLimit {
+        max_docs: 0,
+        ..*limit
+    };
+
+    let mut eof = false;
+    while !eof {
+        match body.data().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                result.bytes_len += chunk.len();
+                if max_body_bytes > 0 && result.bytes_len > max_body_bytes {
+                    return Err(Box::new(StrError::with_status(
+                        format!("MAX_BODY_BYTES_EXCEEDED: {}", max_body_bytes),
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                    )));
+                }
+                splitter.push(&chunk);
+            }
+            None => eof = true,
+        }
+
+        while let Some((Segment::Raw(raw), Segment::Doc(doc))) = splitter.next_segment(eof) {
+            let raw = apply_policy(raw, &mut result)?;
+            send(sender, raw).await?;
+
+            if limit.max_docs > 0 && result.doc_cnt >= limit.max_docs {
+                return Err(Box::new(StrError::with_status(
+                    format!("MAX_DOCS_PER_UPDATE_EXCEEDED: {}", limit.max_docs),
+                    StatusCode::BAD_REQUEST,
+                )));
+            }
+            result.doc_cnt += 1;
+            if result.doc_cnt == 1 {
+                result.first_id = crate::proc_xml::first_doc_id(&doc);
+            }
+
+            match process_doc(&doc, &doc_limit, options, &mut result.timing).await {
+                Ok(Some(changed)) => {
+                    result.changed_doc_cnt += 1;
+                    send(sender, changed).await?
+                }
+                Ok(None) => send(sender, doc).await?,
+                Err(e) if crate::util::error_status(&e).is_some() => return Err(e),
+                Err(e) => {
+                    // 버퍼링 모드와 같이 처리하지 못한 doc은 원문 그대로 보냄
+                    result.parse_error.get_or_insert(e);
+                    send(sender, doc).await?;
+                }
+            }
+        }
+    }
+
+    let (rest, incomplete) = splitter.finish();
+    if incomplete {
+        result.parse_error.get_or_insert_with(|| {
+            Box::new(
+                StrError::new("STREAM_DOC_INCOMPLETE".to_string()).with_kind(ErrorKind::ParseError),
+            )
+        });
+    }
+    let rest = apply_policy(rest, &mut result)?;
+    send(sender, rest).await?;
+
+    Ok(result)
+}
+
+#[test]
+fn doc_splitter_test() {
+    let xml = r#"<?xml version="1.0"?>
+<add><doc boost="1.0"><field name="id">1</field><field name="content"><![CDATA[a </doc> b]]></field></doc>
+<!-- <doc> -->
+<doc><field name="id">2</field><doc><field name="id">2-1</field></doc></doc><doc/>
+<docs>x</docs></add>"#;
+
+    // 1byte씩 넣어도 같은 결과
+    for chunk_size in [1, 7, xml.len()] {
+        let mut splitter = DocSplitter::new();
+        let mut segments = Vec::new();
+        for chunk in xml.as_bytes().chunks(chunk_size) {
+            splitter.push(chunk);
+            while let Some((raw, doc)) = splitter.next_segment(false) {
+                segments.push(raw);
+                segments.push(doc);
+            }
+        }
+        while let Some((raw, doc)) = splitter.next_segment(true) {
+            segments.push(raw);
+            segments.push(doc);
+        }
+        let (rest, incomplete) = splitter.finish();
+        assert!(!incomplete);
+
+        let to_string = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).unwrap();
+        let segments: Vec<(bool, String)> = segments
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Raw(raw) => (false, to_string(&raw)),
+                Segment::Doc(doc) => (true, to_string(&doc)),
+            })
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                (false, "<?xml version=\"1.0\"?>\n<add>".to_string()),
+                (true, r#"<doc boost="1.0"><field name="id">1</field><field name="content"><![CDATA[a </doc> b]]></field></doc>"#.to_string()),
+                (false, "\n<!-- <doc> -->\n".to_string()),
+                (true, r#"<doc><field name="id">2</field><doc><field name="id">2-1</field></doc></doc>"#.to_string()),
+            ],
+            "chunk_size: {}",
+            chunk_size
+        );
+        assert_eq!(to_string(&rest), "<doc/>\n<docs>x</docs></add>");
+    }
+
+    // 완성되지 않은 doc
+    let mut splitter = DocSplitter::new();
+    splitter.push(b"<add><doc><field name=\"id\">1</field>");
+    assert!(splitter.next_segment(true).is_none());
+    let (rest, incomplete) = splitter.finish();
+    assert!(incomplete);
+    assert_eq!(rest, b"<add><doc><field name=\"id\">1</field>");
+}
+
+#[test]
+fn fragmented_field_text_test() {
+    let xml = br#"<add><doc><field name="id">1</field><field name="title">Tom &amp; Jerry<!-- c --> &amp; Spike</field></doc></add>"#;
+
+    // 1byte씩 나누어 받아도 필드 값이 잘리지 않음
+    let mut splitter = DocSplitter::new();
+    let mut docs = Vec::new();
+    for chunk in xml.chunks(1) {
+        splitter.push(chunk);
+        while let Some((_, Segment::Doc(doc))) = splitter.next_segment(false) {
+            docs.push(doc);
+        }
+    }
+    assert_eq!(docs.len(), 1);
+    let parsed = proc_xml::read_xml(&docs[0], &ReadLimit::default()).unwrap();
+    let title = &parsed[0].field().get(b"title".as_slice()).unwrap()[0];
+    assert_eq!(title.to_unescape_str().unwrap(), "Tom & Jerry & Spike");
+}
+
+#[tokio::test]
+async fn stream_update_test() {
+    use crate::xml_doc::Doc;
+    use std::collections::BTreeMap;
+
+    // DB에 접근하지 않도록 seed_id를 미리 cache에 넣어둠
+    crate::SEED_ID_CACHE
+        .put(
+            "cafe.naver.com/moonlightriverside".to_string(),
+            "e7531c15-2384-11ed-b560-42010a025a43".to_string(),
+        )
+        .await;
+    let xml = proc_xml::SAMPLE_XML.as_bytes();
+
+    // 버퍼링 모드
+    let mut docs = proc_xml::read_xml(xml, &ReadLimit::default()).unwrap();
+    let removed = proc_xml::proc_xml(
+        &mut docs,
+        &ProcOptions::default(),
+        &mut ProcTiming::default(),
+    )
+    .await
+    .unwrap();
+    let proc_xml::WriteOk::Changed(buffered, _, _) =
+        proc_xml::write_xml(docs, removed > 0, crate::settings::SeedIdField::default()).unwrap()
+    else {
+        panic!("result is not WriteOk::Changed");
+    };
+
+    // 스트리밍 모드. 작은 chunk로 나누어 보냄
+    let (mut input_sender, input_body) = Body::channel();
+    let (output_sender, output_body) = Body::channel();
+    tokio::spawn(async move {
+        for chunk in xml.chunks(13) {
+            input_sender
+                .send_data(Bytes::copy_from_slice(chunk))
+                .await
+                .unwrap();
+        }
+    });
+    let limit = ReadLimit::default();
+    let options = ProcOptions::default();
+    let (result, streamed) = tokio::join!(
+        stream_update(input_body, output_sender, &limit, 0, &options, None),
+        hyper::body::to_bytes(output_body)
+    );
+    let result = result.unwrap();
+    let streamed = streamed.unwrap();
+    assert_eq!(result.doc_cnt, 2);
+    assert_eq!(result.changed_doc_cnt, 1);
+    assert_eq!(result.bytes_len, xml.len());
+    assert!(result.parse_error.is_none());
+
+    // field 순서는 다를 수 있으므로 값을 비교함
+    let fields_of = |doc: &Doc| -> BTreeMap<Vec<u8>, Vec<String>> {
+        let mut fields = BTreeMap::new();
+        for (name, body_list) in doc.field().iter() {
+            let values = body_list
+                .iter()
+                .map(|body| body.to_unescape_str().unwrap().into_owned())
+                .collect();
+            fields.insert(name.to_vec(), values);
+        }
+        fields
+    };
+    let buffered_docs = proc_xml::read_xml(&buffered, &ReadLimit::default()).unwrap();
+    let streamed_docs = proc_xml::read_xml(&streamed, &ReadLimit::default()).unwrap();
+    assert_eq!(buffered_docs.len(), streamed_docs.len());
+    for (buffered_doc, streamed_doc) in buffered_docs.iter().zip(&streamed_docs) {
+        assert_eq!(fields_of(buffered_doc), fields_of(streamed_doc));
+    }
+
+    // 변경되지 않은 doc은 원문 그대로 전달됨
+    let ori_docs = proc_xml::read_xml(xml, &ReadLimit::default()).unwrap();
+    assert_eq!(streamed_docs[1].ori_str(), ori_docs[1].ori_str());
+
+    // 제한을 넘은 경우 에러를 반환하고 솔라로 보내는 body도 실패함
+    let (output_sender, output_body) = Body::channel();
+    let limit = ReadLimit {
+        max_docs: 1,
+        max_fields_per_doc: 0,
+    };
+    let (result, streamed) = tokio::join!(
+        stream_update(Body::from(xml), output_sender, &limit, 0, &options, None),
+        hyper::body::to_bytes(output_body)
+    );
+    assert_eq!(
+        crate::util::error_status(&result.err().unwrap()),
+        Some(StatusCode::BAD_REQUEST)
+    );
+    assert!(streamed.is_err());
+}
+
+#[tokio::test]
+async fn stream_commit_policy_test() {
+    let xml = "<add commitWithin=\"1\">\n<doc><field name=\"id\">1</field><field name=\"text\">&lt;commit/&gt;</field></doc>\n<commit/>\n</add>";
+    let policy = CommitPolicy {
+        commit_within: std::time::Duration::from_millis(5000),
+        allow_commit: false,
+    };
+    let limit = ReadLimit::default();
+    let options = ProcOptions::default();
+
+    // doc 밖의 조각에서만 commit 요청을 제거하고 doc은 그대로 보냄
+    for chunk_size in [3, xml.len()] {
+        let (mut input_sender, input_body) = Body::channel();
+        let (output_sender, output_body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in xml.as_bytes().chunks(chunk_size) {
+                input_sender
+                    .send_data(Bytes::copy_from_slice(chunk))
+                    .await
+                    .unwrap();
+            }
+        });
+        let (result, streamed) = tokio::join!(
+            stream_update(input_body, output_sender, &limit, 0, &options, Some(policy)),
+            hyper::body::to_bytes(output_body)
+        );
+        assert_eq!(result.unwrap().commit_removed, 1);
+        assert_eq!(
+            streamed.unwrap(),
+            "<add>\n<doc><field name=\"id\">1</field><field name=\"text\">&lt;commit/&gt;</field></doc>\n\n</add>"
+        );
+    }
+}
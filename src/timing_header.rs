@@ -0,0 +1,93 @@
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::HeaderMap;
+use std::time::Duration;
+
+/// proxy가 요청을 받은 뒤 응답을 만들 때까지 걸린 시간(ms)
+pub const X_PROXY_DURATION_MS: HeaderName = HeaderName::from_static("x-proxy-duration-ms");
+
+/// 솔라 요청에 걸린 시간(ms)
+pub const X_PROXY_UPSTREAM_MS: HeaderName = HeaderName::from_static("x-proxy-upstream-ms");
+
+/// update 요청의 doc 수
+pub const X_PROXY_DOCS: HeaderName = HeaderName::from_static("x-proxy-docs");
+
+/// update 요청의 seed_id cache 사용 결과. hit, miss, partial 중 하나
+pub const X_PROXY_SEED_CACHE: HeaderName = HeaderName::from_static("x-proxy-seed-cache");
+
+/// update 요청에만 있는 응답 정보
+#[derive(Debug, Clone, Copy)]
+pub struct UpdateInfo {
+    pub doc_cnt: usize,
+    pub cache_hit: u32,
+    pub cache_miss: u32,
+}
+
+/// 솔라 응답에 proxy 처리 시간 header를 추가함. body는 바꾸지 않음
+/// <br>
+/// seed_id cache를 사용하지 않은 update 요청은 X-Proxy-Seed-Cache를 추가하지 않음
+pub fn add_timing_headers(
+    header_map: &mut HeaderMap,
+    total: Duration,
+    upstream: Duration,
+    update: Option<UpdateInfo>,
+) {
+    header_map.insert(X_PROXY_DURATION_MS, millis(total));
+    header_map.insert(X_PROXY_UPSTREAM_MS, millis(upstream));
+    let Some(update) = update else {
+        return;
+    };
+
+    header_map.insert(X_PROXY_DOCS, update.doc_cnt.into());
+    let seed_cache = match (update.cache_hit, update.cache_miss) {
+        (0, 0) => return,
+        (_, 0) => "hit",
+        (0, _) => "miss",
+        _ => "partial",
+    };
+    header_map.insert(X_PROXY_SEED_CACHE, HeaderValue::from_static(seed_cache));
+}
+
+fn millis(duration: Duration) -> HeaderValue {
+    HeaderValue::from_str(&format!("{:.3}", duration.as_secs_f64() * 1000f64)).unwrap()
+}
+
+#[test]
+fn add_timing_headers_test() {
+    let mut header_map = HeaderMap::new();
+    add_timing_headers(
+        &mut header_map,
+        Duration::from_micros(12_345),
+        Duration::from_millis(10),
+        None,
+    );
+    assert_eq!(header_map[X_PROXY_DURATION_MS], "12.345");
+    assert_eq!(header_map[X_PROXY_UPSTREAM_MS], "10.000");
+    assert!(header_map.get(X_PROXY_DOCS).is_none());
+
+    for (cache_hit, cache_miss, expected) in [
+        (3, 0, Some("hit")),
+        (0, 2, Some("miss")),
+        (1, 1, Some("partial")),
+        (0, 0, None),
+    ] {
+        let mut header_map = HeaderMap::new();
+        let update = UpdateInfo {
+            doc_cnt: 3,
+            cache_hit,
+            cache_miss,
+        };
+        add_timing_headers(
+            &mut header_map,
+            Duration::ZERO,
+            Duration::ZERO,
+            Some(update),
+        );
+        assert_eq!(header_map[X_PROXY_DOCS], "3");
+        assert_eq!(
+            header_map
+                .get(X_PROXY_SEED_CACHE)
+                .map(|value| value.to_str().unwrap()),
+            expected
+        );
+    }
+}
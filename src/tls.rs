@@ -0,0 +1,234 @@
+use crate::BoxedError;
+use log::debug;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// handshake를 마친 클라이언트 연결
+pub type TlsStream = tokio_rustls::server::TlsStream<TcpStream>;
+
+/// 연결 후 TLS handshake를 기다릴 최대 시간
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// handshake가 끝났지만 아직 hyper가 가져가지 않은 연결의 최대 수
+const ACCEPT_QUEUE: usize = 128;
+
+/// 받은 연결에 사용하는 인증서. reload로 인증서 파일을 다시 읽음
+/// <br>
+/// 다시 읽은 인증서는 그 이후의 연결부터 사용하며, 이미 연결된 요청은 이전 인증서를 그대로 사용함
+pub struct TlsConfig {
+    cert_path: String,
+    key_path: String,
+    server_config: RwLock<Arc<ServerConfig>>,
+}
+
+impl TlsConfig {
+    /// PEM 형식의 인증서(chain 포함), key 파일을 읽음
+    pub fn load(cert_path: &str, key_path: &str) -> Result<Self, BoxedError> {
+        Ok(Self {
+            cert_path: cert_path.to_string(),
+            key_path: key_path.to_string(),
+            server_config: RwLock::new(read_server_config(cert_path, key_path)?),
+        })
+    }
+
+    /// 인증서 파일을 다시 읽음. 읽을 수 없는 경우 이전 인증서를 계속 사용함
+    pub fn reload(&self) -> Result<(), BoxedError> {
+        let server_config = read_server_config(&self.cert_path, &self.key_path)?;
+        *self.server_config.write().unwrap() = server_config;
+        Ok(())
+    }
+
+    pub fn cert_path(&self) -> &str {
+        &self.cert_path
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.server_config.read().unwrap().clone())
+    }
+}
+
+fn read_server_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>, BoxedError> {
+    let certs =
+        read_certs(cert_path).map_err(|e| format!("TLS_CERT_LOAD_FAIL: {}, {}", cert_path, e))?;
+    let key = read_key(key_path).map_err(|e| format!("TLS_KEY_LOAD_FAIL: {}, {}", key_path, e))?;
+    let mut server_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("TLS_CERT_INVALID: {}, {}", cert_path, e))?;
+    // 클라이언트와는 HTTP/1.1만 사용함
+    server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(server_config))
+}
+
+fn read_certs(path: &str) -> Result<Vec<Certificate>, BoxedError> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))?;
+    if certs.is_empty() {
+        return Err("no certificate in file".into());
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// PKCS#8, PKCS#1(RSA), SEC1(EC) 형식의 key 중 처음 나오는 key
+fn read_key(path: &str) -> Result<PrivateKey, BoxedError> {
+    use rustls_pemfile::Item;
+
+    for item in rustls_pemfile::read_all(&mut BufReader::new(File::open(path)?))? {
+        if let Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) = item {
+            return Ok(PrivateKey(key));
+        }
+    }
+    Err("no private key in file".into())
+}
+
+/// listener로 받은 연결의 TLS handshake를 마친 연결
+/// <br>
+/// handshake가 느린 연결이 다른 연결을 막지 않도록 연결마다 task에서 handshake함.
+/// 반환한 Accept가 drop되면 listener를 닫음
+pub fn incoming(
+    listener: TcpListener,
+    tls: &'static TlsConfig,
+) -> impl hyper::server::accept::Accept<Conn = TlsStream, Error = std::io::Error> {
+    let (send, recv) = tokio::sync::mpsc::channel(ACCEPT_QUEUE);
+    // 반환한 Accept가 drop되면 바로 끝나고 처리중인 요청이 없으므로 spawn_tracked로 실행하지 않음
+    tokio::spawn(async move {
+        loop {
+            let (stream, remote_addr) = tokio::select! {
+                _ = send.closed() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // fd 부족 등. 잠시 기다린 뒤 다시 받음
+                        debug!("TLS_ACCEPT_FAIL: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                },
+            };
+            let acceptor = tls.acceptor();
+            let send = send.clone();
+            // handshake는 HANDSHAKE_TIMEOUT 안에 끝나고, 종료할 때는 연결을 더 받지 않으므로 spawn_tracked로 실행하지 않음
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = send.send(Ok(stream)).await;
+                    }
+                    Ok(Err(e)) => debug!("TLS_HANDSHAKE_FAIL from {}: {}", remote_addr, e),
+                    Err(_) => debug!("TLS_HANDSHAKE_TIMEOUT from {}", remote_addr),
+                }
+            });
+        }
+    });
+    hyper::server::accept::from_stream(futures_util::stream::unfold(recv, |mut recv| async move {
+        recv.recv().await.map(|conn| (conn, recv))
+    }))
+}
+
+/// TLS 연결의 클라이언트 주소. 이미 끊어진 경우 0.0.0.0:0
+pub fn remote_addr(stream: &TlsStream) -> SocketAddr {
+    stream
+        .get_ref()
+        .0
+        .peer_addr()
+        .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)))
+}
+
+#[tokio::test]
+async fn tls_listener_test() {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server};
+    use std::convert::Infallible;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerName};
+
+    // 테스트마다 만든 self-signed 인증서를 파일로 저장함
+    let dir = std::env::temp_dir().join(format!("solr_proxy_tls_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_path = dir.join("cert.pem").to_string_lossy().into_owned();
+    let key_path = dir.join("key.pem").to_string_lossy().into_owned();
+    let write_cert = || {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        Certificate(cert.serialize_der().unwrap())
+    };
+    let first_cert = write_cert();
+    let tls: &'static TlsConfig =
+        Box::leak(Box::new(TlsConfig::load(&cert_path, &key_path).unwrap()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let make_service = make_service_fn(|conn: &TlsStream| {
+        let remote_addr = remote_addr(conn);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_req| async move {
+                Ok::<_, Infallible>(Response::new(Body::from(format!(
+                    "OK {}",
+                    remote_addr.ip()
+                ))))
+            }))
+        }
+    });
+    tokio::spawn(Server::builder(incoming(listener, tls)).serve(make_service));
+
+    // trusted 인증서로만 handshake하는 클라이언트로 요청함
+    let get = |trusted: Certificate| async move {
+        let mut roots = RootCertStore::empty();
+        roots.add(&trusted).unwrap();
+        let client_config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await?;
+        stream
+            .write_all(
+                b"GET /solr/core/select HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+
+    let response = get(first_cert.clone()).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.ends_with("OK 127.0.0.1"), "{}", response);
+
+    // reload하면 이후 연결부터 새 인증서를 사용함
+    let second_cert = write_cert();
+    tls.reload().unwrap();
+    assert!(get(first_cert).await.is_err());
+    assert!(get(second_cert.clone())
+        .await
+        .unwrap()
+        .starts_with("HTTP/1.1 200 OK"));
+
+    // 다시 읽을 수 없는 경우 에러를 반환하고 이전 인증서를 계속 사용함
+    std::fs::write(&key_path, "not a key").unwrap();
+    let err = tls.reload().unwrap_err().to_string();
+    assert!(err.starts_with("TLS_KEY_LOAD_FAIL"), "{}", err);
+    assert!(get(second_cert).await.is_ok());
+
+    // 시작할 때 읽을 수 없는 경우 파일 경로를 포함한 에러
+    let err = TlsConfig::load(&dir.join("absent.pem").to_string_lossy(), &key_path)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("TLS_CERT_LOAD_FAIL") && err.contains("absent.pem"),
+        "{}",
+        err
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,10 +1,22 @@
+use crate::error_kind::ErrorKind;
 use crate::BoxedError;
-use hyper::{Body, Response};
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{
+    HeaderName, CONNECTION, CONTENT_LENGTH, EXPECT, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE,
+    TRAILER, TRANSFER_ENCODING, UPGRADE,
+};
+use hyper::{Body, HeaderMap, Response, StatusCode};
 use std::error::Error;
 use std::fmt::{Debug, Display};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 pub struct StrError {
     pub err_msg: String,
+    /// 클라이언트에게 돌려줄 status. None인 경우 500으로 처리됨
+    pub status: Option<StatusCode>,
+    /// 통계용 분류. None인 경우 status로 분류함
+    pub kind: Option<ErrorKind>,
 }
 
 impl Display for StrError {
@@ -17,6 +29,8 @@ impl Debug for StrError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("StrError")
             .field("err_msg", &self.err_msg)
+            .field("status", &self.status)
+            .field("kind", &self.kind)
             .finish()
     }
 }
@@ -25,10 +39,54 @@ impl Error for StrError {}
 
 impl StrError {
     pub fn new(err_msg: String) -> Self {
-        StrError { err_msg }
+        StrError {
+            err_msg,
+            status: None,
+            kind: None,
+        }
+    }
+
+    pub fn with_status(err_msg: String, status: StatusCode) -> Self {
+        StrError {
+            err_msg,
+            status: Some(status),
+            kind: None,
+        }
+    }
+
+    /// 통계용 분류를 지정함. 메시지와 status는 바뀌지 않음
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = Some(kind);
+        self
     }
 }
 
+/// 에러에 지정된 status를 찾음. status가 지정되지 않은 에러인 경우 None
+pub fn error_status(err: &BoxedError) -> Option<StatusCode> {
+    err.downcast_ref::<StrError>().and_then(|e| e.status)
+}
+
+/// proxy가 에러 응답을 만들 때 사용할 status
+/// <br>
+/// 지정된 status가 있으면 그대로 사용하고, 없으면 timeout은 504, 파싱할 수 없는 body는 400, 그 외는 500
+pub fn response_status(err: &BoxedError) -> StatusCode {
+    if let Some(status) = error_status(err) {
+        return status;
+    }
+    if err.is::<tokio::time::error::Elapsed>()
+        || matches!(
+            err.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::PoolTimedOut)
+        )
+    {
+        return StatusCode::GATEWAY_TIMEOUT;
+    }
+    if err.is::<quick_xml::Error>() || err.is::<std::str::Utf8Error>() {
+        return StatusCode::BAD_REQUEST;
+    }
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
 /// 에러는 발생했지만 정상적으로 문서는 주고받기 위한 에러처리
 pub struct ResponseWithError {
     pub err: BoxedError,
@@ -50,3 +108,287 @@ impl Debug for ResponseWithError {
 }
 
 impl Error for ResponseWithError {}
+
+/// proxy가 body를 다시 만들어 보내는 경우 원래 body에 대한 헤더(길이, Expect)를 제거함
+/// <br>
+/// 원래 요청의 길이는 새 body와 맞지 않으므로, 실제 body를 기준으로 hyper가 새로 설정하도록 함
+/// <br>
+/// Expect: 100-continue는 클라이언트와의 연결에서 이미 처리했고 body도 proxy가 보내므로 솔라의 100 응답을 기다리지 않도록 제거함
+pub fn remove_resent_body_headers(header_map: &mut HeaderMap) {
+    header_map.remove(CONTENT_LENGTH);
+    header_map.remove(TRANSFER_ENCODING);
+    header_map.remove(EXPECT);
+}
+
+/// 연결 하나에만 해당하는 hop-by-hop 헤더(RFC 7230 6.1)와 Connection 헤더에 적힌 헤더를 제거함
+/// <br>
+/// proxy는 이 헤더들을 다음 연결로 전달하면 안 되므로 솔라 요청과 클라이언트 응답 모두에 사용함
+pub fn remove_hop_by_hop_headers(header_map: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = header_map
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in listed {
+        header_map.remove(name);
+    }
+
+    for name in [
+        CONNECTION,
+        HeaderName::from_static("keep-alive"),
+        HeaderName::from_static("proxy-connection"),
+        PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION,
+        TE,
+        TRAILER,
+        TRANSFER_ENCODING,
+        UPGRADE,
+    ] {
+        header_map.remove(name);
+    }
+}
+
+/// body를 모두 읽어 Bytes로 반환
+/// <br>
+/// 읽는 도중 max_bytes를 넘으면 나머지는 읽지 않고 곧바로 413 에러를 반환함. max_bytes가 0이면 제한 없음
+pub async fn to_bytes_limited(body: &mut Body, max_bytes: usize) -> Result<Bytes, BoxedError> {
+    if max_bytes == 0 {
+        return Ok(hyper::body::to_bytes(body).await?);
+    }
+
+    // Content-Length로 크기를 미리 알 수 있는 경우 읽기 전에 거절
+    let size_hint = body.size_hint();
+    if size_hint.lower() > max_bytes as u64 {
+        return Err(body_too_large(max_bytes));
+    }
+
+    let mut buf = Vec::with_capacity(size_hint.lower() as usize);
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if buf.len() + chunk.len() > max_bytes {
+            return Err(body_too_large(max_bytes));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(Bytes::from(buf))
+}
+
+/// body가 max_bytes를 넘은 경우의 413 에러
+pub fn body_too_large(max_bytes: usize) -> BoxedError {
+    Box::new(StrError::with_status(
+        format!("MAX_BODY_BYTES_EXCEEDED: {}", max_bytes),
+        StatusCode::PAYLOAD_TOO_LARGE,
+    ))
+}
+
+/// body를 그대로 전달하면서 지나간 bytes를 셈. 버퍼링하지 않으며 에러도 그대로 전달함
+struct CountingBody<F: FnOnce(u64)> {
+    inner: Body,
+    bytes: u64,
+    /// 스트림이 끝나거나 중간에 버려질 때 한번만 호출함
+    on_end: Option<F>,
+}
+
+impl<F: FnOnce(u64)> CountingBody<F> {
+    fn finish(&mut self) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(self.bytes);
+        }
+    }
+}
+
+impl<F: FnOnce(u64) + Unpin> futures_util::Stream for CountingBody<F> {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_data(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(chunk))) => this.bytes += chunk.len() as u64,
+            Poll::Ready(None) => this.finish(),
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl<F: FnOnce(u64)> Drop for CountingBody<F> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// body를 클라이언트에 보내는 동안 bytes를 세고, 끝나면 전체 bytes로 on_end를 호출함
+/// <br>
+/// 클라이언트가 중간에 연결을 끊은 경우 그때까지 보낸 bytes로 호출함
+pub fn count_body(body: Body, on_end: impl FnOnce(u64) + Send + Unpin + 'static) -> Body {
+    Body::wrap_stream(CountingBody {
+        inner: body,
+        bytes: 0,
+        on_end: Some(on_end),
+    })
+}
+
+#[tokio::test]
+async fn to_bytes_limited_test() {
+    let (mut sender, mut body) = Body::channel();
+    let (finish_send, finish_recv) = tokio::sync::oneshot::channel::<()>();
+
+    tokio::spawn(async move {
+        for _ in 0..4 {
+            if sender.send_data(Bytes::from(vec![b'a'; 10])).await.is_err() {
+                return;
+            }
+        }
+        // 제한을 넘은 뒤에도 스트림을 끝내지 않음. body 전체를 버퍼링하려 한다면 여기서 멈추게 됨
+        let _ = finish_recv.await;
+    });
+
+    let result = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        to_bytes_limited(&mut body, 25),
+    )
+    .await
+    .expect("body is not aborted early");
+    let err = result.unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::PAYLOAD_TOO_LARGE));
+    let _ = finish_send.send(());
+
+    // 제한 이하인 경우 그대로 읽음
+    let mut body = Body::from("0123456789");
+    let bytes = to_bytes_limited(&mut body, 10).await.unwrap();
+    assert_eq!(&bytes[..], b"0123456789");
+
+    // Content-Length가 제한을 넘는 경우 읽기 전에 거절
+    let mut body = Body::from("0123456789");
+    let err = to_bytes_limited(&mut body, 9).await.unwrap_err();
+    assert_eq!(error_status(&err), Some(StatusCode::PAYLOAD_TOO_LARGE));
+}
+
+#[tokio::test]
+async fn response_status_test() {
+    let status = |err: BoxedError| response_status(&err);
+
+    assert_eq!(
+        status(Box::new(StrError::with_status(
+            "UNKNOWN_PATH /a".to_string(),
+            StatusCode::NOT_FOUND
+        ))),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(status(body_too_large(10)), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // status를 지정하지 않은 에러는 종류로 정함
+    let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+        .await
+        .unwrap_err();
+    assert_eq!(status(Box::new(elapsed)), StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(
+        status(Box::new(sqlx::Error::PoolTimedOut)),
+        StatusCode::GATEWAY_TIMEOUT
+    );
+    let invalid = vec![b'a', 0xff];
+    let utf8_error = std::str::from_utf8(&invalid).unwrap_err();
+    assert_eq!(status(Box::new(utf8_error)), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        status(Box::new(sqlx::Error::RowNotFound)),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+    assert_eq!(
+        status(Box::new(StrError::new("DB_FAIL".to_string()))),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
+
+#[test]
+fn remove_resent_body_headers_test() {
+    use hyper::header::{HeaderValue, CONTENT_TYPE};
+
+    let mut header_map = HeaderMap::new();
+    header_map.insert(CONTENT_LENGTH, HeaderValue::from_static("10"));
+    header_map.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+    header_map.insert(EXPECT, HeaderValue::from_static("100-continue"));
+    header_map.insert(CONTENT_TYPE, HeaderValue::from_static("text/xml"));
+    remove_resent_body_headers(&mut header_map);
+    assert_eq!(header_map.len(), 1);
+    assert_eq!(header_map[CONTENT_TYPE], "text/xml");
+}
+
+#[test]
+fn remove_hop_by_hop_headers_test() {
+    use hyper::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+
+    for name in [
+        "connection",
+        "keep-alive",
+        "proxy-connection",
+        "proxy-authenticate",
+        "proxy-authorization",
+        "te",
+        "trailer",
+        "transfer-encoding",
+        "upgrade",
+    ] {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            HeaderName::from_static(name),
+            HeaderValue::from_static("value"),
+        );
+        header_map.insert(CONTENT_TYPE, HeaderValue::from_static("text/xml"));
+        remove_hop_by_hop_headers(&mut header_map);
+        assert!(!header_map.contains_key(name), "{}", name);
+        assert_eq!(header_map[CONTENT_TYPE], "text/xml");
+    }
+
+    // Connection 헤더에 적힌 헤더도 제거함
+    let mut header_map = HeaderMap::new();
+    header_map.insert(CONNECTION, HeaderValue::from_static("X-Hop, x-other "));
+    header_map.append(CONNECTION, HeaderValue::from_static("close"));
+    header_map.insert("x-hop", HeaderValue::from_static("1"));
+    header_map.insert("x-other", HeaderValue::from_static("2"));
+    header_map.insert("x-end-to-end", HeaderValue::from_static("3"));
+    header_map.insert(ACCEPT, HeaderValue::from_static("text/xml"));
+    header_map.append(ACCEPT, HeaderValue::from_static("application/json"));
+    remove_hop_by_hop_headers(&mut header_map);
+    assert_eq!(header_map.len(), 3);
+    assert_eq!(header_map["x-end-to-end"], "3");
+    assert_eq!(header_map.get_all(ACCEPT).iter().count(), 2);
+}
+
+#[tokio::test]
+async fn count_body_test() {
+    use std::sync::{Arc, Mutex};
+
+    let counted = Arc::new(Mutex::new(Vec::new()));
+    let on_end = |counted: &Arc<Mutex<Vec<u64>>>| {
+        let counted = counted.clone();
+        move |bytes| counted.lock().unwrap().push(bytes)
+    };
+
+    // 여러 chunk를 버퍼링하지 않고 그대로 전달함
+    let (mut sender, body) = Body::channel();
+    let mut body = count_body(body, on_end(&counted));
+    for chunk in ["abc", "de", "fghij"] {
+        sender.send_data(Bytes::from(chunk)).await.unwrap();
+        assert_eq!(body.data().await.unwrap().unwrap(), chunk);
+    }
+    assert!(counted.lock().unwrap().is_empty());
+    drop(sender);
+    assert!(body.data().await.is_none());
+    assert_eq!(*counted.lock().unwrap(), [10]);
+    drop(body);
+    assert_eq!(*counted.lock().unwrap(), [10]);
+
+    // 에러는 그대로 전달하고 그때까지 전달한 bytes를 셈
+    let (mut sender, body) = Body::channel();
+    let mut body = count_body(body, on_end(&counted));
+    sender.send_data(Bytes::from("abcd")).await.unwrap();
+    sender.abort();
+    assert_eq!(body.data().await.unwrap().unwrap(), "abcd");
+    assert!(body.data().await.unwrap().is_err());
+    drop(body);
+    assert_eq!(*counted.lock().unwrap(), [10, 4]);
+}
@@ -1,3 +1,5 @@
+use memchr::memchr;
+
 pub struct AttrParseResult<'a> {
     pub name: &'a [u8],
     pub value: &'a [u8],
@@ -36,63 +38,73 @@ impl<'a> Iterator for AttrParser<'a> {
     type Item = AttrParseResult<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let attr_str = self.attr_str;
+
         // name 시작지점을 찾음. 공백이 아닌 문자를 찾음
-        let (name_start_pos, _) = find_with_start(self.attr_str, self.cursor, |c| {
-            !matches!(c, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
-        })?;
-
-        // name 끝지점을 찾음. 공백 또는 =를 찾음
-        let (name_end_pos, _) = find_with_start(self.attr_str, name_start_pos + 1, |c| {
-            matches!(c, b'=' | b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
-        })?;
-
-        // =를 찾음
-        let mut equal_mark_pos: Option<usize> = None;
-        for (i, &c) in self.attr_str.iter().enumerate().skip(name_end_pos) {
-            if c == b'=' {
-                equal_mark_pos = Some(i);
-                break;
-            }
+        // 따옴표 없는 값 뒤에 /가 남아있을 수 있으므로 /도 건너뜀
+        let name_start_pos = self.cursor
+            + attr_str
+                .get(self.cursor..)?
+                .iter()
+                .position(|&c| !is_space(c) && c != b'/')?;
+
+        // =를 찾음. name 끝지점은 = 또는 그 이전의 첫번째 공백
+        let equal_mark_pos =
+            name_start_pos + 1 + memchr(b'=', attr_str.get(name_start_pos + 1..)?)?;
+        let name_end_pos = attr_str[name_start_pos + 1..equal_mark_pos]
+            .iter()
+            .position(|&c| is_space(c))
+            .map_or(equal_mark_pos, |pos| name_start_pos + 1 + pos);
+
+        // name과 = 사이에는 공백 문자 외의 다른 문자는 만나면 안됨
+        if !attr_str[name_end_pos..equal_mark_pos]
+            .iter()
+            .all(|&c| is_space(c))
+        {
+            return None;
+        }
 
-            // 여기서 공백 문자 외의 다른 문자는 만나면 안됨
-            if !matches!(c, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ') {
-                return None;
+        // = 뒤의 공백을 건너뛴 value 시작지점
+        let value_start_pos = equal_mark_pos
+            + 1
+            + attr_str[equal_mark_pos + 1..]
+                .iter()
+                .position(|&c| !is_space(c))
+                .unwrap_or(attr_str.len() - equal_mark_pos - 1);
+
+        let name = &attr_str[name_start_pos..name_end_pos];
+
+        match attr_str.get(value_start_pos) {
+            // ' 또는 "로 시작한 경우 같은 문자가 나오는 지점까지 value로 사용함
+            Some(&quot) if matches!(quot, b'"' | b'\'') => {
+                let quot_start_pos = value_start_pos + 1;
+                let quot_end_pos = quot_start_pos + memchr(quot, &attr_str[quot_start_pos..])?;
+                self.cursor = quot_end_pos + 1;
+
+                Some(AttrParseResult {
+                    name,
+                    value: &attr_str[quot_start_pos..quot_end_pos],
+                })
+            }
+            // 따옴표 없이 작성된 value는 공백, > 또는 /가 나오는 지점까지 사용함. 값이 없으면 빈 값
+            _ => {
+                let value_end_pos = attr_str[value_start_pos..]
+                    .iter()
+                    .position(|&c| is_space(c) || matches!(c, b'>' | b'/'))
+                    .map_or(attr_str.len(), |pos| value_start_pos + pos);
+                self.cursor = value_end_pos;
+
+                Some(AttrParseResult {
+                    name,
+                    value: &attr_str[value_start_pos..value_end_pos],
+                })
             }
         }
-        let equal_mark_pos = equal_mark_pos?;
-
-        // ' 또는 "가 시작되는 지점을 찾음
-        let (mut quot_start_pos, quot) = find_with_start(self.attr_str, equal_mark_pos + 1, |c| {
-            matches!(c, b'"' | b'\'')
-        })?;
-        quot_start_pos += 1;
-
-        // // ' 또는 "가 끝나는 지점을 찾음
-        let (quot_end_pos, _) = find_with_start(self.attr_str, quot_start_pos, |c| c == quot)?;
-
-        let name = &self.attr_str[name_start_pos..name_end_pos];
-        let value = &self.attr_str[quot_start_pos..quot_end_pos];
-
-        self.cursor = quot_end_pos + 1;
-
-        // println!("name:{}", String::from_utf8_lossy(name));
-        // println!("value:{}", String::from_utf8_lossy(value));
-
-        Some(AttrParseResult { name, value })
     }
 }
 
-fn find_with_start<F>(arr: &[u8], cursor: usize, f: F) -> Option<(usize, u8)>
-where
-    F: Fn(u8) -> bool,
-{
-    for (i, &c) in arr.iter().enumerate().skip(cursor) {
-        if f(c) {
-            return Some((i, c));
-        }
-    }
-
-    None
+fn is_space(c: u8) -> bool {
+    matches!(c, b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
 }
 
 #[test]
@@ -189,3 +201,51 @@ fn attr_test() {
     test = AttrParser::new(b"");
     assert_eq!(test.next(), None);
 }
+
+#[test]
+fn unquoted_attr_test() {
+    // read_xml에 전달되는 속성 문자열은 태그의 >까지 포함함
+    let mut test = AttrParser::new(b" name=id>");
+    let mut test_result = test.next().unwrap();
+    assert_eq!(test_result.name, b"name");
+    assert_eq!(test_result.value, b"id");
+    assert_eq!(test.next(), None);
+
+    // 따옴표가 있는 값과 없는 값이 섞인 경우
+    test = AttrParser::new(br#" a=1 b="2 3" c = 4	d='5'e=6>"#);
+    for (name, value) in [
+        (&b"a"[..], &b"1"[..]),
+        (b"b", b"2 3"),
+        (b"c", b"4"),
+        (b"d", b"5"),
+        (b"e", b"6"),
+    ] {
+        test_result = test.next().unwrap();
+        assert_eq!(test_result.name, name);
+        assert_eq!(test_result.value, value);
+    }
+    assert_eq!(test.next(), None);
+
+    // />로 끝나는 경우 /는 값에 포함되지 않음
+    test = AttrParser::new(b" name=id/>");
+    test_result = test.next().unwrap();
+    assert_eq!(test_result.name, b"name");
+    assert_eq!(test_result.value, b"id");
+    assert_eq!(test.next(), None);
+
+    // 값이 없는 경우 빈 값으로 처리하고 다음 속성을 계속 파싱함
+    test = AttrParser::new(br#"a= /b="c">"#);
+    test_result = test.next().unwrap();
+    assert_eq!(test_result.name, b"a");
+    assert_eq!(test_result.value, b"");
+    test_result = test.next().unwrap();
+    assert_eq!(test_result.name, b"b");
+    assert_eq!(test_result.value, b"c");
+    assert_eq!(test.next(), None);
+
+    test = AttrParser::new(b"a=");
+    test_result = test.next().unwrap();
+    assert_eq!(test_result.name, b"a");
+    assert_eq!(test_result.value, b"");
+    assert_eq!(test.next(), None);
+}
@@ -11,11 +11,11 @@ pub enum BytesOrStr<'xml> {
     /// 원문에 대한 참조
     Bytes(BytesText<'xml>),
     /// 값이 변경/추가된 경우. 원문 데이터가 있을 경우 원문 데이터에 대한 참조는 유지함
-    Str(Cow<'xml, String>, Option<BytesText<'xml>>),
+    Str(Cow<'xml, str>, #[allow(dead_code)] Option<BytesText<'xml>>),
 }
 
 impl<'xml> BytesOrStr<'xml> {
-    pub fn to_unescape_str(&self) -> Result<Cow<str>, quick_xml::Error> {
+    pub fn to_unescape_str(&self) -> Result<Cow<'_, str>, quick_xml::Error> {
         match self {
             BytesOrStr::Bytes(bytes) => Ok(bytes.unescape()?),
             BytesOrStr::Str(str, _) => Ok(Cow::Borrowed(str)),
@@ -32,6 +32,12 @@ pub struct DocField<'xml> {
     has_changed: bool,
 }
 
+impl<'xml> Default for DocField<'xml> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'xml> DocField<'xml> {
     pub fn new() -> Self {
         Self {
@@ -48,6 +54,12 @@ impl<'xml> DocField<'xml> {
         self.field.get(key)
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = (&'xml [u8], &SmallVec<[BytesOrStr<'xml>; 1]>)> {
+        self.field
+            .iter()
+            .map(|(name, body_list)| (*name, body_list))
+    }
+
     pub fn try_reserve(&mut self, size: usize) -> Result<(), hashbrown::TryReserveError> {
         self.field.try_reserve(size)
     }
@@ -65,11 +77,71 @@ impl<'xml> DocField<'xml> {
         self.has_changed = true;
     }
 
+    /// name 필드의 index번째 값을 변경함. 원문 데이터에 대한 참조는 유지함
+    pub fn replace_field_owned(&mut self, name: &[u8], index: usize, value: String) {
+        let Some(body) = self
+            .field
+            .get_mut(name)
+            .and_then(|list| list.get_mut(index))
+        else {
+            return;
+        };
+
+        let ori = match std::mem::replace(body, BytesOrStr::Str(Cow::Borrowed(""), None)) {
+            BytesOrStr::Bytes(bytes) => Some(bytes),
+            BytesOrStr::Str(_, ori) => ori,
+        };
+        *body = BytesOrStr::Str(Cow::Owned(value), ori);
+
+        self.has_changed = true;
+    }
+
+    /// name 필드의 값을 앞에서부터 len개만 남김. 제거된 값의 수 반환
+    pub fn truncate_field(&mut self, name: &[u8], len: usize) -> usize {
+        let Some(body_list) = self.field.get_mut(name) else {
+            return 0;
+        };
+        if body_list.len() <= len {
+            return 0;
+        }
+
+        let removed = body_list.len() - len;
+        body_list.truncate(len);
+        self.has_changed = true;
+        removed
+    }
+
+    /// name 필드의 값 중 keep이 false인 값을 제거함. 모두 제거된 경우 필드도 제거함. 제거된 값의 수 반환
+    pub fn retain_field(
+        &mut self,
+        name: &[u8],
+        mut keep: impl FnMut(&BytesOrStr<'xml>) -> bool,
+    ) -> usize {
+        let Some(body_list) = self.field.get_mut(name) else {
+            return 0;
+        };
+        let before = body_list.len();
+        body_list.retain(|body| keep(body));
+        let removed = before - body_list.len();
+        if body_list.is_empty() {
+            self.field.remove(name);
+        }
+        if removed > 0 {
+            self.has_changed = true;
+        }
+        removed
+    }
+
     pub fn push_field_borrowed(&mut self, name: &'xml [u8], bytes: BytesText<'xml>) {
+        self.push_field(name, BytesOrStr::Bytes(bytes));
+    }
+
+    /// 원문에서 읽은 값을 추가함. 변경 사항으로 보지 않음
+    pub fn push_field(&mut self, name: &'xml [u8], body: BytesOrStr<'xml>) {
         self.field
             .entry(name)
             .or_insert_with(|| SmallVec::with_capacity(1))
-            .push(BytesOrStr::Bytes(bytes));
+            .push(body);
     }
 }
 
@@ -81,11 +153,31 @@ pub struct Doc<'xml> {
 
     /// 원문 doc에 대한 참조. \<doc>으로 시작해서 \</doc>으로 끝남
     ori_str: &'xml [u8],
+
+    /// doc 안의 주석 원문. 변경된 doc을 다시 작성할 때 \<doc> 바로 뒤에 넣음
+    comments: Vec<&'xml [u8]>,
+
+    /// 자식 doc의 원문. 자식 doc을 필드로 감싼 경우 필드 전체. 변경된 doc을 다시 작성할 때 필드 뒤에 넣음
+    children: Vec<&'xml [u8]>,
 }
 
 impl<'xml> Doc<'xml> {
     pub fn new(field: DocField<'xml>, ori_str: &'xml [u8]) -> Self {
-        Self { field, ori_str }
+        Self::with_raw(field, ori_str, Vec::new(), Vec::new())
+    }
+
+    pub fn with_raw(
+        field: DocField<'xml>,
+        ori_str: &'xml [u8],
+        comments: Vec<&'xml [u8]>,
+        children: Vec<&'xml [u8]>,
+    ) -> Self {
+        Self {
+            field,
+            ori_str,
+            comments,
+            children,
+        }
     }
 
     pub fn field(&self) -> &DocField<'xml> {
@@ -96,6 +188,14 @@ impl<'xml> Doc<'xml> {
         self.ori_str
     }
 
+    pub fn comments(&self) -> &[&'xml [u8]] {
+        &self.comments
+    }
+
+    pub fn children(&self) -> &[&'xml [u8]] {
+        &self.children
+    }
+
     pub fn into_inner(self) -> (DocField<'xml>, &'xml [u8]) {
         (self.field, self.ori_str)
     }
@@ -109,10 +209,8 @@ impl<'xml> std::fmt::Debug for Doc<'xml> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Doc")
             .field("field", &self.field)
-            .field(
-                "ori_str",
-                &String::from_utf8(self.ori_str.to_vec()).unwrap(),
-            )
+            // 원문이 UTF-8이 아닐 수 있으므로 lossy 변환
+            .field("ori_str", &String::from_utf8_lossy(self.ori_str))
             .finish()
     }
 }
@@ -0,0 +1,165 @@
+//! 임의의 입력에 대해 AttrParser, read_xml이 panic 없이 동작하는지 확인하는 property 테스트
+//! <br>
+//! panic이 발견된 입력은 tests/regressions 디렉토리에 추가하여 regression_inputs_test에서 계속 확인함
+
+use proptest::prelude::*;
+use solr_proxy::proc_xml::{read_xml, write_xml, ReadLimit, SeedIdField, WriteOk};
+use solr_proxy::xml_attr_parser::AttrParser;
+use std::fs;
+use std::path::Path;
+
+/// read_xml 내부의 분기를 타기 쉽도록 xml 조각을 임의로 이어붙인 입력
+fn xml_fragments() -> impl Strategy<Value = Vec<u8>> {
+    let fragment = prop_oneof![
+        Just(b"<add>".to_vec()),
+        Just(b"</add>".to_vec()),
+        Just(b"<doc>".to_vec()),
+        Just(b"<doc/>".to_vec()),
+        Just(b"</doc>".to_vec()),
+        Just(b"<field name=\"id\">".to_vec()),
+        Just(b"<field name='url'>".to_vec()),
+        Just(b"<field name=".to_vec()),
+        Just(b"<field>".to_vec()),
+        Just(b"</field>".to_vec()),
+        Just(b"<![CDATA[".to_vec()),
+        Just(b"]]>".to_vec()),
+        Just(b"<!--".to_vec()),
+        Just(b"-->".to_vec()),
+        Just(b"&amp;".to_vec()),
+        Just(b"&#xD55C;".to_vec()),
+        Just(b"&bad;".to_vec()),
+        Just("한글".as_bytes().to_vec()),
+        Just(b"\"".to_vec()),
+        Just(b" ".to_vec()),
+        proptest::collection::vec(any::<u8>(), 0..8),
+    ];
+    proptest::collection::vec(fragment, 0..32).prop_map(|list| list.concat())
+}
+
+/// 파싱에 성공한 경우 로그 출력용 Debug, write_xml까지 panic 없이 동작해야 함
+fn read_and_write(xml: &[u8]) {
+    if let Ok(docs) = read_xml(xml, &ReadLimit::default()) {
+        let _ = format!("{:?}", docs);
+        let _ = write_xml(docs, true, SeedIdField::default());
+    }
+}
+
+/// 앞뒤 공백이 없는 field 값. read_xml은 trim_text를 사용하므로 앞뒤 공백은 보존되지 않음
+fn field_value() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9가-힣<>&'\"/:.]([a-zA-Z0-9가-힣<>&'\"/:. \\n]{0,28}[a-zA-Z0-9가-힣<>&'\"/:.])?"
+}
+
+fn field_name() -> impl Strategy<Value = String> {
+    "[a-z_]{1,10}"
+}
+
+type GeneratedDoc = Vec<(String, String)>;
+
+fn generated_docs() -> impl Strategy<Value = Vec<GeneratedDoc>> {
+    proptest::collection::vec(
+        proptest::collection::vec((field_name(), field_value()), 1..8),
+        1..5,
+    )
+}
+
+fn to_xml(docs: &[GeneratedDoc]) -> String {
+    let mut xml = String::from("<add>");
+    for doc in docs {
+        xml.push_str("<doc>");
+        for (name, value) in doc {
+            xml.push_str(&format!(
+                r#"<field name="{}">{}</field>"#,
+                name,
+                quick_xml::escape::escape(value)
+            ));
+        }
+        xml.push_str("</doc>");
+    }
+    xml.push_str("</add>");
+    xml
+}
+
+/// doc별로 필드 이름을 정렬하고, 같은 이름의 값은 원래 순서를 유지한 목록
+fn field_values(xml: &[u8]) -> Vec<Vec<(String, Vec<String>)>> {
+    read_xml(xml, &ReadLimit::default())
+        .unwrap()
+        .iter()
+        .map(|doc| {
+            let mut fields: Vec<(String, Vec<String>)> = doc
+                .field()
+                .iter()
+                .map(|(name, body_list)| {
+                    let values = body_list
+                        .iter()
+                        .map(|body| body.to_unescape_str().unwrap().into_owned())
+                        .collect();
+                    (String::from_utf8(name.to_vec()).unwrap(), values)
+                })
+                .collect();
+            fields.sort();
+            fields
+        })
+        .collect()
+}
+
+proptest! {
+    // 실패한 입력은 tests/regressions에 직접 추가하므로 proptest-regressions 파일은 만들지 않음
+    #![proptest_config(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn attr_parser_no_panic_test(input in proptest::collection::vec(any::<u8>(), 0..256)) {
+        for attr in AttrParser::new(&input) {
+            let _ = (attr.name, attr.value);
+        }
+    }
+
+    #[test]
+    fn read_xml_arbitrary_bytes_test(input in proptest::collection::vec(any::<u8>(), 0..512)) {
+        read_and_write(&input);
+    }
+
+    #[test]
+    fn read_xml_fragments_test(input in xml_fragments()) {
+        read_and_write(&input);
+    }
+
+    #[test]
+    fn read_write_roundtrip_test(docs in generated_docs()) {
+        let xml = to_xml(&docs);
+        let expected = field_values(xml.as_bytes());
+
+        // 모든 doc에 필드를 추가하여 write_doc이 필드를 다시 작성하도록 함
+        let mut parsed = read_xml(xml.as_bytes(), &ReadLimit::default()).unwrap();
+        prop_assert_eq!(parsed.len(), docs.len());
+        for doc in parsed.iter_mut() {
+            doc.field_as_mut().push_field_owned(b"roundtrip_marker", "1".to_string());
+        }
+        let WriteOk::Changed(written, doc_cnt, _) = write_xml(parsed, false, SeedIdField::default()).unwrap() else {
+            panic!("write_xml must rewrite changed docs");
+        };
+        prop_assert_eq!(doc_cnt, docs.len());
+
+        let mut actual = field_values(&written);
+        for fields in actual.iter_mut() {
+            let marker = fields.iter().position(|(name, _)| name == "roundtrip_marker").unwrap();
+            prop_assert_eq!(&fields.remove(marker).1, &vec!["1".to_string()]);
+        }
+        prop_assert_eq!(actual, expected);
+    }
+}
+
+/// 이전에 panic이 발생했던 입력
+#[test]
+fn regression_inputs_test() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/regressions");
+    for entry in fs::read_dir(dir).unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        for attr in AttrParser::new(&input) {
+            let _ = (attr.name, attr.value);
+        }
+        read_and_write(&input);
+    }
+}